@@ -0,0 +1,170 @@
+//! Figure and table captions
+//!
+//! A caption is the line right under an image or a table, either written in
+//! italics or starting with `^caption`:
+//!
+//! ```markdown
+//! ![](results.png)
+//! *Results of the second run*
+//!
+//! | Run | Time |
+//! |-----|------|
+//! | 1   | 3.2s |
+//! ^caption Timings
+//! ```
+//!
+//! Captioned figures and tables are numbered in order, each kind on its own,
+//! and the preview and exports show the captions as "Figure 1: …" and
+//! "Table 1: …". Lines in code blocks are left alone.
+
+use std::ops::Range;
+
+use regex_lite::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptionKind {
+    Figure,
+    Table,
+}
+
+impl CaptionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Figure => "Figure",
+            Self::Table => "Table",
+        }
+    }
+}
+
+/// Caption of a figure or table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Caption {
+    pub kind: CaptionKind,
+    /// Number among the captions of its kind, from 1
+    pub number: usize,
+    /// Caption text, as Markdown
+    pub text: String,
+    /// Byte range of the caption's line, without the line break
+    pub range: Range<usize>,
+}
+
+impl Caption {
+    /// "Figure 1", "Table 2"...
+    pub fn label(&self) -> String {
+        format!("{} {}", self.kind.name(), self.number)
+    }
+}
+
+/// Captions of the figures and tables in `markdown`, in order
+pub fn find_captions(markdown: &str) -> Vec<Caption> {
+    let image = Regex::new(r"^(?:!\[[^\]]*\]\([^)]*\)|!\[\[[^\]]+\]\])$").unwrap();
+    let mut captions = Vec::new();
+    let mut counts = [0, 0];
+    let mut fence: Option<&str> = None;
+    let mut previous: Option<&str> = None;
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim();
+
+        // Skip code blocks
+        let marker = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if open == marker && trimmed.trim_start_matches(['`', '~']).is_empty() => {
+                fence = None;
+                previous = None;
+                continue;
+            }
+            _ => {}
+        }
+        if fence.is_some() {
+            continue;
+        }
+
+        let kind = previous.and_then(|previous| {
+            if image.is_match(previous) {
+                Some(CaptionKind::Figure)
+            } else if previous.starts_with('|') && previous.ends_with('|') {
+                Some(CaptionKind::Table)
+            } else {
+                None
+            }
+        });
+        match (kind, caption_text(trimmed)) {
+            (Some(kind), Some(text)) => {
+                let count = &mut counts[kind as usize];
+                *count += 1;
+                let range = start + (line.len() - line.trim_start().len())..start + line.trim_end().len();
+                captions.push(Caption { kind, number: *count, text: text.to_string(), range });
+                previous = None;
+            }
+            _ => previous = Some(trimmed),
+        }
+    }
+    captions
+}
+
+/// Text of a caption line: an italic line, or one starting with `^caption`
+fn caption_text(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("^caption") {
+        let text = rest.strip_prefix(':').unwrap_or(rest);
+        // Not a block ID such as `^captions`
+        return (text.is_empty() || text.starts_with(char::is_whitespace)).then(|| text.trim());
+    }
+    ['*', '_'].into_iter().find_map(|mark| {
+        let inner = line.strip_prefix(mark)?.strip_suffix(mark)?;
+        // Bold, or more than one italic span
+        let italic = !inner.trim().is_empty() && !inner.starts_with(mark) && !inner.contains(mark);
+        italic.then(|| inner.trim())
+    })
+}
+
+/// `markdown` with each caption line replaced by `render(caption)`, set
+/// apart from the image or table above it and the text below it
+pub fn replace_captions(markdown: &str, render: impl Fn(&Caption) -> String) -> String {
+    let mut result = String::with_capacity(markdown.len());
+    let mut last = 0;
+    for caption in find_captions(markdown) {
+        result.push_str(&markdown[last..caption.range.start]);
+        result.push('\n');
+        result.push_str(&render(&caption));
+        result.push('\n');
+        last = caption.range.end;
+    }
+    result.push_str(&markdown[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_captions() {
+        let markdown = "![](a.png)\n*First plot*\n\n\
+                        ![[b.png]]\n^caption: Second plot\n\n\
+                        | A |\n|---|\n| 1 |\n^caption Values\n\n\
+                        ![](c.png)\n**Not a caption**\n\n\
+                        Text\n*Not under a figure*\n\n\
+                        ```\n![](d.png)\n*In code*\n```\n";
+        let captions = find_captions(markdown);
+        let found: Vec<(String, &str)> =
+            captions.iter().map(|caption| (caption.label(), caption.text.as_str())).collect();
+        assert_eq!(
+            found,
+            [
+                ("Figure 1".to_string(), "First plot"),
+                ("Figure 2".to_string(), "Second plot"),
+                ("Table 1".to_string(), "Values"),
+            ]
+        );
+        assert_eq!(&markdown[captions[0].range.clone()], "*First plot*");
+
+        let replaced =
+            replace_captions("![](a.png)\n*Plot*\nMore", |caption| format!("{}: {}", caption.label(), caption.text));
+        assert_eq!(replaced, "![](a.png)\n\nFigure 1: Plot\n\nMore");
+    }
+}
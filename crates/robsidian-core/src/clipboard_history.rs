@@ -0,0 +1,57 @@
+//! Clipboard history
+//!
+//! Text copied or cut in the app, newest first, so older fragments can be
+//! pasted again. Copying text that is already in the history moves it to the
+//! front instead of adding it twice.
+
+use std::collections::VecDeque;
+
+/// Entries kept before the oldest are dropped
+pub const CLIPBOARD_HISTORY_SIZE: usize = 30;
+
+/// Recently copied text, newest first
+#[derive(Debug, Clone, Default)]
+pub struct ClipboardHistory {
+    entries: VecDeque<String>,
+}
+
+impl ClipboardHistory {
+    /// Remember copied text
+    pub fn push(&mut self, text: &str) {
+        if text.trim().is_empty() {
+            return;
+        }
+        self.entries.retain(|entry| entry != text);
+        self.entries.push_front(text.to_string());
+        self.entries.truncate(CLIPBOARD_HISTORY_SIZE);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_history() {
+        let mut history = ClipboardHistory::default();
+        history.push("first");
+        history.push("  ");
+        history.push("second");
+        history.push("first");
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["first", "second"]);
+
+        for n in 0..CLIPBOARD_HISTORY_SIZE {
+            history.push(&n.to_string());
+        }
+        assert_eq!(history.entries().count(), CLIPBOARD_HISTORY_SIZE);
+        assert_eq!(history.entries().next().map(String::as_str), Some("29"));
+    }
+}
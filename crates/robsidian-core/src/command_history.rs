@@ -0,0 +1,111 @@
+//! Command history of the simple terminal
+//!
+//! The commands run in any of the simple terminal's tabs go into one history
+//! per vault. It is kept in the app data folder, one command per line, in a
+//! file named after a hash of the vault's path, so it survives restarts
+//! without being synced along with the vault. Running a command again right
+//! after itself records it once.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Commands kept before the oldest are dropped
+pub const COMMAND_HISTORY_SIZE: usize = 1000;
+
+/// Commands run, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    /// File the history is saved to; none keeps it in memory
+    file: Option<PathBuf>,
+}
+
+impl CommandHistory {
+    /// History of a vault, or of the app without one, kept in `dir`
+    pub fn load(dir: &Path, vault: Option<&Path>) -> Self {
+        // FNV-1a, so the file name stays the same across builds
+        let hash = vault
+            .map(|vault| vault.to_string_lossy().into_owned())
+            .unwrap_or_default()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+        let file = dir.join(format!("{:016x}.txt", hash));
+        let entries = std::fs::read_to_string(&file)
+            .map(|text| text.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect())
+            .unwrap_or_default();
+        Self { entries, file: Some(file) }
+    }
+
+    /// Remember a command that was run and save the history
+    pub fn push(&mut self, command: &str) -> Result<()> {
+        let command = command.trim();
+        // A command of several lines wouldn't read back as one
+        if command.is_empty() || command.contains('\n') || self.entries.last().is_some_and(|last| last == command) {
+            return Ok(());
+        }
+        self.entries.push(command.to_string());
+        let excess = self.entries.len().saturating_sub(COMMAND_HISTORY_SIZE);
+        self.entries.drain(..excess);
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create history folder: {}", dir.display()))?;
+        }
+        let mut text = self.entries.join("\n");
+        text.push('\n');
+        std::fs::write(file, text).with_context(|| format!("Failed to save command history: {}", file.display()))
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Index of the newest command before `before` that contains `query`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let before = before.min(self.entries.len());
+        self.entries[..before].iter().rposition(|entry| entry.contains(query))
+    }
+
+    /// Indices of the commands that contain `query`, newest first, each
+    /// command listed once
+    pub fn matches(&self, query: &str) -> Vec<usize> {
+        let mut seen = std::collections::HashSet::new();
+        (0..self.entries.len())
+            .rev()
+            .filter(|&index| self.entries[index].contains(query) && seen.insert(&self.entries[index]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_history() {
+        let dir = std::env::temp_dir().join(format!("robsidian_history_{}", std::process::id()));
+        let vault = Path::new("/vault");
+        let mut history = CommandHistory::load(&dir, Some(vault));
+        for command in ["ls", "git status", "git status", " ", "ls -la"] {
+            history.push(command).unwrap();
+        }
+        assert_eq!(history.entries(), ["ls", "git status", "ls -la"]);
+        assert_eq!(history.search("ls", 3), Some(2));
+        assert_eq!(history.search("ls", 2), Some(0));
+        assert_eq!(history.search("cargo", 3), None);
+        history.push("ls").unwrap();
+        assert_eq!(history.matches("ls"), [3, 2]);
+
+        // Saved per vault
+        assert_eq!(CommandHistory::load(&dir, Some(vault)).entries(), history.entries());
+        assert!(CommandHistory::load(&dir, None).entries().is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
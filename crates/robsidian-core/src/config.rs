@@ -0,0 +1,490 @@
+//! Application configuration management
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use super::note_publish::PublishTarget;
+use super::sync::{ConflictStrategy, RemoteConfig};
+
+/// Application configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppConfig {
+    /// Last opened vault path
+    pub last_vault: Option<PathBuf>,
+    /// Recent vaults
+    pub recent_vaults: Vec<PathBuf>,
+    /// Editor settings
+    pub editor: EditorConfig,
+    /// UI settings
+    pub ui: UiConfig,
+    /// Plugin settings
+    pub plugins: PluginConfig,
+    /// Note snapshot settings
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Vault sync settings
+    #[serde(default)]
+    pub sync: SyncConfig,
+    /// Read-only web view settings
+    #[serde(default)]
+    pub web_server: WebServerConfig,
+    /// Where the Publish Note command sends notes
+    #[serde(default)]
+    pub publish: Option<PublishTarget>,
+    /// Vault open in the second window
+    #[serde(default)]
+    pub second_vault: Option<PathBuf>,
+    /// Confirmation before opening web links
+    #[serde(default)]
+    pub links: LinkConfig,
+    /// Shell and look of the embedded terminal
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+}
+
+/// Editor-specific settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditorConfig {
+    /// Font size in pixels
+    pub font_size: f32,
+    /// Tab size in spaces
+    pub tab_size: usize,
+    /// Use soft tabs (spaces instead of tabs)
+    pub soft_tabs: bool,
+    /// Word wrap
+    pub word_wrap: bool,
+    /// Auto-save interval in seconds (0 = disabled)
+    pub auto_save_interval: u64,
+    /// Show line numbers
+    pub show_line_numbers: bool,
+    /// Command that edits a note in another program, e.g. `code --wait`
+    #[serde(default)]
+    pub external_command: Option<String>,
+}
+
+/// Font sizes the terminal can be set to, in points
+pub const TERMINAL_FONT_SIZES: RangeInclusive<f32> = 8.0..=32.0;
+
+/// Terminal font size of a new configuration
+pub const DEFAULT_TERMINAL_FONT_SIZE: f32 = 14.0;
+
+/// UI settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Theme (light/dark)
+    pub theme: String,
+    /// Sidebar width
+    pub sidebar_width: f32,
+    /// Terminal height
+    pub terminal_height: f32,
+    /// Font size of the PTY terminal, in points
+    pub terminal_font_size: f32,
+}
+
+impl UiConfig {
+    /// Set the terminal font size, kept within [`TERMINAL_FONT_SIZES`]
+    pub fn set_terminal_font_size(&mut self, size: f32) {
+        self.terminal_font_size = size.clamp(*TERMINAL_FONT_SIZES.start(), *TERMINAL_FONT_SIZES.end());
+    }
+}
+
+/// Lines of scrollback a terminal can be set to keep
+pub const SCROLLBACK_LINES: RangeInclusive<usize> = 100..=1_000_000;
+
+/// Embedded terminal settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Program new terminals run; empty for the system's shell
+    pub shell: String,
+    /// Arguments passed to the shell
+    pub args: Vec<String>,
+    /// Variables set for the shell on top of the app's environment
+    pub env: BTreeMap<String, String>,
+    /// Start shells in the open vault's folder rather than the home folder
+    pub start_in_vault: bool,
+    /// Lines kept above the screen
+    pub scrollback_lines: usize,
+    /// Name of the color scheme
+    pub color_scheme: String,
+    /// Reveal the folder the shown terminal is in in the file tree
+    pub follow_directory: bool,
+    /// Servers terminal tabs can connect to over SSH
+    pub ssh_hosts: Vec<SshHost>,
+    /// What a program ringing the bell does
+    pub bell: BellStyle,
+    /// What programs may do with the clipboard through OSC 52
+    pub clipboard: ClipboardAccess,
+}
+
+/// What the terminal does when a program rings the bell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BellStyle {
+    Off,
+    /// Flash the terminal
+    #[default]
+    Visual,
+    /// Flash the terminal and, when the window isn't focused, ask the
+    /// system to draw attention to it
+    Notify,
+}
+
+impl BellStyle {
+    pub const ALL: [BellStyle; 3] = [BellStyle::Off, BellStyle::Visual, BellStyle::Notify];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BellStyle::Off => "Off",
+            BellStyle::Visual => "Flash",
+            BellStyle::Notify => "Flash and notify",
+        }
+    }
+}
+
+/// How far programs in the terminal, including those on servers reached
+/// over SSH, may use the system clipboard (OSC 52)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardAccess {
+    #[default]
+    Off,
+    /// Programs may copy to the clipboard
+    Write,
+    /// Programs may also read what is on it
+    ReadWrite,
+}
+
+impl ClipboardAccess {
+    pub const ALL: [ClipboardAccess; 3] = [ClipboardAccess::Off, ClipboardAccess::Write, ClipboardAccess::ReadWrite];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ClipboardAccess::Off => "Off",
+            ClipboardAccess::Write => "Copy only",
+            ClipboardAccess::ReadWrite => "Copy and read",
+        }
+    }
+}
+
+impl TerminalConfig {
+    /// Shell new terminals run: the configured one, else `$SHELL` (`%COMSPEC%`
+    /// on Windows), else bash (cmd)
+    pub fn shell_program(&self) -> String {
+        if !self.shell.trim().is_empty() {
+            return self.shell.trim().to_string();
+        }
+        let (variable, fallback) = if cfg!(windows) { ("COMSPEC", "cmd") } else { ("SHELL", "bash") };
+        std::env::var(variable)
+            .ok()
+            .filter(|shell| !shell.is_empty())
+            .unwrap_or_else(|| fallback.to_string())
+    }
+
+    /// Folder of the theme files adding terminal color schemes
+    pub fn theme_dir() -> PathBuf {
+        ProjectDirs::from("com", "robsidian", "Robsidian")
+            .map(|dirs| dirs.config_dir().join("terminal_themes"))
+            .unwrap_or_else(|| PathBuf::from("terminal_themes"))
+    }
+}
+
+/// Server a terminal tab connects to with the system's `ssh` program, which
+/// also reads `~/.ssh/config` and asks for passwords in the tab
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshHost {
+    /// Name shown for it; the destination when empty
+    pub name: String,
+    pub host: String,
+    /// User to log in as; the one ssh picks when empty
+    pub user: String,
+    /// Port, when not ssh's default
+    pub port: Option<u16>,
+    /// Private key to log in with, rather than ssh's defaults
+    pub identity_file: Option<PathBuf>,
+}
+
+impl SshHost {
+    /// `user@host`, or the host alone
+    pub fn destination(&self) -> String {
+        match self.user.trim() {
+            "" => self.host.trim().to_string(),
+            user => format!("{}@{}", user, self.host.trim()),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.name.trim() {
+            "" => self.destination(),
+            name => name.to_string(),
+        }
+    }
+
+    /// Arguments of the `ssh` program connecting to the host
+    pub fn ssh_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        if let Some(key) = &self.identity_file {
+            args.extend(["-i".to_string(), key.to_string_lossy().into_owned()]);
+        }
+        // A host or user starting with `-` must not be taken for an option
+        args.push("--".to_string());
+        args.push(self.destination());
+        args
+    }
+}
+
+/// Plugin settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Plugin directory
+    pub plugin_dir: Option<PathBuf>,
+    /// Enabled plugins
+    pub enabled_plugins: Vec<String>,
+    /// Folder of user scripts (`.rhai` files)
+    #[serde(default)]
+    pub script_dir: Option<PathBuf>,
+}
+
+/// Local note snapshot settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Snapshot directory (defaults to the app data folder)
+    pub snapshot_dir: Option<PathBuf>,
+    /// Snapshots kept per note (0 = unlimited)
+    pub max_snapshots: usize,
+    /// Days a snapshot is kept (0 = forever)
+    pub max_age_days: u64,
+}
+
+/// Vault sync settings, kept in the app's config folder rather than in the
+/// vault where they would be synced too. The password or secret key is kept
+/// in the system's credential store, not in the settings file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    /// Sync in the background on a timer
+    pub enabled: bool,
+    pub remote: Option<RemoteConfig>,
+    /// Minutes between background syncs (0 = only when asked)
+    pub interval_minutes: u64,
+    pub conflicts: ConflictStrategy,
+}
+
+/// Read-only web view of the vault, for reading notes from other devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebServerConfig {
+    /// Start the server with the app
+    pub enabled: bool,
+    pub port: u16,
+    /// Access token required by the server (generated when empty)
+    pub token: String,
+}
+
+/// External link settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    /// Show the full address before opening a link outside the app
+    pub confirm_external: bool,
+    /// Domains (and their subdomains) opened without asking
+    pub trusted_domains: Vec<String>,
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 14.0,
+            tab_size: 4,
+            soft_tabs: true,
+            word_wrap: true,
+            auto_save_interval: 0,
+            show_line_numbers: true,
+            external_command: None,
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            theme: "dark".to_string(),
+            sidebar_width: 250.0,
+            terminal_height: 200.0,
+            terminal_font_size: DEFAULT_TERMINAL_FONT_SIZE,
+        }
+    }
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            shell: String::new(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            start_in_vault: true,
+            scrollback_lines: 10_000,
+            color_scheme: "Default".to_string(),
+            follow_directory: false,
+            ssh_hosts: Vec::new(),
+            bell: BellStyle::default(),
+            clipboard: ClipboardAccess::default(),
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            snapshot_dir: None,
+            max_snapshots: 50,
+            max_age_days: 90,
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            remote: None,
+            interval_minutes: 5,
+            conflicts: ConflictStrategy::default(),
+        }
+    }
+}
+
+impl Default for WebServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8765,
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            confirm_external: true,
+            trusted_domains: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Get the config file path
+    fn config_path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "robsidian", "Robsidian")
+            .map(|dirs| dirs.config_dir().join("config.json"))
+    }
+
+    /// Load configuration from disk
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let mut config: Self = serde_json::from_str(&content)?;
+        match config.sync.remote.as_mut().map(RemoteConfig::load_secret) {
+            // The password is in the credential store now; saving again only
+            // takes it out of the file, and is tried again on the next start
+            Some(Ok(true)) => {
+                if let Err(e) = config.save() {
+                    tracing::warn!("Failed to remove the sync password from the settings: {:#}", e);
+                }
+            }
+            Some(Err(e)) => tracing::warn!("{:#}", e),
+            _ => {}
+        }
+        Ok(config)
+    }
+
+    /// Save configuration to disk
+    pub fn save(&self) -> Result<()> {
+        let path = Self::config_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+
+        // Ensure config directory exists
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, content)?;
+
+        tracing::info!("Saved config to: {}", path.display());
+        Ok(())
+    }
+
+    /// Add a vault to recent vaults
+    pub fn add_recent_vault(&mut self, path: PathBuf) {
+        // Remove if already exists
+        self.recent_vaults.retain(|p| p != &path);
+        // Add to front
+        self.recent_vaults.insert(0, path);
+        // Keep only last 10
+        self.recent_vaults.truncate(10);
+    }
+
+    /// Get the plugin directory
+    pub fn get_plugin_dir(&self) -> PathBuf {
+        self.plugins.plugin_dir.clone().unwrap_or_else(|| {
+            ProjectDirs::from("com", "robsidian", "Robsidian")
+                .map(|dirs| dirs.data_dir().join("plugins"))
+                .unwrap_or_else(|| PathBuf::from("plugins"))
+        })
+    }
+
+    /// Get the user scripts directory
+    pub fn get_script_dir(&self) -> PathBuf {
+        self.plugins.script_dir.clone().unwrap_or_else(|| {
+            ProjectDirs::from("com", "robsidian", "Robsidian")
+                .map(|dirs| dirs.data_dir().join("scripts"))
+                .unwrap_or_else(|| PathBuf::from("scripts"))
+        })
+    }
+
+    /// Get the note snapshot directory
+    pub fn get_snapshot_dir(&self) -> PathBuf {
+        self.history.snapshot_dir.clone().unwrap_or_else(|| {
+            ProjectDirs::from("com", "robsidian", "Robsidian")
+                .map(|dirs| dirs.data_dir().join("snapshots"))
+                .unwrap_or_else(|| PathBuf::from("snapshots"))
+        })
+    }
+
+    /// Get the folder of the simple terminal's command histories
+    pub fn get_command_history_dir(&self) -> PathBuf {
+        ProjectDirs::from("com", "robsidian", "Robsidian")
+            .map(|dirs| dirs.data_dir().join("terminal_history"))
+            .unwrap_or_else(|| PathBuf::from("terminal_history"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ssh_args_end_options() {
+        let host = SshHost { host: "-oProxyCommand=touch /tmp/x".to_string(), port: Some(2222), ..Default::default() };
+        assert_eq!(host.ssh_args(), ["-p", "2222", "--", "-oProxyCommand=touch /tmp/x"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Note encryption
+//!
+//! Encrypted notes are stored as a header line followed by base64 text:
+//!
+//! ```text
+//! %% robsidian-encrypted v1 %%
+//! <salt (16 bytes) | nonce (12 bytes) | AES-256-GCM ciphertext>
+//! ```
+//!
+//! The key is derived from the passphrase with Argon2id and a random salt per
+//! save. The header is a markdown comment, so other editors show the note as
+//! an opaque block instead of garbled text. Decrypted content only ever lives
+//! in memory.
+
+use std::fmt;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
+use base64::Engine;
+
+/// First line of an encrypted note
+const HEADER: &str = "%% robsidian-encrypted v1 %%";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+/// Width of the base64 lines
+const LINE_WIDTH: usize = 76;
+
+/// A passphrase kept in memory; never shown in debug output or logs
+#[derive(Clone, PartialEq, Eq)]
+pub struct Passphrase(Arc<str>);
+
+impl Passphrase {
+    pub fn new(passphrase: &str) -> Self {
+        Self(passphrase.into())
+    }
+}
+
+impl fmt::Debug for Passphrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Passphrase(..)")
+    }
+}
+
+/// Whether a file's text is an encrypted note
+pub fn is_encrypted(text: &str) -> bool {
+    text.trim_start().starts_with(HEADER)
+}
+
+fn cipher(passphrase: &Passphrase, salt: &[u8]) -> Result<Aes256Gcm> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.0.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive the key: {}", e))?;
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+/// Encrypt a note's content into its stored form
+pub fn encrypt(plaintext: &str, passphrase: &Passphrase) -> Result<String> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(passphrase, &salt)?
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow!("Failed to encrypt the note"))?;
+
+    let mut payload = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&salt);
+    payload.extend_from_slice(&nonce);
+    payload.extend_from_slice(&ciphertext);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(payload);
+
+    let mut armored = format!("{}\n", HEADER);
+    for line in encoded.as_bytes().chunks(LINE_WIDTH) {
+        // base64 is ASCII, so any split is on a character boundary
+        armored.push_str(std::str::from_utf8(line).unwrap_or_default());
+        armored.push('\n');
+    }
+    Ok(armored)
+}
+
+/// Decrypt the stored form of a note
+pub fn decrypt(armored: &str, passphrase: &Passphrase) -> Result<String> {
+    let Some(body) = armored.trim_start().strip_prefix(HEADER) else {
+        bail!("The note is not encrypted");
+    };
+    let encoded: String = body.chars().filter(|c| !c.is_whitespace()).collect();
+    let payload = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .context("The encrypted note is damaged")?;
+    if payload.len() < SALT_LEN + NONCE_LEN {
+        bail!("The encrypted note is damaged");
+    }
+
+    let (salt, rest) = payload.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let plaintext = cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow!("Wrong passphrase, or the encrypted note is damaged"))?;
+    String::from_utf8(plaintext).context("The decrypted note is not valid text")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let passphrase = Passphrase::new("correct horse");
+        let armored = encrypt("# Secret\n\nPIN: 1234\n", &passphrase).unwrap();
+
+        assert!(is_encrypted(&armored));
+        assert!(!armored.contains("Secret"));
+        assert_eq!(decrypt(&armored, &passphrase).unwrap(), "# Secret\n\nPIN: 1234\n");
+        assert!(decrypt(&armored, &Passphrase::new("wrong")).is_err());
+        assert_eq!(format!("{:?}", passphrase), "Passphrase(..)");
+    }
+}
@@ -0,0 +1,300 @@
+//! Line and word diffs and three-way merges between versions of a note
+
+use serde::{Deserialize, Serialize};
+
+/// How a line differs between the old and the new text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Unchanged,
+    Added,
+    Removed,
+}
+
+/// A line of a diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine<'a> {
+    pub kind: DiffKind,
+    pub text: &'a str,
+}
+
+/// `lcs[i][j]`: length of the longest common subsequence of `a[i..]` and `b[j..]`
+fn lcs_table(a: &[&str], b: &[&str]) -> Vec<Vec<usize>> {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    lcs
+}
+
+/// For each line of `a`, the line of `b` it is matched with in a longest
+/// common subsequence
+fn lcs_matches(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    let lcs = lcs_table(a, b);
+    let mut matches = vec![None; a.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+/// Result of a three-way merge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    /// Merged text, with conflict markers around changes made on both sides
+    pub text: String,
+    /// Number of conflicting regions
+    pub conflicts: usize,
+}
+
+/// Merge two versions of a text that both started from `base`, line by line.
+/// Changes made on one side only are taken as is; regions changed
+/// differently on both sides are kept between `<<<<<<<` / `=======` /
+/// `>>>>>>>` markers.
+pub fn merge3(base: &str, mine: &str, theirs: &str) -> Merge {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let mine_lines: Vec<&str> = mine.lines().collect();
+    let theirs_lines: Vec<&str> = theirs.lines().collect();
+    let to_mine = lcs_matches(&base_lines, &mine_lines);
+    let to_theirs = lcs_matches(&base_lines, &theirs_lines);
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    loop {
+        // Next base line both sides kept; everything before it was changed
+        // on at least one side
+        let stable = (b..base_lines.len()).find_map(|k| Some((k, to_mine[k]?, to_theirs[k]?)));
+        let (k, mk, tk) = stable.unwrap_or((base_lines.len(), mine_lines.len(), theirs_lines.len()));
+
+        let base_chunk = &base_lines[b..k];
+        let mine_chunk = &mine_lines[m..mk];
+        let theirs_chunk = &theirs_lines[t..tk];
+        if mine_chunk == theirs_chunk || theirs_chunk == base_chunk {
+            merged.extend(mine_chunk);
+        } else if mine_chunk == base_chunk {
+            merged.extend(theirs_chunk);
+        } else {
+            conflicts += 1;
+            merged.push("<<<<<<< Mine");
+            merged.extend(mine_chunk);
+            merged.push("=======");
+            merged.extend(theirs_chunk);
+            merged.push(">>>>>>> On disk");
+        }
+
+        let Some((k, mk, tk)) = stable else {
+            break;
+        };
+        merged.push(base_lines[k]);
+        (b, m, t) = (k + 1, mk + 1, tk + 1);
+    }
+
+    let mut text = merged.join("\n");
+    if mine.ends_with('\n') || theirs.ends_with('\n') {
+        text.push('\n');
+    }
+    Merge { text, conflicts }
+}
+
+/// Diff two texts line by line (longest common subsequence), listing removed
+/// lines before the lines that replace them
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Skip the common head and tail so the table only covers the changed part
+    let head = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[head..old.len() - tail];
+    let new_mid = &new[head..new.len() - tail];
+
+    let lcs = lcs_table(old_mid, new_mid);
+    let line = |kind, text| DiffLine { kind, text };
+    let mut result: Vec<DiffLine> = old[..head].iter().map(|t| line(DiffKind::Unchanged, *t)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            result.push(line(DiffKind::Unchanged, old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if i < old_mid.len() && (j == new_mid.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(line(DiffKind::Removed, old_mid[i]));
+            i += 1;
+        } else {
+            result.push(line(DiffKind::Added, new_mid[j]));
+            j += 1;
+        }
+    }
+    result.extend(old[old.len() - tail..].iter().map(|t| line(DiffKind::Unchanged, *t)));
+    result
+}
+
+/// Words of unchanged text kept before each word change
+const CONTEXT_WORDS: usize = 4;
+
+/// Largest table, in token pairs, a changed region is diffed word by word
+/// with; larger regions count as replaced as a whole
+const MAX_WORD_TABLE: usize = 4_000_000;
+
+/// Words removed at one place and the words that replaced them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordChange {
+    /// Unchanged words just before the change, to tell where it is
+    pub before: String,
+    pub removed: String,
+    pub added: String,
+}
+
+/// Words and the whitespace between them, as separate tokens
+fn word_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut space = None;
+    for (i, c) in text.char_indices() {
+        if space.is_some_and(|space| space != c.is_whitespace()) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        space = Some(c.is_whitespace());
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Diff two texts word by word. The lines are diffed first, so only the
+/// lines that changed are compared word by word.
+pub fn diff_words(old: &str, new: &str) -> Vec<WordChange> {
+    let lines = diff_lines(old, new);
+    let mut changes = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if lines[i].kind == DiffKind::Unchanged {
+            i += 1;
+            continue;
+        }
+        let hunk_start = i;
+        while i < lines.len() && lines[i].kind != DiffKind::Unchanged {
+            i += 1;
+        }
+        let hunk = &lines[hunk_start..i];
+        let join = |kind| hunk.iter().filter(|l| l.kind == kind).map(|l| l.text).collect::<Vec<_>>().join("\n");
+        let context = hunk_start.checked_sub(1).map_or("", |line| lines[line].text);
+        diff_hunk_words(&join(DiffKind::Removed), &join(DiffKind::Added), context, &mut changes);
+    }
+    changes
+}
+
+/// Word changes between the old and new lines of a changed region, where
+/// the line before the region gives the context of a change at its start
+fn diff_hunk_words(old: &str, new: &str, context: &str, changes: &mut Vec<WordChange>) {
+    let old = word_tokens(old);
+    let new = word_tokens(new);
+    let matches = if old.len().saturating_mul(new.len()) <= MAX_WORD_TABLE {
+        lcs_matches(&old, &new)
+    } else {
+        vec![None; old.len()]
+    };
+
+    let mut before: Vec<&str> = word_tokens(context);
+    if !context.is_empty() {
+        before.push("\n");
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if let Some(matched) = matches.get(i).copied().flatten().filter(|&m| m == j) {
+            before.push(old[i]);
+            i += 1;
+            j = matched + 1;
+            continue;
+        }
+        let removed_end = (i..old.len()).find(|&k| matches[k].is_some()).unwrap_or(old.len());
+        let added_end = matches.get(removed_end).copied().flatten().unwrap_or(new.len());
+        let context_start = before.len().saturating_sub(CONTEXT_WORDS * 2);
+        changes.push(WordChange {
+            before: before[context_start..].concat(),
+            removed: old[i..removed_end].concat(),
+            added: new[j..added_end].concat(),
+        });
+        before.extend(&new[j..added_end]);
+        (i, j) = (removed_end, added_end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("a\nb\nc\nd\n", "a\nx\nc\nd\ne\n");
+        let kinds: Vec<(DiffKind, &str)> = diff.iter().map(|l| (l.kind, l.text)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (DiffKind::Unchanged, "a"),
+                (DiffKind::Removed, "b"),
+                (DiffKind::Added, "x"),
+                (DiffKind::Unchanged, "c"),
+                (DiffKind::Unchanged, "d"),
+                (DiffKind::Added, "e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_words() {
+        let old = "# Title\nThe quick brown fox jumps.\nUnchanged line\n";
+        let new = "# Title\nThe quick red fox leaps.\nUnchanged line\nA new line\n";
+        let change = |before: &str, removed: &str, added: &str| WordChange {
+            before: before.to_string(),
+            removed: removed.to_string(),
+            added: added.to_string(),
+        };
+        assert_eq!(
+            diff_words(old, new),
+            vec![
+                change("# Title\nThe quick ", "brown", "red"),
+                change("The quick red fox ", "jumps.", "leaps."),
+                change("Unchanged line\n", "", "A new line"),
+            ]
+        );
+        assert!(diff_words(old, old).is_empty());
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = "title\none\ntwo\nthree\n";
+        let merge = merge3(base, "title\nONE\ntwo\nthree\n", "title\none\ntwo\nthree\nfour\n");
+        assert_eq!(merge.text, "title\nONE\ntwo\nthree\nfour\n");
+        assert_eq!(merge.conflicts, 0);
+
+        let merge = merge3(base, "title\nmine\ntwo\nthree\n", "title\ntheirs\ntwo\nthree\n");
+        assert_eq!(merge.conflicts, 1);
+        assert_eq!(
+            merge.text,
+            "title\n<<<<<<< Mine\nmine\n=======\ntheirs\n>>>>>>> On disk\ntwo\nthree\n"
+        );
+    }
+}
@@ -0,0 +1,546 @@
+//! Document management for markdown files
+
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::crypto::{decrypt, encrypt, is_encrypted, Passphrase};
+use super::text_encoding::{normalize_line_endings, LineEnding, TextEncoding};
+
+/// Root of the paths of untitled scratch buffers. They live only in memory
+/// until their first save, which asks where to put them.
+pub const SCRATCH_ROOT: &str = "untitled:";
+
+/// Whether a path belongs to an untitled scratch buffer rather than a file
+pub fn is_scratch_path(path: &Path) -> bool {
+    path.starts_with(SCRATCH_ROOT)
+}
+
+/// A markdown document
+#[derive(Debug, Clone)]
+pub struct Document {
+    /// File path
+    pub path: PathBuf,
+    /// Document content
+    pub content: String,
+    /// Whether the document has unsaved changes
+    pub modified: bool,
+    /// Last modification time
+    pub last_modified: Option<SystemTime>,
+    /// Document metadata (YAML frontmatter)
+    pub metadata: DocumentMetadata,
+    /// Line ending the file is saved with (`content` always uses `\n`)
+    pub line_ending: LineEnding,
+    /// Encoding the file is saved with
+    pub encoding: TextEncoding,
+    /// The file as it was when last read or saved, to detect outside edits
+    pub disk: Option<DiskState>,
+    /// Passphrase the note is encrypted with when saved
+    pub passphrase: Option<Passphrase>,
+    /// Whether `content` still holds the encrypted form, waiting for the
+    /// passphrase
+    pub locked: bool,
+}
+
+/// What a document's file held when it was last read or written
+#[derive(Debug, Clone)]
+pub struct DiskState {
+    pub modified: Option<SystemTime>,
+    hash: u64,
+    /// Content of the file, with `\n` line endings
+    pub content: String,
+}
+
+impl DiskState {
+    fn new(path: &Path, bytes: &[u8], content: String) -> Self {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        Self {
+            modified: fs::metadata(path).ok().and_then(|m| m.modified().ok()),
+            hash: hasher.finish(),
+            content,
+        }
+    }
+}
+
+/// A save was refused because the file changed on disk since the document
+/// was read
+#[derive(Debug, thiserror::Error)]
+#[error("{} was changed outside Robsidian", path.display())]
+pub struct SaveConflict {
+    pub path: PathBuf,
+    /// The file as it is now
+    pub disk: DiskState,
+}
+
+/// Document metadata from YAML frontmatter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    pub aliases: Vec<String>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    /// Named visual variants applied to the note's preview
+    #[serde(default, alias = "cssclass")]
+    pub cssclasses: Vec<String>,
+    /// Banner image shown at the top of the preview
+    #[serde(default)]
+    pub banner: Option<String>,
+    /// Banner height in points
+    #[serde(default)]
+    pub banner_height: Option<f32>,
+    /// Vertical focus of the banner image (0.0 = top, 1.0 = bottom)
+    #[serde(default)]
+    pub banner_offset: Option<f32>,
+}
+
+impl Document {
+    /// Create a new empty document
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            content: String::new(),
+            modified: false,
+            last_modified: None,
+            metadata: DocumentMetadata::default(),
+            line_ending: LineEnding::default(),
+            encoding: TextEncoding::default(),
+            disk: None,
+            passphrase: None,
+            locked: false,
+        }
+    }
+
+    /// Create the untitled scratch buffer numbered `number`
+    pub fn scratch(number: usize) -> Self {
+        Self::new(Path::new(SCRATCH_ROOT).join(format!("Untitled {}.md", number)))
+    }
+
+    /// Whether this is a scratch buffer that has no file yet
+    pub fn is_scratch(&self) -> bool {
+        is_scratch_path(&self.path)
+    }
+
+    /// Open a document from a file
+    pub fn open(path: &Path) -> Result<Self> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let (text, encoding) = TextEncoding::decode(&bytes);
+        let line_ending = LineEnding::detect(&text);
+        let content = normalize_line_endings(&text);
+
+        let last_modified = fs::metadata(path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+
+        let locked = is_encrypted(&content);
+        let metadata = if locked {
+            DocumentMetadata::default()
+        } else {
+            Self::parse_frontmatter(&content).unwrap_or_default()
+        };
+        let disk = DiskState::new(path, &bytes, content.clone());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            content,
+            modified: false,
+            last_modified,
+            metadata,
+            line_ending,
+            encoding,
+            disk: Some(disk),
+            passphrase: None,
+            locked,
+        })
+    }
+
+    /// Decrypt a locked note, keeping the passphrase to encrypt it again on save
+    pub fn unlock(&mut self, passphrase: Passphrase) -> Result<()> {
+        if !self.locked {
+            return Ok(());
+        }
+        self.content = normalize_line_endings(&decrypt(&self.content, &passphrase)?);
+        self.locked = false;
+        self.passphrase = Some(passphrase);
+        self.refresh_metadata();
+        if let Some(disk) = &mut self.disk {
+            disk.content = self.content.clone();
+        }
+        Ok(())
+    }
+
+    /// Save the document to disk with its line ending and encoding, returning
+    /// what the file now holds. Fails with a [`SaveConflict`] instead of
+    /// overwriting a file that changed since it was read.
+    pub fn save(&self) -> Result<DiskState> {
+        if self.is_scratch() {
+            bail!("{} has no file yet; choose where to save it", self.title());
+        }
+        if self.locked {
+            bail!("{} is encrypted and was never unlocked", self.path.display());
+        }
+        self.check_disk()?;
+        let text = match &self.passphrase {
+            Some(passphrase) => encrypt(&self.content, passphrase)?,
+            None => self.content.clone(),
+        };
+        let bytes = self
+            .encoding
+            .encode(&self.line_ending.apply(&text))
+            .with_context(|| format!("Failed to encode file: {}", self.path.display()))?;
+        fs::write(&self.path, &bytes)
+            .with_context(|| format!("Failed to save file: {}", self.path.display()))?;
+        tracing::info!("Saved document: {}", self.path.display());
+        Ok(DiskState::new(&self.path, &bytes, self.content.clone()))
+    }
+
+    /// Make sure the file still holds what was last read or saved
+    fn check_disk(&self) -> Result<()> {
+        let Ok(metadata) = fs::metadata(&self.path) else {
+            return Ok(()); // Not created yet, or deleted: nothing to lose
+        };
+        // Same modification time: unchanged, no need to read the file
+        if let Some(disk) = &self.disk {
+            if disk.modified.is_some() && metadata.modified().ok() == disk.modified {
+                return Ok(());
+            }
+        }
+
+        let bytes = fs::read(&self.path)
+            .with_context(|| format!("Failed to read file: {}", self.path.display()))?;
+        let (text, _) = TextEncoding::decode(&bytes);
+        let mut text = normalize_line_endings(&text);
+        // Offer the decrypted content for comparing and merging
+        if let (Some(passphrase), true) = (&self.passphrase, is_encrypted(&text)) {
+            if let Ok(plaintext) = decrypt(&text, passphrase) {
+                text = normalize_line_endings(&plaintext);
+            }
+        }
+        let current = DiskState::new(&self.path, &bytes, text);
+        match &self.disk {
+            Some(disk) if disk.hash == current.hash => Ok(()),
+            _ => Err(SaveConflict {
+                path: self.path.clone(),
+                disk: current,
+            }
+            .into()),
+        }
+    }
+
+    /// Save the document and update modified flag
+    pub fn save_mut(&mut self) -> Result<()> {
+        self.disk = Some(self.save()?);
+        self.modified = false;
+        self.last_modified = Some(SystemTime::now());
+        Ok(())
+    }
+
+    /// Get the document title (filename without extension or metadata title)
+    pub fn title(&self) -> String {
+        self.metadata.title.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Untitled".to_string())
+        })
+    }
+
+    /// Parse YAML frontmatter from content
+    fn parse_frontmatter(content: &str) -> Option<DocumentMetadata> {
+        if !content.starts_with("---") {
+            return None;
+        }
+
+        let end = content[3..].find("---")?;
+        let frontmatter = &content[3..3 + end].trim();
+
+        serde_json::from_str(frontmatter).ok().or_else(|| {
+            // Try simple key-value parsing
+            let mut metadata = DocumentMetadata::default();
+            for line in frontmatter.lines() {
+                if let Some((key, value)) = line.split_once(':') {
+                    let key = key.trim();
+                    let value = value.trim().trim_matches('"');
+                    match key {
+                        "title" => metadata.title = Some(value.to_string()),
+                        "tags" => metadata.tags = Self::parse_list(value),
+                        "aliases" | "alias" => metadata.aliases = Self::parse_list(value),
+                        "cssclass" | "cssclasses" | "style" => {
+                            metadata.cssclasses = Self::parse_list(value);
+                        }
+                        "banner" => {
+                            // Accept plain paths as well as `![[image.png]]` embeds
+                            let banner = value
+                                .trim_start_matches('!')
+                                .trim_start_matches("[[")
+                                .trim_end_matches("]]");
+                            if !banner.is_empty() {
+                                metadata.banner = Some(banner.to_string());
+                            }
+                        }
+                        "banner_height" => metadata.banner_height = value.parse().ok(),
+                        "banner_offset" => metadata.banner_offset = value.parse().ok(),
+                        _ => {}
+                    }
+                }
+            }
+            Some(metadata)
+        })
+    }
+
+    /// Raw frontmatter between the `---` fences
+    fn frontmatter(&self) -> Option<&str> {
+        let rest = self.content.strip_prefix("---")?;
+        let end = rest.find("---")?;
+        Some(rest[..end].trim())
+    }
+
+    /// Value of any frontmatter field as text, lists joined with `, `
+    pub fn frontmatter_field(&self, key: &str) -> Option<String> {
+        let frontmatter = self.frontmatter()?;
+        if let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(frontmatter) {
+            return match fields.get(key)? {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Array(items) => Some(
+                    items
+                        .iter()
+                        .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                value => Some(value.to_string()),
+            };
+        }
+
+        frontmatter.lines().find_map(|line| {
+            let (k, value) = line.split_once(':')?;
+            if k.trim() != key {
+                return None;
+            }
+            let value = value.trim();
+            if value.starts_with('[') {
+                Some(Self::parse_list(value).join(", "))
+            } else {
+                Some(value.trim_matches('"').to_string())
+            }
+        })
+    }
+
+    /// Set a text frontmatter field, adding the field (or the frontmatter)
+    /// if the note doesn't have it yet
+    pub fn set_frontmatter_field(&mut self, key: &str, value: &str) {
+        let Some(frontmatter) = self.frontmatter() else {
+            let content = format!("---\n{}: {}\n---\n\n{}", key, value, self.content);
+            self.set_content(content);
+            return;
+        };
+        let body = self.content_without_frontmatter().to_string();
+
+        let frontmatter = if let Ok(serde_json::Value::Object(mut fields)) = serde_json::from_str(frontmatter) {
+            fields.insert(key.to_string(), serde_json::Value::String(value.to_string()));
+            serde_json::to_string_pretty(&fields).unwrap_or_default()
+        } else {
+            let mut lines: Vec<String> = frontmatter.lines().map(str::to_string).collect();
+            let field = format!("{}: {}", key, value);
+            match lines.iter_mut().find(|line| line.split_once(':').is_some_and(|(k, _)| k.trim() == key)) {
+                Some(line) => *line = field,
+                None => lines.push(field),
+            }
+            lines.join("\n")
+        };
+        self.set_content(format!("---\n{}\n---\n\n{}", frontmatter, body));
+    }
+
+    /// Set `created` on a new note's first save and bump `updated`, both
+    /// written with the chrono `format`. Notes without frontmatter only get
+    /// one when they are new, and an invalid format stamps nothing. Returns
+    /// whether the content changed.
+    pub fn stamp_timestamps(&mut self, format: &str, now: chrono::DateTime<chrono::Local>) -> bool {
+        let is_new = self.disk.as_ref().is_none_or(|disk| disk.content.trim().is_empty());
+        if !is_new && self.frontmatter().is_none() {
+            return false;
+        }
+        let mut stamp = String::new();
+        if write!(stamp, "{}", now.format(format)).is_err() {
+            return false;
+        }
+        let mut changed = false;
+        if is_new && self.frontmatter_field("created").is_none() {
+            self.set_frontmatter_field("created", &stamp);
+            changed = true;
+        }
+        if self.frontmatter_field("updated").as_deref() != Some(stamp.as_str()) {
+            self.set_frontmatter_field("updated", &stamp);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Parse an inline `[a, b]` or comma separated frontmatter list
+    fn parse_list(value: &str) -> Vec<String> {
+        value
+            .trim_matches(|c| c == '[' || c == ']')
+            .split(',')
+            .map(|s| s.trim().trim_matches('"').to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    /// Re-read the frontmatter after the content was edited
+    pub fn refresh_metadata(&mut self) {
+        self.metadata = Self::parse_frontmatter(&self.content).unwrap_or_default();
+    }
+
+    /// Get content without frontmatter for preview
+    pub fn content_without_frontmatter(&self) -> &str {
+        if !self.content.starts_with("---") {
+            return &self.content;
+        }
+
+        if let Some(end) = self.content[3..].find("---") {
+            let after_frontmatter = 3 + end + 3;
+            if after_frontmatter < self.content.len() {
+                return self.content[after_frontmatter..].trim_start();
+            }
+        }
+
+        &self.content
+    }
+
+    /// Change the line ending used when saving
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.modified = true;
+        }
+    }
+
+    /// Change the encoding used when saving
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        if self.encoding != encoding {
+            self.encoding = encoding;
+            self.modified = true;
+        }
+    }
+
+    /// Update content and mark as modified
+    pub fn set_content(&mut self, content: String) {
+        if self.content != content {
+            self.content = content;
+            self.modified = true;
+            self.refresh_metadata();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frontmatter() {
+        let content = r#"---
+title: "Test Document"
+tags: [rust, markdown]
+---
+
+# Content here
+"#;
+        let metadata = Document::parse_frontmatter(content).unwrap();
+        assert_eq!(metadata.title, Some("Test Document".to_string()));
+    }
+
+    #[test]
+    fn test_save_conflict() {
+        let path = std::env::temp_dir().join(format!("robsidian_conflict_{}.md", std::process::id()));
+        fs::write(&path, "original\n").unwrap();
+        let mut doc = Document::open(&path).unwrap();
+        doc.content = "mine\n".to_string();
+
+        fs::write(&path, "changed elsewhere\n").unwrap();
+        let err = doc.save().unwrap_err();
+        let conflict = err.downcast_ref::<SaveConflict>().unwrap();
+        assert_eq!(conflict.disk.content, "changed elsewhere\n");
+
+        // Saving over the state the user has seen is allowed
+        doc.disk = Some(conflict.disk.clone());
+        doc.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "mine\n");
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_cssclasses() {
+        let content = "---\ncssclass: [wide, serif]\n---\n\nBody\n";
+        let metadata = Document::parse_frontmatter(content).unwrap();
+        assert_eq!(metadata.cssclasses, vec!["wide", "serif"]);
+    }
+
+    #[test]
+    fn test_parse_banner() {
+        let content = "---\nbanner: \"![[assets/cover.png]]\"\nbanner_offset: 0.3\n---\n";
+        let metadata = Document::parse_frontmatter(content).unwrap();
+        assert_eq!(metadata.banner.as_deref(), Some("assets/cover.png"));
+        assert_eq!(metadata.banner_offset, Some(0.3));
+        assert_eq!(metadata.banner_height, None);
+    }
+
+    #[test]
+    fn test_set_frontmatter_field() {
+        let mut doc = Document::new(PathBuf::from("note.md"));
+        doc.set_content("Body\n".to_string());
+        doc.set_frontmatter_field("publish_url", "https://example.com/a");
+        assert_eq!(doc.content, "---\npublish_url: https://example.com/a\n---\n\nBody\n");
+
+        doc.set_frontmatter_field("title", "Note");
+        doc.set_frontmatter_field("publish_url", "https://example.com/b");
+        assert_eq!(doc.frontmatter_field("publish_url").as_deref(), Some("https://example.com/b"));
+        assert_eq!(doc.metadata.title.as_deref(), Some("Note"));
+        assert!(doc.content.ends_with("title: Note\n---\n\nBody\n"));
+    }
+
+    #[test]
+    fn test_scratch_document() {
+        let mut doc = Document::scratch(2);
+        doc.set_content("Draft\n".to_string());
+        assert!(doc.is_scratch());
+        assert_eq!(doc.title(), "Untitled 2");
+        assert!(doc.save().is_err());
+        assert!(!is_scratch_path(Path::new("notes/untitled.md")));
+    }
+
+    #[test]
+    fn test_stamp_timestamps() {
+        use chrono::TimeZone;
+        let morning = chrono::Local.with_ymd_and_hms(2024, 5, 1, 9, 30, 0).unwrap();
+        let evening = chrono::Local.with_ymd_and_hms(2024, 5, 1, 18, 0, 0).unwrap();
+
+        let mut doc = Document::new(PathBuf::from("new.md"));
+        doc.set_content("# New".to_string());
+        assert!(doc.stamp_timestamps("%Y-%m-%d %H:%M", morning));
+        assert_eq!(doc.frontmatter_field("created").as_deref(), Some("2024-05-01 09:30"));
+        assert_eq!(doc.frontmatter_field("updated").as_deref(), Some("2024-05-01 09:30"));
+        assert!(!doc.stamp_timestamps("%Y-%m-%d %H:%M", morning));
+
+        // Once on disk, only `updated` moves
+        doc.disk = Some(DiskState::new(&doc.path, doc.content.as_bytes(), doc.content.clone()));
+        assert!(doc.stamp_timestamps("%Y-%m-%d %H:%M", evening));
+        assert_eq!(doc.frontmatter_field("created").as_deref(), Some("2024-05-01 09:30"));
+        assert_eq!(doc.frontmatter_field("updated").as_deref(), Some("2024-05-01 18:00"));
+
+        // Existing notes without frontmatter are left alone
+        let mut plain = Document::new(PathBuf::from("plain.md"));
+        plain.set_content("text".to_string());
+        plain.disk = Some(DiskState::new(&plain.path, b"text", "text".to_string()));
+        assert!(!plain.stamp_timestamps("%Y-%m-%d", evening));
+        assert_eq!(plain.content, "text");
+        assert!(!doc.stamp_timestamps("%Q", evening));
+    }
+}
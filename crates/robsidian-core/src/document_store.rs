@@ -0,0 +1,194 @@
+//! Open documents and their change events
+//!
+//! Every open document lives in the [`DocumentStore`]. Edits go through
+//! [`DocumentStore::edit`], which compares the document before and after and
+//! tells subscribers what changed, so the note index, plugins and anything
+//! else keeping derived state can follow along without polling or cloning.
+//! Subscribers get a channel, so they may run on other threads.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use super::document::Document;
+
+/// Something that happened to an open document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentEvent {
+    Opened(PathBuf),
+    /// The text changed; sent along with `Frontmatter` when that changed too
+    Content(PathBuf),
+    /// The YAML frontmatter changed and the metadata was parsed again
+    Frontmatter(PathBuf),
+    Saved(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+    Closed(PathBuf),
+}
+
+/// Hashes of a document's frontmatter and body
+fn fingerprint(doc: &Document) -> (u64, u64) {
+    let hash = |text: &str| {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        text.hash(&mut hasher);
+        hasher.finish()
+    };
+    let body = doc.content_without_frontmatter();
+    let frontmatter = &doc.content[..doc.content.len() - body.len()];
+    (hash(frontmatter), hash(body))
+}
+
+/// The open documents, by path
+#[derive(Debug, Default)]
+pub struct DocumentStore {
+    documents: HashMap<PathBuf, Document>,
+    subscribers: Vec<Sender<DocumentEvent>>,
+}
+
+impl DocumentStore {
+    /// Receive the events of every document from now on
+    pub fn subscribe(&mut self) -> Receiver<DocumentEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: DocumentEvent) {
+        // Subscribers that dropped their receiver are forgotten
+        self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Document> {
+        self.documents.get(path)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.documents.contains_key(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.documents.keys()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Document> {
+        self.documents.values()
+    }
+
+    /// Change a document; subscribers hear about it once the returned guard
+    /// is dropped
+    pub fn edit(&mut self, path: &Path) -> Option<DocumentEdit<'_>> {
+        let doc = self.documents.get_mut(path)?;
+        let before = fingerprint(doc);
+        Some(DocumentEdit { doc, subscribers: &mut self.subscribers, before })
+    }
+
+    /// Add a document, replacing the one at the same path
+    pub fn insert(&mut self, doc: Document) {
+        let path = doc.path.clone();
+        self.documents.insert(path.clone(), doc);
+        self.emit(DocumentEvent::Opened(path));
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<Document> {
+        let doc = self.documents.remove(path)?;
+        self.emit(DocumentEvent::Closed(path.to_path_buf()));
+        Some(doc)
+    }
+
+    /// Move an open document to the path its file was renamed to
+    pub fn rename(&mut self, from: &Path, to: PathBuf) -> bool {
+        let Some(mut doc) = self.documents.remove(from) else {
+            return false;
+        };
+        doc.path = to.clone();
+        self.documents.insert(to.clone(), doc);
+        self.emit(DocumentEvent::Moved { from: from.to_path_buf(), to });
+        true
+    }
+
+    /// Tell subscribers a document was written to disk
+    pub fn saved(&mut self, path: &Path) {
+        if self.contains(path) {
+            self.emit(DocumentEvent::Saved(path.to_path_buf()));
+        }
+    }
+}
+
+/// A document being changed, see [`DocumentStore::edit`]
+pub struct DocumentEdit<'a> {
+    doc: &'a mut Document,
+    subscribers: &'a mut Vec<Sender<DocumentEvent>>,
+    before: (u64, u64),
+}
+
+impl Deref for DocumentEdit<'_> {
+    type Target = Document;
+
+    fn deref(&self) -> &Document {
+        self.doc
+    }
+}
+
+impl DerefMut for DocumentEdit<'_> {
+    fn deref_mut(&mut self) -> &mut Document {
+        self.doc
+    }
+}
+
+impl Drop for DocumentEdit<'_> {
+    fn drop(&mut self) {
+        let (frontmatter, body) = fingerprint(self.doc);
+        let mut events = Vec::new();
+        if (frontmatter, body) != self.before {
+            events.push(DocumentEvent::Content(self.doc.path.clone()));
+        }
+        if frontmatter != self.before.0 {
+            self.doc.refresh_metadata();
+            events.push(DocumentEvent::Frontmatter(self.doc.path.clone()));
+        }
+        for event in events {
+            self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_events() {
+        let mut store = DocumentStore::default();
+        let events = store.subscribe();
+        let path = PathBuf::from("/vault/Note.md");
+        store.insert(Document::new(path.clone()));
+
+        store.edit(&path).unwrap().content.push_str("Body");
+        // Reading through the guard changes nothing
+        assert_eq!(store.edit(&path).unwrap().content, "Body");
+        store.edit(&path).unwrap().content.insert_str(0, "---\naliases: [N]\n---\n");
+        assert_eq!(store.get(&path).unwrap().metadata.aliases, vec!["N".to_string()]);
+
+        let moved = PathBuf::from("/vault/Renamed.md");
+        assert!(store.rename(&path, moved.clone()));
+        store.remove(&moved);
+
+        let received: Vec<DocumentEvent> = events.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                DocumentEvent::Opened(path.clone()),
+                DocumentEvent::Content(path.clone()),
+                DocumentEvent::Content(path.clone()),
+                DocumentEvent::Frontmatter(path.clone()),
+                DocumentEvent::Moved { from: path, to: moved.clone() },
+                DocumentEvent::Closed(moved),
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! HTML export
+//!
+//! Turns the notes of a vault into a static site, one `.html` page per note
+//! with the vault's folder layout. Headings get slug anchors and `^block`
+//! markers become anchors too, so wiki links such as `[[Note#Heading]]` and
+//! `[[Note#^block]]` keep pointing at the right place in the exported pages.
+//!
+//! Only notes flagged with `publish: true` in their frontmatter are exported,
+//! and those with a `publish_date` only once that day has come. Links to
+//! notes left out of the site become plain text.
+//!
+//! Abbreviations defined in the note or the vault's glossary note become
+//! `<abbr>` elements (see [`Glossary::apply`]), and figure and table captions
+//! numbered `<div class="caption">` elements (see [`crate::captions`]).
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+use super::captions::replace_captions;
+use super::document::Document;
+use super::glossary::Glossary;
+use super::navigation::split_anchor;
+use super::note_index::NoteIndex;
+use super::vault_settings::VaultSettings;
+
+/// Anchor of a heading: lowercase words joined by dashes, keeping letters and
+/// digits of any script
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Anchor for a wiki link fragment: `^id` for block references, otherwise
+/// the slug of the (last) heading in `Heading#Subheading`
+pub fn fragment_anchor(fragment: &str) -> String {
+    let fragment = fragment.rsplit('#').next().unwrap_or(fragment).trim();
+    match fragment.strip_prefix('^') {
+        Some(block) => format!("^{}", block),
+        None => slugify(fragment),
+    }
+}
+
+/// Whether a note is part of the exported site
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublishState {
+    /// Not flagged for publishing (or encrypted, or with an unreadable
+    /// `publish_date`)
+    Draft,
+    /// Flagged, but its publish date is still ahead
+    Scheduled(NaiveDate),
+    Published,
+}
+
+/// Publish state of a note on a given day
+pub fn publish_state(doc: &Document, today: NaiveDate) -> PublishState {
+    let flagged = doc
+        .frontmatter_field("publish")
+        .is_some_and(|value| matches!(value.trim().to_lowercase().as_str(), "true" | "yes"));
+    if !flagged || doc.locked {
+        return PublishState::Draft;
+    }
+    let Some(date) = doc.frontmatter_field("publish_date") else {
+        return PublishState::Published;
+    };
+    // Dates may carry a time, which is ignored
+    match date.trim().get(..10).and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()) {
+        Some(date) if date > today => PublishState::Scheduled(date),
+        Some(_) => PublishState::Published,
+        None => PublishState::Draft,
+    }
+}
+
+/// Publish state of every indexed note, sorted by path
+pub fn publish_plan(notes: &NoteIndex, today: NaiveDate) -> Vec<(PathBuf, PublishState)> {
+    let mut plan: Vec<(PathBuf, PublishState)> = notes
+        .notes()
+        .iter()
+        .filter_map(|note| {
+            let doc = Document::open(&note.path).ok()?;
+            Some((note.path.clone(), publish_state(&doc, today)))
+        })
+        .collect();
+    plan.sort_by(|a, b| a.0.cmp(&b.0));
+    plan
+}
+
+/// Path of a note's page relative to the export folder
+pub fn page_path(vault: &Path, note: &Path) -> PathBuf {
+    note.strip_prefix(vault).unwrap_or(note).with_extension("html")
+}
+
+/// Relative URL from the page at `from` to the page at `to` (both relative
+/// to the export folder)
+fn relative_url(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = from.parent().map(|p| p.components().collect()).unwrap_or_default();
+    let to_parts: Vec<Component> = to.components().collect();
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dir.len() - common];
+    parts.extend(to_parts[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()));
+    parts.join("/")
+}
+
+/// Replace wiki links with markdown links to the exported pages and `^id`
+/// block markers with anchors, leaving fenced code and embeds untouched
+fn rewrite_wiki_syntax(
+    content: &str,
+    note: &Path,
+    vault: &Path,
+    notes: &NoteIndex,
+    published: &HashSet<PathBuf>,
+) -> String {
+    let link_re = regex_lite::Regex::new(r"(!?)\[\[([^\]|]+)(?:\|([^\]]+))?\]\]").unwrap();
+    let block_re = regex_lite::Regex::new(r"\s\^([A-Za-z0-9-]+)\s*$").unwrap();
+    let from = page_path(vault, note);
+
+    let mut output = String::with_capacity(content.len());
+    let mut in_fence = false;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            output.push_str(line);
+            continue;
+        }
+
+        let line = link_re.replace_all(line, |caps: &regex_lite::Captures| {
+            if !caps[1].is_empty() {
+                return caps[0].to_string();
+            }
+            let target = caps[2].trim();
+            let (note_name, fragment) = split_anchor(target);
+            let display = caps.get(3).map_or(target, |m| m.as_str().trim());
+            let anchor = fragment.map(|f| format!("#{}", fragment_anchor(f))).unwrap_or_default();
+
+            if note_name.is_empty() {
+                return format!("[{}](<{}>)", display, anchor);
+            }
+            match notes.resolve(note_name).filter(|path| published.contains(path)) {
+                Some(path) => {
+                    let url = relative_url(&from, &page_path(vault, &path));
+                    format!("[{}](<{}{}>)", display, url, anchor)
+                }
+                // Nothing to link to in the export
+                None => display.to_string(),
+            }
+        });
+        let line = block_re.replace(&line, |caps: &regex_lite::Captures| {
+            format!(" <a id=\"^{}\"></a>", &caps[1])
+        });
+        output.push_str(&line);
+    }
+    output
+}
+
+/// Render a note's markdown (without frontmatter) to HTML, linking only to
+/// the `published` notes
+pub fn note_to_html(doc: &Document, vault: &Path, notes: &NoteIndex, published: &HashSet<PathBuf>) -> String {
+    markdown_to_html(doc.content_without_frontmatter(), &doc.path, vault, notes, published)
+}
+
+/// Render the Markdown of the note at `note` to HTML, like [`note_to_html`]
+pub fn markdown_to_html(
+    content: &str,
+    note: &Path,
+    vault: &Path,
+    notes: &NoteIndex,
+    published: &HashSet<PathBuf>,
+) -> String {
+    let settings = VaultSettings::load(vault).unwrap_or_default();
+    let mut glossary = Glossary::load(&settings.glossary_path(vault));
+    glossary.extend(&Glossary::parse(content));
+    let content = glossary.apply(content, settings.expand_abbreviations);
+    // Before `^caption` could be taken for a block marker; the caption stays
+    // Markdown between the tags
+    let content = replace_captions(&content, |caption| {
+        format!("<div class=\"caption\">\n\n**{}:** {}\n\n</div>\n", caption.label(), caption.text)
+    });
+    let markdown = rewrite_wiki_syntax(&content, note, vault, notes, published);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES;
+    let mut events: Vec<Event> = Parser::new_ext(&markdown, options).collect();
+
+    // Give every heading a unique anchor; repeated headings get `-1`, `-2`...
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut idx = 0;
+    while idx < events.len() {
+        if let Event::Start(Tag::Heading { id: None, .. }) = &events[idx] {
+            let mut text = String::new();
+            for event in &events[idx + 1..] {
+                match event {
+                    Event::End(TagEnd::Heading(_)) => break,
+                    Event::Text(t) | Event::Code(t) => text.push_str(t),
+                    _ => {}
+                }
+            }
+            let slug = slugify(&text);
+            let count = used.entry(slug.clone()).or_insert(0);
+            let anchor = if *count == 0 { slug } else { format!("{}-{}", slug, count) };
+            *count += 1;
+            if let Event::Start(Tag::Heading { id, .. }) = &mut events[idx] {
+                *id = Some(CowStr::from(anchor));
+            }
+        }
+        idx += 1;
+    }
+
+    let mut body = String::new();
+    html::push_html(&mut body, events.into_iter());
+    body
+}
+
+/// A complete HTML page around a rendered body, with extra `head` markup
+pub fn html_page(title: &str, head: &str, body: &str) -> String {
+    let mut escaped = String::new();
+    html::push_html(&mut escaped, std::iter::once(Event::Text(title.into())));
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n{}</head>\n<body>\n{}</body>\n</html>\n",
+        escaped, head, body
+    )
+}
+
+/// A complete HTML page for a note
+fn note_page(doc: &Document, vault: &Path, notes: &NoteIndex, published: &HashSet<PathBuf>) -> String {
+    html_page(&doc.title(), "", &note_to_html(doc, vault, notes, published))
+}
+
+/// Export the published notes of a vault into `out_dir`, returning the
+/// number of pages written
+pub fn export_vault(vault: &Path, out_dir: &Path, notes: &NoteIndex) -> Result<usize> {
+    let published: HashSet<PathBuf> = publish_plan(notes, Local::now().date_naive())
+        .into_iter()
+        .filter(|(_, state)| *state == PublishState::Published)
+        .map(|(path, _)| path)
+        .collect();
+    let mut written = 0;
+    for path in &published {
+        let doc = Document::open(path)?;
+        let page = out_dir.join(page_path(vault, path));
+        if let Some(parent) = page.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create folder: {}", parent.display()))?;
+        }
+        std::fs::write(&page, note_page(&doc, vault, notes, &published))
+            .with_context(|| format!("Failed to write page: {}", page.display()))?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Getting Started!"), "getting-started");
+        assert_eq!(slugify("  API -- v2 "), "api-v2");
+        assert_eq!(slugify("Über Café"), "über-café");
+        assert_eq!(fragment_anchor("Setup#Linux Install"), "linux-install");
+        assert_eq!(fragment_anchor("^abc-1"), "^abc-1");
+    }
+
+    #[test]
+    fn test_note_to_html_anchors() {
+        let vault = std::env::temp_dir().join(format!("robsidian_export_{}", std::process::id()));
+        std::fs::create_dir_all(vault.join("sub")).unwrap();
+        std::fs::write(vault.join("sub/Other Note.md"), "# Intro\n").unwrap();
+        let notes = NoteIndex::build(&vault);
+        let published = HashSet::from([vault.join("sub/Other Note.md")]);
+
+        let mut doc = Document::new(vault.join("Home.md"));
+        doc.content = "# Intro\n\n## Intro\n\nSee [[Other Note#Intro|other]] and [[#Intro]].\n\nKey point ^point\n\n\
+                       ![](plot.png)\n^caption Plot\n"
+            .to_string();
+        let html = note_to_html(&doc, &vault, &notes, &published);
+
+        assert!(html.contains("<h1 id=\"intro\">Intro</h1>"));
+        assert!(html.contains("<h2 id=\"intro-1\">Intro</h2>"));
+        assert!(html.contains("<a href=\"sub/Other%20Note.html#intro\">other</a>"));
+        assert!(html.contains("<a href=\"#intro\">#Intro</a>"));
+        assert!(html.contains("<a id=\"^point\"></a>"));
+        assert!(html.contains("<div class=\"caption\">\n<p><strong>Figure 1:</strong> Plot</p>\n</div>"));
+        assert_eq!(relative_url(Path::new("a/b.html"), Path::new("c/d.html")), "../c/d.html");
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn test_publish_state() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let state = |frontmatter: &str| {
+            let mut doc = Document::new(PathBuf::from("Note.md"));
+            doc.content = format!("---\n{}\n---\n\nBody\n", frontmatter);
+            publish_state(&doc, today)
+        };
+
+        assert_eq!(state("title: Draft"), PublishState::Draft);
+        assert_eq!(state("publish: false"), PublishState::Draft);
+        assert_eq!(state("publish: true"), PublishState::Published);
+        assert_eq!(state("publish: true\npublish_date: 2024-06-01"), PublishState::Published);
+        assert_eq!(
+            state("publish: true\npublish_date: 2024-07-15T09:00"),
+            PublishState::Scheduled(NaiveDate::from_ymd_opt(2024, 7, 15).unwrap())
+        );
+        assert_eq!(state("publish: true\npublish_date: soon"), PublishState::Draft);
+    }
+}
@@ -0,0 +1,170 @@
+//! Editing notes in another program
+//!
+//! The configured command runs with the note's path. The app keeps the note
+//! read-only while the program runs, so the two never hold diverging edits,
+//! and reads the file back once it exits. Terminal editors need a terminal
+//! around them (`kitty -e vim`), and GUI editors a flag that keeps them
+//! running until the file is closed (`code --wait`).
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use anyhow::{bail, Context, Result};
+
+/// Stands for the note's path in an editor command
+pub const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Words of a command line, with double quotes grouping words that hold spaces
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Program and arguments that open `path` with `command`. Each
+/// [`FILE_PLACEHOLDER`] is replaced by the path; without one, the path is
+/// passed last.
+pub fn command_line(command: &str, path: &Path) -> Result<(String, Vec<OsString>)> {
+    let mut words = split_command(command).into_iter();
+    let Some(program) = words.next() else {
+        bail!("No editor command is set");
+    };
+    let mut args = Vec::new();
+    let mut has_file = false;
+    for word in words {
+        if word.contains(FILE_PLACEHOLDER) {
+            let mut arg = OsString::new();
+            let mut parts = word.split(FILE_PLACEHOLDER);
+            arg.push(parts.next().unwrap_or_default());
+            for part in parts {
+                arg.push(path);
+                arg.push(part);
+            }
+            args.push(arg);
+            has_file = true;
+        } else {
+            args.push(OsString::from(word));
+        }
+    }
+    if !has_file {
+        args.push(path.as_os_str().to_owned());
+    }
+    Ok((program, args))
+}
+
+/// A program that was editing a note has exited
+#[derive(Debug)]
+pub struct EditorExited {
+    pub path: PathBuf,
+    /// Name of the program
+    pub editor: String,
+    pub status: Result<ExitStatus>,
+}
+
+/// Notes open in other programs
+pub struct ExternalEditors {
+    exited_tx: Sender<(PathBuf, Result<ExitStatus>)>,
+    exited: Receiver<(PathBuf, Result<ExitStatus>)>,
+    /// Name of the program editing each note
+    running: HashMap<PathBuf, String>,
+}
+
+impl Default for ExternalEditors {
+    fn default() -> Self {
+        let (exited_tx, exited) = mpsc::channel();
+        Self { exited_tx, exited, running: HashMap::new() }
+    }
+}
+
+impl ExternalEditors {
+    /// Start `command` on the note at `path`, waiting for it on a background
+    /// thread
+    pub fn open(&mut self, command: &str, path: &Path) -> Result<()> {
+        if let Some(editor) = self.running.get(path) {
+            bail!("{} is already open in {}", path.display(), editor);
+        }
+        let (program, args) = command_line(command, path)?;
+        let mut child = Command::new(&program)
+            .args(&args)
+            .spawn()
+            .with_context(|| format!("Could not start {}", program))?;
+        let editor = Path::new(&program)
+            .file_stem()
+            .map_or(program.clone(), |name| name.to_string_lossy().to_string());
+        self.running.insert(path.to_path_buf(), editor);
+
+        let exited = self.exited_tx.clone();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            let status = child.wait().context("Could not wait for the editor");
+            let _ = exited.send((path, status));
+        });
+        Ok(())
+    }
+
+    /// Name of the program editing the note at `path`, if one is
+    pub fn editor_of(&self, path: &Path) -> Option<&str> {
+        self.running.get(path).map(String::as_str)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// Collect the editors that exited since the last call (non-blocking)
+    pub fn poll(&mut self) -> Vec<EditorExited> {
+        self.exited
+            .try_iter()
+            .map(|(path, status)| {
+                let editor = self.running.remove(&path).unwrap_or_default();
+                EditorExited { path, editor, status }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let path = Path::new("/notes/My Note.md");
+        let (program, args) = command_line("code --wait", path).unwrap();
+        assert_eq!(program, "code");
+        assert_eq!(args, ["--wait", "/notes/My Note.md"]);
+
+        let (program, args) = command_line(r#""/opt/My Terminal/term" -e vim {file} +1"#, path).unwrap();
+        assert_eq!(program, "/opt/My Terminal/term");
+        assert_eq!(args, ["-e", "vim", "/notes/My Note.md", "+1"]);
+
+        assert!(command_line("  ", path).is_err());
+    }
+}
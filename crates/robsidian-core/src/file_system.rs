@@ -0,0 +1,489 @@
+//! File system operations and file tree management
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use walkdir::WalkDir;
+
+use super::inbox::DEFAULT_INBOX_FOLDER;
+use super::templates::DEFAULT_TEMPLATES_FOLDER;
+use super::vault_settings::VaultSettings;
+
+/// Represents a file or directory in the tree
+#[derive(Debug, Clone)]
+pub struct FileNode {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub children: Vec<FileNode>,
+    pub expanded: bool,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileNode {
+    /// Create a new file node
+    pub fn new(path: PathBuf, is_dir: bool) -> Self {
+        let name = path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+        let modified = std::fs::metadata(&path)
+            .ok()
+            .and_then(|m| m.modified().ok());
+
+        Self {
+            name,
+            path,
+            is_dir,
+            children: Vec::new(),
+            expanded: false,
+            modified,
+        }
+    }
+
+    /// Check if this is a markdown file
+    pub fn is_markdown(&self) -> bool {
+        !self.is_dir
+            && self
+                .path
+                .extension()
+                .map(|ext| ext == "md" || ext == "markdown")
+                .unwrap_or(false)
+    }
+
+    /// Sort children: directories first, then files, alphabetically
+    pub fn sort_children(&mut self) {
+        self.children.sort_by(|a, b| {
+            match (a.is_dir, b.is_dir) {
+                (true, false) => std::cmp::Ordering::Less,
+                (false, true) => std::cmp::Ordering::Greater,
+                _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            }
+        });
+        for child in &mut self.children {
+            child.sort_children();
+        }
+    }
+}
+
+/// Generation of the file tree built last
+static TREE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// File tree representing a vault structure
+#[derive(Debug, Clone, Default)]
+pub struct FileTree {
+    pub root: Option<FileNode>,
+    pub root_path: Option<PathBuf>,
+    /// Different for every build of a tree, so what was read from the
+    /// folders can be dropped once it is built again
+    generation: u64,
+}
+
+impl FileTree {
+    /// Create a file tree from a directory path
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut root = FileNode::new(path.to_path_buf(), true);
+        root.expanded = true;
+
+        Self::build_tree(&mut root, path, 0, 10)?;
+        root.sort_children();
+
+        Ok(Self {
+            root: Some(root),
+            root_path: Some(path.to_path_buf()),
+            generation: TREE_GENERATION.fetch_add(1, Ordering::Relaxed) + 1,
+        })
+    }
+
+    /// Generation of this build of the tree; it changes with every refresh
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Recursively build the file tree
+    fn build_tree(node: &mut FileNode, path: &Path, depth: usize, max_depth: usize) -> Result<()> {
+        if depth >= max_depth {
+            return Ok(());
+        }
+
+        let entries = std::fs::read_dir(path)?;
+
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            let file_name = entry_path
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            // Skip hidden files and directories
+            if file_name.starts_with('.') {
+                continue;
+            }
+
+            // Skip common non-content directories
+            if file_name == "node_modules" || file_name == "target" || file_name == ".git" {
+                continue;
+            }
+
+            let is_dir = entry_path.is_dir();
+            let mut child = FileNode::new(entry_path.clone(), is_dir);
+
+            if is_dir {
+                Self::build_tree(&mut child, &entry_path, depth + 1, max_depth)?;
+            }
+
+            node.children.push(child);
+        }
+
+        Ok(())
+    }
+
+    /// Refresh the file tree
+    pub fn refresh(&mut self) -> Result<()> {
+        if let Some(ref root_path) = self.root_path.clone() {
+            *self = Self::from_path(root_path)?;
+        }
+        Ok(())
+    }
+
+    /// Find a node by path
+    pub fn find_node(&self, path: &Path) -> Option<&FileNode> {
+        self.root.as_ref().and_then(|root| Self::find_in_node(root, path))
+    }
+
+    fn find_in_node<'a>(node: &'a FileNode, path: &Path) -> Option<&'a FileNode> {
+        if node.path == path {
+            return Some(node);
+        }
+
+        for child in &node.children {
+            if let Some(found) = Self::find_in_node(child, path) {
+                return Some(found);
+            }
+        }
+
+        None
+    }
+
+    /// Toggle expansion state of a directory
+    pub fn toggle_expanded(&mut self, path: &Path) {
+        if let Some(ref mut root) = self.root {
+            Self::toggle_in_node(root, path);
+        }
+    }
+
+    fn toggle_in_node(node: &mut FileNode, path: &Path) {
+        if node.path == path {
+            node.expanded = !node.expanded;
+            return;
+        }
+
+        for child in &mut node.children {
+            Self::toggle_in_node(child, path);
+        }
+    }
+}
+
+/// Create a new file in the vault
+pub fn create_file(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, "")?;
+    Ok(())
+}
+
+/// Create a new directory in the vault
+pub fn create_directory(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path)?;
+    Ok(())
+}
+
+/// Delete a file or directory
+pub fn delete(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Rename a file or directory
+pub fn rename(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to)?;
+    Ok(())
+}
+
+/// A free path for a new file in a folder: `stem.ext`, or `stem 1.ext`,
+/// `stem 2.ext`... if that name is taken
+pub fn unique_path(folder: &Path, stem: &str, ext: &str) -> PathBuf {
+    let mut path = folder.join(format!("{}.{}", stem, ext));
+    let mut n = 1;
+    while path.exists() {
+        path = folder.join(format!("{} {}.{}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// Whether `path` is in `vault` once symlinks are followed. What doesn't
+/// exist of it yet is judged by the folder it would be made in; a link
+/// pointing nowhere can't be judged and isn't in the vault.
+pub fn inside_vault(vault: &Path, path: &Path) -> bool {
+    let Ok(vault) = vault.canonicalize() else {
+        return false;
+    };
+    path.ancestors()
+        .find(|part| part.symlink_metadata().is_ok())
+        .and_then(|part| part.canonicalize().ok())
+        .is_some_and(|existing| existing.starts_with(&vault))
+}
+
+/// `rel` in `vault`, if it only names folders and files below the vault and
+/// stays in it once symlinks are followed
+pub fn vault_file(vault: &Path, rel: &Path) -> Option<PathBuf> {
+    if !rel.components().all(|part| matches!(part, Component::Normal(_))) {
+        return None;
+    }
+    let file = vault.join(rel);
+    inside_vault(vault, &file).then_some(file)
+}
+
+/// Copy a file into a folder, numbering its name if the folder has a file
+/// of that name already. Returns the new path.
+pub fn copy_into(file: &Path, folder: &Path) -> Result<PathBuf> {
+    let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = file.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+    let target = unique_path(folder, &stem, &ext);
+    std::fs::copy(file, &target)?;
+    Ok(target)
+}
+
+/// A note listed by a folder note's `{{children}}` placeholder
+#[derive(Debug, Clone)]
+pub struct ChildNote {
+    pub title: String,
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+/// List the notes that belong to the folder of `note`, excluding the note itself.
+///
+/// Markdown files directly inside the folder are listed, as well as the folder
+/// notes (`Sub/Sub.md`) of immediate subfolders. Results are sorted by title.
+pub fn list_child_notes(note: &Path) -> Vec<ChildNote> {
+    let Some(folder) = note.parent() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return Vec::new();
+    };
+
+    let mut children = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || path == note {
+            continue;
+        }
+
+        let candidate = if path.is_dir() {
+            path.join(format!("{}.md", name))
+        } else {
+            path
+        };
+
+        let node = FileNode::new(candidate.clone(), false);
+        if !candidate.is_file() || !node.is_markdown() {
+            continue;
+        }
+
+        children.push(ChildNote {
+            title: candidate
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            path: candidate,
+            modified: node.modified,
+        });
+    }
+
+    children.sort_by_key(|child| child.title.to_lowercase());
+    children
+}
+
+/// Resolve a wiki link note name to its file in the vault, if it exists
+pub fn resolve_note_link(vault: &Path, note: &str) -> Option<PathBuf> {
+    let path = vault.join(format!("{}.md", note.trim()));
+    path.is_file().then_some(path)
+}
+
+/// Format a file modification time for display
+pub fn format_modified(time: Option<SystemTime>) -> String {
+    time.map(|t| {
+        chrono::DateTime::<chrono::Local>::from(t)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    })
+    .unwrap_or_default()
+}
+
+/// Get all markdown files in a directory recursively
+pub fn get_markdown_files(path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .map(|ext| ext == "md" || ext == "markdown")
+                .unwrap_or(false)
+        })
+        .map(|e| e.path().to_path_buf())
+        .collect()
+}
+
+/// Starter structure a new vault can be created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VaultPreset {
+    /// A welcome note, templates and daily notes
+    #[default]
+    Plain,
+    /// Plain, plus folders for fleeting, literature and permanent notes
+    Zettelkasten,
+    /// Plain, plus Projects, Areas, Resources and Archives folders
+    Para,
+}
+
+/// Template every preset adds for daily notes
+const DAILY_TEMPLATE: &str = "# {{date}}\n\n## Tasks\n\n- [ ] \n\n## Notes\n\n";
+
+impl VaultPreset {
+    pub const ALL: [Self; 3] = [Self::Plain, Self::Zettelkasten, Self::Para];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Plain => "Plain",
+            Self::Zettelkasten => "Zettelkasten",
+            Self::Para => "PARA",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Plain => "A welcome note, a templates folder and a daily notes folder.",
+            Self::Zettelkasten => "Inbox, literature and permanent notes, with templates for each.",
+            Self::Para => "Projects, Areas, Resources and Archives, with a project template.",
+        }
+    }
+
+    /// Folders of the preset, relative to the vault
+    fn folders(self) -> &'static [&'static str] {
+        match self {
+            Self::Plain => &["Daily"],
+            Self::Zettelkasten => &["Daily", "Inbox", "Literature", "Permanent"],
+            Self::Para => &["Daily", "Inbox", "1 Projects", "2 Areas", "3 Resources", "4 Archives"],
+        }
+    }
+
+    /// Templates of the preset, by name
+    fn templates(self) -> Vec<(&'static str, &'static str)> {
+        let mut templates = vec![("Daily Note", DAILY_TEMPLATE)];
+        match self {
+            Self::Plain => {}
+            Self::Zettelkasten => {
+                templates.push(("Permanent Note", "---\ntags: []\n---\n\n# {{title}}\n\n\n\n## Links\n\n- \n"));
+                templates.push(("Literature Note", "---\nsource: \n---\n\n# {{title}}\n\n## Summary\n\n## Quotes\n\n"));
+            }
+            Self::Para => {
+                let project = "---\nstatus: active\ndue: \n---\n\n# {{title}}\n\n## Goal\n\n## Tasks\n\n- [ ] \n";
+                templates.push(("Project", project));
+            }
+        }
+        templates
+    }
+
+    fn welcome(self) -> String {
+        let layout = match self {
+            Self::Plain => "Write notes anywhere in the vault and link them with [[wiki links]].".to_string(),
+            Self::Zettelkasten => "- **Inbox**: quick thoughts to work through later\n\
+                                   - **Literature**: notes on what you read, in your own words\n\
+                                   - **Permanent**: one idea per note, linked to the others"
+                .to_string(),
+            Self::Para => "- **1 Projects**: work with a goal and a deadline\n\
+                           - **2 Areas**: responsibilities kept up over time\n\
+                           - **3 Resources**: topics of interest and reference material\n\
+                           - **4 Archives**: whatever is no longer active"
+                .to_string(),
+        };
+        format!(
+            "# Welcome\n\nThis vault was set up with the {} layout.\n\n{}\n\n\
+             Daily notes go in **Daily**, and the note templates are in **{}**.\n",
+            self.name(),
+            layout,
+            DEFAULT_TEMPLATES_FOLDER
+        )
+    }
+}
+
+/// Create a vault at `path`, which must not exist or be empty, laid out as
+/// `preset`, or left empty without one. Returns the welcome note, if any.
+pub fn create_vault(path: &Path, preset: Option<VaultPreset>) -> Result<Option<PathBuf>> {
+    if path.read_dir().is_ok_and(|mut entries| entries.next().is_some()) {
+        bail!("{} already exists and isn't empty", path.display());
+    }
+    std::fs::create_dir_all(path).with_context(|| format!("Failed to create vault: {}", path.display()))?;
+    let Some(preset) = preset else {
+        return Ok(None);
+    };
+
+    for folder in preset.folders() {
+        std::fs::create_dir_all(path.join(folder))?;
+    }
+    let templates = path.join(DEFAULT_TEMPLATES_FOLDER);
+    std::fs::create_dir_all(&templates)?;
+    for (name, content) in preset.templates() {
+        std::fs::write(templates.join(format!("{}.md", name)), content)?;
+    }
+    let welcome = path.join("Welcome.md");
+    std::fs::write(&welcome, preset.welcome())?;
+
+    let mut settings = VaultSettings {
+        templates_folder: Some(PathBuf::from(DEFAULT_TEMPLATES_FOLDER)),
+        ..VaultSettings::default()
+    };
+    if preset.folders().contains(&DEFAULT_INBOX_FOLDER) {
+        settings.inbox_folder = Some(PathBuf::from(DEFAULT_INBOX_FOLDER));
+    }
+    if preset == VaultPreset::Para {
+        settings.archive_folder = Some(PathBuf::from("4 Archives"));
+    }
+    settings.save(path)?;
+    Ok(Some(welcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_vault() {
+        let dir = std::env::temp_dir().join(format!("robsidian_new_vault_{}", std::process::id()));
+        let welcome = create_vault(&dir.join("para"), Some(VaultPreset::Para)).unwrap();
+        assert_eq!(welcome, Some(dir.join("para/Welcome.md")));
+        assert!(dir.join("para/1 Projects").is_dir());
+        assert!(dir.join("para/Templates/Daily Note.md").is_file());
+        let settings = VaultSettings::load(&dir.join("para")).unwrap();
+        assert_eq!(settings.archive_folder, Some(PathBuf::from("4 Archives")));
+
+        // An existing vault isn't overwritten
+        assert!(create_vault(&dir.join("para"), None).is_err());
+        assert_eq!(create_vault(&dir.join("empty"), None).unwrap(), None);
+        assert!(dir.join("empty").is_dir());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,370 @@
+//! Flashcards and spaced repetition
+//!
+//! Notes hold cards in two forms. A question and its answer on consecutive
+//! lines:
+//!
+//! ```markdown
+//! Q:: What does SM-2 stand for?
+//! A:: SuperMemo 2
+//! ```
+//!
+//! and cloze deletions as in Anki, where each number is a card hiding its
+//! parts of the line, with an optional hint:
+//!
+//! ```markdown
+//! {{c1::Canberra}} is the capital of {{c2::Australia::country}}.
+//! ```
+//!
+//! A note's cards form the deck named by its `deck` frontmatter field, or by
+//! the note's title. Reviews are scheduled with SM-2 and kept in
+//! `.robsidian/reviews.json`, keyed by note and card text, so a card whose
+//! question is edited starts over as a new one.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{Days, NaiveDate};
+use regex_lite::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::document::Document;
+use super::note_index::NoteIndex;
+use super::vault_settings::SETTINGS_DIR;
+
+/// New cards of a deck introduced per review session
+pub const NEW_CARDS_PER_SESSION: usize = 20;
+/// Interval, in days, from which a card counts as learned
+pub const MATURE_INTERVAL: u32 = 21;
+
+/// Ease of a card never reviewed
+const INITIAL_EASE: f32 = 2.5;
+const MIN_EASE: f32 = 1.3;
+
+/// A card of a note
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    /// Question, or the line with the card's clozes hidden as `[…]`
+    pub front: String,
+    /// Answer, or the line with its clozes shown in bold
+    pub back: String,
+    /// Byte offset of the card in the note
+    pub offset: usize,
+    /// Identifies the card within its note
+    pub key: String,
+}
+
+/// Cards in a note's content, in order. Code blocks are skipped.
+pub fn parse_cards(content: &str) -> Vec<Card> {
+    let cloze = Regex::new(r"\{\{c(\d+)::(.*?)(?:::(.*?))?\}\}").unwrap();
+    let mut cards = Vec::new();
+    let mut question: Option<(&str, usize)> = None;
+    let mut in_code = false;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+
+        if let Some(front) = trimmed.strip_prefix("Q::") {
+            question = Some((front.trim(), start));
+            continue;
+        }
+        if let (Some((front, start)), Some(back)) = (question.take(), trimmed.strip_prefix("A::")) {
+            cards.push(Card {
+                front: front.to_string(),
+                back: back.trim().to_string(),
+                offset: start,
+                key: format!("qa:{:016x}", fnv_hash(front)),
+            });
+            continue;
+        }
+
+        let mut numbers: Vec<&str> =
+            cloze.captures_iter(trimmed).filter_map(|c| c.get(1)).map(|n| n.as_str()).collect();
+        numbers.sort_by_key(|number| number.parse::<u32>().unwrap_or(0));
+        numbers.dedup();
+        for number in numbers {
+            let side = |hide: bool| {
+                cloze.replace_all(trimmed, |c: &regex_lite::Captures| match (&c[1] == number, hide) {
+                    (true, true) => format!("[{}]", c.get(3).map_or("…", |hint| hint.as_str())),
+                    (true, false) => format!("**{}**", &c[2]),
+                    (false, _) => c[2].to_string(),
+                })
+            };
+            cards.push(Card {
+                front: side(true).into_owned(),
+                back: side(false).into_owned(),
+                offset: start,
+                key: format!("c{}:{:016x}", number, fnv_hash(trimmed)),
+            });
+        }
+    }
+    cards
+}
+
+/// FNV-1a, so keys stay the same across builds
+fn fnv_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// How well a card was remembered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub const ALL: [Grade; 4] = [Self::Again, Self::Hard, Self::Good, Self::Easy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Again => "Again",
+            Self::Hard => "Hard",
+            Self::Good => "Good",
+            Self::Easy => "Easy",
+        }
+    }
+
+    /// SM-2 quality of the response, from 0 to 5
+    fn quality(self) -> f32 {
+        match self {
+            Self::Again => 1.0,
+            Self::Hard => 3.0,
+            Self::Good => 4.0,
+            Self::Easy => 5.0,
+        }
+    }
+}
+
+/// Schedule of a card that was reviewed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewState {
+    pub due: NaiveDate,
+    /// Days until the next review
+    pub interval: u32,
+    pub ease: f32,
+    /// Reviews in a row remembered
+    pub repetitions: u32,
+    /// Times forgotten after being remembered
+    pub lapses: u32,
+    pub last_review: NaiveDate,
+}
+
+impl ReviewState {
+    /// State of a new card after its first review
+    pub fn first(grade: Grade, today: NaiveDate) -> Self {
+        let mut state =
+            Self { due: today, interval: 0, ease: INITIAL_EASE, repetitions: 0, lapses: 0, last_review: today };
+        state.review(grade, today);
+        state
+    }
+
+    /// Schedule the next review, SM-2 style. A card forgotten is due again
+    /// the same day.
+    pub fn review(&mut self, grade: Grade, today: NaiveDate) {
+        let q = grade.quality();
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(MIN_EASE);
+        if grade == Grade::Again {
+            if self.repetitions > 0 {
+                self.lapses += 1;
+            }
+            self.repetitions = 0;
+            self.interval = 0;
+        } else {
+            self.repetitions += 1;
+            self.interval = match self.repetitions {
+                1 => 1,
+                2 => 6,
+                _ => (self.interval as f32 * self.ease).round() as u32,
+            };
+            if grade == Grade::Easy {
+                self.interval = (self.interval as f32 * 1.3).ceil() as u32;
+            }
+        }
+        self.due = today.checked_add_days(Days::new(self.interval as u64)).unwrap_or(today);
+        self.last_review = today;
+    }
+}
+
+/// A card with the note and deck it belongs to
+#[derive(Debug, Clone)]
+pub struct DeckCard {
+    pub note: PathBuf,
+    pub deck: String,
+    pub card: Card,
+}
+
+impl DeckCard {
+    /// Key of the card's review state: its note, relative to the vault, and
+    /// its key in the note
+    pub fn review_key(&self, vault: &Path) -> String {
+        let note = self.note.strip_prefix(vault).unwrap_or(&self.note);
+        format!("{}#{}", note.to_string_lossy().replace('\\', "/"), self.card.key)
+    }
+}
+
+/// Cards of the notes in a vault, skipping encrypted notes
+pub fn vault_cards(notes: &NoteIndex) -> Vec<DeckCard> {
+    let mut cards = Vec::new();
+    for note in notes.notes() {
+        let Ok(doc) = Document::open(&note.path) else {
+            continue;
+        };
+        if doc.locked {
+            continue;
+        }
+        let offset = doc.content.len() - doc.content_without_frontmatter().len();
+        let parsed = parse_cards(doc.content_without_frontmatter());
+        if parsed.is_empty() {
+            continue;
+        }
+        let deck = doc.frontmatter_field("deck").filter(|deck| !deck.trim().is_empty()).unwrap_or_else(|| doc.title());
+        cards.extend(parsed.into_iter().map(|card| DeckCard {
+            note: note.path.clone(),
+            deck: deck.trim().to_string(),
+            card: Card { offset: card.offset + offset, ..card },
+        }));
+    }
+    cards
+}
+
+/// Review states of a vault's cards, by [`DeckCard::review_key`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewLog {
+    pub cards: BTreeMap<String, ReviewState>,
+}
+
+impl ReviewLog {
+    fn path(vault: &Path) -> PathBuf {
+        vault.join(SETTINGS_DIR).join("reviews.json")
+    }
+
+    /// Load the review log of a vault (empty if it has none yet)
+    pub fn load(vault: &Path) -> Result<Self> {
+        let path = Self::path(vault);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read review log: {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid review log: {}", path.display()))
+    }
+
+    pub fn save(&self, vault: &Path) -> Result<()> {
+        let path = Self::path(vault);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to save review log: {}", path.display()))
+    }
+
+    /// Record a review of the card under `key`
+    pub fn review(&mut self, key: &str, grade: Grade, today: NaiveDate) {
+        match self.cards.get_mut(key) {
+            Some(state) => state.review(grade, today),
+            None => {
+                self.cards.insert(key.to_string(), ReviewState::first(grade, today));
+            }
+        }
+    }
+}
+
+/// Counts of a deck's cards
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeckStats {
+    pub name: String,
+    pub total: usize,
+    /// Never reviewed
+    pub new: usize,
+    /// Reviewed before and due by today
+    pub due: usize,
+    /// With an interval of [`MATURE_INTERVAL`] days or more
+    pub mature: usize,
+    pub reviewed_today: usize,
+}
+
+/// Statistics of each deck, by name
+pub fn deck_stats(cards: &[DeckCard], log: &ReviewLog, vault: &Path, today: NaiveDate) -> Vec<DeckStats> {
+    let mut decks: BTreeMap<&str, DeckStats> = BTreeMap::new();
+    for card in cards {
+        let stats = decks
+            .entry(card.deck.as_str())
+            .or_insert_with(|| DeckStats { name: card.deck.clone(), ..DeckStats::default() });
+        stats.total += 1;
+        match log.cards.get(&card.review_key(vault)) {
+            None => stats.new += 1,
+            Some(state) => {
+                stats.due += usize::from(state.due <= today);
+                stats.mature += usize::from(state.interval >= MATURE_INTERVAL);
+                stats.reviewed_today += usize::from(state.last_review == today);
+            }
+        }
+    }
+    decks.into_values().collect()
+}
+
+/// Indices in `cards` of a deck's cards to review today: those due, most
+/// overdue first, then up to [`NEW_CARDS_PER_SESSION`] new ones
+pub fn review_queue(cards: &[DeckCard], deck: &str, log: &ReviewLog, vault: &Path, today: NaiveDate) -> Vec<usize> {
+    let mut due = Vec::new();
+    let mut new = Vec::new();
+    for (index, card) in cards.iter().enumerate().filter(|(_, card)| card.deck == deck) {
+        match log.cards.get(&card.review_key(vault)) {
+            Some(state) if state.due <= today => due.push((state.due, index)),
+            Some(_) => {}
+            None => new.push(index),
+        }
+    }
+    due.sort();
+    due.into_iter().map(|(_, index)| index).chain(new.into_iter().take(NEW_CARDS_PER_SESSION)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cards() {
+        let content = "Q:: Capital of France?\nA:: Paris\n\n\
+                       {{c1::Canberra}} is in {{c2::Australia::country}}.\n\n\
+                       ```\nQ:: Not a card\nA:: Skipped\n```\n";
+        let cards = parse_cards(content);
+        let sides: Vec<(&str, &str)> = cards.iter().map(|card| (card.front.as_str(), card.back.as_str())).collect();
+        assert_eq!(
+            sides,
+            [
+                ("Capital of France?", "Paris"),
+                ("[…] is in Australia.", "**Canberra** is in Australia."),
+                ("Canberra is in [country].", "Canberra is in **Australia**."),
+            ]
+        );
+        assert_eq!(cards[1].offset, content.find("{{c1").unwrap());
+    }
+
+    #[test]
+    fn test_review_schedule() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let mut state = ReviewState::first(Grade::Good, today);
+        assert_eq!((state.interval, state.due), (1, NaiveDate::from_ymd_opt(2024, 3, 2).unwrap()));
+        state.review(Grade::Good, today);
+        assert_eq!(state.interval, 6);
+        state.review(Grade::Good, today);
+        assert_eq!(state.interval, 15);
+
+        state.review(Grade::Again, today);
+        assert_eq!((state.interval, state.due, state.lapses, state.repetitions), (0, today, 1, 0));
+        assert!(state.ease < INITIAL_EASE);
+    }
+}
@@ -0,0 +1,166 @@
+//! Abbreviations and their definitions
+//!
+//! Terms are defined on lines of their own, as in PHP Markdown Extra:
+//!
+//! ```markdown
+//! *[HTML]: Hyper Text Markup Language
+//! ```
+//!
+//! A note's definitions apply to that note; those in the vault's glossary
+//! note (see [`VaultSettings::glossary_path`]) apply to every note. Defined
+//! terms are matched as whole words, case-sensitively.
+//!
+//! [`VaultSettings::glossary_path`]: super::vault_settings::VaultSettings::glossary_path
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// Glossary note read when a vault doesn't name one, relative to the vault
+pub const DEFAULT_GLOSSARY_NOTE: &str = "Glossary.md";
+
+/// Term and definition of a `*[TERM]: definition` line
+pub fn definition_line(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim().strip_prefix("*[")?;
+    let (term, definition) = rest.split_once("]:")?;
+    let term = term.trim();
+    (!term.is_empty()).then_some((term, definition.trim()))
+}
+
+/// Defined terms, by term
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Glossary {
+    terms: BTreeMap<String, String>,
+}
+
+impl Glossary {
+    /// Definitions in a note's markdown
+    pub fn parse(markdown: &str) -> Self {
+        let terms = markdown
+            .lines()
+            .filter_map(definition_line)
+            .map(|(term, definition)| (term.to_string(), definition.to_string()))
+            .collect();
+        Self { terms }
+    }
+
+    /// Definitions in the note at `path`; none if it can't be read
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path).map(|text| Self::parse(&text)).unwrap_or_default()
+    }
+
+    /// Add the terms of `other`, whose definitions win
+    pub fn extend(&mut self, other: &Glossary) {
+        self.terms.extend(other.terms.iter().map(|(term, definition)| (term.clone(), definition.clone())));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn definition(&self, term: &str) -> Option<&str> {
+        self.terms.get(term).map(String::as_str)
+    }
+
+    /// Where defined terms occur in `text` as whole words, in order, with
+    /// their definitions. Longer terms win over terms they contain.
+    pub fn occurrences<'a>(&'a self, text: &str) -> Vec<(Range<usize>, &'a str)> {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut found: Vec<(Range<usize>, &str)> = Vec::new();
+        for (term, definition) in &self.terms {
+            for (start, _) in text.match_indices(term.as_str()) {
+                let end = start + term.len();
+                let bounded = !text[..start].ends_with(is_word) && !text[end..].starts_with(is_word);
+                if bounded {
+                    found.push((start..end, definition));
+                }
+            }
+        }
+        found.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+        let mut covered = 0;
+        found.retain(|(range, _)| {
+            let keep = range.start >= covered;
+            if keep {
+                covered = range.end;
+            }
+            keep
+        });
+        found
+    }
+
+    /// Markdown for export: definition lines are dropped and terms become
+    /// `<abbr>` elements showing their definition on hover. With `expand`,
+    /// the first occurrence of each term is spelled out as well, as in
+    /// "Hyper Text Markup Language (HTML)". Code, links and HTML are left as
+    /// they are.
+    pub fn apply(&self, markdown: &str, expand: bool) -> String {
+        let protected = regex_lite::Regex::new(r"`[^`]*`|\[\[[^\]]*\]\]|\]\([^)]*\)|<[^>]*>|https?://\S+").unwrap();
+        let mut expanded = std::collections::HashSet::new();
+        let mut output = String::with_capacity(markdown.len());
+        let mut in_fence = false;
+        for line in markdown.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            if definition_line(line).is_some() && !in_fence {
+                continue;
+            }
+            if in_fence || self.is_empty() {
+                output.push_str(line);
+                continue;
+            }
+
+            let mut last = 0;
+            let spans = protected.find_iter(line).map(|m| m.range()).chain(std::iter::once(line.len()..line.len()));
+            for span in spans {
+                let text = &line[last..span.start];
+                let mut pos = 0;
+                for (range, definition) in self.occurrences(text) {
+                    let term = &text[range.clone()];
+                    output.push_str(&text[pos..range.start]);
+                    let title = definition.replace('&', "&amp;").replace('"', "&quot;");
+                    let abbr = format!("<abbr title=\"{}\">{}</abbr>", title, term);
+                    if expand && expanded.insert(term.to_string()) {
+                        output.push_str(&format!("{} ({})", definition, abbr));
+                    } else {
+                        output.push_str(&abbr);
+                    }
+                    pos = range.end;
+                }
+                output.push_str(&text[pos..]);
+                output.push_str(&line[span.clone()]);
+                last = span.end;
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glossary() {
+        let mut glossary = Glossary::parse("*[HTML]: Hyper Text Markup Language\n*[CSS]: Cascading Style Sheets\nText\n");
+        glossary.extend(&Glossary::parse("*[HTML5]: HTML, version 5\n*[CSS]: Style sheets"));
+        assert_eq!(glossary.definition("CSS"), Some("Style sheets"));
+
+        let text = "HTML5 and HTML, not XHTML or CSSOM; CSS.";
+        let found: Vec<&str> = glossary.occurrences(text).into_iter().map(|(range, _)| &text[range]).collect();
+        assert_eq!(found, ["HTML5", "HTML", "CSS"]);
+
+        let markdown = "*[HTML]: Hyper Text Markup Language\nHTML is `HTML` in [[HTML]], HTML.\n```\nHTML\n```\n";
+        let glossary = Glossary::parse(markdown);
+        let abbr = "<abbr title=\"Hyper Text Markup Language\">HTML</abbr>";
+        assert_eq!(
+            glossary.apply(markdown, false),
+            format!("{} is `HTML` in [[HTML]], {}.\n```\nHTML\n```\n", abbr, abbr)
+        );
+        assert_eq!(
+            glossary.apply(markdown, true),
+            format!("Hyper Text Markup Language ({}) is `HTML` in [[HTML]], {}.\n```\nHTML\n```\n", abbr, abbr)
+        );
+    }
+}
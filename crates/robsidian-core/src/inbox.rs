@@ -0,0 +1,136 @@
+//! Inbox triage
+//!
+//! Notes collected in the vault's inbox folder are reviewed one at a time and
+//! moved, tagged, merged into another note, archived, or deleted. Deleted
+//! notes go to the vault's `.trash` folder rather than being removed.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use super::file_system::{get_markdown_files, rename, unique_path};
+
+/// Inbox folder used when the vault settings don't name one
+pub const DEFAULT_INBOX_FOLDER: &str = "Inbox";
+/// Archive folder used when the vault settings don't name one
+pub const DEFAULT_ARCHIVE_FOLDER: &str = "Archive";
+/// Folder receiving deleted notes
+pub const TRASH_FOLDER: &str = ".trash";
+
+/// Notes waiting in an inbox folder, oldest path first
+pub fn inbox_notes(dir: &Path) -> Vec<PathBuf> {
+    let mut notes = get_markdown_files(dir);
+    notes.sort();
+    notes
+}
+
+/// Folders of a vault relative to its root, skipping hidden ones
+pub fn vault_folders(vault: &Path) -> Vec<PathBuf> {
+    let mut folders: Vec<PathBuf> = WalkDir::new(vault)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .filter_map(|e| e.path().strip_prefix(vault).ok().map(Path::to_path_buf))
+        .collect();
+    folders.sort();
+    folders
+}
+
+/// Move a note into a folder (created if needed), numbering the name if a
+/// note with the same name is already there. Returns the new path.
+pub fn move_into(note: &Path, folder: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(folder)
+        .with_context(|| format!("Failed to create folder: {}", folder.display()))?;
+    let stem = note.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = note.extension().unwrap_or_default().to_string_lossy();
+    let target = unique_path(folder, &stem, &ext);
+    rename(note, &target).with_context(|| format!("Failed to move {}", note.display()))?;
+    Ok(target)
+}
+
+/// Add tags to a note's frontmatter `tags` list, creating the field or the
+/// frontmatter as needed. Tags already present are not repeated.
+pub fn add_tags(content: &str, tags: &[String]) -> String {
+    let tags: Vec<&str> = tags.iter().map(|t| t.trim().trim_start_matches('#')).filter(|t| !t.is_empty()).collect();
+    if tags.is_empty() {
+        return content.to_string();
+    }
+
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return format!("---\ntags: [{}]\n---\n{}", tags.join(", "), content);
+    };
+    let Some(end) = rest.find("\n---").map(|i| i + 1).or_else(|| rest.starts_with("---").then_some(0)) else {
+        return format!("---\ntags: [{}]\n---\n{}", tags.join(", "), content);
+    };
+    let (frontmatter, body) = rest.split_at(end);
+    let mut lines: Vec<String> = frontmatter.lines().map(str::to_string).collect();
+
+    match lines.iter().position(|line| line.trim_start().starts_with("tags:")) {
+        Some(idx) => {
+            let value = lines[idx].split_once(':').map(|(_, v)| v.trim()).unwrap_or_default().to_string();
+            if value.is_empty() {
+                // Block list: `tags:` followed by `  - tag` lines
+                let mut last = idx;
+                let mut existing = Vec::new();
+                while let Some(item) = lines.get(last + 1).and_then(|l| l.trim_start().strip_prefix("- ")) {
+                    existing.push(item.trim().to_string());
+                    last += 1;
+                }
+                let new: Vec<String> = tags
+                    .iter()
+                    .filter(|t| !existing.iter().any(|e| e == *t))
+                    .map(|t| format!("  - {}", t))
+                    .collect();
+                lines.splice(last + 1..last + 1, new);
+            } else {
+                let mut existing: Vec<String> = value
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .split(',')
+                    .map(|s| s.trim().trim_matches('"').to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                for tag in tags {
+                    if !existing.iter().any(|e| e == tag) {
+                        existing.push(tag.to_string());
+                    }
+                }
+                lines[idx] = format!("tags: [{}]", existing.join(", "));
+            }
+        }
+        None => lines.push(format!("tags: [{}]", tags.join(", "))),
+    }
+
+    let mut result = String::from("---\n");
+    for line in lines {
+        result.push_str(&line);
+        result.push('\n');
+    }
+    result.push_str(body);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_tags() {
+        assert_eq!(add_tags("Body\n", &tags(&["#idea"])), "---\ntags: [idea]\n---\nBody\n");
+        assert_eq!(
+            add_tags("---\ntitle: X\ntags: [a, b]\n---\nBody\n", &tags(&["b", "c"])),
+            "---\ntitle: X\ntags: [a, b, c]\n---\nBody\n"
+        );
+        assert_eq!(
+            add_tags("---\ntags:\n  - a\ntitle: X\n---\n", &tags(&["a", "new"])),
+            "---\ntags:\n  - a\n  - new\ntitle: X\n---\n"
+        );
+        assert_eq!(add_tags("---\ntitle: X\n---\n", &tags(&["t"])), "---\ntitle: X\ntags: [t]\n---\n");
+    }
+}
@@ -0,0 +1,294 @@
+//! Background document I/O
+//!
+//! Opening and saving run on a small pool of worker threads so a huge note or
+//! a slow network drive never blocks a frame. The UI polls for completions
+//! once per frame. Successful saves also record a snapshot of the note, or,
+//! for an encrypted note, delete the plain text ones recorded before.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Result;
+
+use super::crypto::Passphrase;
+use super::document::{DiskState, Document};
+use super::snapshots::SnapshotStore;
+
+/// Number of worker threads
+const WORKERS: usize = 2;
+
+/// Kind of operation running for a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOperation {
+    Opening,
+    Saving,
+}
+
+impl IoOperation {
+    /// Progress text shown next to the spinner
+    pub fn label(self) -> &'static str {
+        match self {
+            IoOperation::Opening => "Opening...",
+            IoOperation::Saving => "Saving...",
+        }
+    }
+}
+
+enum IoJob {
+    Open(PathBuf),
+    /// Save a snapshot of a document
+    Save(Box<Document>, Option<SnapshotStore>),
+    /// Encrypt a note that isn't open
+    Encrypt(PathBuf, Passphrase, Option<SnapshotStore>),
+}
+
+/// A finished operation
+pub enum IoResult {
+    Opened {
+        path: PathBuf,
+        result: Result<Box<Document>>,
+    },
+    Saved {
+        path: PathBuf,
+        /// Content that was written, to tell whether the document changed since
+        content: String,
+        result: Result<DiskState>,
+    },
+    Encrypted {
+        path: PathBuf,
+        /// False when the note was encrypted already
+        result: Result<bool>,
+    },
+}
+
+/// Worker pool running document I/O
+pub struct IoPool {
+    jobs: Sender<IoJob>,
+    results: Receiver<IoResult>,
+    pending: Vec<(PathBuf, IoOperation)>,
+    /// Saves waiting for an earlier save of the same path, so writes land in order
+    queued_saves: HashMap<PathBuf, Document>,
+    /// Where saved notes are snapshotted, if anywhere
+    snapshots: Option<SnapshotStore>,
+}
+
+impl Default for IoPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoPool {
+    /// Start the worker threads
+    pub fn new() -> Self {
+        let (jobs_tx, jobs_rx) = mpsc::channel::<IoJob>();
+        let (results_tx, results_rx) = mpsc::channel();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+
+        for _ in 0..WORKERS {
+            let jobs_rx = Arc::clone(&jobs_rx);
+            let results_tx = results_tx.clone();
+            thread::spawn(move || loop {
+                // The lock is only held while waiting for the next job
+                let job = match jobs_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break,
+                };
+                let Ok(job) = job else {
+                    break; // Pool dropped
+                };
+                let result = match job {
+                    IoJob::Open(path) => IoResult::Opened {
+                        result: Document::open(&path).map(Box::new),
+                        path,
+                    },
+                    IoJob::Save(doc, snapshots) => {
+                        let result = doc.save();
+                        if let (Ok(_), Some(snapshots)) = (&result, snapshots) {
+                            snapshot(&snapshots, &doc);
+                        }
+                        IoResult::Saved {
+                            result,
+                            path: doc.path,
+                            content: doc.content,
+                        }
+                    }
+                    IoJob::Encrypt(path, passphrase, snapshots) => IoResult::Encrypted {
+                        result: encrypt(&path, passphrase, snapshots.as_ref()),
+                        path,
+                    },
+                };
+                if results_tx.send(result).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            jobs: jobs_tx,
+            results: results_rx,
+            pending: Vec::new(),
+            queued_saves: HashMap::new(),
+            snapshots: None,
+        }
+    }
+
+    /// Record a snapshot of every note saved from now on
+    pub fn set_snapshots(&mut self, snapshots: SnapshotStore) {
+        self.snapshots = Some(snapshots);
+    }
+
+    /// Start opening a document unless it is already being opened
+    pub fn open(&mut self, path: PathBuf) {
+        if self.operation(&path) == Some(IoOperation::Opening) {
+            return;
+        }
+        self.pending.push((path.clone(), IoOperation::Opening));
+        let _ = self.jobs.send(IoJob::Open(path));
+    }
+
+    /// Start encrypting a note that isn't open
+    pub fn encrypt(&mut self, path: PathBuf, passphrase: Passphrase) {
+        self.pending.push((path.clone(), IoOperation::Saving));
+        let _ = self.jobs.send(IoJob::Encrypt(path, passphrase, self.snapshots.clone()));
+    }
+
+    /// Start saving a snapshot of a document. If the path is already being
+    /// saved, the snapshot is written once that finishes (replacing any
+    /// snapshot already waiting).
+    pub fn save(&mut self, doc: Document) {
+        if self.operation(&doc.path) == Some(IoOperation::Saving) {
+            self.queued_saves.insert(doc.path.clone(), doc);
+            return;
+        }
+        self.pending.push((doc.path.clone(), IoOperation::Saving));
+        let _ = self.jobs.send(IoJob::Save(Box::new(doc), self.snapshots.clone()));
+    }
+
+    /// Operation currently running for a path
+    pub fn operation(&self, path: &Path) -> Option<IoOperation> {
+        self.pending
+            .iter()
+            .find(|(pending, _)| pending == path)
+            .map(|(_, op)| *op)
+    }
+
+    /// Whether any operation is still running
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Collect the operations finished since the last call (non-blocking)
+    pub fn poll(&mut self) -> Vec<IoResult> {
+        let results: Vec<IoResult> = self.results.try_iter().collect();
+        for result in &results {
+            let (path, op) = match result {
+                IoResult::Opened { path, .. } => (path, IoOperation::Opening),
+                IoResult::Saved { path, .. } | IoResult::Encrypted { path, .. } => (path, IoOperation::Saving),
+            };
+            if let Some(idx) = self.pending.iter().position(|(p, o)| p == path && *o == op) {
+                self.pending.remove(idx);
+            }
+            if op == IoOperation::Saving {
+                if let Some(mut doc) = self.queued_saves.remove(path) {
+                    // The file now holds the earlier save, not an outside edit
+                    if let IoResult::Saved { result: Ok(disk), .. } = result {
+                        doc.disk = Some(disk.clone());
+                    }
+                    self.save(doc);
+                }
+            }
+        }
+        results
+    }
+}
+
+/// Record a snapshot of a saved note. Encrypted notes are never copied out
+/// in plain text, so their earlier snapshots go instead.
+fn snapshot(snapshots: &SnapshotStore, doc: &Document) {
+    let result = match doc.passphrase {
+        Some(_) => snapshots.purge(&doc.path),
+        None => snapshots.record(&doc.path, &doc.content),
+    };
+    if let Err(e) = result {
+        tracing::warn!("{:#}", e);
+    }
+}
+
+/// Encrypt a note on disk, returning false if it already was
+fn encrypt(path: &Path, passphrase: Passphrase, snapshots: Option<&SnapshotStore>) -> Result<bool> {
+    let mut doc = Document::open(path)?;
+    if doc.locked || doc.passphrase.is_some() {
+        return Ok(false);
+    }
+    doc.passphrase = Some(passphrase);
+    doc.save()?;
+    if let Some(snapshots) = snapshots {
+        snapshot(snapshots, &doc);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait(pool: &mut IoPool) -> IoResult {
+        for _ in 0..500 {
+            if let Some(result) = pool.poll().pop() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("I/O did not finish");
+    }
+
+    #[test]
+    fn test_save_then_open() {
+        let path = std::env::temp_dir().join(format!("robsidian_io_{}.md", std::process::id()));
+        let mut doc = Document::new(path.clone());
+        doc.content = "# Saved in the background\n".to_string();
+
+        let mut pool = IoPool::new();
+        pool.save(doc);
+        assert_eq!(pool.operation(&path), Some(IoOperation::Saving));
+        assert!(matches!(wait(&mut pool), IoResult::Saved { result: Ok(_), .. }));
+
+        pool.open(path.clone());
+        match wait(&mut pool) {
+            IoResult::Opened { result, .. } => {
+                assert_eq!(result.unwrap().content, "# Saved in the background\n");
+            }
+            _ => panic!("expected an open result"),
+        }
+        assert!(!pool.is_busy());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_encrypt_purges_snapshots() {
+        let dir = std::env::temp_dir().join(format!("robsidian_io_encrypt_{}", std::process::id()));
+        let path = dir.join("Secret.md");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&path, "plain text").unwrap();
+        let snapshots = SnapshotStore::new(dir.join("snapshots"), &Default::default());
+        snapshots.record(&path, "plain text").unwrap();
+
+        let mut pool = IoPool::new();
+        pool.set_snapshots(snapshots.clone());
+        for encrypted in [true, false] {
+            pool.encrypt(path.clone(), Passphrase::new("hunter2"));
+            match wait(&mut pool) {
+                IoResult::Encrypted { result, .. } => assert_eq!(result.unwrap(), encrypted),
+                _ => panic!("expected an encrypt result"),
+            }
+        }
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("plain text"));
+        assert!(snapshots.list(&path).is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
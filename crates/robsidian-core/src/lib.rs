@@ -0,0 +1,72 @@
+//! Robsidian's vault logic, without a user interface
+//!
+//! Documents, the vault's files and the note index, configuration, sync,
+//! publishing and the other parts that don't draw anything live here, free of
+//! egui, so the desktop app, command-line tools, tests and other frontends
+//! can share them. The desktop app is a client of this crate like any other.
+//!
+//! The main entry points:
+//!
+//! - [`document::Document`] opens, edits and saves a note; open notes are kept
+//!   in a [`document_store::DocumentStore`], which announces their changes.
+//! - [`file_system::FileTree`] and [`file_system::get_markdown_files`] list a
+//!   vault's files.
+//! - [`note_index::NoteIndex`] finds notes by name or alias and resolves wiki
+//!   links.
+//! - [`config::AppConfig`] is the app's settings, [`vault_settings`] those
+//!   stored in a vault.
+//! - [`io_tasks::IoPool`] and [`scheduler::Scheduler`] run work off the
+//!   calling thread.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use robsidian_core::document::Document;
+//! use robsidian_core::note_index::NoteIndex;
+//!
+//! let vault = Path::new("/home/me/Notes");
+//! let index = NoteIndex::build(vault);
+//! if let Some(path) = index.resolve("Reading List") {
+//!     let mut note = Document::open(&path)?;
+//!     note.content.push_str("\n- [ ] Piranesi\n");
+//!     note.save()?;
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod captions;
+pub mod clipboard_history;
+pub mod command_history;
+pub mod config;
+pub mod crypto;
+pub mod diff;
+pub mod document;
+pub mod document_store;
+pub mod export;
+pub mod external_editor;
+pub mod file_system;
+pub mod flashcards;
+pub mod glossary;
+pub mod inbox;
+pub mod io_tasks;
+pub mod link_refactor;
+pub mod link_safety;
+pub mod navigation;
+pub mod note_index;
+pub mod note_publish;
+pub mod note_share;
+pub mod paste_guard;
+pub mod scheduler;
+pub mod settings_bundle;
+pub mod snapshots;
+pub mod sync;
+pub mod templates;
+pub mod text_encoding;
+pub mod text_position;
+pub mod text_stats;
+pub mod transform;
+pub mod vault_api;
+pub mod vault_settings;
+pub mod vault_transfer;
+pub mod web_server;
+pub mod web_capture;
@@ -0,0 +1,442 @@
+//! Updating links when a file is renamed
+//!
+//! Renaming a note or an attachment rewrites every reference to it across the
+//! vault: wiki links and embeds (`[[Note]]`, `![[image.png|200]]`, by name or
+//! by vault path) and Markdown links and images (`[text](../Note.md)`,
+//! `![](<img/a b.png>)`, relative to the linking note or to the vault root).
+//! Each reference keeps its style, its alias and its `#heading` anchor.
+//!
+//! The links of a whole vault can also be converted from one style to the
+//! other, for tools that only understand one of them. The conversion is
+//! planned first, so it can be previewed and notes left out, then written.
+
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+use super::file_system::get_markdown_files;
+
+/// Characters escaped in rewritten Markdown link targets
+const LINK_PATH: &AsciiSet = &CONTROLS.add(b' ').add(b'(').add(b')').add(b'%').add(b'<').add(b'>');
+
+/// Path components of a vault-relative path
+fn parts(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|part| match part {
+            Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Resolve a link's path against a folder (as components), `None` if it
+/// climbs out of the vault
+fn resolve(folder: &[String], link: &str) -> Option<Vec<String>> {
+    let (mut resolved, link) = match link.strip_prefix('/') {
+        Some(rooted) => (Vec::new(), rooted),
+        None => (folder.to_vec(), link),
+    };
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                resolved.pop()?;
+            }
+            name => resolved.push(name.to_string()),
+        }
+    }
+    Some(resolved)
+}
+
+/// A path relative to `folder` leading to `target`
+fn relative(folder: &[String], target: &[String]) -> String {
+    let common = folder.iter().zip(target).take_while(|(a, b)| a == b).count();
+    let mut path: Vec<&str> = vec![".."; folder.len() - common];
+    path.extend(target[common..].iter().map(String::as_str));
+    path.join("/")
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// A renamed file, as vault-relative paths
+struct Rename {
+    old: Vec<String>,
+    new: Vec<String>,
+    note: bool,
+}
+
+impl Rename {
+    /// New wiki target for an old one, in the same style (name or path, with
+    /// or without the `.md` extension)
+    fn wiki_target(&self, target: &str) -> Option<String> {
+        let strip = |parts: &[String]| {
+            let joined = parts.join("/");
+            match joined.rsplit_once('.') {
+                Some((stem, _)) if self.note => stem.to_string(),
+                _ => joined,
+            }
+        };
+        let old_name = &self.old[self.old.len() - 1..];
+        let new_name = &self.new[self.new.len() - 1..];
+        let candidates = [
+            (old_name.join("/"), new_name.join("/")),
+            (self.old.join("/"), self.new.join("/")),
+            (strip(old_name), strip(new_name)),
+            (strip(&self.old), strip(&self.new)),
+        ];
+        candidates
+            .into_iter()
+            .find(|(old, _)| old.eq_ignore_ascii_case(target))
+            .map(|(_, new)| new)
+    }
+}
+
+/// Rewrite the references in one note (at `note`, relative to the vault) to a
+/// file renamed from `old` to `new`. Links in code are left alone. Returns
+/// `None` when nothing refers to it.
+pub fn rewrite_links(content: &str, note: &Path, old: &Path, new: &Path) -> Option<String> {
+    let rename = Rename { old: parts(old), new: parts(new), note: is_markdown(old) };
+    if rename.old.is_empty() || rename.new.is_empty() {
+        return None;
+    }
+    let folder = note.parent().map(parts).unwrap_or_default();
+    let mut changed = false;
+
+    let code = code_ranges(content);
+    let in_code = |code: &[Range<usize>], at: usize| code.iter().any(|range| range.contains(&at));
+    let wiki = regex_lite::Regex::new(r"(!?\[\[)([^\]|#]+)((?:#[^\]|]*)?(?:\|[^\]]*)?\]\])").unwrap();
+    let content = wiki.replace_all(content, |caps: &regex_lite::Captures| match rename.wiki_target(caps[2].trim()) {
+        Some(target) if !in_code(&code, caps.get(0).unwrap().start()) => {
+            changed = true;
+            format!("{}{}{}", &caps[1], target, &caps[3])
+        }
+        _ => caps[0].to_string(),
+    });
+
+    // The wiki links written above shift the offsets, so the code is found again
+    let code = code_ranges(&content);
+    let markdown = regex_lite::Regex::new(r"(!?\[[^\]]*\]\()(<[^>\n]+>|[^)\s]+)").unwrap();
+    let content = markdown.replace_all(&content, |caps: &regex_lite::Captures| {
+        let url = &caps[2];
+        let bracketed = url.starts_with('<');
+        let url = url.trim_start_matches('<').trim_end_matches('>');
+        let (path, anchor) = match url.find('#') {
+            Some(i) => url.split_at(i),
+            None => (url, ""),
+        };
+        let decoded = percent_decode_str(path).decode_utf8_lossy();
+        let linked = resolve(&folder, &decoded).as_ref() == Some(&rename.old);
+        if path.is_empty() || path.contains(':') || !linked || in_code(&code, caps.get(0).unwrap().start()) {
+            return caps[0].to_string();
+        }
+        changed = true;
+        let target = if path.starts_with('/') {
+            format!("/{}", rename.new.join("/"))
+        } else {
+            relative(&folder, &rename.new)
+        };
+        if bracketed {
+            format!("{}<{}{}>", &caps[1], target, anchor)
+        } else {
+            format!("{}{}{}", &caps[1], utf8_percent_encode(&target, LINK_PATH), anchor)
+        }
+    });
+
+    changed.then(|| content.into_owned())
+}
+
+/// Rename a file inside the vault and update the links to it in every note.
+/// Returns the notes that were rewritten.
+pub fn rename_with_links(vault: &Path, from: &Path, to: &Path) -> Result<Vec<PathBuf>> {
+    let old = from.strip_prefix(vault).context("The file isn't in the vault")?;
+    let new = to.strip_prefix(vault).context("The new name isn't in the vault")?;
+    if to.exists() {
+        bail!("{} already exists", new.display());
+    }
+    std::fs::rename(from, to).with_context(|| format!("Failed to rename {}", old.display()))?;
+
+    let mut relinked = Vec::new();
+    for note in get_markdown_files(vault) {
+        let Ok(rel) = note.strip_prefix(vault) else {
+            continue;
+        };
+        let Ok(content) = std::fs::read_to_string(&note) else {
+            continue;
+        };
+        if let Some(updated) = rewrite_links(&content, rel, old, new) {
+            std::fs::write(&note, updated).with_context(|| format!("Failed to update {}", rel.display()))?;
+            relinked.push(note);
+        }
+    }
+    Ok(relinked)
+}
+
+/// How links are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// `[[Note#Heading|text]]` and `![[image.png]]`
+    Wiki,
+    /// `[text](Note.md#Heading)` and `![](image.png)`
+    Markdown,
+}
+
+impl LinkStyle {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wiki => "Wiki links",
+            Self::Markdown => "Markdown links",
+        }
+    }
+}
+
+/// A link written in the other style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertedLink {
+    pub old: String,
+    pub new: String,
+}
+
+/// Files of a vault, as vault-relative path components, for resolving links
+#[derive(Debug, Default)]
+pub struct VaultFiles {
+    files: Vec<Vec<String>>,
+}
+
+impl VaultFiles {
+    /// Every file of the vault outside hidden folders
+    pub fn scan(vault: &Path) -> Self {
+        let mut files: Vec<Vec<String>> = walkdir::WalkDir::new(vault)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| Some(parts(entry.path().strip_prefix(vault).ok()?)))
+            .collect();
+        files.sort();
+        Self { files }
+    }
+
+    /// File a wiki target names, by vault path or by name, with or without
+    /// the `.md` extension of notes
+    fn find_wiki(&self, target: &str) -> Option<&[String]> {
+        let with_md = format!("{}.md", target);
+        let matches = |name: &str| name.eq_ignore_ascii_case(target) || name.eq_ignore_ascii_case(&with_md);
+        self.files
+            .iter()
+            .find(|file| matches(&file.join("/")))
+            .or_else(|| self.files.iter().find(|file| matches(&file[file.len() - 1])))
+            .map(Vec::as_slice)
+    }
+
+    /// Shortest wiki target for a file: its name when no other file has
+    /// it, else its vault path. Notes lose their `.md` extension.
+    fn wiki_target(&self, file: &[String]) -> String {
+        let name = &file[file.len() - 1];
+        let unique = self.files.iter().filter(|other| other[other.len() - 1].eq_ignore_ascii_case(name)).count() == 1;
+        let target = if unique { name.clone() } else { file.join("/") };
+        match target.strip_suffix(".md") {
+            Some(stem) => stem.to_string(),
+            None => target,
+        }
+    }
+
+    fn contains(&self, file: &[String]) -> bool {
+        self.files.iter().any(|known| known == file)
+    }
+}
+
+/// Byte ranges of the code blocks and inline code of a note, whose links
+/// are left alone
+pub fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let marker = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker));
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some((marker, offset)),
+            (Some((open, start)), Some(marker)) if open == marker => {
+                ranges.push(start..offset + line.len());
+                fence = None;
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    if let Some((_, start)) = fence {
+        ranges.push(start..content.len());
+    }
+    let inline = regex_lite::Regex::new(r"`[^`\n]+`").unwrap();
+    ranges.extend(inline.find_iter(content).map(|found| found.range()));
+    ranges
+}
+
+/// Write the links of one note (at `note`, relative to the vault) to files
+/// of the vault in `style`. Links to files that don't exist, external links
+/// and links in code are left alone. Returns the new content and the links
+/// converted.
+pub fn convert_links(content: &str, note: &Path, files: &VaultFiles, style: LinkStyle) -> (String, Vec<ConvertedLink>) {
+    let folder = note.parent().map(parts).unwrap_or_default();
+    let code = code_ranges(content);
+    let in_code = |at: usize| code.iter().any(|range| range.contains(&at));
+    let mut converted = Vec::new();
+    let mut convert = |old: &str, new: Option<String>| match new {
+        Some(new) => {
+            converted.push(ConvertedLink { old: old.to_string(), new: new.clone() });
+            new
+        }
+        None => old.to_string(),
+    };
+
+    let content = match style {
+        LinkStyle::Markdown => {
+            let wiki = regex_lite::Regex::new(r"(!?)\[\[([^\]|#]+)(#[^\]|]*)?(?:\|([^\]]*))?\]\]").unwrap();
+            wiki.replace_all(content, |caps: &regex_lite::Captures| {
+                let new = (!in_code(caps.get(0).unwrap().start()))
+                    .then(|| files.find_wiki(caps[2].trim()))
+                    .flatten()
+                    .map(|file| {
+                        let anchor = caps.get(3).map_or("", |anchor| anchor.as_str());
+                        let text = match (caps.get(4), caps.get(1).unwrap().is_empty()) {
+                            (Some(alias), _) => alias.as_str().to_string(),
+                            // Embeds have no text
+                            (None, false) => String::new(),
+                            (None, true) if anchor.is_empty() => caps[2].trim().to_string(),
+                            (None, true) => format!("{} > {}", caps[2].trim(), &anchor[1..]),
+                        };
+                        let path = utf8_percent_encode(&relative(&folder, file), LINK_PATH).to_string();
+                        let anchor = utf8_percent_encode(anchor, LINK_PATH);
+                        format!("{}[{}]({}{})", &caps[1], text, path, anchor)
+                    });
+                convert(&caps[0], new)
+            })
+        }
+        LinkStyle::Wiki => {
+            let markdown = regex_lite::Regex::new(r"(!?)\[([^\]]*)\]\((<[^>\n]+>|[^)\s]+)\)").unwrap();
+            markdown.replace_all(content, |caps: &regex_lite::Captures| {
+                let url = caps[3].trim_start_matches('<').trim_end_matches('>');
+                let (path, anchor) = match url.find('#') {
+                    Some(i) => url.split_at(i),
+                    None => (url, ""),
+                };
+                let decoded = percent_decode_str(path).decode_utf8_lossy();
+                let file = resolve(&folder, &decoded).filter(|file| !file.is_empty() && files.contains(file));
+                let external = path.is_empty() || path.contains(':');
+                let new = file.filter(|_| !external && !in_code(caps.get(0).unwrap().start())).map(|file| {
+                    let target = files.wiki_target(&file);
+                    let anchor = percent_decode_str(anchor).decode_utf8_lossy();
+                    let text = &caps[2];
+                    let shown = format!("{}{}", target, anchor);
+                    if text.is_empty() || text == shown || text == target {
+                        format!("{}[[{}{}]]", &caps[1], target, anchor)
+                    } else {
+                        format!("{}[[{}{}|{}]]", &caps[1], target, anchor, text)
+                    }
+                });
+                convert(&caps[0], new)
+            })
+        }
+    };
+    (content.into_owned(), converted)
+}
+
+/// Links of one note to convert, before anything is written
+#[derive(Debug, Clone)]
+pub struct NoteConversion {
+    pub note: PathBuf,
+    pub links: Vec<ConvertedLink>,
+    content: String,
+}
+
+/// The notes of a vault with links to write in `style`, and how they'd
+/// change; nothing is written yet
+pub fn plan_link_conversion(vault: &Path, style: LinkStyle) -> Vec<NoteConversion> {
+    let files = VaultFiles::scan(vault);
+    let mut notes = get_markdown_files(vault);
+    notes.sort();
+    notes
+        .into_iter()
+        .filter_map(|note| {
+            let rel = note.strip_prefix(vault).ok()?;
+            if rel.components().any(|part| part.as_os_str().to_string_lossy().starts_with('.')) {
+                return None;
+            }
+            let content = std::fs::read_to_string(&note).ok()?;
+            let (content, links) = convert_links(&content, rel, &files, style);
+            (!links.is_empty()).then_some(NoteConversion { note, links, content })
+        })
+        .collect()
+}
+
+/// Write the converted notes
+pub fn apply_link_conversion(conversions: &[NoteConversion]) -> Result<()> {
+    for conversion in conversions {
+        std::fs::write(&conversion.note, &conversion.content)
+            .with_context(|| format!("Failed to update {}", conversion.note.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_links() {
+        let note = Path::new("Daily/Today.md");
+        let content = "![[cat.png|200]] [[Notes/Old Note#Top|see]] [[old note]] \
+                       ![](../img/cat.png) [x](</Notes/Old Note.md#Top>) [y](../Notes/Old%20Note.md) [z](Other.md)";
+
+        let image = rewrite_links(content, note, Path::new("img/cat.png"), Path::new("img/kitten.png")).unwrap();
+        assert!(image.starts_with("![[kitten.png|200]]"));
+        assert!(image.contains("![](../img/kitten.png)"));
+
+        let renamed = rewrite_links(content, note, Path::new("Notes/Old Note.md"), Path::new("Notes/New Note.md"));
+        assert_eq!(
+            renamed.unwrap(),
+            "![[cat.png|200]] [[Notes/New Note#Top|see]] [[New Note]] \
+             ![](../img/cat.png) [x](</Notes/New Note.md#Top>) [y](../Notes/New%20Note.md) [z](Other.md)"
+        );
+
+        assert_eq!(rewrite_links(content, note, Path::new("img/dog.png"), Path::new("img/pup.png")), None);
+
+        let code = "```\n[[Old Note]] [x](../Notes/Old%20Note.md)\n```\n`[[Old Note]]` [[Old Note]]\n";
+        assert_eq!(
+            rewrite_links(code, note, Path::new("Notes/Old Note.md"), Path::new("Notes/New Note.md")).unwrap(),
+            "```\n[[Old Note]] [x](../Notes/Old%20Note.md)\n```\n`[[Old Note]]` [[New Note]]\n"
+        );
+        let fenced = "```\n[[Old Note]] [x](../Notes/Old%20Note.md)\n```\n";
+        assert_eq!(rewrite_links(fenced, note, Path::new("Notes/Old Note.md"), Path::new("Notes/New Note.md")), None);
+    }
+
+    #[test]
+    fn test_convert_links() {
+        let parts = |path: &str| path.split('/').map(str::to_string).collect();
+        let files = VaultFiles {
+            files: vec![parts("Notes/Old Note.md"), parts("img/cat.png"), parts("Daily/Today.md")],
+        };
+        let note = Path::new("Daily/Today.md");
+        let wiki = "See [[Old Note#Top|this]], [[old note]] and ![[cat.png|200]]. [[Missing]] `[[Old Note]]`";
+        let (markdown, links) = convert_links(wiki, note, &files, LinkStyle::Markdown);
+        assert_eq!(
+            markdown,
+            "See [this](../Notes/Old%20Note.md#Top), [old note](../Notes/Old%20Note.md) and \
+             ![200](../img/cat.png). [[Missing]] `[[Old Note]]`"
+        );
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].old, "[[Old Note#Top|this]]");
+
+        let (back, _) = convert_links(&markdown, note, &files, LinkStyle::Wiki);
+        assert_eq!(
+            back,
+            "See [[Old Note#Top|this]], [[Old Note|old note]] and ![[cat.png|200]]. [[Missing]] `[[Old Note]]`"
+        );
+        let (web, links) = convert_links("[site](https://example.com)", note, &files, LinkStyle::Wiki);
+        assert_eq!((web.as_str(), links.len()), ("[site](https://example.com)", 0));
+    }
+}
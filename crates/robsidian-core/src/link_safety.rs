@@ -0,0 +1,67 @@
+//! External link checks
+//!
+//! Links in notes can say one thing and point somewhere else
+//! (`[bank.com](https://evil.example)`), so opening a web address can be
+//! confirmed first, showing where it really goes. Domains the user trusts
+//! skip the confirmation.
+
+use url::Url;
+
+use super::config::LinkConfig;
+
+/// Host of a web address, lowercased
+pub fn link_host(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(|host| host.trim_start_matches("www.").to_lowercase())
+}
+
+/// Whether a host is one of the domains, or a subdomain of one
+fn host_in(host: &str, domains: &[String]) -> bool {
+    domains.iter().any(|domain| {
+        let domain = domain.trim().trim_start_matches("www.").to_lowercase();
+        !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+    })
+}
+
+/// Whether opening `url` should be confirmed first
+pub fn needs_confirmation(config: &LinkConfig, url: &str) -> bool {
+    if !config.confirm_external {
+        return false;
+    }
+    match link_host(url) {
+        Some(host) => !host_in(&host, &config.trusted_domains),
+        // `mailto:` and other addresses without a host
+        None => true,
+    }
+}
+
+/// The host a link's text claims to go to, when it's not where the link
+/// actually goes
+pub fn misleading_host(text: &str, url: &str) -> Option<String> {
+    let text = text.trim();
+    let shown = if text.contains("://") { text.to_string() } else { format!("https://{}", text) };
+    if text.contains(char::is_whitespace) || !text.contains('.') {
+        return None;
+    }
+    let claimed = link_host(&shown).filter(|host| host.contains('.'))?;
+    let actual = link_host(url)?;
+    (claimed != actual && !actual.ends_with(&format!(".{}", claimed))).then_some(claimed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_checks() {
+        let config = LinkConfig { confirm_external: true, trusted_domains: vec!["example.com".to_string()] };
+        assert!(!needs_confirmation(&config, "https://docs.example.com/page"));
+        assert!(!needs_confirmation(&config, "https://www.Example.com"));
+        assert!(needs_confirmation(&config, "https://notexample.com"));
+        assert!(needs_confirmation(&config, "mailto:someone@example.com"));
+        assert!(!needs_confirmation(&LinkConfig { confirm_external: false, ..config }, "https://evil.test"));
+
+        assert_eq!(misleading_host("bank.com", "https://evil.test/login"), Some("bank.com".to_string()));
+        assert_eq!(misleading_host("https://example.com/a", "https://docs.example.com/b"), None);
+        assert_eq!(misleading_host("the docs", "https://evil.test"), None);
+    }
+}
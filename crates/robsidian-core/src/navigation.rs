@@ -0,0 +1,205 @@
+//! Navigation history for editor panes
+//!
+//! Each pane keeps its own list of visited locations so following links
+//! never loses the place you came from.
+
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// Maximum number of entries kept per history
+const MAX_ENTRIES: usize = 100;
+
+/// A visited location: a note and optionally a heading (or `^block`) inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavEntry {
+    pub path: PathBuf,
+    pub heading: Option<String>,
+}
+
+impl NavEntry {
+    /// Create an entry for a note, optionally anchored at a heading
+    pub fn new(path: impl Into<PathBuf>, heading: Option<String>) -> Self {
+        Self {
+            path: path.into(),
+            heading,
+        }
+    }
+
+    /// Display label such as `Note` or `Note > Heading`
+    pub fn label(&self) -> String {
+        let name = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string());
+        match &self.heading {
+            Some(heading) => format!("{} > {}", name, heading),
+            None => name,
+        }
+    }
+}
+
+/// Back/forward history of one pane
+#[derive(Debug, Clone, Default)]
+pub struct NavigationHistory {
+    entries: Vec<NavEntry>,
+    current: usize,
+}
+
+impl NavigationHistory {
+    /// Create a history starting at `entry`
+    pub fn new(entry: NavEntry) -> Self {
+        Self {
+            entries: vec![entry],
+            current: 0,
+        }
+    }
+
+    /// The location currently shown
+    pub fn current(&self) -> Option<&NavEntry> {
+        self.entries.get(self.current)
+    }
+
+    /// All entries, oldest first
+    pub fn entries(&self) -> &[NavEntry] {
+        &self.entries
+    }
+
+    /// Index of the current entry in [`Self::entries`]
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    /// Record a visit, discarding any forward entries.
+    /// Visiting the current location again is a no-op.
+    pub fn visit(&mut self, entry: NavEntry) {
+        if self.current() == Some(&entry) {
+            return;
+        }
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.current + 1);
+        }
+        self.entries.push(entry);
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.current = self.entries.len() - 1;
+    }
+
+    /// Whether there is an older entry to go back to
+    pub fn can_go_back(&self) -> bool {
+        self.current > 0
+    }
+
+    /// Whether there is a newer entry to go forward to
+    pub fn can_go_forward(&self) -> bool {
+        self.current + 1 < self.entries.len()
+    }
+
+    /// Step back, returning the new current location
+    pub fn back(&mut self) -> Option<&NavEntry> {
+        if !self.can_go_back() {
+            return None;
+        }
+        self.current -= 1;
+        self.current()
+    }
+
+    /// Step forward, returning the new current location
+    pub fn forward(&mut self) -> Option<&NavEntry> {
+        if !self.can_go_forward() {
+            return None;
+        }
+        self.current += 1;
+        self.current()
+    }
+
+    /// Jump to an entry picked from the history list
+    pub fn jump_to(&mut self, index: usize) -> Option<&NavEntry> {
+        if index >= self.entries.len() {
+            return None;
+        }
+        self.current = index;
+        self.current()
+    }
+}
+
+/// Split a link target such as `Note#Heading` into note and heading parts
+pub fn split_anchor(target: &str) -> (&str, Option<&str>) {
+    match target.split_once('#') {
+        Some((note, heading)) if !heading.trim().is_empty() => (note.trim(), Some(heading.trim())),
+        Some((note, _)) => (note.trim(), None),
+        None => (target.trim(), None),
+    }
+}
+
+/// Id of the block reference ending a block's source, such as `summary` for
+/// a paragraph ending in `^summary`
+pub fn block_id(source: &str) -> Option<&str> {
+    let last = source.trim_end().rsplit(char::is_whitespace).next()?;
+    let id = last.strip_prefix('^')?;
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then_some(id)
+}
+
+/// A short block id not yet referenced in `content`
+pub fn new_block_id(content: &str) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    (0u32..)
+        .map(|attempt| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            (content, attempt).hash(&mut hasher);
+            let mut hash = hasher.finish();
+            (0..6)
+                .map(|_| {
+                    let digit = DIGITS[(hash % DIGITS.len() as u64) as usize] as char;
+                    hash /= DIGITS.len() as u64;
+                    digit
+                })
+                .collect::<String>()
+        })
+        .find(|id| !content.contains(&format!("^{}", id)))
+        .expect("some id is unused")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_ids() {
+        assert_eq!(block_id("A paragraph. ^summary\n"), Some("summary"));
+        assert_eq!(block_id("- item\n- other ^list-1"), Some("list-1"));
+        assert_eq!(block_id("^only"), Some("only"));
+        assert_eq!(block_id("x^2 is not one"), None);
+        assert_eq!(block_id("Ends with ^"), None);
+
+        let id = new_block_id("Text ^abc");
+        assert_eq!(id.len(), 6);
+        assert_ne!(id, "abc");
+    }
+
+    #[test]
+    fn test_back_forward() {
+        let mut history = NavigationHistory::new(NavEntry::new("a.md", None));
+        history.visit(NavEntry::new("b.md", None));
+        history.visit(NavEntry::new("b.md", Some("Intro".into())));
+
+        assert_eq!(history.back().unwrap().path, PathBuf::from("b.md"));
+        assert_eq!(history.back().unwrap().path, PathBuf::from("a.md"));
+        assert!(history.back().is_none());
+        assert_eq!(history.forward().unwrap().heading.as_deref(), None);
+
+        // A new visit drops the forward entries
+        history.visit(NavEntry::new("c.md", None));
+        assert!(!history.can_go_forward());
+        assert_eq!(history.entries().len(), 3);
+    }
+
+    #[test]
+    fn test_split_anchor() {
+        assert_eq!(split_anchor("Note#Heading"), ("Note", Some("Heading")));
+        assert_eq!(split_anchor("#Heading"), ("", Some("Heading")));
+        assert_eq!(split_anchor("Note"), ("Note", None));
+    }
+}
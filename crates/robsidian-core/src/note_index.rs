@@ -0,0 +1,216 @@
+//! Index of the notes in a vault
+//!
+//! Maps note names and frontmatter aliases to files. Used to resolve wiki
+//! links and to find notes in the quick switcher and link autocompletion.
+
+use std::path::{Path, PathBuf};
+
+use super::document::Document;
+use super::file_system::{get_markdown_files, resolve_note_link};
+
+/// A note known to the index
+#[derive(Debug, Clone)]
+pub struct IndexedNote {
+    pub path: PathBuf,
+    /// File name without extension, as used in wiki links
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// Tags of the frontmatter
+    pub tags: Vec<String>,
+}
+
+/// A search result: a note, found by its name or by one of its aliases
+#[derive(Debug, Clone, Copy)]
+pub struct NoteMatch<'a> {
+    pub note: &'a IndexedNote,
+    pub alias: Option<&'a str>,
+}
+
+impl NoteMatch<'_> {
+    /// Display label: `Note`, or `Alias → Note` for alias matches
+    pub fn label(&self) -> String {
+        match self.alias {
+            Some(alias) => format!("{} → {}", alias, self.note.name),
+            None => self.note.name.clone(),
+        }
+    }
+
+    /// Wiki link contents for the match, keeping the alias as display text
+    pub fn link_text(&self) -> String {
+        match self.alias {
+            Some(alias) => format!("{}|{}", self.note.name, alias),
+            None => self.note.name.clone(),
+        }
+    }
+
+    fn matched_text(&self) -> &str {
+        self.alias.unwrap_or(&self.note.name)
+    }
+}
+
+/// Notes of the open vault with their aliases
+#[derive(Debug, Clone, Default)]
+pub struct NoteIndex {
+    vault: Option<PathBuf>,
+    notes: Vec<IndexedNote>,
+}
+
+impl NoteIndex {
+    /// Index every note in a vault, skipping hidden folders
+    pub fn build(vault: &Path) -> Self {
+        let mut notes: Vec<IndexedNote> = get_markdown_files(vault)
+            .into_iter()
+            .filter(|path| {
+                path.strip_prefix(vault).is_ok_and(|rel| {
+                    !rel.components()
+                        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+                })
+            })
+            .filter_map(|path| Document::open(&path).ok())
+            .map(|doc| Self::entry(&doc))
+            .collect();
+        notes.sort_by_key(|note| note.name.to_lowercase());
+
+        Self {
+            vault: Some(vault.to_path_buf()),
+            notes,
+        }
+    }
+
+    fn entry(doc: &Document) -> IndexedNote {
+        IndexedNote {
+            path: doc.path.clone(),
+            name: doc
+                .path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            aliases: doc.metadata.aliases.clone(),
+            tags: doc.metadata.tags.clone(),
+        }
+    }
+
+    /// Folder of the indexed vault
+    pub fn vault(&self) -> Option<&Path> {
+        self.vault.as_deref()
+    }
+
+    /// Every indexed note, sorted by name
+    pub fn notes(&self) -> &[IndexedNote] {
+        &self.notes
+    }
+
+    /// The indexed note at `path`
+    pub fn note(&self, path: &Path) -> Option<&IndexedNote> {
+        self.notes.iter().find(|note| note.path == path)
+    }
+
+    /// Tags of the notes, without `#`, sorted and each once
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> =
+            self.notes.iter().flat_map(|note| &note.tags).map(|tag| tag.trim_start_matches('#').to_string()).collect();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        tags
+    }
+
+    /// Add a document to the index or refresh its aliases and tags
+    pub fn update(&mut self, doc: &Document) {
+        let in_vault = self.vault.as_ref().is_some_and(|vault| doc.path.starts_with(vault));
+        match self.notes.iter_mut().find(|note| note.path == doc.path) {
+            Some(note) => {
+                note.aliases = doc.metadata.aliases.clone();
+                note.tags = doc.metadata.tags.clone();
+            }
+            None if in_vault => self.notes.push(Self::entry(doc)),
+            None => {}
+        }
+    }
+
+    /// Resolve a wiki link target (without `#heading`): a path relative to
+    /// the vault, then a note name anywhere in it, then an alias
+    pub fn resolve(&self, target: &str) -> Option<PathBuf> {
+        let target = target.trim();
+        if let Some(path) = self.vault.as_deref().and_then(|vault| resolve_note_link(vault, target)) {
+            return Some(path);
+        }
+        self.notes
+            .iter()
+            .find(|note| note.name.eq_ignore_ascii_case(target))
+            .or_else(|| {
+                self.notes.iter().find(|note| {
+                    note.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(target))
+                })
+            })
+            .map(|note| note.path.clone())
+    }
+
+    /// Notes whose name or alias contains every word of `query`
+    /// (case-insensitive), prefix matches first. An empty query lists every
+    /// note by name.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<NoteMatch<'_>> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+        let matches_query = |text: &str| {
+            let text = text.to_lowercase();
+            words.iter().all(|word| text.contains(word))
+        };
+
+        let mut results = Vec::new();
+        for note in &self.notes {
+            if matches_query(&note.name) {
+                results.push(NoteMatch { note, alias: None });
+            }
+            if words.is_empty() {
+                continue;
+            }
+            for alias in &note.aliases {
+                if matches_query(alias) {
+                    results.push(NoteMatch { note, alias: Some(alias) });
+                }
+            }
+        }
+
+        if !words.is_empty() {
+            results.sort_by_key(|m| {
+                let text = m.matched_text().to_lowercase();
+                (!text.starts_with(&query), text.len())
+            });
+        }
+        results.truncate(limit);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(name: &str, aliases: &[&str]) -> IndexedNote {
+        IndexedNote {
+            path: PathBuf::from(format!("{}.md", name)),
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_aliases() {
+        let index = NoteIndex {
+            vault: None,
+            notes: vec![
+                note("Rust Ownership", &["Borrow Checker"]),
+                note("Borrowing Books", &[]),
+            ],
+        };
+
+        assert_eq!(index.resolve("borrow checker"), Some(PathBuf::from("Rust Ownership.md")));
+        assert_eq!(index.resolve("Borrowing Books"), Some(PathBuf::from("Borrowing Books.md")));
+        assert_eq!(index.resolve("Missing"), None);
+
+        let labels: Vec<String> = index.search("borrow", 10).iter().map(NoteMatch::label).collect();
+        assert_eq!(labels, vec!["Borrow Checker → Rust Ownership", "Borrowing Books"]);
+        assert_eq!(index.search("checker", 10)[0].link_text(), "Rust Ownership|Borrow Checker");
+    }
+}
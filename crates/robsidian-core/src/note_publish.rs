@@ -0,0 +1,162 @@
+//! Publishing single notes to the web
+//!
+//! Pushes a note to a GitHub Gist, or to any endpoint accepting the same
+//! small JSON request, and gives back the address it's reachable at. That
+//! address is kept in the note's `publish_url` frontmatter field, so
+//! publishing the note again updates the same Gist or post.
+
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Frontmatter field holding the published address of a note
+pub const URL_FIELD: &str = "publish_url";
+
+const GIST_API: &str = "https://api.github.com/gists";
+const USER_AGENT: &str = concat!("Robsidian/", env!("CARGO_PKG_VERSION"));
+
+/// Where notes are published to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum PublishTarget {
+    /// GitHub Gist, with a personal access token allowed to create gists
+    Gist { token: String, public: bool },
+    /// A web endpoint that takes `{"title", "filename", "content", "url"}`
+    /// as a JSON POST and answers with `{"url"}`; `url` is the address of an
+    /// earlier publish, or null for a new post
+    Endpoint { url: String, token: String },
+}
+
+/// A note to publish
+#[derive(Debug, Clone)]
+pub struct Publication {
+    pub title: String,
+    pub filename: String,
+    /// Markdown, without the frontmatter
+    pub content: String,
+    /// Address of the previous publish of the note, if any
+    pub url: Option<String>,
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .user_agent(USER_AGENT)
+        .build()
+}
+
+/// Id of a Gist from its page address (`https://gist.github.com/<user>/<id>`)
+fn gist_id(url: &str) -> Option<&str> {
+    if !url.contains("gist.github.com/") {
+        return None;
+    }
+    url.trim_end_matches('/').rsplit('/').next().filter(|id| !id.is_empty())
+}
+
+/// Publish a note, returning its address
+pub fn publish(target: &PublishTarget, note: &Publication) -> Result<String> {
+    let agent = agent();
+    let response = match target {
+        PublishTarget::Gist { token, public } => {
+            if token.trim().is_empty() {
+                return Err(anyhow!("No GitHub token set in the publish settings"));
+            }
+            let body = json!({
+                "description": note.title,
+                "public": public,
+                "files": { note.filename.as_str(): { "content": note.content } },
+            });
+            // Update the note's Gist when it has one
+            let request = match note.url.as_deref().and_then(gist_id) {
+                Some(id) => agent.patch(&format!("{}/{}", GIST_API, id)),
+                None => agent.post(GIST_API),
+            };
+            request
+                .set("Accept", "application/vnd.github+json")
+                .set("Authorization", &format!("Bearer {}", token.trim()))
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string())
+                .context("GitHub refused the Gist")?
+        }
+        PublishTarget::Endpoint { url, token } => {
+            let mut request = agent.post(url.trim()).set("Content-Type", "application/json");
+            if !token.trim().is_empty() {
+                request = request.set("Authorization", &format!("Bearer {}", token.trim()));
+            }
+            let body = json!({
+                "title": note.title,
+                "filename": note.filename,
+                "content": note.content,
+                "url": note.url,
+            });
+            request
+                .send_string(&body.to_string())
+                .with_context(|| format!("Failed to publish to {}", url))?
+        }
+    };
+
+    let reply: serde_json::Value = serde_json::from_str(&response.into_string()?).context("Invalid response")?;
+    let key = match target {
+        PublishTarget::Gist { .. } => "html_url",
+        PublishTarget::Endpoint { .. } => "url",
+    };
+    reply[key]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("The response has no \"{}\"", key))
+}
+
+/// Notes being published in the background
+pub struct Publisher {
+    results_tx: Sender<(PathBuf, Result<String>)>,
+    results: Receiver<(PathBuf, Result<String>)>,
+    running: usize,
+}
+
+impl Default for Publisher {
+    fn default() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        Self { results_tx, results, running: 0 }
+    }
+}
+
+impl Publisher {
+    /// Publish the note at `path` on a background thread
+    pub fn start(&mut self, path: PathBuf, target: PublishTarget, note: Publication) {
+        let results = self.results_tx.clone();
+        self.running += 1;
+        thread::spawn(move || {
+            let result = publish(&target, &note);
+            let _ = results.send((path, result));
+        });
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.running > 0
+    }
+
+    /// Collect the publishes finished since the last call (non-blocking),
+    /// with the note each was started for
+    pub fn poll(&mut self) -> Vec<(PathBuf, Result<String>)> {
+        let results: Vec<_> = self.results.try_iter().collect();
+        self.running -= results.len();
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gist_id() {
+        assert_eq!(gist_id("https://gist.github.com/someone/aa5a315d61ae9438b18d"), Some("aa5a315d61ae9438b18d"));
+        assert_eq!(gist_id("https://gist.github.com/aa5a315d61ae9438b18d/"), Some("aa5a315d61ae9438b18d"));
+        assert_eq!(gist_id("https://blog.example.com/posts/1"), None);
+    }
+}
@@ -0,0 +1,316 @@
+//! Sharing a note outside the vault
+//!
+//! Packs a note for someone who doesn't have the vault: transcluded notes
+//! (`![[Other Note]]`, `![[Other Note#Section]]`) are inlined, and embedded
+//! attachments travel with it. The bundle is either a zip holding the note
+//! and an `attachments/` folder, or a single HTML page with the attachments
+//! as `data:` URIs.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{Datelike, Local, Timelike};
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+
+use super::document::Document;
+use super::export::{html_page, markdown_to_html};
+use super::navigation::split_anchor;
+use super::note_index::NoteIndex;
+use super::vault_transfer::{embeds, resolve_attachment};
+
+/// Transclusions nested deeper than this are left as links
+const MAX_DEPTH: usize = 4;
+
+/// Folder of the attachments inside a zip bundle
+const ATTACHMENTS: &str = "attachments";
+
+const PAGE_STYLE: &str = "<style>body{max-width:46em;margin:2em auto;padding:0 1em;\
+                          font-family:sans-serif;line-height:1.5}img{max-width:100%}\
+                          pre{overflow-x:auto}blockquote{color:#555;border-left:3px solid #ccc;\
+                          margin-left:0;padding-left:1em}</style>\n";
+
+/// What a note is shared as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareFormat {
+    Zip,
+    Html,
+}
+
+impl ShareFormat {
+    /// The format matching a file's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "zip" => Some(ShareFormat::Zip),
+            "html" | "htm" => Some(ShareFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+/// The lines of a note under a heading, up to the next heading of the same
+/// or a higher level
+fn section<'a>(content: &'a str, heading: &str) -> Option<&'a str> {
+    let level = |line: &str| {
+        let hashes = line.chars().take_while(|&c| c == '#').count();
+        (hashes > 0 && line[hashes..].starts_with(' ')).then_some(hashes)
+    };
+    let mut start = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        match (start, level(line)) {
+            (None, Some(depth)) if line[depth..].trim().eq_ignore_ascii_case(heading) => {
+                start = Some((offset, depth));
+            }
+            (Some((begin, depth)), Some(found)) if found <= depth => return Some(&content[begin..offset]),
+            _ => {}
+        }
+        offset += line.len();
+    }
+    start.map(|(begin, _)| &content[begin..])
+}
+
+/// Replace note embeds with the notes' content, outside code blocks
+fn inline_notes(content: &str, notes: &NoteIndex, stack: &mut Vec<PathBuf>) -> String {
+    let embed = regex_lite::Regex::new(r"!\[\[([^\]|]+)(?:\|[^\]]*)?\]\]").unwrap();
+    let mut output = String::with_capacity(content.len());
+    let mut in_fence = false;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            output.push_str(line);
+            continue;
+        }
+        let line = embed.replace_all(line, |caps: &regex_lite::Captures| {
+            let (name, heading) = split_anchor(&caps[1]);
+            if Path::new(name).extension().is_some_and(|ext| ext != "md") {
+                return caps[0].to_string();
+            }
+            let Some(path) = notes.resolve(name) else {
+                return caps[0].to_string();
+            };
+            if stack.len() > MAX_DEPTH || stack.contains(&path) {
+                return format!("[[{}]]", &caps[1]);
+            }
+            let Ok(doc) = Document::open(&path) else {
+                return caps[0].to_string();
+            };
+            let body = doc.content_without_frontmatter();
+            let body = match heading {
+                Some(heading) => section(body, heading).unwrap_or(body),
+                None => body,
+            };
+            stack.push(path);
+            let inlined = inline_notes(body, notes, stack);
+            stack.pop();
+            format!("\n{}\n", inlined.trim_end())
+        });
+        output.push_str(&line);
+    }
+    output
+}
+
+/// MIME type of an attachment, by extension
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A note ready to be shared: its Markdown with transclusions inlined, and
+/// the attachments it embeds by their name in the bundle
+struct Bundle {
+    title: String,
+    markdown: String,
+    attachments: Vec<(String, PathBuf)>,
+}
+
+impl Bundle {
+    fn collect(doc: &Document, vault: &Path, notes: &NoteIndex) -> Self {
+        let mut markdown = inline_notes(doc.content_without_frontmatter(), notes, &mut vec![doc.path.clone()]);
+        let mut attachments: Vec<(String, PathBuf)> = Vec::new();
+        let mut names = HashSet::new();
+        for embed in embeds(&markdown) {
+            let Some(file) = resolve_attachment(vault, &doc.path, &embed) else {
+                continue;
+            };
+            let name = match attachments.iter().find(|(_, path)| *path == file) {
+                Some((name, _)) => name.clone(),
+                None => {
+                    let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                    let ext = file.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+                    let mut name = format!("{}{}", stem, ext);
+                    let mut n = 1;
+                    while !names.insert(name.clone()) {
+                        name = format!("{}-{}{}", stem, n, ext);
+                        n += 1;
+                    }
+                    attachments.push((name.clone(), file));
+                    name
+                }
+            };
+            // Relative to the bundle; the HTML page swaps these for data URIs
+            markdown = markdown.replace(&embed.text, &format!("![](<{}/{}>)", ATTACHMENTS, name));
+        }
+        Bundle { title: doc.title(), markdown, attachments }
+    }
+
+    fn zip(&self) -> Result<Vec<u8>> {
+        let file_name = format!("{}.md", self.title.replace(['/', '\\'], "-"));
+        let mut files = vec![(file_name, self.markdown.clone().into_bytes())];
+        for (name, path) in &self.attachments {
+            let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            files.push((format!("{}/{}", ATTACHMENTS, name), data));
+        }
+        write_zip(&files)
+    }
+
+    fn html(&self, doc: &Document, vault: &Path, notes: &NoteIndex) -> Result<String> {
+        let mut markdown = self.markdown.clone();
+        for (name, path) in &self.attachments {
+            let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let mime = mime_type(path);
+            let uri = format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(data));
+            let embed = format!("![](<{}/{}>)", ATTACHMENTS, name);
+            let inlined = if mime.starts_with("image/") {
+                format!("![{}]({})", name, uri)
+            } else {
+                format!("[{}]({})", name, uri)
+            };
+            markdown = markdown.replace(&embed, &inlined);
+        }
+        // No other notes travel with the page, so links to them become text
+        let body = markdown_to_html(&markdown, &doc.path, vault, notes, &HashSet::new());
+        Ok(html_page(&self.title, PAGE_STYLE, &format!("<h1>{}</h1>\n{}", escape(&self.title), body)))
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Pack a note, the notes it transcludes and its attachments in `format`
+pub fn share_note(doc: &Document, vault: &Path, notes: &NoteIndex, format: ShareFormat) -> Result<Vec<u8>> {
+    let bundle = Bundle::collect(doc, vault, notes);
+    match format {
+        ShareFormat::Zip => bundle.zip(),
+        ShareFormat::Html => Ok(bundle.html(doc, vault, notes)?.into_bytes()),
+    }
+}
+
+/// A zip archive of deflated files, named with `/` separators
+fn write_zip(files: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let now = Local::now();
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let date = ((((now.year() - 1980).max(0) as u32) << 9) | (now.month() << 5) | now.day()) as u16;
+    // Version 2.0, UTF-8 names, deflate
+    let header = |out: &mut Vec<u8>, crc: u32, compressed: usize, size: usize, name: &str| {
+        for field in [20u16, 0x0800, 8, time, date] {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [crc, compressed as u32, size as u32] {
+            out.extend_from_slice(&field.to_le_bytes());
+        }
+        out.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+    };
+
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in files {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let offset = out.len() as u32;
+
+        out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        header(&mut out, crc.sum(), compressed.len(), data.len(), name);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&compressed);
+
+        directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        header(&mut directory, crc.sum(), compressed.len(), data.len(), name);
+        // Comment length, disk, internal and external attributes
+        directory.extend_from_slice(&[0; 10]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = out.len() as u32;
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+    out.extend_from_slice(&directory_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_note() {
+        let vault = std::env::temp_dir().join(format!("robsidian_share_{}", std::process::id()));
+        std::fs::create_dir_all(vault.join("img")).unwrap();
+        std::fs::write(vault.join("img/dot.png"), b"\x89PNG").unwrap();
+        std::fs::write(vault.join("Part.md"), "# Intro\nskipped\n## Steps\nDo it ![[Main]]\n# End\n").unwrap();
+        std::fs::write(vault.join("Main.md"), "Start\n![[Part#Steps]]\n![[dot.png|50]]\n").unwrap();
+        let notes = NoteIndex::build(&vault);
+        let doc = Document::open(&vault.join("Main.md")).unwrap();
+
+        let bundle = Bundle::collect(&doc, &vault, &notes);
+        assert_eq!(bundle.markdown, "Start\n\n## Steps\nDo it [[Main]]\n\n![](<attachments/dot.png>)\n");
+        assert_eq!(bundle.attachments, vec![("dot.png".to_string(), vault.join("img/dot.png"))]);
+
+        let html = String::from_utf8(share_note(&doc, &vault, &notes, ShareFormat::Html).unwrap()).unwrap();
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw==\""));
+        let zip = share_note(&doc, &vault, &notes, ShareFormat::Zip).unwrap();
+        assert!(zip.starts_with(b"PK\x03\x04"));
+        assert!(zip.windows(19).any(|w| w == b"attachments/dot.png"));
+
+        std::fs::remove_dir_all(&vault).ok();
+    }
+
+    #[test]
+    fn test_share_leaves_outside_files() {
+        let root = std::env::temp_dir().join(format!("robsidian_share_outside_{}", std::process::id()));
+        let vault = root.join("Vault");
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(root.join("key.png"), b"secret").unwrap();
+        let absolute = root.join("key.png").to_string_lossy().into_owned();
+        std::fs::write(vault.join("Main.md"), format!("![](../key.png)\n![]({absolute})\n")).unwrap();
+        let notes = NoteIndex::build(&vault);
+        let doc = Document::open(&vault.join("Main.md")).unwrap();
+
+        let bundle = Bundle::collect(&doc, &vault, &notes);
+        assert!(bundle.attachments.is_empty());
+        let zip = share_note(&doc, &vault, &notes, ShareFormat::Zip).unwrap();
+        assert!(!zip.windows(6).any(|w| w == b"secret"));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
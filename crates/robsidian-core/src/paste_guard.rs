@@ -0,0 +1,117 @@
+//! Large paste guard
+//!
+//! Pasting a megabyte log or a source file as markdown makes every frame
+//! re-parse one enormous paragraph. Pastes that are very large or look like
+//! code are held back so the user can fence them instead.
+
+/// Pastes larger than this are always held back
+pub const LARGE_PASTE_BYTES: usize = 64 * 1024;
+
+/// Lines a paste needs before it can look like code
+const MIN_CODE_LINES: usize = 3;
+
+/// Whether a paste should be confirmed before it's inserted
+pub fn needs_confirmation(text: &str) -> bool {
+    text.len() > LARGE_PASTE_BYTES || looks_like_code(text)
+}
+
+/// Whether a line looks like a log entry (starts with a date, time or level)
+fn is_log_line(line: &str) -> bool {
+    let line = line.trim_start_matches('[');
+    let digits = line.chars().take(4).filter(char::is_ascii_digit).count();
+    let dated = digits == 4 && matches!(line.chars().nth(4), Some('-' | '/'));
+    let timed = line.len() > 8 && line.as_bytes()[2] == b':' && line.as_bytes()[5] == b':';
+    let level = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"].iter().any(|level| line.starts_with(level));
+    dated || timed || level
+}
+
+/// Whether a line looks like source code
+fn is_code_line(line: &str) -> bool {
+    let trimmed = line.trim_end();
+    trimmed.ends_with([';', '{', '}', ')', ','])
+        || trimmed.starts_with("    ")
+        || trimmed.starts_with('\t')
+        || trimmed.contains(" => ")
+        || trimmed.contains("::")
+}
+
+/// Whether most lines of a paste look like code or log output. Markdown
+/// lists, quotes and tables are never code.
+pub fn looks_like_code(text: &str) -> bool {
+    if text.trim_start().starts_with("```") {
+        return false;
+    }
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.len() < MIN_CODE_LINES {
+        return false;
+    }
+    let markdown = lines
+        .iter()
+        .filter(|line| {
+            let line = line.trim_start();
+            line.starts_with(['#', '>', '|']) || line.starts_with("- ") || line.starts_with("* ")
+        })
+        .count();
+    let code = lines.iter().filter(|line| is_code_line(line) || is_log_line(line)).count();
+    markdown * 4 < lines.len() && code * 2 >= lines.len()
+}
+
+/// Best guess at the language of pasted code, for the fence's info string
+pub fn guess_language(text: &str) -> &'static str {
+    let trimmed = text.trim_start();
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    let has = |needle: &str| text.contains(needle);
+
+    if trimmed.starts_with(['{', '[']) && serde_json::from_str::<serde_json::Value>(text).is_ok() {
+        "json"
+    } else if trimmed.starts_with("#!") && (has("bash") || has("/sh")) {
+        "bash"
+    } else if trimmed.starts_with('<') {
+        "html"
+    } else if has("fn ") && (has("let ") || has("::") || has("->")) || has("impl ") || has("pub struct ") {
+        "rust"
+    } else if has("def ") && has(":\n") || trimmed.starts_with("import ") && !has(";") {
+        "python"
+    } else if has("function ") || has("const ") && has(" => ") || has("console.log") {
+        "javascript"
+    } else if has("package main") || has("func ") && has(":= ") {
+        "go"
+    } else if ["SELECT ", "INSERT INTO ", "CREATE TABLE "].iter().any(|kw| text.to_uppercase().contains(kw)) {
+        "sql"
+    } else if lines.iter().filter(|line| is_log_line(line)).count() * 2 >= lines.len() {
+        "log"
+    } else {
+        ""
+    }
+}
+
+/// Wrap text in a fenced code block, with a fence longer than any run of
+/// backticks inside it
+pub fn fence(text: &str, lang: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, lang.trim(), text.trim_end_matches('\n'), fence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_guard() {
+        let rust = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+        assert!(needs_confirmation(rust));
+        assert_eq!(guess_language(rust), "rust");
+
+        let log = "2024-05-01 12:00:01 INFO started\n2024-05-01 12:00:02 WARN slow\n2024-05-01 12:00:03 ERROR down\n";
+        assert!(looks_like_code(log));
+        assert_eq!(guess_language(log), "log");
+        assert_eq!(guess_language("{\"a\": [1, 2]}"), "json");
+
+        assert!(!needs_confirmation("# Title\n\n- one;\n- two;\n- three;\n"));
+        assert!(!needs_confirmation("A short sentence."));
+        assert!(needs_confirmation(&"word ".repeat(LARGE_PASTE_BYTES)));
+
+        assert_eq!(fence("a ``` b", "md"), "````md\na ``` b\n````\n");
+    }
+}
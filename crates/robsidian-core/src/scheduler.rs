@@ -0,0 +1,252 @@
+//! Background work within a frame budget
+//!
+//! Work that would stall a frame, like building the note index, goes through
+//! the [`Scheduler`]. Jobs run on worker threads fed by a bounded queue and
+//! hand back a closure that applies their result on the UI thread. Smaller
+//! tasks that need the app itself run there in slices. Each frame the app
+//! takes work until [`FRAME_BUDGET`] is spent and leaves the rest for the
+//! next frame, so work piling up slows itself down instead of the UI.
+//!
+//! Every task is timed by name; the diagnostics window shows the timings.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Time per frame given to scheduled work on the UI thread
+pub const FRAME_BUDGET: Duration = Duration::from_millis(4);
+
+/// Number of worker threads
+const WORKERS: usize = 2;
+
+/// Jobs waiting for a worker before new ones are held back
+const QUEUE_SIZE: usize = 16;
+
+/// Applies the result of a job to the context, on the UI thread
+pub type Apply<C> = Box<dyn FnOnce(&mut C) + Send>;
+
+type Job<C> = (String, Box<dyn FnOnce() -> Apply<C> + Send>);
+
+/// A piece of work taken from the scheduler, see [`Scheduler::next_work`]
+pub enum Work<C> {
+    /// The result of a finished job
+    Apply { name: String, apply: Apply<C> },
+    /// One slice of a foreground task
+    Slice { name: String, step: Box<dyn FnMut(&mut C) -> bool> },
+}
+
+impl<C> Work<C> {
+    /// Run the work; false if a slice has more to do
+    pub fn run(&mut self, context: &mut C) -> bool {
+        match self {
+            Work::Apply { apply, .. } => {
+                let apply = std::mem::replace(apply, Box::new(|_| {}));
+                apply(context);
+                true
+            }
+            Work::Slice { step, .. } => step(context),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Work::Apply { name, .. } | Work::Slice { name, .. } => name,
+        }
+    }
+}
+
+/// Timings of one kind of task
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskStats {
+    /// Jobs finished on worker threads
+    pub jobs: u64,
+    /// Times the task ran on the UI thread
+    pub runs: u64,
+    /// Time spent on worker threads
+    pub worker_time: Duration,
+    /// Time spent on the UI thread
+    pub frame_time: Duration,
+    /// Longest single run on the UI thread
+    pub longest: Duration,
+}
+
+/// Runs deferred work on worker threads and in per-frame slices
+pub struct Scheduler<C> {
+    jobs: SyncSender<Job<C>>,
+    /// Jobs that didn't fit in the queue yet
+    held: VecDeque<Job<C>>,
+    results: Receiver<(String, Duration, Apply<C>)>,
+    /// Jobs handed to the workers and not back yet
+    running: usize,
+    slices: VecDeque<Work<C>>,
+    stats: BTreeMap<String, TaskStats>,
+    frames: u64,
+    frames_over_budget: u64,
+    last_frame: Duration,
+}
+
+impl<C: 'static> Default for Scheduler<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: 'static> Scheduler<C> {
+    /// Start the worker threads
+    pub fn new() -> Self {
+        let (jobs_tx, jobs_rx) = mpsc::sync_channel::<Job<C>>(QUEUE_SIZE);
+        let (results_tx, results_rx) = mpsc::channel();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+
+        for _ in 0..WORKERS {
+            let jobs_rx = Arc::clone(&jobs_rx);
+            let results_tx = results_tx.clone();
+            thread::spawn(move || loop {
+                let job = jobs_rx.lock().unwrap().recv();
+                let Ok((name, work)) = job else {
+                    break;
+                };
+                let started = Instant::now();
+                let apply = work();
+                if results_tx.send((name, started.elapsed(), apply)).is_err() {
+                    break;
+                }
+            });
+        }
+
+        Self {
+            jobs: jobs_tx,
+            held: VecDeque::new(),
+            results: results_rx,
+            running: 0,
+            slices: VecDeque::new(),
+            stats: BTreeMap::new(),
+            frames: 0,
+            frames_over_budget: 0,
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    /// Run a job on a worker thread; the closure it returns is applied on the
+    /// UI thread
+    pub fn spawn(&mut self, name: &str, work: impl FnOnce() -> Apply<C> + Send + 'static) {
+        self.held.push_back((name.to_string(), Box::new(work)));
+        self.send_held();
+    }
+
+    /// Run a task on the UI thread, one slice at a time, until it returns true
+    pub fn defer(&mut self, name: &str, step: impl FnMut(&mut C) -> bool + 'static) {
+        self.slices.push_back(Work::Slice { name: name.to_string(), step: Box::new(step) });
+    }
+
+    fn send_held(&mut self) {
+        while let Some(job) = self.held.pop_front() {
+            match self.jobs.try_send(job) {
+                Ok(()) => self.running += 1,
+                Err(TrySendError::Full(job) | TrySendError::Disconnected(job)) => {
+                    self.held.push_front(job);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Take the next piece of work: finished jobs first, then slices
+    pub fn next_work(&mut self) -> Option<Work<C>> {
+        if let Ok((name, elapsed, apply)) = self.results.try_recv() {
+            self.running -= 1;
+            let stats = self.stats.entry(name.clone()).or_default();
+            stats.jobs += 1;
+            stats.worker_time += elapsed;
+            self.send_held();
+            return Some(Work::Apply { name, apply });
+        }
+        self.send_held();
+        self.slices.pop_front()
+    }
+
+    /// Record how long work took; slices that have more to do go back in line
+    pub fn finish(&mut self, work: Work<C>, elapsed: Duration, finished: bool) {
+        let stats = self.stats.entry(work.name().to_string()).or_default();
+        stats.runs += 1;
+        stats.frame_time += elapsed;
+        stats.longest = stats.longest.max(elapsed);
+        if !finished {
+            self.slices.push_back(work);
+        }
+    }
+
+    /// Record the time a frame spent on scheduled work
+    pub fn end_frame(&mut self, elapsed: Duration) {
+        self.frames += 1;
+        self.last_frame = elapsed;
+        if elapsed > FRAME_BUDGET {
+            self.frames_over_budget += 1;
+        }
+    }
+
+    /// Whether any work is waiting or running
+    pub fn is_busy(&self) -> bool {
+        self.running > 0 || !self.held.is_empty() || !self.slices.is_empty()
+    }
+
+    /// Timings by task name
+    pub fn stats(&self) -> &BTreeMap<String, TaskStats> {
+        &self.stats
+    }
+
+    /// Jobs on worker threads, jobs held back, and slices waiting
+    pub fn queued(&self) -> (usize, usize, usize) {
+        (self.running, self.held.len(), self.slices.len())
+    }
+
+    /// Frames that ran scheduled work, and how many went over the budget
+    pub fn frames(&self) -> (u64, u64) {
+        (self.frames, self.frames_over_budget)
+    }
+
+    /// Time the last frame spent on scheduled work
+    pub fn last_frame(&self) -> Duration {
+        self.last_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler() {
+        let mut scheduler = Scheduler::<Vec<u32>>::new();
+        for n in 0..QUEUE_SIZE as u32 * 2 {
+            scheduler.spawn("job", move || Box::new(move |out: &mut Vec<u32>| out.push(n)));
+        }
+        let mut left = 3;
+        scheduler.defer("slices", move |out: &mut Vec<u32>| {
+            out.push(100);
+            left -= 1;
+            left == 0
+        });
+        assert!(scheduler.is_busy());
+
+        let mut out = Vec::new();
+        for _ in 0..1000 {
+            if !scheduler.is_busy() {
+                break;
+            }
+            while let Some(mut work) = scheduler.next_work() {
+                let finished = work.run(&mut out);
+                scheduler.finish(work, Duration::ZERO, finished);
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        assert!(!scheduler.is_busy());
+        assert_eq!(out.len(), QUEUE_SIZE * 2 + 3);
+        assert_eq!(out.iter().filter(|&&n| n == 100).count(), 3);
+        assert_eq!(scheduler.stats()["job"].jobs, QUEUE_SIZE as u64 * 2);
+        assert_eq!(scheduler.stats()["slices"].runs, 3);
+    }
+}
@@ -0,0 +1,269 @@
+//! Settings bundles, to move a setup to another machine
+//!
+//! A bundle is one JSON file holding the app's settings, the terminal theme
+//! files, the user scripts and the list of enabled plugins. The plugins
+//! themselves aren't included; they are installed again on the other
+//! machine. Settings tied to this machine (vault and folder paths, the
+//! shell, the external editor, SSH hosts and their keys), credentials
+//! (tokens, passwords, sync servers, the shell's environment) and the
+//! terminal's clipboard access stay out of the bundle and are kept as they
+//! are when one is imported.
+//!
+//! Robsidian has no configurable keymap or snippets yet, so there are none
+//! to export.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::config::AppConfig;
+
+/// Version of the bundle format written
+const BUNDLE_VERSION: u32 = 1;
+
+/// A part of a bundle that can be imported on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundlePart {
+    Settings,
+    TerminalThemes,
+    Scripts,
+    Plugins,
+}
+
+impl BundlePart {
+    pub const ALL: [BundlePart; 4] =
+        [BundlePart::Settings, BundlePart::TerminalThemes, BundlePart::Scripts, BundlePart::Plugins];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BundlePart::Settings => "Settings",
+            BundlePart::TerminalThemes => "Terminal themes",
+            BundlePart::Scripts => "User scripts",
+            BundlePart::Plugins => "Enabled plugins",
+        }
+    }
+}
+
+/// The settings of a setup, as exported to one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingsBundle {
+    pub version: u32,
+    /// App settings, without this machine's paths and the credentials
+    pub config: AppConfig,
+    /// Theme files by file name
+    #[serde(default)]
+    pub terminal_themes: BTreeMap<String, String>,
+    /// `.rhai` scripts by file name
+    #[serde(default)]
+    pub scripts: BTreeMap<String, String>,
+    /// Ids of the enabled plugins
+    #[serde(default)]
+    pub enabled_plugins: Vec<String>,
+}
+
+impl SettingsBundle {
+    /// Gather `config`, the theme files in `theme_dir` and the scripts in
+    /// `script_dir`
+    pub fn collect(config: &AppConfig, theme_dir: &Path, script_dir: &Path) -> Result<Self> {
+        let mut portable = config.clone();
+        keep_local(&mut portable, &AppConfig::default());
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            config: portable,
+            terminal_themes: read_files(theme_dir, "json")?,
+            scripts: read_files(script_dir, "rhai")?,
+            enabled_plugins: config.plugins.enabled_plugins.clone(),
+        })
+    }
+
+    /// Read a bundle written by [`SettingsBundle::write`]
+    pub fn read(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings bundle: {}", path.display()))?;
+        let bundle: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Not a settings bundle: {}", path.display()))?;
+        if bundle.version > BUNDLE_VERSION {
+            bail!("The settings bundle is from a newer version of Robsidian");
+        }
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write settings bundle: {}", path.display()))
+    }
+
+    /// Parts the bundle has something for
+    pub fn parts(&self) -> Vec<BundlePart> {
+        BundlePart::ALL
+            .into_iter()
+            .filter(|part| match part {
+                BundlePart::Settings => true,
+                BundlePart::TerminalThemes => !self.terminal_themes.is_empty(),
+                BundlePart::Scripts => !self.scripts.is_empty(),
+                BundlePart::Plugins => !self.enabled_plugins.is_empty(),
+            })
+            .collect()
+    }
+
+    /// Import `parts` into `config` and the theme and script folders. Files
+    /// of the same name are replaced; others are left alone.
+    pub fn apply(
+        &self,
+        parts: &[BundlePart],
+        config: &mut AppConfig,
+        theme_dir: &Path,
+        script_dir: &Path,
+    ) -> Result<()> {
+        for part in parts {
+            match part {
+                BundlePart::Settings => {
+                    let local = std::mem::replace(config, self.config.clone());
+                    keep_local(config, &local);
+                }
+                BundlePart::TerminalThemes => write_files(theme_dir, &self.terminal_themes)?,
+                BundlePart::Scripts => write_files(script_dir, &self.scripts)?,
+                BundlePart::Plugins => {
+                    for id in &self.enabled_plugins {
+                        if !config.plugins.enabled_plugins.contains(id) {
+                            config.plugins.enabled_plugins.push(id.clone());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Put the settings of `local` that don't travel between machines back into
+/// `config`: paths, credentials, the shell and its environment, and what
+/// terminal programs may do with the clipboard, which a bundle from
+/// elsewhere must not be able to widen
+fn keep_local(config: &mut AppConfig, local: &AppConfig) {
+    config.last_vault = local.last_vault.clone();
+    config.recent_vaults = local.recent_vaults.clone();
+    config.second_vault = local.second_vault.clone();
+    config.plugins = local.plugins.clone();
+    config.history.snapshot_dir = local.history.snapshot_dir.clone();
+    config.web_server.token = local.web_server.token.clone();
+    config.publish = local.publish.clone();
+    config.sync.remote = local.sync.remote.clone();
+    config.editor.external_command = local.editor.external_command.clone();
+    config.terminal.shell = local.terminal.shell.clone();
+    config.terminal.args = local.terminal.args.clone();
+    config.terminal.env = local.terminal.env.clone();
+    config.terminal.ssh_hosts = local.terminal.ssh_hosts.clone();
+    config.terminal.clipboard = local.terminal.clipboard;
+}
+
+/// Files of a folder with the extension, by name; none if it doesn't exist
+fn read_files(dir: &Path, extension: &str) -> Result<BTreeMap<String, String>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(BTreeMap::new());
+    };
+    let mut files = BTreeMap::new();
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != extension) {
+            continue;
+        }
+        let content =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        files.insert(path.file_name().unwrap_or_default().to_string_lossy().into_owned(), content);
+    }
+    Ok(files)
+}
+
+/// Write files into a folder by name. Names that would reach outside of it
+/// are refused.
+fn write_files(dir: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for (name, content) in files {
+        if Path::new(name).file_name().is_none_or(|file_name| file_name != name.as_str()) {
+            bail!("Refusing to write {:?} from the settings bundle", name);
+        }
+        let path = dir.join(name);
+        std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ClipboardAccess, SshHost};
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("robsidian_bundle_{}", std::process::id()));
+        let (themes, scripts) = (dir.join("themes"), dir.join("scripts"));
+        std::fs::create_dir_all(&themes).unwrap();
+        std::fs::write(themes.join("nord.json"), "{}").unwrap();
+        std::fs::write(themes.join("notes.txt"), "not a theme").unwrap();
+
+        let mut config = AppConfig { last_vault: Some("/home/me/Notes".into()), ..Default::default() };
+        config.web_server.token = "secret".to_string();
+        config.editor.font_size = 18.0;
+        config.plugins.enabled_plugins = vec!["word-count".to_string()];
+        let bundle = SettingsBundle::collect(&config, &themes, &scripts).unwrap();
+        assert_eq!(bundle.terminal_themes.keys().collect::<Vec<_>>(), ["nord.json"]);
+        assert!(bundle.config.last_vault.is_none());
+        assert!(bundle.config.web_server.token.is_empty());
+        assert_eq!(bundle.parts(), [BundlePart::Settings, BundlePart::TerminalThemes, BundlePart::Plugins]);
+
+        let path = dir.join("bundle.json");
+        bundle.write(&path).unwrap();
+        let bundle = SettingsBundle::read(&path).unwrap();
+
+        // Another machine keeps its vault and token, and gets only the
+        // picked parts
+        let mut other = AppConfig { last_vault: Some("/Users/me/Notes".into()), ..Default::default() };
+        other.web_server.token = "other".to_string();
+        let other_themes = dir.join("other_themes");
+        bundle.apply(&[BundlePart::Settings, BundlePart::Plugins], &mut other, &other_themes, &scripts).unwrap();
+        assert_eq!(other.editor.font_size, 18.0);
+        assert_eq!(other.last_vault, Some("/Users/me/Notes".into()));
+        assert_eq!(other.web_server.token, "other");
+        assert_eq!(other.plugins.enabled_plugins, ["word-count"]);
+        assert!(!other_themes.exists());
+
+        let mut sneaky = bundle.clone();
+        sneaky.terminal_themes.insert("../escape.json".to_string(), "{}".to_string());
+        assert!(sneaky.apply(&[BundlePart::TerminalThemes], &mut other, &other_themes, &scripts).is_err());
+        assert!(!dir.join("escape.json").exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_bundle_keeps_machine_settings() {
+        let dir = std::env::temp_dir().join(format!("robsidian_bundle_local_{}", std::process::id()));
+        let mut config = AppConfig::default();
+        config.terminal.env.insert("OPENAI_API_KEY".to_string(), "sk-secret".to_string());
+        config.terminal.ssh_hosts.push(SshHost {
+            host: "example.com".to_string(),
+            identity_file: Some("/home/me/.ssh/id_ed25519".into()),
+            ..Default::default()
+        });
+        config.terminal.scrollback_lines = 5_000;
+        config.terminal.clipboard = ClipboardAccess::ReadWrite;
+        let bundle = SettingsBundle::collect(&config, &dir, &dir).unwrap();
+        assert!(bundle.config.terminal.env.is_empty());
+        assert!(bundle.config.terminal.ssh_hosts.is_empty());
+        assert_eq!(bundle.config.terminal.clipboard, ClipboardAccess::Off);
+
+        // Even a bundle edited to ask for it can't open up the clipboard
+        let mut sneaky = bundle.clone();
+        sneaky.config.terminal.clipboard = ClipboardAccess::ReadWrite;
+        let mut other = AppConfig::default();
+        other.terminal.env.insert("EDITOR".to_string(), "nvim".to_string());
+        sneaky.apply(&[BundlePart::Settings], &mut other, &dir, &dir).unwrap();
+        assert_eq!(other.terminal.scrollback_lines, 5_000);
+        assert_eq!(other.terminal.clipboard, ClipboardAccess::Off);
+        assert_eq!(other.terminal.env.get("EDITOR").map(String::as_str), Some("nvim"));
+    }
+}
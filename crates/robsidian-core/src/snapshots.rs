@@ -0,0 +1,170 @@
+//! Local note snapshots
+//!
+//! Every save keeps a copy of the note in the app data folder, independent of
+//! any version control in the vault. Each note gets its own folder (named
+//! after a hash of its path) holding one file per snapshot, named by the
+//! save time in milliseconds. Next to each snapshot after the first, a
+//! `.diff.json` file keeps the words that changed since the one before.
+//! Old snapshots are pruned by count and age.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeZone};
+
+use super::config::HistoryConfig;
+use super::diff::{diff_words, WordChange};
+
+/// A stored version of a note
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub time: DateTime<Local>,
+    file: PathBuf,
+}
+
+impl Snapshot {
+    /// Content of the note at the time of the snapshot
+    pub fn read(&self) -> Result<String> {
+        std::fs::read_to_string(&self.file)
+            .with_context(|| format!("Failed to read snapshot: {}", self.file.display()))
+    }
+
+    /// Words that changed since the previous snapshot; `None` for the first
+    /// one, or one taken before changes were recorded
+    pub fn changes(&self) -> Option<Vec<WordChange>> {
+        let json = std::fs::read_to_string(self.changes_file()).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn changes_file(&self) -> PathBuf {
+        self.file.with_extension("diff.json")
+    }
+}
+
+/// Snapshot folder with its retention limits
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+    max_snapshots: usize,
+    max_age_days: u64,
+}
+
+impl SnapshotStore {
+    pub fn new(dir: PathBuf, config: &HistoryConfig) -> Self {
+        Self {
+            dir,
+            max_snapshots: config.max_snapshots,
+            max_age_days: config.max_age_days,
+        }
+    }
+
+    /// Folder holding the snapshots of a note
+    fn note_dir(&self, note: &Path) -> PathBuf {
+        // FNV-1a, so the folder name stays the same across builds
+        let hash = note
+            .to_string_lossy()
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+        self.dir.join(format!("{:016x}", hash))
+    }
+
+    /// Snapshots of a note, newest first
+    pub fn list(&self, note: &Path) -> Vec<Snapshot> {
+        let Ok(entries) = std::fs::read_dir(self.note_dir(note)) else {
+            return Vec::new();
+        };
+        let mut snapshots: Vec<(i64, Snapshot)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file = entry.path();
+                let millis: i64 = file.file_stem()?.to_str()?.parse().ok()?;
+                let time = Local.timestamp_millis_opt(millis).single()?;
+                Some((millis, Snapshot { time, file }))
+            })
+            .collect();
+        snapshots.sort_by_key(|(millis, _)| std::cmp::Reverse(*millis));
+        snapshots.into_iter().map(|(_, snapshot)| snapshot).collect()
+    }
+
+    /// Delete every snapshot of a note, as when it gets encrypted
+    pub fn purge(&self, note: &Path) -> Result<()> {
+        let dir = self.note_dir(note);
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete snapshots: {}", dir.display()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Store a snapshot of a note's content, with the words that changed
+    /// since the newest one, unless it matches that, then prune old
+    /// snapshots
+    pub fn record(&self, note: &Path, content: &str) -> Result<()> {
+        let snapshots = self.list(note);
+        let previous = snapshots.first().and_then(|s| s.read().ok());
+        if previous.as_deref() == Some(content) {
+            return Ok(());
+        }
+
+        let dir = self.note_dir(note);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create snapshot folder: {}", dir.display()))?;
+        let now = Local::now();
+        let file = dir.join(format!("{}.md", now.timestamp_millis()));
+        std::fs::write(&file, content)
+            .with_context(|| format!("Failed to write snapshot: {}", file.display()))?;
+        if let Some(previous) = previous {
+            let snapshot = Snapshot { time: now, file };
+            let changes = serde_json::to_string(&diff_words(&previous, content))?;
+            std::fs::write(snapshot.changes_file(), changes)
+                .with_context(|| format!("Failed to write snapshot changes: {}", snapshot.file.display()))?;
+        }
+
+        // The new snapshot is always kept
+        let max_age = chrono::Duration::days(self.max_age_days as i64);
+        for (idx, snapshot) in snapshots.iter().enumerate() {
+            let too_many = self.max_snapshots > 0 && idx + 1 >= self.max_snapshots;
+            let too_old = self.max_age_days > 0 && now - snapshot.time > max_age;
+            if too_many || too_old {
+                let _ = std::fs::remove_file(&snapshot.file);
+                let _ = std::fs::remove_file(snapshot.changes_file());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_prune() {
+        let dir = std::env::temp_dir().join(format!("robsidian_snapshots_{}", std::process::id()));
+        let config = HistoryConfig {
+            max_snapshots: 2,
+            ..Default::default()
+        };
+        let store = SnapshotStore::new(dir.clone(), &config);
+        let note = Path::new("/vault/Note.md");
+
+        for content in ["one", "one", "two", "three"] {
+            store.record(note, content).unwrap();
+            // Snapshot names have millisecond resolution
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let contents: Vec<String> = store.list(note).iter().map(|s| s.read().unwrap()).collect();
+        assert_eq!(contents, vec!["three", "two"]);
+        let changes = store.list(note)[0].changes().unwrap();
+        assert_eq!((changes[0].removed.as_str(), changes[0].added.as_str()), ("two", "three"));
+        // Pruned snapshots take their changes along: two snapshots, each
+        // with its changes, are left
+        assert_eq!(std::fs::read_dir(store.note_dir(note)).unwrap().count(), 4);
+        store.purge(note).unwrap();
+        assert!(store.list(note).is_empty());
+        store.purge(note).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
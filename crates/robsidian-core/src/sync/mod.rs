@@ -0,0 +1,672 @@
+//! Vault sync
+//!
+//! Keeps a vault in step with a WebDAV server or an S3 bucket. Every run
+//! compares three views of each file: its content hash on disk, its version
+//! (ETag) on the remote, and both as they were after the last sync, kept in
+//! `.robsidian/sync.json`. A file changed on one side only is copied to the
+//! other, a file deleted on one side only is deleted on the other, and a file
+//! changed on both sides is a conflict settled by the configured
+//! [`ConflictStrategy`]. Local deletions move files to the vault's trash.
+//! Paths the remote lists that would reach outside the vault are skipped.
+//!
+//! Passwords and secret keys aren't written to the settings file; they are
+//! kept in the system's credential store.
+
+pub mod s3;
+pub mod webdav;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use super::config::SyncConfig;
+use super::file_system::unique_path;
+use super::inbox::TRASH_FOLDER;
+use super::vault_settings::SETTINGS_DIR;
+
+/// File holding the state of the last sync, inside the settings folder
+const STATE_FILE: &str = "sync.json";
+
+/// Service the remotes' secrets are kept under in the credential store
+const KEYRING_SERVICE: &str = "robsidian-sync";
+
+/// A file on the remote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteFile {
+    /// Changes whenever the content does (the ETag where available)
+    pub version: String,
+}
+
+/// Storage a vault is synced with. Paths are relative to the vault and use
+/// `/` separators.
+pub trait Remote {
+    /// Every file on the remote
+    fn list(&mut self) -> Result<HashMap<String, RemoteFile>>;
+    fn get(&mut self, path: &str) -> Result<Vec<u8>>;
+    /// Upload a file, returning its new version if the remote reports it
+    fn put(&mut self, path: &str, data: &[u8]) -> Result<Option<String>>;
+    fn delete(&mut self, path: &str) -> Result<()>;
+}
+
+/// Where a vault is synced to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum RemoteConfig {
+    WebDav {
+        /// Folder on the server holding the vault
+        url: String,
+        username: String,
+        /// Kept in the credential store, see [`RemoteConfig::store_secret`]
+        #[serde(default, skip_serializing)]
+        password: String,
+    },
+    S3 {
+        /// Service address, e.g. `https://s3.eu-west-1.amazonaws.com`
+        endpoint: String,
+        region: String,
+        bucket: String,
+        /// Key prefix the vault is stored under
+        prefix: String,
+        access_key: String,
+        /// Kept in the credential store, see [`RemoteConfig::store_secret`]
+        #[serde(default, skip_serializing)]
+        secret_key: String,
+    },
+}
+
+impl RemoteConfig {
+    /// Identifies the remote; the sync state is dropped when it changes
+    fn id(&self) -> String {
+        match self {
+            RemoteConfig::WebDav { url, username, .. } => format!("webdav:{}@{}", username, url),
+            RemoteConfig::S3 { endpoint, bucket, prefix, .. } => format!("s3:{}/{}/{}", endpoint, bucket, prefix),
+        }
+    }
+
+    /// The password or secret key
+    fn secret(&self) -> &str {
+        match self {
+            RemoteConfig::WebDav { password, .. } => password,
+            RemoteConfig::S3 { secret_key, .. } => secret_key,
+        }
+    }
+
+    fn keyring_entry(&self) -> Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, &self.id()).context("Failed to reach the credential store")
+    }
+
+    /// Keep the password or secret key in the system's credential store,
+    /// or remove it from there when it is empty
+    pub fn store_secret(&self) -> Result<()> {
+        let entry = self.keyring_entry()?;
+        let result = if self.secret().is_empty() {
+            match entry.delete_credential() {
+                Err(keyring::Error::NoEntry) => Ok(()),
+                result => result,
+            }
+        } else {
+            entry.set_password(self.secret())
+        };
+        result.context("Failed to save the sync password in the credential store")
+    }
+
+    /// Fill in the password or secret key from the credential store. One
+    /// read from settings written by older versions is moved there instead,
+    /// returning true so the settings are saved without it.
+    pub fn load_secret(&mut self) -> Result<bool> {
+        if !self.secret().is_empty() {
+            return self.store_secret().map(|_| true);
+        }
+        let secret = match self.keyring_entry()?.get_password() {
+            Err(keyring::Error::NoEntry) => return Ok(false),
+            secret => secret.context("Failed to read the sync password from the credential store")?,
+        };
+        match self {
+            RemoteConfig::WebDav { password, .. } => *password = secret,
+            RemoteConfig::S3 { secret_key, .. } => *secret_key = secret,
+        }
+        Ok(false)
+    }
+
+    fn connect(&self) -> Result<Box<dyn Remote>> {
+        Ok(match self {
+            RemoteConfig::WebDav { url, username, password } => {
+                Box::new(webdav::WebDavRemote::new(url, username, password)?)
+            }
+            RemoteConfig::S3 { endpoint, region, bucket, prefix, access_key, secret_key } => Box::new(
+                s3::S3Remote::new(endpoint, region, bucket, prefix, access_key, secret_key)?,
+            ),
+        })
+    }
+}
+
+/// How a file changed on both sides is settled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Keep the local file and save the remote one next to it as a
+    /// conflict copy
+    #[default]
+    KeepBoth,
+    PreferLocal,
+    PreferRemote,
+}
+
+impl ConflictStrategy {
+    pub const ALL: [ConflictStrategy; 3] =
+        [ConflictStrategy::KeepBoth, ConflictStrategy::PreferLocal, ConflictStrategy::PreferRemote];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ConflictStrategy::KeepBoth => "Keep both",
+            ConflictStrategy::PreferLocal => "Prefer this device",
+            ConflictStrategy::PreferRemote => "Prefer the remote",
+        }
+    }
+}
+
+/// A file as it was after the last sync
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FileState {
+    /// Content hash on disk
+    hash: String,
+    /// Version on the remote
+    version: String,
+    /// Modification time and size on disk, to skip rehashing unchanged files
+    modified: u128,
+    size: u64,
+}
+
+/// State of the last sync of a vault
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    remote: String,
+    files: BTreeMap<String, FileState>,
+}
+
+impl SyncState {
+    fn path(vault: &Path) -> PathBuf {
+        vault.join(SETTINGS_DIR).join(STATE_FILE)
+    }
+
+    /// The state of the last sync with `remote` (empty if there was none)
+    fn load(vault: &Path, remote: &str) -> Self {
+        let state: Self = std::fs::read_to_string(Self::path(vault))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        if state.remote == remote {
+            state
+        } else {
+            Self { remote: remote.to_string(), files: BTreeMap::new() }
+        }
+    }
+
+    fn save(&self, vault: &Path) -> Result<()> {
+        let path = Self::path(vault);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to save sync state: {}", path.display()))
+    }
+}
+
+fn hash(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// A file on disk
+struct LocalFile {
+    hash: String,
+    modified: u128,
+    size: u64,
+}
+
+/// Whether a vault path takes part in sync: hidden files and folders are
+/// skipped, except for the vault settings
+fn is_synced(rel: &str) -> bool {
+    rel != format!("{}/{}", SETTINGS_DIR, STATE_FILE)
+        && rel.split('/').all(|part| !part.starts_with('.') || part == SETTINGS_DIR)
+}
+
+/// Whether a path the remote lists stays inside the vault: every part a
+/// plain name, without backslashes or a drive letter
+fn is_inside_vault(rel: &str) -> bool {
+    let drive = rel.as_bytes().get(1) == Some(&b':') && rel.as_bytes()[0].is_ascii_alphabetic();
+    !drive
+        && !rel.contains('\\')
+        && rel.split('/').all(|part| {
+            let mut components = Path::new(part).components();
+            matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+        })
+}
+
+/// Every synced file of the vault, hashing only those changed since the
+/// last sync
+fn scan_local(vault: &Path, state: &SyncState) -> Result<HashMap<String, LocalFile>> {
+    let mut files = HashMap::new();
+    for entry in WalkDir::new(vault).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(rel) = entry.path().strip_prefix(vault) else {
+            continue;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        if !is_synced(&rel) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |time| time.as_nanos());
+        let size = metadata.len();
+        let hash = match state.files.get(&rel) {
+            Some(known) if known.modified == modified && known.size == size => known.hash.clone(),
+            _ => hash(&std::fs::read(entry.path())?),
+        };
+        files.insert(rel, LocalFile { hash, modified, size });
+    }
+    Ok(files)
+}
+
+/// What a sync run does with a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncAction {
+    Upload(String),
+    Download(String),
+    DeleteLocal(String),
+    DeleteRemote(String),
+    /// Changed on both sides (or on both sides for the first time)
+    Conflict(String),
+    /// Gone on both sides; only the state entry is dropped
+    Forget(String),
+}
+
+/// Work out what to do with every file, from the local hashes and remote
+/// versions compared with the last sync
+fn plan(local: &HashMap<String, String>, remote: &HashMap<String, String>, state: &SyncState) -> Vec<SyncAction> {
+    let paths: BTreeSet<&String> = local.keys().chain(remote.keys()).chain(state.files.keys()).collect();
+    let mut actions = Vec::new();
+    for path in paths {
+        let known = state.files.get(path);
+        let local_changed = local.get(path) != known.map(|k| &k.hash);
+        let remote_changed = remote.get(path) != known.map(|k| &k.version);
+        let action = match (local.contains_key(path), remote.contains_key(path), known.is_some()) {
+            (true, true, _) if local_changed && remote_changed => SyncAction::Conflict(path.clone()),
+            (true, true, _) if local_changed => SyncAction::Upload(path.clone()),
+            (true, true, _) if remote_changed => SyncAction::Download(path.clone()),
+            (true, true, _) => continue,
+            // Deleted remotely: keep the file if it was edited here since
+            (true, false, true) if !local_changed => SyncAction::DeleteLocal(path.clone()),
+            (true, false, _) => SyncAction::Upload(path.clone()),
+            (false, true, true) if !remote_changed => SyncAction::DeleteRemote(path.clone()),
+            (false, true, _) => SyncAction::Download(path.clone()),
+            (false, false, _) => SyncAction::Forget(path.clone()),
+        };
+        actions.push(action);
+    }
+    actions
+}
+
+/// What a sync run did
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub uploaded: usize,
+    /// Files written or deleted on disk, relative to the vault
+    pub changed_locally: Vec<PathBuf>,
+    pub deleted_remotely: usize,
+    /// Conflict copies saved next to the local files
+    pub conflict_copies: Vec<PathBuf>,
+    /// Files that could not be synced, with the reason
+    pub errors: Vec<String>,
+}
+
+/// A sync run over a vault
+struct Sync<'a> {
+    vault: &'a Path,
+    remote: &'a mut dyn Remote,
+    state: SyncState,
+    local: HashMap<String, LocalFile>,
+    report: SyncReport,
+}
+
+impl Sync<'_> {
+    fn record(&mut self, path: &str, hash: String, version: String) {
+        let (modified, size) = std::fs::metadata(self.vault.join(path))
+            .map(|metadata| {
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |time| time.as_nanos());
+                (modified, metadata.len())
+            })
+            .unwrap_or_default();
+        self.state.files.insert(path.to_string(), FileState { hash, version, modified, size });
+    }
+
+    fn upload(&mut self, path: &str, remote_version: Option<&String>) -> Result<()> {
+        let data = std::fs::read(self.vault.join(path))?;
+        let version = self.remote.put(path, &data)?;
+        // Without a reported version the next listing shows a change, which
+        // is recognised as the same content when downloaded
+        let version = version.or_else(|| remote_version.cloned()).unwrap_or_default();
+        self.record(path, hash(&data), version);
+        self.report.uploaded += 1;
+        Ok(())
+    }
+
+    fn write_local(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let target = self.vault.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, data).with_context(|| format!("Failed to write {}", target.display()))?;
+        self.report.changed_locally.push(PathBuf::from(path));
+        Ok(())
+    }
+
+    fn download(&mut self, path: &str, version: &str) -> Result<()> {
+        let data = self.remote.get(path)?;
+        let data_hash = hash(&data);
+        if self.local.get(path).map(|file| &file.hash) != Some(&data_hash) {
+            self.write_local(path, &data)?;
+        }
+        self.record(path, data_hash, version.to_string());
+        Ok(())
+    }
+
+    /// Move a file deleted remotely into the vault's trash
+    fn delete_local(&mut self, path: &str) -> Result<()> {
+        let source = self.vault.join(path);
+        let trash = self.vault.join(TRASH_FOLDER);
+        std::fs::create_dir_all(&trash)?;
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = source.extension().unwrap_or_default().to_string_lossy();
+        std::fs::rename(&source, unique_path(&trash, &stem, &ext))
+            .with_context(|| format!("Failed to move {} to the trash", source.display()))?;
+        self.state.files.remove(path);
+        self.report.changed_locally.push(PathBuf::from(path));
+        Ok(())
+    }
+
+    fn conflict(&mut self, path: &str, version: &str, strategy: ConflictStrategy) -> Result<()> {
+        let data = self.remote.get(path)?;
+        let data_hash = hash(&data);
+        let local_hash = self.local.get(path).map(|file| file.hash.clone()).unwrap_or_default();
+        if data_hash == local_hash {
+            self.record(path, data_hash, version.to_string());
+            return Ok(());
+        }
+        match strategy {
+            ConflictStrategy::PreferLocal => self.upload(path, Some(&version.to_string())),
+            ConflictStrategy::PreferRemote => {
+                self.write_local(path, &data)?;
+                self.record(path, data_hash, version.to_string());
+                Ok(())
+            }
+            ConflictStrategy::KeepBoth => {
+                let file = Path::new(path);
+                let folder = self.vault.join(file.parent().unwrap_or(Path::new("")));
+                let stem = format!(
+                    "{} (conflict {})",
+                    file.file_stem().unwrap_or_default().to_string_lossy(),
+                    chrono::Local::now().format("%Y-%m-%d %H%M")
+                );
+                let ext = file.extension().unwrap_or_default().to_string_lossy();
+                let copy = unique_path(&folder, &stem, &ext);
+                std::fs::write(&copy, &data).with_context(|| format!("Failed to write {}", copy.display()))?;
+                self.report.conflict_copies.push(copy.strip_prefix(self.vault).unwrap_or(&copy).to_path_buf());
+                // The local version wins the original name; the copy is
+                // uploaded on the next run
+                self.upload(path, Some(&version.to_string()))
+            }
+        }
+    }
+}
+
+/// Sync a vault with a remote
+pub fn sync_vault(vault: &Path, remote_config: &RemoteConfig, strategy: ConflictStrategy) -> Result<SyncReport> {
+    let mut remote = remote_config.connect()?;
+    sync_with(vault, remote.as_mut(), &remote_config.id(), strategy)
+}
+
+/// Sync a vault with a connected remote, identified by `remote_id`
+fn sync_with(vault: &Path, remote: &mut dyn Remote, remote_id: &str, strategy: ConflictStrategy) -> Result<SyncReport> {
+    let state = SyncState::load(vault, remote_id);
+    let local = scan_local(vault, &state)?;
+    let mut report = SyncReport::default();
+    let mut remote_files = HashMap::new();
+    for (path, file) in remote.list().context("Failed to list the remote files")? {
+        if !is_inside_vault(&path) {
+            report.errors.push(format!("{:?}: the remote lists it outside of the vault", path));
+        } else if is_synced(&path) {
+            remote_files.insert(path, file.version);
+        }
+    }
+    let local_hashes = local.iter().map(|(path, file)| (path.clone(), file.hash.clone())).collect();
+    let actions = plan(&local_hashes, &remote_files, &state);
+
+    let mut sync = Sync { vault, remote, state, local, report };
+    for action in actions {
+        let result = match &action {
+            SyncAction::Upload(path) => sync.upload(path, remote_files.get(path)),
+            SyncAction::Download(path) => sync.download(path, &remote_files[path]),
+            SyncAction::DeleteLocal(path) => sync.delete_local(path),
+            SyncAction::DeleteRemote(path) => sync.remote.delete(path).map(|_| {
+                sync.state.files.remove(path);
+                sync.report.deleted_remotely += 1;
+            }),
+            SyncAction::Conflict(path) => sync.conflict(path, &remote_files[path], strategy),
+            SyncAction::Forget(path) => {
+                sync.state.files.remove(path);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            sync.report.errors.push(format!("{:?}: {:#}", action, e));
+        }
+    }
+
+    // Files found unchanged on both sides keep their state; refresh the
+    // modification times so they aren't rehashed next time
+    for (path, file) in &sync.local {
+        if let Some(known) = sync.state.files.get_mut(path) {
+            if known.hash == file.hash {
+                known.modified = known.modified.max(file.modified);
+                known.size = file.size;
+            }
+        }
+    }
+    sync.state.save(vault)?;
+    Ok(sync.report)
+}
+
+/// Sync status shown in the status bar
+#[derive(Debug, Clone, Default)]
+pub enum SyncStatus {
+    #[default]
+    Idle,
+    Syncing,
+    Synced(chrono::DateTime<chrono::Local>),
+    Failed(String),
+}
+
+/// Runs syncs in the background, one at a time
+pub struct Syncer {
+    results_tx: Sender<Result<SyncReport>>,
+    results: Receiver<Result<SyncReport>>,
+    last_start: Option<Instant>,
+    pub status: SyncStatus,
+}
+
+impl Default for Syncer {
+    fn default() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        Self { results_tx, results, last_start: None, status: SyncStatus::Idle }
+    }
+}
+
+impl Syncer {
+    pub fn is_busy(&self) -> bool {
+        matches!(self.status, SyncStatus::Syncing)
+    }
+
+    /// Whether the timer calls for another sync
+    pub fn is_due(&self, config: &SyncConfig) -> bool {
+        config.enabled
+            && config.interval_minutes > 0
+            && !self.is_busy()
+            && self
+                .last_start
+                .is_none_or(|start| start.elapsed() >= Duration::from_secs(config.interval_minutes * 60))
+    }
+
+    /// Start syncing a vault, unless a sync is already running
+    pub fn start(&mut self, vault: PathBuf, remote: RemoteConfig, strategy: ConflictStrategy) {
+        if self.is_busy() {
+            return;
+        }
+        self.status = SyncStatus::Syncing;
+        self.last_start = Some(Instant::now());
+        let results = self.results_tx.clone();
+        thread::spawn(move || {
+            let _ = results.send(sync_vault(&vault, &remote, strategy));
+        });
+    }
+
+    /// The result of a finished sync, if one finished since the last call
+    pub fn poll(&mut self) -> Option<Result<SyncReport>> {
+        let result = self.results.try_recv().ok()?;
+        self.status = match &result {
+            Ok(report) if !report.errors.is_empty() => {
+                SyncStatus::Failed(format!("{} files could not be synced", report.errors.len()))
+            }
+            Ok(_) => SyncStatus::Synced(chrono::Local::now()),
+            Err(e) => SyncStatus::Failed(format!("{:#}", e)),
+        };
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let known = |hash: &str, version: &str| FileState {
+            hash: hash.to_string(),
+            version: version.to_string(),
+            ..Default::default()
+        };
+        let mut state = SyncState::default();
+        for (path, hash, version) in [
+            ("same.md", "h1", "v1"),
+            ("edited.md", "h1", "v1"),
+            ("remote-edit.md", "h1", "v1"),
+            ("both.md", "h1", "v1"),
+            ("deleted-here.md", "h1", "v1"),
+            ("deleted-there.md", "h1", "v1"),
+            ("edited-deleted.md", "h1", "v1"),
+            ("gone.md", "h1", "v1"),
+        ] {
+            state.files.insert(path.to_string(), known(hash, version));
+        }
+        let map = |entries: &[(&str, &str)]| -> HashMap<String, String> {
+            entries.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let local = map(&[
+            ("same.md", "h1"),
+            ("edited.md", "h2"),
+            ("remote-edit.md", "h1"),
+            ("both.md", "h2"),
+            ("deleted-there.md", "h1"),
+            ("edited-deleted.md", "h2"),
+            ("new-here.md", "h1"),
+            ("new-both.md", "h1"),
+        ]);
+        let remote = map(&[
+            ("same.md", "v1"),
+            ("edited.md", "v1"),
+            ("remote-edit.md", "v2"),
+            ("both.md", "v2"),
+            ("deleted-here.md", "v1"),
+            ("new-there.md", "v1"),
+            ("new-both.md", "v1"),
+        ]);
+
+        let actions = plan(&local, &remote, &state);
+        let s = |path: &str| path.to_string();
+        assert_eq!(
+            actions,
+            vec![
+                SyncAction::Conflict(s("both.md")),
+                SyncAction::DeleteRemote(s("deleted-here.md")),
+                SyncAction::DeleteLocal(s("deleted-there.md")),
+                SyncAction::Upload(s("edited-deleted.md")),
+                SyncAction::Upload(s("edited.md")),
+                SyncAction::Forget(s("gone.md")),
+                SyncAction::Conflict(s("new-both.md")),
+                SyncAction::Upload(s("new-here.md")),
+                SyncAction::Download(s("new-there.md")),
+                SyncAction::Download(s("remote-edit.md")),
+            ]
+        );
+        assert!(is_synced(".robsidian/vault.json"));
+        assert!(!is_synced(".robsidian/sync.json"));
+        assert!(!is_synced(".trash/old.md"));
+    }
+
+    /// Remote serving files from memory
+    struct MemoryRemote(HashMap<String, Vec<u8>>);
+
+    impl Remote for MemoryRemote {
+        fn list(&mut self) -> Result<HashMap<String, RemoteFile>> {
+            Ok(self.0.iter().map(|(path, data)| (path.clone(), RemoteFile { version: hash(data) })).collect())
+        }
+
+        fn get(&mut self, path: &str) -> Result<Vec<u8>> {
+            self.0.get(path).cloned().context("No such file")
+        }
+
+        fn put(&mut self, path: &str, data: &[u8]) -> Result<Option<String>> {
+            self.0.insert(path.to_string(), data.to_vec());
+            Ok(Some(hash(data)))
+        }
+
+        fn delete(&mut self, path: &str) -> Result<()> {
+            self.0.remove(path);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_remote_paths_outside_vault() {
+        let dir = std::env::temp_dir().join(format!("robsidian_sync_{}", std::process::id()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(&vault).unwrap();
+        let absolute = format!("{}/absolute.md", dir.to_string_lossy().replace('\\', "/"));
+        let listing = ["Notes/ok.md", "../escape.md", "Notes/../../escape.md", &absolute, "C:/drive.md", "a\\..\\b.md"];
+        let mut remote = MemoryRemote(listing.iter().map(|path| (path.to_string(), b"owned".to_vec())).collect());
+
+        let report = sync_with(&vault, &mut remote, "memory", ConflictStrategy::KeepBoth).unwrap();
+        assert_eq!(report.changed_locally, [PathBuf::from("Notes/ok.md")]);
+        assert_eq!(report.errors.len(), 5);
+        assert!(!dir.join("escape.md").exists());
+        assert!(!dir.join("absolute.md").exists());
+        assert!(is_inside_vault("Meetings/10:30 standup.md"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,179 @@
+//! WebDAV remote
+//!
+//! Lists the server folder with `PROPFIND` one level at a time (many servers
+//! refuse `Depth: infinity`) and creates missing folders with `MKCOL` before
+//! uploading into them.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use url::Url;
+
+use super::{Remote, RemoteFile};
+
+/// Characters escaped in a path segment
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/><d:getlastmodified/><d:getcontentlength/><d:resourcetype/></d:prop></d:propfind>"#;
+
+/// A folder on a WebDAV server
+pub struct WebDavRemote {
+    agent: ureq::Agent,
+    /// Folder URL, ending with `/`
+    base: Url,
+    authorization: String,
+    /// Folders known to exist on the server
+    folders: HashSet<String>,
+}
+
+impl WebDavRemote {
+    pub fn new(url: &str, username: &str, password: &str) -> Result<Self> {
+        let mut url = url.trim().to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let base = Url::parse(&url).with_context(|| format!("Not a web address: {}", url))?;
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        Ok(Self {
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).build(),
+            base,
+            authorization: format!("Basic {}", credentials),
+            folders: HashSet::new(),
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        let encoded: Vec<String> = path.split('/').map(|part| utf8_percent_encode(part, SEGMENT).to_string()).collect();
+        Ok(self.base.join(&encoded.join("/"))?)
+    }
+
+    fn request(&self, method: &str, path: &str) -> Result<ureq::Request> {
+        Ok(self.agent.request_url(method, &self.url(path)?).set("Authorization", &self.authorization))
+    }
+
+    /// Entries of a folder: (path relative to the vault, is folder, version)
+    fn list_folder(&self, folder: &str) -> Result<Vec<(String, bool, String)>> {
+        let body = self
+            .request("PROPFIND", folder)?
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml")
+            .send_string(PROPFIND_BODY)
+            .with_context(|| format!("Failed to list {}", self.url(folder).map(|u| u.to_string()).unwrap_or_default()))?
+            .into_string()?;
+        parse_multistatus(&body, self.base.path())
+    }
+
+    /// Create the folders above a file that aren't known to exist
+    fn create_parents(&mut self, path: &str) -> Result<()> {
+        let parts: Vec<&str> = path.split('/').collect();
+        for end in 1..parts.len() {
+            let folder = format!("{}/", parts[..end].join("/"));
+            if self.folders.contains(&folder) {
+                continue;
+            }
+            match self.request("MKCOL", &folder)?.call() {
+                // 405: the folder exists already
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to create folder {}", folder)),
+            }
+            self.folders.insert(folder);
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `PROPFIND` response into (path relative to `base_path`, is
+/// folder, version) entries, skipping the listed folder itself
+fn parse_multistatus(xml: &str, base_path: &str) -> Result<Vec<(String, bool, String)>> {
+    let document = roxmltree::Document::parse(xml).context("Invalid PROPFIND response")?;
+    let dav = |node: &roxmltree::Node, name: &str| node.tag_name().name() == name && node.tag_name().namespace() == Some("DAV:");
+    let mut entries = Vec::new();
+    for response in document.descendants().filter(|node| dav(node, "response")) {
+        let Some(href) = response.descendants().find(|node| dav(node, "href")).and_then(|node| node.text()) else {
+            continue;
+        };
+        // The href may be a full URL or an absolute path
+        let href_path = Url::parse(href).map(|url| url.path().to_string()).unwrap_or_else(|_| href.trim().to_string());
+        let decoded = percent_decode_str(&href_path).decode_utf8_lossy().to_string();
+        let base = percent_decode_str(base_path).decode_utf8_lossy().to_string();
+        let Some(rel) = decoded.strip_prefix(&base) else {
+            continue;
+        };
+        let rel = rel.trim_matches('/').to_string();
+        if rel.is_empty() {
+            continue;
+        }
+        let is_folder = response.descendants().any(|node| dav(&node, "collection"));
+        let prop = |name: &str| {
+            response
+                .descendants()
+                .find(|node| dav(node, name))
+                .and_then(|node| node.text())
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let etag = prop("getetag");
+        let version = if etag.is_empty() {
+            format!("{}/{}", prop("getlastmodified"), prop("getcontentlength"))
+        } else {
+            etag
+        };
+        entries.push((rel, is_folder, version));
+    }
+    Ok(entries)
+}
+
+impl Remote for WebDavRemote {
+    fn list(&mut self) -> Result<HashMap<String, RemoteFile>> {
+        let mut files = HashMap::new();
+        let mut pending = vec![String::new()];
+        while let Some(folder) = pending.pop() {
+            for (path, is_folder, version) in self.list_folder(&folder)? {
+                if is_folder {
+                    // Hidden folders never hold synced files, except settings
+                    if path.rsplit('/').next().is_some_and(|name| name.starts_with('.') && name != super::SETTINGS_DIR) {
+                        continue;
+                    }
+                    self.folders.insert(format!("{}/", path));
+                    pending.push(format!("{}/", path));
+                } else {
+                    files.insert(path, RemoteFile { version });
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn get(&mut self, path: &str) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.request("GET", path)?
+            .call()
+            .with_context(|| format!("Failed to download {}", path))?
+            .into_reader()
+            .read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn put(&mut self, path: &str, data: &[u8]) -> Result<Option<String>> {
+        self.create_parents(path)?;
+        let response = self
+            .request("PUT", path)?
+            .send_bytes(data)
+            .with_context(|| format!("Failed to upload {}", path))?;
+        Ok(response.header("ETag").map(str::to_string))
+    }
+
+    fn delete(&mut self, path: &str) -> Result<()> {
+        match self.request("DELETE", path)?.call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(ureq::Error::Status(code, _)) => bail!("Failed to delete {} (HTTP {})", path, code),
+            Err(e) => Err(e).with_context(|| format!("Failed to delete {}", path)),
+        }
+    }
+}
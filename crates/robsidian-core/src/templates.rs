@@ -0,0 +1,63 @@
+//! Note templates
+//!
+//! Templates are notes kept in a folder of the vault (`Templates` unless the
+//! vault settings name another). Inserting one fills in `{{expressions}}`:
+//!
+//! - `{{title}}`, `{{date}}` and `{{time}}` for the note and the current time
+//! - `{{fields.<key>}}` for a frontmatter field of the note the template is
+//!   inserted into, so e.g. meeting minutes can pick up the note's `project`
+//!
+//! Unknown expressions and missing fields are left as written.
+
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use super::document::Document;
+use super::file_system::get_markdown_files;
+
+/// Templates folder used when the vault settings don't name one
+pub const DEFAULT_TEMPLATES_FOLDER: &str = "Templates";
+
+/// Templates in a folder, sorted by path
+pub fn list_templates(dir: &Path) -> Vec<PathBuf> {
+    let mut templates = get_markdown_files(dir);
+    templates.sort();
+    templates
+}
+
+/// Fill in the expressions of a template for the note it is inserted into
+pub fn render_template(template: &str, note: &Document) -> String {
+    let re = regex_lite::Regex::new(r"\{\{\s*([\w.-]+)\s*\}\}").unwrap();
+    let now = Local::now();
+    re.replace_all(template, |caps: &regex_lite::Captures| {
+        let expr = &caps[1];
+        let value = match expr {
+            "title" => Some(note.title()),
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "time" => Some(now.format("%H:%M").to_string()),
+            _ => expr
+                .strip_prefix("fields.")
+                .and_then(|key| note.frontmatter_field(key)),
+        };
+        value.unwrap_or_else(|| caps[0].to_string())
+    })
+    .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fields() {
+        let mut note = Document::new(PathBuf::from("Standup.md"));
+        note.content = "---\nproject: Apollo\nattendees: [Ann, Bo]\n---\n# Standup\n".to_string();
+
+        let rendered = render_template(
+            "## {{title}} ({{ fields.project }})\nWith {{fields.attendees}}\n{{fields.missing}}",
+            &note,
+        );
+        assert_eq!(rendered, "## Standup (Apollo)\nWith Ann, Bo\n{{fields.missing}}");
+    }
+}
@@ -0,0 +1,175 @@
+//! Line endings and text encodings of note files
+//!
+//! Documents are edited with `\n` line endings in a UTF-8 string. The file's
+//! original line ending and encoding are detected on open and restored on
+//! save, so opening and saving a note never rewrites it behind your back.
+
+use anyhow::{bail, Result};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Line ending style of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Every line ending, in menu order
+    pub const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::Crlf];
+
+    /// Short name shown in the status bar
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        }
+    }
+
+    /// The style used by most lines of `text` (LF for text without newlines)
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Convert `\n` line endings to this style
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            LineEnding::Lf => text.to_string(),
+            LineEnding::Crlf => text.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Normalize any line endings to `\n`
+pub fn normalize_line_endings(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// Text encoding of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, used for files that are not valid UTF-8
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Every encoding, in menu order
+    pub const ALL: [TextEncoding; 5] = [
+        TextEncoding::Utf8,
+        TextEncoding::Utf8Bom,
+        TextEncoding::Utf16Le,
+        TextEncoding::Utf16Be,
+        TextEncoding::Latin1,
+    ];
+
+    /// Short name shown in the status bar
+    pub fn label(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf8Bom => "UTF-8 with BOM",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Utf16Be => "UTF-16 BE",
+            TextEncoding::Latin1 => "ISO-8859-1",
+        }
+    }
+
+    /// Detect the encoding of `bytes` from its BOM and decode it.
+    /// Bytes that are neither marked nor valid UTF-8 are read as ISO-8859-1.
+    pub fn decode(bytes: &[u8]) -> (String, TextEncoding) {
+        if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+            return (String::from_utf8_lossy(rest).into_owned(), TextEncoding::Utf8Bom);
+        }
+        if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+            return (decode_utf16(rest, u16::from_le_bytes), TextEncoding::Utf16Le);
+        }
+        if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+            return (decode_utf16(rest, u16::from_be_bytes), TextEncoding::Utf16Be);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), TextEncoding::Utf8),
+            Err(_) => (bytes.iter().map(|&b| b as char).collect(), TextEncoding::Latin1),
+        }
+    }
+
+    /// Encode `text` in this encoding, including its BOM
+    pub fn encode(self, text: &str) -> Result<Vec<u8>> {
+        let bytes = match self {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+            TextEncoding::Utf16Le => {
+                let mut bytes = UTF16LE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+                bytes
+            }
+            TextEncoding::Utf16Be => {
+                let mut bytes = UTF16BE_BOM.to_vec();
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+                bytes
+            }
+            TextEncoding::Latin1 => {
+                if let Some(c) = text.chars().find(|&c| c as u32 > 0xFF) {
+                    bail!("'{}' cannot be saved as {}", c, self.label());
+                }
+                text.chars().map(|c| c as u8).collect()
+            }
+        };
+        Ok(bytes)
+    }
+}
+
+/// Decode UTF-16 code units, replacing invalid ones
+fn decode_utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\nc\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("single line"), LineEnding::Lf);
+
+        let normalized = normalize_line_endings("a\r\nb\r\n");
+        assert_eq!(normalized, "a\nb\n");
+        assert_eq!(LineEnding::Crlf.apply(&normalized), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let text = "# Café ✓\n";
+        for encoding in TextEncoding::ALL {
+            if encoding == TextEncoding::Latin1 {
+                assert!(encoding.encode(text).is_err());
+                continue;
+            }
+            let bytes = encoding.encode(text).unwrap();
+            assert_eq!(TextEncoding::decode(&bytes), (text.to_string(), encoding));
+        }
+
+        // Invalid UTF-8 falls back to ISO-8859-1
+        let (latin, encoding) = TextEncoding::decode(b"caf\xE9");
+        assert_eq!((latin.as_str(), encoding), ("café", TextEncoding::Latin1));
+        assert_eq!(encoding.encode(&latin).unwrap(), b"caf\xE9");
+    }
+}
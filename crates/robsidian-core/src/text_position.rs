@@ -0,0 +1,103 @@
+//! UTF-8 safe text positions
+//!
+//! Documents are addressed by byte offsets, egui text edits by character
+//! indices, and people by line and column. These helpers convert between the
+//! three and never produce an offset inside a multi-byte character.
+
+use std::ops::Range;
+
+/// Largest character boundary at or before `byte` (clamped to the text)
+pub fn floor_char_boundary(text: &str, byte: usize) -> usize {
+    let mut byte = byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    byte
+}
+
+/// Smallest character boundary at or after `byte` (clamped to the text)
+pub fn ceil_char_boundary(text: &str, byte: usize) -> usize {
+    let mut byte = byte.min(text.len());
+    while !text.is_char_boundary(byte) {
+        byte += 1;
+    }
+    byte
+}
+
+/// Boundary of the character before `byte`, if there is one
+pub fn prev_char_boundary(text: &str, byte: usize) -> Option<usize> {
+    text[..floor_char_boundary(text, byte)]
+        .char_indices()
+        .next_back()
+        .map(|(idx, _)| idx)
+}
+
+/// Shrink-safe version of `range`: both ends moved onto character boundaries
+pub fn clamp_range(text: &str, range: Range<usize>) -> Range<usize> {
+    let start = floor_char_boundary(text, range.start);
+    let end = ceil_char_boundary(text, range.end).max(start);
+    start..end
+}
+
+/// Byte offset of the `idx`-th character (the text length past the end)
+pub fn char_to_byte(text: &str, idx: usize) -> usize {
+    text.char_indices().nth(idx).map_or(text.len(), |(byte, _)| byte)
+}
+
+/// Character index of a byte offset, rounding down inside a character
+pub fn byte_to_char(text: &str, byte: usize) -> usize {
+    text[..floor_char_boundary(text, byte)].chars().count()
+}
+
+/// Zero-based line and column (in characters) of a byte offset
+pub fn byte_to_line_col(text: &str, byte: usize) -> (usize, usize) {
+    let before = &text[..floor_char_boundary(text, byte)];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count())
+}
+
+/// Byte offset of a zero-based line and column, with the column clamped to
+/// the line's end. Returns `None` if the text has no such line.
+pub fn line_col_to_byte(text: &str, line: usize, col: usize) -> Option<usize> {
+    let line_start = if line == 0 {
+        0
+    } else {
+        text.match_indices('\n').nth(line - 1).map(|(idx, _)| idx + 1)?
+    };
+    let line_end = text[line_start..]
+        .find('\n')
+        .map_or(text.len(), |idx| line_start + idx);
+    Some(
+        text[line_start..line_end]
+            .char_indices()
+            .nth(col)
+            .map_or(line_end, |(idx, _)| line_start + idx),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multibyte_positions() {
+        let text = "héllo\n日本語\nend";
+        // Offsets inside a character snap to its boundaries
+        assert_eq!(floor_char_boundary(text, 2), 1);
+        assert_eq!(ceil_char_boundary(text, 2), 3);
+        assert_eq!(clamp_range(text, 2..8), 1..10);
+        assert_eq!(prev_char_boundary(text, 3), Some(1));
+
+        assert_eq!(char_to_byte(text, 2), 3);
+        assert_eq!(byte_to_char(text, 3), 2);
+        assert_eq!(char_to_byte(text, 100), text.len());
+
+        let second_char = "héllo\n日".len();
+        assert_eq!(byte_to_line_col(text, second_char), (1, 1));
+        assert_eq!(line_col_to_byte(text, 1, 1), Some(second_char));
+        // Columns past the end of a line clamp to it
+        assert_eq!(line_col_to_byte(text, 0, 99), Some("héllo".len()));
+        assert_eq!(line_col_to_byte(text, 3, 0), None);
+    }
+}
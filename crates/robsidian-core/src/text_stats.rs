@@ -0,0 +1,109 @@
+//! Text statistics
+//!
+//! Word and character counts plus the classic Flesch readability scores.
+//! Syllables are estimated from vowel groups, which is close enough for
+//! English prose and meaningless for code or other languages.
+
+/// Words read per minute, for reading time estimates
+const WORDS_PER_MINUTE: usize = 230;
+
+/// Counts for a piece of text
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextStats {
+    pub words: usize,
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+}
+
+/// Estimated syllables of a word
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    // Silent final "e", as in "make" but not "table"
+    if let [.., before, 'l', 'e'] = letters.as_slice() {
+        if is_vowel(*before) && count > 1 {
+            count -= 1;
+        }
+    } else if letters.last() == Some(&'e') && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+impl TextStats {
+    pub fn of(text: &str) -> Self {
+        let words: Vec<&str> =
+            text.split_whitespace().filter(|word| word.chars().any(char::is_alphanumeric)).collect();
+        let mut sentences = 0;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if matches!(c, '.' | '!' | '?') && chars.peek().is_none_or(|next| next.is_whitespace()) {
+                sentences += 1;
+            }
+        }
+        if sentences == 0 && !words.is_empty() {
+            sentences = 1;
+        }
+        Self {
+            words: words.len(),
+            characters: text.chars().count(),
+            characters_no_spaces: text.chars().filter(|c| !c.is_whitespace()).count(),
+            sentences,
+            syllables: words.iter().map(|word| syllables(word)).sum(),
+        }
+    }
+
+    pub fn words_per_sentence(&self) -> f32 {
+        self.words as f32 / self.sentences.max(1) as f32
+    }
+
+    pub fn syllables_per_word(&self) -> f32 {
+        self.syllables as f32 / self.words.max(1) as f32
+    }
+
+    /// Flesch reading ease: higher is easier, 60-70 is plain English
+    pub fn reading_ease(&self) -> f32 {
+        206.835 - 1.015 * self.words_per_sentence() - 84.6 * self.syllables_per_word()
+    }
+
+    /// Flesch-Kincaid grade level: the US school grade needed to follow the text
+    pub fn grade_level(&self) -> f32 {
+        0.39 * self.words_per_sentence() + 11.8 * self.syllables_per_word() - 15.59
+    }
+
+    /// Estimated reading time in minutes, rounded up
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(WORDS_PER_MINUTE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_stats() {
+        let stats = TextStats::of("The cat sat on the mat. It was a table, v1.2 - fine!");
+        assert_eq!(stats.words, 12);
+        assert_eq!(stats.sentences, 2);
+        assert_eq!(stats.characters, 52);
+        assert_eq!(stats.characters_no_spaces, 40);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("readability"), 5);
+        assert!(stats.reading_ease() > 90.0);
+        assert_eq!(TextStats::of("  ").words, 0);
+    }
+}
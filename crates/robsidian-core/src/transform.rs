@@ -0,0 +1,326 @@
+//! Markdown text transforms
+//!
+//! Conversions work on whole lines: a selection is first widened to the lines
+//! it touches, then rewritten. Applying a conversion to text that already has
+//! that form converts it back. Text transforms change case or reorder lines.
+
+use std::ops::Range;
+
+use super::text_position::clamp_range;
+
+/// Kinds of block a selection can be converted into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionConversion {
+    Quote,
+    Callout,
+    CodeBlock { lang: String },
+    NumberedList,
+}
+
+impl SelectionConversion {
+    /// Human readable name of the conversion
+    pub fn label(&self) -> &'static str {
+        match self {
+            SelectionConversion::Quote => "Quote",
+            SelectionConversion::Callout => "Callout",
+            SelectionConversion::CodeBlock { .. } => "Code Block",
+            SelectionConversion::NumberedList => "Numbered List",
+        }
+    }
+}
+
+/// Case and line transforms applied to a selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextTransform {
+    Uppercase,
+    Lowercase,
+    TitleCase,
+    SortLines,
+    RemoveDuplicateLines,
+    JoinLines,
+}
+
+impl TextTransform {
+    /// Every transform, in menu order
+    pub const ALL: [TextTransform; 6] = [
+        TextTransform::Uppercase,
+        TextTransform::Lowercase,
+        TextTransform::TitleCase,
+        TextTransform::SortLines,
+        TextTransform::RemoveDuplicateLines,
+        TextTransform::JoinLines,
+    ];
+
+    /// Human readable name of the transform
+    pub fn label(self) -> &'static str {
+        match self {
+            TextTransform::Uppercase => "UPPERCASE",
+            TextTransform::Lowercase => "lowercase",
+            TextTransform::TitleCase => "Title Case",
+            TextTransform::SortLines => "Sort Lines",
+            TextTransform::RemoveDuplicateLines => "Remove Duplicate Lines",
+            TextTransform::JoinLines => "Join Lines",
+        }
+    }
+
+    /// Whether the transform works on whole lines rather than the exact selection
+    fn is_line_based(self) -> bool {
+        matches!(
+            self,
+            TextTransform::SortLines | TextTransform::RemoveDuplicateLines | TextTransform::JoinLines
+        )
+    }
+
+    /// Apply the transform to a piece of text
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            TextTransform::Uppercase => text.to_uppercase(),
+            TextTransform::Lowercase => text.to_lowercase(),
+            TextTransform::TitleCase => title_case(text),
+            TextTransform::SortLines => {
+                let mut lines: Vec<&str> = text.lines().collect();
+                lines.sort_by_key(|line| line.to_lowercase());
+                lines.join("\n")
+            }
+            TextTransform::RemoveDuplicateLines => {
+                let mut seen = std::collections::HashSet::new();
+                text.lines()
+                    .filter(|line| seen.insert(*line))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            TextTransform::JoinLines => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Capitalize the first letter of every word and lowercase the rest
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '\'' {
+            if word_start {
+                result.extend(c.to_uppercase());
+            } else {
+                result.extend(c.to_lowercase());
+            }
+            word_start = false;
+        } else {
+            result.push(c);
+            word_start = c.is_whitespace() || c == '-' || c == '_';
+        }
+    }
+    result
+}
+
+/// Apply a text transform to `selection` (a byte range), returning the new
+/// content and the byte range of the transformed text. Line transforms widen
+/// the selection to whole lines.
+pub fn transform_selection(
+    content: &str,
+    selection: Range<usize>,
+    transform: TextTransform,
+) -> (String, Range<usize>) {
+    let range = if transform.is_line_based() {
+        line_range(content, selection)
+    } else {
+        clamp_range(content, selection)
+    };
+
+    let replacement = transform.apply(&content[range.clone()]);
+    let new_content = format!(
+        "{}{}{}",
+        &content[..range.start],
+        replacement,
+        &content[range.end..]
+    );
+    let new_range = range.start..range.start + replacement.len();
+    (new_content, new_range)
+}
+
+/// Prefix every line of a block with `> `
+pub fn quote_block(text: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Turn a block into a callout of the given kind
+pub fn callout_block(text: &str, kind: &str) -> String {
+    format!("> [!{}]\n{}", kind, quote_block(text))
+}
+
+/// Widen a byte range to the full lines it touches (without the final newline)
+pub fn line_range(content: &str, range: Range<usize>) -> Range<usize> {
+    let range = clamp_range(content, range);
+    let start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let end = content[range.end..]
+        .find('\n')
+        .map_or(content.len(), |i| range.end + i);
+    start..end
+}
+
+/// Convert the lines touched by `selection` (a byte range), returning the new
+/// content and the byte range of the converted text
+pub fn convert_selection(
+    content: &str,
+    selection: Range<usize>,
+    conversion: &SelectionConversion,
+) -> (String, Range<usize>) {
+    let lines = line_range(content, selection);
+    let text = &content[lines.clone()];
+
+    let replacement = match conversion {
+        SelectionConversion::Quote => match unquote(text) {
+            Some(inner) => inner,
+            None => quote_block(text),
+        },
+        SelectionConversion::Callout => match uncallout(text) {
+            Some(inner) => inner,
+            None => callout_block(text, "note"),
+        },
+        SelectionConversion::CodeBlock { lang } => match unfence(text) {
+            Some(inner) => inner,
+            None => format!("```{}\n{}\n```", lang.trim(), text),
+        },
+        SelectionConversion::NumberedList => match unnumber(text) {
+            Some(inner) => inner,
+            None => number_lines(text),
+        },
+    };
+
+    let new_content = format!(
+        "{}{}{}",
+        &content[..lines.start],
+        replacement,
+        &content[lines.end..]
+    );
+    let new_range = lines.start..lines.start + replacement.len();
+    (new_content, new_range)
+}
+
+/// Strip `> ` from every line if all lines are quoted
+fn unquote(text: &str) -> Option<String> {
+    text.lines()
+        .map(|line| {
+            line.strip_prefix("> ")
+                .or_else(|| line.strip_prefix('>'))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// Remove the `> [!kind]` header and quoting of a callout
+fn uncallout(text: &str) -> Option<String> {
+    let (header, body) = text.split_once('\n').unwrap_or((text, ""));
+    let header = header.trim();
+    if !(header.starts_with("> [!") || header.starts_with(">[!")) {
+        return None;
+    }
+    unquote(body)
+}
+
+/// Remove the fences around a code block
+fn unfence(text: &str) -> Option<String> {
+    let (open, rest) = text.split_once('\n')?;
+    let (body, close) = rest.rsplit_once('\n').unwrap_or(("", rest));
+    if open.trim_start().starts_with("```") && close.trim() == "```" {
+        Some(body.to_string())
+    } else {
+        None
+    }
+}
+
+/// Strip `1. ` style markers if every non-empty line has one
+fn unnumber(text: &str) -> Option<String> {
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return Some(line);
+            }
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            line[digits..].strip_prefix(". ")
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// Number each non-empty line, replacing any bullet marker
+fn number_lines(text: &str) -> String {
+    let mut number = 0;
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return line.to_string();
+            }
+            number += 1;
+            let item = line
+                .trim_start()
+                .strip_prefix("- ")
+                .or_else(|| line.trim_start().strip_prefix("* "))
+                .unwrap_or(line.trim_start());
+            format!("{}. {}", number, item)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_round_trip() {
+        let content = "Intro\nfirst line\nsecond line\nOutro";
+        // Selection starts and ends mid-line
+        let selection = 8..15;
+        let conversions = [
+            SelectionConversion::Quote,
+            SelectionConversion::Callout,
+            SelectionConversion::CodeBlock { lang: "rust".into() },
+            SelectionConversion::NumberedList,
+        ];
+
+        for conversion in conversions {
+            let (converted, range) = convert_selection(content, selection.clone(), &conversion);
+            assert_ne!(converted, content, "{:?}", conversion);
+            let (restored, _) = convert_selection(&converted, range, &conversion);
+            assert_eq!(restored, content, "{:?}", conversion);
+        }
+    }
+
+    #[test]
+    fn test_text_transforms() {
+        assert_eq!(TextTransform::TitleCase.apply("the QUICK brown-fox's den"), "The Quick Brown-Fox's Den");
+        assert_eq!(TextTransform::Uppercase.apply("straße"), "STRASSE");
+        assert_eq!(TextTransform::SortLines.apply("b\nA\nc"), "A\nb\nc");
+        assert_eq!(TextTransform::RemoveDuplicateLines.apply("a\nb\na"), "a\nb");
+        assert_eq!(TextTransform::JoinLines.apply("a\n  b\n\nc"), "a b c");
+
+        // Line transforms cover the whole lines of a partial selection
+        let (sorted, range) = transform_selection("x\nzeta\nalpha\ny", 4..8, TextTransform::SortLines);
+        assert_eq!(sorted, "x\nalpha\nzeta\ny");
+        assert_eq!(range, 2..12);
+    }
+
+    #[test]
+    fn test_convert_output() {
+        let content = "- a\n- b";
+        let (list, _) = convert_selection(content, 0..1, &SelectionConversion::NumberedList);
+        assert_eq!(list, "1. a\n- b");
+        let (code, range) = convert_selection(content, 0..content.len(), &SelectionConversion::CodeBlock { lang: "md".into() });
+        assert_eq!(code, "```md\n- a\n- b\n```");
+        assert_eq!(range, 0..code.len());
+        assert_eq!(callout_block("a", "note"), "> [!note]\n> a");
+    }
+}
@@ -0,0 +1,423 @@
+//! JSON API over a vault
+//!
+//! Serves the vault services (note index, search, documents and their
+//! change events) over HTTP, for editors, phone apps and scripts that work
+//! with a vault without the app's window, see `robsidian --serve`. Every
+//! request needs the access token as `Authorization: Bearer <token>`.
+//!
+//! | Request | Answer |
+//! |---|---|
+//! | `GET /api/notes` | Every note: `path`, `name`, `aliases` |
+//! | `GET /api/search?q=...&limit=20` | Notes whose name or alias matches |
+//! | `GET /api/search?q=...&content=true` | Notes whose text contains every word, with the first matching line |
+//! | `GET /api/resolve?link=...` | Path a wiki link points to |
+//! | `GET /api/note?path=...` | A note's `content`, `title` and `frontmatter` |
+//! | `PUT /api/note?path=...` | Replaces (or creates) a note with the request body |
+//! | `GET /api/events?since=0` | Changes made through the API after sequence number `since` |
+//!
+//! Paths are relative to the vault, with `/` between folders.
+//! Search results carry the note's folder or tag `color` (`#rrggbb`) when it
+//! has one, so clients can list them as the app does.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+use super::document::Document;
+use super::document_store::{DocumentEvent, DocumentStore};
+use super::file_system::{get_markdown_files, vault_file};
+use super::note_index::NoteIndex;
+use super::vault_settings::{format_color, VaultSettings};
+
+/// Address the API listens on unless told otherwise: this computer only
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:27125";
+/// How long the note index is reused before it's built again, to notice
+/// changes made outside the API
+const INDEX_MAX_AGE: Duration = Duration::from_secs(30);
+/// Events kept for `/api/events`
+const MAX_EVENTS: usize = 1000;
+/// Results of a search unless `limit` says otherwise
+const DEFAULT_LIMIT: usize = 20;
+/// Largest request body read, in bytes
+const MAX_BODY: u64 = 8 * 1024 * 1024;
+
+/// A response to send
+#[derive(Debug)]
+struct Reply {
+    status: u16,
+    body: Value,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self { status, body: json!({ "error": message.into() }) }
+    }
+
+    fn unauthorized() -> Self {
+        Self::error(401, "Send the access token as \"Authorization: Bearer <token>\"")
+    }
+}
+
+/// Value of a query parameter
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').filter_map(|pair| pair.split_once('=')).find(|(k, _)| *k == key).map(|(_, value)| {
+        percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().to_string()
+    })
+}
+
+/// A request's body as text, refused past `MAX_BODY`
+fn read_body(reader: impl Read) -> Result<String, Reply> {
+    let mut body = Vec::new();
+    if reader.take(MAX_BODY + 1).read_to_end(&mut body).is_err() {
+        return Err(Reply::error(400, "The body could not be read"));
+    }
+    if body.len() as u64 > MAX_BODY {
+        return Err(Reply::error(413, format!("The body must be at most {} bytes", MAX_BODY)));
+    }
+    String::from_utf8(body).map_err(|_| Reply::error(400, "The body must be UTF-8 text"))
+}
+
+/// A note's path from a request: relative, inside the vault once symlinks
+/// are followed, not hidden, and a Markdown file
+fn note_path(vault: &Path, rel: &str) -> Option<PathBuf> {
+    let rel = Path::new(rel);
+    let hidden = rel.components().any(|part| part.as_os_str().to_string_lossy().starts_with('.'));
+    if hidden || rel.extension().is_none_or(|ext| ext != "md") {
+        return None;
+    }
+    vault_file(vault, rel)
+}
+
+/// The vault services behind the API
+pub struct VaultService {
+    vault: PathBuf,
+    token: String,
+    index: Arc<NoteIndex>,
+    index_built: Instant,
+    documents: DocumentStore,
+    document_events: Receiver<DocumentEvent>,
+    /// Recent events, numbered from 1
+    events: VecDeque<(u64, DocumentEvent)>,
+    last_event: u64,
+}
+
+impl VaultService {
+    pub fn new(vault: PathBuf, token: String) -> Self {
+        let mut documents = DocumentStore::default();
+        let document_events = documents.subscribe();
+        Self {
+            index: Arc::new(NoteIndex::build(&vault)),
+            index_built: Instant::now(),
+            vault,
+            token,
+            documents,
+            document_events,
+            events: VecDeque::new(),
+            last_event: 0,
+        }
+    }
+
+    fn rel(&self, path: &Path) -> String {
+        path.strip_prefix(&self.vault).unwrap_or(path).to_string_lossy().replace('\\', "/")
+    }
+
+    /// Folder or tag color of a note, as `#rrggbb`
+    fn color(&self, settings: &VaultSettings, path: &Path) -> Option<String> {
+        let tags = self.index.note(path).map_or(&[][..], |note| &note.tags[..]);
+        settings.color_of(&self.vault, path, tags).map(format_color)
+    }
+
+    fn index(&mut self) -> Arc<NoteIndex> {
+        if self.index_built.elapsed() > INDEX_MAX_AGE {
+            self.index = Arc::new(NoteIndex::build(&self.vault));
+            self.index_built = Instant::now();
+        }
+        self.index.clone()
+    }
+
+    /// Answer a request. `authorization` is the `Authorization` header.
+    /// Whether an `Authorization` header carries the access token
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        authorization.and_then(|value| value.strip_prefix("Bearer ")) == Some(self.token.as_str())
+    }
+
+    fn handle(&mut self, method: &Method, url: &str, authorization: Option<&str>, body: &str) -> Reply {
+        if !self.authorized(authorization) {
+            return Reply::unauthorized();
+        }
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        match (method, path) {
+            (Method::Get, "/api/notes") => self.notes(),
+            (Method::Get, "/api/search") => self.search(query),
+            (Method::Get, "/api/resolve") => self.resolve(query),
+            (Method::Get, "/api/note") => self.read_note(query),
+            (Method::Put, "/api/note") => self.write_note(query, body),
+            (Method::Get, "/api/events") => self.events(query),
+            _ => Reply::error(404, format!("No API at {} {}", method, path)),
+        }
+    }
+
+    fn notes(&mut self) -> Reply {
+        let index = self.index();
+        let notes: Vec<Value> = index
+            .notes()
+            .iter()
+            .map(|note| json!({ "path": self.rel(&note.path), "name": note.name, "aliases": note.aliases }))
+            .collect();
+        Reply::ok(Value::Array(notes))
+    }
+
+    fn search(&mut self, query: &str) -> Reply {
+        let text = query_param(query, "q").unwrap_or_default();
+        let limit = query_param(query, "limit").and_then(|limit| limit.parse().ok()).unwrap_or(DEFAULT_LIMIT);
+        let index = self.index();
+        let settings = VaultSettings::load(&self.vault).unwrap_or_default();
+        if query_param(query, "content").as_deref() == Some("true") {
+            return Reply::ok(Value::Array(self.search_content(&text, limit, &settings)));
+        }
+        let results: Vec<Value> = index
+            .search(&text, limit)
+            .iter()
+            .map(|found| {
+                let path = &found.note.path;
+                let color = self.color(&settings, path);
+                json!({ "path": self.rel(path), "name": found.note.name, "alias": found.alias, "color": color })
+            })
+            .collect();
+        Reply::ok(Value::Array(results))
+    }
+
+    /// Notes containing every word of `text`, with the first line holding one
+    fn search_content(&self, text: &str, limit: usize, settings: &VaultSettings) -> Vec<Value> {
+        let words: Vec<String> = text.to_lowercase().split_whitespace().map(str::to_string).collect();
+        if words.is_empty() {
+            return Vec::new();
+        }
+        get_markdown_files(&self.vault)
+            .into_iter()
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(&path).ok()?;
+                let lower = content.to_lowercase();
+                if !words.iter().all(|word| lower.contains(word)) {
+                    return None;
+                }
+                let line = content.lines().find(|line| {
+                    let line = line.to_lowercase();
+                    words.iter().any(|word| line.contains(word))
+                });
+                let color = self.color(settings, &path);
+                Some(json!({ "path": self.rel(&path), "line": line.unwrap_or_default().trim(), "color": color }))
+            })
+            .take(limit)
+            .collect()
+    }
+
+    fn resolve(&mut self, query: &str) -> Reply {
+        let Some(link) = query_param(query, "link") else {
+            return Reply::error(400, "Missing link");
+        };
+        // The link may point to a heading or block
+        let target = link.split(['#', '|']).next().unwrap_or_default();
+        match self.index().resolve(target) {
+            Some(path) => Reply::ok(json!({ "path": self.rel(&path) })),
+            None => Reply::error(404, format!("No note for [[{}]]", link)),
+        }
+    }
+
+    fn read_note(&mut self, query: &str) -> Reply {
+        let Some(path) = query_param(query, "path").and_then(|rel| note_path(&self.vault, &rel)) else {
+            return Reply::error(400, "Missing or invalid path");
+        };
+        let doc = match Document::open(&path) {
+            Ok(doc) => doc,
+            Err(_) => return Reply::error(404, format!("No note at {}", self.rel(&path))),
+        };
+        if doc.locked {
+            return Reply::ok(json!({ "path": self.rel(&path), "title": doc.title(), "locked": true }));
+        }
+        Reply::ok(json!({
+            "path": self.rel(&path),
+            "title": doc.title(),
+            "frontmatter": doc.metadata,
+            "content": doc.content,
+            "body": doc.content_without_frontmatter(),
+            "locked": false,
+        }))
+    }
+
+    fn write_note(&mut self, query: &str, body: &str) -> Reply {
+        let Some(path) = query_param(query, "path").and_then(|rel| note_path(&self.vault, &rel)) else {
+            return Reply::error(400, "Missing or invalid path");
+        };
+        // Start from what's on disk, so changes made elsewhere aren't
+        // mistaken for a conflict
+        let doc = if path.exists() {
+            match Document::open(&path) {
+                Ok(doc) => doc,
+                Err(e) => return Reply::error(500, format!("{:#}", e)),
+            }
+        } else {
+            Document::new(path.clone())
+        };
+        if doc.locked {
+            return Reply::error(409, "The note is encrypted");
+        }
+        if let Some(parent) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                return Reply::error(500, e.to_string());
+            }
+        }
+        self.documents.insert(doc);
+        let saved = {
+            let Some(mut doc) = self.documents.edit(&path) else {
+                return Reply::error(500, "The note was not opened");
+            };
+            doc.content = body.replace("\r\n", "\n");
+            doc.save_mut()
+        };
+        if let Err(e) = saved {
+            self.documents.remove(&path);
+            return Reply::error(500, format!("{:#}", e));
+        }
+        self.documents.saved(&path);
+        if let Some(doc) = self.documents.get(&path) {
+            Arc::make_mut(&mut self.index).update(doc);
+        }
+        self.documents.remove(&path);
+        self.record_events();
+        Reply::ok(json!({ "path": self.rel(&path), "event": self.last_event }))
+    }
+
+    /// Number and keep the document events sent since the last call
+    fn record_events(&mut self) {
+        while let Ok(event) = self.document_events.try_recv() {
+            self.last_event += 1;
+            self.events.push_back((self.last_event, event));
+            if self.events.len() > MAX_EVENTS {
+                self.events.pop_front();
+            }
+        }
+    }
+
+    fn events(&mut self, query: &str) -> Reply {
+        let since: u64 = query_param(query, "since").and_then(|since| since.parse().ok()).unwrap_or(0);
+        let events: Vec<Value> = self
+            .events
+            .iter()
+            .filter(|(number, _)| *number > since)
+            .map(|(number, event)| {
+                let (kind, path) = match event {
+                    DocumentEvent::Opened(path) => ("opened", path),
+                    DocumentEvent::Content(path) => ("content", path),
+                    DocumentEvent::Frontmatter(path) => ("frontmatter", path),
+                    DocumentEvent::Saved(path) => ("saved", path),
+                    DocumentEvent::Moved { to, .. } => ("moved", to),
+                    DocumentEvent::Closed(path) => ("closed", path),
+                };
+                json!({ "event": number, "kind": kind, "path": self.rel(path) })
+            })
+            .collect();
+        Reply::ok(json!({ "last": self.last_event, "events": events }))
+    }
+}
+
+/// Serve a vault's API at `address` until the process ends
+pub fn serve(vault: PathBuf, address: SocketAddr, token: String) -> Result<()> {
+    let server = Server::http(address).map_err(|e| anyhow!("Could not listen on {}: {}", address, e))?;
+    let mut service = VaultService::new(vault, token);
+    for mut request in server.incoming_requests() {
+        let authorization = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Authorization"))
+            .map(|header| header.value.to_string());
+        // Nothing of the body is read for a request without the token
+        let reply = if !service.authorized(authorization.as_deref()) {
+            Reply::unauthorized()
+        } else {
+            match read_body(request.as_reader()) {
+                Ok(body) => service.handle(request.method(), request.url(), authorization.as_deref(), &body),
+                Err(reply) => reply,
+            }
+        };
+        if reply.status >= 400 {
+            tracing::info!("{} {} -> {}", request.method(), request.url(), reply.status);
+        }
+        let response = Response::from_string(reply.body.to_string())
+            .with_status_code(reply.status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").expect("valid header"));
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_api() {
+        let vault = std::env::temp_dir().join(format!("robsidian_api_{}", std::process::id()));
+        std::fs::create_dir_all(vault.join("Sub")).unwrap();
+        std::fs::write(vault.join("Home.md"), "---\naliases: [Start]\n---\n# Home\nSee the garden.\n").unwrap();
+        let mut service = VaultService::new(vault.clone(), "abc".to_string());
+        let auth = Some("Bearer abc");
+        let get = |service: &mut VaultService, url: &str| service.handle(&Method::Get, url, auth, "");
+
+        assert_eq!(service.handle(&Method::Get, "/api/notes", None, "").status, 401);
+        assert_eq!(get(&mut service, "/api/notes").body[0]["aliases"], json!(["Start"]));
+        assert_eq!(get(&mut service, "/api/resolve?link=start%23Intro").body["path"], "Home.md");
+        assert_eq!(get(&mut service, "/api/search?q=garden&content=true").body[0]["line"], "See the garden.");
+        let mut settings = VaultSettings::default();
+        settings.set_folder_color(&vault, &vault, Some([255, 0, 0]));
+        settings.save(&vault).unwrap();
+        assert_eq!(get(&mut service, "/api/search?q=home").body[0]["color"], "#ff0000");
+        assert_eq!(get(&mut service, "/api/note?path=../secret.md").status, 400);
+        #[cfg(unix)]
+        {
+            let outside = std::env::temp_dir().join(format!("robsidian_api_outside_{}", std::process::id()));
+            std::fs::create_dir_all(&outside).unwrap();
+            std::fs::write(outside.join("secret.md"), "private").unwrap();
+            std::os::unix::fs::symlink(&outside, vault.join("Linked")).unwrap();
+            assert_eq!(get(&mut service, "/api/note?path=Linked/secret.md").status, 400);
+            let put = service.handle(&Method::Put, "/api/note?path=Linked/new.md", auth, "x");
+            assert_eq!(put.status, 400);
+            assert!(!outside.join("new.md").exists());
+            let _ = std::fs::remove_dir_all(outside);
+        }
+
+        let put = service.handle(&Method::Put, "/api/note?path=Sub/New+Note.md", auth, "---\naliases: [Fresh]\n---\nHi");
+        assert_eq!(put.status, 200);
+        assert_eq!(std::fs::read_to_string(vault.join("Sub/New Note.md")).unwrap(), "---\naliases: [Fresh]\n---\nHi");
+        assert_eq!(get(&mut service, "/api/resolve?link=Fresh").body["path"], "Sub/New Note.md");
+        assert_eq!(get(&mut service, "/api/note?path=Sub/New%20Note.md").body["body"], "Hi");
+
+        let events = get(&mut service, "/api/events?since=0").body;
+        let kinds: Vec<&str> = events["events"].as_array().unwrap().iter().map(|e| e["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["opened", "content", "frontmatter", "saved", "closed"]);
+        let since = events["last"].as_u64().unwrap();
+        assert!(get(&mut service, &format!("/api/events?since={}", since)).body["events"].as_array().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn test_read_body() {
+        assert_eq!(read_body("Hi".as_bytes()).unwrap(), "Hi");
+        assert_eq!(read_body(&[0xff, 0xfe][..]).unwrap_err().status, 400);
+        assert_eq!(read_body(std::io::repeat(b'a')).unwrap_err().status, 413);
+    }
+}
@@ -0,0 +1,376 @@
+//! Per-vault settings
+//!
+//! Stored inside the vault in `.robsidian/vault.json`, so they travel with the
+//! notes. Note paths are kept relative to the vault root.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::glossary::DEFAULT_GLOSSARY_NOTE;
+use super::inbox::{DEFAULT_ARCHIVE_FOLDER, DEFAULT_INBOX_FOLDER};
+use super::templates::DEFAULT_TEMPLATES_FOLDER;
+use super::web_capture::DEFAULT_READ_LATER_FOLDER;
+
+/// Folder inside a vault holding Robsidian's files
+pub const SETTINGS_DIR: &str = ".robsidian";
+
+/// A heading or block of a note kept in the bookmarks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// Note, relative to the vault
+    pub note: PathBuf,
+    /// Heading text, or `^id` for a block
+    pub anchor: String,
+}
+
+/// A bookmarked note with the headings and blocks bookmarked in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookmarkedNote {
+    pub path: PathBuf,
+    /// Whether the note itself is starred
+    pub starred: bool,
+    /// Headings and `^block` ids, in the order they were bookmarked
+    pub anchors: Vec<String>,
+}
+
+/// Default chrono format of the `created`/`updated` frontmatter fields
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// `created`/`updated` frontmatter fields maintained on save
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FrontmatterTimestamps {
+    pub enabled: bool,
+    /// chrono format the timestamps are written with
+    pub format: String,
+}
+
+impl Default for FrontmatterTimestamps {
+    fn default() -> Self {
+        Self { enabled: true, format: DEFAULT_TIMESTAMP_FORMAT.to_string() }
+    }
+}
+
+/// Settings belonging to one vault
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    /// Starred notes, in the order they were starred
+    pub starred: Vec<PathBuf>,
+    /// Bookmarked headings and blocks, in the order they were added
+    pub bookmarks: Vec<Bookmark>,
+    /// Headings and blocks shown as widgets in the sidebar, in the order
+    /// they were pinned
+    pub pinned_blocks: Vec<Bookmark>,
+    /// Folder holding note templates, relative to the vault
+    pub templates_folder: Option<PathBuf>,
+    /// Folder reviewed in the inbox, relative to the vault
+    pub inbox_folder: Option<PathBuf>,
+    /// Folder archived inbox notes are moved to, relative to the vault
+    pub archive_folder: Option<PathBuf>,
+    /// Folders whose notes are encrypted when saved, relative to the vault
+    pub encrypted_folders: Vec<PathBuf>,
+    /// Folder captured web articles are saved to, relative to the vault
+    pub read_later_folder: Option<PathBuf>,
+    /// Note whose abbreviations apply to every note, relative to the vault
+    pub glossary_note: Option<PathBuf>,
+    /// Spell out abbreviations where they first appear in exported notes
+    pub expand_abbreviations: bool,
+    /// Keep `created`/`updated` in the frontmatter of saved notes
+    pub timestamps: FrontmatterTimestamps,
+    /// Colors of folders, relative to the vault, as `#rrggbb`
+    pub folder_colors: BTreeMap<PathBuf, String>,
+    /// Colors of tags, written without `#`, as `#rrggbb`
+    pub tag_colors: BTreeMap<String, String>,
+}
+
+/// `#rrggbb` as red, green and blue
+pub fn parse_color(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim().strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Red, green and blue as `#rrggbb`
+pub fn format_color([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+impl VaultSettings {
+    fn settings_path(vault: &Path) -> PathBuf {
+        vault.join(SETTINGS_DIR).join("vault.json")
+    }
+
+    /// Load the settings of a vault (defaults if it has none yet)
+    pub fn load(vault: &Path) -> Result<Self> {
+        let path = Self::settings_path(vault);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read vault settings: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid vault settings: {}", path.display()))
+    }
+
+    /// Save the settings into the vault
+    pub fn save(&self, vault: &Path) -> Result<()> {
+        let path = Self::settings_path(vault);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to save vault settings: {}", path.display()))
+    }
+
+    /// Whether a note is starred
+    pub fn is_starred(&self, vault: &Path, note: &Path) -> bool {
+        note.strip_prefix(vault)
+            .is_ok_and(|rel| self.starred.iter().any(|starred| starred == rel))
+    }
+
+    /// Star or unstar a note, returning whether it is now starred.
+    /// Notes outside the vault can't be starred.
+    pub fn toggle_star(&mut self, vault: &Path, note: &Path) -> bool {
+        let Ok(rel) = note.strip_prefix(vault) else {
+            return false;
+        };
+        if let Some(idx) = self.starred.iter().position(|starred| starred == rel) {
+            self.starred.remove(idx);
+            false
+        } else {
+            self.starred.push(rel.to_path_buf());
+            true
+        }
+    }
+
+    /// Whether a heading or block of a note is bookmarked
+    pub fn is_bookmarked(&self, vault: &Path, note: &Path, anchor: &str) -> bool {
+        note.strip_prefix(vault)
+            .is_ok_and(|rel| self.bookmarks.iter().any(|b| b.note == rel && b.anchor == anchor))
+    }
+
+    /// Bookmark a heading or block, or remove its bookmark, returning whether
+    /// it is now bookmarked. Notes outside the vault can't be bookmarked.
+    pub fn toggle_bookmark(&mut self, vault: &Path, note: &Path, anchor: &str) -> bool {
+        let Ok(rel) = note.strip_prefix(vault) else {
+            return false;
+        };
+        if let Some(idx) = self.bookmarks.iter().position(|b| b.note == rel && b.anchor == anchor) {
+            self.bookmarks.remove(idx);
+            false
+        } else {
+            self.bookmarks.push(Bookmark { note: rel.to_path_buf(), anchor: anchor.to_string() });
+            true
+        }
+    }
+
+    /// Pin a heading or `^block` of a note to the sidebar, or unpin it.
+    /// Returns whether it is pinned now.
+    pub fn toggle_pin(&mut self, vault: &Path, note: &Path, anchor: &str) -> bool {
+        let Ok(rel) = note.strip_prefix(vault) else {
+            return false;
+        };
+        if let Some(idx) = self.pinned_blocks.iter().position(|b| b.note == rel && b.anchor == anchor) {
+            self.pinned_blocks.remove(idx);
+            false
+        } else {
+            self.pinned_blocks.push(Bookmark { note: rel.to_path_buf(), anchor: anchor.to_string() });
+            true
+        }
+    }
+
+    /// Carry a note's star, bookmarks and pinned blocks over to its new path
+    pub fn rename_note(&mut self, vault: &Path, from: &Path, to: &Path) {
+        let (Ok(from), Ok(to)) = (from.strip_prefix(vault), to.strip_prefix(vault)) else {
+            return;
+        };
+        for starred in self.starred.iter_mut().filter(|starred| *starred == from) {
+            *starred = to.to_path_buf();
+        }
+        for bookmark in self.bookmarks.iter_mut().chain(&mut self.pinned_blocks).filter(|b| b.note == from) {
+            bookmark.note = to.to_path_buf();
+        }
+    }
+
+    /// Starred notes and notes with bookmarks, starred ones first, each with
+    /// its bookmarked headings and blocks
+    pub fn bookmarked_notes(&self, vault: &Path) -> Vec<BookmarkedNote> {
+        let mut notes: Vec<BookmarkedNote> = self
+            .starred
+            .iter()
+            .map(|rel| BookmarkedNote { path: vault.join(rel), starred: true, anchors: Vec::new() })
+            .collect();
+        for bookmark in &self.bookmarks {
+            let path = vault.join(&bookmark.note);
+            match notes.iter_mut().find(|note| note.path == path) {
+                Some(note) => note.anchors.push(bookmark.anchor.clone()),
+                None => notes.push(BookmarkedNote { path, starred: false, anchors: vec![bookmark.anchor.clone()] }),
+            }
+        }
+        notes
+    }
+
+    /// Folder holding the vault's note templates
+    pub fn templates_dir(&self, vault: &Path) -> PathBuf {
+        vault.join(
+            self.templates_folder
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_TEMPLATES_FOLDER)),
+        )
+    }
+
+    /// Folder reviewed in the inbox
+    pub fn inbox_dir(&self, vault: &Path) -> PathBuf {
+        vault.join(self.inbox_folder.as_deref().unwrap_or(Path::new(DEFAULT_INBOX_FOLDER)))
+    }
+
+    /// Folder archived inbox notes are moved to
+    pub fn archive_dir(&self, vault: &Path) -> PathBuf {
+        vault.join(self.archive_folder.as_deref().unwrap_or(Path::new(DEFAULT_ARCHIVE_FOLDER)))
+    }
+
+    /// Folder captured web articles are saved to
+    pub fn read_later_dir(&self, vault: &Path) -> PathBuf {
+        vault.join(self.read_later_folder.as_deref().unwrap_or(Path::new(DEFAULT_READ_LATER_FOLDER)))
+    }
+
+    /// Note whose abbreviations apply to every note of the vault
+    pub fn glossary_path(&self, vault: &Path) -> PathBuf {
+        vault.join(self.glossary_note.as_deref().unwrap_or(Path::new(DEFAULT_GLOSSARY_NOTE)))
+    }
+
+    /// Whether a note is inside a folder marked for encryption
+    pub fn in_encrypted_folder(&self, vault: &Path, note: &Path) -> bool {
+        note.strip_prefix(vault)
+            .is_ok_and(|rel| self.encrypted_folders.iter().any(|folder| rel.starts_with(folder)))
+    }
+
+    /// Mark or unmark a folder for encryption, returning whether it is now
+    /// marked
+    pub fn toggle_encrypted_folder(&mut self, vault: &Path, folder: &Path) -> bool {
+        let Ok(rel) = folder.strip_prefix(vault) else {
+            return false;
+        };
+        if let Some(idx) = self.encrypted_folders.iter().position(|f| f == rel) {
+            self.encrypted_folders.remove(idx);
+            false
+        } else {
+            self.encrypted_folders.push(rel.to_path_buf());
+            true
+        }
+    }
+
+    /// Color of a note or folder: that of the first of its `tags` with one,
+    /// else that of the innermost colored folder it is, or is in
+    pub fn color_of(&self, vault: &Path, path: &Path, tags: &[String]) -> Option<[u8; 3]> {
+        let tag_color = tags.iter().find_map(|tag| {
+            let tag = tag.trim_start_matches('#');
+            self.tag_colors.iter().find(|(name, _)| name.eq_ignore_ascii_case(tag)).and_then(|(_, c)| parse_color(c))
+        });
+        if tag_color.is_some() {
+            return tag_color;
+        }
+        let rel = path.strip_prefix(vault).ok()?;
+        rel.ancestors().find_map(|folder| self.folder_colors.get(folder).and_then(|color| parse_color(color)))
+    }
+
+    /// Color a folder, or clear its color with `None`
+    pub fn set_folder_color(&mut self, vault: &Path, folder: &Path, color: Option<[u8; 3]>) {
+        let Ok(rel) = folder.strip_prefix(vault) else {
+            return;
+        };
+        match color {
+            Some(color) => self.folder_colors.insert(rel.to_path_buf(), format_color(color)),
+            None => self.folder_colors.remove(rel),
+        };
+    }
+
+    /// Color a tag, or clear its color with `None`
+    pub fn set_tag_color(&mut self, tag: &str, color: Option<[u8; 3]>) {
+        let tag = tag.trim_start_matches('#').to_string();
+        match color {
+            Some(color) => self.tag_colors.insert(tag, format_color(color)),
+            None => self.tag_colors.remove(&tag),
+        };
+    }
+
+    /// Absolute paths of the starred notes
+    pub fn starred_notes(&self, vault: &Path) -> Vec<PathBuf> {
+        self.starred.iter().map(|rel| vault.join(rel)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_of() {
+        let vault = Path::new("/vault");
+        let mut settings = VaultSettings::default();
+        settings.set_folder_color(vault, &vault.join("Work"), Some([255, 0, 0]));
+        settings.set_folder_color(vault, &vault.join("Work/Done"), Some([0, 128, 0]));
+        settings.set_tag_color("#urgent", Some([0, 0, 255]));
+        assert_eq!(settings.folder_colors.get(Path::new("Work")).map(String::as_str), Some("#ff0000"));
+
+        let note = vault.join("Work/Done/Report.md");
+        assert_eq!(settings.color_of(vault, &note, &[]), Some([0, 128, 0]));
+        assert_eq!(settings.color_of(vault, &vault.join("Work/Plan.md"), &[]), Some([255, 0, 0]));
+        assert_eq!(settings.color_of(vault, &note, &["Urgent".to_string()]), Some([0, 0, 255]));
+        assert_eq!(settings.color_of(vault, &vault.join("Home.md"), &[]), None);
+
+        settings.set_folder_color(vault, &vault.join("Work/Done"), None);
+        assert_eq!(settings.color_of(vault, &note, &[]), Some([255, 0, 0]));
+        assert_eq!(parse_color("#12ab"), None);
+    }
+
+    #[test]
+    fn test_toggle_star() {
+        let vault = Path::new("/vault");
+        let note = vault.join("folder/Note.md");
+        let mut settings = VaultSettings::default();
+
+        assert!(settings.toggle_star(vault, &note));
+        assert!(settings.is_starred(vault, &note));
+        assert_eq!(settings.starred, vec![PathBuf::from("folder/Note.md")]);
+        assert_eq!(settings.starred_notes(vault), vec![note.clone()]);
+
+        assert!(!settings.toggle_star(vault, &note));
+        assert!(!settings.is_starred(vault, &note));
+        assert!(!settings.toggle_star(vault, Path::new("/elsewhere/Note.md")));
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let vault = Path::new("/vault");
+        let starred = vault.join("Starred.md");
+        let note = vault.join("folder/Note.md");
+        let mut settings = VaultSettings::default();
+
+        settings.toggle_star(vault, &starred);
+        assert!(settings.toggle_bookmark(vault, &note, "Intro"));
+        assert!(settings.toggle_bookmark(vault, &note, "^summary"));
+        assert!(settings.toggle_bookmark(vault, &starred, "Plan"));
+        assert!(settings.is_bookmarked(vault, &note, "Intro"));
+
+        let notes = settings.bookmarked_notes(vault);
+        assert_eq!(notes.len(), 2);
+        assert_eq!((notes[0].path.clone(), notes[0].starred), (starred.clone(), true));
+        assert_eq!(notes[0].anchors, ["Plan"]);
+        assert_eq!((notes[1].path.clone(), notes[1].starred), (note.clone(), false));
+        assert_eq!(notes[1].anchors, ["Intro", "^summary"]);
+
+        let renamed = vault.join("Renamed.md");
+        settings.rename_note(vault, &note, &renamed);
+        assert!(settings.is_bookmarked(vault, &renamed, "^summary"));
+        assert!(!settings.toggle_bookmark(vault, &renamed, "Intro"));
+        assert_eq!(settings.bookmarked_notes(vault)[1].anchors, ["^summary"]);
+    }
+}
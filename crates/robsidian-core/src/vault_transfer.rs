@@ -0,0 +1,250 @@
+//! Moving and copying notes between vaults
+//!
+//! A note lands in the same folder of the other vault, numbered if a note of
+//! that name is there already. The attachments it embeds can come along,
+//! keeping their place relative to the vault root; an attachment that would
+//! overwrite a different file is numbered and the embed updated to match.
+//! Attachments are always copied, since other notes may still use them.
+//!
+//! When a note is moved, wiki links to it from the notes left behind would
+//! break, so they can be rewritten into links to the note's new file.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use walkdir::WalkDir;
+
+use super::file_system::{copy_into, get_markdown_files, inside_vault};
+
+/// Characters escaped in the `file://` links left behind by a move
+const LINK_PATH: &AsciiSet = &CONTROLS.add(b' ').add(b'(').add(b')').add(b'#').add(b'%').add(b'?');
+
+/// How a note is transferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferOptions {
+    /// Remove the note from its vault afterwards
+    pub remove_original: bool,
+    /// Copy the files the note embeds
+    pub attachments: bool,
+    /// Point links to a moved note at its new file
+    pub fix_links: bool,
+}
+
+/// What a transfer did
+#[derive(Debug, Default)]
+pub struct TransferReport {
+    /// The note in the other vault
+    pub note: PathBuf,
+    /// Attachments copied along
+    pub attachments: usize,
+    /// Notes of the source vault whose links were rewritten
+    pub relinked: Vec<PathBuf>,
+}
+
+/// An embed in a note: its full text and the file it refers to
+pub struct Embed {
+    pub text: String,
+    pub src: String,
+    pub wiki: bool,
+}
+
+/// The wiki and Markdown embeds of a note
+pub fn embeds(content: &str) -> Vec<Embed> {
+    let wiki = regex_lite::Regex::new(r"!\[\[([^\]|#]+)(?:[|#][^\]]*)?\]\]").unwrap();
+    let markdown = regex_lite::Regex::new(r"!\[[^\]]*\]\(<?([^)>\s]+)>?(?:\s+[^)]*)?\)").unwrap();
+    let mut found: Vec<Embed> = wiki
+        .captures_iter(content)
+        .map(|caps| Embed { text: caps[0].to_string(), src: caps[1].trim().to_string(), wiki: true })
+        .collect();
+    found.extend(markdown.captures_iter(content).map(|caps| Embed {
+        text: caps[0].to_string(),
+        src: caps[1].trim().to_string(),
+        wiki: false,
+    }));
+    found
+}
+
+/// The file an embed refers to: a path relative to the note's folder or one
+/// of its parents, or for wiki embeds a file of that name anywhere in the
+/// vault. Notes and web addresses aren't attachments, and neither is
+/// anything outside the vault: paths going up or starting at the root, and
+/// files reached through symlinks leading out of it.
+pub fn resolve_attachment(vault: &Path, note: &Path, embed: &Embed) -> Option<PathBuf> {
+    if embed.src.contains("://") {
+        return None;
+    }
+    let src = Path::new(&embed.src);
+    if src.extension().is_none_or(|ext| ext == "md") {
+        return None;
+    }
+    if !src.components().all(|part| matches!(part, Component::Normal(_))) {
+        return None;
+    }
+    let folder = note.parent()?;
+    let found = folder
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(vault))
+        .map(|ancestor| ancestor.join(src))
+        .find(|candidate| candidate.is_file() && inside_vault(vault, candidate));
+    if found.is_some() || !embed.wiki {
+        return found;
+    }
+    let name = src.file_name()?;
+    WalkDir::new(vault)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .find(|entry| entry.file_type().is_file() && entry.file_name() == name && inside_vault(vault, entry.path()))
+        .map(|entry| entry.into_path())
+}
+
+/// Copy an attachment to the same place in the target vault, reusing an
+/// identical file already there
+fn copy_attachment(file: &Path, source_vault: &Path, target_vault: &Path) -> Result<PathBuf> {
+    let rel = file.strip_prefix(source_vault)?;
+    let target = target_vault.join(rel);
+    if target.is_file() && std::fs::read(&target)? == std::fs::read(file)? {
+        return Ok(target);
+    }
+    let folder = target.parent().context("Attachment has no folder")?;
+    std::fs::create_dir_all(folder)?;
+    copy_into(file, folder)
+}
+
+/// Rewrite the wiki links in `content` that point at `name` (the note's file
+/// name) or `rel` (its path in the vault, without extension) into a Markdown
+/// link to `url`. Embeds are left alone.
+fn relink(content: &str, name: &str, rel: &str, url: &str) -> String {
+    let link = regex_lite::Regex::new(r"(!?)\[\[([^\]|#]+)(#[^\]|]*)?(?:\|([^\]]+))?\]\]").unwrap();
+    link.replace_all(content, |caps: &regex_lite::Captures| {
+        let target = caps[2].trim();
+        let matches = target.eq_ignore_ascii_case(name) || target.eq_ignore_ascii_case(rel);
+        if !caps[1].is_empty() || !matches {
+            return caps[0].to_string();
+        }
+        let text = caps.get(4).map_or(target, |alias| alias.as_str());
+        format!("[{}]({})", text, url)
+    })
+    .into_owned()
+}
+
+/// Move or copy a note from `source_vault` into `target_vault`
+pub fn transfer_note(
+    note: &Path,
+    source_vault: &Path,
+    target_vault: &Path,
+    options: TransferOptions,
+) -> Result<TransferReport> {
+    let rel = note.strip_prefix(source_vault).context("The note isn't in the vault")?;
+    if target_vault.starts_with(source_vault) || source_vault.starts_with(target_vault) {
+        bail!("Pick a different vault");
+    }
+    let folder = target_vault.join(rel.parent().unwrap_or(Path::new("")));
+    std::fs::create_dir_all(&folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+    let copy = copy_into(note, &folder)?;
+    let mut report = TransferReport { note: copy.clone(), ..Default::default() };
+
+    if options.attachments {
+        let mut content = std::fs::read_to_string(&copy).unwrap_or_default();
+        let original = content.clone();
+        for embed in embeds(&original) {
+            let Some(file) = resolve_attachment(source_vault, note, &embed) else {
+                continue;
+            };
+            let copied = copy_attachment(&file, source_vault, target_vault)?;
+            report.attachments += 1;
+            if copied.file_name() != file.file_name() {
+                // Renamed to avoid a clash: embed the new file by its path
+                let new_rel = copied.strip_prefix(target_vault)?.to_string_lossy().replace('\\', "/");
+                let updated = if embed.wiki {
+                    embed.text.replacen(&embed.src, &new_rel, 1)
+                } else {
+                    embed.text.replacen(&embed.src, &new_rel.replace(' ', "%20"), 1)
+                };
+                content = content.replace(&embed.text, &updated);
+            }
+        }
+        if content != original {
+            std::fs::write(&copy, content)?;
+        }
+    }
+
+    if options.remove_original {
+        std::fs::remove_file(note).with_context(|| format!("Failed to remove {}", note.display()))?;
+        if options.fix_links {
+            let name = note.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let rel_name = rel.with_extension("").to_string_lossy().replace('\\', "/");
+            let url = format!("file://{}", utf8_percent_encode(&copy.to_string_lossy(), LINK_PATH));
+            for other in get_markdown_files(source_vault) {
+                let Ok(content) = std::fs::read_to_string(&other) else {
+                    continue;
+                };
+                let updated = relink(&content, &name, &rel_name, &url);
+                if updated != content {
+                    std::fs::write(&other, updated)?;
+                    report.relinked.push(other);
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_note_between_vaults() {
+        let root = std::env::temp_dir().join(format!("robsidian_transfer_{}", std::process::id()));
+        let (source, target) = (root.join("A"), root.join("B"));
+        std::fs::create_dir_all(source.join("Projects")).unwrap();
+        std::fs::create_dir_all(source.join("attachments")).unwrap();
+        std::fs::create_dir_all(target.join("Projects")).unwrap();
+        std::fs::create_dir_all(target.join("attachments")).unwrap();
+        std::fs::write(source.join("Projects/Plan.md"), "Chart: ![[chart.png]]\n").unwrap();
+        std::fs::write(source.join("attachments/chart.png"), "new chart").unwrap();
+        std::fs::write(source.join("Home.md"), "See [[Plan|the plan]] and [[Other]].\n").unwrap();
+        std::fs::write(target.join("Projects/Plan.md"), "Their plan\n").unwrap();
+        std::fs::write(target.join("attachments/chart.png"), "old chart").unwrap();
+
+        let options = TransferOptions { remove_original: true, attachments: true, fix_links: true };
+        let report = transfer_note(&source.join("Projects/Plan.md"), &source, &target, options).unwrap();
+
+        assert_eq!(report.note, target.join("Projects/Plan 1.md"));
+        assert_eq!(report.attachments, 1);
+        assert_eq!(std::fs::read_to_string(&report.note).unwrap(), "Chart: ![[attachments/chart 1.png]]\n");
+        assert_eq!(std::fs::read_to_string(target.join("attachments/chart 1.png")).unwrap(), "new chart");
+        assert!(!source.join("Projects/Plan.md").exists());
+        let home = std::fs::read_to_string(source.join("Home.md")).unwrap();
+        assert!(home.starts_with("See [the plan](file://"));
+        assert!(home.contains("/B/Projects/Plan%201.md) and [[Other]]."));
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_attachments_stay_in_vault() {
+        let root = std::env::temp_dir().join(format!("robsidian_transfer_outside_{}", std::process::id()));
+        let vault = root.join("Vault");
+        std::fs::create_dir_all(vault.join("Notes")).unwrap();
+        std::fs::write(root.join("secret.png"), "outside").unwrap();
+        std::fs::write(vault.join("inside.png"), "inside").unwrap();
+        let note = vault.join("Notes/Note.md");
+        let absolute = root.join("secret.png").to_string_lossy().into_owned();
+        let content = format!("![](inside.png) ![](../../secret.png) ![]({absolute})\n");
+        std::fs::write(&note, &content).unwrap();
+
+        let found: Vec<_> = embeds(&content).iter().map(|embed| resolve_attachment(&vault, &note, embed)).collect();
+        assert_eq!(found, vec![Some(vault.join("inside.png")), None, None]);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&root, vault.join("linked")).unwrap();
+            let embed = Embed { text: "![[linked/secret.png]]".into(), src: "linked/secret.png".into(), wiki: true };
+            assert_eq!(resolve_attachment(&vault, &note, &embed), None);
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,496 @@
+//! Read-later web capture
+//!
+//! Fetches a web page, keeps only its readable article (the `<article>` or
+//! `<main>` element, or else the element holding the most paragraph text)
+//! and converts it to markdown. Images are downloaded next to the note so the
+//! capture still reads well offline. The page's address, title and capture
+//! time go into the note's frontmatter.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use scraper::{ElementRef, Html, Node, Selector};
+use url::Url;
+
+use super::export::slugify;
+use super::file_system::unique_path;
+
+/// Folder captured articles are saved to, relative to the vault
+pub const DEFAULT_READ_LATER_FOLDER: &str = "Read Later";
+/// Folder next to the captured notes holding their images
+const ATTACHMENTS_FOLDER: &str = "attachments";
+/// Largest image downloaded; bigger ones stay remote links
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+/// Most images downloaded for one article
+const MAX_IMAGES: usize = 50;
+const USER_AGENT: &str = concat!("Robsidian/", env!("CARGO_PKG_VERSION"));
+
+/// Elements that never hold article text
+const SKIPPED: &[&str] = &[
+    "script", "style", "nav", "header", "footer", "aside", "form", "noscript", "iframe", "svg", "button",
+];
+
+/// The readable part of a page
+#[derive(Debug, Clone)]
+pub struct Article {
+    pub title: String,
+    pub author: Option<String>,
+    pub site: Option<String>,
+    pub markdown: String,
+    /// Absolute URLs of the images referenced in `markdown`
+    pub images: Vec<String>,
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("valid selector")
+}
+
+/// `content` of the first `<meta>` matching a selector
+fn meta(html: &Html, css: &str) -> Option<String> {
+    html.select(&selector(css))
+        .filter_map(|el| el.value().attr("content"))
+        .map(|content| content.trim().to_string())
+        .find(|content| !content.is_empty())
+}
+
+fn text_of(el: ElementRef) -> String {
+    el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Element holding the article: a semantic container if the page has one,
+/// otherwise the element with the most paragraph text
+fn content_root(html: &Html) -> Option<ElementRef<'_>> {
+    for css in ["article", "main", "[role=main]"] {
+        if let Some(el) = html.select(&selector(css)).next() {
+            return Some(el);
+        }
+    }
+
+    let mut scores = HashMap::new();
+    for paragraph in html.select(&selector("p")) {
+        if let Some(parent) = paragraph.parent() {
+            *scores.entry(parent.id()).or_insert(0) += text_of(paragraph).len();
+        }
+    }
+    scores
+        .into_iter()
+        .max_by_key(|(_, score)| *score)
+        .and_then(|(id, _)| html.tree.get(id))
+        .and_then(ElementRef::wrap)
+        .or_else(|| html.select(&selector("body")).next())
+}
+
+/// Extract the readable article of a page as markdown. Links and images are
+/// made absolute against `base`.
+pub fn extract_article(html: &str, base: &Url) -> Article {
+    let html = Html::parse_document(html);
+    let title = meta(&html, "meta[property='og:title']")
+        .or_else(|| html.select(&selector("title")).next().map(text_of))
+        .or_else(|| html.select(&selector("h1")).next().map(text_of))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| base.host_str().unwrap_or("Untitled").to_string());
+
+    let mut converter = Converter { base, images: Vec::new() };
+    let mut markdown = String::new();
+    if let Some(root) = content_root(&html) {
+        converter.children(root, &mut markdown, 0);
+    }
+
+    Article {
+        title,
+        author: meta(&html, "meta[name='author']"),
+        site: meta(&html, "meta[property='og:site_name']"),
+        markdown: tidy(&markdown),
+        images: converter.images,
+    }
+}
+
+/// HTML to markdown conversion of an article
+struct Converter<'a> {
+    base: &'a Url,
+    images: Vec<String>,
+}
+
+impl Converter<'_> {
+    fn resolve(&self, href: &str) -> Option<String> {
+        let url = self.base.join(href.trim()).ok()?;
+        matches!(url.scheme(), "http" | "https" | "mailto").then(|| url.to_string())
+    }
+
+    fn children(&mut self, el: ElementRef, out: &mut String, depth: usize) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(text) => push_text(out, text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child, out, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Children rendered on a single line
+    fn inline(&mut self, el: ElementRef, depth: usize) -> String {
+        let mut text = String::new();
+        self.children(el, &mut text, depth);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn element(&mut self, el: ElementRef, out: &mut String, depth: usize) {
+        let name = el.value().name();
+        match name {
+            _ if SKIPPED.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(1);
+                let text = self.inline(el, depth);
+                if !text.is_empty() {
+                    block(out, &format!("{} {}", "#".repeat(level), text));
+                }
+            }
+            "p" | "div" | "section" | "article" | "main" | "figure" | "figcaption" | "dl" | "dd" | "dt" => {
+                break_block(out);
+                self.children(el, out, depth);
+                break_block(out);
+            }
+            "br" => out.push('\n'),
+            "hr" => block(out, "---"),
+            "a" => {
+                let text = self.inline(el, depth);
+                match el.value().attr("href").and_then(|href| self.resolve(href)) {
+                    Some(url) if !text.is_empty() && !href_is_fragment(el) => {
+                        out.push_str(&format!("[{}](<{}>)", text, url))
+                    }
+                    _ => out.push_str(&text),
+                }
+            }
+            "img" => {
+                let src = el.value().attr("src").or_else(|| el.value().attr("data-src"));
+                if let Some(url) = src.and_then(|src| self.resolve(src)) {
+                    let alt = el.value().attr("alt").unwrap_or_default().replace(['[', ']'], "");
+                    out.push_str(&format!("![{}](<{}>)", alt.trim(), url));
+                    if !self.images.contains(&url) {
+                        self.images.push(url);
+                    }
+                }
+            }
+            "strong" | "b" => wrap(out, "**", &self.inline(el, depth)),
+            "em" | "i" => wrap(out, "*", &self.inline(el, depth)),
+            "del" | "s" => wrap(out, "~~", &self.inline(el, depth)),
+            "code" => wrap(out, "`", &text_of(el)),
+            "pre" => {
+                let code: String = el.text().collect();
+                block(out, &format!("```\n{}\n```", code.trim_end_matches('\n')));
+            }
+            "ul" | "ol" => {
+                break_block(out);
+                let items = el.child_elements().filter(|child| child.value().name() == "li");
+                for (number, item) in items.enumerate() {
+                    let marker = if name == "ol" { format!("{}.", number + 1) } else { "-".to_string() };
+                    let mut text = String::new();
+                    self.children(item, &mut text, depth + 1);
+                    let text = tidy(&text);
+                    let indent = "  ".repeat(depth);
+                    let continuation = " ".repeat(marker.len() + 1);
+                    for (idx, line) in text.lines().filter(|line| !line.trim().is_empty()).enumerate() {
+                        if idx == 0 {
+                            out.push_str(&format!("{}{} {}\n", indent, marker, line));
+                        } else if line.starts_with("  ") || line.starts_with("- ") || starts_numbered(line) {
+                            // Nested list items keep their own indentation
+                            out.push_str(&format!("{}{}\n", indent, line));
+                        } else {
+                            out.push_str(&format!("{}{}{}\n", indent, continuation, line));
+                        }
+                    }
+                }
+                break_block(out);
+            }
+            "blockquote" => {
+                let mut text = String::new();
+                self.children(el, &mut text, depth);
+                let quoted: Vec<String> = tidy(&text)
+                    .lines()
+                    .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                    .collect();
+                block(out, &quoted.join("\n"));
+            }
+            "table" => {
+                let rows: Vec<Vec<String>> = el
+                    .select(&selector("tr"))
+                    .map(|row| {
+                        row.child_elements()
+                            .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                            .map(|cell| self.inline(cell, depth).replace('|', "\\|"))
+                            .collect()
+                    })
+                    .filter(|cells: &Vec<String>| !cells.is_empty())
+                    .collect();
+                if let Some(header) = rows.first() {
+                    let mut table = format!("| {} |\n|{}|", header.join(" | "), " --- |".repeat(header.len()));
+                    for row in &rows[1..] {
+                        table.push_str(&format!("\n| {} |", row.join(" | ")));
+                    }
+                    block(out, &table);
+                }
+            }
+            _ => self.children(el, out, depth),
+        }
+    }
+}
+
+/// Links to another spot of the same page point nowhere in the note
+fn href_is_fragment(el: ElementRef) -> bool {
+    el.value().attr("href").is_some_and(|href| href.starts_with('#'))
+}
+
+fn starts_numbered(line: &str) -> bool {
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && line[digits..].starts_with(". ")
+}
+
+/// Append page text, collapsing whitespace like a browser does
+fn push_text(out: &mut String, text: &str) {
+    let at_line_start = out.is_empty() || out.ends_with('\n');
+    if text.starts_with(char::is_whitespace) && !at_line_start && !out.ends_with(' ') {
+        out.push(' ');
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    out.push_str(&words.join(" "));
+    if !words.is_empty() && text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn wrap(out: &mut String, marker: &str, text: &str) {
+    if !text.is_empty() {
+        out.push_str(&format!("{}{}{}", marker, text, marker));
+    }
+}
+
+/// Start a new paragraph unless one was just started
+fn break_block(out: &mut String) {
+    let trimmed = out.trim_end_matches(' ').len();
+    out.truncate(trimmed);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+    }
+}
+
+fn block(out: &mut String, text: &str) {
+    break_block(out);
+    out.push_str(text);
+    break_block(out);
+}
+
+/// Trim trailing spaces and collapse runs of blank lines
+fn tidy(markdown: &str) -> String {
+    let mut output = String::new();
+    let mut blank = 0;
+    for line in markdown.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !output.is_empty() {
+            output.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        blank = 0;
+        output.push_str(line);
+    }
+    output
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .user_agent(USER_AGENT)
+        .build()
+}
+
+/// File extension of a downloaded image
+fn image_extension(url: &str, content_type: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    for ext in ["png", "jpg", "jpeg", "gif", "webp", "svg", "avif"] {
+        if path.ends_with(&format!(".{}", ext)) {
+            return ext;
+        }
+    }
+    match content_type {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        _ => "jpg",
+    }
+}
+
+/// Download an image into `path` (without extension), returning the file
+/// written
+fn download_image(agent: &ureq::Agent, url: &str, path: &Path) -> Result<PathBuf> {
+    let response = agent.get(url).call()?;
+    if !response.content_type().starts_with("image/") {
+        bail!("Not an image: {}", url);
+    }
+    let path = path.with_extension(image_extension(url, response.content_type()));
+    let mut bytes = Vec::new();
+    response.into_reader().take(MAX_IMAGE_BYTES + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+        bail!("Image too large: {}", url);
+    }
+    std::fs::write(&path, bytes).with_context(|| format!("Failed to write image: {}", path.display()))?;
+    Ok(path)
+}
+
+/// Text usable as a note's file name
+fn file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .filter(|c| !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']'))
+        .take(100)
+        .collect();
+    let name = name.trim().trim_start_matches('.');
+    if name.is_empty() { "Untitled".to_string() } else { name.to_string() }
+}
+
+fn yaml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Fetch a page and save its article as a new note in `folder`, returning
+/// the note's path. Images that fail to download stay remote links.
+pub fn capture(url: &str, folder: &Path) -> Result<PathBuf> {
+    let base = Url::parse(url.trim()).with_context(|| format!("Not a web address: {}", url))?;
+    if !matches!(base.scheme(), "http" | "https") {
+        bail!("Only http and https pages can be captured");
+    }
+    let agent = agent();
+    let html = agent
+        .get(base.as_str())
+        .call()
+        .with_context(|| format!("Failed to fetch {}", base))?
+        .into_string()
+        .context("Failed to read the page")?;
+    let article = extract_article(&html, &base);
+    if article.markdown.is_empty() {
+        bail!("No readable content found on {}", base);
+    }
+
+    std::fs::create_dir_all(folder).with_context(|| format!("Failed to create folder: {}", folder.display()))?;
+    let name = file_name(&article.title);
+    let note = unique_path(folder, &name, "md");
+
+    let mut markdown = article.markdown.clone();
+    let attachments = folder.join(ATTACHMENTS_FOLDER);
+    let stem = slugify(&note.file_stem().unwrap_or_default().to_string_lossy());
+    for (idx, image) in article.images.iter().take(MAX_IMAGES).enumerate() {
+        if idx == 0 {
+            std::fs::create_dir_all(&attachments)?;
+        }
+        let target = attachments.join(format!("{}-{}", stem, idx + 1));
+        if let Ok(path) = download_image(&agent, image, &target) {
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            markdown = markdown.replace(
+                &format!("](<{}>)", image),
+                &format!("]({}/{})", ATTACHMENTS_FOLDER, file),
+            );
+        }
+    }
+
+    let mut frontmatter = format!("---\ntitle: {}\nsource: {}\n", yaml_string(&article.title), yaml_string(base.as_str()));
+    if let Some(author) = &article.author {
+        frontmatter.push_str(&format!("author: {}\n", yaml_string(author)));
+    }
+    if let Some(site) = &article.site {
+        frontmatter.push_str(&format!("site: {}\n", yaml_string(site)));
+    }
+    frontmatter.push_str(&format!(
+        "captured: {}\ntags: [read-later]\n---\n\n",
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    ));
+
+    std::fs::write(&note, format!("{}# {}\n\n{}\n", frontmatter, article.title, markdown))
+        .with_context(|| format!("Failed to write note: {}", note.display()))?;
+    Ok(note)
+}
+
+/// Captures running in the background
+pub struct WebCaptures {
+    results_tx: Sender<(String, Result<PathBuf>)>,
+    results: Receiver<(String, Result<PathBuf>)>,
+    running: usize,
+}
+
+impl Default for WebCaptures {
+    fn default() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        Self { results_tx, results, running: 0 }
+    }
+}
+
+impl WebCaptures {
+    /// Capture a page into `folder` on a background thread
+    pub fn start(&mut self, url: String, folder: PathBuf) {
+        let results = self.results_tx.clone();
+        self.running += 1;
+        thread::spawn(move || {
+            let result = capture(&url, &folder);
+            let _ = results.send((url, result));
+        });
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.running > 0
+    }
+
+    /// Collect the captures finished since the last call (non-blocking),
+    /// with the address each was started for
+    pub fn poll(&mut self) -> Vec<(String, Result<PathBuf>)> {
+        let results: Vec<_> = self.results.try_iter().collect();
+        self.running -= results.len();
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_article() {
+        let html = r#"<html><head>
+            <title>Ignored</title>
+            <meta property="og:title" content="Slow Tools">
+            <meta name="author" content="Ada">
+            </head><body>
+            <nav><a href="/">Home</a></nav>
+            <article>
+              <h1>Slow Tools</h1>
+              <p>Some <strong>bold</strong> and <a href="/more">a link</a>.</p>
+              <img src="img/a.png" alt="Chart">
+              <ul><li>One</li><li>Two<ul><li>Inner</li></ul></li></ul>
+              <pre><code>let x = 1;</code></pre>
+              <script>track()</script>
+            </article>
+            </body></html>"#;
+        let base = Url::parse("https://example.com/posts/slow").unwrap();
+        let article = extract_article(html, &base);
+
+        assert_eq!(article.title, "Slow Tools");
+        assert_eq!(article.author.as_deref(), Some("Ada"));
+        assert_eq!(article.images, vec!["https://example.com/posts/img/a.png"]);
+        assert_eq!(
+            article.markdown,
+            "# Slow Tools\n\nSome **bold** and [a link](<https://example.com/more>).\n\n\
+             ![Chart](<https://example.com/posts/img/a.png>)\n\n- One\n- Two\n  - Inner\n\n```\nlet x = 1;\n```"
+        );
+        assert_eq!(file_name("A/B: C?"), "AB C");
+    }
+}
@@ -0,0 +1,379 @@
+//! Read-only web view of a vault
+//!
+//! A small HTTP server for reading notes from a phone or another computer on
+//! the local network while the app runs. Notes are rendered like the HTML
+//! export, at the same relative addresses (`Folder/Note.html`), so wiki links
+//! between them work; attachments are served as they are. Every request needs
+//! the access token, given once as `?token=...` and then remembered in a
+//! cookie.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use anyhow::{anyhow, bail, Result};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use tiny_http::{Header, Response, Server};
+
+use super::document::Document;
+use super::export::{note_to_html, page_path};
+use super::note_index::NoteIndex;
+
+/// Cookie holding the access token
+const COOKIE: &str = "robsidian_token";
+/// How long the server reuses its note index before rebuilding it
+const INDEX_MAX_AGE: Duration = Duration::from_secs(30);
+/// Characters escaped in a path segment of a link
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+const STYLE: &str = "body{font-family:-apple-system,system-ui,sans-serif;max-width:46rem;margin:0 auto;padding:1rem;\
+line-height:1.6;color:#222;background:#fff}a{color:#7c5cff}img{max-width:100%}pre{overflow-x:auto;background:#f4f4f4;\
+padding:.75rem;border-radius:4px}code{background:#f4f4f4;padding:0 .2rem}table{border-collapse:collapse}\
+td,th{border:1px solid #ccc;padding:.25rem .5rem}nav{margin-bottom:1rem;font-size:.9rem}ul.notes{padding-left:1.2rem}\
+@media (prefers-color-scheme:dark){body{color:#ddd;background:#1e1e1e}pre,code{background:#2a2a2a}}";
+
+/// A new random access token
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Refuse a token that can't be sent in a URL and a cookie as it is
+pub fn check_token(token: &str) -> Result<()> {
+    if token.is_empty() {
+        bail!("The access token is empty");
+    }
+    if !token.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~')) {
+        bail!("The access token may only hold letters, digits, '-', '_', '.' and '~'");
+    }
+    Ok(())
+}
+
+/// Address other devices on the network can reach this computer at, if it
+/// has one. No packets are sent; connecting a UDP socket only picks the
+/// outgoing interface.
+pub fn lan_address() -> Option<std::net::IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        STYLE,
+        body
+    )
+}
+
+/// A response to send
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+    /// Token to remember in a cookie
+    set_token: bool,
+}
+
+impl Reply {
+    fn html(status: u16, html: String) -> Self {
+        Self { status, content_type: "text/html; charset=utf-8", body: html.into_bytes(), set_token: false }
+    }
+
+    fn not_found() -> Self {
+        Self::html(404, page("Not found", "<p>Not found. <a href=\"/\">All notes</a></p>"))
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Serves a vault's notes
+struct Site {
+    vault: PathBuf,
+    /// The vault with symlinks resolved, which every served file must be in
+    root: PathBuf,
+    token: String,
+    index: Arc<NoteIndex>,
+    index_built: Instant,
+    /// Index being built again in the background
+    rebuilding: Option<Receiver<NoteIndex>>,
+}
+
+impl Site {
+    fn new(vault: PathBuf, token: String) -> Self {
+        Self {
+            root: vault.canonicalize().unwrap_or_else(|_| vault.clone()),
+            index: Arc::new(NoteIndex::build(&vault)),
+            index_built: Instant::now(),
+            rebuilding: None,
+            vault,
+            token,
+        }
+    }
+
+    /// The note index, rebuilt in the background once it's old; requests
+    /// keep using the previous one meanwhile
+    fn index(&mut self) -> Arc<NoteIndex> {
+        if let Some(rebuilding) = &self.rebuilding {
+            match rebuilding.try_recv() {
+                Ok(index) => {
+                    self.index = Arc::new(index);
+                    self.rebuilding = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.rebuilding = None,
+            }
+        }
+        if self.rebuilding.is_none() && self.index_built.elapsed() > INDEX_MAX_AGE {
+            let (tx, rx) = mpsc::channel();
+            let vault = self.vault.clone();
+            thread::spawn(move || {
+                let _ = tx.send(NoteIndex::build(&vault));
+            });
+            self.rebuilding = Some(rx);
+            self.index_built = Instant::now();
+        }
+        self.index.clone()
+    }
+
+    /// Whether `file` is in the vault once symlinks are followed
+    fn inside_vault(&self, file: &Path) -> bool {
+        file.canonicalize().is_ok_and(|file| file.starts_with(&self.root))
+    }
+
+    /// Answer a request for `url`, sent with the given `Cookie` header
+    fn handle(&mut self, url: &str, cookie: Option<&str>) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let token_in_query = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .any(|(key, value)| key == "token" && percent_decode_str(value).decode_utf8_lossy() == self.token);
+        let token_in_cookie = cookie.is_some_and(|cookie| {
+            cookie
+                .split(';')
+                .filter_map(|pair| pair.trim().split_once('='))
+                .any(|(key, value)| key == COOKIE && value == self.token)
+        });
+        if !token_in_query && !token_in_cookie {
+            return Reply::html(
+                401,
+                page("Locked", "<p>Open the address shown in Robsidian, including its <code>?token=</code>.</p>"),
+            );
+        }
+
+        let mut reply = self.route(&percent_decode_str(path).decode_utf8_lossy());
+        reply.set_token = token_in_query;
+        reply
+    }
+
+    fn route(&mut self, path: &str) -> Reply {
+        let rel = Path::new(path.trim_start_matches('/'));
+        // Only plain names: no `..`, no hidden files or settings
+        let safe = rel.components().all(|part| match part {
+            Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+            _ => false,
+        });
+        if rel.as_os_str().is_empty() {
+            return self.note_list();
+        }
+        if !safe {
+            return Reply::not_found();
+        }
+
+        if rel.extension().is_some_and(|ext| ext == "html") {
+            let vault = self.vault.clone();
+            let index = self.index();
+            let Some(note) = index.notes().iter().find(|note| page_path(&vault, &note.path) == rel) else {
+                return Reply::not_found();
+            };
+            if !self.inside_vault(&note.path) {
+                return Reply::not_found();
+            }
+            return match Document::open(&note.path) {
+                Ok(doc) => Reply::html(200, self.note_page(&doc, &index, rel)),
+                Err(_) => Reply::not_found(),
+            };
+        }
+
+        let file = self.vault.join(rel);
+        if !self.inside_vault(&file) {
+            return Reply::not_found();
+        }
+        match std::fs::read(&file) {
+            Ok(body) => Reply { status: 200, content_type: content_type(&file), body, set_token: false },
+            Err(_) => Reply::not_found(),
+        }
+    }
+
+    fn note_page(&self, doc: &Document, index: &NoteIndex, rel: &Path) -> String {
+        let home = "../".repeat(rel.components().count() - 1);
+        let nav = format!("<nav><a href=\"{}./\">All notes</a></nav>\n", home);
+        let body = if doc.locked {
+            "<p>🔒 This note is encrypted. Unlock it in Robsidian.</p>".to_string()
+        } else {
+            // Every note of the vault can be linked to
+            let all = index.notes().iter().map(|note| note.path.clone()).collect();
+            note_to_html(doc, &self.vault, index, &all)
+        };
+        page(&doc.title(), &format!("{}{}", nav, body))
+    }
+
+    /// Index page: every note, grouped by folder
+    fn note_list(&mut self) -> Reply {
+        let vault = self.vault.clone();
+        let mut pages: Vec<PathBuf> = self.index().notes().iter().map(|note| page_path(&vault, &note.path)).collect();
+        pages.sort();
+
+        let name = vault.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut body = format!("<h1>{}</h1>\n", escape_html(&name));
+        let mut folder: Option<PathBuf> = None;
+        for page in &pages {
+            let parent = page.parent().map(Path::to_path_buf).unwrap_or_default();
+            if folder.as_ref() != Some(&parent) {
+                if folder.is_some() {
+                    body.push_str("</ul>\n");
+                }
+                if !parent.as_os_str().is_empty() {
+                    body.push_str(&format!("<h3>{}</h3>\n", escape_html(&parent.to_string_lossy())));
+                }
+                body.push_str("<ul class=\"notes\">\n");
+                folder = Some(parent);
+            }
+            let href: Vec<String> = page
+                .components()
+                .map(|part| utf8_percent_encode(&part.as_os_str().to_string_lossy(), SEGMENT).to_string())
+                .collect();
+            let title = page.file_stem().unwrap_or_default().to_string_lossy();
+            body.push_str(&format!("<li><a href=\"/{}\">{}</a></li>\n", href.join("/"), escape_html(&title)));
+        }
+        if folder.is_some() {
+            body.push_str("</ul>\n");
+        }
+        Reply::html(200, page(&name, &body))
+    }
+}
+
+/// A running web server; stopped when dropped
+pub struct WebServer {
+    server: Arc<Server>,
+    pub port: u16,
+}
+
+impl WebServer {
+    /// Serve a vault on all interfaces at `port`
+    pub fn start(vault: PathBuf, port: u16, token: String) -> Result<Self> {
+        check_token(&token)?;
+        let server = Server::http(SocketAddr::from(([0, 0, 0, 0], port)))
+            .map_err(|e| anyhow!("Could not listen on port {}: {}", port, e))?;
+        let port = server.server_addr().to_ip().map_or(port, |addr| addr.port());
+        let server = Arc::new(server);
+
+        let incoming = server.clone();
+        thread::spawn(move || {
+            let mut site = Site::new(vault, token.clone());
+            for request in incoming.incoming_requests() {
+                let cookie = request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv("Cookie"))
+                    .map(|header| header.value.to_string());
+                let reply = site.handle(request.url(), cookie.as_deref());
+                let mut response = Response::from_data(reply.body).with_status_code(reply.status);
+                if let Ok(header) = Header::from_bytes("Content-Type", reply.content_type) {
+                    response.add_header(header);
+                }
+                if reply.set_token {
+                    let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict", COOKIE, token);
+                    match Header::from_bytes("Set-Cookie", cookie) {
+                        Ok(header) => response.add_header(header),
+                        Err(()) => tracing::warn!("Could not set the web view's token cookie"),
+                    }
+                }
+                let _ = request.respond(response);
+            }
+        });
+        Ok(Self { server, port })
+    }
+}
+
+impl Drop for WebServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes() {
+        let vault = std::env::temp_dir().join(format!("robsidian_web_{}", std::process::id()));
+        std::fs::create_dir_all(vault.join("Sub")).unwrap();
+        std::fs::write(vault.join("Home.md"), "# Home\n\nSee [[Other]].\n").unwrap();
+        std::fs::write(vault.join("Sub/Other.md"), "Other text\n").unwrap();
+        let mut site = Site::new(vault.clone(), "abc".to_string());
+        let text = |reply: &Reply| String::from_utf8_lossy(&reply.body).to_string();
+
+        assert_eq!(site.handle("/", None).status, 401);
+        assert_eq!(site.handle("/?token=wrong", None).status, 401);
+        let list = site.handle("/?token=abc", None);
+        assert!(list.set_token);
+        assert!(text(&list).contains("<a href=\"/Sub/Other.html\">Other</a>"));
+
+        let note = site.handle("/Home.html", Some("theme=dark; robsidian_token=abc"));
+        assert_eq!(note.status, 200);
+        assert!(text(&note).contains("<a href=\"Sub/Other.html\">Other</a>"));
+        assert_eq!(site.handle("/Sub/../../etc/passwd", Some("robsidian_token=abc")).status, 404);
+        assert_eq!(site.handle("/.robsidian/vault.json", Some("robsidian_token=abc")).status, 404);
+        assert_eq!(site.handle("/Home.html?token=%61bc", None).status, 200);
+
+        // Symlinks don't lead out of the vault
+        #[cfg(unix)]
+        {
+            let outside = vault.with_extension("outside");
+            std::fs::write(&outside, "secret").unwrap();
+            std::os::unix::fs::symlink(&outside, vault.join("leak.txt")).unwrap();
+            assert_eq!(site.handle("/leak.txt", Some("robsidian_token=abc")).status, 404);
+            let _ = std::fs::remove_file(outside);
+        }
+
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[test]
+    fn test_check_token() {
+        assert!(check_token(&generate_token()).is_ok());
+        assert!(check_token("").is_err());
+        assert!(check_token("a b").is_err());
+        assert!(check_token("abc\r\nSet-Cookie: x").is_err());
+    }
+}
@@ -1,20 +1,131 @@
 //! Main application state and UI coordination
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
 
+use anyhow::{bail, Result};
 use eframe::egui;
 
-use crate::core::{config::AppConfig, document::Document, file_system::FileTree};
+use robsidian_core::{
+    clipboard_history::ClipboardHistory,
+    command_history::CommandHistory,
+    config::{AppConfig, BellStyle, TerminalConfig, DEFAULT_TERMINAL_FONT_SIZE},
+    crypto::Passphrase,
+    diff::merge3,
+    document::{is_scratch_path, Document, SaveConflict},
+    document_store::{DocumentEdit, DocumentEvent, DocumentStore},
+    export::{export_vault, publish_plan},
+    external_editor::{EditorExited, ExternalEditors},
+    file_system::{copy_into, create_file, create_vault, get_markdown_files, FileTree, VaultPreset},
+    flashcards::{vault_cards, ReviewLog},
+    glossary::Glossary,
+    inbox::{add_tags, inbox_notes, move_into, vault_folders, TRASH_FOLDER},
+    io_tasks::{IoOperation, IoPool, IoResult},
+    link_refactor::{apply_link_conversion, rename_with_links, NoteConversion},
+    link_safety,
+    navigation::{split_anchor, NavEntry, NavigationHistory},
+    note_index::NoteIndex,
+    note_publish::{Publication, Publisher, URL_FIELD},
+    note_share::{share_note, ShareFormat},
+    paste_guard::{fence, needs_confirmation},
+    scheduler::{Scheduler, FRAME_BUDGET},
+    settings_bundle::{BundlePart, SettingsBundle},
+    snapshots::SnapshotStore,
+    sync::{RemoteConfig, SyncReport, SyncStatus, Syncer},
+    templates::{list_templates, render_template},
+    text_encoding::{LineEnding, TextEncoding},
+    text_position::clamp_range,
+    text_stats::TextStats,
+    transform::{convert_selection, transform_selection, SelectionConversion, TextTransform},
+    vault_settings::{FrontmatterTimestamps, VaultSettings},
+    vault_transfer::transfer_note,
+    web_capture::WebCaptures,
+    web_server::{generate_token, lan_address, WebServer},
+};
+use crate::plugin::api::PluginContext;
+use crate::plugin::loader::{DownloadResult, DownloadedPlugin, PluginDownloads};
 use crate::plugin::manager::PluginManager;
-use crate::terminal::{PtyTerminalState, TerminalState};
+use crate::plugin::script::{ScriptInput, ScriptManager};
+use crate::terminal::{ControlCommand, PtyTerminalState, PtyTerminalTab, SplitDirection, TerminalState};
 use crate::ui::{
     block_renderer::BlockAction,
-    editor::EditorPanel, file_tree::FileTreePanel,
+    clipboard_picker::ClipboardPicker,
+    encryption::{EncryptTarget, PassphraseDialog, UnlockForm},
+    flashcards::{FlashcardAction, FlashcardReview},
+    link_style::LinkStyleDialog,
+    pinned_blocks::PinnedNotes,
+    inbox::{InboxAction, InboxReview},
+    link_confirm::{LinkConfirmDialog, LinkSettingsDialog},
+    command_palette::{Command, CommandPalette},
+    diagnostics::DiagnosticsWindow,
+    notifications::{content_hash, Notifications, Severity, ToastAction},
+    paste_prompt::PastePrompt,
+    plugin_command::{PluginCommandPrompt, PluginInvocation},
+    plugin_install::PluginInstallPrompt,
+    plugin_permissions::PluginPermissionPrompt,
+    plugin_panels::{PanelAction, PluginPanels},
+    presentation::PresentationWindow,
+    publish_overview::{PublishAction, PublishOverview},
+    publish_settings::PublishSettingsDialog,
+    render_cache,
+    quick_switcher::{QuickSwitcher, SwitchTarget},
+    rename_dialog::RenameDialog,
+    save_conflict::{ConflictChoice, SaveConflictDialog},
+    sync_settings::SyncSettingsDialog,
+    editor::{EditorPanel, LinkCompletion, SOURCE_EDITOR_ID}, file_history::FileHistoryDialog, file_tree::FileTreePanel, images,
+    ime,
     live_preview::LivePreviewEditor, preview::PreviewPanel,
-    terminal::{PtyTerminalPanel, TerminalPanel},
+    new_vault::NewVaultDialog,
+    settings_import::SettingsImportDialog,
+    note_colors::{NoteColors, NoteColorsDialog},
+    note_elements::{show_note_elements, NoteElementCounter},
+    tabs::TabBar,
+    template_picker::TemplatePicker,
+    terminal::{PtyTerminalPanel, TerminalPanel, TERMINAL_ID},
+    ssh_connect::SshConnectDialog,
+    terminal_settings::TerminalSettingsDialog,
+    text_stats::show_text_stats,
+    theme,
+    web_capture::CapturePrompt,
+    vault_transfer::{TransferDialog, TransferRequest},
+    vault_window::{VaultWindow, VaultWindowEvent},
+    web_server::{WebServerAction, WebServerDialog},
 };
 
+/// What to open on start, from the command line:
+/// `robsidian [--vault <folder>] [--open <note>]`, or
+/// `robsidian --serve [--vault <folder>] [--listen <address>] [--token <token>]`
+/// to serve the vault's API without a window
+#[derive(Debug, Default)]
+pub struct LaunchOptions {
+    pub vault: Option<PathBuf>,
+    pub note: Option<PathBuf>,
+    pub serve: bool,
+    pub listen: Option<String>,
+    pub token: Option<String>,
+}
+
+impl LaunchOptions {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--vault" => options.vault = args.next().map(PathBuf::from),
+                "--open" => options.note = args.next().map(PathBuf::from),
+                "--serve" => options.serve = true,
+                "--listen" => options.listen = args.next(),
+                "--token" => options.token = args.next(),
+                _ => tracing::warn!("Ignoring unknown argument: {}", arg),
+            }
+        }
+        options
+    }
+}
+
 /// View mode for the editor area
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum ViewMode {
@@ -26,25 +137,89 @@ pub enum ViewMode {
     TerminalWithTree,
 }
 
+impl ViewMode {
+    /// Modes an editor tab can display its document in
+    pub const DOCUMENT_MODES: [ViewMode; 4] = [
+        ViewMode::Editor,
+        ViewMode::LivePreview,
+        ViewMode::Preview,
+        ViewMode::Split,
+    ];
+
+    /// Human readable name of the mode
+    pub fn label(self) -> &'static str {
+        match self {
+            ViewMode::Editor => "Source",
+            ViewMode::Preview => "Reading",
+            ViewMode::Split => "Split",
+            ViewMode::LivePreview => "Live Preview",
+            ViewMode::TerminalWithTree => "Terminal + File Tree",
+        }
+    }
+
+    /// The document mode following this one, used by the tab toggle button
+    pub fn next_document_mode(self) -> ViewMode {
+        let modes = Self::DOCUMENT_MODES;
+        let idx = modes.iter().position(|m| *m == self).unwrap_or(0);
+        modes[(idx + 1) % modes.len()]
+    }
+}
+
+/// An open editor tab
+#[derive(Debug, Clone)]
+pub struct EditorTab {
+    /// Path of the document shown in the tab
+    pub path: PathBuf,
+    /// How this tab displays its document
+    pub view_mode: ViewMode,
+    /// Locations visited in this tab
+    pub history: NavigationHistory,
+    /// Pinned tabs keep their note; links followed from them open in a new tab
+    pub pinned: bool,
+}
+
+/// A folder whose notes are being encrypted in the background
+struct FolderEncryption {
+    folder: PathBuf,
+    /// Notes not done yet
+    waiting: HashSet<PathBuf>,
+    /// Notes encrypted so far, leaving out those that already were
+    encrypted: usize,
+}
+
 /// Main application state
 pub struct RobsidianApp {
     /// Path to the current vault (workspace)
     pub vault_path: Option<PathBuf>,
-    /// Open documents indexed by path
-    pub documents: HashMap<PathBuf, Document>,
+    /// Open documents
+    pub documents: DocumentStore,
+    /// Changes to open documents, to keep the note index and plugins current
+    document_events: Receiver<DocumentEvent>,
     /// Currently active document path
     pub active_document: Option<PathBuf>,
+    /// Open editor tabs, in display order
+    pub tabs: Vec<EditorTab>,
+    /// Index of the active tab in `tabs`
+    pub active_tab: usize,
+    /// Untitled scratch notes created so far, to number the next one
+    scratch_count: usize,
     /// File tree state
     pub file_tree: FileTree,
     /// Terminal state (simple command-based)
     pub terminal: TerminalState,
     /// PTY terminal state (interactive shell)
     pub pty_terminal: PtyTerminalState,
+    /// Folder the shown terminal was last seen in, while following it
+    pub terminal_directory: Option<PathBuf>,
+    /// Folder the file tree should open up to and scroll to
+    pub reveal_in_tree: Option<PathBuf>,
     /// Plugin manager
     pub plugin_manager: PluginManager,
+    /// User scripts and the commands they register
+    pub scripts: ScriptManager,
     /// Application configuration
     pub config: AppConfig,
-    /// Current view mode
+    /// App-wide view mode; also the mode new tabs start in
     pub view_mode: ViewMode,
     /// Whether sidebar is visible
     pub sidebar_visible: bool,
@@ -54,50 +229,284 @@ pub struct RobsidianApp {
     pub commonmark_cache: egui_commonmark::CommonMarkCache,
     /// Live preview editor state
     pub live_preview_editor: LivePreviewEditor,
+    /// Footnotes, citations, figures and tables of the active note
+    note_elements: NoteElementCounter,
+    /// Selection in the source editor, as a byte range of the active document
+    pub editor_selection: Option<Range<usize>>,
+    /// Selection to apply to the source editor on the next frame
+    pub pending_editor_selection: Option<Range<usize>>,
+    /// Language being entered for a "convert to code block" command
+    pub code_block_prompt: Option<String>,
+    /// Notes open in an external editor
+    pub external_editors: ExternalEditors,
+    /// Command being entered to open notes in an external editor
+    pub external_editor_prompt: Option<String>,
+    /// Command palette state
+    pub command_palette: CommandPalette,
+    /// Background document opening and saving
+    pub io: IoPool,
+    /// Toasts and notification history
+    pub notifications: Notifications,
+    /// Names and aliases of the vault's notes
+    pub note_index: Arc<NoteIndex>,
+    /// Settings stored in the open vault (starred notes)
+    pub vault_settings: VaultSettings,
+    /// Quick switcher state
+    pub quick_switcher: QuickSwitcher,
+    /// Wiki link autocompletion in the source editor
+    pub link_completion: LinkCompletion,
+    /// Detached preview window for presenting a note
+    pub presentation: PresentationWindow,
+    /// Local snapshots taken on save
+    pub snapshots: SnapshotStore,
+    /// File history dialog state
+    pub file_history: FileHistoryDialog,
+    /// Template picker state
+    pub template_picker: TemplatePicker,
+    /// Saves refused because the file changed on disk
+    pub save_conflicts: SaveConflictDialog,
+    /// Inbox review state
+    pub inbox: InboxReview,
+    pub flashcards: FlashcardReview,
+    /// Link style conversion dialog state
+    pub link_style: LinkStyleDialog,
+    /// Closed notes read for the blocks pinned to the sidebar
+    pub pinned_notes: PinnedNotes,
+    /// Folder and tag colors dialog state
+    pub note_colors_dialog: NoteColorsDialog,
+    /// Passphrase of encrypted notes, once entered in this session
+    pub vault_passphrase: Option<Passphrase>,
+    /// Passphrase field shown for a locked note
+    pub unlock_form: UnlockForm,
+    /// Dialog asking for a new passphrase
+    pub passphrase_dialog: PassphraseDialog,
+    /// Folders being encrypted
+    folder_encryptions: Vec<FolderEncryption>,
+    /// Notes the user asked to save, announced once written
+    manual_saves: HashSet<PathBuf>,
+    /// Address prompt for capturing a web article
+    pub capture_prompt: CapturePrompt,
+    /// Web articles being captured
+    pub web_captures: WebCaptures,
+    /// Which notes the HTML export includes
+    pub publish_overview: PublishOverview,
+    /// Notes being published to the web
+    pub publisher: Publisher,
+    /// Publish settings dialog state
+    pub publish_settings: PublishSettingsDialog,
+    /// Background vault sync
+    pub syncer: Syncer,
+    /// Sync settings dialog state
+    pub sync_settings: SyncSettingsDialog,
+    pub terminal_settings: TerminalSettingsDialog,
+    /// SSH quick-connect dialog state
+    pub ssh_connect: SshConnectDialog,
+    /// New vault dialog state
+    pub new_vault: NewVaultDialog,
+    /// Settings import dialog state
+    pub settings_import: SettingsImportDialog,
+    /// Read-only web view of the vault, while running
+    pub web_server: Option<WebServer>,
+    /// Web server dialog state
+    pub web_server_dialog: WebServerDialog,
+    /// Another vault, open in a second window
+    pub vault_window: VaultWindow,
+    /// Move/copy to vault dialog state
+    pub transfer_dialog: TransferDialog,
+    /// Text recently copied or cut in the app
+    pub clipboard_history: ClipboardHistory,
+    /// Clipboard history picker state
+    pub clipboard_picker: ClipboardPicker,
+    /// Arguments prompt for plugin commands
+    pub plugin_command_prompt: PluginCommandPrompt,
+    /// Confirmation for large or code-like pastes
+    pub paste_prompt: PastePrompt,
+    /// Rename dialog for files in the tree
+    pub rename_dialog: RenameDialog,
+    /// Plugin installs and update checks in progress
+    pub plugin_downloads: PluginDownloads,
+    pub plugin_install_prompt: PluginInstallPrompt,
+    /// Permissions of downloaded plugins, to allow before they're installed
+    pub plugin_permissions: PluginPermissionPrompt,
+    /// Confirmation before opening web links
+    pub link_confirm: LinkConfirmDialog,
+    pub link_settings: LinkSettingsDialog,
+    /// Panels added by plugins
+    pub plugin_panels: PluginPanels,
+    /// Background work run within a frame budget
+    pub scheduler: Scheduler<RobsidianApp>,
+    pub diagnostics: DiagnosticsWindow,
 }
 
 impl RobsidianApp {
     /// Create a new application instance
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, launch: LaunchOptions) -> Self {
         // Configure fonts and styles
         Self::configure_fonts(&cc.egui_ctx);
+        images::install_loaders(&cc.egui_ctx);
 
         // Load config or use defaults
         let config = AppConfig::load().unwrap_or_default();
 
         // Load last vault if configured
-        let vault_path = config.last_vault.clone();
+        let vault_path = launch.vault.or_else(|| config.last_vault.clone());
         let file_tree = if let Some(ref path) = vault_path {
             FileTree::from_path(path).unwrap_or_default()
         } else {
             FileTree::default()
         };
 
-        Self {
+        let mut notifications = Notifications::default();
+        let vault_settings = vault_path
+            .as_deref()
+            .map(|vault| Self::load_vault_settings(vault, &mut notifications))
+            .unwrap_or_default();
+        let mut plugin_manager = PluginManager::new();
+        Self::load_plugins(&mut plugin_manager, &config, vault_path.clone(), &mut notifications);
+        let mut scripts = ScriptManager::new();
+        if let Some(path) = &vault_path {
+            scripts.set_vault(path.clone());
+        }
+        for error in scripts.load(&config.get_script_dir()) {
+            notifications.error(error);
+        }
+        let snapshots = SnapshotStore::new(config.get_snapshot_dir(), &config.history);
+        let mut io = IoPool::new();
+        io.set_snapshots(snapshots.clone());
+
+        let mut terminal = TerminalState::new();
+        terminal.history = CommandHistory::load(&config.get_command_history_dir(), vault_path.as_deref());
+        let mut pty_terminal = PtyTerminalState::new(&config.terminal, vault_path.as_deref());
+        let ctx = cc.egui_ctx.clone();
+        pty_terminal.set_waker(Arc::new(move || ctx.request_repaint()));
+
+        let mut documents = DocumentStore::default();
+        let document_events = documents.subscribe();
+
+        let mut app = Self {
             vault_path,
-            documents: HashMap::new(),
+            documents,
+            document_events,
             active_document: None,
+            tabs: Vec::new(),
+            active_tab: 0,
+            scratch_count: 0,
             file_tree,
-            terminal: TerminalState::new(),
-            pty_terminal: PtyTerminalState::new(),
-            plugin_manager: PluginManager::new(),
+            terminal,
+            pty_terminal,
+            terminal_directory: None,
+            reveal_in_tree: None,
+            plugin_manager,
+            scripts,
             config,
             view_mode: ViewMode::Split,
             sidebar_visible: true,
             terminal_visible: false,
             commonmark_cache: egui_commonmark::CommonMarkCache::default(),
             live_preview_editor: LivePreviewEditor::new(),
+            note_elements: NoteElementCounter::default(),
+            editor_selection: None,
+            pending_editor_selection: None,
+            code_block_prompt: None,
+            external_editors: ExternalEditors::default(),
+            external_editor_prompt: None,
+            command_palette: CommandPalette::default(),
+            io,
+            notifications,
+            note_index: Arc::default(),
+            vault_settings,
+            quick_switcher: QuickSwitcher::default(),
+            link_completion: LinkCompletion::default(),
+            presentation: PresentationWindow::default(),
+            snapshots,
+            file_history: FileHistoryDialog::default(),
+            template_picker: TemplatePicker::default(),
+            save_conflicts: SaveConflictDialog::default(),
+            inbox: InboxReview::default(),
+            flashcards: FlashcardReview::default(),
+            link_style: LinkStyleDialog::default(),
+            pinned_notes: PinnedNotes::default(),
+            note_colors_dialog: NoteColorsDialog::default(),
+            vault_passphrase: None,
+            unlock_form: UnlockForm::default(),
+            passphrase_dialog: PassphraseDialog::default(),
+            folder_encryptions: Vec::new(),
+            manual_saves: HashSet::new(),
+            capture_prompt: CapturePrompt::default(),
+            web_captures: WebCaptures::default(),
+            publish_overview: PublishOverview::default(),
+            publisher: Publisher::default(),
+            publish_settings: PublishSettingsDialog::default(),
+            syncer: Syncer::default(),
+            sync_settings: SyncSettingsDialog::default(),
+            terminal_settings: TerminalSettingsDialog::default(),
+            ssh_connect: SshConnectDialog::default(),
+            new_vault: NewVaultDialog::default(),
+            settings_import: SettingsImportDialog::default(),
+            web_server: None,
+            web_server_dialog: WebServerDialog::default(),
+            vault_window: VaultWindow::default(),
+            transfer_dialog: TransferDialog::default(),
+            clipboard_history: ClipboardHistory::default(),
+            clipboard_picker: ClipboardPicker::default(),
+            plugin_command_prompt: PluginCommandPrompt::default(),
+            paste_prompt: PastePrompt::default(),
+            rename_dialog: RenameDialog::default(),
+            plugin_downloads: PluginDownloads::default(),
+            plugin_install_prompt: PluginInstallPrompt::default(),
+            plugin_permissions: PluginPermissionPrompt::default(),
+            link_confirm: LinkConfirmDialog::default(),
+            link_settings: LinkSettingsDialog::default(),
+            plugin_panels: PluginPanels::default(),
+            scheduler: Scheduler::new(),
+            diagnostics: DiagnosticsWindow::default(),
+        };
+        app.rebuild_note_index();
+        app.reload_glossary();
+        if let Some(vault) = app.config.second_vault.clone() {
+            if app.vault_window.open(vault).is_err() {
+                app.config.second_vault = None;
+            }
+        }
+        if app.config.web_server.enabled {
+            app.start_web_server();
+        }
+        if let Some(note) = launch.note {
+            app.open_document(note);
+        }
+        app
+    }
+
+    /// Load the plugins enabled in the config for the vault being opened,
+    /// reporting any that fail
+    fn load_plugins(plugins: &mut PluginManager, config: &AppConfig, vault_path: Option<PathBuf>, notifications: &mut Notifications) {
+        let plugin_dir = config.get_plugin_dir();
+        plugins.set_context(PluginContext { data_dir: plugin_dir.clone(), vault_path });
+        plugins.discover(&plugin_dir);
+        for id in &config.plugins.enabled_plugins {
+            if let Err(e) = plugins.enable_plugin(id, &plugin_dir) {
+                notifications.error(format!(
+                    "Plugin '{}' could not be loaded: {}. Check its manifest.json and module in {}.",
+                    id,
+                    e,
+                    plugin_dir.join(id).display()
+                ));
+            }
         }
     }
 
     /// Configure custom fonts
-    fn configure_fonts(_ctx: &egui::Context) {
-        // Use default fonts for now
+    fn configure_fonts(ctx: &egui::Context) {
+        let mut fonts = egui::FontDefinitions::default();
+
+        // Font families used by note style variants (e.g. `cssclass: serif`)
+        theme::register_note_fonts(&mut fonts);
+        ctx.set_fonts(fonts);
+
         // Custom fonts can be added by placing font files in assets/fonts/
         // and using include_bytes! to embed them
         //
         // Example:
-        // let mut fonts = egui::FontDefinitions::default();
         // fonts.font_data.insert(
         //     "custom".to_owned(),
         //     egui::FontData::from_static(include_bytes!("../assets/fonts/Font.ttf")),
@@ -109,48 +518,2194 @@ impl RobsidianApp {
         // ctx.set_fonts(fonts);
     }
 
-    /// Open a vault (workspace directory)
-    pub fn open_vault(&mut self, path: PathBuf) {
-        self.vault_path = Some(path.clone());
-        self.file_tree = FileTree::from_path(&path).unwrap_or_default();
-        self.config.last_vault = Some(path);
-        let _ = self.config.save();
+    /// Build the note index again in the background
+    fn rebuild_note_index(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        self.scheduler.spawn("Build note index", move || {
+            let index = Arc::new(NoteIndex::build(&vault));
+            Box::new(move |app: &mut RobsidianApp| {
+                // The vault may have been switched while this was building
+                if app.vault_path.as_deref() == Some(vault.as_path()) {
+                    app.note_index = index;
+                }
+            })
+        });
+    }
+
+    /// Run finished background work and deferred tasks until the frame's
+    /// budget is spent
+    fn run_scheduled_work(&mut self) {
+        let frame = std::time::Instant::now();
+        while frame.elapsed() < FRAME_BUDGET {
+            let Some(mut work) = self.scheduler.next_work() else {
+                break;
+            };
+            let started = std::time::Instant::now();
+            let finished = work.run(self);
+            self.scheduler.finish(work, started.elapsed(), finished);
+        }
+        self.scheduler.end_frame(frame.elapsed());
+    }
+
+    /// Open a vault (workspace directory)
+    pub fn open_vault(&mut self, path: PathBuf) {
+        self.vault_path = Some(path.clone());
+        self.plugin_manager.set_vault(path.clone());
+        self.scripts.set_vault(path.clone());
+        self.file_tree = FileTree::from_path(&path).unwrap_or_default();
+        self.rebuild_note_index();
+        self.vault_settings = Self::load_vault_settings(&path, &mut self.notifications);
+        self.reload_glossary();
+        self.pty_terminal.configure(&self.config.terminal, Some(&path));
+        self.terminal.history = CommandHistory::load(&self.config.get_command_history_dir(), Some(&path));
+        self.notifications.info(format!("Opened vault {}", path.display()));
+        self.config.add_recent_vault(path.clone());
+        if self.web_server.take().is_some() {
+            self.start_web_server();
+        }
+        self.config.last_vault = Some(path);
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+    }
+
+    /// Save the settings, terminal themes, user scripts and enabled plugins
+    /// to one file, to set up another machine the same way
+    pub fn export_settings(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Export settings")
+            .set_file_name("robsidian-settings.json")
+            .add_filter("Settings bundle", &["json"])
+            .save_file()
+        else {
+            return;
+        };
+        let script_dir = self.config.get_script_dir();
+        let exported = SettingsBundle::collect(&self.config, &TerminalConfig::theme_dir(), &script_dir)
+            .and_then(|bundle| bundle.write(&path));
+        match exported {
+            Ok(()) => {
+                let message = format!("Exported settings to {}", path.display());
+                self.notifications.push(Severity::Success, message, None);
+            }
+            Err(e) => self.notifications.error(format!("Could not export settings: {:#}", e)),
+        }
+    }
+
+    /// Pick a settings bundle and offer its parts to import
+    pub fn import_settings(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("Import settings")
+            .add_filter("Settings bundle", &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+        match SettingsBundle::read(&path) {
+            Ok(bundle) => self.settings_import.open(path, bundle),
+            Err(e) => self.notifications.error(format!("{:#}", e)),
+        }
+    }
+
+    /// Import the picked parts of a settings bundle and put them to use
+    fn apply_settings_bundle(&mut self, bundle: SettingsBundle, parts: Vec<BundlePart>) {
+        let mut config = self.config.clone();
+        let script_dir = self.config.get_script_dir();
+        if let Err(e) = bundle.apply(&parts, &mut config, &TerminalConfig::theme_dir(), &script_dir) {
+            self.notifications.error(format!("Could not import settings: {:#}", e));
+            return;
+        }
+        self.config = config;
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+        // Also picks up the imported theme files
+        self.pty_terminal.configure(&self.config.terminal, self.vault_path.as_deref());
+        if parts.contains(&BundlePart::Scripts) {
+            self.reload_scripts();
+        }
+
+        let mut missing = Vec::new();
+        if parts.contains(&BundlePart::Plugins) {
+            let plugin_dir = self.config.get_plugin_dir();
+            self.plugin_manager.discover(&plugin_dir);
+            for id in bundle.enabled_plugins {
+                if self.plugin_manager.enabled_plugins().contains(&id) {
+                    continue;
+                }
+                if !self.plugin_manager.available_plugins().iter().any(|plugin| plugin.id == id) {
+                    missing.push(id);
+                } else if let Err(e) = self.plugin_manager.enable_plugin(&id, &plugin_dir) {
+                    self.notifications.error(format!("Plugin '{}' could not be loaded: {}", id, e));
+                }
+            }
+        }
+        self.notifications.push(Severity::Success, "Imported settings", None);
+        if !missing.is_empty() {
+            self.notifications.info(format!(
+                "Install {} to use the imported plugins; they are enabled once installed",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    /// Open the new vault dialog, suggesting a vault next to the open one
+    pub fn open_new_vault_dialog(&mut self) {
+        let location = self.vault_path.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+        self.new_vault.open(location);
+    }
+
+    /// Create a vault laid out as `preset` and open it, with its welcome
+    /// note
+    fn create_vault(&mut self, path: PathBuf, preset: Option<VaultPreset>) {
+        match create_vault(&path, preset) {
+            Ok(welcome) => {
+                self.open_vault(path);
+                if let Some(welcome) = welcome {
+                    self.open_document(welcome);
+                }
+            }
+            Err(e) => self.notifications.error(format!("Could not create vault: {:#}", e)),
+        }
+    }
+
+    /// Load the settings stored in a vault, reporting unreadable ones
+    fn load_vault_settings(vault: &Path, notifications: &mut Notifications) -> VaultSettings {
+        VaultSettings::load(vault).unwrap_or_else(|e| {
+            notifications.warning(format!("{:#}. Using default vault settings.", e));
+            VaultSettings::default()
+        })
+    }
+
+    /// Read the vault's glossary note again, for the abbreviations underlined
+    /// in every note
+    fn reload_glossary(&mut self) {
+        let glossary = self
+            .vault_path
+            .as_deref()
+            .map(|vault| Glossary::load(&self.vault_settings.glossary_path(vault)))
+            .unwrap_or_default();
+        self.live_preview_editor.set_vault_glossary(glossary);
+    }
+
+    /// Whether notes exported from the vault spell out abbreviations
+    pub fn set_expand_abbreviations(&mut self, expand: bool) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            self.notifications.info("Open a vault to change its export settings");
+            return;
+        };
+        self.vault_settings.expand_abbreviations = expand;
+        if let Err(e) = self.vault_settings.save(vault) {
+            self.notifications.error(format!("{:#}", e));
+            return;
+        }
+        let message = if expand {
+            "Exported notes spell out the first use of each abbreviation"
+        } else {
+            "Exported notes no longer spell out abbreviations"
+        };
+        self.notifications.info(message);
+    }
+
+    /// How saved notes of the vault keep their `created`/`updated` fields
+    pub fn set_frontmatter_timestamps(&mut self, timestamps: FrontmatterTimestamps) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            self.notifications.info("Open a vault to change its timestamp settings");
+            return;
+        };
+        let toggled = timestamps.enabled != self.vault_settings.timestamps.enabled;
+        self.vault_settings.timestamps = timestamps;
+        if let Err(e) = self.vault_settings.save(vault) {
+            self.notifications.error(format!("{:#}", e));
+            return;
+        }
+        if toggled {
+            let message = if self.vault_settings.timestamps.enabled {
+                "Saved notes keep `created` and `updated` in their frontmatter"
+            } else {
+                "Saved notes no longer get frontmatter timestamps"
+            };
+            self.notifications.info(message);
+        }
+    }
+
+    /// Whether a note is starred in the open vault
+    pub fn is_starred(&self, path: &Path) -> bool {
+        self.vault_path
+            .as_deref()
+            .is_some_and(|vault| self.vault_settings.is_starred(vault, path))
+    }
+
+    /// Star or unstar a note and save the vault's settings
+    pub fn toggle_star(&mut self, path: &Path) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            return;
+        };
+        self.vault_settings.toggle_star(vault, path);
+        if let Err(e) = self.vault_settings.save(vault) {
+            self.notifications.error(format!("{:#}", e));
+        }
+    }
+
+    /// Bookmark a heading or `^block` of a note, or remove its bookmark, and
+    /// save the vault's settings
+    pub fn toggle_bookmark(&mut self, path: &Path, anchor: &str) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            return;
+        };
+        let added = self.vault_settings.toggle_bookmark(vault, path, anchor);
+        if let Err(e) = self.vault_settings.save(vault) {
+            self.notifications.error(format!("{:#}", e));
+            return;
+        }
+        let label = NavEntry::new(path, Some(anchor.to_string())).label();
+        let message = if added { format!("Bookmarked {}", label) } else { format!("Removed the bookmark of {}", label) };
+        self.notifications.info(message);
+    }
+
+    /// Pin a heading or `^block` of a note to the sidebar, or unpin it
+    pub fn toggle_pin(&mut self, path: &Path, anchor: &str) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            return;
+        };
+        let pinned = self.vault_settings.toggle_pin(vault, path, anchor);
+        if let Err(e) = self.vault_settings.save(vault) {
+            self.notifications.error(format!("{:#}", e));
+            return;
+        }
+        let label = NavEntry::new(path, Some(anchor.to_string())).label();
+        let message = if pinned { format!("Pinned {} to the sidebar", label) } else { format!("Unpinned {}", label) };
+        self.notifications.info(message);
+    }
+
+    /// Decrypt the active note with a passphrase typed into the unlock form
+    fn unlock_active_document(&mut self, passphrase: Passphrase) {
+        let Some(mut doc) = self.active_document.as_ref().and_then(|path| self.documents.edit(path)) else {
+            return;
+        };
+        match doc.unlock(passphrase.clone()) {
+            Ok(()) => {
+                self.vault_passphrase = Some(passphrase);
+                self.unlock_form.error = None;
+            }
+            Err(e) => self.unlock_form.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Encrypt the active note, or save it in plain text again if it is
+    /// encrypted
+    pub fn toggle_active_encryption(&mut self) {
+        let Some(path) = self.active_document.clone() else {
+            return;
+        };
+        let passphrase = self.vault_passphrase.clone();
+        // The folder's setting decides for its notes; a note decrypted here
+        // would be encrypted again on its next save
+        let in_encrypted_folder =
+            self.vault_path.as_deref().is_some_and(|vault| self.vault_settings.in_encrypted_folder(vault, &path));
+        let Some(mut doc) = self.documents.edit(&path) else {
+            return;
+        };
+        if doc.locked {
+            self.notifications.info("Unlock the note first");
+            return;
+        }
+        if doc.passphrase.is_some() && in_encrypted_folder {
+            drop(doc);
+            self.notifications.info("Notes in an encrypted folder stay encrypted. Stop encrypting the folder first.");
+            return;
+        }
+        if doc.passphrase.take().is_some() {
+            let title = doc.title();
+            self.io.save(doc.clone());
+            self.notifications.warning(format!("{} is now saved unencrypted", title));
+            return;
+        }
+        drop(doc);
+        match passphrase {
+            Some(passphrase) => {
+                self.encrypt_note(&path, passphrase);
+            }
+            None => self.passphrase_dialog.open(EncryptTarget::Note(path)),
+        }
+    }
+
+    /// Mark a folder for encryption and encrypt its notes, or unmark it
+    pub fn toggle_folder_encryption(&mut self, folder: &Path) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        let marked = self.vault_settings.encrypted_folders.iter().any(|f| vault.join(f) == folder);
+        if marked {
+            self.vault_settings.toggle_encrypted_folder(&vault, folder);
+            if let Err(e) = self.vault_settings.save(&vault) {
+                self.notifications.error(format!("{:#}", e));
+            }
+            self.notifications.info(format!(
+                "New notes in {} are no longer encrypted. Notes already encrypted stay encrypted.",
+                folder.display()
+            ));
+            return;
+        }
+        match self.vault_passphrase.clone() {
+            Some(passphrase) => self.encrypt_folder(folder, passphrase),
+            None => self.passphrase_dialog.open(EncryptTarget::Folder(folder.to_path_buf())),
+        }
+    }
+
+    /// Encrypt a note with a passphrase and save it in the background.
+    /// Returns false for an open note that is encrypted already.
+    fn encrypt_note(&mut self, path: &Path, passphrase: Passphrase) -> bool {
+        match self.documents.edit(path) {
+            Some(mut doc) => {
+                if doc.locked || doc.passphrase.is_some() {
+                    return false;
+                }
+                doc.passphrase = Some(passphrase);
+                self.io.save(doc.clone());
+            }
+            None => self.io.encrypt(path.to_path_buf(), passphrase),
+        }
+        true
+    }
+
+    /// Mark a folder for encryption and encrypt the notes already in it in
+    /// the background
+    fn encrypt_folder(&mut self, folder: &Path, passphrase: Passphrase) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        self.vault_settings.toggle_encrypted_folder(&vault, folder);
+        if let Err(e) = self.vault_settings.save(&vault) {
+            self.notifications.error(format!("{:#}", e));
+        }
+        let mut encryption = FolderEncryption { folder: folder.to_path_buf(), waiting: HashSet::new(), encrypted: 0 };
+        for note in get_markdown_files(folder) {
+            if self.documents.contains(&note) {
+                if self.encrypt_note(&note, passphrase.clone()) {
+                    encryption.encrypted += 1;
+                }
+            } else {
+                self.io.encrypt(note.clone(), passphrase.clone());
+                encryption.waiting.insert(note);
+            }
+        }
+        self.vault_passphrase = Some(passphrase);
+        self.folder_encryptions.push(encryption);
+        self.report_folder_encryptions();
+    }
+
+    /// Tell how many notes were encrypted in the folders that are done
+    fn report_folder_encryptions(&mut self) {
+        let (done, running) = std::mem::take(&mut self.folder_encryptions)
+            .into_iter()
+            .partition(|encryption| encryption.waiting.is_empty());
+        self.folder_encryptions = running;
+        for encryption in done {
+            let message = format!("Encrypted {} notes in {}", encryption.encrypted, encryption.folder.display());
+            self.notifications.push(Severity::Success, message, None);
+        }
+    }
+
+    /// Pin or unpin the active tab
+    pub fn toggle_pin_active_tab(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.pinned = !tab.pinned;
+        }
+    }
+
+    /// Open a document, focusing its tab or opening a new one
+    pub fn open_document(&mut self, path: PathBuf) {
+        self.load_document(&path);
+        match self.tabs.iter().position(|tab| tab.path == path) {
+            Some(idx) => self.activate_tab(idx),
+            None => {
+                self.tabs.push(EditorTab {
+                    path: path.clone(),
+                    view_mode: self.default_document_mode(),
+                    history: NavigationHistory::new(NavEntry::new(path, None)),
+                    pinned: false,
+                });
+                self.activate_tab(self.tabs.len() - 1);
+            }
+        }
+    }
+
+    /// Start loading a document in the background if it isn't in memory yet.
+    /// Tabs showing it display a spinner until it arrives.
+    fn load_document(&mut self, path: &Path) {
+        if !self.documents.contains(path) {
+            self.io.open(path.to_path_buf());
+        }
+    }
+
+    /// Bring the note index and plugins up to date with the open documents
+    fn handle_document_events(&mut self) {
+        while let Ok(event) = self.document_events.try_recv() {
+            let errors = match &event {
+                DocumentEvent::Opened(path) | DocumentEvent::Frontmatter(path) | DocumentEvent::Saved(path) => {
+                    let Some(doc) = self.documents.get(path) else {
+                        continue;
+                    };
+                    // Aliases may have changed. Scratch notes join the index once saved.
+                    let path = path.clone();
+                    if !doc.is_scratch() {
+                        self.scheduler.defer("Update note index", move |app| {
+                            if let Some(doc) = app.documents.get(&path) {
+                                Arc::make_mut(&mut app.note_index).update(doc);
+                            }
+                            true
+                        });
+                    }
+                    match event {
+                        DocumentEvent::Opened(_) => self.plugin_manager.on_document_open(doc),
+                        DocumentEvent::Saved(_) => self.plugin_manager.on_document_save(doc),
+                        _ => Vec::new(),
+                    }
+                }
+                DocumentEvent::Content(_) | DocumentEvent::Moved { .. } | DocumentEvent::Closed(_) => Vec::new(),
+            };
+            for error in errors {
+                self.notifications.warning(error);
+            }
+        }
+    }
+
+    /// Apply a finished background open or save
+    fn handle_io_result(&mut self, result: IoResult) {
+        match result {
+            IoResult::Opened { path, result: Ok(mut doc) } => {
+                if !self.documents.contains(&path) {
+                    // Try the passphrase entered earlier before asking for one
+                    if let (true, Some(passphrase)) = (doc.locked, &self.vault_passphrase) {
+                        let _ = doc.unlock(passphrase.clone());
+                    }
+                    self.documents.insert(*doc);
+                }
+            }
+            IoResult::Opened { path, result: Err(e) } => {
+                self.notifications.push(
+                    Severity::Error,
+                    format!("{:#}", e),
+                    Some(ToastAction::RetryOpen(path.clone())),
+                );
+                self.close_tabs_for(&path);
+            }
+            IoResult::Saved { path, content, result: Ok(disk) } => {
+                let Some(mut doc) = self.documents.edit(&path) else {
+                    return;
+                };
+                doc.disk = Some(disk);
+                // Edits made while saving still need saving
+                if doc.content == content {
+                    doc.modified = false;
+                    doc.last_modified = Some(std::time::SystemTime::now());
+                }
+                let title = doc.title();
+                drop(doc);
+                self.documents.saved(&path);
+                if self.vault_path.as_deref().is_some_and(|vault| self.vault_settings.glossary_path(vault) == path) {
+                    self.reload_glossary();
+                }
+                // A new file, such as a scratch note saved for the first time
+                let in_vault = self.vault_path.as_ref().is_some_and(|vault| path.starts_with(vault));
+                if in_vault && self.file_tree.find_node(&path).is_none() {
+                    if let Err(e) = self.file_tree.refresh() {
+                        self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+                    }
+                }
+                // Background saves go unannounced. Offer to get back to a note
+                // saved while looking at another.
+                if self.manual_saves.remove(&path) {
+                    let action = (self.active_document.as_ref() != Some(&path)).then_some(ToastAction::Open(path));
+                    self.notifications.push(Severity::Success, format!("Saved {}", title), action);
+                }
+            }
+            IoResult::Saved { path, result: Err(e), .. } if e.is::<SaveConflict>() => {
+                self.manual_saves.remove(&path);
+                if let Ok(conflict) = e.downcast::<SaveConflict>() {
+                    self.save_conflicts.push(conflict);
+                }
+            }
+            IoResult::Encrypted { path, result } => {
+                let folder = self.folder_encryptions.iter_mut().find(|encryption| encryption.waiting.contains(&path));
+                if let Some(encryption) = folder {
+                    encryption.waiting.remove(&path);
+                    encryption.encrypted += usize::from(matches!(result, Ok(true)));
+                }
+                if let Err(e) = result {
+                    self.notifications.error(format!("Could not encrypt {}: {:#}", path.display(), e));
+                }
+                self.report_folder_encryptions();
+            }
+            IoResult::Saved { path, result: Err(e), .. } => {
+                self.manual_saves.remove(&path);
+                // The note stays modified, so nothing is lost by retrying later
+                self.notifications.push(
+                    Severity::Error,
+                    format!("{:#}. Your changes are kept in the editor.", e),
+                    Some(ToastAction::RetrySave(path)),
+                );
+            }
+        }
+    }
+
+    /// Apply the user's decision on a save conflict
+    fn resolve_save_conflict(&mut self, conflict: SaveConflict, choice: ConflictChoice) {
+        let Some(mut doc) = self.documents.edit(&conflict.path) else {
+            return;
+        };
+        let base = doc.disk.as_ref().map(|disk| disk.content.clone()).unwrap_or_default();
+        let disk_content = conflict.disk.content.clone();
+        match choice {
+            ConflictChoice::KeepMine => {
+                doc.disk = Some(conflict.disk);
+                self.io.save(doc.clone());
+            }
+            ConflictChoice::UseDisk => {
+                doc.set_content(disk_content);
+                doc.disk = Some(conflict.disk);
+                doc.modified = false;
+            }
+            ConflictChoice::Merge => {
+                let merge = merge3(&base, &doc.content, &disk_content);
+                doc.set_content(merge.text);
+                doc.disk = Some(conflict.disk);
+                if merge.conflicts > 0 {
+                    self.notifications.warning(format!(
+                        "{} conflicting {} marked with <<<<<<< and >>>>>>> in {}. Resolve and save again.",
+                        merge.conflicts,
+                        if merge.conflicts == 1 { "change is" } else { "changes are" },
+                        doc.title()
+                    ));
+                } else {
+                    self.notifications.info(format!("Merged the changes on disk into {}. Save to keep them.", doc.title()));
+                }
+            }
+            ConflictChoice::Later => {}
+        }
+    }
+
+    /// Close the tabs showing a document that could not be opened
+    fn close_tabs_for(&mut self, path: &PathBuf) {
+        self.tabs.retain(|tab| &tab.path != path);
+        if self.active_document.as_ref() == Some(path) || self.active_tab >= self.tabs.len() {
+            self.active_tab = self.active_tab.min(self.tabs.len().saturating_sub(1));
+            self.active_document = self.active_tab().map(|tab| tab.path.clone());
+        }
+    }
+
+    /// Make a tab the active one
+    pub fn activate_tab(&mut self, idx: usize) {
+        if let Some(tab) = self.tabs.get(idx) {
+            self.active_tab = idx;
+            self.active_document = Some(tab.path.clone());
+            self.editor_selection = None;
+        }
+    }
+
+    /// Navigate the active tab to a note (and heading), recording it in the
+    /// tab's history. A pinned tab keeps its note and the target opens in
+    /// another tab instead.
+    pub fn navigate_to(&mut self, entry: NavEntry) {
+        let keep_tab = self
+            .active_tab()
+            .is_some_and(|tab| tab.pinned && tab.path != entry.path);
+        if self.tabs.is_empty() || keep_tab {
+            self.open_document(entry.path.clone());
+        }
+        self.load_document(&entry.path);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.history.visit(entry.clone());
+        }
+        self.show_entry(entry);
+    }
+
+    /// Follow a wiki link target such as `Note`, `Note#Heading` or `#Heading`
+    pub fn follow_link(&mut self, target: &str) {
+        let (note, heading) = split_anchor(target);
+        let path = if note.is_empty() {
+            self.active_document.clone()
+        } else {
+            self.note_index.resolve(note)
+        };
+        if let Some(path) = path {
+            self.navigate_to(NavEntry::new(path, heading.map(str::to_string)));
+        }
+    }
+
+    /// Go back in the active tab's history
+    pub fn go_back(&mut self) {
+        let entry = self.active_tab_mut().and_then(|tab| tab.history.back().cloned());
+        if let Some(entry) = entry {
+            self.show_entry(entry);
+        }
+    }
+
+    /// Go forward in the active tab's history
+    pub fn go_forward(&mut self) {
+        let entry = self.active_tab_mut().and_then(|tab| tab.history.forward().cloned());
+        if let Some(entry) = entry {
+            self.show_entry(entry);
+        }
+    }
+
+    /// Jump to an entry of the active tab's history
+    pub fn jump_in_history(&mut self, index: usize) {
+        let entry = self.active_tab_mut().and_then(|tab| tab.history.jump_to(index).cloned());
+        if let Some(entry) = entry {
+            self.show_entry(entry);
+        }
+    }
+
+    /// Show a history entry in the active tab without recording it
+    fn show_entry(&mut self, entry: NavEntry) {
+        self.load_document(&entry.path);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.path = entry.path.clone();
+        }
+        self.active_document = Some(entry.path);
+        self.editor_selection = None;
+        if let Some(heading) = entry.heading {
+            self.live_preview_editor.scroll_to_heading(heading);
+        }
+    }
+
+    /// Mode new tabs are opened in
+    fn default_document_mode(&self) -> ViewMode {
+        if ViewMode::DOCUMENT_MODES.contains(&self.view_mode) {
+            self.view_mode
+        } else {
+            ViewMode::Split
+        }
+    }
+
+    /// Get the active tab
+    pub fn active_tab(&self) -> Option<&EditorTab> {
+        self.tabs.get(self.active_tab)
+    }
+
+    /// Get the active tab mutably
+    pub fn active_tab_mut(&mut self) -> Option<&mut EditorTab> {
+        self.tabs.get_mut(self.active_tab)
+    }
+
+    /// View mode used for the active tab
+    pub fn active_view_mode(&self) -> ViewMode {
+        self.active_tab()
+            .map(|tab| tab.view_mode)
+            .unwrap_or_else(|| self.default_document_mode())
+    }
+
+    /// Switch view mode: document modes apply to the active tab and become
+    /// the default for new tabs
+    pub fn set_view_mode(&mut self, mode: ViewMode) {
+        self.view_mode = mode;
+        if ViewMode::DOCUMENT_MODES.contains(&mode) {
+            if let Some(tab) = self.active_tab_mut() {
+                tab.view_mode = mode;
+            }
+        }
+    }
+
+    /// Run a code block in the PTY terminal and show the terminal
+    pub fn run_code_block(&mut self, lang: Option<&str>, code: &str) {
+        match self.pty_terminal.run_code(lang, code) {
+            Ok(()) => self.set_view_mode(ViewMode::TerminalWithTree),
+            Err(e) => self.notifications.warning(format!("{:#}", e)),
+        }
+    }
+
+    /// Convert the lines of the source editor selection, or convert them back
+    /// if they already have that form
+    pub fn convert_editor_selection(&mut self, conversion: SelectionConversion) {
+        self.edit_editor_selection(|content, selection| convert_selection(content, selection, &conversion));
+    }
+
+    /// Apply a case or line transform to the source editor selection
+    pub fn transform_editor_selection(&mut self, transform: TextTransform) {
+        self.edit_editor_selection(|content, selection| transform_selection(content, selection, transform));
+    }
+
+    /// Rewrite the active document around the source editor selection and
+    /// select the rewritten text
+    fn edit_editor_selection(
+        &mut self,
+        edit: impl FnOnce(&str, Range<usize>) -> (String, Range<usize>),
+    ) {
+        let Some(selection) = self.editor_selection.clone() else {
+            return;
+        };
+        let Some(mut doc) = self.active_document_mut() else {
+            return;
+        };
+
+        // The selection may be stale if the document changed since it was
+        // made; rewriting text the user didn't select would corrupt the note
+        if clamp_range(&doc.content, selection.clone()) != selection {
+            return;
+        }
+        let (content, range) = edit(&doc.content, selection);
+        doc.set_content(content);
+        drop(doc);
+        self.pending_editor_selection = Some(range.clone());
+        self.editor_selection = Some(range);
+    }
+
+    /// Pick a template to insert into the active note
+    pub fn open_template_picker(&mut self) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            self.notifications.info("Open a vault to use its templates");
+            return;
+        };
+        if self.active_document().is_none() {
+            self.notifications.info("Open a note to insert a template into it");
+            return;
+        }
+        let folder = self.vault_settings.templates_dir(vault);
+        let templates = list_templates(&folder);
+        if templates.is_empty() {
+            self.notifications.info(format!("No templates in {}", folder.display()));
+            return;
+        }
+        self.template_picker.open(folder, templates);
+    }
+
+    /// Insert a template into the active note, replacing the editor
+    /// selection or appending it when the source editor isn't shown
+    fn insert_template(&mut self, template: &Path) {
+        let template = match Document::open(template) {
+            Ok(template) => template,
+            Err(e) => {
+                self.notifications.error(format!("{:#}", e));
+                return;
+            }
+        };
+        let Some(doc) = self.active_document() else {
+            return;
+        };
+        let text = render_template(template.content_without_frontmatter(), doc);
+        self.insert_at_cursor(&text);
+    }
+
+    /// Replace the editor selection with text, or add it at the end of the
+    /// note when it isn't being edited
+    fn insert_at_cursor(&mut self, text: &str) {
+        let in_editor = matches!(self.active_view_mode(), ViewMode::Editor | ViewMode::Split);
+        let selection = self.editor_selection.clone().filter(|_| in_editor);
+        let Some(mut doc) = self.active_document_mut() else {
+            return;
+        };
+        // A stale selection isn't replaced; the text goes at the end instead
+        let range = match selection.filter(|selection| clamp_range(&doc.content, selection.clone()) == *selection) {
+            Some(selection) => selection,
+            None => doc.content.len()..doc.content.len(),
+        };
+        let mut content = doc.content.clone();
+        content.replace_range(range.clone(), text);
+        doc.set_content(content);
+        drop(doc);
+
+        let cursor = range.start + text.len();
+        self.pending_editor_selection = Some(cursor..cursor);
+        self.editor_selection = Some(cursor..cursor);
+    }
+
+    /// Open a terminal tab in `dir` and show it
+    pub fn open_terminal_here(&mut self, dir: &Path) {
+        self.pty_terminal.new_tab_in(dir);
+        self.set_view_mode(ViewMode::TerminalWithTree);
+    }
+
+    /// Ask for attention when a program rings the bell in the background, and
+    /// tell about long commands finishing while the terminal isn't watched.
+    /// The output is processed here so this works while it is hidden too.
+    fn notify_terminal_events(&mut self, ctx: &egui::Context) {
+        self.pty_terminal.process_all_output();
+        let active_note = self.active_document.as_deref().filter(|path| !is_scratch_path(path));
+        self.pty_terminal.set_active_note(active_note);
+        self.handle_control_commands();
+        let window_focused = ctx.input(|i| i.viewport().focused.unwrap_or(true));
+        if self.pty_terminal.take_bell() && self.config.terminal.bell == BellStyle::Notify && !window_focused {
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(egui::UserAttentionType::Informational));
+        }
+        if let Some(text) = self.pty_terminal.take_clipboard() {
+            ctx.copy_text(text);
+        }
+        self.pty_terminal
+            .answer_clipboard_requests(|| arboard::Clipboard::new().ok()?.get_text().ok());
+
+        let watched = window_focused
+            && self.view_mode == ViewMode::TerminalWithTree
+            && ctx.memory(|memory| memory.has_focus(egui::Id::new(TERMINAL_ID)));
+        for finished in self.pty_terminal.take_finished() {
+            if watched {
+                continue;
+            }
+            let secs = finished.duration.as_secs();
+            let took = if secs >= 60 { format!("{}m {}s", secs / 60, secs % 60) } else { format!("{}s", secs) };
+            match finished.exit_code {
+                Some(code) if code != 0 => self
+                    .notifications
+                    .warning(format!("{}: command failed with exit code {} after {}", finished.tab, code, took)),
+                _ => self.notifications.info(format!("{}: command finished after {}", finished.tab, took)),
+            }
+        }
+    }
+
+    /// Open or create the notes scripts in the terminal asked for through
+    /// the control pipe
+    fn handle_control_commands(&mut self) {
+        for command in self.pty_terminal.take_control_commands() {
+            let command = match command {
+                Ok(command) => command,
+                Err(e) => {
+                    self.notifications.warning(format!("{:#}", e));
+                    continue;
+                }
+            };
+            let note = match command.note(self.vault_path.as_deref()) {
+                Ok(note) => note,
+                Err(e) => {
+                    self.notifications.warning(format!("{:#}", e));
+                    continue;
+                }
+            };
+            if matches!(command, ControlCommand::New(_)) && !note.exists() {
+                if let Err(e) = create_file(&note) {
+                    self.notifications.error(format!("Could not create {}: {:#}", note.display(), e));
+                    continue;
+                }
+                if let Err(e) = self.file_tree.refresh() {
+                    self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+                }
+                self.rebuild_note_index();
+            }
+            if note.is_file() {
+                self.open_document(note);
+            } else {
+                self.notifications.warning(format!("No note at {}", note.display()));
+            }
+        }
+    }
+
+    /// Reveal the folder the shown terminal is in in the file tree, when it
+    /// moves to another one in the vault and following is on
+    fn follow_terminal_directory(&mut self) {
+        if !self.config.terminal.follow_directory || self.view_mode != ViewMode::TerminalWithTree {
+            self.terminal_directory = None;
+            return;
+        }
+        let directory = self.pty_terminal.current_tab().and_then(PtyTerminalTab::directory);
+        if directory == self.terminal_directory {
+            return;
+        }
+        self.terminal_directory = directory.clone();
+        let vault = self.vault_path.as_deref();
+        if vault.is_some_and(|vault| directory.as_ref().is_some_and(|dir| dir.starts_with(vault))) {
+            self.reveal_in_tree = directory;
+        }
+    }
+
+    /// Insert the terminal's selection, or the last command's output, into
+    /// the active note as a code block
+    fn insert_terminal_output(&mut self) {
+        if self.active_document().is_none() {
+            self.notifications.info("Open a note to insert the output into it");
+            return;
+        }
+        let output = self.pty_terminal.current_tab().and_then(PtyTerminalTab::output_for_note);
+        match output {
+            Some(output) => self.insert_at_cursor(&fence(&output, "")),
+            None => self.notifications.info("Select text in the terminal, or run a command that prints something"),
+        }
+    }
+
+    /// Offer the clipboard history for pasting into the active note
+    pub fn open_clipboard_picker(&mut self) {
+        if self.active_document().is_none() {
+            self.notifications.info("Open a note to paste into it");
+        } else if self.clipboard_history.is_empty() {
+            self.notifications.info("Nothing copied yet");
+        } else {
+            self.clipboard_picker.open();
+        }
+    }
+
+    /// Ask for the arguments of a plugin's command
+    pub fn open_plugin_command(&mut self, plugin: &str, name: &str) {
+        let command = self.plugin_manager.commands().into_iter().find(|(id, c)| id == plugin && c.name == name);
+        match command {
+            Some((plugin, command)) => self.plugin_command_prompt.open(plugin, command),
+            None => self.notifications.warning(format!("Plugin command '{}' is no longer available", name)),
+        }
+    }
+
+    /// Download a plugin in the background, to install once its
+    /// permissions are allowed
+    pub fn install_plugin(&mut self, source: String) {
+        self.notifications.info(format!("Downloading plugin from {}...", source));
+        let loader = self.plugin_manager.loader();
+        self.plugin_downloads.download(loader, source);
+    }
+
+    /// Unpack a downloaded plugin and load it again if it's running. A new
+    /// plugin is enabled when `enable` is set, once its permissions were
+    /// allowed.
+    fn finish_plugin_install(&mut self, plugin: DownloadedPlugin, enable: bool) {
+        let plugin_dir = self.config.get_plugin_dir();
+        let manifest = plugin.manifest.clone();
+        if let Err(e) = self.plugin_manager.loader().install(&plugin, &plugin_dir) {
+            self.notifications.error(format!("Could not install {}: {:#}", manifest.name, e));
+            return;
+        }
+        self.plugin_manager.discover(&plugin_dir);
+        let id = manifest.id.clone();
+        let loaded = if self.config.plugins.enabled_plugins.contains(&id) {
+            self.plugin_manager.reload_plugin(&id, &plugin_dir)
+        } else if enable {
+            self.config.plugins.enabled_plugins.push(id.clone());
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+            self.plugin_manager.enable_plugin(&id, &plugin_dir)
+        } else {
+            Ok(())
+        };
+        match loaded {
+            Ok(()) => {
+                let message = format!("Installed {} {}", manifest.name, manifest.version);
+                self.notifications.push(Severity::Success, message, None)
+            }
+            Err(e) => self.notifications.error(format!("{} was installed but failed to load: {}", manifest.name, e)),
+        }
+    }
+
+    /// Look for newer versions of the plugins installed from the web
+    pub fn check_plugin_updates(&mut self) {
+        if self.plugin_downloads.is_busy() {
+            return;
+        }
+        let installed = self.plugin_manager.available_plugins().to_vec();
+        self.plugin_downloads.check_updates(installed, self.config.get_plugin_dir());
+    }
+
+    fn handle_plugin_download(&mut self, result: DownloadResult) {
+        let plugin_dir = self.config.get_plugin_dir();
+        match result {
+            DownloadResult::Downloaded(Ok(plugin)) => {
+                // An update asking for nothing new goes in without asking again
+                self.plugin_manager.discover(&plugin_dir);
+                let granted = self
+                    .plugin_manager
+                    .available_plugins()
+                    .iter()
+                    .find(|manifest| manifest.id == plugin.manifest.id)
+                    .map(|manifest| manifest.permissions.clone());
+                match granted {
+                    Some(granted) if plugin.manifest.permissions.iter().all(|p| granted.contains(p)) => {
+                        self.finish_plugin_install(plugin, false)
+                    }
+                    granted => self.plugin_permissions.open(plugin, granted),
+                }
+            }
+            DownloadResult::Downloaded(Err(e)) => {
+                self.notifications.error(format!("Could not install plugin: {:#}", e))
+            }
+            DownloadResult::UpdateAvailable { id, version, source } => {
+                let name = self
+                    .plugin_manager
+                    .available_plugins()
+                    .iter()
+                    .find(|manifest| manifest.id == id)
+                    .map_or(id.clone(), |manifest| manifest.name.clone());
+                let message = match version {
+                    Some(version) => format!("{} {} is available", name, version),
+                    None => format!("A newer version of {} is available", name),
+                };
+                self.notifications.push(Severity::Info, message, Some(ToastAction::UpdatePlugin(source)));
+            }
+            DownloadResult::Checked { updates, failures } => {
+                for failure in &failures {
+                    self.notifications.warning(format!("Could not check for updates of {}", failure));
+                }
+                if updates == 0 && failures.is_empty() {
+                    self.notifications.info("All plugins are up to date");
+                }
+            }
+        }
+    }
+
+    /// Pass what was done in plugin panels on to the plugins
+    fn handle_panel_actions(&mut self, actions: Vec<PanelAction>) {
+        for action in actions {
+            match action {
+                PanelAction::Event { plugin, panel, widget, value } => {
+                    if let Err(e) = self.plugin_manager.panel_event(&plugin, &panel, &widget, &value) {
+                        self.notifications.error(format!("Plugin '{}': {:#}", plugin, e));
+                    }
+                }
+                PanelAction::OpenUrl { url, text } => self.open_external_url(url, Some(text)),
+            }
+        }
+    }
+
+    /// File tree, with plugins' sidebar panels below it
+    fn show_sidebar(&mut self, ui: &mut egui::Ui) {
+        if PluginPanels::has_sidebar(&self.plugin_manager) {
+            let actions = egui::TopBottomPanel::bottom("plugin_panels")
+                .resizable(true)
+                .show_inside(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("plugin_panels_scroll")
+                        .show(ui, |ui| self.plugin_panels.show_sidebar(ui, &self.plugin_manager))
+                        .inner
+                })
+                .inner;
+            self.handle_panel_actions(actions);
+        }
+        FileTreePanel::show(ui, self);
+    }
+
+    /// Run a plugin command, inserting or showing its answer
+    /// Load the user scripts again, after they were edited
+    pub fn reload_scripts(&mut self) {
+        let errors = self.scripts.load(&self.config.get_script_dir());
+        let count = self.scripts.commands().len();
+        if errors.is_empty() {
+            self.notifications.push(Severity::Success, format!("Loaded {} script command(s)", count), None);
+        }
+        for error in errors {
+            self.notifications.error(error);
+        }
+    }
+
+    /// Show the scripts folder in the file manager, creating it first
+    pub fn open_scripts_folder(&mut self) {
+        let dir = self.config.get_script_dir();
+        if let Err(e) = std::fs::create_dir_all(&dir).map_err(anyhow::Error::from).and_then(|_| Ok(open::that(&dir)?)) {
+            self.notifications.error(format!("Could not open {}: {:#}", dir.display(), e));
+        }
+    }
+
+    /// Run a script command on the active note and apply what it changed
+    fn run_script(&mut self, script: &str, name: &str) {
+        let in_editor = matches!(self.active_view_mode(), ViewMode::Editor | ViewMode::Split);
+        // Locked notes only hold their encrypted text
+        let document = self.active_document().filter(|doc| !doc.locked).map(|doc| doc.content.clone());
+        let selection = match (&document, self.editor_selection.clone().filter(|_| in_editor)) {
+            (Some(content), Some(selection)) => clamp_range(content, selection),
+            (Some(content), None) => content.len()..content.len(),
+            (None, _) => 0..0,
+        };
+        let outcome = self.scripts.run(script, name, ScriptInput { document, selection });
+
+        if let Some(content) = outcome.document {
+            if let Some(mut doc) = self.active_document_mut() {
+                doc.set_content(content);
+            }
+        }
+        for message in outcome.messages {
+            self.notifications.info(message);
+        }
+        if let Some(error) = outcome.error {
+            self.notifications.error(error);
+        }
+        if !outcome.written.is_empty() {
+            for file in &outcome.written {
+                self.reload_from_disk(file);
+            }
+            if let Err(e) = self.file_tree.refresh() {
+                self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+            }
+            self.rebuild_note_index();
+        }
+    }
+
+    fn run_plugin_command(&mut self, invocation: PluginInvocation) {
+        let result = self.plugin_manager.execute_command(&invocation.plugin, &invocation.command, &invocation.args);
+        match result {
+            Ok(Some(answer)) if invocation.insert && self.active_document().is_some() => self.insert_at_cursor(&answer),
+            Ok(Some(answer)) => self.notifications.info(format!("{}: {}", invocation.command, answer)),
+            Ok(None) => self.notifications.push(Severity::Success, format!("Ran {}", invocation.command), None),
+            Err(e) => self.notifications.error(format!("{}: {:#}", invocation.command, e)),
+        }
+    }
+
+    /// Open a web address in the browser, asking first unless its domain
+    /// is trusted. `text` is what the link showed, if known.
+    pub fn open_external_url(&mut self, url: String, text: Option<String>) {
+        if link_safety::needs_confirmation(&self.config.links, &url) {
+            self.link_confirm.open(url, text);
+        } else if let Err(e) = open::that(&url) {
+            self.notifications.error(format!("Could not open {}: {}", url, e));
+        }
+    }
+
+    /// Route the links egui widgets opened this frame (preview hyperlinks)
+    /// through the link confirmation
+    fn intercept_opened_urls(&mut self, ctx: &egui::Context) {
+        let mut urls = Vec::new();
+        ctx.output_mut(|o| {
+            o.commands.retain(|command| match command {
+                egui::OutputCommand::OpenUrl(open) => {
+                    urls.push(open.url.clone());
+                    false
+                }
+                _ => true,
+            })
+        });
+        for url in urls {
+            self.open_external_url(url, None);
+        }
+    }
+
+    /// Add the text copied or cut this frame to the clipboard history
+    fn remember_copied_text(&mut self, ctx: &egui::Context) {
+        let copied: Vec<String> = ctx.output(|o| {
+            o.commands
+                .iter()
+                .filter_map(|command| match command {
+                    egui::OutputCommand::CopyText(text) => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+        for text in copied {
+            self.plugin_manager.set_clipboard(&text);
+            self.clipboard_history.push(&text);
+        }
+        if let Some(text) = self.plugin_manager.take_copied_text() {
+            self.clipboard_history.push(&text);
+            ctx.copy_text(text);
+        }
+    }
+
+    /// Start reviewing the notes in the vault's inbox folder
+    pub fn open_inbox(&mut self) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            self.notifications.info("Open a vault to review its inbox");
+            return;
+        };
+        let dir = self.vault_settings.inbox_dir(vault);
+        let notes = inbox_notes(&dir);
+        if notes.is_empty() {
+            self.notifications.info(format!("No notes in {}", dir.display()));
+            return;
+        }
+        self.inbox.open(notes, vault_folders(vault));
+    }
+
+    /// Colors of the vault's notes and folders
+    pub fn note_colors(&self) -> NoteColors<'_> {
+        NoteColors { vault: self.vault_path.as_deref(), settings: &self.vault_settings, notes: &self.note_index }
+    }
+
+    /// Show the decks of the flashcards in the vault's notes
+    pub fn open_flashcards(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to review its flashcards");
+            return;
+        };
+        // A log that can't be read isn't replaced by a new one
+        match ReviewLog::load(&vault) {
+            Ok(log) => self.flashcards.open(&vault, vault_cards(&self.note_index), log),
+            Err(e) => self.notifications.error(format!("{:#}", e)),
+        }
+    }
+
+    fn handle_flashcard_action(&mut self, action: FlashcardAction) {
+        match action {
+            FlashcardAction::SaveLog => {
+                let Some(vault) = &self.vault_path else {
+                    return;
+                };
+                if let Err(e) = self.flashcards.log().save(vault) {
+                    self.notifications.error(format!("{:#}", e));
+                }
+            }
+            FlashcardAction::Open { note, offset } => {
+                self.open_document(note);
+                self.reveal_offset(offset);
+            }
+        }
+    }
+
+    /// Write the previewed link conversions, leaving out notes with unsaved
+    /// changes, and reload the converted notes that are open
+    fn convert_link_style(&mut self, conversions: Vec<NoteConversion>) {
+        let (unsaved, conversions): (Vec<_>, Vec<_>) = conversions
+            .into_iter()
+            .partition(|conversion| self.documents.get(&conversion.note).is_some_and(|doc| doc.modified));
+        for conversion in &unsaved {
+            let name = conversion.note.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            self.notifications.warning(format!("Save {} to convert its links", name));
+        }
+        if let Err(e) = apply_link_conversion(&conversions) {
+            self.notifications.error(format!("Could not convert links: {:#}", e));
+        }
+        for conversion in &conversions {
+            self.reload_from_disk(&conversion.note);
+        }
+        self.rebuild_note_index();
+        let links: usize = conversions.iter().map(|conversion| conversion.links.len()).sum();
+        let message = format!("Converted {} link(s) in {} note(s)", links, conversions.len());
+        self.notifications.push(Severity::Success, message, None);
+    }
+
+    /// Run an inbox action on the reviewed note
+    fn handle_inbox_action(&mut self, action: InboxAction) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        let name = |path: &Path| path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let result = match action {
+            InboxAction::Open(note) => {
+                self.open_document(note);
+                return;
+            }
+            InboxAction::AddTags { note, tags } => {
+                let result = self.edit_note(&note, |content| add_tags(content, &tags));
+                if let Err(e) = result {
+                    self.notifications.error(format!("{:#}", e));
+                } else {
+                    self.inbox.reload_current();
+                }
+                return;
+            }
+            InboxAction::Move { note, folder } => self
+                .relocate_note(&note, &vault.join(&folder))
+                .map(|_| format!("Moved {} to {}", name(&note), folder.display())),
+            InboxAction::Archive(note) => self
+                .relocate_note(&note, &self.vault_settings.archive_dir(&vault))
+                .map(|_| format!("Archived {}", name(&note))),
+            InboxAction::Delete(note) => self
+                .relocate_note(&note, &vault.join(TRASH_FOLDER))
+                .map(|_| format!("Moved {} to the trash", name(&note))),
+            InboxAction::MergeInto { note, target } => Document::open(&note)
+                .and_then(|doc| {
+                    if doc.locked {
+                        bail!("{} is encrypted and can't be merged", doc.title());
+                    }
+                    let body = doc.content_without_frontmatter().trim().to_string();
+                    self.edit_note(&target, |content| format!("{}\n\n{}\n", content.trim_end(), body))
+                })
+                .and_then(|_| self.relocate_note(&note, &vault.join(TRASH_FOLDER)))
+                .map(|_| format!("Merged {} into {}", name(&note), name(&target))),
+        };
+        match result {
+            Ok(message) => {
+                self.notifications.push(Severity::Success, message, None);
+                self.inbox.remove_current();
+            }
+            Err(e) => self.notifications.error(format!("{:#}", e)),
+        }
+    }
+
+    /// Change a note's content and save it right away, going through the
+    /// open document if there is one
+    fn edit_note(&mut self, path: &Path, edit: impl FnOnce(&str) -> String) -> Result<()> {
+        let Some(mut doc) = self.documents.edit(path) else {
+            let mut doc = Document::open(path)?;
+            doc.content = edit(&doc.content);
+            doc.save()?;
+            return Ok(());
+        };
+        let content = edit(&doc.content);
+        doc.set_content(content);
+        doc.save_mut()?;
+        drop(doc);
+        self.documents.saved(path);
+        Ok(())
+    }
+
+    /// Move a note into a folder, closing it first
+    fn relocate_note(&mut self, note: &Path, folder: &Path) -> Result<PathBuf> {
+        if self.documents.get(note).is_some_and(|doc| doc.modified) {
+            bail!("Save {} before moving it", note.display());
+        }
+        let target = move_into(note, folder)?;
+        self.documents.remove(note);
+        self.close_tabs_for(&note.to_path_buf());
+        if let Err(e) = self.file_tree.refresh() {
+            self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+        }
+        self.rebuild_note_index();
+        Ok(target)
+    }
+
+    /// Show the snapshots of the active note
+    pub fn open_file_history(&mut self) {
+        match self.active_document.clone() {
+            Some(path) => self.file_history.open(path, &self.snapshots),
+            None => self.notifications.info("Open a note to see its history"),
+        }
+    }
+
+    /// Replace a note's content with a restored snapshot, offering to undo
+    fn restore_snapshot(&mut self, path: PathBuf, content: String) {
+        let Some(mut doc) = self.documents.edit(&path) else {
+            self.notifications.warning("The note was closed before it could be restored");
+            return;
+        };
+        let previous_content = doc.content.clone();
+        doc.set_content(content);
+        self.notifications.push(
+            Severity::Success,
+            format!("Restored an older version of {}", doc.title()),
+            Some(ToastAction::undo(path, previous_content, &doc.content)),
+        );
+    }
+
+    /// Publish the active note to the configured Gist or endpoint
+    pub fn publish_active_note(&mut self) {
+        let Some(doc) = self.active_document() else {
+            self.notifications.info("Open a note to publish it");
+            return;
+        };
+        if doc.locked {
+            self.notifications.warning("Unlock the note to publish it");
+            return;
+        }
+        let Some(target) = self.config.publish.clone() else {
+            self.notifications.info("Choose where to publish notes first");
+            self.publish_settings.open(&self.config.publish);
+            return;
+        };
+        let path = doc.path.clone();
+        let note = Publication {
+            title: doc.title(),
+            filename: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            content: doc.content_without_frontmatter().to_string(),
+            url: doc.frontmatter_field(URL_FIELD),
+        };
+        self.notifications.info(format!("Publishing {}...", note.title));
+        self.publisher.start(path, target, note);
+    }
+
+    /// Remember where a note was published in its frontmatter
+    fn handle_publish_result(&mut self, path: PathBuf, result: Result<String>) {
+        let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let url = match result {
+            Ok(url) => url,
+            Err(e) => {
+                self.notifications.error(format!("Could not publish {}: {:#}", name, e));
+                return;
+            }
+        };
+        match self.documents.edit(&path) {
+            Some(mut doc) => {
+                // Leave unsaved edits for the user to save
+                let unsaved = doc.modified;
+                doc.set_frontmatter_field(URL_FIELD, &url);
+                if !unsaved {
+                    self.io.save(doc.clone());
+                }
+            }
+            None => {
+                let saved = Document::open(&path).and_then(|mut doc| {
+                    doc.set_frontmatter_field(URL_FIELD, &url);
+                    doc.save_mut()
+                });
+                if let Err(e) = saved {
+                    self.notifications.warning(format!("Could not store the address in {}: {:#}", name, e));
+                }
+            }
+        }
+        self.notifications.push(Severity::Success, format!("Published {} to {}", name, url), None);
+    }
+
+    /// Ask for a web page to capture into the vault's read-later folder
+    pub fn open_capture_prompt(&mut self) {
+        if self.vault_path.is_none() {
+            self.notifications.info("Open a vault to capture web articles into");
+            return;
+        }
+        self.capture_prompt.open();
+    }
+
+    /// Start capturing a web page in the background
+    fn capture_web_article(&mut self, url: String) {
+        let Some(vault) = self.vault_path.as_deref() else {
+            return;
+        };
+        let folder = self.vault_settings.read_later_dir(vault);
+        self.notifications.info(format!("Capturing {}...", url));
+        self.web_captures.start(url, folder);
+    }
+
+    /// Report a finished capture and show the new note in the vault
+    fn handle_web_capture(&mut self, url: String, result: Result<PathBuf>) {
+        match result {
+            Ok(note) => {
+                if let Err(e) = self.file_tree.refresh() {
+                    self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+                }
+                self.rebuild_note_index();
+                let name = note.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                self.notifications.push(
+                    Severity::Success,
+                    format!("Saved {} for later", name),
+                    Some(ToastAction::Open(note)),
+                );
+            }
+            Err(e) => self.notifications.error(format!("Could not capture {}: {:#}", url, e)),
+        }
+    }
+
+    /// Re-read an open note changed on disk by the app itself. Notes with
+    /// unsaved edits are left alone: saving them later runs into the usual
+    /// outside-edit check.
+    fn reload_from_disk(&mut self, path: &Path) {
+        let Some(mut doc) = self.documents.edit(path) else {
+            return;
+        };
+        if doc.modified {
+            return;
+        }
+        if let Ok(mut reloaded) = Document::open(path) {
+            if let Some(passphrase) = doc.passphrase.clone() {
+                let _ = reloaded.unlock(passphrase);
+            }
+            *doc = reloaded;
+        }
+    }
+
+    /// Ask which known vault to move or copy a note to
+    pub fn open_transfer_dialog(&mut self, note: PathBuf, remove_original: bool) {
+        let mut vaults: Vec<PathBuf> = Vec::new();
+        for vault in self.config.second_vault.iter().chain(&self.config.recent_vaults) {
+            if Some(vault) != self.vault_path.as_ref() && vault.is_dir() && !vaults.contains(vault) {
+                vaults.push(vault.clone());
+            }
+        }
+        self.transfer_dialog.open(note, vaults, remove_original);
+    }
+
+    /// Rename a note or attachment, updating the links to it across the vault
+    fn rename_file(&mut self, path: PathBuf, name: String) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            self.notifications.warning(format!("\"{}\" isn't a valid file name", name));
+            return;
+        }
+        // Other notes' links are rewritten on disk, so none may have unsaved edits
+        if self.documents.values().any(|doc| doc.modified && !doc.is_scratch()) {
+            self.notifications.warning("Save your changes before renaming");
+            return;
+        }
+        let mut target = path.with_file_name(&name);
+        if target.extension().is_none() {
+            if let Some(ext) = path.extension() {
+                target.set_extension(ext);
+            }
+        }
+        let relinked = match rename_with_links(&vault, &path, &target) {
+            Ok(relinked) => relinked,
+            Err(e) => {
+                self.notifications.error(format!("Could not rename: {:#}", e));
+                return;
+            }
+        };
+
+        if self.documents.rename(&path, target.clone()) {
+            let active = self.active_document.as_ref() == Some(&path);
+            self.close_tabs_for(&path);
+            if active {
+                self.open_document(target.clone());
+            }
+        }
+        self.vault_settings.rename_note(&vault, &path, &target);
+        if let Err(e) = self.vault_settings.save(&vault) {
+            self.notifications.error(format!("{:#}", e));
+        }
+        for note in &relinked {
+            self.reload_from_disk(note);
+        }
+        if let Err(e) = self.file_tree.refresh() {
+            self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+        }
+        self.rebuild_note_index();
+
+        let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let message = match relinked.len() {
+            0 => format!("Renamed to {}", name),
+            n => format!("Renamed to {}, updating links in {} note(s)", name, n),
+        };
+        self.notifications.push(Severity::Success, message, None);
+    }
+
+    /// Move or copy a note into another vault
+    fn transfer_to_vault(&mut self, request: TransferRequest) {
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+        let TransferRequest { note, vault: target, options } = request;
+        let name = note.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        if options.remove_original && self.documents.get(&note).is_some_and(|doc| doc.modified) {
+            self.notifications.warning(format!("Save {} before moving it", name));
+            return;
+        }
+        let report = match transfer_note(&note, &vault, &target, options) {
+            Ok(report) => report,
+            Err(e) => {
+                self.notifications.error(format!("Could not transfer {}: {:#}", name, e));
+                return;
+            }
+        };
+
+        if options.remove_original {
+            self.documents.remove(&note);
+            self.close_tabs_for(&note);
+        }
+        for path in &report.relinked {
+            self.reload_from_disk(path);
+        }
+        if let Err(e) = self.file_tree.refresh() {
+            self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+        }
+        self.rebuild_note_index();
+        if self.vault_window.vault() == Some(target.as_path()) {
+            let _ = self.vault_window.open(target.clone());
+        }
+
+        let target_name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut message = format!(
+            "{} {} to {}",
+            if options.remove_original { "Moved" } else { "Copied" },
+            name,
+            target_name
+        );
+        let new_name = report.note.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        if new_name != name {
+            message.push_str(&format!(" as {}", new_name));
+        }
+        if report.attachments > 0 {
+            message.push_str(&format!(" with {} attachment(s)", report.attachments));
+        }
+        if !report.relinked.is_empty() {
+            message.push_str(&format!(", updating links in {} note(s)", report.relinked.len()));
+        }
+        self.notifications.push(Severity::Success, message, None);
+    }
+
+    /// Sync the vault with its remote in the background
+    pub fn sync_now(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to sync it");
+            return;
+        };
+        let Some(remote) = self.config.sync.remote.clone() else {
+            self.notifications.info("Choose where to sync the vault to first");
+            self.sync_settings.open(&self.config.sync);
+            return;
+        };
+        self.syncer.start(vault, remote, self.config.sync.conflicts);
+    }
+
+    /// Reload notes changed by a sync and report conflicts and failures
+    fn handle_sync_result(&mut self, result: Result<SyncReport>) {
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                self.notifications.error(format!("Sync failed: {:#}", e));
+                return;
+            }
+        };
+        let Some(vault) = self.vault_path.clone() else {
+            return;
+        };
+
+        for rel in &report.changed_locally {
+            self.reload_from_disk(&vault.join(rel));
+        }
+        if !report.changed_locally.is_empty() || !report.conflict_copies.is_empty() {
+            if let Err(e) = self.file_tree.refresh() {
+                self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+            }
+            self.rebuild_note_index();
+        }
+        for copy in report.conflict_copies {
+            self.notifications.push(
+                Severity::Warning,
+                format!("Sync conflict: the remote version was saved as {}", copy.display()),
+                Some(ToastAction::Open(vault.join(copy))),
+            );
+        }
+        if let Some(first) = report.errors.first() {
+            self.notifications.warning(format!(
+                "{} files could not be synced. First error: {}",
+                report.errors.len(),
+                first
+            ));
+        }
+    }
+
+    /// Show which notes the HTML export will include
+    pub fn open_publish_overview(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to see what it publishes");
+            return;
+        };
+        let plan = publish_plan(&self.note_index, chrono::Local::now().date_naive());
+        self.publish_overview.open(vault, plan);
+    }
+
+    /// Export the published notes as HTML pages into a folder picked by the
+    /// user
+    pub fn export_vault_html(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to export it");
+            return;
+        };
+        let Some(out_dir) = rfd::FileDialog::new().set_title("Export to folder").pick_folder() else {
+            return;
+        };
+        match export_vault(&vault, &out_dir, &self.note_index) {
+            Ok(0) => self
+                .notifications
+                .info("No notes are published yet. Add \"publish: true\" to a note's frontmatter."),
+            Ok(count) => self.notifications.push(
+                Severity::Success,
+                format!("Exported {} notes to {}", count, out_dir.display()),
+                None,
+            ),
+            Err(e) => self.notifications.error(format!("Export failed: {:#}", e)),
+        }
+    }
+
+    /// Save the active note, the notes it transcludes and its attachments as
+    /// a zip or a self-contained HTML page, as picked by the user
+    pub fn share_active_note(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to share its notes");
+            return;
+        };
+        let Some(doc) = self.active_document.as_ref().and_then(|path| self.documents.get(path)) else {
+            self.notifications.info("Open a note to share it");
+            return;
+        };
+        if doc.locked {
+            self.notifications.warning("Unlock the note before sharing it");
+            return;
+        }
+        let Some(out) = rfd::FileDialog::new()
+            .set_title("Share note")
+            .set_file_name(format!("{}.zip", doc.title()))
+            .add_filter("Zip archive", &["zip"])
+            .add_filter("Web page", &["html"])
+            .save_file()
+        else {
+            return;
+        };
+        let format = ShareFormat::from_path(&out).unwrap_or(ShareFormat::Zip);
+        let result = share_note(doc, &vault, &self.note_index, format)
+            .and_then(|bundle| Ok(std::fs::write(&out, bundle)?));
+        match result {
+            Ok(()) => self.notifications.push(Severity::Success, format!("Shared note as {}", out.display()), None),
+            Err(e) => self.notifications.error(format!("Sharing failed: {:#}", e)),
+        }
+    }
+
+    /// Serve the vault read-only on the local network
+    pub fn start_web_server(&mut self) {
+        let Some(vault) = self.vault_path.clone() else {
+            self.notifications.info("Open a vault to serve it");
+            return;
+        };
+        let settings = &mut self.config.web_server;
+        if settings.token.is_empty() {
+            settings.token = generate_token();
+        }
+        // Stop the old server first so its port is free
+        self.web_server = None;
+        match WebServer::start(vault, settings.port, settings.token.clone()) {
+            Ok(server) => {
+                self.web_server = Some(server);
+                self.config.web_server.enabled = true;
+            }
+            Err(e) => {
+                self.notifications.error(format!("Could not start the web view: {:#}", e));
+                return;
+            }
+        }
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+    }
+
+    /// Change the PTY terminal's font size. The terminal takes the new number
+    /// of rows and columns when it's next drawn.
+    pub fn set_terminal_font_size(&mut self, size: f32) {
+        self.config.ui.set_terminal_font_size(size);
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+    }
+
+    /// Open a vault in the second window, remembering it for the next start
+    pub fn open_second_vault(&mut self, path: PathBuf) {
+        if let Err(e) = self.vault_window.open(path.clone()) {
+            self.notifications.error(format!("Could not open {}: {:#}", path.display(), e));
+            return;
+        }
+        self.config.add_recent_vault(path.clone());
+        self.config.second_vault = Some(path);
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+    }
+
+    fn handle_vault_window_event(&mut self, event: VaultWindowEvent) {
+        match event {
+            VaultWindowEvent::Copied(note) => {
+                let name = note.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                self.notifications.push(Severity::Success, format!("Copied {} to the second vault", name), None);
+            }
+            VaultWindowEvent::Failed(error) => self.notifications.error(error),
+            VaultWindowEvent::Closed => {
+                self.config.second_vault = None;
+                if let Err(e) = self.config.save() {
+                    self.notifications.warning(format!("Could not save settings: {:#}", e));
+                }
+            }
+        }
+    }
+
+    /// Copy a note dragged from the second window into a folder of the vault
+    pub fn copy_note_into(&mut self, note: &Path, folder: &Path) {
+        if self.vault_path.as_deref().is_some_and(|vault| note.starts_with(vault)) {
+            return;
+        }
+        match copy_into(note, folder) {
+            Ok(copy) => {
+                if let Err(e) = self.file_tree.refresh() {
+                    self.notifications.warning(format!("Could not refresh the file tree: {:#}", e));
+                }
+                self.rebuild_note_index();
+                let name = copy.file_name().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                self.notifications.push(Severity::Success, format!("Copied {}", name), Some(ToastAction::Open(copy)));
+            }
+            Err(e) => self.notifications.error(format!("Could not copy {}: {:#}", note.display(), e)),
+        }
+    }
+
+    pub fn stop_web_server(&mut self) {
+        self.web_server = None;
+        self.config.web_server.enabled = false;
+        if let Err(e) = self.config.save() {
+            self.notifications.warning(format!("Could not save settings: {:#}", e));
+        }
+    }
+
+    /// Address of the running web view, with its access token
+    fn web_server_address(&self) -> Option<String> {
+        let server = self.web_server.as_ref()?;
+        let host = lan_address().map_or("127.0.0.1".to_string(), |ip| ip.to_string());
+        Some(format!("http://{}:{}/?token={}", host, server.port, self.config.web_server.token))
+    }
+
+    /// Open the quick switcher, offering the other recent vaults too
+    pub fn open_quick_switcher(&mut self) {
+        let others = self
+            .config
+            .recent_vaults
+            .iter()
+            .filter(|vault| Some(*vault) != self.vault_path.as_ref())
+            .cloned()
+            .collect();
+        self.quick_switcher.set_other_vaults(others);
+        self.quick_switcher.open();
+    }
+
+    /// Open a note picked in the quick switcher, switching vaults or
+    /// starting another window for notes of other vaults
+    fn open_switch_target(&mut self, target: SwitchTarget) {
+        let Some(vault) = target.vault else {
+            self.open_document(target.note);
+            return;
+        };
+        if target.new_window {
+            let spawned = std::env::current_exe().and_then(|exe| {
+                std::process::Command::new(exe)
+                    .arg("--vault")
+                    .arg(&vault)
+                    .arg("--open")
+                    .arg(&target.note)
+                    .spawn()
+            });
+            if let Err(e) = spawned {
+                self.notifications.error(format!("Could not open a new window: {}", e));
+            }
+            return;
+        }
+        self.open_vault(vault);
+        self.open_document(target.note);
+    }
+
+    /// Present the active note in the presentation window, or close it
+    pub fn toggle_presentation(&mut self) {
+        match self.active_document.clone() {
+            Some(path) => self.presentation.toggle(path),
+            None if self.presentation.is_open() => self.presentation.close(),
+            None => self.notifications.info("Open a note to present it"),
+        }
+    }
+
+    /// Run a command picked from the palette
+    pub fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Save => self.save_active_document(),
+            Command::NewScratchNote => self.new_scratch_note(),
+            Command::OpenInExternalEditor => self.open_in_external_editor(),
+            Command::FileHistory => self.open_file_history(),
+            Command::ReviewInbox => self.open_inbox(),
+            Command::ReviewFlashcards => self.open_flashcards(),
+            Command::ConvertLinkStyle => match self.vault_path.clone() {
+                Some(vault) => self.link_style.open(&vault),
+                None => self.notifications.info("Open a vault to convert its links"),
+            },
+            Command::CaptureWebArticle => self.open_capture_prompt(),
+            Command::ExportHtml => self.export_vault_html(),
+            Command::ExpandAbbreviations => self.set_expand_abbreviations(!self.vault_settings.expand_abbreviations),
+            Command::FrontmatterTimestamps => {
+                let mut timestamps = self.vault_settings.timestamps.clone();
+                timestamps.enabled = !timestamps.enabled;
+                self.set_frontmatter_timestamps(timestamps);
+            }
+            Command::ShareNote => self.share_active_note(),
+            Command::PublishOverview => self.open_publish_overview(),
+            Command::PublishNote => self.publish_active_note(),
+            Command::PublishSettings => self.publish_settings.open(&self.config.publish),
+            Command::LinkSettings => self.link_settings.open(&self.config.links),
+            Command::SyncNow => self.sync_now(),
+            Command::SyncSettings => self.sync_settings.open(&self.config.sync),
+            Command::NoteColors => match self.vault_path.as_deref() {
+                Some(vault) => {
+                    self.note_colors_dialog.open(&self.vault_settings, vault_folders(vault), self.note_index.tags())
+                }
+                None => self.notifications.info("Open a vault to color its folders and tags"),
+            },
+            Command::QuickSwitcher => self.open_quick_switcher(),
+            Command::CopyToVault | Command::MoveToVault => {
+                let remove_original = command == Command::MoveToVault;
+                match self.active_document.clone() {
+                    Some(note) => self.open_transfer_dialog(note, remove_original),
+                    None => self.notifications.info("Open a note to transfer it"),
+                }
+            }
+            Command::NewVault => self.open_new_vault_dialog(),
+            Command::ExportSettings => self.export_settings(),
+            Command::ImportSettings => self.import_settings(),
+            Command::OpenSecondVault => {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.open_second_vault(path);
+                }
+            }
+            Command::ToggleStar => {
+                if let Some(path) = self.active_document.clone() {
+                    self.toggle_star(&path);
+                }
+            }
+            Command::TogglePin => self.toggle_pin_active_tab(),
+            Command::ToggleEncryption => self.toggle_active_encryption(),
+            Command::ToggleSidebar => self.sidebar_visible = !self.sidebar_visible,
+            Command::ToggleTerminal => self.terminal_visible = !self.terminal_visible,
+            Command::TerminalFind => {
+                self.set_view_mode(ViewMode::TerminalWithTree);
+                if let Some(tab) = self.pty_terminal.current_tab_mut() {
+                    tab.open_search();
+                }
+            }
+            Command::TerminalNew => {
+                self.pty_terminal.new_tab();
+                self.set_view_mode(ViewMode::TerminalWithTree);
+            }
+            Command::TerminalNewShell { program, .. } => {
+                self.pty_terminal.new_tab_with_shell(program);
+                self.set_view_mode(ViewMode::TerminalWithTree);
+            }
+            Command::TerminalClear | Command::TerminalKill => {
+                self.set_view_mode(ViewMode::TerminalWithTree);
+                let Some(tab) = self.pty_terminal.current_tab_mut() else {
+                    return;
+                };
+                let result = if command == Command::TerminalClear { tab.clear() } else { tab.kill() };
+                if let Err(e) = result {
+                    self.notifications.warning(format!("{:#}", e));
+                }
+            }
+            Command::TerminalRename => {
+                self.set_view_mode(ViewMode::TerminalWithTree);
+                self.pty_terminal.start_renaming();
+            }
+            Command::TerminalNextTab | Command::TerminalPreviousTab => {
+                self.pty_terminal.cycle_tab(command == Command::TerminalNextTab);
+                self.set_view_mode(ViewMode::TerminalWithTree);
+            }
+            Command::TerminalInsertOutput => self.insert_terminal_output(),
+            Command::TerminalSplitRight | Command::TerminalSplitDown => {
+                let direction = if command == Command::TerminalSplitRight {
+                    SplitDirection::Right
+                } else {
+                    SplitDirection::Down
+                };
+                self.pty_terminal.split(direction);
+                self.set_view_mode(ViewMode::TerminalWithTree);
+            }
+            Command::TerminalSshConnect => self.ssh_connect.open(),
+            Command::TerminalSettings => self.terminal_settings.open(&self.config.terminal),
+            Command::TerminalFontLarger => self.set_terminal_font_size(self.config.ui.terminal_font_size + 1.0),
+            Command::TerminalFontSmaller => self.set_terminal_font_size(self.config.ui.terminal_font_size - 1.0),
+            Command::TerminalFontReset => self.set_terminal_font_size(DEFAULT_TERMINAL_FONT_SIZE),
+            Command::TogglePresentation => self.toggle_presentation(),
+            Command::WebServer => self.web_server_dialog.open(),
+            Command::Diagnostics => self.diagnostics.open(),
+            Command::InsertTemplate => self.open_template_picker(),
+            Command::PasteFromHistory => self.open_clipboard_picker(),
+            Command::GoBack => self.go_back(),
+            Command::GoForward => self.go_forward(),
+            Command::SetViewMode(mode) => self.set_view_mode(mode),
+            Command::Transform(transform) => self.transform_editor_selection(transform),
+            Command::Convert(conversion) => self.convert_editor_selection(conversion),
+            Command::ConvertToCodeBlock => self.code_block_prompt = Some(String::new()),
+            Command::SetLineEnding(line_ending) => {
+                if let Some(mut doc) = self.active_document_mut() {
+                    doc.set_line_ending(line_ending);
+                }
+            }
+            Command::SetEncoding(encoding) => {
+                if let Some(mut doc) = self.active_document_mut() {
+                    doc.set_encoding(encoding);
+                }
+            }
+            Command::InstallPlugin => self.plugin_install_prompt.open(),
+            Command::CheckPluginUpdates => self.check_plugin_updates(),
+            Command::ReloadScripts => self.reload_scripts(),
+            Command::OpenScriptsFolder => self.open_scripts_folder(),
+            Command::Script { script, name } => self.run_script(&script, &name),
+            Command::Plugin { plugin, name } => self.open_plugin_command(&plugin, &name),
+        }
+    }
+
+    /// Prompt for the language of a "convert to code block" command
+    fn show_code_block_prompt(&mut self, ctx: &egui::Context) {
+        let Some(lang) = self.code_block_prompt.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Convert to Code Block")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Language (optional):");
+                let response = ui.text_edit_singleline(lang);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    confirmed = true;
+                }
+                if ui.button("Convert").clicked() {
+                    confirmed = true;
+                }
+            });
+
+        if confirmed {
+            let lang = self.code_block_prompt.take().unwrap_or_default();
+            self.convert_editor_selection(SelectionConversion::CodeBlock { lang });
+        } else if !open {
+            self.code_block_prompt = None;
+        }
+    }
+
+    /// Edit the active note in the configured program, asking for the
+    /// command the first time
+    pub fn open_in_external_editor(&mut self) {
+        let Some(doc) = self.active_document() else {
+            return;
+        };
+        if doc.is_scratch() || doc.locked {
+            self.notifications.warning("Save the note before opening it in another program");
+            return;
+        }
+        // The program reads the file, so it must hold what the editor shows
+        if doc.modified {
+            self.notifications.warning("Save your changes before opening the note in another program");
+            return;
+        }
+        let path = doc.path.clone();
+        match self.config.editor.external_command.clone() {
+            Some(command) => self.launch_external_editor(&command, &path),
+            None => {
+                let command = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).unwrap_or_default();
+                self.external_editor_prompt = Some(command);
+            }
+        }
+    }
+
+    fn launch_external_editor(&mut self, command: &str, path: &Path) {
+        if let Err(e) = self.external_editors.open(command, path) {
+            self.notifications.error(format!("{:#}", e));
+        }
+    }
+
+    /// Read a note back once the program editing it has exited
+    fn handle_editor_exited(&mut self, exited: EditorExited) {
+        self.reload_from_disk(&exited.path);
+        match exited.status {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.notifications.warning(format!("{} exited with {}", exited.editor, status)),
+            Err(e) => self.notifications.error(format!("{:#}", e)),
+        }
+    }
+
+    /// Ask for the command that opens notes in an external editor
+    fn show_external_editor_prompt(&mut self, ctx: &egui::Context) {
+        let Some(command) = self.external_editor_prompt.as_mut() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("External Editor")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Command to edit notes with, e.g. \"code --wait\" or \"kitty -e vim\".");
+                ui.label("{file} stands for the note; without it the note's path goes last.");
+                let response = ui.text_edit_singleline(command);
+                response.request_focus();
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    confirmed = true;
+                }
+                if ui.button("Open").clicked() {
+                    confirmed = true;
+                }
+            });
+
+        if confirmed {
+            let command = self.external_editor_prompt.take().unwrap_or_default();
+            if command.trim().is_empty() {
+                return;
+            }
+            self.config.editor.external_command = Some(command);
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+            self.open_in_external_editor();
+        } else if !open {
+            self.external_editor_prompt = None;
+        }
+    }
+
+    /// Open an untitled note that lives only in memory until it is saved
+    pub fn new_scratch_note(&mut self) {
+        self.scratch_count += 1;
+        let doc = Document::scratch(self.scratch_count);
+        let path = doc.path.clone();
+        self.documents.insert(doc);
+        self.open_document(path);
     }
 
-    /// Open a document
-    pub fn open_document(&mut self, path: PathBuf) {
-        if !self.documents.contains_key(&path) {
-            match Document::open(&path) {
-                Ok(doc) => {
-                    // Notify plugins
-                    self.plugin_manager.on_document_open(&doc);
-                    self.documents.insert(path.clone(), doc);
-                }
-                Err(e) => {
-                    tracing::error!("Failed to open document: {}", e);
-                    return;
-                }
+    /// Ask where to save a scratch note, then give it that path and save it
+    fn save_scratch_note(&mut self, path: PathBuf) {
+        let Some(doc) = self.documents.get(&path) else {
+            return;
+        };
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("Save note")
+            .set_file_name(format!("{}.md", doc.title()))
+            .add_filter("Markdown", &["md"]);
+        if let Some(vault) = &self.vault_path {
+            dialog = dialog.set_directory(vault);
+        }
+        let Some(mut target) = dialog.save_file() else {
+            return;
+        };
+        if target.extension().is_none() {
+            target.set_extension("md");
+        }
+        if self.documents.contains(&target) {
+            self.notifications.warning(format!("{} is open in another tab", target.display()));
+            return;
+        }
+
+        self.documents.rename(&path, target.clone());
+        for tab in self.tabs.iter_mut().filter(|tab| tab.path == path) {
+            tab.path = target.clone();
+            tab.history = NavigationHistory::new(NavEntry::new(target.clone(), None));
+        }
+        if self.active_document.as_ref() == Some(&path) {
+            self.active_document = Some(target.clone());
+        }
+        self.stamp_timestamps(&target);
+        if let Some(doc) = self.documents.get(&target) {
+            self.io.save(doc.clone());
+            self.manual_saves.insert(target);
+        }
+    }
+
+    /// Bring a note's `created`/`updated` frontmatter up to date before it
+    /// is saved. The saved content includes the stamp, so the note comes
+    /// back clean instead of being dirtied again.
+    fn stamp_timestamps(&mut self, path: &Path) {
+        let settings = &self.vault_settings.timestamps;
+        if !settings.enabled || self.vault_path.is_none() || path.extension().is_none_or(|ext| ext != "md") {
+            return;
+        }
+        let format = settings.format.clone();
+        if let Some(mut doc) = self.documents.edit(path) {
+            if !doc.locked && (doc.modified || doc.disk.is_none()) {
+                doc.stamp_timestamps(&format, chrono::Local::now());
             }
         }
-        self.active_document = Some(path);
     }
 
-    /// Save the active document
+    /// Save the active document in the background
     pub fn save_active_document(&mut self) {
-        if let Some(ref path) = self.active_document {
-            if let Some(doc) = self.documents.get(path) {
-                if let Err(e) = doc.save() {
-                    tracing::error!("Failed to save document: {}", e);
+        let Some(path) = self.active_document.clone() else {
+            return;
+        };
+        if is_scratch_path(&path) {
+            self.save_scratch_note(path);
+            return;
+        }
+        // Notes in an encrypted folder are encrypted on their first save
+        let encrypt = self
+            .vault_path
+            .as_deref()
+            .is_some_and(|vault| self.vault_settings.in_encrypted_folder(vault, &path))
+            && self.documents.get(&path).is_some_and(|doc| doc.passphrase.is_none() && !doc.locked);
+        if encrypt {
+            match self.vault_passphrase.clone() {
+                Some(passphrase) => {
+                    self.encrypt_note(&path, passphrase);
                 }
+                None => self.passphrase_dialog.open(EncryptTarget::Note(path)),
             }
+            return;
+        }
+        self.stamp_timestamps(&path);
+        if let Some(doc) = self.documents.get(&path) {
+            self.io.save(doc.clone());
+            self.manual_saves.insert(path);
         }
     }
 
     /// Get the active document mutably
-    pub fn active_document_mut(&mut self) -> Option<&mut Document> {
+    pub fn active_document_mut(&mut self) -> Option<DocumentEdit<'_>> {
+        // Notes open in another program stay read-only until it exits
         self.active_document
             .as_ref()
-            .and_then(|path| self.documents.get_mut(path))
+            .filter(|path| self.external_editors.editor_of(path).is_none())
+            .and_then(|path| self.documents.edit(path))
     }
 
     /// Get the active document
@@ -160,27 +2715,293 @@ impl RobsidianApp {
             .and_then(|path| self.documents.get(path))
     }
 
+    /// Render the status bar: the notification history toggle, save progress,
+    /// and the active document's line ending and encoding, each clickable to
+    /// convert
+    fn render_status_bar(&mut self, ctx: &egui::Context) {
+        let elements = self
+            .active_document
+            .as_ref()
+            .and_then(|path| self.documents.get(path))
+            .map(|doc| self.note_elements.elements(&doc.content));
+        let doc = self.active_document();
+        let formats = doc.map(|doc| (doc.line_ending, doc.encoding));
+        let saving = doc.is_some_and(|doc| self.io.operation(&doc.path) == Some(IoOperation::Saving));
+        let in_editor = matches!(self.active_view_mode(), ViewMode::Editor | ViewMode::Split);
+        let selection = doc.and_then(|doc| {
+            let range = clamp_range(&doc.content, self.editor_selection.clone().filter(|_| in_editor)?);
+            (!range.is_empty()).then(|| TextStats::of(&doc.content[range]))
+        });
+        let doc_stats = doc.map(|doc| TextStats::of(&doc.content));
+
+        let mut command = None;
+        let mut panel_actions = Vec::new();
+        let mut jump = None;
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let notifications = &mut self.notifications;
+                if ui
+                    .selectable_label(notifications.history_open, format!("🔔 {}", notifications.count()))
+                    .on_hover_text("Notifications")
+                    .clicked()
+                {
+                    notifications.history_open = !notifications.history_open;
+                    notifications.problems_only = false;
+                }
+                let problems = notifications.problem_count();
+                if problems > 0
+                    && ui
+                        .button(egui::RichText::new(format!("⚠ {}", problems)).color(ui.visuals().warn_fg_color))
+                        .on_hover_text("Show problems")
+                        .clicked()
+                {
+                    notifications.history_open = true;
+                    notifications.problems_only = true;
+                }
+                if saving {
+                    ui.spinner();
+                    ui.label(IoOperation::Saving.label());
+                }
+                if self.config.sync.remote.is_some() {
+                    let response = match &self.syncer.status {
+                        SyncStatus::Idle => ui.button("☁").on_hover_text("Not synced yet. Click to sync now"),
+                        SyncStatus::Syncing => {
+                            ui.spinner();
+                            ui.label("Syncing...")
+                        }
+                        SyncStatus::Synced(time) => ui
+                            .button(format!("☁ {}", time.format("%H:%M")))
+                            .on_hover_text(format!("Last synced at {}. Click to sync now", time.format("%H:%M:%S"))),
+                        SyncStatus::Failed(error) => ui
+                            .button(egui::RichText::new("☁ Sync failed").color(ui.visuals().error_fg_color))
+                            .on_hover_text(format!("{}. Click to try again", error)),
+                    };
+                    if response.clicked() && !self.syncer.is_busy() {
+                        command = Some(Command::SyncNow);
+                    }
+                }
+
+                panel_actions = self.plugin_panels.show_status_bar(ui, &self.plugin_manager);
+
+                let Some((line_ending, encoding)) = formats else {
+                    return;
+                };
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.menu_button(encoding.label(), |ui| {
+                        ui.label("Save with encoding");
+                        for option in TextEncoding::ALL {
+                            if ui.selectable_label(option == encoding, option.label()).clicked() {
+                                command = Some(Command::SetEncoding(option));
+                                ui.close();
+                            }
+                        }
+                    });
+                    ui.menu_button(line_ending.label(), |ui| {
+                        ui.label("Convert line endings");
+                        for option in LineEnding::ALL {
+                            if ui.selectable_label(option == line_ending, option.label()).clicked() {
+                                command = Some(Command::SetLineEnding(option));
+                                ui.close();
+                            }
+                        }
+                    });
+                    let doc_stats = doc_stats.unwrap_or_default();
+                    let counts = match &selection {
+                        Some(stats) => format!(
+                            "{} of {} words, {} characters",
+                            stats.words, doc_stats.words, stats.characters
+                        ),
+                        None => format!("{} words, {} characters", doc_stats.words, doc_stats.characters),
+                    };
+                    ui.menu_button(counts, |ui| {
+                        ui.label(if selection.is_some() { "Selection" } else { "Whole note" });
+                        show_text_stats(ui, selection.as_ref().unwrap_or(&doc_stats));
+                    })
+                    .response
+                    .on_hover_text("Statistics and readability");
+                    if let Some(elements) = &elements {
+                        jump = show_note_elements(ui, elements);
+                    }
+                });
+            });
+        });
+
+        self.handle_panel_actions(panel_actions);
+        if let Some(command) = command {
+            self.run_command(command);
+        }
+        if let Some(offset) = jump {
+            self.reveal_offset(offset);
+        }
+    }
+
+    /// Show the active note at a byte offset: the source editor's cursor
+    /// moves there, and live preview scrolls to its block
+    fn reveal_offset(&mut self, offset: usize) {
+        self.pending_editor_selection = Some(offset..offset);
+        self.editor_selection = Some(offset..offset);
+        self.live_preview_editor.scroll_to_offset(offset);
+    }
+
     /// Render the top menu bar
     fn render_menu_bar(&mut self, ctx: &egui::Context) {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
+                    if ui.button("New Vault...").clicked() {
+                        self.open_new_vault_dialog();
+                        ui.close();
+                    }
                     if ui.button("Open Vault...").clicked() {
                         if let Some(path) = rfd::FileDialog::new().pick_folder() {
                             self.open_vault(path);
                         }
                         ui.close();
                     }
-                    if ui.button("Save").clicked() {
+                    if ui.button("Open Vault in Second Window...").clicked() {
+                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                            self.open_second_vault(path);
+                        }
+                        ui.close();
+                    }
+                    if ui.add(egui::Button::new("Quick Switcher...").shortcut_text("Ctrl+O")).clicked() {
+                        self.open_quick_switcher();
+                        ui.close();
+                    }
+                    if ui.add(egui::Button::new("New Untitled Note").shortcut_text("Ctrl+N")).clicked() {
+                        self.new_scratch_note();
+                        ui.close();
+                    }
+                    if ui.add(egui::Button::new("Save").shortcut_text("Ctrl+S")).clicked() {
                         self.save_active_document();
                         ui.close();
                     }
+                    if ui.button("Open in External Editor").clicked() {
+                        self.open_in_external_editor();
+                        ui.close();
+                    }
+                    if ui.button("Review Inbox...").clicked() {
+                        self.open_inbox();
+                        ui.close();
+                    }
+                    if ui.button("Capture Web Article...").clicked() {
+                        self.open_capture_prompt();
+                        ui.close();
+                    }
+                    if ui.button("Sync Now").clicked() {
+                        self.sync_now();
+                        ui.close();
+                    }
+                    if ui.button("Sync Settings...").clicked() {
+                        self.sync_settings.open(&self.config.sync);
+                        ui.close();
+                    }
+                    if ui.button("Export Settings...").clicked() {
+                        self.export_settings();
+                        ui.close();
+                    }
+                    if ui.button("Import Settings...").clicked() {
+                        self.import_settings();
+                        ui.close();
+                    }
+                    if ui.button("File History...").clicked() {
+                        self.open_file_history();
+                        ui.close();
+                    }
+                    if ui.button("Export Vault to HTML...").clicked() {
+                        self.export_vault_html();
+                        ui.close();
+                    }
+                    let mut expand = self.vault_settings.expand_abbreviations;
+                    if ui.checkbox(&mut expand, "Expand Abbreviations on Export").changed() {
+                        self.set_expand_abbreviations(expand);
+                    }
+                    ui.menu_button("Frontmatter Timestamps", |ui| {
+                        let mut timestamps = self.vault_settings.timestamps.clone();
+                        let toggled =
+                            ui.checkbox(&mut timestamps.enabled, "Stamp created and updated on save").changed();
+                        ui.horizontal(|ui| {
+                            ui.label("Format");
+                            ui.add_enabled(timestamps.enabled, egui::TextEdit::singleline(&mut timestamps.format));
+                        });
+                        let mut preview = String::new();
+                        if write!(preview, "{}", chrono::Local::now().format(&timestamps.format)).is_err() {
+                            preview = "Invalid format".to_string();
+                        }
+                        ui.weak(preview);
+                        if toggled || timestamps.format != self.vault_settings.timestamps.format {
+                            self.set_frontmatter_timestamps(timestamps);
+                        }
+                    });
+                    if ui.button("Share Note...").clicked() {
+                        self.share_active_note();
+                        ui.close();
+                    }
+                    if ui.button("Publish Overview...").clicked() {
+                        self.open_publish_overview();
+                        ui.close();
+                    }
+                    if ui.button("Publish Note to the Web").clicked() {
+                        self.publish_active_note();
+                        ui.close();
+                    }
+                    if ui.button("Publish Settings...").clicked() {
+                        self.publish_settings.open(&self.config.publish);
+                        ui.close();
+                    }
+                    if ui.button("Link Settings...").clicked() {
+                        self.link_settings.open(&self.config.links);
+                        ui.close();
+                    }
                     ui.separator();
                     if ui.button("Exit").clicked() {
                         ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     }
                 });
 
+                ui.menu_button("Edit", |ui| {
+                    if ui.add(egui::Button::new("Command Palette...").shortcut_text("Ctrl+P")).clicked() {
+                        self.command_palette.open();
+                        ui.close();
+                    }
+                    if ui.button("Insert Template...").clicked() {
+                        self.open_template_picker();
+                        ui.close();
+                    }
+                    if ui.add(egui::Button::new("Paste from History...").shortcut_text("Ctrl+Shift+V")).clicked() {
+                        self.open_clipboard_picker();
+                        ui.close();
+                    }
+                    ui.separator();
+                    let has_selection = self.editor_selection.is_some() && self.active_document.is_some();
+                    ui.add_enabled_ui(has_selection, |ui| {
+                        ui.menu_button("Transform Selection", |ui| {
+                            for transform in TextTransform::ALL {
+                                if ui.button(transform.label()).clicked() {
+                                    self.transform_editor_selection(transform);
+                                    ui.close();
+                                }
+                            }
+                        });
+                        ui.menu_button("Convert Selection", |ui| {
+                            for conversion in [
+                                SelectionConversion::Quote,
+                                SelectionConversion::Callout,
+                                SelectionConversion::NumberedList,
+                            ] {
+                                if ui.button(conversion.label()).clicked() {
+                                    self.convert_editor_selection(conversion);
+                                    ui.close();
+                                }
+                            }
+                            if ui.button("Code Block...").clicked() {
+                                self.code_block_prompt = Some(String::new());
+                                ui.close();
+                            }
+                        });
+                    });
+                });
+
                 ui.menu_button("View", |ui| {
                     if ui.button("Toggle Sidebar").clicked() {
                         self.sidebar_visible = !self.sidebar_visible;
@@ -190,28 +3011,68 @@ impl RobsidianApp {
                         self.terminal_visible = !self.terminal_visible;
                         ui.close();
                     }
-                    ui.separator();
-                    ui.label("Editor Modes:");
-                    if ui.selectable_label(self.view_mode == ViewMode::Editor, "Editor Only").clicked() {
-                        self.view_mode = ViewMode::Editor;
+                    if ui
+                        .selectable_label(self.presentation.is_open(), "Presentation Window")
+                        .clicked()
+                    {
+                        self.toggle_presentation();
                         ui.close();
                     }
-                    if ui.selectable_label(self.view_mode == ViewMode::Preview, "Preview Only").clicked() {
-                        self.view_mode = ViewMode::Preview;
+                    if ui.selectable_label(self.web_server.is_some(), "Web View for Other Devices...").clicked() {
+                        self.web_server_dialog.open();
                         ui.close();
                     }
-                    if ui.selectable_label(self.view_mode == ViewMode::Split, "Split View").clicked() {
-                        self.view_mode = ViewMode::Split;
+                    if ui.selectable_label(self.diagnostics.is_open(), "Diagnostics...").clicked() {
+                        self.diagnostics.open();
                         ui.close();
                     }
-                    if ui.selectable_label(self.view_mode == ViewMode::LivePreview, "Live Preview").clicked() {
-                        self.view_mode = ViewMode::LivePreview;
-                        ui.close();
+                    ui.separator();
+                    ui.label("Editor Modes (active tab):");
+                    let in_terminal_layout = self.view_mode == ViewMode::TerminalWithTree;
+                    let current = self.active_view_mode();
+                    for mode in ViewMode::DOCUMENT_MODES {
+                        if ui
+                            .selectable_label(!in_terminal_layout && current == mode, mode.label())
+                            .clicked()
+                        {
+                            self.set_view_mode(mode);
+                            ui.close();
+                        }
                     }
                     ui.separator();
                     ui.label("Terminal Mode:");
-                    if ui.selectable_label(self.view_mode == ViewMode::TerminalWithTree, "Terminal + File Tree").clicked() {
-                        self.view_mode = ViewMode::TerminalWithTree;
+                    if ui.selectable_label(in_terminal_layout, ViewMode::TerminalWithTree.label()).clicked() {
+                        self.set_view_mode(ViewMode::TerminalWithTree);
+                        ui.close();
+                    }
+                    ui.horizontal(|ui| {
+                        let size = self.config.ui.terminal_font_size;
+                        ui.label("Terminal font size:");
+                        if ui.small_button("-").clicked() {
+                            self.set_terminal_font_size(size - 1.0);
+                        }
+                        ui.label(format!("{}", size));
+                        if ui.small_button("+").clicked() {
+                            self.set_terminal_font_size(size + 1.0);
+                        }
+                    });
+                    if ui.button("Terminal Settings...").clicked() {
+                        self.terminal_settings.open(&self.config.terminal);
+                        ui.close();
+                    }
+                });
+
+                ui.menu_button("Go", |ui| {
+                    let (can_back, can_forward) = self
+                        .active_tab()
+                        .map(|tab| (tab.history.can_go_back(), tab.history.can_go_forward()))
+                        .unwrap_or_default();
+                    if ui.add_enabled(can_back, egui::Button::new("Back").shortcut_text("Alt+Left")).clicked() {
+                        self.go_back();
+                        ui.close();
+                    }
+                    if ui.add_enabled(can_forward, egui::Button::new("Forward").shortcut_text("Alt+Right")).clicked() {
+                        self.go_forward();
                         ui.close();
                     }
                 });
@@ -221,6 +3082,49 @@ impl RobsidianApp {
                         // TODO: Open plugin manager dialog
                         ui.close();
                     }
+                    if ui.button("Install from URL...").clicked() {
+                        self.plugin_install_prompt.open();
+                        ui.close();
+                    }
+                    let checking = self.plugin_downloads.is_busy();
+                    if ui.add_enabled(!checking, egui::Button::new("Check for Updates")).clicked() {
+                        self.check_plugin_updates();
+                        ui.close();
+                    }
+                    let commands = self.plugin_manager.commands();
+                    if !commands.is_empty() {
+                        ui.separator();
+                    }
+                    for (plugin, command) in commands {
+                        let mut button = ui.button(format!("{}...", command.name));
+                        if !command.description.is_empty() {
+                            button = button.on_hover_text(&command.description);
+                        }
+                        if button.clicked() {
+                            self.plugin_command_prompt.open(plugin, command);
+                            ui.close();
+                        }
+                    }
+                    ui.separator();
+                    ui.menu_button("Scripts", |ui| {
+                        for command in self.scripts.commands().to_vec() {
+                            if ui.button(&command.name).on_hover_text(format!("{}.rhai", command.script)).clicked() {
+                                self.run_script(&command.script, &command.name);
+                                ui.close();
+                            }
+                        }
+                        if !self.scripts.commands().is_empty() {
+                            ui.separator();
+                        }
+                        if ui.button("Reload Scripts").clicked() {
+                            self.reload_scripts();
+                            ui.close();
+                        }
+                        if ui.button("Open Scripts Folder").clicked() {
+                            self.open_scripts_folder();
+                            ui.close();
+                        }
+                    });
                 });
             });
         });
@@ -229,21 +3133,315 @@ impl RobsidianApp {
 
 impl eframe::App for RobsidianApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        ime::update(ctx);
+        for result in self.io.poll() {
+            self.handle_io_result(result);
+        }
+        self.handle_document_events();
+        self.run_scheduled_work();
+        for error in self.pty_terminal.take_theme_errors() {
+            self.notifications.warning(error);
+        }
+        for error in self.pty_terminal.take_errors() {
+            self.notifications.warning(format!(
+                "{}. Install the shell or pick another one from \"+ New\" in the terminal.",
+                error
+            ));
+        }
+        self.follow_terminal_directory();
+        render_cache::file_tree_changed(ctx, self.file_tree.generation());
+        self.notify_terminal_events(ctx);
+        for (url, result) in self.web_captures.poll() {
+            self.handle_web_capture(url, result);
+        }
+        for result in self.plugin_downloads.poll() {
+            self.handle_plugin_download(result);
+        }
+        for error in self.plugin_manager.deliver_responses() {
+            self.notifications.warning(error);
+        }
+        for exited in self.external_editors.poll() {
+            self.handle_editor_exited(exited);
+        }
+        for (path, result) in self.publisher.poll() {
+            self.handle_publish_result(path, result);
+        }
+        if let Some(result) = self.syncer.poll() {
+            self.handle_sync_result(result);
+        }
+        if self.vault_path.is_some() && self.config.sync.remote.is_some() && self.syncer.is_due(&self.config.sync) {
+            self.sync_now();
+        }
+        if self.config.sync.enabled {
+            // Wake up for the sync timer even while idle
+            ctx.request_repaint_after(std::time::Duration::from_secs(30));
+        }
+        if self.io.is_busy()
+            || self.web_captures.is_busy()
+            || self.publisher.is_busy()
+            || self.syncer.is_busy()
+            || self.plugin_downloads.is_busy()
+            || self.plugin_manager.is_busy()
+            || self.scheduler.is_busy()
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(50));
+        }
+        if self.external_editors.is_busy() {
+            // Editors stay open for a while; notice their exit soon enough
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+
+        // Ctrl+Shift+V reaches egui as a paste with Shift held: offer the
+        // clipboard history instead of pasting
+        // The terminal pastes with Ctrl+Shift+V itself
+        let terminal_focused = ctx.memory(|m| m.has_focus(egui::Id::new(TERMINAL_ID)));
+        let paste_from_history = ctx.input_mut(|i| {
+            let from_history = i.modifiers.command && i.modifiers.shift && !terminal_focused;
+            let events = i.events.len();
+            i.events.retain(|event| !(from_history && matches!(event, egui::Event::Paste(_))));
+            i.events.len() != events
+        });
+        if paste_from_history {
+            self.open_clipboard_picker();
+        }
+
+        // Large or code-like pastes into the editor wait for confirmation
+        if ctx.memory(|m| m.has_focus(egui::Id::new(SOURCE_EDITOR_ID))) {
+            let mut held = None;
+            ctx.input_mut(|i| {
+                i.events.retain(|event| match event {
+                    egui::Event::Paste(text) if held.is_none() && needs_confirmation(text) => {
+                        held = Some(text.clone());
+                        false
+                    }
+                    _ => true,
+                })
+            });
+            if let Some(text) = held {
+                self.paste_prompt.open(text);
+            }
+        }
+
         // Handle keyboard shortcuts
+        let mut navigate_back = false;
+        let mut navigate_forward = false;
         ctx.input(|i| {
             if i.modifiers.ctrl && i.key_pressed(egui::Key::S) {
                 self.save_active_document();
             }
+            if i.modifiers.ctrl && i.key_pressed(egui::Key::N) {
+                self.new_scratch_note();
+            }
             if i.modifiers.ctrl && i.key_pressed(egui::Key::B) {
                 self.sidebar_visible = !self.sidebar_visible;
             }
             if i.modifiers.ctrl && i.key_pressed(egui::Key::Backtick) {
                 self.terminal_visible = !self.terminal_visible;
             }
+            let terminal_shown = self.view_mode == ViewMode::TerminalWithTree;
+            if terminal_shown && i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::F) {
+                if let Some(tab) = self.pty_terminal.current_tab_mut() {
+                    tab.open_search();
+                }
+            }
+            if i.modifiers.ctrl && i.key_pressed(egui::Key::P) {
+                self.command_palette.open();
+            }
+            if i.modifiers.ctrl && i.key_pressed(egui::Key::O) {
+                self.open_quick_switcher();
+            }
+            // Back/forward: Alt+Left/Right and mouse buttons 4/5
+            navigate_back = (i.modifiers.alt && i.key_pressed(egui::Key::ArrowLeft))
+                || i.pointer.button_pressed(egui::PointerButton::Extra1);
+            navigate_forward = (i.modifiers.alt && i.key_pressed(egui::Key::ArrowRight))
+                || i.pointer.button_pressed(egui::PointerButton::Extra2);
         });
+        if navigate_back {
+            self.go_back();
+        }
+        if navigate_forward {
+            self.go_forward();
+        }
 
         // Render menu bar
         self.render_menu_bar(ctx);
+        self.show_code_block_prompt(ctx);
+        self.show_external_editor_prompt(ctx);
+        let extra_commands = self
+            .plugin_manager
+            .commands()
+            .into_iter()
+            .map(|(plugin, command)| Command::Plugin { plugin, name: command.name })
+            .chain(self.scripts.commands().iter().map(|command| Command::Script {
+                script: command.script.clone(),
+                name: command.name.clone(),
+            }))
+            .collect();
+        if let Some(command) = self.command_palette.show(ctx, extra_commands) {
+            self.run_command(command);
+        }
+        if let Some(invocation) = self.plugin_command_prompt.show(ctx) {
+            self.run_plugin_command(invocation);
+        }
+        if let Some(source) = self.plugin_install_prompt.show(ctx) {
+            self.install_plugin(source);
+        }
+        if let Some(plugin) = self.plugin_permissions.show(ctx) {
+            self.finish_plugin_install(plugin, true);
+        }
+        if let Some((folders, tags)) = self.note_colors_dialog.show(ctx) {
+            self.vault_settings.folder_colors = folders;
+            self.vault_settings.tag_colors = tags;
+            if let Some(vault) = &self.vault_path {
+                if let Err(e) = self.vault_settings.save(vault) {
+                    self.notifications.error(format!("{:#}", e));
+                }
+            }
+        }
+        // Built from the fields, as the switcher is borrowed mutably alongside
+        let colors =
+            NoteColors { vault: self.vault_path.as_deref(), settings: &self.vault_settings, notes: &self.note_index };
+        if let Some(target) = self.quick_switcher.show(ctx, &self.note_index, &colors) {
+            self.open_switch_target(target);
+        }
+        self.presentation.show(ctx, &self.documents);
+        if let Some(request) = self.transfer_dialog.show(ctx) {
+            self.transfer_to_vault(request);
+        }
+        if let Some((path, name)) = self.rename_dialog.show(ctx) {
+            self.rename_file(path, name);
+        }
+        for event in self.vault_window.show(ctx) {
+            self.handle_vault_window_event(event);
+        }
+        if let Some((conflict, choice)) = self.save_conflicts.show(ctx, &self.documents) {
+            self.resolve_save_conflict(conflict, choice);
+        }
+        if let Some((target, passphrase)) = self.passphrase_dialog.show(ctx) {
+            match target {
+                EncryptTarget::Note(path) => {
+                    self.encrypt_note(&path, passphrase.clone());
+                    self.vault_passphrase = Some(passphrase);
+                }
+                EncryptTarget::Folder(folder) => self.encrypt_folder(&folder, passphrase),
+            }
+        }
+        if let Some(action) = self.inbox.show(ctx, &self.note_index) {
+            self.handle_inbox_action(action);
+        }
+        if let Some(action) = self.flashcards.show(ctx) {
+            self.handle_flashcard_action(action);
+        }
+        if let Some(conversions) = self.link_style.show(ctx) {
+            self.convert_link_style(conversions);
+        }
+        if let Some(target) = self.publish_settings.show(ctx) {
+            self.config.publish = target;
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+        }
+        if let Some(link) = self.link_confirm.show(ctx) {
+            if let Some(domain) = link.trust {
+                self.config.links.trusted_domains.push(domain);
+                if let Err(e) = self.config.save() {
+                    self.notifications.warning(format!("Could not save settings: {:#}", e));
+                }
+            }
+            if let Err(e) = open::that(&link.url) {
+                self.notifications.error(format!("Could not open {}: {}", link.url, e));
+            }
+        }
+        if let Some(links) = self.link_settings.show(ctx) {
+            self.config.links = links;
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+        }
+        if let Some(sync) = self.sync_settings.show(ctx) {
+            if let Some(Err(e)) = sync.remote.as_ref().map(RemoteConfig::store_secret) {
+                self.notifications.warning(format!("{:#}", e));
+            }
+            self.config.sync = sync;
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+        }
+        if let Some((path, preset)) = self.new_vault.show(ctx) {
+            self.create_vault(path, preset);
+        }
+        if let Some((bundle, parts)) = self.settings_import.show(ctx) {
+            self.apply_settings_bundle(bundle, parts);
+        }
+        if let Some((host, save)) = self.ssh_connect.show(ctx, &self.config.terminal.ssh_hosts) {
+            if save && !self.config.terminal.ssh_hosts.contains(&host) {
+                self.config.terminal.ssh_hosts.push(host.clone());
+                self.pty_terminal.configure(&self.config.terminal, self.vault_path.as_deref());
+                if let Err(e) = self.config.save() {
+                    self.notifications.warning(format!("Could not save settings: {:#}", e));
+                }
+            }
+            self.pty_terminal.connect_ssh(&host);
+            self.set_view_mode(ViewMode::TerminalWithTree);
+        }
+        if let Some(terminal) = self.terminal_settings.show(ctx, self.pty_terminal.color_schemes()) {
+            self.pty_terminal.configure(&terminal, self.vault_path.as_deref());
+            self.config.terminal = terminal;
+            if let Err(e) = self.config.save() {
+                self.notifications.warning(format!("Could not save settings: {:#}", e));
+            }
+        }
+        match self.publish_overview.show(ctx) {
+            Some(PublishAction::Open(path)) => self.open_document(path),
+            Some(PublishAction::Export) => self.export_vault_html(),
+            None => {}
+        }
+        let address = self.web_server_address();
+        match self.web_server_dialog.show(ctx, &mut self.config.web_server, address.as_deref()) {
+            Some(WebServerAction::Start) => self.start_web_server(),
+            Some(WebServerAction::Stop) => self.stop_web_server(),
+            None => {}
+        }
+        self.diagnostics.show(ctx, &self.scheduler);
+        if self.diagnostics.is_open() {
+            // Keep the timings current
+            ctx.request_repaint_after(std::time::Duration::from_millis(500));
+        }
+        if let Some(url) = self.capture_prompt.show(ctx) {
+            self.capture_web_article(url);
+        }
+        if let Some(template) = self.template_picker.show(ctx) {
+            self.insert_template(&template);
+        }
+        if let Some(text) = self.clipboard_picker.show(ctx, &self.clipboard_history) {
+            self.insert_at_cursor(&text);
+        }
+        if let Some(text) = self.paste_prompt.show(ctx) {
+            self.insert_at_cursor(&text);
+        }
+        if let Some((path, content)) = self.file_history.show(ctx, &self.documents) {
+            self.restore_snapshot(path, content);
+        }
+        match self.notifications.show(ctx) {
+            Some(ToastAction::Undo { path, content, edited }) => match self.documents.edit(&path) {
+                // Later edits would be lost
+                Some(doc) if content_hash(&doc.content) != edited => {
+                    drop(doc);
+                    self.notifications.info("The note was edited since, so it was left as it is");
+                }
+                Some(mut doc) => doc.set_content(content),
+                None => {}
+            },
+            Some(ToastAction::Open(path)) | Some(ToastAction::RetryOpen(path)) => self.open_document(path),
+            Some(ToastAction::RetrySave(path)) => {
+                if let Some(doc) = self.documents.get(&path) {
+                    self.io.save(doc.clone());
+                    self.manual_saves.insert(path);
+                }
+            }
+            Some(ToastAction::UpdatePlugin(source)) => self.install_plugin(source),
+            None => {}
+        }
 
         // Handle TerminalWithTree mode specially - it has its own layout
         if self.view_mode == ViewMode::TerminalWithTree {
@@ -253,14 +3451,18 @@ impl eframe::App for RobsidianApp {
                 .default_width(250.0)
                 .min_width(150.0)
                 .show(ctx, |ui| {
-                    FileTreePanel::show(ui, self);
+                    self.show_sidebar(ui);
                 });
 
             // Central area: PTY Terminal
             egui::CentralPanel::default().show(ctx, |ui| {
-                PtyTerminalPanel::show(ui, &mut self.pty_terminal, ctx);
+                if PtyTerminalPanel::show(ui, &mut self.pty_terminal, ctx, self.config.ui.terminal_font_size) {
+                    self.insert_terminal_output();
+                }
             });
 
+            self.remember_copied_text(ctx);
+            self.intercept_opened_urls(ctx);
             return;
         }
 
@@ -272,10 +3474,12 @@ impl eframe::App for RobsidianApp {
                 .default_width(250.0)
                 .min_width(150.0)
                 .show(ctx, |ui| {
-                    FileTreePanel::show(ui, self);
+                    self.show_sidebar(ui);
                 });
         }
 
+        self.render_status_bar(ctx);
+
         // Render terminal panel at bottom
         if self.terminal_visible {
             egui::TopBottomPanel::bottom("terminal_panel")
@@ -289,7 +3493,51 @@ impl eframe::App for RobsidianApp {
 
         // Render main content area
         egui::CentralPanel::default().show(ctx, |ui| {
-            match self.view_mode {
+            if !self.tabs.is_empty() {
+                TabBar::show(ui, self);
+                ui.separator();
+            }
+
+            // The active note is still being read in the background
+            let loading = self
+                .active_document
+                .as_ref()
+                .is_some_and(|path| !self.documents.contains(path));
+            if loading {
+                ui.centered_and_justified(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(IoOperation::Opening.label());
+                    });
+                });
+                return;
+            }
+
+            // Encrypted note waiting for its passphrase
+            if let Some(title) = self.active_document().filter(|doc| doc.locked).map(Document::title) {
+                if let Some(passphrase) = self.unlock_form.show(ui, &title) {
+                    self.unlock_active_document(passphrase);
+                }
+                return;
+            }
+
+            // Note open in another program: show it read-only until that exits
+            let external_editor = self
+                .active_document
+                .as_deref()
+                .and_then(|path| self.external_editors.editor_of(path))
+                .map(str::to_string);
+            if let Some(editor) = external_editor {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Editing in {}. The note is read-only here until {} exits.", editor, editor));
+                });
+                ui.separator();
+                PreviewPanel::show(ui, self);
+                return;
+            }
+
+            match self.active_view_mode() {
                 ViewMode::Editor => {
                     EditorPanel::show(ui, self);
                 }
@@ -326,8 +3574,8 @@ impl eframe::App for RobsidianApp {
                     let mut editor = std::mem::take(&mut self.live_preview_editor);
 
                     let action = if let Some(path) = active_path {
-                        if let Some(doc) = self.documents.get_mut(&path) {
-                            editor.show(ui, doc)
+                        if let Some(mut doc) = self.documents.edit(&path) {
+                            editor.show(ui, &mut doc, &self.note_index)
                         } else {
                             None
                         }
@@ -345,17 +3593,42 @@ impl eframe::App for RobsidianApp {
                     if let Some(action) = action {
                         match action {
                             BlockAction::NavigateToNote(target) => {
-                                // Find and open the target note
-                                if let Some(vault) = &self.vault_path {
-                                    let target_path = vault.join(format!("{}.md", target));
-                                    if target_path.exists() {
-                                        self.open_document(target_path);
-                                    }
+                                self.follow_link(&target);
+                            }
+                            BlockAction::OpenUrl { url, text } => self.open_external_url(url, Some(text)),
+                            BlockAction::OpenFile(path) => {
+                                self.navigate_to(NavEntry::new(path, None));
+                            }
+                            BlockAction::RunCode { lang, code } => {
+                                self.run_code_block(lang.as_deref(), &code);
+                            }
+                            BlockAction::BlockDeleted { previous_content } => {
+                                if let Some(path) = self.active_document.clone() {
+                                    let undo = self.documents.get(&path).map(|doc| {
+                                        ToastAction::undo(path.clone(), previous_content, &doc.content)
+                                    });
+                                    self.notifications.push(Severity::Info, "Block deleted", undo);
+                                }
+                            }
+                            BlockAction::Bookmark(anchor) => {
+                                if let Some(path) = self.active_document.clone() {
+                                    self.toggle_bookmark(&path, &anchor);
+                                }
+                            }
+                            BlockAction::Pin(anchor) => {
+                                if let Some(path) = self.active_document.clone() {
+                                    self.toggle_pin(&path, &anchor);
                                 }
                             }
-                            BlockAction::OpenUrl(url) => {
-                                // Open URL in default browser
-                                let _ = open::that(&url);
+                            BlockAction::JumpToFootnote { .. }
+                            | BlockAction::BookmarkBlock
+                            | BlockAction::PinBlock
+                            | BlockAction::EditBlock
+                            | BlockAction::CopyBlock
+                            | BlockAction::DeleteBlock
+                            | BlockAction::ConvertToQuote
+                            | BlockAction::ConvertToCallout => {
+                                // Handled inside the live preview editor
                             }
                         }
                     }
@@ -366,5 +3639,7 @@ impl eframe::App for RobsidianApp {
                 }
             }
         });
+        self.remember_copied_text(ctx);
+        self.intercept_opened_urls(ctx);
     }
 }
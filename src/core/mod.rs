@@ -1,5 +0,0 @@
-//! Core functionality for document management, file operations, and configuration
-
-pub mod config;
-pub mod document;
-pub mod file_system;
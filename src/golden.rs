@@ -0,0 +1,36 @@
+//! Golden files for tests
+//!
+//! Tests compare what they produce with files under `tests/fixtures`. Run
+//! them with `UPDATE_GOLDEN=1` to rewrite the files after an intended change,
+//! and review the diff before committing it.
+
+use std::path::{Path, PathBuf};
+
+/// Directory of the fixtures of one test suite
+pub fn fixtures_dir(suite: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(suite)
+}
+
+/// Files of a fixtures directory with the given extension, sorted by name
+pub fn fixtures(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no .{} fixtures in {}", extension, dir.display());
+    paths
+}
+
+/// Compare `actual` with the golden file at `path`, or rewrite it when
+/// `UPDATE_GOLDEN` is set
+pub fn assert_golden(path: &Path, actual: &str) {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("missing golden file {}; run with UPDATE_GOLDEN=1", path.display()));
+    assert!(expected == actual, "{} differs from the golden file:\n{}", path.display(), actual);
+}
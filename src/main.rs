@@ -3,13 +3,18 @@
 //! A Rust-based markdown editor with file explorer, terminal, and plugin support.
 
 mod app;
-mod core;
+#[cfg(test)]
+mod golden;
 mod plugin;
 mod terminal;
 mod ui;
 
-use app::RobsidianApp;
+use anyhow::Context;
+use app::{LaunchOptions, RobsidianApp};
 use eframe::egui;
+use robsidian_core::config::AppConfig;
+use robsidian_core::vault_api::{self, DEFAULT_ADDRESS};
+use robsidian_core::web_server::generate_token;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn main() -> eframe::Result<()> {
@@ -20,6 +25,14 @@ fn main() -> eframe::Result<()> {
         .init();
 
     tracing::info!("Starting Robsidian...");
+    let launch = LaunchOptions::from_args(std::env::args().skip(1));
+    if launch.serve {
+        if let Err(e) = serve(launch) {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -32,6 +45,21 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Robsidian",
         native_options,
-        Box::new(|cc| Ok(Box::new(RobsidianApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(RobsidianApp::new(cc, launch)))),
     )
 }
+
+/// Serve the vault's API without a window, until the process is stopped
+fn serve(launch: LaunchOptions) -> anyhow::Result<()> {
+    let config = AppConfig::load().unwrap_or_default();
+    let vault = launch
+        .vault
+        .or(config.last_vault)
+        .context("No vault to serve. Pass one with --vault <folder>.")?;
+    let listen = launch.listen.as_deref().unwrap_or(DEFAULT_ADDRESS);
+    let address = listen.parse().with_context(|| format!("\"{}\" isn't an address like {}", listen, DEFAULT_ADDRESS))?;
+    let token = launch.token.unwrap_or_else(generate_token);
+    println!("Serving {} at http://{}/api/", vault.display(), address);
+    println!("Authorization: Bearer {}", token);
+    vault_api::serve(vault, address, token)
+}
@@ -1,6 +1,6 @@
 //! Plugin API definitions
 
-use crate::core::document::Document;
+use robsidian_core::document::Document;
 
 /// Context provided to plugins
 #[derive(Debug, Clone)]
@@ -88,6 +88,37 @@ impl PluginCommand {
     }
 }
 
+/// Split a command's argument line into arguments, keeping double-quoted
+/// text together
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(current);
+    }
+    args
+}
+
 /// Plugin metadata
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PluginManifest {
@@ -108,7 +139,7 @@ pub struct PluginManifest {
 }
 
 /// Plugin permissions
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PluginPermission {
     /// Read files in the vault
@@ -121,6 +152,38 @@ pub enum PluginPermission {
     Network,
     /// Access to clipboard
     Clipboard,
+    /// Add commands to the command palette
+    Commands,
+    /// Show panels in the sidebar and status bar
+    Ui,
+}
+
+impl PluginPermission {
+    /// What the permission allows, as told to the user
+    pub fn description(self) -> &'static str {
+        match self {
+            PluginPermission::ReadVault => "Read the notes in your vault",
+            PluginPermission::WriteVault => "Change and create notes in your vault",
+            PluginPermission::Shell => "Run shell commands",
+            PluginPermission::Network => "Send and receive data over the network",
+            PluginPermission::Clipboard => "Read and change the clipboard",
+            PluginPermission::Commands => "Add commands to the command palette",
+            PluginPermission::Ui => "Show panels in the sidebar and status bar",
+        }
+    }
+
+    /// Name of the permission in manifests
+    pub fn key(self) -> &'static str {
+        match self {
+            PluginPermission::ReadVault => "read_vault",
+            PluginPermission::WriteVault => "write_vault",
+            PluginPermission::Shell => "shell",
+            PluginPermission::Network => "network",
+            PluginPermission::Clipboard => "clipboard",
+            PluginPermission::Commands => "commands",
+            PluginPermission::Ui => "ui",
+        }
+    }
 }
 
 /// Events that can be sent to plugins
@@ -137,3 +200,14 @@ pub enum PluginEvent {
     /// A command was invoked
     Command { name: String, args: Vec<String> },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args(r#"  one "two words"  "" three"#), ["one", "two words", "", "three"]);
+        assert!(split_args("   ").is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+//! Host side of the plugin API
+//!
+//! Plugins are WebAssembly components targeting the `plugin` world in
+//! `wit/plugin.wit`. Each host interface the world offers is gated by a
+//! manifest permission: it is only added to a plugin's linker when the
+//! plugin declares that permission, and a plugin importing an interface it
+//! wasn't granted is refused before it runs. No WASI is provided.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use wasmtime::component::{Component as WasmComponent, Linker};
+use wasmtime::Engine;
+
+use super::api::{PluginCommand, PluginPermission};
+use super::widgets::{parse_widgets, PanelLocation, PluginPanel};
+
+wasmtime::component::bindgen!({
+    path: "wit",
+    world: "plugin",
+});
+
+use robsidian::plugin::{clipboard, commands, host, network, ui, vault_read, vault_write};
+
+/// Package of the host interfaces, as in import names
+const PACKAGE: &str = "robsidian:plugin/";
+
+/// Requests a plugin may have in flight at once
+const MAX_REQUESTS: usize = 8;
+
+/// Permission a host interface needs, if any
+fn required_permission(interface: &str) -> Option<Option<PluginPermission>> {
+    Some(match interface {
+        "host" => None,
+        "vault-read" => Some(PluginPermission::ReadVault),
+        "vault-write" => Some(PluginPermission::WriteVault),
+        "commands" => Some(PluginPermission::Commands),
+        "network" => Some(PluginPermission::Network),
+        "clipboard" => Some(PluginPermission::Clipboard),
+        "ui" => Some(PluginPermission::Ui),
+        _ => return None,
+    })
+}
+
+/// Refuse a component that imports anything it wasn't granted
+pub fn check_imports(engine: &Engine, component: &WasmComponent, granted: &[PluginPermission]) -> Result<()> {
+    for (name, _) in component.component_type().imports(engine) {
+        let interface = name.strip_prefix(PACKAGE).map(|rest| rest.split('@').next().unwrap_or(rest));
+        match interface.and_then(required_permission) {
+            Some(None) => {}
+            Some(Some(permission)) if granted.contains(&permission) => {}
+            Some(Some(permission)) => {
+                bail!("uses {} without the \"{}\" permission in its manifest", name, permission.key())
+            }
+            None => bail!("imports {}, which Robsidian doesn't provide", name),
+        }
+    }
+    Ok(())
+}
+
+/// Link the host interfaces a plugin was granted
+pub fn link(linker: &mut Linker<PluginHost>, granted: &[PluginPermission]) -> Result<()> {
+    host::add_to_linker(linker, |state| state)?;
+    for permission in granted {
+        match permission {
+            PluginPermission::ReadVault => vault_read::add_to_linker(linker, |state| state)?,
+            PluginPermission::WriteVault => vault_write::add_to_linker(linker, |state| state)?,
+            PluginPermission::Commands => commands::add_to_linker(linker, |state| state)?,
+            PluginPermission::Network => network::add_to_linker(linker, |state| state)?,
+            PluginPermission::Clipboard => clipboard::add_to_linker(linker, |state| state)?,
+            PluginPermission::Ui => ui::add_to_linker(linker, |state| state)?,
+            PluginPermission::Shell => {}
+        }
+    }
+    Ok(())
+}
+
+/// State a plugin's host functions work on
+pub struct PluginHost {
+    pub plugin_id: String,
+    pub vault: Option<PathBuf>,
+    /// Commands the plugin registered
+    pub commands: Vec<PluginCommand>,
+    /// Text last copied in the app, readable by the plugin
+    pub clipboard: Option<String>,
+    /// Text the plugin put on the clipboard, for the app to pick up
+    pub copied: Option<String>,
+    /// Panels the plugin registered, in order
+    pub panels: Vec<PluginPanel>,
+    /// HTTP requests in flight, by id
+    requests: Vec<(u32, Receiver<Result<String, String>>)>,
+    next_request: u32,
+}
+
+impl PluginHost {
+    pub fn new(plugin_id: &str) -> Self {
+        Self {
+            plugin_id: plugin_id.to_string(),
+            vault: None,
+            commands: Vec::new(),
+            clipboard: None,
+            copied: None,
+            panels: Vec::new(),
+            requests: Vec::new(),
+            next_request: 0,
+        }
+    }
+
+    /// Whether HTTP requests are still in flight
+    pub fn has_requests(&self) -> bool {
+        !self.requests.is_empty()
+    }
+
+    /// Answers of the HTTP requests that finished since the last call
+    pub fn finished_requests(&mut self) -> Vec<(u32, Result<String, String>)> {
+        let mut finished = Vec::new();
+        self.requests.retain(|(id, rx)| match rx.try_recv() {
+            Ok(response) => {
+                finished.push((*id, response));
+                false
+            }
+            Err(mpsc::TryRecvError::Empty) => true,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                finished.push((*id, Err("The request was lost".to_string())));
+                false
+            }
+        });
+        finished
+    }
+
+    /// Resolve a vault-relative path, refusing any that leave the vault
+    fn vault_path(&self, path: &str) -> Result<PathBuf, String> {
+        let vault = self.vault.as_ref().ok_or("No vault is open")?;
+        robsidian_core::file_system::vault_file(vault, Path::new(path)).ok_or_else(|| format!("Not a path inside the vault: {}", path))
+    }
+}
+
+impl host::Host for PluginHost {
+    fn log(&mut self, message: String) {
+        tracing::info!("[plugin {}] {}", self.plugin_id, message);
+    }
+}
+
+impl vault_read::Host for PluginHost {
+    fn read_note(&mut self, path: String) -> Result<String, String> {
+        let file = self.vault_path(&path)?;
+        std::fs::read_to_string(file).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn list_notes(&mut self) -> Vec<String> {
+        let Some(vault) = self.vault.as_deref() else {
+            return Vec::new();
+        };
+        robsidian_core::file_system::get_markdown_files(vault)
+            .iter()
+            .filter_map(|note| note.strip_prefix(vault).ok())
+            .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+}
+
+impl vault_write::Host for PluginHost {
+    fn write_note(&mut self, path: String, content: String) -> Result<(), String> {
+        let file = self.vault_path(&path)?;
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(file, content).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+impl commands::Host for PluginHost {
+    fn register_command(&mut self, name: String, description: String) {
+        self.commands.retain(|command| command.name != name);
+        self.commands.push(PluginCommand::new(name, description));
+    }
+}
+
+impl network::Host for PluginHost {
+    fn http_get(&mut self, url: String) -> u32 {
+        let id = self.next_request;
+        self.next_request = self.next_request.wrapping_add(1);
+        let (tx, rx) = mpsc::channel();
+        if self.requests.len() >= MAX_REQUESTS {
+            let _ = tx.send(Err("Too many requests in flight".to_string()));
+        } else {
+            // The request runs off the UI thread; the plugin hears back
+            // through `on-http-response`
+            thread::spawn(move || {
+                let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(10)).build();
+                let response = agent
+                    .get(&url)
+                    .call()
+                    .map_err(|e| e.to_string())
+                    .and_then(|response| response.into_string().map_err(|e| e.to_string()));
+                let _ = tx.send(response);
+            });
+        }
+        self.requests.push((id, rx));
+        id
+    }
+}
+
+impl clipboard::Host for PluginHost {
+    fn read_text(&mut self) -> Option<String> {
+        self.clipboard.clone()
+    }
+
+    fn write_text(&mut self, text: String) {
+        self.copied = Some(text);
+    }
+}
+
+impl ui::Host for PluginHost {
+    fn register_panel(&mut self, id: String, title: String, location: ui::PanelLocation) {
+        let location = match location {
+            ui::PanelLocation::Sidebar => PanelLocation::Sidebar,
+            ui::PanelLocation::StatusBar => PanelLocation::StatusBar,
+        };
+        match self.panels.iter_mut().find(|panel| panel.id == id) {
+            Some(panel) => {
+                panel.title = title;
+                panel.location = location;
+            }
+            None => self.panels.push(PluginPanel { id, title, location, widgets: Vec::new() }),
+        }
+    }
+
+    fn update_panel(&mut self, id: String, widgets: String) -> Result<(), String> {
+        let panel = self.panels.iter_mut().find(|panel| panel.id == id).ok_or("No panel with that id")?;
+        panel.widgets = parse_widgets(&widgets).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn remove_panel(&mut self, id: String) {
+        self.panels.retain(|panel| panel.id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vault_paths_stay_inside() {
+        let dir = std::env::temp_dir().join(format!("robsidian_plugin_vault_{}", std::process::id()));
+        let (vault, outside) = (dir.join("vault"), dir.join("outside"));
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.md"), "secret").unwrap();
+
+        let mut host = PluginHost::new("test");
+        host.vault = Some(vault.clone());
+        assert_eq!(host.vault_path("Notes/a.md"), Ok(vault.join("Notes/a.md")));
+        assert!(host.vault_path("../secret.md").is_err());
+        assert!(host.vault_path("/etc/passwd").is_err());
+
+        // Links in the vault can't be followed out of it
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            symlink(&outside, vault.join("linked")).unwrap();
+            symlink(outside.join("missing.md"), vault.join("dangling.md")).unwrap();
+            assert!(host.vault_path("linked/secret.md").is_err());
+            assert!(host.vault_path("linked/new.md").is_err());
+            assert!(host.vault_path("dangling.md").is_err());
+            assert!(vault_read::Host::read_note(&mut host, "linked/secret.md".to_string()).is_err());
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_http_get_answers_later() {
+        use network::Host as _;
+
+        let mut host = PluginHost::new("test");
+        let ids: Vec<u32> = (0..=MAX_REQUESTS).map(|_| host.http_get("not a url".to_string())).collect();
+        assert_eq!(ids, (0..=MAX_REQUESTS as u32).collect::<Vec<_>>());
+
+        let mut answers = Vec::new();
+        while host.has_requests() {
+            answers.extend(host.finished_requests());
+            thread::sleep(Duration::from_millis(10));
+        }
+        answers.sort_by_key(|(id, _)| *id);
+        assert_eq!(answers.len(), MAX_REQUESTS + 1);
+        assert!(answers.iter().all(|(_, response)| response.is_err()));
+        assert_eq!(answers[MAX_REQUESTS].1, Err("Too many requests in flight".to_string()));
+    }
+}
@@ -1,16 +1,55 @@
 //! Plugin loader for WASM plugins
+//!
+//! Plugins are WebAssembly components built against the `plugin` world in
+//! `wit/plugin.wit` (see [`super::host`] for what they may import). Each call
+//! into a plugin may run a bounded amount of guest code, so a plugin stuck in
+//! a loop traps instead of freezing the app.
+//!
+//! Plugins can also be installed from the web: a `.tar.gz` archive holding
+//! `manifest.json` and the component (at its root or in one folder), given
+//! by its URL or as the latest release of a GitHub repository. The archive is
+//! checked like a plugin being loaded before it's unpacked, and the user
+//! confirms the permissions it asks for. Where it came from, and which
+//! release it was, are remembered so newer versions can be offered later
+//! without downloading them.
 
-use std::path::Path;
+use std::io::Read;
+use std::path::{Component as PathComponent, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::read::GzDecoder;
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store};
 
-use super::api::PluginManifest;
+use super::api::{PluginCommand, PluginManifest};
+use super::host::{self, PluginHost};
+use super::widgets::PluginPanel;
+
+/// Guest instructions (roughly) one hook call may run
+const FUEL_PER_CALL: u64 = 2_000_000_000;
+
+/// File in an installed plugin's folder recording where it came from
+const SOURCE_FILE: &str = "source.txt";
+
+/// File in an installed plugin's folder naming the release it came from
+const RELEASE_FILE: &str = "release.txt";
+
+/// Largest plugin archive downloaded
+const MAX_ARCHIVE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Largest size a plugin archive may unpack to
+const MAX_UNPACKED_BYTES: u64 = 256 * 1024 * 1024;
+
+const USER_AGENT: &str = concat!("Robsidian/", env!("CARGO_PKG_VERSION"));
 
 /// Plugin loader for loading WASM plugins
+#[derive(Clone)]
 pub struct PluginLoader {
     /// Wasmtime engine
-    #[allow(dead_code)]
-    engine: wasmtime::Engine,
+    engine: Engine,
 }
 
 impl Default for PluginLoader {
@@ -22,7 +61,10 @@ impl Default for PluginLoader {
 impl PluginLoader {
     /// Create a new plugin loader
     pub fn new() -> Self {
-        let engine = wasmtime::Engine::default();
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).expect("valid wasmtime config");
         Self { engine }
     }
 
@@ -41,20 +83,30 @@ impl PluginLoader {
 
         // Read WASM bytes
         let wasm_bytes = std::fs::read(&wasm_path)?;
+        self.instantiate(manifest, &wasm_bytes)
+    }
+
+    /// Compile a plugin's component, refusing it if it imports more than
+    /// its manifest grants
+    fn compile(&self, manifest: &PluginManifest, wasm: &[u8]) -> Result<Component> {
+        let component = Component::new(&self.engine, wasm).context("Not a WebAssembly component")?;
+        host::check_imports(&self.engine, &component, &manifest.permissions)?;
+        Ok(component)
+    }
+
+    /// Compile and instantiate a plugin's component with the host
+    /// interfaces its manifest grants
+    fn instantiate(&self, manifest: PluginManifest, wasm: &[u8]) -> Result<LoadedPlugin> {
+        let component = self.compile(&manifest, wasm)?;
 
-        // Compile the module
-        let module = wasmtime::Module::new(&self.engine, &wasm_bytes)?;
+        let mut linker = Linker::new(&self.engine);
+        host::link(&mut linker, &manifest.permissions)?;
 
-        // Create store and instance
-        let mut store = wasmtime::Store::new(&self.engine, ());
-        let instance = wasmtime::Instance::new(&mut store, &module, &[])?;
+        let mut store = Store::new(&self.engine, PluginHost::new(&manifest.id));
+        store.set_fuel(FUEL_PER_CALL)?;
+        let bindings = host::Plugin::instantiate(&mut store, &component, &linker)?;
 
-        Ok(LoadedPlugin {
-            manifest,
-            _module: module,
-            _instance: instance,
-            _store: store,
-        })
+        Ok(LoadedPlugin { manifest, bindings, store })
     }
 
     /// Discover plugins in a directory
@@ -76,16 +128,307 @@ impl PluginLoader {
     }
 }
 
+/// Where an installed plugin was downloaded from, if it was
+pub fn install_source(plugin_dir: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(plugin_dir.join(SOURCE_FILE)).ok()?;
+    Some(source.trim().to_string()).filter(|source| !source.is_empty())
+}
+
+/// `owner/repo` of a GitHub repository given as `owner/repo` or its address
+fn github_repo(source: &str) -> Option<(&str, &str)> {
+    let path = source
+        .strip_prefix("https://github.com/")
+        .or_else(|| source.strip_prefix("github.com/"))
+        .or_else(|| (!source.contains("://")).then_some(source))?;
+    let mut parts = path.trim_end_matches('/').trim_end_matches(".git").split('/');
+    let owner = parts.next().filter(|owner| !owner.is_empty())?;
+    let repo = parts.next().filter(|repo| !repo.is_empty())?;
+    Some((owner, repo))
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(Duration::from_secs(60)).user_agent(USER_AGENT).build()
+}
+
+/// The latest archive of a source
+struct Release {
+    url: String,
+    /// Tag of the GitHub release
+    tag: Option<String>,
+}
+
+/// Latest archive of a source: a direct URL as is, or the `.tar.gz` asset
+/// (else the source tarball) of a repo's latest release
+fn latest_release(agent: &ureq::Agent, source: &str) -> Result<Release> {
+    if source.ends_with(".tar.gz") || source.ends_with(".tgz") {
+        return Ok(Release { url: source.to_string(), tag: None });
+    }
+    let (owner, repo) = github_repo(source).ok_or_else(|| anyhow!("Not a .tar.gz URL or GitHub repository"))?;
+    let api = format!("https://api.github.com/repos/{}/{}/releases/latest", owner, repo);
+    let body = agent
+        .get(&api)
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .with_context(|| format!("No release found for {}/{}", owner, repo))?
+        .into_string()?;
+    let release: serde_json::Value = serde_json::from_str(&body)?;
+    let asset = release["assets"].as_array().into_iter().flatten().find_map(|asset| {
+        let url = asset["browser_download_url"].as_str()?;
+        (url.ends_with(".tar.gz") || url.ends_with(".tgz")).then_some(url)
+    });
+    let url = asset
+        .or_else(|| release["tarball_url"].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("The latest release of {}/{} has no archive", owner, repo))?;
+    Ok(Release { url, tag: release["tag_name"].as_str().map(str::to_string) })
+}
+
+/// What identifies the build an archive address serves: its ETag, else its
+/// modification date
+fn archive_tag(response: &ureq::Response) -> Option<String> {
+    response.header("ETag").or_else(|| response.header("Last-Modified")).map(str::to_string)
+}
+
+/// Whether a newer release of an installed plugin was published, and
+/// its version if the source tells: found by the tag of the latest
+/// GitHub release, or by the ETag of an archive address, without
+/// downloading the archive
+fn check_release(manifest: &PluginManifest, source: &str, plugin_dir: &Path) -> Result<(bool, Option<String>)> {
+    let agent = agent();
+    let release = latest_release(&agent, source)?;
+    if let Some(tag) = release.tag {
+        return Ok((is_newer(&tag, &manifest.version), Some(tag)));
+    }
+    let response = agent.head(&release.url).call().with_context(|| format!("Failed to reach {}", release.url))?;
+    let installed = std::fs::read_to_string(plugin_dir.join(RELEASE_FILE)).ok();
+    let newer = match (archive_tag(&response), installed) {
+        (Some(published), Some(installed)) => published != installed.trim(),
+        // Nothing to compare: the archive's address is all there is
+        _ => false,
+    };
+    Ok((newer, None))
+}
+
+/// Unpack gzipped data, refusing more than `limit` bytes of it
+fn gunzip(data: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut unpacked = Vec::new();
+    GzDecoder::new(data).take(limit + 1).read_to_end(&mut unpacked).context("Not a .tar.gz archive")?;
+    if unpacked.len() as u64 > limit {
+        bail!("The archive unpacks to more than {} MB", limit / (1024 * 1024));
+    }
+    Ok(unpacked)
+}
+
+/// Files of a tar archive, with their paths. Only regular files are kept.
+fn untar(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let field = |header: &[u8], range: std::ops::Range<usize>| {
+        let bytes = &header[range];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+    };
+    let mut files = Vec::new();
+    let mut long_name = None;
+    let mut offset = 0;
+    while offset + 512 <= data.len() {
+        let header = &data[offset..offset + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = usize::from_str_radix(&field(header, 124..136), 8).context("Corrupt archive")?;
+        let body = data.get(offset + 512..offset + 512 + size).context("Truncated archive")?;
+        let prefix = field(header, 345..500);
+        let name = match long_name.take() {
+            Some(name) => name,
+            None if prefix.is_empty() => field(header, 0..100),
+            None => format!("{}/{}", prefix, field(header, 0..100)),
+        };
+        match header[156] {
+            b'0' | 0 => files.push((name, body.to_vec())),
+            // GNU long name for the next entry
+            b'L' => long_name = Some(String::from_utf8_lossy(body).trim_end_matches('\0').to_string()),
+            _ => {}
+        }
+        offset += 512 + size.div_ceil(512) * 512;
+    }
+    Ok(files)
+}
+
+/// A plugin downloaded and checked, ready to be unpacked
+pub struct DownloadedPlugin {
+    pub manifest: PluginManifest,
+    /// Where it was downloaded from, as the user gave it
+    pub source: String,
+    /// Release tag, or the archive's ETag, to tell newer releases by
+    release: Option<String>,
+    /// Its files, relative to the plugin's folder
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl PluginLoader {
+    /// Download a plugin archive and check it: the manifest must be valid
+    /// and the component must load with the permissions it declares
+    pub fn download(&self, source: &str) -> Result<DownloadedPlugin> {
+        let agent = agent();
+        let release = latest_release(&agent, source.trim())?;
+        let response = agent.get(&release.url).call().with_context(|| format!("Failed to download {}", release.url))?;
+        let tag = release.tag.or_else(|| archive_tag(&response));
+        let mut gzipped = Vec::new();
+        response.into_reader().take(MAX_ARCHIVE_BYTES).read_to_end(&mut gzipped)?;
+        let tar = gunzip(&gzipped, MAX_UNPACKED_BYTES)?;
+        let mut plugin = self.check_archive(source.trim(), untar(&tar)?)?;
+        plugin.release = tag;
+        Ok(plugin)
+    }
+
+    /// Find the plugin in an archive's files and check it
+    fn check_archive(&self, source: &str, entries: Vec<(String, Vec<u8>)>) -> Result<DownloadedPlugin> {
+        // The manifest closest to the root marks the plugin's folder
+        let root = entries
+            .iter()
+            .filter_map(|(name, _)| name.strip_suffix("manifest.json"))
+            .filter(|root| root.is_empty() || root.ends_with('/'))
+            .min_by_key(|root| root.len())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("The archive has no manifest.json"))?;
+        let mut files = Vec::new();
+        for (name, data) in entries {
+            let Some(rel) = name.strip_prefix(&root) else {
+                continue;
+            };
+            let rel = PathBuf::from(rel);
+            if !rel.components().all(|part| matches!(part, PathComponent::Normal(_))) {
+                bail!("The archive has a file outside its folder: {}", name);
+            }
+            files.push((rel, data));
+        }
+
+        let file = |name: &Path| files.iter().find(|(rel, _)| rel == name).map(|(_, data)| data.as_slice());
+        let manifest: PluginManifest =
+            serde_json::from_slice(file(Path::new("manifest.json")).unwrap_or_default()).context("Invalid manifest.json")?;
+        if manifest.id.is_empty() || !manifest.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            bail!("Invalid plugin id \"{}\"", manifest.id);
+        }
+        let wasm = file(Path::new(&manifest.entry_point))
+            .ok_or_else(|| anyhow!("The archive has no {}", manifest.entry_point))?;
+        self.compile(&manifest, wasm).with_context(|| format!("{} can't be loaded", manifest.entry_point))?;
+
+        Ok(DownloadedPlugin { manifest, source: source.to_string(), release: None, files })
+    }
+
+    /// Unpack a downloaded plugin into its folder, replacing any older
+    /// version. Returns the folder.
+    pub fn install(&self, plugin: &DownloadedPlugin, plugins_dir: &Path) -> Result<PathBuf> {
+        let target = plugins_dir.join(&plugin.manifest.id);
+        let staging = plugins_dir.join(format!(".{}.partial", plugin.manifest.id));
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        for (rel, data) in &plugin.files {
+            let path = staging.join(rel);
+            if let Some(folder) = path.parent() {
+                std::fs::create_dir_all(folder)?;
+            }
+            std::fs::write(&path, data).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        std::fs::write(staging.join(SOURCE_FILE), &plugin.source)?;
+        if let Some(release) = &plugin.release {
+            std::fs::write(staging.join(RELEASE_FILE), release)?;
+        }
+        if target.exists() {
+            std::fs::remove_dir_all(&target).with_context(|| format!("Failed to remove {}", target.display()))?;
+        }
+        std::fs::rename(&staging, &target)?;
+        Ok(target)
+    }
+}
+
+/// Whether `available` is a newer version than `installed`
+pub fn is_newer(available: &str, installed: &str) -> bool {
+    match (semver::Version::parse(available.trim_start_matches('v')), semver::Version::parse(installed)) {
+        (Ok(available), Ok(installed)) => available > installed,
+        _ => available.trim_start_matches('v') != installed,
+    }
+}
+
+/// Work done by [`PluginDownloads`]
+pub enum DownloadResult {
+    /// A plugin was downloaded and checked, to be installed once the user
+    /// allows what it asks for
+    Downloaded(Result<DownloadedPlugin>),
+    /// A newer version of an installed plugin was published
+    UpdateAvailable { id: String, version: Option<String>, source: String },
+    /// An update check finished, having found `updates` newer versions
+    Checked { updates: usize, failures: Vec<String> },
+}
+
+/// Plugin installs and update checks running in the background
+pub struct PluginDownloads {
+    results_tx: Sender<DownloadResult>,
+    results: Receiver<DownloadResult>,
+    running: usize,
+}
+
+impl Default for PluginDownloads {
+    fn default() -> Self {
+        let (results_tx, results) = mpsc::channel();
+        Self { results_tx, results, running: 0 }
+    }
+}
+
+impl PluginDownloads {
+    /// Download and check a plugin on a background thread
+    pub fn download(&mut self, loader: &PluginLoader, source: String) {
+        let (loader, results) = (loader.clone(), self.results_tx.clone());
+        self.running += 1;
+        thread::spawn(move || {
+            let _ = results.send(DownloadResult::Downloaded(loader.download(&source)));
+        });
+    }
+
+    /// Look for newer versions of the installed plugins that came from the web
+    pub fn check_updates(&mut self, installed: Vec<PluginManifest>, plugins_dir: PathBuf) {
+        let results = self.results_tx.clone();
+        self.running += 1;
+        thread::spawn(move || {
+            let (mut updates, mut failures) = (0, Vec::new());
+            for manifest in installed {
+                let Some(source) = install_source(&plugins_dir.join(&manifest.id)) else {
+                    continue;
+                };
+                match check_release(&manifest, &source, &plugins_dir.join(&manifest.id)) {
+                    Ok((true, version)) => {
+                        updates += 1;
+                        let _ = results.send(DownloadResult::UpdateAvailable { id: manifest.id, version, source });
+                    }
+                    Ok((false, _)) => {}
+                    Err(e) => failures.push(format!("{}: {:#}", manifest.name, e)),
+                }
+            }
+            let _ = results.send(DownloadResult::Checked { updates, failures });
+        });
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.running > 0
+    }
+
+    /// Collect the work finished since the last call (non-blocking)
+    pub fn poll(&mut self) -> Vec<DownloadResult> {
+        let results: Vec<_> = self.results.try_iter().collect();
+        let finished = results.iter().filter(|result| !matches!(result, DownloadResult::UpdateAvailable { .. }));
+        self.running -= finished.count();
+        results
+    }
+}
+
 /// A loaded WASM plugin
 pub struct LoadedPlugin {
     /// Plugin manifest
     pub manifest: PluginManifest,
-    /// Compiled WASM module
-    _module: wasmtime::Module,
-    /// WASM instance
-    _instance: wasmtime::Instance,
+    /// Exports of the plugin's instance
+    bindings: host::Plugin,
     /// WASM store
-    _store: wasmtime::Store<()>,
+    store: Store<PluginHost>,
 }
 
 impl LoadedPlugin {
@@ -104,11 +447,198 @@ impl LoadedPlugin {
         &self.manifest.version
     }
 
-    /// Call a function in the plugin
-    #[allow(dead_code)]
-    pub fn call(&mut self, _func_name: &str, _args: &[wasmtime::Val]) -> Result<Vec<wasmtime::Val>> {
-        // TODO: Implement function calls
-        // This requires proper WIT bindings to be implemented
-        Ok(Vec::new())
+    /// Run guest code with a fresh fuel allowance
+    fn call<R>(
+        &mut self,
+        hook: &str,
+        f: impl FnOnce(&host::Plugin, &mut Store<PluginHost>) -> wasmtime::Result<R>,
+    ) -> Result<R> {
+        self.store.set_fuel(FUEL_PER_CALL)?;
+        f(&self.bindings, &mut self.store).with_context(|| format!("{} failed", hook))
+    }
+
+    pub fn on_load(&mut self, context: &str) -> Result<()> {
+        self.call("on-load", |plugin, store| plugin.call_on_load(store, context))
+    }
+
+    pub fn on_unload(&mut self) -> Result<()> {
+        self.call("on-unload", |plugin, store| plugin.call_on_unload(store))
+    }
+
+    pub fn on_document_open(&mut self, path: &str, content: &str) -> Result<()> {
+        self.call("on-document-open", |plugin, store| plugin.call_on_document_open(store, path, content))
+    }
+
+    pub fn on_document_save(&mut self, path: &str, content: &str) -> Result<()> {
+        self.call("on-document-save", |plugin, store| plugin.call_on_document_save(store, path, content))
+    }
+
+    /// Run one of the plugin's commands, returning its answer
+    pub fn on_command(&mut self, name: &str, args: &[String]) -> Result<Option<String>> {
+        self.call("on-command", |plugin, store| plugin.call_on_command(store, name, args))
+    }
+
+    pub fn on_panel_event(&mut self, panel: &str, widget: &str, value: &str) -> Result<()> {
+        self.call("on-panel-event", |plugin, store| plugin.call_on_panel_event(store, panel, widget, value))
+    }
+
+    /// Hand the plugin the answers of its finished HTTP requests
+    pub fn deliver_responses(&mut self) -> Result<()> {
+        for (id, response) in self.store.data_mut().finished_requests() {
+            let response = response.as_deref().map_err(String::as_str);
+            self.call("on-http-response", |plugin, store| plugin.call_on_http_response(store, id, response))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the plugin waits for HTTP requests
+    pub fn has_requests(&self) -> bool {
+        self.store.data().has_requests()
+    }
+
+    /// Commands the plugin registered
+    pub fn commands(&self) -> &[PluginCommand] {
+        &self.store.data().commands
+    }
+
+    /// Panels the plugin registered
+    pub fn panels(&self) -> &[PluginPanel] {
+        &self.store.data().panels
+    }
+
+    pub fn set_vault(&mut self, vault: Option<PathBuf>) {
+        self.store.data_mut().vault = vault;
+    }
+
+    /// Offer the text last copied in the app to the plugin
+    pub fn set_clipboard(&mut self, text: Option<String>) {
+        self.store.data_mut().clipboard = text;
+    }
+
+    /// Text the plugin put on the clipboard since the last call
+    pub fn take_copied_text(&mut self) -> Option<String> {
+        self.store.data_mut().copied.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::api::PluginPermission;
+
+    /// Answers every command with the command's name, and spins forever
+    /// when a document is opened
+    const ECHO_PLUGIN: &str = r#"(component
+        (core module $m
+            (memory (export "memory") 1)
+            (global $next (mut i32) (i32.const 1024))
+            (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+                (local $ptr i32)
+                (local.set $ptr (i32.and
+                    (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+                    (i32.sub (i32.const 0) (local.get 2))))
+                (global.set $next (i32.add (local.get $ptr) (local.get 3)))
+                (local.get $ptr))
+            (func (export "on-load") (param i32 i32))
+            (func (export "on-unload"))
+            (func (export "on-document-open") (param i32 i32 i32 i32)
+                (loop $forever (br $forever)))
+            (func (export "on-document-save") (param i32 i32 i32 i32))
+            (func (export "on-panel-event") (param i32 i32 i32 i32 i32 i32))
+            (func (export "on-http-response") (param i32 i32 i32 i32))
+            (func (export "on-command") (param i32 i32 i32 i32) (result i32)
+                (i32.store8 (i32.const 0) (i32.const 1))
+                (i32.store (i32.const 4) (local.get 0))
+                (i32.store (i32.const 8) (local.get 1))
+                (i32.const 0)))
+        (core instance $i (instantiate $m))
+        (func (export "on-load") (param "context" string)
+            (canon lift (core func $i "on-load") (memory $i "memory") (realloc (func $i "realloc"))))
+        (func (export "on-unload")
+            (canon lift (core func $i "on-unload")))
+        (func (export "on-document-open") (param "path" string) (param "content" string)
+            (canon lift (core func $i "on-document-open") (memory $i "memory") (realloc (func $i "realloc"))))
+        (func (export "on-document-save") (param "path" string) (param "content" string)
+            (canon lift (core func $i "on-document-save") (memory $i "memory") (realloc (func $i "realloc"))))
+        (func (export "on-command") (param "name" string) (param "args" (list string)) (result (option string))
+            (canon lift (core func $i "on-command") (memory $i "memory") (realloc (func $i "realloc"))))
+        (func (export "on-panel-event") (param "panel" string) (param "widget" string) (param "value" string)
+            (canon lift (core func $i "on-panel-event") (memory $i "memory") (realloc (func $i "realloc"))))
+        (func (export "on-http-response") (param "request" u32) (param "response" (result string (error string)))
+            (canon lift (core func $i "on-http-response") (memory $i "memory") (realloc (func $i "realloc")))))"#;
+
+    fn manifest(permissions: Vec<PluginPermission>) -> PluginManifest {
+        PluginManifest {
+            id: "echo".to_string(),
+            name: "Echo".to_string(),
+            version: "1.0.0".to_string(),
+            description: String::new(),
+            author: String::new(),
+            entry_point: "plugin.wasm".to_string(),
+            permissions,
+        }
+    }
+
+    #[test]
+    fn test_call_hooks() {
+        let loader = PluginLoader::new();
+        let mut plugin = loader.instantiate(manifest(Vec::new()), ECHO_PLUGIN.as_bytes()).unwrap();
+
+        plugin.on_load("{}").unwrap();
+        let answer = plugin.on_command("hello", &["world".to_string()]).unwrap();
+        assert_eq!(answer.as_deref(), Some("hello"));
+        assert!(plugin.on_document_open("Note.md", "text").is_err());
+
+        // Host interfaces need their permission
+        let writer = ECHO_PLUGIN.replacen(
+            "(component",
+            r#"(component
+            (import "robsidian:plugin/vault-write" (instance
+                (export "write-note" (func (param "path" string) (param "content" string)
+                    (result (result (error string)))))))"#,
+            1,
+        );
+        let refused = loader.instantiate(manifest(Vec::new()), writer.as_bytes());
+        assert!(refused.err().unwrap().to_string().contains("write_vault"));
+        assert!(loader.instantiate(manifest(vec![PluginPermission::WriteVault]), writer.as_bytes()).is_ok());
+    }
+
+    /// A tar entry for a regular file
+    fn tar_entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+        header[156] = b'0';
+        let mut entry = header;
+        entry.extend(data);
+        entry.resize(512 + data.len().div_ceil(512) * 512, 0);
+        entry
+    }
+
+    #[test]
+    fn test_check_archive() {
+        let manifest = serde_json::to_vec(&manifest(Vec::new())).unwrap();
+        let mut tar = tar_entry("echo-1.0/manifest.json", &manifest);
+        tar.extend(tar_entry("echo-1.0/plugin.wasm", ECHO_PLUGIN.as_bytes()));
+        tar.extend(vec![0u8; 1024]);
+
+        let loader = PluginLoader::new();
+        let plugin = loader.check_archive("someone/echo", untar(&tar).unwrap()).unwrap();
+        assert_eq!(plugin.manifest.id, "echo");
+        assert_eq!(plugin.files.len(), 2);
+        assert!(plugin.files.iter().any(|(rel, _)| rel == Path::new("plugin.wasm")));
+
+        let broken = untar(&tar_entry("manifest.json", &manifest)).unwrap();
+        assert!(loader.check_archive("someone/echo", broken).is_err());
+
+        // A bomb unpacking past the limit is refused
+        let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        std::io::Write::write_all(&mut gzipped, &vec![0u8; 1 << 20]).unwrap();
+        let gzipped = gzipped.finish().unwrap();
+        assert!(gunzip(&gzipped, 1 << 20).is_ok());
+        assert!(gunzip(&gzipped, (1 << 20) - 1).is_err());
+
+        assert_eq!(github_repo("https://github.com/someone/echo.git"), Some(("someone", "echo")));
+        assert!(is_newer("v1.2.0", "1.1.9") && !is_newer("1.0.0", "1.0.0"));
     }
 }
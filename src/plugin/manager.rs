@@ -1,11 +1,15 @@
 //! Plugin manager for loading and managing plugins
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::api::{PluginContext, PluginManifest};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+
+use super::api::{PluginCommand, PluginContext, PluginManifest};
 use super::loader::{LoadedPlugin, PluginLoader};
-use crate::core::document::Document;
+use super::widgets::PluginPanel;
+use robsidian_core::document::Document;
 
 /// Plugin manager
 pub struct PluginManager {
@@ -40,88 +44,189 @@ impl PluginManager {
     }
 
     /// Set the plugin context
-    #[allow(dead_code)]
     pub fn set_context(&mut self, context: PluginContext) {
         self.context = context;
     }
 
+    /// Point plugins' vault access at another vault
+    pub fn set_vault(&mut self, vault: PathBuf) {
+        for plugin in self.plugins.values_mut() {
+            plugin.set_vault(Some(vault.clone()));
+        }
+        self.context.vault_path = Some(vault);
+    }
+
     /// Discover available plugins
-    pub fn discover(&mut self, plugins_dir: &PathBuf) {
+    pub fn discover(&mut self, plugins_dir: &Path) {
         self.available_plugins = self.loader.discover_plugins(plugins_dir);
         tracing::info!("Discovered {} plugins", self.available_plugins.len());
     }
 
     /// Get available plugins
-    #[allow(dead_code)]
     pub fn available_plugins(&self) -> &[PluginManifest] {
         &self.available_plugins
     }
 
     /// Enable a plugin
-    #[allow(dead_code)]
-    pub fn enable_plugin(&mut self, id: &str, plugins_dir: &PathBuf) -> Result<(), String> {
+    pub fn enable_plugin(&mut self, id: &str, plugins_dir: &Path) -> Result<(), String> {
         if self.plugins.contains_key(id) {
             return Ok(());
         }
 
         let plugin_dir = plugins_dir.join(id);
-        match self.loader.load_plugin(&plugin_dir) {
+        let context = json!({
+            "data_dir": self.context.data_dir,
+            "vault": self.context.vault_path,
+        })
+        .to_string();
+        let loaded = self.loader.load_plugin(&plugin_dir).and_then(|mut plugin| {
+            plugin.set_vault(self.context.vault_path.clone());
+            plugin.on_load(&context)?;
+            Ok(plugin)
+        });
+        match loaded {
             Ok(plugin) => {
                 tracing::info!("Loaded plugin: {} v{}", plugin.name(), plugin.version());
                 self.enabled_plugins.push(id.to_string());
                 self.plugins.insert(id.to_string(), plugin);
                 Ok(())
             }
-            Err(e) => {
-                tracing::error!("Failed to load plugin {}: {}", id, e);
-                Err(format!("Failed to load plugin: {}", e))
-            }
+            Err(e) => Err(format!("{:#}", e)),
         }
     }
 
     /// Disable a plugin
-    #[allow(dead_code)]
     pub fn disable_plugin(&mut self, id: &str) {
-        self.plugins.remove(id);
+        if let Some(mut plugin) = self.plugins.remove(id) {
+            if let Err(e) = plugin.on_unload() {
+                tracing::warn!("Plugin {} failed to unload: {:#}", id, e);
+            }
+        }
         self.enabled_plugins.retain(|p| p != id);
         tracing::info!("Disabled plugin: {}", id);
     }
 
+    /// Load a plugin again after its files changed, if it's enabled
+    pub fn reload_plugin(&mut self, id: &str, plugins_dir: &Path) -> Result<(), String> {
+        if !self.plugins.contains_key(id) {
+            return Ok(());
+        }
+        self.disable_plugin(id);
+        self.enable_plugin(id, plugins_dir)
+    }
+
+    pub fn loader(&self) -> &PluginLoader {
+        &self.loader
+    }
+
     /// Get enabled plugin IDs
     #[allow(dead_code)]
     pub fn enabled_plugins(&self) -> &[String] {
         &self.enabled_plugins
     }
 
-    /// Notify plugins that a document was opened
-    pub fn on_document_open(&mut self, _doc: &Document) {
-        // TODO: Call plugin hooks
-        for (id, _plugin) in &mut self.plugins {
-            tracing::debug!("Notifying plugin {} of document open", id);
+    /// Call a document hook in every plugin, returning the failures
+    fn notify_document(
+        &mut self,
+        doc: &Document,
+        hook: fn(&mut LoadedPlugin, &str, &str) -> Result<()>,
+    ) -> Vec<String> {
+        // Encrypted notes stay private while locked
+        let content = if doc.locked { "" } else { doc.content.as_str() };
+        // Vault-relative, as the vault-read interface expects
+        let path = match self.context.vault_path.as_deref().and_then(|vault| doc.path.strip_prefix(vault).ok()) {
+            Some(rel) => rel.to_string_lossy().replace('\\', "/"),
+            None => doc.path.to_string_lossy().to_string(),
+        };
+        let mut errors = Vec::new();
+        for plugin in self.plugins.values_mut() {
+            if let Err(e) = hook(plugin, &path, content) {
+                errors.push(format!("Plugin '{}': {:#}", plugin.name(), e));
+            }
         }
+        errors
     }
 
-    /// Notify plugins that a document was saved
-    #[allow(dead_code)]
-    pub fn on_document_save(&mut self, _doc: &Document) {
-        // TODO: Call plugin hooks
-        for (id, _plugin) in &mut self.plugins {
-            tracing::debug!("Notifying plugin {} of document save", id);
+    /// Notify plugins that a document was opened, returning the failures
+    pub fn on_document_open(&mut self, doc: &Document) -> Vec<String> {
+        self.notify_document(doc, LoadedPlugin::on_document_open)
+    }
+
+    /// Notify plugins that a document was saved, returning the failures
+    pub fn on_document_save(&mut self, doc: &Document) -> Vec<String> {
+        self.notify_document(doc, LoadedPlugin::on_document_save)
+    }
+
+    /// Commands registered by enabled plugins, with the plugin's ID
+    pub fn commands(&self) -> Vec<(String, PluginCommand)> {
+        let mut commands: Vec<_> = self
+            .plugins
+            .iter()
+            .flat_map(|(id, plugin)| plugin.commands().iter().map(|command| (id.clone(), command.clone())))
+            .collect();
+        commands.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+        commands
+    }
+
+    /// Panels registered by enabled plugins, with the plugin's ID
+    pub fn panels(&self) -> Vec<(&str, &PluginPanel)> {
+        let mut panels: Vec<_> = self
+            .plugins
+            .iter()
+            .flat_map(|(id, plugin)| plugin.panels().iter().map(move |panel| (id.as_str(), panel)))
+            .collect();
+        panels.sort_by_key(|(id, _)| *id);
+        panels
+    }
+
+    /// Tell a plugin one of its panel's widgets was used
+    pub fn panel_event(&mut self, plugin_id: &str, panel: &str, widget: &str, value: &str) -> Result<()> {
+        let plugin = self
+            .plugins
+            .get_mut(plugin_id)
+            .ok_or_else(|| anyhow!("Plugin '{}' isn't enabled", plugin_id))?;
+        plugin.on_panel_event(panel, widget, value)
+    }
+
+    /// Hand plugins the answers of their finished HTTP requests, returning
+    /// the failures
+    pub fn deliver_responses(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        for plugin in self.plugins.values_mut() {
+            if let Err(e) = plugin.deliver_responses() {
+                errors.push(format!("Plugin '{}': {:#}", plugin.name(), e));
+            }
         }
+        errors
     }
 
-    /// Execute a plugin command
-    #[allow(dead_code)]
-    pub fn execute_command(&mut self, plugin_id: &str, command: &str, args: &[&str]) -> Option<String> {
-        if let Some(_plugin) = self.plugins.get_mut(plugin_id) {
-            // TODO: Execute command in plugin
-            tracing::debug!("Executing command {} in plugin {}", command, plugin_id);
-            Some(format!("Command '{}' executed with args: {:?}", command, args))
-        } else {
-            None
+    /// Whether plugins wait for HTTP requests
+    pub fn is_busy(&self) -> bool {
+        self.plugins.values().any(LoadedPlugin::has_requests)
+    }
+
+    /// Let plugins with clipboard access read the text last copied
+    pub fn set_clipboard(&mut self, text: &str) {
+        for plugin in self.plugins.values_mut() {
+            plugin.set_clipboard(Some(text.to_string()));
         }
     }
 
+    /// Text a plugin put on the clipboard, if any did
+    pub fn take_copied_text(&mut self) -> Option<String> {
+        self.plugins.values_mut().filter_map(LoadedPlugin::take_copied_text).last()
+    }
+
+    /// Execute a plugin command, returning the plugin's answer
+    pub fn execute_command(&mut self, plugin_id: &str, command: &str, args: &[String]) -> Result<Option<String>> {
+        let plugin = self
+            .plugins
+            .get_mut(plugin_id)
+            .ok_or_else(|| anyhow!("Plugin '{}' isn't enabled", plugin_id))?;
+        tracing::debug!("Executing command {} in plugin {}", command, plugin_id);
+        plugin.on_command(command, args)
+    }
+
     /// Get plugin count
     #[allow(dead_code)]
     pub fn plugin_count(&self) -> usize {
@@ -1,5 +1,8 @@
 //! Plugin system for extending Robsidian functionality
 
 pub mod api;
+pub mod host;
 pub mod loader;
 pub mod manager;
+pub mod script;
+pub mod widgets;
@@ -0,0 +1,309 @@
+//! User scripts
+//!
+//! Small automations written in [Rhai](https://rhai.rs), kept as `.rhai` files
+//! in the scripts folder. Unlike WASM plugins they need no compiling. A script
+//! registers commands when it's loaded; those commands can change the active
+//! note or read and write the vault's files.
+//!
+//! ```rhai
+//! register_command("Upper-case Selection", "upper");
+//!
+//! fn upper() {
+//!     replace_selection(selection().to_upper());
+//! }
+//! ```
+//!
+//! Functions available to scripts:
+//!
+//! | Function | Does |
+//! |---|---|
+//! | `register_command(name, function)` | Adds a command running `function` |
+//! | `document()` / `set_document(text)` | Text of the active note |
+//! | `selection()` / `replace_selection(text)` | Selected text in the editor |
+//! | `vault_files()` | Vault-relative paths of every note |
+//! | `read_file(path)` / `write_file(path, text)` | Files in the vault |
+//! | `notify(message)`, `print(message)` | Shows a notification |
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use robsidian_core::file_system::get_markdown_files;
+
+/// Operations a script may run per call, so a runaway loop can't hang the app
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// A command registered by a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptCommand {
+    /// File name of the script, without `.rhai`
+    pub script: String,
+    pub name: String,
+    function: String,
+}
+
+/// What a script sees of the app while it runs, and what it changed
+#[derive(Debug, Default)]
+struct ScriptState {
+    vault: Option<PathBuf>,
+    /// Script being loaded, owning the commands it registers
+    loading: Option<String>,
+    commands: Vec<ScriptCommand>,
+    document: Option<String>,
+    selection: Range<usize>,
+    changed: bool,
+    messages: Vec<String>,
+    written: Vec<PathBuf>,
+}
+
+impl ScriptState {
+    /// A vault file from a path given by a script; paths may not leave the
+    /// vault
+    fn vault_file(&self, path: &str) -> Result<PathBuf, Box<EvalAltResult>> {
+        let vault = self.vault.as_ref().ok_or("No vault is open")?;
+        let rel = Path::new(path);
+        if !rel.components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir)) {
+            return Err(format!("\"{}\" isn't a path inside the vault", path).into());
+        }
+        Ok(vault.join(rel))
+    }
+}
+
+/// What the active note looks like when a script command starts
+#[derive(Debug, Default)]
+pub struct ScriptInput {
+    pub document: Option<String>,
+    /// Byte range of the editor selection
+    pub selection: Range<usize>,
+}
+
+/// What a script command did
+#[derive(Debug, Default)]
+pub struct ScriptOutcome {
+    /// New text of the active note, if the script changed it
+    pub document: Option<String>,
+    /// Notifications and printed lines, including the command's return value
+    pub messages: Vec<String>,
+    /// Vault files the script wrote
+    pub written: Vec<PathBuf>,
+    pub error: Option<String>,
+}
+
+/// Loads the user's scripts and runs their commands
+pub struct ScriptManager {
+    engine: Engine,
+    state: Arc<Mutex<ScriptState>>,
+    scripts: HashMap<String, AST>,
+    commands: Vec<ScriptCommand>,
+}
+
+impl Default for ScriptManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptManager {
+    pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(ScriptState::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let shared = Arc::clone(&state);
+        engine.register_fn("register_command", move |name: &str, function: &str| {
+            let mut state = shared.lock().unwrap();
+            if let Some(script) = state.loading.clone() {
+                state.commands.push(ScriptCommand { script, name: name.to_string(), function: function.to_string() });
+            }
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("document", move || shared.lock().unwrap().document.clone().unwrap_or_default());
+        let shared = Arc::clone(&state);
+        engine.register_fn("set_document", move |text: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut state = shared.lock().unwrap();
+            let document = state.document.as_mut().ok_or("No note is open")?;
+            *document = text.to_string();
+            state.selection = text.len()..text.len();
+            state.changed = true;
+            Ok(())
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("selection", move || {
+            let state = shared.lock().unwrap();
+            let document = state.document.as_deref().unwrap_or_default();
+            document.get(state.selection.clone()).unwrap_or_default().to_string()
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("replace_selection", move |text: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut state = shared.lock().unwrap();
+            let range = state.selection.clone();
+            let document = state.document.as_mut().ok_or("No note is open")?;
+            if document.get(range.clone()).is_none() {
+                return Err("The selection is out of date".into());
+            }
+            document.replace_range(range.clone(), text);
+            state.selection = range.start..range.start + text.len();
+            state.changed = true;
+            Ok(())
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("vault_files", move || -> Array {
+            let state = shared.lock().unwrap();
+            let Some(vault) = state.vault.as_deref() else {
+                return Array::new();
+            };
+            get_markdown_files(vault)
+                .iter()
+                .filter_map(|path| path.strip_prefix(vault).ok())
+                .map(|rel| Dynamic::from(rel.to_string_lossy().replace('\\', "/")))
+                .collect()
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("read_file", move |path: &str| -> Result<String, Box<EvalAltResult>> {
+            let file = shared.lock().unwrap().vault_file(path)?;
+            std::fs::read_to_string(&file).map_err(|e| format!("Could not read {}: {}", path, e).into())
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("write_file", move |path: &str, text: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut state = shared.lock().unwrap();
+            let file = state.vault_file(path)?;
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| format!("Could not write {}: {}", path, e))?;
+            }
+            std::fs::write(&file, text).map_err(|e| format!("Could not write {}: {}", path, e))?;
+            state.written.push(file);
+            Ok(())
+        });
+        let shared = Arc::clone(&state);
+        engine.register_fn("notify", move |message: &str| shared.lock().unwrap().messages.push(message.to_string()));
+        let shared = Arc::clone(&state);
+        engine.on_print(move |message| shared.lock().unwrap().messages.push(message.to_string()));
+
+        Self { engine, state, scripts: HashMap::new(), commands: Vec::new() }
+    }
+
+    /// Give scripts access to another vault
+    pub fn set_vault(&mut self, vault: PathBuf) {
+        self.state.lock().unwrap().vault = Some(vault);
+    }
+
+    /// Load every script in a folder, replacing the ones loaded before.
+    /// Returns the scripts that failed.
+    pub fn load(&mut self, dir: &Path) -> Vec<String> {
+        self.scripts.clear();
+        self.commands.clear();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        files.sort();
+
+        let mut errors = Vec::new();
+        for file in files {
+            let name = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+            let ast = match std::fs::read_to_string(&file) {
+                Ok(source) => self.engine.compile(source).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            // Top-level statements run once, registering the script's commands
+            let loaded = ast.and_then(|ast| {
+                self.state.lock().unwrap().loading = Some(name.clone());
+                let result = self.engine.run_ast(&ast).map(|_| ast).map_err(|e| e.to_string());
+                self.state.lock().unwrap().loading = None;
+                result
+            });
+            let registered = std::mem::take(&mut self.state.lock().unwrap().commands);
+            match loaded {
+                Ok(ast) => {
+                    self.commands.extend(registered);
+                    self.scripts.insert(name, ast);
+                }
+                Err(e) => errors.push(format!("Script '{}': {}", name, e)),
+            }
+        }
+        errors
+    }
+
+    /// Commands registered by the loaded scripts
+    pub fn commands(&self) -> &[ScriptCommand] {
+        &self.commands
+    }
+
+    /// Run a script's command against the active note
+    pub fn run(&mut self, script: &str, name: &str, input: ScriptInput) -> ScriptOutcome {
+        let command = self.commands.iter().find(|command| command.script == script && command.name == name);
+        let Some((command, ast)) = command.and_then(|command| Some((command, self.scripts.get(script)?))) else {
+            return ScriptOutcome { error: Some(format!("No script command named '{}'", name)), ..Default::default() };
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            state.document = input.document;
+            state.selection = input.selection;
+            state.changed = false;
+        }
+        let result = self.engine.call_fn::<Dynamic>(&mut Scope::new(), ast, &command.function, ());
+
+        let mut state = self.state.lock().unwrap();
+        let document = state.document.take();
+        let mut outcome = ScriptOutcome {
+            document: document.filter(|_| state.changed),
+            messages: std::mem::take(&mut state.messages),
+            written: std::mem::take(&mut state.written),
+            error: None,
+        };
+        match result {
+            Ok(value) if !value.is_unit() => outcome.messages.push(value.to_string()),
+            Ok(_) => {}
+            Err(e) => outcome.error = Some(format!("Script '{}': {}", script, e)),
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_commands() {
+        let dir = std::env::temp_dir().join(format!("robsidian_scripts_{}", std::process::id()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::write(vault.join("A.md"), "alpha").unwrap();
+        std::fs::write(
+            dir.join("tools.rhai"),
+            r#"register_command("Shout", "shout");
+               register_command("Count", "count");
+               register_command("Escape", "escape");
+               fn shout() { replace_selection(selection().to_upper() + "!"); }
+               fn count() { write_file("Index.md", vault_files().len().to_string()); "done" }
+               fn escape() { read_file("../secret") }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.rhai"), "fn (").unwrap();
+
+        let mut scripts = ScriptManager::new();
+        scripts.set_vault(vault.clone());
+        let errors = scripts.load(&dir);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(scripts.commands().len(), 3);
+
+        let input = ScriptInput { document: Some("say hi now".into()), selection: 4..6 };
+        let outcome = scripts.run("tools", "Shout", input);
+        assert_eq!(outcome.document.as_deref(), Some("say HI! now"));
+
+        let outcome = scripts.run("tools", "Count", ScriptInput::default());
+        assert_eq!((outcome.document, outcome.messages), (None, vec!["done".to_string()]));
+        assert_eq!(std::fs::read_to_string(vault.join("Index.md")).unwrap(), "1");
+
+        assert!(scripts.run("tools", "Escape", ScriptInput::default()).error.unwrap().contains("inside the vault"));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,130 @@
+//! Plugin panel contents
+//!
+//! Plugins can't draw; they describe a panel as a small tree of widgets, sent
+//! as JSON through the `ui` interface, and the app renders it with egui.
+//! Trees are limited in size and depth so a plugin can't stall the frame.
+//!
+//! ```json
+//! [{"type": "heading", "text": "Tasks"},
+//!  {"type": "horizontal", "children": [
+//!     {"type": "checkbox", "id": "done-1", "text": "Write report", "checked": false},
+//!     {"type": "button", "id": "remove-1", "text": "🗑"}]}]
+//! ```
+
+use anyhow::{bail, Result};
+use serde::Deserialize;
+
+/// Widgets in one panel, counting nested ones
+pub const MAX_WIDGETS: usize = 500;
+
+/// Nesting of layout widgets
+pub const MAX_DEPTH: usize = 8;
+
+/// One widget of a panel
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Widget {
+    Label { text: String },
+    Heading { text: String },
+    /// Monospace, selectable text
+    Code { text: String },
+    Separator,
+    Button { id: String, text: String },
+    Checkbox { id: String, text: String, checked: bool },
+    /// Sends its text when Enter is pressed
+    TextInput {
+        id: String,
+        #[serde(default)]
+        value: String,
+        #[serde(default)]
+        hint: String,
+    },
+    /// A fraction between 0 and 1
+    Progress {
+        value: f32,
+        #[serde(default)]
+        text: String,
+    },
+    /// A web link, opened like links in notes
+    Link { text: String, url: String },
+    Horizontal { children: Vec<Widget> },
+    Vertical { children: Vec<Widget> },
+    Collapsing {
+        title: String,
+        children: Vec<Widget>,
+        #[serde(default)]
+        open: bool,
+    },
+}
+
+impl Widget {
+    fn children(&self) -> &[Widget] {
+        match self {
+            Widget::Horizontal { children } | Widget::Vertical { children } | Widget::Collapsing { children, .. } => {
+                children
+            }
+            _ => &[],
+        }
+    }
+}
+
+/// Count widgets, failing past the limits
+fn check(widgets: &[Widget], depth: usize, count: &mut usize) -> Result<()> {
+    if depth > MAX_DEPTH {
+        bail!("Widgets are nested more than {} deep", MAX_DEPTH);
+    }
+    for widget in widgets {
+        *count += 1;
+        if *count > MAX_WIDGETS {
+            bail!("A panel can't have more than {} widgets", MAX_WIDGETS);
+        }
+        check(widget.children(), depth + 1, count)?;
+    }
+    Ok(())
+}
+
+/// Parse a panel's widget tree
+pub fn parse_widgets(json: &str) -> Result<Vec<Widget>> {
+    let widgets: Vec<Widget> = serde_json::from_str(json)?;
+    check(&widgets, 0, &mut 0)?;
+    Ok(widgets)
+}
+
+/// Where a plugin's panel is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelLocation {
+    Sidebar,
+    StatusBar,
+}
+
+/// A panel registered by a plugin
+#[derive(Debug, Clone)]
+pub struct PluginPanel {
+    pub id: String,
+    pub title: String,
+    pub location: PanelLocation,
+    pub widgets: Vec<Widget>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_widgets() {
+        let widgets = parse_widgets(
+            r#"[{"type": "heading", "text": "Tasks"},
+                {"type": "horizontal", "children": [{"type": "button", "id": "go", "text": "Go"}, {"type": "separator"}]},
+                {"type": "text_input", "id": "q"}]"#,
+        )
+        .unwrap();
+        assert_eq!(widgets.len(), 3);
+        assert_eq!(widgets[2], Widget::TextInput { id: "q".into(), value: String::new(), hint: String::new() });
+
+        assert!(parse_widgets(r#"[{"type": "script", "code": "x"}]"#).is_err());
+        let deep = format!("{}[]{}", r#"[{"type": "vertical", "children": "#.repeat(10), "}]".repeat(10));
+        assert!(parse_widgets(&deep).unwrap_err().to_string().contains("nested"));
+        let many = format!("[{}]", vec![r#"{"type": "separator"}"#; MAX_WIDGETS + 1].join(","));
+        assert!(parse_widgets(&many).is_err());
+    }
+}
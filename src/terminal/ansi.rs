@@ -3,7 +3,9 @@
 //! This module parses ANSI escape sequences from terminal output
 //! and applies them to a TerminalBuffer.
 
-use crate::terminal::buffer::{color_256_to_rgb, TerminalBuffer, ANSI_COLORS};
+use crate::terminal::buffer::{color_256_to_rgb, TerminalBuffer, TerminalEvent, ANSI_COLORS};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
 use egui::Color32;
 use vte::{Params, Perform};
 
@@ -47,7 +49,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
 
     fn execute(&mut self, byte: u8) {
         match byte {
-            0x07 => {} // Bell - ignore
+            0x07 => self.buffer.push_event(TerminalEvent::Bell),
             0x08 => self.buffer.backspace(),
             0x09 => self.buffer.tab(),
             0x0A => self.buffer.newline(),
@@ -74,11 +76,50 @@ impl<'a> Perform for TerminalPerformer<'a> {
             return;
         }
 
-        // Handle OSC 0, 1, 2 (window title) - we ignore these for now
-        // Handle OSC 8 (hyperlinks) - we ignore these for now
+        match params[0] {
+            // Window title (2), icon name (1) or both (0); tabs show either.
+            // The title may itself hold semicolons.
+            b"0" | b"1" | b"2" => {
+                let title = params[1..].join(&b';');
+                self.buffer.set_title(&String::from_utf8_lossy(&title));
+            }
+            // Current directory: OSC 7 ; file://host/path
+            b"7" => {
+                let url = params[1..].join(&b';');
+                self.buffer.set_directory_url(&String::from_utf8_lossy(&url));
+            }
+            // Hyperlink: OSC 8 ; params ; URI
+            b"8" => {
+                let uri = params.get(2..).unwrap_or_default().join(&b';');
+                self.buffer.set_hyperlink(&String::from_utf8_lossy(&uri));
+            }
+            // Shell integration marks: OSC 133 ; C before a command runs,
+            // OSC 133 ; D ; exit code after it
+            b"133" => match params.get(1).copied() {
+                Some(b"C") => self.buffer.push_event(TerminalEvent::CommandStarted),
+                Some(b"D") => {
+                    let code = params.get(2).and_then(|code| std::str::from_utf8(code).ok()?.parse().ok());
+                    self.buffer.push_event(TerminalEvent::CommandFinished(code));
+                }
+                _ => {}
+            },
+            // Clipboard: OSC 52 ; selections ; base64 text, or `?` to ask
+            // for it. Whether the app goes along is up to its settings.
+            b"52" => match params.get(2).copied() {
+                Some(b"?") => self.buffer.push_event(TerminalEvent::ClipboardRequest),
+                Some(data) => {
+                    if let Ok(text) = BASE64.decode(data) {
+                        let text = String::from_utf8_lossy(&text).into_owned();
+                        self.buffer.push_event(TerminalEvent::SetClipboard(text));
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
     }
 
-    fn csi_dispatch(&mut self, params: &Params, _intermediates: &[u8], _ignore: bool, action: char) {
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
         let params: Vec<u16> = params.iter().map(|p| p.first().copied().unwrap_or(0)).collect();
 
         match action {
@@ -125,7 +166,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
                 // Cursor Position
                 let row = params.first().copied().unwrap_or(1).saturating_sub(1);
                 let col = params.get(1).copied().unwrap_or(1).saturating_sub(1);
-                self.buffer.set_cursor(col, row);
+                self.buffer.set_cursor_position(col, row);
             }
 
             // Erase
@@ -195,10 +236,18 @@ impl<'a> Perform for TerminalPerformer<'a> {
                 self.buffer.set_scroll_region(top, bottom);
             }
 
-            // Private modes (DECSET/DECRST) - common ones
-            'h' | 'l' => {
-                // Set/Reset mode - we handle a few common ones
-                // Most are ignored for simplicity
+            // Private modes (DECSET/DECRST); the others are ignored
+            'h' | 'l' if intermediates == b"?" => {
+                let enabled = action == 'h';
+                for mode in &params {
+                    match mode {
+                        1 => self.buffer.set_application_cursor(enabled),
+                        6 => self.buffer.set_origin_mode(enabled),
+                        25 => self.buffer.set_cursor_visible(enabled),
+                        2004 => self.buffer.set_bracketed_paste(enabled),
+                        _ => tracing::trace!("Unknown private mode: {}", mode),
+                    }
+                }
             }
 
             _ => {
@@ -220,12 +269,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
             }
             b'M' => {
                 // RI - Reverse Index
-                let cursor = self.buffer.cursor();
-                if cursor.row == 0 {
-                    self.buffer.scroll_down(1);
-                } else {
-                    self.buffer.move_cursor(0, -1);
-                }
+                self.buffer.reverse_index();
             }
             b'c' => {
                 // RIS - Reset
@@ -233,6 +277,7 @@ impl<'a> Perform for TerminalPerformer<'a> {
                 self.buffer.set_cursor(0, 0);
                 self.buffer.reset_style();
                 self.buffer.reset_scroll_region();
+                self.buffer.reset_modes();
             }
             _ => {}
         }
@@ -3,10 +3,34 @@
 //! This module provides a terminal buffer that stores styled characters,
 //! manages cursor position, and handles scrolling.
 
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use egui::Color32;
+use percent_encoding::percent_decode_str;
+use unicode_width::UnicodeWidthChar;
+
+/// Character of the cell covered by the right half of a double-width
+/// character
+pub const WIDE_SPACER: char = '\0';
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Most combining marks kept on one cell, so a flood of them (as in "zalgo"
+/// text) can't grow a cell without bound
+const MAX_COMBINING: usize = 16;
+
+/// Source of line revisions, shared by all terminals so a revision names one
+/// state of one line
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
 
 /// A single styled character in the terminal
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct StyledChar {
     pub c: char,
     pub fg: Color32,
@@ -16,6 +40,13 @@ pub struct StyledChar {
     pub underline: bool,
     pub strikethrough: bool,
     pub inverse: bool,
+    /// Hyperlink (OSC 8) the character belongs to, see [`TerminalBuffer::hyperlink`]
+    pub link: Option<usize>,
+    /// Combining marks (accents, variation selectors, emoji modifiers) drawn
+    /// together with `c`
+    pub combining: String,
+    /// Whether `c` is double-width; the next cell is then a [`WIDE_SPACER`]
+    pub wide: bool,
 }
 
 impl Default for StyledChar {
@@ -29,6 +60,9 @@ impl Default for StyledChar {
             underline: false,
             strikethrough: false,
             inverse: false,
+            link: None,
+            combining: String::new(),
+            wide: false,
         }
     }
 }
@@ -42,6 +76,20 @@ impl StyledChar {
         }
     }
 
+    /// Whether the cell is the right half of a double-width character
+    pub fn is_spacer(&self) -> bool {
+        self.c == WIDE_SPACER
+    }
+
+    /// Append the text of the cell: nothing for a spacer, else the
+    /// character with its combining marks
+    pub fn push_text(&self, text: &mut String) {
+        if !self.is_spacer() {
+            text.push(self.c);
+            text.push_str(&self.combining);
+        }
+    }
+
     /// Get the effective foreground color (considering inverse)
     pub fn effective_fg(&self) -> Color32 {
         if self.inverse {
@@ -64,8 +112,11 @@ impl StyledChar {
 /// A single line in the terminal buffer
 #[derive(Debug, Clone)]
 pub struct TerminalLine {
+    /// Cells of the line; change them through [`Self::set`] and the other
+    /// methods so the revision follows
     pub chars: Vec<StyledChar>,
     pub wrapped: bool, // Whether this line is a continuation of the previous
+    revision: u64,
 }
 
 impl TerminalLine {
@@ -74,9 +125,20 @@ impl TerminalLine {
         Self {
             chars: vec![StyledChar::default(); width],
             wrapped: false,
+            revision: next_revision(),
         }
     }
 
+    /// Changes whenever a cell of the line does, and only then: a line moved
+    /// by scrolling keeps it, so renderers can reuse what they laid out
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn touch(&mut self) {
+        self.revision = next_revision();
+    }
+
     /// Get the character at the given column
     pub fn get(&self, col: usize) -> Option<&StyledChar> {
         self.chars.get(col)
@@ -86,6 +148,7 @@ impl TerminalLine {
     pub fn set(&mut self, col: usize, ch: StyledChar) {
         if col < self.chars.len() {
             self.chars[col] = ch;
+            self.touch();
         }
     }
 
@@ -95,18 +158,39 @@ impl TerminalLine {
             *ch = StyledChar::default();
         }
         self.wrapped = false;
+        self.touch();
     }
 
     /// Resize the line to the given width
     pub fn resize(&mut self, width: usize) {
         self.chars.resize(width, StyledChar::default());
+        self.touch();
     }
 
     /// Get the content as a string (trimming trailing spaces)
     pub fn to_string_trimmed(&self) -> String {
-        let s: String = self.chars.iter().map(|c| c.c).collect();
+        let mut s = String::new();
+        for ch in &self.chars {
+            ch.push_text(&mut s);
+        }
         s.trim_end().to_string()
     }
+
+    /// Blank out what is left of a double-width character when one of its
+    /// halves at `col` is about to be overwritten
+    fn break_wide_at(&mut self, col: usize) {
+        let (start, end) = match self.chars.get(col) {
+            Some(ch) if ch.is_spacer() => (col.saturating_sub(1), col),
+            Some(ch) if ch.wide => (col, col + 1),
+            _ => return,
+        };
+        for ch in self.chars.iter_mut().take(end + 1).skip(start) {
+            ch.c = ' ';
+            ch.wide = false;
+            ch.combining.clear();
+        }
+        self.touch();
+    }
 }
 
 /// Cursor position in the terminal
@@ -116,6 +200,47 @@ pub struct CursorPos {
     pub col: u16,
 }
 
+/// A cell, counting lines from the top of the scrollback
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CellPos {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Cells selected with the mouse, from where the drag started to where it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub anchor: CellPos,
+    pub head: CellPos,
+}
+
+impl Selection {
+    /// First and last selected cell
+    pub fn range(&self) -> (CellPos, CellPos) {
+        (self.anchor.min(self.head), self.anchor.max(self.head))
+    }
+
+    pub fn contains(&self, pos: CellPos) -> bool {
+        let (start, end) = self.range();
+        start <= pos && pos <= end
+    }
+}
+
+/// Something the program signalled, for the tab to act on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalEvent {
+    Bell,
+    /// The shell started running a command (OSC 133 ; C)
+    CommandStarted,
+    /// The command finished, with its exit code if the shell gave one
+    /// (OSC 133 ; D)
+    CommandFinished(Option<i32>),
+    /// The program copied text to the clipboard (OSC 52)
+    SetClipboard(String),
+    /// The program asked what is on the clipboard (OSC 52 with `?`)
+    ClipboardRequest,
+}
+
 /// Terminal buffer that stores the screen content
 pub struct TerminalBuffer {
     lines: Vec<TerminalLine>,
@@ -126,6 +251,24 @@ pub struct TerminalBuffer {
     size: (u16, u16),          // (cols, rows)
     current_style: StyledChar, // Current style for new characters
     max_scrollback: usize,
+    /// Lines the view is scrolled back into the scrollback; 0 follows the output
+    view_offset: usize,
+    /// Whether the program asked for pastes wrapped in escape sequences
+    bracketed_paste: bool,
+    /// Whether the cursor is shown (DECTCEM)
+    cursor_visible: bool,
+    /// Whether the cursor keys send application sequences (DECCKM)
+    application_cursor: bool,
+    /// Whether cursor positions count from the scroll region (DECOM)
+    origin_mode: bool,
+    /// Targets of the hyperlinks printed so far, indexed by `StyledChar::link`
+    hyperlinks: Vec<String>,
+    /// Window title last set by the program (OSC 0, 1 or 2)
+    title: Option<String>,
+    /// Directory the shell last reported being in (OSC 7)
+    directory: Option<PathBuf>,
+    /// Events not taken yet
+    events: Vec<TerminalEvent>,
 }
 
 impl TerminalBuffer {
@@ -144,6 +287,15 @@ impl TerminalBuffer {
             size: (cols, rows),
             current_style: StyledChar::default(),
             max_scrollback: 10000,
+            view_offset: 0,
+            bracketed_paste: false,
+            cursor_visible: true,
+            application_cursor: false,
+            origin_mode: false,
+            hyperlinks: Vec::new(),
+            title: None,
+            directory: None,
+            events: Vec::new(),
         }
     }
 
@@ -163,6 +315,17 @@ impl TerminalBuffer {
         self.cursor.row = row.min(self.size.1.saturating_sub(1));
     }
 
+    /// Move the cursor to a position given by the program, which counts
+    /// from the top of the scroll region and stays in it in origin mode
+    pub fn set_cursor_position(&mut self, col: u16, row: u16) {
+        if self.origin_mode {
+            let (top, bottom) = self.scroll_region;
+            self.set_cursor(col, row.saturating_add(top).min(bottom));
+        } else {
+            self.set_cursor(col, row);
+        }
+    }
+
     /// Move cursor relatively
     pub fn move_cursor(&mut self, dcol: i16, drow: i16) {
         let new_col = (self.cursor.col as i16 + dcol).max(0) as u16;
@@ -197,9 +360,269 @@ impl TerminalBuffer {
         &self.scrollback
     }
 
-    /// Put a character at the current cursor position
+    /// Drop the scrollback, leaving the screen as it is
+    pub fn clear_scrollback(&mut self) {
+        self.scrollback.clear();
+        self.view_offset = 0;
+    }
+
+    /// Keep at most `lines` lines of scrollback, dropping the oldest ones
+    pub fn set_max_scrollback(&mut self, lines: usize) {
+        self.max_scrollback = lines;
+        let excess = self.scrollback.len().saturating_sub(lines);
+        self.scrollback.drain(..excess);
+        self.view_offset = self.view_offset.min(self.scrollback.len());
+    }
+
+    /// Lines the view is scrolled back from the live screen
+    pub fn view_offset(&self) -> usize {
+        self.view_offset
+    }
+
+    /// Scroll the view back into the scrollback (positive) or forward
+    pub fn scroll_view(&mut self, lines: isize) {
+        self.set_view_offset(self.view_offset.saturating_add_signed(lines));
+    }
+
+    pub fn set_view_offset(&mut self, offset: usize) {
+        self.view_offset = offset.min(self.scrollback.len());
+    }
+
+    /// Lines in view: the end of the scrollback while scrolled back, then the
+    /// top of the screen
+    pub fn visible_lines(&self) -> impl Iterator<Item = &TerminalLine> {
+        let rows = self.lines.len();
+        let from_scrollback = self.view_offset.min(rows);
+        let start = self.scrollback.len() - self.view_offset;
+        self.scrollback[start..start + from_scrollback]
+            .iter()
+            .chain(&self.lines[..rows - from_scrollback])
+    }
+
+    /// Lines in the scrollback and on the screen
+    pub fn line_count(&self) -> usize {
+        self.scrollback.len() + self.lines.len()
+    }
+
+    /// Line of the scrollback followed by the screen
+    pub fn line_at(&self, line: usize) -> Option<&TerminalLine> {
+        match line.checked_sub(self.scrollback.len()) {
+            Some(row) => self.lines.get(row),
+            None => self.scrollback.get(line),
+        }
+    }
+
+    /// Line number, counted from the top of the scrollback, of the first
+    /// line in view
+    pub fn first_visible_line(&self) -> usize {
+        self.scrollback.len() - self.view_offset
+    }
+
+    /// Scroll the view so `line`, counted from the top of the scrollback, is
+    /// in view, a third of the way down when it wasn't
+    pub fn reveal_line(&mut self, line: usize) {
+        let rows = self.lines.len();
+        let first = self.first_visible_line();
+        if (first..first + rows).contains(&line) {
+            return;
+        }
+        let top = line.saturating_sub(rows / 3);
+        self.set_view_offset(self.scrollback.len().saturating_sub(top));
+    }
+
+    /// Changes whenever a cell of the scrollback or the screen does: every
+    /// change touches a screen line, and lines only reach the scrollback by
+    /// scrolling a new one onto the screen
+    pub fn content_revision(&self) -> (usize, u64) {
+        let newest = self.lines.iter().map(TerminalLine::revision).max().unwrap_or_default();
+        (self.scrollback.len(), newest)
+    }
+
+    /// Text of the selected cells. Lines that wrapped are joined again, and
+    /// trailing blanks of the other lines are dropped.
+    pub fn selected_text(&self, selection: &Selection) -> String {
+        let (start, end) = selection.range();
+        let mut text = String::new();
+        for number in start.line..=end.line {
+            let Some(line) = self.line_at(number) else {
+                break;
+            };
+            let from = if number == start.line { start.col } else { 0 };
+            let to = if number == end.line { end.col + 1 } else { line.chars.len() };
+            let mut part = String::new();
+            for ch in line.chars.get(from..to.min(line.chars.len()).max(from)).unwrap_or_default() {
+                ch.push_text(&mut part);
+            }
+            if number < end.line && self.line_at(number + 1).is_some_and(|next| next.wrapped) {
+                text.push_str(&part);
+            } else {
+                text.push_str(part.trim_end());
+                if number < end.line {
+                    text.push('\n');
+                }
+            }
+        }
+        text
+    }
+
+    /// Output of the last command: the lines between the prompt the cursor
+    /// is at and the previous line starting with the same prompt, found by
+    /// the text before the cursor. `None` when there is no earlier prompt or
+    /// the command printed nothing.
+    pub fn last_output(&self) -> Option<String> {
+        let cursor_line = self.scrollback.len() + self.cursor.row as usize;
+        let line_text = |number: usize| self.line_at(number).map(TerminalLine::to_string_trimmed).unwrap_or_default();
+        let prompt_line = line_text(cursor_line);
+        let prompt: String = prompt_line.chars().take(self.cursor.col as usize).collect();
+        let prompt = prompt.trim();
+        if prompt.is_empty() {
+            return None;
+        }
+        let command_line = (0..cursor_line).rev().find(|number| line_text(*number).starts_with(prompt))?;
+        // A long command wraps onto the lines after its prompt
+        let wrapped = |number: &usize| self.line_at(*number).is_some_and(|line| line.wrapped);
+        let first = (command_line + 1..cursor_line).find(|number| !wrapped(number))?;
+        let end = CellPos { line: cursor_line - 1, col: usize::from(self.size.0) };
+        let output = self.selected_text(&Selection { anchor: CellPos { line: first, col: 0 }, head: end });
+        let output = output.trim_matches('\n');
+        (!output.trim().is_empty()).then(|| output.to_string())
+    }
+
+    /// Whether pastes should be wrapped in `ESC [200~` and `ESC [201~`
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    pub fn set_bracketed_paste(&mut self, enabled: bool) {
+        self.bracketed_paste = enabled;
+    }
+
+    /// Whether the program wants the cursor shown
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn set_cursor_visible(&mut self, visible: bool) {
+        self.cursor_visible = visible;
+    }
+
+    /// Whether the cursor keys should send `ESC O` sequences rather than
+    /// `ESC [` ones
+    pub fn application_cursor(&self) -> bool {
+        self.application_cursor
+    }
+
+    pub fn set_application_cursor(&mut self, enabled: bool) {
+        self.application_cursor = enabled;
+    }
+
+    /// Switch origin mode, which also moves the cursor to the new home
+    pub fn set_origin_mode(&mut self, enabled: bool) {
+        self.origin_mode = enabled;
+        self.set_cursor_position(0, 0);
+    }
+
+    /// Switch the modes a program can set back to their defaults
+    pub fn reset_modes(&mut self) {
+        self.bracketed_paste = false;
+        self.cursor_visible = true;
+        self.application_cursor = false;
+        self.origin_mode = false;
+    }
+
+    /// Title the program gave the window, such as the current directory or
+    /// the command it runs
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Set the window title; an empty title goes back to none
+    pub fn set_title(&mut self, title: &str) {
+        self.title = (!title.trim().is_empty()).then(|| title.to_string());
+    }
+
+    /// Directory the shell reported being in, if it reports that
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    /// Take the directory from a `file://host/path` URL, as shells report it
+    pub fn set_directory_url(&mut self, url: &str) {
+        let Some(rest) = url.strip_prefix("file://") else {
+            return;
+        };
+        let path = rest.find('/').map_or("", |start| &rest[start..]);
+        let path = percent_decode_str(path).decode_utf8_lossy();
+        // `/C:/Users` on Windows
+        let path = if cfg!(windows) { path.trim_start_matches('/') } else { &path };
+        if !path.is_empty() {
+            self.directory = Some(PathBuf::from(path));
+        }
+    }
+
+    pub fn push_event(&mut self, event: TerminalEvent) {
+        self.events.push(event);
+    }
+
+    /// Events signalled since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<TerminalEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Link the characters printed from now on to `uri`; an empty `uri` ends
+    /// the link
+    pub fn set_hyperlink(&mut self, uri: &str) {
+        self.current_style.link = (!uri.is_empty()).then(|| {
+            // Programs tend to print the same few links again and again
+            self.hyperlinks.iter().rposition(|known| known == uri).unwrap_or_else(|| {
+                self.hyperlinks.push(uri.to_string());
+                self.hyperlinks.len() - 1
+            })
+        });
+    }
+
+    /// Target of a hyperlink printed by the program
+    pub fn hyperlink(&self, link: usize) -> Option<&str> {
+        self.hyperlinks.get(link).map(String::as_str)
+    }
+
+    /// Link under a cell: a hyperlink the program printed, or else a plain
+    /// web address in the text
+    pub fn link_at(&self, pos: CellPos) -> Option<String> {
+        let line = self.line_at(pos.line)?;
+        if let Some(link) = line.get(pos.col)?.link {
+            return self.hyperlink(link).map(str::to_string);
+        }
+        find_urls(&line.chars)
+            .into_iter()
+            .find(|span| span.contains(&pos.col))
+            .map(|span| {
+                let mut url = String::new();
+                line.chars[span].iter().for_each(|ch| ch.push_text(&mut url));
+                url
+            })
+    }
+
+    /// Put a character at the current cursor position. East Asian wide
+    /// characters and most emoji take two cells; combining marks join the
+    /// character before them.
     pub fn put_char(&mut self, c: char) {
-        if self.cursor.col >= self.size.0 {
+        // Skin tones modify the emoji before them, and a zero-width joiner
+        // glues the next emoji onto the one before it (as in family emoji)
+        let joins = matches!(c, '\u{1F3FB}'..='\u{1F3FF}')
+            && self.previous_cell().is_some_and(|ch| ch.wide)
+            || self.previous_cell().is_some_and(|ch| ch.combining.ends_with(ZERO_WIDTH_JOINER));
+        if joins {
+            return self.combine_char(c);
+        }
+        let width = match c.width() {
+            Some(0) | None => return self.combine_char(c),
+            // A line too narrow for a wide character shows it in one cell
+            Some(2) if self.size.0 >= 2 => 2,
+            Some(_) => 1,
+        };
+        // A wide character that doesn't fit in the last column goes to the next line
+        if self.cursor.col + width > self.size.0 {
             // Wrap to next line
             self.cursor.col = 0;
             self.cursor.row += 1;
@@ -207,18 +630,64 @@ impl TerminalBuffer {
                 self.scroll_up(1);
                 self.cursor.row = self.size.1 - 1;
             }
+            if let Some(line) = self.lines.get_mut(self.cursor.row as usize) {
+                line.wrapped = true;
+            }
         }
 
         let row = self.cursor.row as usize;
         let col = self.cursor.col as usize;
 
-        if row < self.lines.len() {
-            let mut styled = self.current_style;
+        if let Some(line) = self.lines.get_mut(row) {
+            for col in col..col + width as usize {
+                line.break_wide_at(col);
+            }
+            let mut styled = self.current_style.clone();
             styled.c = c;
-            self.lines[row].set(col, styled);
+            styled.wide = width == 2;
+            if width == 2 {
+                let mut spacer = self.current_style.clone();
+                spacer.c = WIDE_SPACER;
+                line.set(col + 1, spacer);
+            }
+            line.set(col, styled);
+        }
+
+        self.cursor.col += width;
+    }
+
+    /// Column of the character printed last on the cursor's line
+    fn previous_col(&self) -> Option<usize> {
+        let col = (self.cursor.col.min(self.size.0) as usize).checked_sub(1)?;
+        let line = self.lines.get(self.cursor.row as usize)?;
+        match line.chars.get(col) {
+            Some(ch) if ch.is_spacer() => col.checked_sub(1),
+            _ => Some(col),
         }
+    }
 
-        self.cursor.col += 1;
+    fn previous_cell(&self) -> Option<&StyledChar> {
+        self.lines.get(self.cursor.row as usize)?.get(self.previous_col()?)
+    }
+
+    /// Add a zero-width character to the one printed before the cursor
+    fn combine_char(&mut self, c: char) {
+        if c.is_control() {
+            return;
+        }
+        let Some(col) = self.previous_col() else {
+            return;
+        };
+        let row = self.cursor.row as usize;
+        let Some(line) = self.lines.get_mut(row) else {
+            return;
+        };
+        if let Some(ch) = line.chars.get_mut(col) {
+            if ch.combining.chars().count() < MAX_COMBINING {
+                ch.combining.push(c);
+                line.touch();
+            }
+        }
     }
 
     /// Handle newline
@@ -257,13 +726,21 @@ impl TerminalBuffer {
 
         for _ in 0..n {
             if top < self.lines.len() {
-                // Move top line to scrollback
+                // Move top line to scrollback, unless it leaves a region below
+                // the top of the screen (e.g. a status bar stays fixed above)
                 let line = self.lines.remove(top);
-                self.scrollback.push(line);
+                if top == 0 {
+                    self.scrollback.push(line);
+                    // A view scrolled back stays on the lines it shows
+                    if self.view_offset > 0 {
+                        self.view_offset += 1;
+                    }
+                }
 
                 // Trim scrollback if needed
                 if self.scrollback.len() > self.max_scrollback {
                     self.scrollback.remove(0);
+                    self.view_offset = self.view_offset.min(self.scrollback.len());
                 }
 
                 // Insert new line at bottom
@@ -274,6 +751,16 @@ impl TerminalBuffer {
         }
     }
 
+    /// Move the cursor up a line, scrolling the region down when it is at
+    /// the region's top
+    pub fn reverse_index(&mut self) {
+        if self.cursor.row == self.scroll_region.0 {
+            self.scroll_down(1);
+        } else {
+            self.move_cursor(0, -1);
+        }
+    }
+
     /// Scroll down by n lines within the scroll region
     pub fn scroll_down(&mut self, n: u16) {
         let top = self.scroll_region.0 as usize;
@@ -369,9 +856,13 @@ impl TerminalBuffer {
         &mut self.current_style
     }
 
-    /// Reset current style to default
+    /// Reset current style to default. An open hyperlink is not a style and
+    /// stays open.
     pub fn reset_style(&mut self) {
-        self.current_style = StyledChar::default();
+        self.current_style = StyledChar {
+            link: self.current_style.link,
+            ..Default::default()
+        };
     }
 
     /// Resize the buffer
@@ -435,6 +926,53 @@ impl TerminalBuffer {
     }
 }
 
+/// Characters that end a web address in terminal output
+const URL_TERMINATORS: &[char] = &['<', '>', '"', '\'', '`', '{', '}', '|', '\\', '^'];
+
+/// Columns of the `http://` and `https://` addresses in a line of cells.
+/// Punctuation ending a sentence and closing brackets without an opening one
+/// in the address are left out.
+pub fn find_urls(chars: &[StyledChar]) -> Vec<Range<usize>> {
+    let text: Vec<char> = chars.iter().map(|ch| ch.c).collect();
+    let in_url = |c: char| !c.is_whitespace() && !c.is_control() && !URL_TERMINATORS.contains(&c);
+    let starts_at = |col: usize, scheme: &str| {
+        scheme.chars().enumerate().all(|(i, c)| text.get(col + i).is_some_and(|t| t.eq_ignore_ascii_case(&c)))
+    };
+
+    let mut urls = Vec::new();
+    let mut col = 0;
+    while col < text.len() {
+        let scheme = ["https://", "http://"].into_iter().find(|scheme| starts_at(col, scheme));
+        let Some(scheme) = scheme else {
+            col += 1;
+            continue;
+        };
+        let mut end = col + scheme.len();
+        while end < text.len() && in_url(text[end]) {
+            end += 1;
+        }
+        while end > col + scheme.len() {
+            let last = text[end - 1];
+            let opened = |open: char| text[col..end].iter().filter(|&&c| c == open).count();
+            let unbalanced = match last {
+                ')' => opened('(') < opened(')'),
+                ']' => opened('[') < opened(']'),
+                _ => false,
+            };
+            if ".,;:!?*".contains(last) || unbalanced {
+                end -= 1;
+            } else {
+                break;
+            }
+        }
+        if end > col + scheme.len() {
+            urls.push(col..end);
+        }
+        col = end.max(col + 1);
+    }
+    urls
+}
+
 /// Standard ANSI colors
 pub const ANSI_COLORS: [Color32; 16] = [
     Color32::from_rgb(0, 0, 0),       // Black
@@ -474,3 +1012,145 @@ pub fn color_256_to_rgb(index: u8) -> Color32 {
         Color32::from_rgb(gray, gray, gray)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_line(buffer: &mut TerminalBuffer, text: &str) {
+        for c in text.chars() {
+            buffer.put_char(c);
+        }
+        buffer.carriage_return();
+        buffer.newline();
+    }
+
+    #[test]
+    fn test_scrollback_view() {
+        let mut buffer = TerminalBuffer::new(10, 3);
+        for n in 0..5 {
+            write_line(&mut buffer, &n.to_string());
+        }
+        let shown = |buffer: &TerminalBuffer| -> Vec<String> {
+            buffer.visible_lines().map(|line| line.to_string_trimmed()).collect()
+        };
+        assert_eq!(shown(&buffer), vec!["3", "4", ""]);
+
+        buffer.scroll_view(2);
+        assert_eq!(shown(&buffer), vec!["1", "2", "3"]);
+        // New output doesn't move a view that is scrolled back
+        write_line(&mut buffer, "5");
+        assert_eq!(shown(&buffer), vec!["1", "2", "3"]);
+
+        buffer.scroll_view(100);
+        assert_eq!(buffer.view_offset(), buffer.scrollback().len());
+        assert_eq!(shown(&buffer), vec!["0", "1", "2"]);
+        buffer.scroll_view(-100);
+        assert_eq!(shown(&buffer), vec!["4", "5", ""]);
+    }
+
+    #[test]
+    fn test_last_output() {
+        let mut buffer = TerminalBuffer::new(20, 5);
+        write_line(&mut buffer, "$ ls");
+        write_line(&mut buffer, "old.md");
+        write_line(&mut buffer, "$ ls notes");
+        write_line(&mut buffer, "a.md   b.md");
+        write_line(&mut buffer, "c.md");
+        buffer.put_char('$');
+        buffer.put_char(' ');
+        assert_eq!(buffer.last_output().as_deref(), Some("a.md   b.md\nc.md"));
+
+        write_line(&mut buffer, "true");
+        buffer.put_char('$');
+        buffer.put_char(' ');
+        assert_eq!(buffer.last_output(), None);
+    }
+
+    #[test]
+    fn test_directory_url() {
+        let mut buffer = TerminalBuffer::new(10, 2);
+        buffer.set_directory_url("https://example.com/");
+        assert_eq!(buffer.directory(), None);
+        buffer.set_directory_url("file://host/home/me/My%20Notes");
+        let expected = if cfg!(windows) { "home/me/My Notes" } else { "/home/me/My Notes" };
+        assert_eq!(buffer.directory(), Some(Path::new(expected)));
+    }
+
+    #[test]
+    fn test_events() {
+        let mut buffer = TerminalBuffer::new(10, 2);
+        let mut parser = crate::terminal::AnsiParser::new();
+        parser.process(b"\x1b]133;C\x07make\x07\x1b]133;D;2\x1b\\\x1b]133;D\x07", &mut buffer);
+        parser.process(b"\x1b]52;c;aGVsbG8=\x07\x1b]52;c;?\x07\x1b]52;c;!!\x07", &mut buffer);
+        assert_eq!(
+            buffer.take_events(),
+            [
+                TerminalEvent::CommandStarted,
+                TerminalEvent::Bell,
+                TerminalEvent::CommandFinished(Some(2)),
+                TerminalEvent::CommandFinished(None),
+                TerminalEvent::SetClipboard("hello".to_string()),
+                TerminalEvent::ClipboardRequest,
+            ]
+        );
+        assert!(buffer.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_line_revisions() {
+        let mut buffer = TerminalBuffer::new(10, 2);
+        write_line(&mut buffer, "a");
+        let first = buffer.lines()[0].revision();
+        buffer.put_char('b');
+        assert_ne!(buffer.lines()[1].revision(), first);
+
+        // Scrolling moves lines without changing them
+        let second = buffer.lines()[1].revision();
+        buffer.newline();
+        assert_eq!(buffer.scrollback()[0].revision(), first);
+        assert_eq!(buffer.lines()[0].revision(), second);
+
+        buffer.set_cursor(0, 0);
+        buffer.clear_to_eol();
+        assert_ne!(buffer.lines()[0].revision(), second);
+    }
+
+    #[test]
+    fn test_selected_text() {
+        let mut buffer = TerminalBuffer::new(4, 4);
+        write_line(&mut buffer, "ab");
+        // Wraps onto a second line
+        write_line(&mut buffer, "cdefg");
+        let line = buffer.first_visible_line();
+        let select = |anchor: (usize, usize), head: (usize, usize)| Selection {
+            anchor: CellPos { line: line + anchor.0, col: anchor.1 },
+            head: CellPos { line: line + head.0, col: head.1 },
+        };
+
+        assert_eq!(buffer.selected_text(&select((0, 0), (2, 3))), "ab\ncdefg");
+        // Dragging backwards selects the same cells
+        assert_eq!(buffer.selected_text(&select((2, 0), (0, 1))), "b\ncdefg");
+        assert_eq!(buffer.selected_text(&select((1, 2), (1, 2))), "e");
+    }
+
+    #[test]
+    fn test_links() {
+        let mut buffer = TerminalBuffer::new(60, 4);
+        write_line(&mut buffer, "see (https://example.com/a_(b)), or http://x.io.");
+        buffer.set_hyperlink("file:///tmp/log");
+        write_line(&mut buffer, "log");
+        buffer.set_hyperlink("");
+        write_line(&mut buffer, "https://");
+
+        let line = buffer.first_visible_line();
+        let link = |row: usize, col: usize| buffer.link_at(CellPos { line: line + row, col });
+        assert_eq!(link(0, 10).as_deref(), Some("https://example.com/a_(b)"));
+        assert_eq!(link(0, 40).as_deref(), Some("http://x.io"));
+        assert_eq!(link(0, 47), None);
+        assert_eq!(link(0, 2), None);
+        assert_eq!(link(1, 1).as_deref(), Some("file:///tmp/log"));
+        assert_eq!(link(1, 3), None);
+        assert_eq!(link(2, 3), None);
+    }
+}
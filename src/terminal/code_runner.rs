@@ -0,0 +1,46 @@
+//! Running fenced code blocks from notes in the terminal
+//!
+//! Shell code is typed into the shown terminal when it runs a shell of the
+//! block's language, and into a new tab with one otherwise. Code of other
+//! languages runs in a tab of its own, passed to the interpreter on its
+//! command line.
+
+/// How code of a fenced block's language is run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    /// Typed into a shell with one of these names; the first is started when
+    /// none runs. Empty for any shell, as for blocks without a language.
+    Shell(&'static [&'static str]),
+    /// Passed to `program` after `flag`
+    Interpreter { program: &'static str, flag: &'static str },
+}
+
+/// How to run code of a block in `lang`, if it can be run
+pub fn runner(lang: Option<&str>) -> Option<Runner> {
+    let Some(lang) = lang.map(str::trim).filter(|lang| !lang.is_empty()) else {
+        return Some(Runner::Shell(&[]));
+    };
+    let python = if cfg!(windows) { "python" } else { "python3" };
+    match lang.to_ascii_lowercase().as_str() {
+        "sh" | "bash" | "zsh" | "shell" | "console" => Some(Runner::Shell(&["bash", "sh", "zsh", "dash", "ksh"])),
+        "nu" | "nushell" => Some(Runner::Shell(&["nu"])),
+        "pwsh" | "powershell" | "ps1" => Some(Runner::Shell(&["pwsh", "powershell"])),
+        "cmd" | "bat" => Some(Runner::Shell(&["cmd"])),
+        "python" | "python3" | "py" => Some(Runner::Interpreter { program: python, flag: "-c" }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner() {
+        assert_eq!(runner(None), Some(Runner::Shell(&[])));
+        assert_eq!(runner(Some(" ")), Some(Runner::Shell(&[])));
+        assert!(matches!(runner(Some("Bash")), Some(Runner::Shell(shells)) if shells.contains(&"bash")));
+        assert!(matches!(runner(Some("py")), Some(Runner::Interpreter { flag: "-c", .. })));
+        assert_eq!(runner(Some("rust")), None);
+    }
+}
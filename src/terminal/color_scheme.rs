@@ -0,0 +1,267 @@
+//! Colors the terminal is drawn in
+//!
+//! The buffer keeps the colors programs ask for as the default scheme's
+//! colors; a scheme maps them when the terminal is drawn, so switching
+//! schemes recolors output already on screen as well.
+//!
+//! Besides the built-in schemes, every `.json` file in the terminal themes
+//! folder (see [`TerminalConfig::theme_dir`]) adds one:
+//!
+//! ```json
+//! {
+//!     "name": "Nord",
+//!     "foreground": "#d8dee9",
+//!     "background": "#2e3440",
+//!     "cursor": "#d8dee9",
+//!     "ansi": ["#3b4252", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#88c0d0", "#e5e9f0",
+//!              "#4c566a", "#bf616a", "#a3be8c", "#ebcb8b", "#81a1c1", "#b48ead", "#8fbcbb", "#eceff4"]
+//! }
+//! ```
+//!
+//! The name defaults to the file's, the cursor to the foreground and the
+//! ANSI colors to the default scheme's.
+//!
+//! [`TerminalConfig::theme_dir`]: robsidian_core::config::TerminalConfig::theme_dir
+
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use egui::Color32;
+use serde::Deserialize;
+
+use robsidian_core::vault_settings::parse_color;
+
+use super::buffer::{StyledChar, ANSI_COLORS};
+
+/// Default foreground, background, cursor and 16 ANSI colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScheme {
+    pub name: Cow<'static, str>,
+    pub foreground: Color32,
+    pub background: Color32,
+    pub cursor: Color32,
+    pub ansi: [Color32; 16],
+}
+
+/// `0xrrggbb` as a color
+const fn rgb(hex: u32) -> Color32 {
+    Color32::from_rgb((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+/// Schemes to pick from; the first is the default
+pub const COLOR_SCHEMES: &[ColorScheme] = &[
+    ColorScheme {
+        name: Cow::Borrowed("Default"),
+        foreground: Color32::LIGHT_GRAY,
+        background: Color32::from_rgb(30, 30, 30),
+        cursor: Color32::WHITE,
+        ansi: ANSI_COLORS,
+    },
+    ColorScheme {
+        name: Cow::Borrowed("Light"),
+        foreground: Color32::from_rgb(51, 51, 51),
+        background: Color32::from_rgb(248, 248, 248),
+        cursor: Color32::from_rgb(51, 51, 51),
+        ansi: [
+            Color32::from_rgb(0, 0, 0),
+            Color32::from_rgb(205, 49, 49),
+            Color32::from_rgb(0, 138, 0),
+            Color32::from_rgb(148, 152, 0),
+            Color32::from_rgb(4, 81, 165),
+            Color32::from_rgb(188, 5, 188),
+            Color32::from_rgb(5, 152, 188),
+            Color32::from_rgb(85, 85, 85),
+            Color32::from_rgb(102, 102, 102),
+            Color32::from_rgb(205, 49, 49),
+            Color32::from_rgb(20, 160, 20),
+            Color32::from_rgb(181, 186, 0),
+            Color32::from_rgb(4, 81, 165),
+            Color32::from_rgb(188, 5, 188),
+            Color32::from_rgb(5, 152, 188),
+            Color32::from_rgb(165, 165, 165),
+        ],
+    },
+    ColorScheme {
+        name: Cow::Borrowed("Solarized Dark"),
+        foreground: rgb(0x839496),
+        background: rgb(0x002b36),
+        cursor: rgb(0x93a1a1),
+        ansi: [
+            rgb(0x073642), rgb(0xdc322f), rgb(0x859900), rgb(0xb58900),
+            rgb(0x268bd2), rgb(0xd33682), rgb(0x2aa198), rgb(0xeee8d5),
+            rgb(0x002b36), rgb(0xcb4b16), rgb(0x586e75), rgb(0x657b83),
+            rgb(0x839496), rgb(0x6c71c4), rgb(0x93a1a1), rgb(0xfdf6e3),
+        ],
+    },
+    ColorScheme {
+        name: Cow::Borrowed("Solarized Light"),
+        foreground: rgb(0x657b83),
+        background: rgb(0xfdf6e3),
+        cursor: rgb(0x586e75),
+        ansi: [
+            rgb(0x073642), rgb(0xdc322f), rgb(0x859900), rgb(0xb58900),
+            rgb(0x268bd2), rgb(0xd33682), rgb(0x2aa198), rgb(0xeee8d5),
+            rgb(0x002b36), rgb(0xcb4b16), rgb(0x586e75), rgb(0x657b83),
+            rgb(0x839496), rgb(0x6c71c4), rgb(0x93a1a1), rgb(0xfdf6e3),
+        ],
+    },
+    ColorScheme {
+        name: Cow::Borrowed("Dracula"),
+        foreground: rgb(0xf8f8f2),
+        background: rgb(0x282a36),
+        cursor: rgb(0xf8f8f2),
+        ansi: [
+            rgb(0x21222c), rgb(0xff5555), rgb(0x50fa7b), rgb(0xf1fa8c),
+            rgb(0xbd93f9), rgb(0xff79c6), rgb(0x8be9fd), rgb(0xf8f8f2),
+            rgb(0x6272a4), rgb(0xff6e6e), rgb(0x69ff94), rgb(0xffffa5),
+            rgb(0xd6acff), rgb(0xff92df), rgb(0xa4ffff), rgb(0xffffff),
+        ],
+    },
+    ColorScheme {
+        name: Cow::Borrowed("Gruvbox Dark"),
+        foreground: rgb(0xebdbb2),
+        background: rgb(0x282828),
+        cursor: rgb(0xebdbb2),
+        ansi: [
+            rgb(0x282828), rgb(0xcc241d), rgb(0x98971a), rgb(0xd79921),
+            rgb(0x458588), rgb(0xb16286), rgb(0x689d6a), rgb(0xa89984),
+            rgb(0x928374), rgb(0xfb4934), rgb(0xb8bb26), rgb(0xfabd2f),
+            rgb(0x83a598), rgb(0xd3869b), rgb(0x8ec07c), rgb(0xebdbb2),
+        ],
+    },
+];
+
+/// A theme file's contents
+#[derive(Deserialize)]
+struct ThemeFile {
+    name: Option<String>,
+    foreground: String,
+    background: String,
+    cursor: Option<String>,
+    #[serde(default)]
+    ansi: Vec<String>,
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        COLOR_SCHEMES[0].clone()
+    }
+}
+
+impl ColorScheme {
+    /// Scheme called `name` among `schemes`, or the default one if there is
+    /// none
+    pub fn named<'a>(schemes: &'a [ColorScheme], name: &str) -> &'a ColorScheme {
+        schemes
+            .iter()
+            .find(|scheme| scheme.name.eq_ignore_ascii_case(name))
+            .unwrap_or(&COLOR_SCHEMES[0])
+    }
+
+    /// Scheme of a theme file
+    pub fn from_theme_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let theme: ThemeFile =
+            serde_json::from_str(&text).with_context(|| format!("Invalid terminal theme {}", path.display()))?;
+        let color = |hex: &str| {
+            parse_color(hex)
+                .map(|[r, g, b]| Color32::from_rgb(r, g, b))
+                .with_context(|| format!("Invalid color \"{}\" in {}", hex, path.display()))
+        };
+        let foreground = color(&theme.foreground)?;
+        let mut ansi = ANSI_COLORS;
+        match theme.ansi.len() {
+            0 => {}
+            16 => {
+                for (slot, hex) in ansi.iter_mut().zip(&theme.ansi) {
+                    *slot = color(hex)?;
+                }
+            }
+            count => bail!("{} has {} ANSI colors rather than 16", path.display(), count),
+        }
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Self {
+            name: Cow::Owned(theme.name.unwrap_or(stem)),
+            foreground,
+            background: color(&theme.background)?,
+            cursor: theme.cursor.as_deref().map(color).transpose()?.unwrap_or(foreground),
+            ansi,
+        })
+    }
+
+    /// The built-in schemes followed by those of the theme files in `dir`,
+    /// with the errors of the files that couldn't be read
+    pub fn load_all(dir: &Path) -> (Vec<ColorScheme>, Vec<String>) {
+        let mut schemes = COLOR_SCHEMES.to_vec();
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (schemes, errors);
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            match Self::from_theme_file(&path) {
+                // A theme file of a built-in scheme's name replaces it
+                Ok(scheme) => match schemes.iter_mut().find(|known| known.name == scheme.name) {
+                    Some(known) => *known = scheme,
+                    None => schemes.push(scheme),
+                },
+                Err(e) => errors.push(format!("{:#}", e)),
+            }
+        }
+        (schemes, errors)
+    }
+
+    /// This scheme's version of a color stored in the buffer. Transparent
+    /// stands for the default background.
+    pub fn color(&self, color: Color32) -> Color32 {
+        let default = &COLOR_SCHEMES[0];
+        if color == Color32::TRANSPARENT {
+            self.background
+        } else if color == default.foreground {
+            self.foreground
+        } else if let Some(index) = default.ansi.iter().position(|ansi| *ansi == color) {
+            self.ansi[index]
+        } else {
+            color
+        }
+    }
+
+    /// Text color of a cell
+    pub fn fg(&self, ch: &StyledChar) -> Color32 {
+        self.color(ch.effective_fg())
+    }
+
+    /// Background of a cell, or `None` for the default background
+    pub fn bg(&self, ch: &StyledChar) -> Option<Color32> {
+        let bg = ch.effective_bg();
+        (bg != Color32::TRANSPARENT).then(|| self.color(bg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_files() {
+        let dir = std::env::temp_dir().join(format!("robsidian_themes_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Paper.json"), r##"{"foreground": "#101010", "background": "#fafafa"}"##).unwrap();
+        std::fs::write(dir.join("broken.json"), r##"{"foreground": "#10", "background": "#fafafa"}"##).unwrap();
+
+        let (schemes, errors) = ColorScheme::load_all(&dir);
+        assert_eq!(schemes.len(), COLOR_SCHEMES.len() + 1);
+        assert_eq!(errors.len(), 1);
+        let paper = ColorScheme::named(&schemes, "paper");
+        assert_eq!(paper.name, "Paper");
+        assert_eq!(paper.cursor, Color32::from_rgb(16, 16, 16));
+        assert_eq!(paper.color(ANSI_COLORS[1]), ANSI_COLORS[1]);
+        assert_eq!(ColorScheme::named(&schemes, "missing").name, "Default");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
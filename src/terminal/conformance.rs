@@ -0,0 +1,132 @@
+//! Terminal conformance tests
+//!
+//! Each `.ansi` file under `tests/fixtures/terminal` is a byte stream: `vim`
+//! is a session recorded with `script(1)`, the others are written by hand
+//! after what vttest, shell prompts and full-screen programs send. A stream
+//! is fed through [`AnsiParser`] into a [`TerminalBuffer`], and the resulting
+//! grid is compared with the `.grid` file next to it. The first line of the
+//! grid file gives the terminal size of the stream, e.g. `size 80x24`,
+//! and a `modes` line lists the DEC private modes left switched on.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::golden::{assert_golden, fixtures, fixtures_dir};
+
+use super::buffer::{StyledChar, TerminalBuffer, TerminalLine};
+use super::AnsiParser;
+
+/// Size line of a grid file
+fn read_size(grid: &Path) -> (u16, u16) {
+    let text = std::fs::read_to_string(grid).unwrap_or_else(|_| panic!("{} is missing", grid.display()));
+    let size = text.lines().next().and_then(|line| line.strip_prefix("size ")).unwrap_or_default();
+    let (cols, rows) = size.split_once('x').unwrap_or_else(|| panic!("{} has no size line", grid.display()));
+    (cols.parse().unwrap(), rows.parse().unwrap())
+}
+
+/// Feed `stream` to a fresh terminal, `chunk` bytes at a time as a PTY would
+fn replay(stream: &[u8], (cols, rows): (u16, u16), chunk: usize) -> TerminalBuffer {
+    let mut buffer = TerminalBuffer::new(cols, rows);
+    let mut parser = AnsiParser::new();
+    for bytes in stream.chunks(chunk) {
+        parser.process(bytes, &mut buffer);
+    }
+    buffer
+}
+
+/// Attributes of a character that differ from the default style
+fn describe_style(ch: &StyledChar) -> String {
+    let default = StyledChar::default();
+    let mut parts = Vec::new();
+    if ch.fg != default.fg {
+        parts.push(format!("fg={:02x}{:02x}{:02x}", ch.fg.r(), ch.fg.g(), ch.fg.b()));
+    }
+    if ch.bg != default.bg {
+        parts.push(format!("bg={:02x}{:02x}{:02x}", ch.bg.r(), ch.bg.g(), ch.bg.b()));
+    }
+    let flags = [
+        (ch.bold, "bold"),
+        (ch.italic, "italic"),
+        (ch.underline, "underline"),
+        (ch.strikethrough, "strikethrough"),
+        (ch.inverse, "inverse"),
+    ];
+    parts.extend(flags.iter().filter(|(set, _)| *set).map(|(_, name)| name.to_string()));
+    if let Some(link) = ch.link {
+        parts.push(format!("link={}", link));
+    }
+    parts.join(" ")
+}
+
+/// Text of a line between bars, so trailing blanks show, then its styled runs.
+/// Wide characters leave out their spacer cell, which keeps the columns
+/// lined up in a monospace view.
+fn dump_line(out: &mut String, label: &str, line: &TerminalLine) {
+    let mut text = String::new();
+    for ch in &line.chars {
+        ch.push_text(&mut text);
+    }
+    let wrapped = if line.wrapped { " wrapped" } else { "" };
+    writeln!(out, "{} |{}|{}", label, text, wrapped).unwrap();
+
+    let mut col = 0;
+    while col < line.chars.len() {
+        let style = describe_style(&line.chars[col]);
+        let end = (col..line.chars.len())
+            .find(|&end| describe_style(&line.chars[end]) != style)
+            .unwrap_or(line.chars.len());
+        if !style.is_empty() {
+            writeln!(out, "    {}..{} {}", col, end, style).unwrap();
+        }
+        col = end;
+    }
+}
+
+fn dump_grid(buffer: &TerminalBuffer) -> String {
+    let (cols, rows) = buffer.size();
+    let cursor = buffer.cursor();
+    let mut out = String::new();
+    writeln!(out, "size {}x{}", cols, rows).unwrap();
+    writeln!(out, "cursor {},{}", cursor.row, cursor.col).unwrap();
+    let modes = [
+        (!buffer.cursor_visible(), "cursor-hidden"),
+        (buffer.application_cursor(), "application-cursor"),
+        (buffer.bracketed_paste(), "bracketed-paste"),
+    ];
+    let modes: Vec<&str> = modes.iter().filter(|(set, _)| *set).map(|(_, name)| *name).collect();
+    if !modes.is_empty() {
+        writeln!(out, "modes {}", modes.join(" ")).unwrap();
+    }
+    if let Some(title) = buffer.title() {
+        writeln!(out, "title {}", title).unwrap();
+    }
+    for (idx, link) in (0..).map_while(|idx| buffer.hyperlink(idx).map(|link| (idx, link))) {
+        writeln!(out, "link {} {}", idx, link).unwrap();
+    }
+    for line in buffer.scrollback() {
+        dump_line(&mut out, "scrollback", line);
+    }
+    for (row, line) in buffer.lines().iter().enumerate() {
+        dump_line(&mut out, &format!("{:>10}", row), line);
+    }
+    out
+}
+
+#[test]
+fn test_recorded_streams() {
+    for path in fixtures(&fixtures_dir("terminal"), "ansi") {
+        let stream = std::fs::read(&path).unwrap();
+        let grid = path.with_extension("grid");
+        let size = read_size(&grid);
+
+        let buffer = replay(&stream, size, stream.len().max(1));
+        let expected = dump_grid(&buffer);
+        assert_golden(&grid, &expected);
+
+        // Reads from a PTY split escape sequences anywhere
+        for chunk in [1, 3, 7] {
+            let split = dump_grid(&replay(&stream, size, chunk));
+            assert!(split == expected, "{} differs when fed {} bytes at a time:\n{}", path.display(), chunk, split);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Control pipe, through which scripts in the terminal ask the app to open
+//! or create notes
+//!
+//! On Unix the app makes a named pipe (FIFO) and tells shells its path in
+//! `ROBSIDIAN_CONTROL`, next to `ROBSIDIAN_VAULT` and
+//! `ROBSIDIAN_ACTIVE_NOTE`. Each line written to the pipe is a command:
+//!
+//! | Line | Effect |
+//! |---|---|
+//! | `open <path>` | Opens the note |
+//! | `new <path>` | Creates the note unless it exists, then opens it |
+//!
+//! Paths are relative to the vault, or absolute inside it; `.md` may be
+//! left out. Paths leading out of the vault are refused.
+//!
+//! ```sh
+//! echo "new Daily/$(date +%F)" > "$ROBSIDIAN_CONTROL"
+//! ```
+//!
+//! The pipe lives in a folder only the user can open, in
+//! `$XDG_RUNTIME_DIR` where there is one. Windows has no such pipe yet, so
+//! shells there only get the vault and the active note.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+
+use super::pty::Waker;
+
+/// Whether this system has control pipes
+pub const AVAILABLE: bool = cfg!(unix);
+
+/// A request read from the control pipe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    Open(PathBuf),
+    /// Create the note unless it exists, then open it
+    New(PathBuf),
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Result<Self> {
+        let (verb, path) = line.trim().split_once(char::is_whitespace).unwrap_or((line.trim(), ""));
+        let path = path.trim();
+        if path.is_empty() {
+            bail!("Missing note path in terminal command \"{}\"", line.trim());
+        }
+        match verb {
+            "open" => Ok(ControlCommand::Open(path.into())),
+            "new" => Ok(ControlCommand::New(path.into())),
+            _ => bail!("Unknown terminal command \"{}\"; use open or new", verb),
+        }
+    }
+
+    /// Note the command is about: relative paths are taken from `vault`, and
+    /// `.md` is added to names without an extension. Paths outside of the
+    /// vault, also through a symbolic link, are refused.
+    pub fn note(&self, vault: Option<&Path>) -> Result<PathBuf> {
+        let (ControlCommand::Open(path) | ControlCommand::New(path)) = self;
+        let Some(vault) = vault else {
+            bail!("Open a vault before sending terminal commands");
+        };
+        let mut note = vault.join(path);
+        if path.components().any(|part| part == Component::ParentDir) || !note.starts_with(vault) {
+            bail!("{} is outside of the vault", path.display());
+        }
+        if note.extension().is_none() {
+            note.set_extension("md");
+        }
+        // What exists of the path must be in the vault once links are followed
+        let vault = vault.canonicalize().with_context(|| format!("Failed to find {}", vault.display()))?;
+        let existing = note.ancestors().find_map(|part| part.canonicalize().ok());
+        if !existing.is_some_and(|existing| existing.starts_with(&vault)) {
+            bail!("{} is outside of the vault", path.display());
+        }
+        Ok(note)
+    }
+}
+
+/// Named pipe scripts write commands to, removed with its folder when
+/// dropped
+pub struct ControlPipe {
+    path: PathBuf,
+    lines: Receiver<String>,
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl ControlPipe {
+    /// Make a pipe in a new folder only the user can open, and read it in
+    /// the background
+    #[cfg(unix)]
+    pub fn create() -> Result<Self> {
+        use std::ffi::{CString, OsString};
+        use std::io::BufRead;
+        use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+        let base = std::env::var_os("XDG_RUNTIME_DIR").map_or_else(std::env::temp_dir, PathBuf::from);
+        let template = CString::new(base.join("robsidian-XXXXXX").into_os_string().into_vec())?;
+        let template = template.into_raw();
+        // SAFETY: `template` is a NUL-terminated, writable path ending in
+        // XXXXXX, which mkdtemp replaces; it's taken back right after
+        let created = unsafe { libc::mkdtemp(template) };
+        // SAFETY: `template` came from `CString::into_raw` and keeps its length
+        let template = unsafe { CString::from_raw(template) };
+        if created.is_null() {
+            bail!("Failed to create a folder in {}: {}", base.display(), std::io::Error::last_os_error());
+        }
+        // mkdtemp made the folder readable by the user alone
+        let dir = PathBuf::from(OsString::from_vec(template.into_bytes()));
+        let path = dir.join("control");
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        // SAFETY: `c_path` is a valid, NUL-terminated path
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            let error = std::io::Error::last_os_error();
+            let _ = std::fs::remove_dir(&dir);
+            bail!("Failed to create {}: {}", path.display(), error);
+        }
+        // Opened for writing too, so reads wait for the next writer rather
+        // than ending when one closes the pipe
+        let file = match std::fs::OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = std::fs::remove_file(&path);
+                let _ = std::fs::remove_dir(&dir);
+                return Err(e).with_context(|| format!("Failed to open {}", path.display()));
+            }
+        };
+
+        let (tx, lines) = std::sync::mpsc::channel();
+        let waker: Arc<Mutex<Option<Waker>>> = Arc::default();
+        let wake = Arc::clone(&waker);
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(file).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if tx.send(line).is_err() {
+                    break;
+                }
+                if let Some(wake) = wake.lock().unwrap().as_ref() {
+                    wake();
+                }
+            }
+        });
+        Ok(Self { path, lines, waker })
+    }
+
+    #[cfg(not(unix))]
+    pub fn create() -> Result<Self> {
+        bail!("Terminal control pipes aren't available on this system")
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Call `waker` whenever a command arrives
+    pub fn set_waker(&self, waker: Waker) {
+        *self.waker.lock().unwrap() = Some(waker);
+    }
+
+    /// Commands written since the last call
+    pub fn take_commands(&self) -> Vec<Result<ControlCommand>> {
+        self.lines.try_iter().map(|line| ControlCommand::parse(&line)).collect()
+    }
+}
+
+impl Drop for ControlPipe {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_control_commands() {
+        let vault = std::env::temp_dir().join(format!("robsidian_control_{}", std::process::id()));
+        std::fs::create_dir_all(vault.join("Daily")).unwrap();
+        let command = ControlCommand::parse("new Daily/2024-05-01\n").unwrap();
+        assert_eq!(command, ControlCommand::New("Daily/2024-05-01".into()));
+        assert_eq!(command.note(Some(&vault)).unwrap(), vault.join("Daily/2024-05-01.md"));
+        let inside = format!("open {}", vault.join("Plan.md").display());
+        assert_eq!(ControlCommand::parse(&inside).unwrap().note(Some(&vault)).unwrap(), vault.join("Plan.md"));
+        for outside in ["open /etc/passwd", "new ../escape", "new Daily/../../escape"] {
+            assert!(ControlCommand::parse(outside).unwrap().note(Some(&vault)).is_err(), "{}", outside);
+        }
+        assert!(command.note(None).is_err());
+        assert!(ControlCommand::parse("open").is_err());
+        assert!(ControlCommand::parse("delete Plan").is_err());
+        let _ = std::fs::remove_dir_all(vault);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_control_pipe() {
+        let pipe = ControlPipe::create().unwrap();
+        std::fs::write(pipe.path(), "open Inbox\n\nnew Ideas\n").unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut commands = Vec::new();
+        while commands.len() < 2 && std::time::Instant::now() < deadline {
+            commands.extend(pipe.take_commands().into_iter().map(Result::unwrap));
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(commands, [ControlCommand::Open("Inbox".into()), ControlCommand::New("Ideas".into())]);
+        let path = pipe.path().to_path_buf();
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(path.parent().unwrap()).unwrap().permissions().mode() & 0o777, 0o700);
+        drop(pipe);
+        assert!(!path.exists() && !path.parent().unwrap().exists());
+    }
+}
@@ -2,23 +2,42 @@
 
 pub mod ansi;
 pub mod buffer;
+pub mod code_runner;
+pub mod color_scheme;
+#[cfg(test)]
+mod conformance;
+pub mod control;
 pub mod pty;
+pub mod search;
 pub mod shell;
+pub mod shell_diagnostics;
 
 pub use ansi::AnsiParser;
-pub use buffer::{CursorPos, StyledChar, TerminalBuffer, TerminalLine};
-pub use pty::{PtyTerminal, TerminalKey};
+pub use color_scheme::ColorScheme;
+pub use control::{ControlCommand, ControlPipe};
+pub use buffer::{CellPos, CursorPos, Selection, StyledChar, TerminalBuffer, TerminalEvent, TerminalLine};
+pub use pty::{PtyTerminal, ShellOptions, TerminalKey, Waker};
+pub use search::TerminalSearch;
+pub use shell_diagnostics::ShellDiagnostics;
 
 use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use std::io::{BufRead, BufReader};
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use robsidian_core::command_history::CommandHistory;
+use robsidian_core::config::{BellStyle, ClipboardAccess, SshHost, TerminalConfig};
+
+use code_runner::{runner, Runner};
+
 /// Terminal tab state
 #[derive(Debug, Clone)]
 pub struct TerminalTab {
     /// Output lines
     pub output: Vec<String>,
-    /// Command history
-    pub history: Vec<String>,
     /// Current working directory
     pub cwd: std::path::PathBuf,
 }
@@ -27,7 +46,6 @@ impl Default for TerminalTab {
     fn default() -> Self {
         Self {
             output: Vec::new(),
-            history: Vec::new(),
             cwd: std::env::current_dir().unwrap_or_default(),
         }
     }
@@ -42,8 +60,20 @@ pub struct TerminalState {
     pub active_tab: usize,
     /// Current input
     pub input: String,
+    /// Commands run in any of the tabs
+    pub history: CommandHistory,
     /// History index for navigation
     history_index: Option<usize>,
+    /// Ctrl+R search through the history, while open
+    pub history_search: Option<HistorySearch>,
+}
+
+/// Query typed to find an earlier command, as with Ctrl+R in a shell
+#[derive(Debug, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    /// History entry found for the query
+    found: Option<usize>,
 }
 
 impl Default for TerminalState {
@@ -59,7 +89,9 @@ impl TerminalState {
             tabs: vec![TerminalTab::default()],
             active_tab: 0,
             input: String::new(),
+            history: CommandHistory::default(),
             history_index: None,
+            history_search: None,
         }
     }
 
@@ -98,9 +130,12 @@ impl TerminalState {
         }
 
         // Add to history
+        let saved = self.history.push(&command);
         if let Some(tab) = self.current_tab_mut() {
-            tab.history.push(command.clone());
             tab.output.push(format!("$ {}", command));
+            if let Err(e) = saved {
+                tab.output.push(format!("{:#}", e));
+            }
         }
 
         // Clear input
@@ -224,42 +259,34 @@ impl TerminalState {
     }
 
     /// Navigate history up
-    #[allow(dead_code)]
     pub fn history_up(&mut self) {
-        let Some(tab) = self.tabs.get(self.active_tab) else {
-            return;
-        };
-
-        if tab.history.is_empty() {
+        let history = self.history.entries();
+        if history.is_empty() {
             return;
         }
 
-        let history_len = tab.history.len();
+        let history_len = history.len();
         let new_index = match self.history_index {
             Some(i) if i > 0 => i - 1,
             Some(i) => i,
             None => history_len - 1,
         };
 
-        let new_input = tab.history[new_index].clone();
+        let new_input = history[new_index].clone();
         self.history_index = Some(new_index);
         self.input = new_input;
     }
 
     /// Navigate history down
-    #[allow(dead_code)]
     pub fn history_down(&mut self) {
-        let Some(tab) = self.tabs.get(self.active_tab) else {
-            return;
-        };
-
+        let history = self.history.entries();
         let Some(i) = self.history_index else {
             return;
         };
 
-        let history_len = tab.history.len();
+        let history_len = history.len();
         if i < history_len - 1 {
-            let new_input = tab.history[i + 1].clone();
+            let new_input = history[i + 1].clone();
             self.history_index = Some(i + 1);
             self.input = new_input;
         } else {
@@ -267,6 +294,76 @@ impl TerminalState {
             self.input.clear();
         }
     }
+
+    /// Open the history search, or find the next older match when it is open
+    pub fn reverse_search(&mut self) {
+        if self.history_search.is_none() {
+            self.history_search = Some(HistorySearch::default());
+            self.update_search();
+        } else {
+            self.step_search(1);
+        }
+    }
+
+    /// Find the next newer match of the history search
+    pub fn forward_search(&mut self) {
+        self.step_search(-1);
+    }
+
+    /// Move the history search `step` matches older, staying on the first
+    /// or last one
+    fn step_search(&mut self, step: isize) {
+        let matches = self.search_matches();
+        if let Some(search) = &mut self.history_search {
+            let current = matches.iter().position(|&index| Some(index) == search.found);
+            let next = current.map_or(0, |current| current.saturating_add_signed(step).min(matches.len() - 1));
+            search.found = matches.get(next).copied();
+        }
+    }
+
+    /// Find the newest command matching the search query again, after it
+    /// was edited
+    pub fn update_search(&mut self) {
+        let newest = self.search_matches().first().copied();
+        if let Some(search) = &mut self.history_search {
+            search.found = newest;
+        }
+    }
+
+    /// History entries matching the search query, newest first
+    pub fn search_matches(&self) -> Vec<usize> {
+        self.history_search.as_ref().map_or_else(Vec::new, |search| self.history.matches(&search.query))
+    }
+
+    /// Make a history entry the search's match
+    pub fn select_match(&mut self, index: usize) {
+        if let Some(search) = &mut self.history_search {
+            search.found = Some(index);
+        }
+    }
+
+    /// Whether history entry `index` is the search's match
+    pub fn is_found(&self, index: usize) -> bool {
+        self.history_search.as_ref().is_some_and(|search| search.found == Some(index))
+    }
+
+    /// Command the history search found
+    pub fn found_command(&self) -> Option<&str> {
+        let found = self.history_search.as_ref()?.found?;
+        self.history.entries().get(found).map(String::as_str)
+    }
+
+    /// Close the history search, taking the command it found as the input
+    /// if `accept`
+    pub fn finish_search(&mut self, accept: bool) {
+        if accept {
+            if let Some(command) = self.found_command() {
+                self.input = command.to_string();
+                self.history_index = None;
+            }
+        }
+        self.history_search = None;
+    }
 }
 
 /// Helper module for home directory
@@ -287,100 +384,277 @@ mod dirs {
 
 /// PTY-based terminal tab for interactive shell sessions
 pub struct PtyTerminalTab {
-    /// The PTY terminal instance
-    pub pty: PtyTerminal,
+    /// The PTY terminal instance (`None` if the shell failed to start)
+    pub pty: Option<PtyTerminal>,
+    /// Shell the tab was asked to run
+    shell: String,
     /// Terminal buffer for rendering
     pub buffer: TerminalBuffer,
     /// ANSI parser for processing output
     pub parser: AnsiParser,
+    /// Text selected with the mouse
+    pub selection: Option<Selection>,
+    /// Search through the scrollback and screen, while its bar is open
+    pub search: Option<TerminalSearch>,
     /// Error message if shell failed to start
     pub error: Option<String>,
+    /// What was tried when the shell failed to start
+    pub diagnostics: Option<ShellDiagnostics>,
+    /// Whether `error` was already passed on by [`PtyTerminalState::take_errors`]
+    error_reported: bool,
+    /// Server the tab is connected to, when it runs ssh
+    pub remote: Option<SshHost>,
+    /// Name of the color scheme the tab is drawn in, when not the settings'
+    pub color_scheme: Option<String>,
+    /// Name the user gave the tab, shown in place of the title
+    pub name: Option<String>,
+    /// When a program last rang the bell
+    pub bell: Option<Instant>,
+    /// Whether the bell rang since [`PtyTerminalState::take_bell`] last looked
+    bell_pending: bool,
+    /// Text the program last copied (OSC 52), not passed on yet
+    clipboard: Option<String>,
+    /// Whether the program asked what is on the clipboard
+    clipboard_requested: bool,
+    started: Instant,
+    /// When the running command started, as the shell marked it
+    command_started: Option<Instant>,
+    /// Exit codes and run times of the long commands that finished since
+    /// [`PtyTerminalState::take_finished`] last looked
+    finished: Vec<(Option<i32>, Duration)>,
+    /// Whether the tab's process was seen to exit
+    exited: bool,
 }
 
-impl PtyTerminalTab {
-    /// Create a new PTY terminal tab with Nushell
-    pub fn new_nushell() -> Self {
-        Self::new_shell("nu")
-    }
+/// A command that ran for [`LONG_COMMAND`] or more and finished
+#[derive(Debug, Clone)]
+pub struct FinishedCommand {
+    /// Name of its tab
+    pub tab: String,
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+}
 
-    /// Create a new PTY terminal tab with PowerShell
-    pub fn new_powershell() -> Self {
-        #[cfg(windows)]
-        {
-            Self::new_shell("pwsh")
-        }
-        #[cfg(not(windows))]
-        {
-            Self::new_shell("pwsh")
-        }
-    }
+/// Time a command runs for before its end is worth telling about
+pub const LONG_COMMAND: Duration = Duration::from_secs(10);
 
-    /// Create a new PTY terminal tab with the default shell
-    pub fn new_default() -> Self {
-        #[cfg(windows)]
-        {
-            Self::new_shell("cmd")
-        }
-        #[cfg(not(windows))]
-        {
-            Self::new_shell("bash")
-        }
-    }
+/// Shells offered for new tabs besides the default one, as (name, program)
+pub const OTHER_SHELLS: &[(&str, &str)] = &[
+    ("Nushell", "nu"),
+    ("PowerShell", "pwsh"),
+    #[cfg(windows)]
+    ("CMD", "cmd"),
+    #[cfg(not(windows))]
+    ("Bash", "bash"),
+];
 
-    /// Create a new PTY terminal tab with specified shell
-    pub fn new_shell(shell: &str) -> Self {
-        match PtyTerminal::new_shell(shell) {
+impl PtyTerminalTab {
+    /// Create a new PTY terminal tab running `shell` started as `options` say
+    pub fn spawn(shell: &str, options: &ShellOptions) -> Self {
+        match PtyTerminal::spawn(shell, options) {
             Ok(pty) => {
                 let (cols, rows) = pty.size();
                 Self {
-                    pty,
+                    pty: Some(pty),
+                    shell: shell.to_string(),
                     buffer: TerminalBuffer::new(cols, rows),
                     parser: AnsiParser::new(),
+                    selection: None,
+                    search: None,
                     error: None,
+                    diagnostics: None,
+                    error_reported: false,
+                    remote: None,
+                    color_scheme: None,
+                    name: None,
+                    bell: None,
+                    bell_pending: false,
+                    clipboard: None,
+                    clipboard_requested: false,
+                    started: Instant::now(),
+                    command_started: None,
+                    finished: Vec::new(),
+                    exited: false,
                 }
             }
             Err(e) => {
+                let path = options.env.iter().find(|(name, _)| name == "PATH").map(|(_, path)| path.into());
                 // Return a tab with error state
                 Self {
-                    pty: PtyTerminal::new_shell("cmd").unwrap_or_else(|_| {
-                        panic!("Failed to create fallback shell")
-                    }),
+                    pty: None,
+                    shell: shell.to_string(),
                     buffer: TerminalBuffer::new(80, 24),
                     parser: AnsiParser::new(),
-                    error: Some(format!("Failed to start {}: {}", shell, e)),
+                    selection: None,
+                    search: None,
+                    error: Some(format!("Failed to start {}: {:#}", shell, e)),
+                    diagnostics: Some(ShellDiagnostics::new(shell, path.or_else(|| std::env::var_os("PATH")))),
+                    error_reported: false,
+                    remote: None,
+                    color_scheme: None,
+                    name: None,
+                    bell: None,
+                    bell_pending: false,
+                    clipboard: None,
+                    clipboard_requested: false,
+                    started: Instant::now(),
+                    command_started: None,
+                    finished: Vec::new(),
+                    exited: false,
                 }
             }
         }
     }
 
-    /// Process pending output from the PTY
+    /// Name of the shell the tab runs, or of the server it is connected to
+    pub fn shell_name(&self) -> String {
+        if let Some(remote) = &self.remote {
+            return remote.label();
+        }
+        self.pty.as_ref().map_or(self.shell.as_str(), |pty| pty.shell_name()).to_string()
+    }
+
+    /// Title the running program reported, if any
+    pub fn title(&self) -> Option<&str> {
+        self.buffer.title()
+    }
+
+    /// Name the user gave the tab, or else the title the program reported
+    pub fn label(&self) -> Option<&str> {
+        self.name.as_deref().or_else(|| self.title())
+    }
+
+    /// Folder the shell is in: the one it last reported, or else, on Linux,
+    /// that of its process. None for a remote session, whose folders aren't
+    /// on this machine.
+    pub fn directory(&self) -> Option<PathBuf> {
+        if self.remote.is_some() {
+            return None;
+        }
+        if let Some(directory) = self.buffer.directory() {
+            return Some(directory.to_path_buf());
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(pid) = self.pty.as_ref().and_then(PtyTerminal::process_id) {
+            return std::fs::read_link(format!("/proc/{}/cwd", pid)).ok();
+        }
+        None
+    }
+
+    /// Process pending output from the PTY, and note the bell, the long
+    /// commands that finished and the process exiting
     pub fn process_output(&mut self) {
-        let output = self.pty.read_output();
+        let Some(pty) = &mut self.pty else {
+            return;
+        };
+        let output = pty.read_output();
         if !output.is_empty() {
             self.parser.process(&output, &mut self.buffer);
         }
+        if !self.exited {
+            if let Some(code) = pty.exit_code() {
+                self.exited = true;
+                if self.started.elapsed() >= LONG_COMMAND {
+                    self.finished.push((i32::try_from(code).ok(), self.started.elapsed()));
+                }
+            }
+        }
+
+        for event in self.buffer.take_events() {
+            match event {
+                TerminalEvent::Bell => {
+                    self.bell = Some(Instant::now());
+                    self.bell_pending = true;
+                }
+                TerminalEvent::CommandStarted => self.command_started = Some(Instant::now()),
+                TerminalEvent::SetClipboard(text) => self.clipboard = Some(text),
+                TerminalEvent::ClipboardRequest => self.clipboard_requested = true,
+                TerminalEvent::CommandFinished(exit_code) => {
+                    let duration = self.command_started.take().map(|started| started.elapsed());
+                    if let Some(duration) = duration.filter(|duration| *duration >= LONG_COMMAND) {
+                        self.finished.push((exit_code, duration));
+                    }
+                }
+            }
+        }
     }
 
     /// Write input to the PTY
     pub fn write(&mut self, data: &[u8]) -> anyhow::Result<()> {
-        self.pty.write(data)
+        self.running_pty()?.write(data)
+    }
+
+    /// Paste text into the shell, marked as a paste if the program asked
+    /// for bracketed paste so it isn't run line by line
+    pub fn paste(&mut self, text: &str) -> anyhow::Result<()> {
+        // Terminals send Enter as a carriage return
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if self.buffer.bracketed_paste() {
+            // A pasted end marker would end the paste early
+            let text = text.replace("\x1b[201~", "");
+            self.write(format!("\x1b[200~{}\x1b[201~", text).as_bytes())
+        } else {
+            self.write(text.as_bytes())
+        }
+    }
+
+    /// Open the search bar, or go back to it, with the focus on the query
+    pub fn open_search(&mut self) {
+        self.search.get_or_insert_with(TerminalSearch::default).focus = true;
+    }
+
+    /// Text of the current selection
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection.map(|selection| self.buffer.selected_text(&selection))
+    }
+
+    /// Text to put in a note: the selection, or else the last command's
+    /// output
+    pub fn output_for_note(&self) -> Option<String> {
+        self.selected_text().or_else(|| self.buffer.last_output())
     }
 
     /// Send a special key
     pub fn send_key(&mut self, key: TerminalKey) -> anyhow::Result<()> {
-        self.pty.send_key(key)
+        let application_cursor = self.buffer.application_cursor();
+        self.running_pty()?.send_key(key, application_cursor)
     }
 
     /// Resize the terminal
     pub fn resize(&mut self, cols: u16, rows: u16) -> anyhow::Result<()> {
-        self.pty.resize(cols, rows)?;
+        self.running_pty()?.resize(cols, rows)?;
         self.buffer.resize(cols, rows);
         Ok(())
     }
 
+    /// Columns and rows of the terminal
+    pub fn size(&self) -> (u16, u16) {
+        self.buffer.size()
+    }
+
+    /// Drop the scrollback and the selection, and have the shell redraw its
+    /// prompt at the top of the screen (Ctrl+L)
+    pub fn clear(&mut self) -> anyhow::Result<()> {
+        self.buffer.clear_scrollback();
+        self.selection = None;
+        self.write(b"\x0c")
+    }
+
+    /// Kill the process the tab runs. The tab stays open, showing its output.
+    pub fn kill(&mut self) -> anyhow::Result<()> {
+        self.running_pty()?.kill()
+    }
+
     /// Check if the shell is still running
     pub fn is_alive(&mut self) -> bool {
-        self.pty.is_alive()
+        self.pty.as_mut().is_some_and(PtyTerminal::is_alive)
+    }
+
+    /// The PTY, or an error if the shell never started
+    fn running_pty(&mut self) -> anyhow::Result<&mut PtyTerminal> {
+        self.pty
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("{} is not running", self.shell))
     }
 }
 
@@ -392,51 +666,402 @@ pub struct PtyTerminalState {
     pub active_tab: usize,
     /// Default shell to use for new tabs
     pub default_shell: String,
+    /// How new tabs start their shell
+    options: ShellOptions,
+    /// Lines of scrollback each tab keeps
+    scrollback_lines: usize,
+    /// Built-in color schemes and those of the theme files
+    color_schemes: Vec<ColorScheme>,
+    /// Name of the color scheme tabs are drawn in unless they pick another
+    color_scheme: String,
+    /// Theme file errors not reported yet
+    theme_errors: Vec<String>,
+    /// Wakes the UI when a tab gets output
+    waker: Option<Waker>,
+    /// Servers new tabs can connect to
+    pub ssh_hosts: Vec<SshHost>,
+    /// What the bell does
+    pub bell_style: BellStyle,
+    /// What programs may do with the clipboard
+    pub clipboard_access: ClipboardAccess,
+    /// Pipe shells send commands for the app to, where there is one
+    control: Option<ControlPipe>,
+    /// Vault and active note new shells are told about
+    vault: Option<PathBuf>,
+    active_note: Option<PathBuf>,
+    /// Name being typed for the current tab, while it is renamed
+    pub renaming: Option<String>,
+    /// Tabs shown at once in split panes, in order; empty when not split.
+    /// The active tab is the focused pane.
+    pub panes: Vec<usize>,
+    /// How split panes are arranged
+    pub split_direction: SplitDirection,
+}
+
+/// Where split panes go
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// Side by side
+    #[default]
+    Right,
+    /// Stacked
+    Down,
 }
 
 impl Default for PtyTerminalState {
     fn default() -> Self {
-        Self::new()
+        Self::new(&TerminalConfig::default(), None)
     }
 }
 
 impl PtyTerminalState {
-    /// Create a new PTY terminal state with one Nushell tab
-    pub fn new() -> Self {
-        Self {
-            tabs: vec![PtyTerminalTab::new_nushell()],
+    /// Create a new PTY terminal state with one tab, set up as `config` says
+    pub fn new(config: &TerminalConfig, vault: Option<&Path>) -> Self {
+        let mut state = Self {
+            tabs: Vec::new(),
             active_tab: 0,
-            default_shell: "nu".to_string(),
+            default_shell: String::new(),
+            options: ShellOptions::default(),
+            scrollback_lines: 0,
+            color_schemes: Vec::new(),
+            color_scheme: String::new(),
+            theme_errors: Vec::new(),
+            waker: None,
+            ssh_hosts: Vec::new(),
+            bell_style: BellStyle::default(),
+            clipboard_access: ClipboardAccess::default(),
+            control: ControlPipe::create()
+                .inspect_err(|e| tracing::info!("No terminal control pipe: {:#}", e))
+                .ok(),
+            vault: None,
+            active_note: None,
+            renaming: None,
+            panes: Vec::new(),
+            split_direction: SplitDirection::default(),
+        };
+        state.configure(config, vault);
+        state.new_tab();
+        state
+    }
+
+    /// Start new tabs as `config` says, in `vault` if it asks for that, and
+    /// apply its scrollback size and colors to every tab
+    pub fn configure(&mut self, config: &TerminalConfig, vault: Option<&Path>) {
+        self.default_shell = config.shell_program();
+        let cwd = if config.start_in_vault { vault.map(Path::to_path_buf) } else { None };
+        self.options = ShellOptions {
+            args: config.args.clone(),
+            env: config.env.iter().map(|(name, value)| (name.clone(), value.clone())).collect(),
+            cwd: cwd.or_else(dirs::home_dir),
+        };
+        self.scrollback_lines = config.scrollback_lines;
+        for tab in &mut self.tabs {
+            tab.buffer.set_max_scrollback(self.scrollback_lines);
         }
+        (self.color_schemes, self.theme_errors) = ColorScheme::load_all(&TerminalConfig::theme_dir());
+        self.color_scheme = config.color_scheme.clone();
+        self.ssh_hosts = config.ssh_hosts.clone();
+        self.bell_style = config.bell;
+        self.clipboard_access = config.clipboard;
+        self.vault = vault.map(Path::to_path_buf);
+        self.set_context_env();
     }
 
-    /// Create a new PTY terminal state with specified shell
-    pub fn with_shell(shell: &str) -> Self {
-        Self {
-            tabs: vec![PtyTerminalTab::new_shell(shell)],
-            active_tab: 0,
-            default_shell: shell.to_string(),
+    /// Tell shells started from now on which note is active
+    pub fn set_active_note(&mut self, note: Option<&Path>) {
+        if self.active_note.as_deref() != note {
+            self.active_note = note.map(Path::to_path_buf);
+            self.set_context_env();
+        }
+    }
+
+    /// Pass the vault, the active note and the control pipe to new shells
+    /// in `ROBSIDIAN_*` variables
+    fn set_context_env(&mut self) {
+        const NAMES: [&str; 3] = ["ROBSIDIAN_VAULT", "ROBSIDIAN_ACTIVE_NOTE", "ROBSIDIAN_CONTROL"];
+        self.options.env.retain(|(name, _)| !NAMES.contains(&name.as_str()));
+        let paths = [self.vault.as_deref(), self.active_note.as_deref(), self.control.as_ref().map(ControlPipe::path)];
+        for (name, path) in NAMES.into_iter().zip(paths) {
+            if let Some(path) = path {
+                self.options.env.push((name.to_string(), path.to_string_lossy().into_owned()));
+            }
         }
     }
 
+    /// Commands scripts sent through the control pipe since the last call
+    pub fn take_control_commands(&mut self) -> Vec<anyhow::Result<ControlCommand>> {
+        self.control.as_ref().map(ControlPipe::take_commands).unwrap_or_default()
+    }
+
+    /// Colors the terminals are drawn in
+    pub fn color_scheme(&self) -> &ColorScheme {
+        let tab_scheme = self.current_tab().and_then(|tab| tab.color_scheme.as_deref());
+        ColorScheme::named(&self.color_schemes, tab_scheme.unwrap_or(&self.color_scheme))
+    }
+
+    /// Color schemes to pick from
+    pub fn color_schemes(&self) -> &[ColorScheme] {
+        &self.color_schemes
+    }
+
+    /// Draw the current tab in the scheme called `name`, or in the settings'
+    /// scheme with `None`
+    pub fn set_tab_color_scheme(&mut self, name: Option<String>) {
+        if let Some(tab) = self.current_tab_mut() {
+            tab.color_scheme = name;
+        }
+    }
+
+    /// Theme files that couldn't be read, not reported yet
+    pub fn take_theme_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.theme_errors)
+    }
+
+    /// Shell start errors not reported yet
+    pub fn take_errors(&mut self) -> Vec<String> {
+        self.tabs
+            .iter_mut()
+            .filter(|tab| !tab.error_reported)
+            .filter_map(|tab| {
+                tab.error_reported = true;
+                tab.error.clone()
+            })
+            .collect()
+    }
+
+    /// Whether a program rang the bell since the last call
+    pub fn take_bell(&mut self) -> bool {
+        let mut rang = false;
+        for tab in &mut self.tabs {
+            rang |= std::mem::take(&mut tab.bell_pending);
+        }
+        rang
+    }
+
+    /// Text a program copied since the last call, if programs may copy
+    pub fn take_clipboard(&mut self) -> Option<String> {
+        let mut copied = None;
+        for tab in &mut self.tabs {
+            copied = tab.clipboard.take().or(copied);
+        }
+        copied.filter(|_| self.clipboard_access != ClipboardAccess::Off)
+    }
+
+    /// Answer the programs that asked what is on the clipboard with what
+    /// `read` finds there, if programs may read it; otherwise the request
+    /// goes unanswered, as in other terminals
+    pub fn answer_clipboard_requests(&mut self, read: impl FnOnce() -> Option<String>) {
+        let asked: Vec<usize> = (0..self.tabs.len())
+            .filter(|&index| std::mem::take(&mut self.tabs[index].clipboard_requested))
+            .collect();
+        if asked.is_empty() || self.clipboard_access != ClipboardAccess::ReadWrite {
+            return;
+        }
+        let contents = read().unwrap_or_default();
+        let reply = format!("\x1b]52;c;{}\x1b\\", BASE64.encode(contents));
+        for index in asked {
+            if let Err(e) = self.tabs[index].write(reply.as_bytes()) {
+                tracing::warn!("Failed to send the clipboard to the terminal: {:#}", e);
+            }
+        }
+    }
+
+    /// Long commands that finished since the last call
+    pub fn take_finished(&mut self) -> Vec<FinishedCommand> {
+        let mut finished = Vec::new();
+        for tab in &mut self.tabs {
+            let name = tab.title().map_or_else(|| tab.shell_name(), str::to_string);
+            finished.extend(tab.finished.drain(..).map(|(exit_code, duration)| FinishedCommand {
+                tab: name.clone(),
+                exit_code,
+                duration,
+            }));
+        }
+        finished
+    }
+
+    /// Wake the UI with `waker` whenever any tab, now or later, gets output,
+    /// so the terminal only needs a frame when something changed
+    pub fn set_waker(&mut self, waker: Waker) {
+        for pty in self.tabs.iter().filter_map(|tab| tab.pty.as_ref()) {
+            pty.set_waker(Arc::clone(&waker));
+        }
+        if let Some(control) = &self.control {
+            control.set_waker(Arc::clone(&waker));
+        }
+        self.waker = Some(waker);
+    }
+
     /// Create a new tab with the default shell
     pub fn new_tab(&mut self) {
-        self.tabs.push(PtyTerminalTab::new_shell(&self.default_shell));
-        self.active_tab = self.tabs.len() - 1;
+        let shell = self.default_shell.clone();
+        self.new_tab_with_shell(&shell);
     }
 
     /// Create a new tab with specific shell
     pub fn new_tab_with_shell(&mut self, shell: &str) {
-        self.tabs.push(PtyTerminalTab::new_shell(shell));
-        self.active_tab = self.tabs.len() - 1;
+        let tab = self.spawn_tab(shell);
+        self.push_tab(tab);
     }
 
-    /// Close the current tab
+    /// Tab running `shell`. The configured arguments are only passed to the
+    /// default shell.
+    fn spawn_tab(&self, shell: &str) -> PtyTerminalTab {
+        if shell == self.default_shell {
+            PtyTerminalTab::spawn(shell, &self.options)
+        } else {
+            PtyTerminalTab::spawn(shell, &ShellOptions { args: Vec::new(), ..self.options.clone() })
+        }
+    }
+
+    /// Create a new tab with the default shell started in `dir`
+    pub fn new_tab_in(&mut self, dir: &Path) {
+        let options = ShellOptions { cwd: Some(dir.to_path_buf()), ..self.options.clone() };
+        let tab = PtyTerminalTab::spawn(&self.default_shell, &options);
+        self.push_tab(tab);
+    }
+
+    /// Start `shell` in place of the current tab, as after its shell failed
+    /// to start. A tab that ran ssh connects to the same server again.
+    pub fn replace_current_tab(&mut self, shell: &str) {
+        let remote = self.current_tab().and_then(|tab| tab.remote.clone()).filter(|_| shell == "ssh");
+        let mut tab = match remote {
+            Some(remote) => Self::spawn_ssh(&remote, &self.options),
+            None => self.spawn_tab(shell),
+        };
+        tab.color_scheme = self.current_tab().and_then(|tab| tab.color_scheme.clone());
+        tab.name = self.current_tab().and_then(|tab| tab.name.clone());
+        self.adopt(&mut tab);
+        match self.tabs.get_mut(self.active_tab) {
+            Some(current) => *current = tab,
+            None => self.push_tab(tab),
+        }
+    }
+
+    /// Split the focused pane, starting the default shell in the new pane,
+    /// which gets the focus. Every pane is arranged in `direction`.
+    pub fn split(&mut self, direction: SplitDirection) {
+        let focused = self.active_tab;
+        let mut panes = std::mem::take(&mut self.panes);
+        if panes.is_empty() {
+            panes.push(focused);
+        }
+        self.new_tab();
+        let at = panes.iter().position(|pane| *pane == focused).map_or(panes.len(), |index| index + 1);
+        panes.insert(at, self.active_tab);
+        self.panes = panes;
+        self.split_direction = direction;
+    }
+
+    /// Tabs shown: those of the panes, or the active one alone
+    pub fn shown_tabs(&self) -> Vec<usize> {
+        if self.panes.len() > 1 {
+            self.panes.clone()
+        } else {
+            vec![self.active_tab]
+        }
+    }
+
+    /// Move the focus to the next pane, or the previous one, wrapping around
+    pub fn focus_pane(&mut self, next: bool) {
+        let Some(index) = self.panes.iter().position(|pane| *pane == self.active_tab) else {
+            return;
+        };
+        let count = self.panes.len();
+        let index = if next { (index + 1) % count } else { (index + count - 1) % count };
+        self.active_tab = self.panes[index];
+        self.renaming = None;
+    }
+
+    /// Create a new tab connected to `host` over SSH. The remote session goes
+    /// through the same parser and buffer as a local shell.
+    pub fn connect_ssh(&mut self, host: &SshHost) {
+        let tab = Self::spawn_ssh(host, &self.options);
+        self.push_tab(tab);
+    }
+
+    fn spawn_ssh(host: &SshHost, options: &ShellOptions) -> PtyTerminalTab {
+        let mut tab = PtyTerminalTab::spawn("ssh", &ShellOptions { args: host.ssh_args(), ..options.clone() });
+        tab.remote = Some(host.clone());
+        tab
+    }
+
+    /// Create a new tab running `program` with `args` instead of a shell
+    pub fn new_tab_running(&mut self, program: &str, args: Vec<String>) {
+        let tab = PtyTerminalTab::spawn(program, &ShellOptions { args, ..self.options.clone() });
+        self.push_tab(tab);
+    }
+
+    /// Add a tab and switch to it, showing it in the focused pane when split
+    fn push_tab(&mut self, mut tab: PtyTerminalTab) {
+        self.adopt(&mut tab);
+        self.tabs.push(tab);
+        self.switch_tab(self.tabs.len() - 1);
+    }
+
+    /// Set a new tab up like the others
+    fn adopt(&self, tab: &mut PtyTerminalTab) {
+        tab.buffer.set_max_scrollback(self.scrollback_lines);
+        if let (Some(pty), Some(waker)) = (&tab.pty, &self.waker) {
+            pty.set_waker(Arc::clone(waker));
+        }
+    }
+
+    /// Run the code of a fenced block in `lang`, switching to the tab it
+    /// runs in (see [`code_runner`]). A shell that fails to start is
+    /// reported by [`Self::take_errors`], like any other.
+    pub fn run_code(&mut self, lang: Option<&str>, code: &str) -> anyhow::Result<()> {
+        let Some(runner) = runner(lang) else {
+            anyhow::bail!("Cannot run code blocks in {}", lang.unwrap_or_default());
+        };
+        match runner {
+            Runner::Shell(shells) => {
+                let shell_name = |shell: &str| {
+                    Path::new(shell).file_stem().map_or(shell.to_string(), |name| name.to_string_lossy().to_string())
+                };
+                let fits = |shell: &str| shells.is_empty() || shells.contains(&shell_name(shell).as_str());
+                let current_fits = self
+                    .current_tab_mut()
+                    .is_some_and(|tab| tab.is_alive() && tab.remote.is_none() && fits(&tab.shell_name()));
+                if !current_fits {
+                    let shell = if fits(&self.default_shell) { self.default_shell.clone() } else { shells[0].to_string() };
+                    self.new_tab_with_shell(&shell);
+                }
+                let Some(tab) = self.current_tab_mut().filter(|tab| tab.pty.is_some()) else {
+                    return Ok(());
+                };
+                // Terminals send Enter as a carriage return
+                for line in code.lines() {
+                    tab.write(format!("{}\r", line).as_bytes())?;
+                }
+            }
+            Runner::Interpreter { program, flag } => {
+                self.new_tab_running(program, vec![flag.to_string(), code.to_string()]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the current tab, and its pane when split
     pub fn close_current_tab(&mut self) {
         if self.tabs.len() > 1 {
-            self.tabs.remove(self.active_tab);
-            if self.active_tab >= self.tabs.len() {
-                self.active_tab = self.tabs.len() - 1;
+            self.renaming = None;
+            let closed = self.active_tab;
+            let pane = self.panes.iter().position(|pane| *pane == closed);
+            self.tabs.remove(closed);
+            self.panes.retain(|pane| *pane != closed);
+            for pane in &mut self.panes {
+                if *pane > closed {
+                    *pane -= 1;
+                }
+            }
+            self.active_tab = match pane {
+                // The pane that took the closed one's place
+                Some(index) if !self.panes.is_empty() => self.panes[index.min(self.panes.len() - 1)],
+                _ => closed.min(self.tabs.len() - 1),
+            };
+            if self.panes.len() < 2 {
+                self.panes.clear();
             }
         }
     }
@@ -451,10 +1076,42 @@ impl PtyTerminalState {
         self.tabs.get_mut(self.active_tab)
     }
 
-    /// Switch to a specific tab
+    /// Switch to a specific tab. When split, a tab not in a pane takes the
+    /// focused pane's place.
     pub fn switch_tab(&mut self, index: usize) {
-        if index < self.tabs.len() {
-            self.active_tab = index;
+        if index >= self.tabs.len() {
+            return;
+        }
+        if index != self.active_tab {
+            self.renaming = None;
+        }
+        if !self.panes.contains(&index) {
+            if let Some(pane) = self.panes.iter_mut().find(|pane| **pane == self.active_tab) {
+                *pane = index;
+            }
+        }
+        self.active_tab = index;
+    }
+
+    /// Switch to the next tab, or the previous one, wrapping around
+    pub fn cycle_tab(&mut self, forward: bool) {
+        let count = self.tabs.len();
+        if count > 1 {
+            let index = if forward { (self.active_tab + 1) % count } else { (self.active_tab + count - 1) % count };
+            self.switch_tab(index);
+        }
+    }
+
+    /// Start typing a name for the current tab, from the one it shows
+    pub fn start_renaming(&mut self) {
+        self.renaming = self.current_tab().map(|tab| tab.label().unwrap_or_default().to_string());
+    }
+
+    /// Name the current tab; a blank name goes back to the title
+    pub fn rename_current_tab(&mut self, name: &str) {
+        if let Some(tab) = self.current_tab_mut() {
+            let name = name.trim();
+            tab.name = (!name.is_empty()).then(|| name.to_string());
         }
     }
 
@@ -4,16 +4,34 @@
 //! allowing Nushell or other shells to be embedded within the application.
 
 use anyhow::{Context, Result};
-use portable_pty::{native_pty_system, Child, CommandBuilder, PtyPair, PtySize};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtyPair, PtySize};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
 use std::thread;
 
+/// Called from the reader thread when output arrives, to wake up the UI
+pub type Waker = Arc<dyn Fn() + Send + Sync>;
+
+/// How a shell is started, besides which program it is
+#[derive(Debug, Clone, Default)]
+pub struct ShellOptions {
+    pub args: Vec<String>,
+    /// Variables set on top of the app's environment
+    pub env: Vec<(String, String)>,
+    /// Working directory; the app's own when `None`
+    pub cwd: Option<PathBuf>,
+}
+
 /// A PTY-based terminal that manages a shell subprocess
 pub struct PtyTerminal {
     child: Box<dyn Child + Send + Sync>,
+    /// Kept to resize the PTY; dropping it would close the terminal
+    master: Box<dyn MasterPty + Send>,
     writer: Box<dyn Write + Send>,
     output_rx: Receiver<Vec<u8>>,
+    waker: Arc<Mutex<Option<Waker>>>,
     size: PtySize,
     shell_name: String,
 }
@@ -26,6 +44,12 @@ impl PtyTerminal {
 
     /// Create a new PTY terminal with the specified shell
     pub fn new_shell(shell: &str) -> Result<Self> {
+        Self::spawn(shell, &ShellOptions::default())
+    }
+
+    /// Create a new PTY terminal running `shell`, a program name or path,
+    /// started as `options` say
+    pub fn spawn(shell: &str, options: &ShellOptions) -> Result<Self> {
         let pty_system = native_pty_system();
 
         let size = PtySize {
@@ -40,14 +64,24 @@ impl PtyTerminal {
             .context("Failed to open PTY pair")?;
 
         let mut cmd = CommandBuilder::new(shell);
+        cmd.args(&options.args);
+        if let Some(cwd) = options.cwd.as_ref().filter(|cwd| cwd.is_dir()) {
+            cmd.cwd(cwd);
+        }
 
         // Set environment variables for better terminal experience
         cmd.env("TERM", "xterm-256color");
 
         // For Nushell, disable some features that don't work well in embedded terminals
-        if shell == "nu" {
+        let shell_name = Path::new(shell)
+            .file_stem()
+            .map_or(shell.to_string(), |name| name.to_string_lossy().to_string());
+        if shell_name == "nu" {
             cmd.env("NO_COLOR", "0"); // Allow colors
         }
+        for (name, value) in &options.env {
+            cmd.env(name, value);
+        }
 
         let child = pair
             .slave
@@ -66,20 +100,32 @@ impl PtyTerminal {
 
         // Set up non-blocking output reading via channel
         let (output_tx, output_rx) = mpsc::channel();
-        Self::spawn_reader_thread(reader, output_tx);
+        let waker = Arc::new(Mutex::new(None));
+        Self::spawn_reader_thread(reader, output_tx, Arc::clone(&waker));
 
         Ok(Self {
             child,
+            master: pair.master,
             writer,
             output_rx,
+            waker,
             size,
-            shell_name: shell.to_string(),
+            shell_name,
         })
     }
 
     /// Spawn a background thread to read PTY output
-    fn spawn_reader_thread(mut reader: Box<dyn Read + Send>, tx: Sender<Vec<u8>>) {
+    fn spawn_reader_thread(
+        mut reader: Box<dyn Read + Send>,
+        tx: Sender<Vec<u8>>,
+        waker: Arc<Mutex<Option<Waker>>>,
+    ) {
         thread::spawn(move || {
+            let wake = || {
+                if let Some(wake) = waker.lock().unwrap().as_ref() {
+                    wake();
+                }
+            };
             let mut buf = [0u8; 4096];
             loop {
                 match reader.read(&mut buf) {
@@ -88,6 +134,7 @@ impl PtyTerminal {
                         if tx.send(buf[..n].to_vec()).is_err() {
                             break; // Receiver dropped
                         }
+                        wake();
                     }
                     Err(e) => {
                         tracing::error!("PTY read error: {}", e);
@@ -95,9 +142,16 @@ impl PtyTerminal {
                     }
                 }
             }
+            // Let the UI notice the shell exited
+            wake();
         });
     }
 
+    /// Call `waker` whenever output arrives
+    pub fn set_waker(&self, waker: Waker) {
+        *self.waker.lock().unwrap() = Some(waker);
+    }
+
     /// Write data to the PTY (send to shell)
     pub fn write(&mut self, data: &[u8]) -> Result<()> {
         self.writer
@@ -125,17 +179,17 @@ impl PtyTerminal {
         output
     }
 
-    /// Resize the PTY
+    /// Resize the PTY, which signals the shell (SIGWINCH) so full-screen
+    /// programs redraw at the new size
     pub fn resize(&mut self, cols: u16, rows: u16) -> Result<()> {
-        self.size = PtySize {
+        let size = PtySize {
             rows,
             cols,
             pixel_width: 0,
             pixel_height: 0,
         };
-        // Note: portable-pty's resize is on the pair.master, which we don't store
-        // For now, we track the size but can't actually resize
-        // TODO: Store master reference for resize support
+        self.master.resize(size).context("Failed to resize PTY")?;
+        self.size = size;
         Ok(())
     }
 
@@ -153,6 +207,16 @@ impl PtyTerminal {
         }
     }
 
+    /// Exit code of the child process, once it exited
+    pub fn exit_code(&mut self) -> Option<u32> {
+        self.child.try_wait().ok().flatten().map(|status| status.exit_code())
+    }
+
+    /// Process id of the shell
+    pub fn process_id(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+
     /// Kill the child process
     pub fn kill(&mut self) -> Result<()> {
         self.child.kill().context("Failed to kill PTY child process")
@@ -163,9 +227,10 @@ impl PtyTerminal {
         &self.shell_name
     }
 
-    /// Send a special key sequence
-    pub fn send_key(&mut self, key: TerminalKey) -> Result<()> {
-        let seq = key.to_escape_sequence();
+    /// Send a special key sequence, in application cursor mode if
+    /// `application_cursor`
+    pub fn send_key(&mut self, key: TerminalKey, application_cursor: bool) -> Result<()> {
+        let seq = key.to_escape_sequence(application_cursor);
         self.write(seq)
     }
 }
@@ -194,9 +259,17 @@ pub enum TerminalKey {
 }
 
 impl TerminalKey {
-    /// Convert key to ANSI escape sequence
-    pub fn to_escape_sequence(self) -> &'static [u8] {
+    /// Convert key to ANSI escape sequence. In application cursor mode,
+    /// which full-screen programs switch on, the arrows, Home and End send
+    /// `ESC O` sequences instead.
+    pub fn to_escape_sequence(self, application_cursor: bool) -> &'static [u8] {
         match self {
+            TerminalKey::Up if application_cursor => b"\x1bOA",
+            TerminalKey::Down if application_cursor => b"\x1bOB",
+            TerminalKey::Right if application_cursor => b"\x1bOC",
+            TerminalKey::Left if application_cursor => b"\x1bOD",
+            TerminalKey::Home if application_cursor => b"\x1bOH",
+            TerminalKey::End if application_cursor => b"\x1bOF",
             TerminalKey::Up => b"\x1b[A",
             TerminalKey::Down => b"\x1b[B",
             TerminalKey::Right => b"\x1b[C",
@@ -0,0 +1,195 @@
+//! Finding text in a terminal's scrollback and screen
+//!
+//! Matches are found line by line, so text that wrapped onto the next line
+//! isn't found across the break. The search runs again whenever the
+//! terminal's content changes, so matches in new output show up as it
+//! arrives.
+
+use std::ops::Range;
+
+use regex_lite::{Regex, RegexBuilder};
+
+use super::buffer::TerminalBuffer;
+
+/// Cells of a line that match the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Line, counted from the top of the scrollback
+    pub line: usize,
+    pub cols: Range<usize>,
+}
+
+/// Query typed into a terminal's search bar and what it found
+#[derive(Debug, Default)]
+pub struct TerminalSearch {
+    pub query: String,
+    /// Take the query as a regular expression rather than as plain text
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Move the keyboard focus to the query on the next frame
+    pub focus: bool,
+    matches: Vec<SearchMatch>,
+    /// Index of the match the view was moved to
+    current: Option<usize>,
+    /// Why the query isn't a valid regular expression
+    error: Option<String>,
+    /// Query, options and buffer revision the matches were found for
+    searched: Option<(String, bool, bool, (usize, u64))>,
+}
+
+impl TerminalSearch {
+    /// Find the matches again if the query, its options or the buffer changed
+    /// since the last search. A new query moves to the match nearest the end
+    /// of the output; otherwise the current match stays the one at or after
+    /// where it was. Returns whether the view should move to the current
+    /// match: only a new query moves it, so output arriving meanwhile doesn't
+    /// pull the view away from where it was scrolled.
+    pub fn update(&mut self, buffer: &TerminalBuffer) -> bool {
+        let key = (self.query.clone(), self.regex, self.case_sensitive, buffer.content_revision());
+        if self.searched.as_ref() == Some(&key) {
+            return false;
+        }
+        let new_query = self.searched.as_ref().is_none_or(|(query, regex, case_sensitive, _)| {
+            (query, *regex, *case_sensitive) != (&self.query, self.regex, self.case_sensitive)
+        });
+        self.searched = Some(key);
+
+        let previous = self.current().map(|found| (found.line, found.cols.start));
+        self.matches.clear();
+        self.error = None;
+        if !self.query.is_empty() {
+            match self.pattern() {
+                Ok(pattern) => self.matches = find(buffer, &pattern),
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+
+        self.current = match previous {
+            Some(position) if !new_query => self
+                .matches
+                .iter()
+                .position(|found| (found.line, found.cols.start) >= position)
+                .or(self.matches.len().checked_sub(1)),
+            _ => self.matches.len().checked_sub(1),
+        };
+        new_query && self.current.is_some()
+    }
+
+    fn pattern(&self) -> Result<Regex, regex_lite::Error> {
+        let pattern = if self.regex { self.query.clone() } else { regex_lite::escape(&self.query) };
+        RegexBuilder::new(&pattern).case_insensitive(!self.case_sensitive).build()
+    }
+
+    pub fn matches(&self) -> &[SearchMatch] {
+        &self.matches
+    }
+
+    pub fn current(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current?)
+    }
+
+    /// Position of the current match among all of them, counting from 1
+    pub fn current_number(&self) -> Option<usize> {
+        self.current.map(|index| index + 1)
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Move to the match after the current one, wrapping around to the first
+    pub fn next(&mut self) -> Option<&SearchMatch> {
+        let count = self.matches.len();
+        self.current = self.current.map(|index| (index + 1) % count.max(1));
+        self.current()
+    }
+
+    /// Move to the match before the current one, wrapping around to the last
+    pub fn previous(&mut self) -> Option<&SearchMatch> {
+        let count = self.matches.len();
+        self.current = self.current.map(|index| (index + count.max(1) - 1) % count.max(1));
+        self.current()
+    }
+}
+
+/// Every match of `pattern` in the scrollback and on the screen, in order
+fn find(buffer: &TerminalBuffer, pattern: &Regex) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    let mut text = String::new();
+    // Column of the cell each byte of `text` comes from
+    let mut cols = Vec::new();
+    for number in 0..buffer.line_count() {
+        let Some(line) = buffer.line_at(number) else {
+            break;
+        };
+        text.clear();
+        cols.clear();
+        for (col, ch) in line.chars.iter().enumerate() {
+            if ch.is_spacer() {
+                continue;
+            }
+            ch.push_text(&mut text);
+            cols.resize(text.len(), col);
+        }
+        for found in pattern.find_iter(&text) {
+            if found.is_empty() {
+                continue;
+            }
+            let start = cols[found.start()];
+            let last = cols[found.end() - 1];
+            let width = if line.chars[last].wide { 2 } else { 1 };
+            matches.push(SearchMatch { line: number, cols: start..last + width });
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_line(buffer: &mut TerminalBuffer, text: &str) {
+        for c in text.chars() {
+            buffer.put_char(c);
+        }
+        buffer.carriage_return();
+        buffer.newline();
+    }
+
+    #[test]
+    fn test_search() {
+        let mut buffer = TerminalBuffer::new(20, 3);
+        write_line(&mut buffer, "error: one");
+        write_line(&mut buffer, "漢字 Error two");
+        write_line(&mut buffer, "ok");
+
+        let mut search = TerminalSearch { query: "error".to_string(), ..Default::default() };
+        assert!(search.update(&buffer));
+        let found: Vec<_> = search.matches().iter().map(|found| (found.line, found.cols.clone())).collect();
+        // The double-width characters before the match take two cells each
+        assert_eq!(found, [(0, 0..5), (1, 5..10)]);
+        assert_eq!(search.current().map(|found| found.line), Some(1));
+        assert_eq!(search.next().map(|found| found.line), Some(0));
+        assert_eq!(search.previous().map(|found| found.line), Some(1));
+
+        search.case_sensitive = true;
+        search.update(&buffer);
+        assert_eq!(search.matches().len(), 1);
+
+        // New output keeps the current match
+        search.case_sensitive = false;
+        search.update(&buffer);
+        search.previous();
+        write_line(&mut buffer, "no match");
+        assert!(!search.update(&buffer));
+        assert_eq!(search.current().map(|found| found.line), Some(0));
+
+        search.regex = true;
+        search.query = r"漢\S+".to_string();
+        search.update(&buffer);
+        assert_eq!(search.current().map(|found| found.cols.clone()), Some(0..4));
+        search.query = "(".to_string();
+        search.update(&buffer);
+        assert!(search.error().is_some() && search.matches().is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+//! Why a shell didn't start
+//!
+//! When a terminal tab's shell fails to start, the tab shows what was tried:
+//! the program, where it was looked for on the PATH the shell would have
+//! got, and the shells that can be found there instead, so another one is a
+//! click away.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Shells offered in place of one that didn't start, in order of preference
+#[cfg(windows)]
+const CANDIDATE_SHELLS: &[&str] = &["pwsh", "powershell", "cmd", "nu", "bash"];
+#[cfg(not(windows))]
+const CANDIDATE_SHELLS: &[&str] = &["bash", "zsh", "fish", "sh", "nu", "pwsh"];
+
+/// What was found out about a shell that didn't start
+#[derive(Debug, Clone)]
+pub struct ShellDiagnostics {
+    /// Program the tab was asked to run
+    pub shell: String,
+    /// Where the program was found, if it was: it exists but didn't start
+    pub found: Option<PathBuf>,
+    /// Folders of the PATH, in the order they were searched
+    pub path: Vec<PathBuf>,
+    /// Shells found on the PATH, with where
+    pub candidates: Vec<(String, PathBuf)>,
+}
+
+impl ShellDiagnostics {
+    /// Look for `shell` and the other shells on `path`, the PATH the shell
+    /// was started with
+    pub fn new(shell: &str, path: Option<OsString>) -> Self {
+        let path: Vec<PathBuf> = path.map(|path| std::env::split_paths(&path).collect()).unwrap_or_default();
+        let candidates = CANDIDATE_SHELLS
+            .iter()
+            .filter(|candidate| **candidate != shell)
+            .filter_map(|candidate| Some((candidate.to_string(), find_program(candidate, &path)?)))
+            .collect();
+        Self { shell: shell.to_string(), found: find_program(shell, &path), path, candidates }
+    }
+}
+
+/// Executable `program` names, either as a path or by a name looked up in
+/// the `path` folders
+pub fn find_program(program: &str, path: &[PathBuf]) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    let names: Vec<OsString> = if cfg!(windows) && program.extension().is_none() {
+        ["exe", "cmd", "bat", "com"].iter().map(|ext| program.with_extension(ext).into_os_string()).collect()
+    } else {
+        vec![program.as_os_str().to_owned()]
+    };
+    path.iter()
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|file| file.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_program() {
+        let dir = std::env::temp_dir().join(format!("robsidian_shells_{}", std::process::id()));
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let name = if cfg!(windows) { "myshell.exe" } else { "myshell" };
+        std::fs::write(bin.join(name), "").unwrap();
+
+        let path = [dir.join("missing"), bin.clone()];
+        assert_eq!(find_program("myshell", &path), Some(bin.join(name)));
+        assert_eq!(find_program("othershell", &path), None);
+        let full = bin.join(name);
+        assert_eq!(find_program(&full.to_string_lossy(), &[]), Some(full));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -3,31 +3,59 @@
 //! This module provides rendering functions for different markdown blocks,
 //! used by the live preview editor to display formatted content.
 
-use eframe::egui::{self, Color32, FontId, RichText, Ui};
-
-use super::markdown_blocks::{InlineSpan, ListItem, ParsedBlock, TableCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use eframe::egui::{self, Color32, FontId, Response, RichText, Ui};
+
+use super::live_preview::SimpleLivePreview;
+use super::markdown_blocks::{link_destination, InlineSpan, ListItem, ParsedBlock, TableAlignment, TableCell};
+use super::render_cache::{cached_child_notes, cached_galley, cached_inline, cached_note_preview};
+use super::source_map;
+use crate::terminal::code_runner::runner;
+use robsidian_core::file_system::format_modified;
+use robsidian_core::glossary::{definition_line, Glossary};
+use robsidian_core::navigation::split_anchor;
+use robsidian_core::note_index::NoteIndex;
+
+/// Number of blocks shown in a wiki link hover preview
+const LINK_PREVIEW_BLOCKS: usize = 6;
+
+/// Information about the note being rendered
+#[derive(Debug, Clone, Default)]
+pub struct RenderContext {
+    /// Path of the note the blocks belong to
+    pub note_path: Option<PathBuf>,
+    /// Footnote definitions of the note, keyed by label
+    pub footnotes: HashMap<String, String>,
+    /// Notes of the vault, used to resolve wiki links for hover previews
+    pub notes: Arc<NoteIndex>,
+    /// Abbreviations defined for the note
+    pub glossary: Arc<Glossary>,
+}
 
 /// Render a parsed block to the UI
-pub fn render_block(ui: &mut Ui, block: &ParsedBlock) -> Option<BlockAction> {
+pub fn render_block(ui: &mut Ui, block: &ParsedBlock, ctx: &RenderContext) -> Option<BlockAction> {
     match block {
         ParsedBlock::Heading { level, text, .. } => render_heading(ui, *level, text),
-        ParsedBlock::Paragraph { text, .. } => render_paragraph(ui, text),
+        ParsedBlock::Paragraph { text, .. } => render_paragraph(ui, text, ctx),
         ParsedBlock::CodeBlock { lang, code, .. } => render_code_block(ui, lang.as_deref(), code),
         ParsedBlock::List {
             items,
             ordered,
             start,
             ..
-        } => render_list(ui, items, *ordered, *start),
+        } => render_list(ui, items, *ordered, *start, ctx),
         ParsedBlock::WikiLink {
             target, display, ..
-        } => render_wiki_link(ui, target, display.as_deref()),
-        ParsedBlock::BlockQuote { content, .. } => render_blockquote(ui, content),
+        } => render_wiki_link(ui, target, display.as_deref(), ctx),
+        ParsedBlock::BlockQuote { content, .. } => render_blockquote(ui, content, ctx),
         ParsedBlock::HorizontalRule { .. } => {
             render_horizontal_rule(ui);
             None
         }
-        ParsedBlock::Table { headers, rows, .. } => render_table(ui, headers, rows),
+        ParsedBlock::Table { headers, rows, .. } => render_table(ui, headers, rows, ctx),
         ParsedBlock::Image {
             alt, url, title, ..
         } => render_image(ui, alt, url, title.as_deref()),
@@ -35,6 +63,10 @@ pub fn render_block(ui: &mut Ui, block: &ParsedBlock) -> Option<BlockAction> {
             ui.add_space(8.0);
             None
         }
+        ParsedBlock::FootnoteDefinition { label, text, .. } => {
+            render_footnote_definition(ui, label, text)
+        }
+        ParsedBlock::ChildNotes { .. } => render_child_notes(ui, ctx),
     }
 }
 
@@ -44,7 +76,79 @@ pub enum BlockAction {
     /// Navigate to a wiki link target
     NavigateToNote(String),
     /// Open external URL
-    OpenUrl(String),
+    OpenUrl { url: String, text: String },
+    /// Open a note by its path
+    OpenFile(PathBuf),
+    /// Jump between a footnote reference and its definition
+    JumpToFootnote { label: String, to_definition: bool },
+    /// Start editing the block
+    EditBlock,
+    /// Copy the block's markdown source to the clipboard
+    CopyBlock,
+    /// Remove the block from the note
+    DeleteBlock,
+    /// Reported after a block was deleted, with the note's content from before
+    BlockDeleted { previous_content: String },
+    /// Rewrite the block as a blockquote
+    ConvertToQuote,
+    /// Rewrite the block as a callout
+    ConvertToCallout,
+    /// Bookmark the block, giving it a `^id` first if it has none
+    BookmarkBlock,
+    /// Add or remove the bookmark of a heading or `^block` in the note
+    Bookmark(String),
+    /// Pin the block to the sidebar, giving it a `^id` first if it has none
+    PinBlock,
+    /// Pin a heading or `^block` of the note to the sidebar, or unpin it
+    Pin(String),
+    /// Run a code block's contents in the terminal
+    RunCode { lang: Option<String>, code: String },
+}
+
+/// Show the hover toolbar for a block in the top-right corner of `rect`
+pub fn render_block_toolbar(ui: &mut Ui, rect: egui::Rect, block: &ParsedBlock) -> Option<BlockAction> {
+    let mut action = None;
+
+    egui::Area::new(ui.id().with("block_toolbar"))
+        .order(egui::Order::Foreground)
+        .pivot(egui::Align2::RIGHT_TOP)
+        .fixed_pos(rect.right_top())
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style())
+                .inner_margin(egui::Margin::same(2))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 2.0;
+
+                        if let ParsedBlock::CodeBlock { lang, code, .. } = block {
+                            let runnable = runner(lang.as_deref()).is_some();
+                            if runnable && ui.small_button("▶").on_hover_text("Run in terminal").clicked() {
+                                action = Some(BlockAction::RunCode {
+                                    lang: lang.clone(),
+                                    code: code.clone(),
+                                });
+                            }
+                        }
+
+                        let buttons = [
+                            ("✏", "Edit", BlockAction::EditBlock),
+                            ("📋", "Copy", BlockAction::CopyBlock),
+                            ("❝", "Convert to quote", BlockAction::ConvertToQuote),
+                            ("💬", "Convert to callout", BlockAction::ConvertToCallout),
+                            ("🔖", "Bookmark", BlockAction::BookmarkBlock),
+                            ("📌", "Pin to the sidebar", BlockAction::PinBlock),
+                            ("🗑", "Delete block", BlockAction::DeleteBlock),
+                        ];
+                        for (icon, hover, block_action) in buttons {
+                            if ui.small_button(icon).on_hover_text(hover).clicked() {
+                                action = Some(block_action);
+                            }
+                        }
+                    });
+                });
+        });
+
+    action
 }
 
 /// Render a heading
@@ -63,13 +167,16 @@ pub fn render_heading(ui: &mut Ui, level: u8, text: &str) -> Option<BlockAction>
         _ => Color32::from_rgb(180, 180, 180),
     };
 
+    // Headings are rendered as a single label, so inline markup is dropped
+    let text = inline_spans_plain_text(&cached_inline(ui, text));
+
     ui.horizontal(|ui| {
         let rich_text = RichText::new(text)
             .font(FontId::proportional(font_size))
             .color(text_color)
             .strong();
 
-        ui.label(rich_text);
+        source_map::label(ui, rich_text);
     });
 
     // Add spacing after heading
@@ -83,52 +190,146 @@ pub fn render_heading(ui: &mut Ui, level: u8, text: &str) -> Option<BlockAction>
 }
 
 /// Render a paragraph with inline formatting
-pub fn render_paragraph(ui: &mut Ui, text: &str) -> Option<BlockAction> {
-    let spans = super::markdown_blocks::parse_inline(text);
+pub fn render_paragraph(ui: &mut Ui, text: &str, ctx: &RenderContext) -> Option<BlockAction> {
+    if text.lines().all(|line| definition_line(line).is_some()) {
+        render_definitions(ui, text);
+        return None;
+    }
+    let spans = cached_inline(ui, text);
+
+    let action = ui
+        .horizontal_wrapped(|ui| render_inline_spans(ui, &spans, ctx))
+        .inner;
+
+    ui.add_space(8.0);
+    action
+}
+
+/// Render inline spans into the current horizontal layout
+pub fn render_inline_spans(
+    ui: &mut Ui,
+    spans: &[InlineSpan],
+    ctx: &RenderContext,
+) -> Option<BlockAction> {
     let mut action = None;
 
-    ui.horizontal_wrapped(|ui| {
-        for span in &spans {
-            match span {
-                InlineSpan::Text(t) => {
-                    ui.label(t);
+    for span in spans {
+        match span {
+            InlineSpan::Text(t) => render_text(ui, t, &ctx.glossary),
+            InlineSpan::WikiLink { target, display } => {
+                let link_text = display.as_deref().unwrap_or(target);
+                let source = match display {
+                    Some(display) => format!("[[{}|{}]]", target, display),
+                    None => format!("[[{}]]", target),
+                };
+                let response = source_map::link(ui, link_text, source);
+                if response.clicked() {
+                    action = Some(BlockAction::NavigateToNote(target.clone()));
                 }
-                InlineSpan::WikiLink { target, display } => {
-                    let link_text = display.as_deref().unwrap_or(target);
-                    let response = ui.link(link_text);
-                    if response.clicked() {
-                        action = Some(BlockAction::NavigateToNote(target.clone()));
-                    }
-                    if response.hovered() {
-                        response.on_hover_text(format!("Open: {}", target));
-                    }
-                }
-                InlineSpan::Code(code) => {
-                    let text = RichText::new(code)
-                        .font(FontId::monospace(14.0))
-                        .background_color(Color32::from_rgb(45, 45, 45));
-                    ui.label(text);
-                }
-                InlineSpan::Bold(t) => {
-                    ui.label(RichText::new(t).strong());
-                }
-                InlineSpan::Italic(t) => {
-                    ui.label(RichText::new(t).italics());
+                show_link_hover(ui, response, target, ctx);
+            }
+            InlineSpan::Code(code) => {
+                let text = RichText::new(code)
+                    .font(FontId::monospace(14.0))
+                    .background_color(Color32::from_rgb(45, 45, 45));
+                source_map::label(ui, text);
+            }
+            InlineSpan::Bold(t) => {
+                source_map::label(ui, RichText::new(t).strong());
+            }
+            InlineSpan::Italic(t) => {
+                source_map::label(ui, RichText::new(t).italics());
+            }
+            InlineSpan::BoldItalic(t) => {
+                source_map::label(ui, RichText::new(t).strong().italics());
+            }
+            InlineSpan::Strikethrough(t) => {
+                source_map::label(ui, RichText::new(t).strikethrough());
+            }
+            InlineSpan::Highlight(t) => {
+                source_map::label(
+                    ui,
+                    RichText::new(t)
+                        .color(Color32::from_rgb(30, 30, 30))
+                        .background_color(Color32::from_rgb(230, 200, 90)),
+                );
+            }
+            InlineSpan::Link { text, url } => {
+                let response = source_map::link(ui, text, format!("[{}]({})", text, link_destination(url)));
+                if response.clicked() {
+                    action = Some(BlockAction::OpenUrl { url: url.clone(), text: text.clone() });
                 }
-                InlineSpan::Link { text, url } => {
-                    let response = ui.link(text);
-                    if response.clicked() {
-                        action = Some(BlockAction::OpenUrl(url.clone()));
-                    }
+            }
+            InlineSpan::FootnoteRef(label) => {
+                if let Some(a) = render_footnote_ref(ui, label, ctx) {
+                    action = Some(a);
                 }
             }
+            InlineSpan::Math(tex) => {
+                let text = RichText::new(tex)
+                    .font(FontId::monospace(14.0))
+                    .italics()
+                    .color(Color32::from_rgb(190, 170, 230));
+                source_map::label_from(ui, text, format!("${}$", tex));
+            }
         }
-    });
+    }
 
-    ui.add_space(8.0);
     action
 }
 
+/// Render plain text, with the abbreviations of the glossary underlined by
+/// dots and their definitions shown on hover
+fn render_text(ui: &mut Ui, text: &str, glossary: &Glossary) {
+    let mut pos = 0;
+    for (range, definition) in glossary.occurrences(text) {
+        if range.start > pos {
+            source_map::label(ui, &text[pos..range.start]);
+        }
+        let response = source_map::label(ui, &text[range.clone()]).on_hover_text(definition);
+        let underline = [response.rect.left_bottom(), response.rect.right_bottom()];
+        let color = ui.visuals().weak_text_color();
+        ui.painter().extend(egui::Shape::dotted_line(&underline, color, 3.0, 0.75));
+        pos = range.end;
+    }
+    if pos < text.len() {
+        source_map::label(ui, &text[pos..]);
+    }
+}
+
+/// Render a paragraph of `*[TERM]: definition` lines as a list of terms
+fn render_definitions(ui: &mut Ui, text: &str) {
+    for (term, definition) in text.lines().filter_map(definition_line) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(term).strong());
+            ui.label(RichText::new(definition).weak());
+        });
+    }
+    ui.add_space(8.0);
+}
+
+/// Get the text that inline spans display, without any markup
+fn inline_spans_plain_text(spans: &[InlineSpan]) -> String {
+    spans
+        .iter()
+        .map(|span| match span {
+            InlineSpan::Text(t)
+            | InlineSpan::Code(t)
+            | InlineSpan::Bold(t)
+            | InlineSpan::Italic(t)
+            | InlineSpan::BoldItalic(t)
+            | InlineSpan::Strikethrough(t)
+            | InlineSpan::Highlight(t)
+            | InlineSpan::Math(t) => t.clone(),
+            InlineSpan::WikiLink { target, display } => {
+                display.clone().unwrap_or_else(|| target.clone())
+            }
+            InlineSpan::Link { text, .. } => text.clone(),
+            InlineSpan::FootnoteRef(label) => format!("[{}]", label),
+        })
+        .collect()
+}
+
 /// Render a code block with optional syntax highlighting
 pub fn render_code_block(ui: &mut Ui, lang: Option<&str>, code: &str) -> Option<BlockAction> {
     let bg_color = Color32::from_rgb(40, 40, 40);
@@ -155,11 +356,23 @@ pub fn render_code_block(ui: &mut Ui, lang: Option<&str>, code: &str) -> Option<
 
             // Code content
             ui.horizontal_wrapped(|ui| {
-                ui.label(
-                    RichText::new(code)
+                let galley = cached_galley(ui, "code_block", code, ui.available_width(), |wrap_width| {
+                    // Laid out as a label starting a wrapped row would be
+                    let text = RichText::new(code)
                         .font(FontId::monospace(14.0))
-                        .color(Color32::from_rgb(200, 200, 200)),
-                );
+                        .color(Color32::from_rgb(200, 200, 200));
+                    let mut job = Arc::unwrap_or_clone(egui::WidgetText::from(text).into_layout_job(
+                        ui.style(),
+                        egui::FontSelection::Default,
+                        ui.text_valign(),
+                    ));
+                    job.wrap.max_width = wrap_width;
+                    job.first_row_min_height = ui.cursor().height();
+                    job.halign = egui::Align::Min;
+                    job.justify = false;
+                    job
+                });
+                source_map::label(ui, galley);
             });
         });
 
@@ -173,6 +386,24 @@ pub fn render_list(
     items: &[ListItem],
     ordered: bool,
     start: Option<u64>,
+    ctx: &RenderContext,
+) -> Option<BlockAction> {
+    let action = render_list_items(ui, items, ordered, start, 0, ctx);
+    ui.add_space(8.0);
+    action
+}
+
+/// Bullets used for unordered lists, cycling with nesting depth
+const LIST_BULLETS: [&str; 3] = ["•", "◦", "▪"];
+
+/// Render list items, recursing into sublists one indentation level deeper
+fn render_list_items(
+    ui: &mut Ui,
+    items: &[ListItem],
+    ordered: bool,
+    start: Option<u64>,
+    depth: usize,
+    ctx: &RenderContext,
 ) -> Option<BlockAction> {
     let mut action = None;
     let start_num = start.unwrap_or(1);
@@ -180,7 +411,7 @@ pub fn render_list(
     for (idx, item) in items.iter().enumerate() {
         ui.horizontal(|ui| {
             // Indent
-            ui.add_space(16.0);
+            ui.add_space(16.0 * (depth + 1) as f32);
 
             // Bullet or number
             if let Some(checked) = item.checkbox {
@@ -199,7 +430,7 @@ pub fn render_list(
                 );
             } else {
                 ui.label(
-                    RichText::new("•")
+                    RichText::new(LIST_BULLETS[depth % LIST_BULLETS.len()])
                         .color(Color32::from_rgb(150, 150, 150)),
                 );
             }
@@ -207,49 +438,37 @@ pub fn render_list(
             ui.add_space(4.0);
 
             // Item text (with inline parsing)
-            let spans = super::markdown_blocks::parse_inline(&item.text);
-            for span in &spans {
-                match span {
-                    InlineSpan::Text(t) => {
-                        ui.label(t);
-                    }
-                    InlineSpan::WikiLink { target, display } => {
-                        let link_text = display.as_deref().unwrap_or(target);
-                        let response = ui.link(link_text);
-                        if response.clicked() {
-                            action = Some(BlockAction::NavigateToNote(target.clone()));
-                        }
-                    }
-                    InlineSpan::Code(code) => {
-                        let text = RichText::new(code)
-                            .font(FontId::monospace(14.0))
-                            .background_color(Color32::from_rgb(45, 45, 45));
-                        ui.label(text);
-                    }
-                    _ => {}
-                }
+            let spans = cached_inline(ui, &item.text);
+            if let Some(a) = render_inline_spans(ui, &spans, ctx) {
+                action = Some(a);
             }
         });
 
         // Render nested items (if any)
         if !item.children.is_empty() {
-            ui.horizontal(|ui| {
-                ui.add_space(16.0);
-                ui.vertical(|ui| {
-                    if let Some(child_action) = render_list(ui, &item.children, ordered, None) {
-                        action = Some(child_action);
-                    }
-                });
-            });
+            if let Some(child_action) = render_list_items(
+                ui,
+                &item.children,
+                item.children_ordered,
+                item.children_start,
+                depth + 1,
+                ctx,
+            ) {
+                action = Some(child_action);
+            }
         }
     }
 
-    ui.add_space(8.0);
     action
 }
 
 /// Render a wiki link
-pub fn render_wiki_link(ui: &mut Ui, target: &str, display: Option<&str>) -> Option<BlockAction> {
+pub fn render_wiki_link(
+    ui: &mut Ui,
+    target: &str,
+    display: Option<&str>,
+    ctx: &RenderContext,
+) -> Option<BlockAction> {
     let link_text = display.unwrap_or(target);
     let mut action = None;
 
@@ -264,19 +483,135 @@ pub fn render_wiki_link(ui: &mut Ui, target: &str, display: Option<&str>) -> Opt
             action = Some(BlockAction::NavigateToNote(target.to_string()));
         }
 
-        if response.hovered() {
-            response.on_hover_text(format!("Open note: {}", target));
+        show_link_hover(ui, response, target, ctx);
+    });
+
+    action
+}
+
+/// Hover popup for a wiki link: the first blocks of the target note while
+/// Ctrl is held, otherwise just the target name
+fn show_link_hover(ui: &Ui, response: Response, target: &str, ctx: &RenderContext) {
+    if !response.hovered() {
+        return;
+    }
+
+    let (note, _) = split_anchor(target);
+    let preview_path = Some(note)
+        .filter(|_| ui.input(|i| i.modifiers.command))
+        .and_then(|note| ctx.notes.resolve(note));
+
+    match preview_path.and_then(|path| cached_note_preview(ui, &path)) {
+        Some(preview) => {
+            response.on_hover_ui(|ui| {
+                ui.set_max_width(420.0);
+                ui.label(RichText::new(&preview.title).strong());
+                ui.separator();
+                SimpleLivePreview::show(ui, &preview.body, Some(LINK_PREVIEW_BLOCKS));
+            });
+        }
+        None => {
+            response.on_hover_text(format!("Open note: {} (hold Ctrl to preview)", target));
+        }
+    }
+}
+
+/// Render a footnote reference as a superscript link with a hover preview
+fn render_footnote_ref(ui: &mut Ui, label: &str, ctx: &RenderContext) -> Option<BlockAction> {
+    let text = RichText::new(format!("[{}]", label)).small().raised();
+    let response = source_map::link(ui, text, format!("[^{}]", label));
+    let clicked = response.clicked();
+
+    let hover_text = ctx
+        .footnotes
+        .get(label)
+        .cloned()
+        .unwrap_or_else(|| format!("Missing footnote: {}", label));
+    response.on_hover_text(hover_text);
+
+    clicked.then(|| BlockAction::JumpToFootnote {
+        label: label.to_string(),
+        to_definition: true,
+    })
+}
+
+/// Render a footnote definition with a link back to its reference
+pub fn render_footnote_definition(ui: &mut Ui, label: &str, text: &str) -> Option<BlockAction> {
+    let mut action = None;
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(
+            RichText::new(format!("{}.", label))
+                .font(FontId::proportional(12.0))
+                .color(Color32::from_rgb(150, 150, 150)),
+        );
+        let spans = cached_inline(ui, text);
+        ui.label(RichText::new(inline_spans_plain_text(&spans)).font(FontId::proportional(12.0)));
+
+        if ui
+            .link(RichText::new("\u{21A9}").small())
+            .on_hover_text("Back to reference")
+            .clicked()
+        {
+            action = Some(BlockAction::JumpToFootnote {
+                label: label.to_string(),
+                to_definition: false,
+            });
         }
     });
 
+    ui.add_space(4.0);
+    action
+}
+
+/// Render the live list of notes in the current note's folder
+pub fn render_child_notes(ui: &mut Ui, ctx: &RenderContext) -> Option<BlockAction> {
+    let Some(note_path) = &ctx.note_path else {
+        return None;
+    };
+
+    let children = cached_child_notes(ui, note_path);
+    let mut action = None;
+
+    if children.is_empty() {
+        ui.label(
+            RichText::new("No notes in this folder")
+                .italics()
+                .color(Color32::from_rgb(128, 128, 128)),
+        );
+    }
+
+    for child in children.iter() {
+        ui.horizontal(|ui| {
+            ui.add_space(16.0);
+            ui.label(RichText::new("•").color(Color32::from_rgb(150, 150, 150)));
+            ui.add_space(4.0);
+
+            if ui.link(&child.title).clicked() {
+                action = Some(BlockAction::OpenFile(child.path.clone()));
+            }
+
+            ui.label(
+                RichText::new(format_modified(child.modified))
+                    .font(FontId::proportional(12.0))
+                    .color(Color32::from_rgb(128, 128, 128)),
+            );
+        });
+    }
+
+    ui.add_space(8.0);
     action
 }
 
 /// Render a blockquote
-pub fn render_blockquote(ui: &mut Ui, content: &[ParsedBlock]) -> Option<BlockAction> {
+pub fn render_blockquote(
+    ui: &mut Ui,
+    content: &[ParsedBlock],
+    ctx: &RenderContext,
+) -> Option<BlockAction> {
     let mut action = None;
 
-    egui::Frame::none()
+    let frame_rect = egui::Frame::none()
         .fill(Color32::from_rgb(35, 35, 40))
         .inner_margin(egui::Margin {
             left: 12,
@@ -285,25 +620,21 @@ pub fn render_blockquote(ui: &mut Ui, content: &[ParsedBlock]) -> Option<BlockAc
             bottom: 8,
         })
         .show(ui, |ui| {
-            // Left border effect
-            let rect = ui.max_rect();
-            ui.painter().rect_filled(
-                egui::Rect::from_min_size(
-                    rect.min,
-                    egui::vec2(4.0, rect.height()),
-                ),
-                0.0,
-                Color32::from_rgb(100, 100, 120),
-            );
-
-            ui.add_space(8.0);
-
             for block in content {
-                if let Some(a) = render_block(ui, block) {
+                if let Some(a) = render_block(ui, block, ctx) {
                     action = Some(a);
                 }
             }
-        });
+        })
+        .response
+        .rect;
+
+    // Left border, painted once the quote's height (including nested quotes) is known
+    ui.painter().rect_filled(
+        egui::Rect::from_min_size(frame_rect.min, egui::vec2(4.0, frame_rect.height())),
+        0.0,
+        Color32::from_rgb(100, 100, 120),
+    );
 
     ui.add_space(8.0);
     action
@@ -321,10 +652,12 @@ pub fn render_table(
     ui: &mut Ui,
     headers: &[TableCell],
     rows: &[Vec<TableCell>],
+    ctx: &RenderContext,
 ) -> Option<BlockAction> {
     use egui_extras::{Column, TableBuilder};
 
     let num_cols = headers.len().max(1);
+    let mut action = None;
 
     TableBuilder::new(ui)
         .striped(true)
@@ -332,7 +665,10 @@ pub fn render_table(
         .header(20.0, |mut header| {
             for cell in headers {
                 header.col(|ui| {
-                    ui.strong(&cell.content);
+                    ui.visuals_mut().override_text_color = Some(ui.visuals().strong_text_color());
+                    if let Some(a) = render_table_cell(ui, cell, ctx) {
+                        action = Some(a);
+                    }
                 });
             }
         })
@@ -341,7 +677,9 @@ pub fn render_table(
                 body.row(18.0, |mut row_ui| {
                     for cell in row {
                         row_ui.col(|ui| {
-                            ui.label(&cell.content);
+                            if let Some(a) = render_table_cell(ui, cell, ctx) {
+                                action = Some(a);
+                            }
                         });
                     }
                 });
@@ -349,7 +687,33 @@ pub fn render_table(
         });
 
     ui.add_space(8.0);
-    None
+    action
+}
+
+/// Render a single table cell with its column alignment
+fn render_table_cell(ui: &mut Ui, cell: &TableCell, ctx: &RenderContext) -> Option<BlockAction> {
+    let spans = cached_inline(ui, &cell.content);
+
+    // Inline spans are laid out left to right, so alignment is applied by
+    // padding with the free space left next to the measured text
+    let font = egui::TextStyle::Body.resolve(ui.style());
+    let text_width = cached_galley(ui, "table_cell", (&cell.content, &font), f32::INFINITY, |wrap_width| {
+        egui::text::LayoutJob::simple(inline_spans_plain_text(&spans), font.clone(), Color32::WHITE, wrap_width)
+    })
+    .size()
+    .x;
+    let free = (ui.available_width() - text_width).max(0.0);
+
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        match cell.alignment {
+            TableAlignment::Left => {}
+            TableAlignment::Center => ui.add_space(free / 2.0),
+            TableAlignment::Right => ui.add_space(free),
+        }
+        render_inline_spans(ui, &spans, ctx)
+    })
+    .inner
 }
 
 /// Render an image (placeholder for now)
@@ -0,0 +1,104 @@
+//! Clipboard history picker
+//!
+//! Lists recently copied text, newest first; the picked entry is pasted into
+//! the active note.
+
+use eframe::egui::{self, Key};
+
+use robsidian_core::clipboard_history::ClipboardHistory;
+
+/// Characters of an entry shown in the list
+const PREVIEW_CHARS: usize = 80;
+
+/// One line preview of an entry
+fn preview(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
+
+/// Clipboard history picker state
+#[derive(Debug, Default)]
+pub struct ClipboardPicker {
+    open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl ClipboardPicker {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Show the picker, returning the text picked this frame
+    pub fn show(&mut self, ctx: &egui::Context, history: &ClipboardHistory) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let query = self.query.to_lowercase();
+        let matches: Vec<&String> = history.entries().filter(|entry| entry.to_lowercase().contains(&query)).collect();
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = None;
+        let mut close = false;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                close = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) && self.selected + 1 < matches.len() {
+                self.selected += 1;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::Enter) {
+                picked = matches.get(self.selected).map(|entry| (*entry).clone());
+            }
+        });
+
+        egui::Window::new("Clipboard History")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(500.0, 0.0))
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Find copied text...")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.weak("Nothing copied yet");
+                    }
+                    for (idx, entry) in matches.iter().enumerate() {
+                        let response = ui.selectable_label(idx == self.selected, preview(entry));
+                        if response.clicked() {
+                            picked = Some((*entry).clone());
+                        }
+                        if entry.contains('\n') || entry.chars().count() > PREVIEW_CHARS {
+                            response.on_hover_text(entry.as_str());
+                        }
+                    }
+                });
+            });
+
+        if picked.is_some() || close {
+            self.open = false;
+        }
+        picked
+    }
+}
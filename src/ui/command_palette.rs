@@ -0,0 +1,400 @@
+//! Command palette
+//!
+//! A searchable list of app commands, opened with Ctrl+P. Commands are plain
+//! values; the app runs the chosen one through `RobsidianApp::run_command`.
+
+use eframe::egui::{self, Key};
+
+use crate::app::ViewMode;
+use crate::terminal::OTHER_SHELLS;
+use robsidian_core::text_encoding::{LineEnding, TextEncoding};
+use robsidian_core::transform::{SelectionConversion, TextTransform};
+
+/// A command that can be run from the palette
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Save,
+    /// Open an untitled note that is kept in memory until saved
+    NewScratchNote,
+    /// Edit the active note in the configured program, read-only here meanwhile
+    OpenInExternalEditor,
+    FileHistory,
+    ReviewInbox,
+    ReviewFlashcards,
+    /// Write every link of the vault as wiki links, or as Markdown links
+    ConvertLinkStyle,
+    CaptureWebArticle,
+    ExportHtml,
+    /// Spell out the first use of each abbreviation in exported notes, or stop
+    ExpandAbbreviations,
+    /// Keep `created`/`updated` in the frontmatter of saved notes, or stop
+    FrontmatterTimestamps,
+    /// Pack the active note with its attachments into a zip or HTML file
+    ShareNote,
+    PublishOverview,
+    /// Publish the active note to a Gist or web endpoint
+    PublishNote,
+    PublishSettings,
+    LinkSettings,
+    SyncNow,
+    SyncSettings,
+    /// Color folders and tags, and the notes in or with them
+    NoteColors,
+    QuickSwitcher,
+    /// Create a vault, empty or from a preset, and open it
+    NewVault,
+    /// Save the settings, terminal themes, scripts and enabled plugins to
+    /// one file
+    ExportSettings,
+    /// Take over the picked parts of an exported settings file
+    ImportSettings,
+    /// Open another vault in a second window
+    OpenSecondVault,
+    CopyToVault,
+    MoveToVault,
+    ToggleStar,
+    TogglePin,
+    /// Encrypt the active note, or stop encrypting it
+    ToggleEncryption,
+    ToggleSidebar,
+    ToggleTerminal,
+    /// Open a terminal tab running the default shell
+    TerminalNew,
+    /// Open a terminal tab running another shell
+    TerminalNewShell { name: &'static str, program: &'static str },
+    /// Drop the current terminal tab's scrollback and clear its screen
+    TerminalClear,
+    /// Kill the process running in the current terminal tab
+    TerminalKill,
+    TerminalRename,
+    TerminalNextTab,
+    TerminalPreviousTab,
+    /// Search the scrollback and screen of the shown terminal
+    TerminalFind,
+    /// Put the terminal's selection or last output into the active note
+    TerminalInsertOutput,
+    /// Split the terminal's focused pane, side by side or stacked
+    TerminalSplitRight,
+    TerminalSplitDown,
+    /// Open a terminal tab on a server
+    TerminalSshConnect,
+    TerminalSettings,
+    /// Make the PTY terminal's text larger
+    TerminalFontLarger,
+    TerminalFontSmaller,
+    TerminalFontReset,
+    /// Present the active note in a separate window
+    TogglePresentation,
+    WebServer,
+    /// Timings of background work
+    Diagnostics,
+    InsertTemplate,
+    /// Paste an older entry of the clipboard history
+    PasteFromHistory,
+    GoBack,
+    GoForward,
+    SetViewMode(ViewMode),
+    Transform(TextTransform),
+    Convert(SelectionConversion),
+    /// Convert to a code block, asking for the language first
+    ConvertToCodeBlock,
+    SetLineEnding(LineEnding),
+    SetEncoding(TextEncoding),
+    InstallPlugin,
+    CheckPluginUpdates,
+    ReloadScripts,
+    OpenScriptsFolder,
+    /// A command registered by a user script
+    Script { script: String, name: String },
+    /// A command registered by an enabled plugin
+    Plugin { plugin: String, name: String },
+}
+
+impl Command {
+    /// Every command shown in the palette
+    pub fn all() -> Vec<Command> {
+        let mut commands = vec![
+            Command::Save,
+            Command::NewScratchNote,
+            Command::OpenInExternalEditor,
+            Command::FileHistory,
+            Command::ReviewInbox,
+            Command::ReviewFlashcards,
+            Command::ConvertLinkStyle,
+            Command::CaptureWebArticle,
+            Command::ExportHtml,
+            Command::ExpandAbbreviations,
+            Command::FrontmatterTimestamps,
+            Command::ShareNote,
+            Command::PublishOverview,
+            Command::PublishNote,
+            Command::PublishSettings,
+            Command::LinkSettings,
+            Command::SyncNow,
+            Command::SyncSettings,
+            Command::NoteColors,
+            Command::QuickSwitcher,
+            Command::NewVault,
+            Command::ExportSettings,
+            Command::ImportSettings,
+            Command::OpenSecondVault,
+            Command::CopyToVault,
+            Command::MoveToVault,
+            Command::ToggleStar,
+            Command::TogglePin,
+            Command::ToggleEncryption,
+            Command::ToggleSidebar,
+            Command::ToggleTerminal,
+            Command::TerminalNew,
+            Command::TerminalClear,
+            Command::TerminalKill,
+            Command::TerminalRename,
+            Command::TerminalNextTab,
+            Command::TerminalPreviousTab,
+            Command::TerminalFind,
+            Command::TerminalInsertOutput,
+            Command::TerminalSplitRight,
+            Command::TerminalSplitDown,
+            Command::TerminalSshConnect,
+            Command::TerminalSettings,
+            Command::TerminalFontLarger,
+            Command::TerminalFontSmaller,
+            Command::TerminalFontReset,
+            Command::TogglePresentation,
+            Command::WebServer,
+            Command::Diagnostics,
+            Command::InsertTemplate,
+            Command::PasteFromHistory,
+            Command::InstallPlugin,
+            Command::CheckPluginUpdates,
+            Command::ReloadScripts,
+            Command::OpenScriptsFolder,
+            Command::GoBack,
+            Command::GoForward,
+        ];
+        commands.extend(OTHER_SHELLS.iter().map(|&(name, program)| Command::TerminalNewShell { name, program }));
+        commands.extend(ViewMode::DOCUMENT_MODES.map(Command::SetViewMode));
+        commands.push(Command::SetViewMode(ViewMode::TerminalWithTree));
+        commands.extend(TextTransform::ALL.map(Command::Transform));
+        commands.extend(
+            [
+                SelectionConversion::Quote,
+                SelectionConversion::Callout,
+                SelectionConversion::NumberedList,
+            ]
+            .map(Command::Convert),
+        );
+        commands.push(Command::ConvertToCodeBlock);
+        commands.extend(LineEnding::ALL.map(Command::SetLineEnding));
+        commands.extend(TextEncoding::ALL.map(Command::SetEncoding));
+        commands
+    }
+
+    /// Name shown in the palette
+    pub fn label(&self) -> String {
+        match self {
+            Command::Save => "File: Save".to_string(),
+            Command::NewScratchNote => "File: New Untitled Note".to_string(),
+            Command::OpenInExternalEditor => "File: Open in External Editor".to_string(),
+            Command::FileHistory => "File: File History".to_string(),
+            Command::ReviewInbox => "File: Review Inbox".to_string(),
+            Command::ReviewFlashcards => "File: Review Flashcards".to_string(),
+            Command::ConvertLinkStyle => "File: Convert Link Style...".to_string(),
+            Command::CaptureWebArticle => "File: Capture Web Article".to_string(),
+            Command::ExportHtml => "File: Export Vault to HTML".to_string(),
+            Command::ExpandAbbreviations => "File: Toggle Expanding Abbreviations on Export".to_string(),
+            Command::FrontmatterTimestamps => "File: Toggle Frontmatter Timestamps".to_string(),
+            Command::ShareNote => "File: Share Note".to_string(),
+            Command::PublishOverview => "File: Publish Overview".to_string(),
+            Command::PublishNote => "Publish: Publish Note to the Web".to_string(),
+            Command::PublishSettings => "Publish: Settings".to_string(),
+            Command::LinkSettings => "Links: External Link Settings".to_string(),
+            Command::SyncNow => "Sync: Sync Now".to_string(),
+            Command::SyncSettings => "Sync: Settings".to_string(),
+            Command::NoteColors => "View: Folder and Tag Colors".to_string(),
+            Command::QuickSwitcher => "File: Quick Switcher".to_string(),
+            Command::NewVault => "File: New Vault...".to_string(),
+            Command::ExportSettings => "File: Export Settings...".to_string(),
+            Command::ImportSettings => "File: Import Settings...".to_string(),
+            Command::OpenSecondVault => "File: Open Vault in Second Window".to_string(),
+            Command::CopyToVault => "File: Copy Note to Another Vault".to_string(),
+            Command::MoveToVault => "File: Move Note to Another Vault".to_string(),
+            Command::ToggleStar => "File: Star/Unstar Note".to_string(),
+            Command::TogglePin => "Tab: Pin/Unpin".to_string(),
+            Command::ToggleEncryption => "Note: Encrypt/Decrypt".to_string(),
+            Command::ToggleSidebar => "View: Toggle Sidebar".to_string(),
+            Command::ToggleTerminal => "View: Toggle Terminal".to_string(),
+            Command::TerminalNew => "Terminal: New Terminal (Default Shell)".to_string(),
+            Command::TerminalNewShell { name, .. } => format!("Terminal: New Terminal ({})", name),
+            Command::TerminalClear => "Terminal: Clear".to_string(),
+            Command::TerminalKill => "Terminal: Kill Current Process".to_string(),
+            Command::TerminalRename => "Terminal: Rename Tab…".to_string(),
+            Command::TerminalNextTab => "Terminal: Next Tab".to_string(),
+            Command::TerminalPreviousTab => "Terminal: Previous Tab".to_string(),
+            Command::TerminalFind => "Terminal: Find".to_string(),
+            Command::TerminalInsertOutput => "Terminal: Insert Output into Note".to_string(),
+            Command::TerminalSplitRight => "Terminal: Split Right".to_string(),
+            Command::TerminalSplitDown => "Terminal: Split Down".to_string(),
+            Command::TerminalSshConnect => "Terminal: Connect over SSH…".to_string(),
+            Command::TerminalSettings => "Terminal: Settings".to_string(),
+            Command::TerminalFontLarger => "Terminal: Increase Font Size".to_string(),
+            Command::TerminalFontSmaller => "Terminal: Decrease Font Size".to_string(),
+            Command::TerminalFontReset => "Terminal: Reset Font Size".to_string(),
+            Command::TogglePresentation => "View: Toggle Presentation Window".to_string(),
+            Command::WebServer => "View: Web View for Other Devices".to_string(),
+            Command::Diagnostics => "View: Diagnostics".to_string(),
+            Command::InsertTemplate => "Edit: Insert Template".to_string(),
+            Command::PasteFromHistory => "Edit: Paste from Clipboard History".to_string(),
+            Command::GoBack => "Go: Back".to_string(),
+            Command::GoForward => "Go: Forward".to_string(),
+            Command::SetViewMode(mode) => format!("View: {}", mode.label()),
+            Command::Transform(transform) => format!("Transform Selection: {}", transform.label()),
+            Command::Convert(conversion) => format!("Convert Selection: {}", conversion.label()),
+            Command::ConvertToCodeBlock => "Convert Selection: Code Block...".to_string(),
+            Command::SetLineEnding(line_ending) => format!("File: Convert Line Endings to {}", line_ending.label()),
+            Command::SetEncoding(encoding) => format!("File: Save with Encoding {}", encoding.label()),
+            Command::InstallPlugin => "Plugins: Install from URL".to_string(),
+            Command::CheckPluginUpdates => "Plugins: Check for Updates".to_string(),
+            Command::ReloadScripts => "Scripts: Reload".to_string(),
+            Command::OpenScriptsFolder => "Scripts: Open Folder".to_string(),
+            Command::Script { name, .. } => format!("Script: {}", name),
+            Command::Plugin { name, .. } => format!("Plugin: {}", name),
+        }
+    }
+
+    /// Keyboard shortcut hint, if the command has one
+    pub fn shortcut(&self) -> Option<&'static str> {
+        match self {
+            Command::Save => Some("Ctrl+S"),
+            Command::NewScratchNote => Some("Ctrl+N"),
+            Command::QuickSwitcher => Some("Ctrl+O"),
+            Command::PasteFromHistory => Some("Ctrl+Shift+V"),
+            Command::ToggleSidebar => Some("Ctrl+B"),
+            Command::ToggleTerminal => Some("Ctrl+`"),
+            Command::TerminalFind => Some("Ctrl+Shift+F"),
+            Command::TerminalNextTab => Some("Ctrl+PageDown"),
+            Command::TerminalPreviousTab => Some("Ctrl+PageUp"),
+            Command::GoBack => Some("Alt+Left"),
+            Command::GoForward => Some("Alt+Right"),
+            _ => None,
+        }
+    }
+}
+
+/// Whether every word of the query appears in the label (case-insensitive)
+fn matches_query(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query
+        .to_lowercase()
+        .split_whitespace()
+        .all(|word| label.contains(word))
+}
+
+/// Command palette state
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    query: String,
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// Open the palette with an empty query
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Show the palette, returning the command picked this frame. `extra`
+    /// commands (from plugins) are listed after the app's own.
+    pub fn show(&mut self, ctx: &egui::Context, extra: Vec<Command>) -> Option<Command> {
+        if !self.open {
+            return None;
+        }
+
+        let commands: Vec<Command> = Command::all()
+            .into_iter()
+            .chain(extra)
+            .filter(|command| matches_query(&command.label(), &self.query))
+            .collect();
+        self.selected = self.selected.min(commands.len().saturating_sub(1));
+
+        let mut picked = None;
+        let mut moved = false;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                self.open = false;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) && self.selected + 1 < commands.len() {
+                self.selected += 1;
+                moved = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                self.selected = self.selected.saturating_sub(1);
+                moved = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::Enter) {
+                picked = commands.get(self.selected).cloned();
+            }
+        });
+
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(480.0, 0.0))
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command...")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if commands.is_empty() {
+                        ui.weak("No matching commands");
+                    }
+                    for (idx, command) in commands.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let row = ui.selectable_label(idx == self.selected, command.label());
+                            if moved && idx == self.selected {
+                                row.scroll_to_me(None);
+                            }
+                            if row.clicked() {
+                                picked = Some(command.clone());
+                            }
+                            if let Some(shortcut) = command.shortcut() {
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.weak(shortcut);
+                                });
+                            }
+                        });
+                    }
+                });
+            });
+
+        if picked.is_some() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_query() {
+        assert!(matches_query("Transform Selection: Title Case", "title sel"));
+        assert!(!matches_query("View: Toggle Sidebar", "terminal"));
+    }
+}
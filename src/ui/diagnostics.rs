@@ -0,0 +1,74 @@
+//! Diagnostics window
+//!
+//! Shows what the background scheduler has been doing: how long each kind of
+//! task took on worker threads and on the UI thread, and how often frames
+//! went over their budget.
+
+use std::time::Duration;
+
+use eframe::egui;
+
+use robsidian_core::scheduler::{Scheduler, FRAME_BUDGET};
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Diagnostics window state
+#[derive(Debug, Default)]
+pub struct DiagnosticsWindow {
+    open: bool,
+}
+
+impl DiagnosticsWindow {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn show<C: 'static>(&mut self, ctx: &egui::Context, scheduler: &Scheduler<C>) {
+        if !self.open {
+            return;
+        }
+
+        egui::Window::new("Diagnostics").open(&mut self.open).default_width(520.0).show(ctx, |ui| {
+            let (frames, over_budget) = scheduler.frames();
+            let (running, held, slices) = scheduler.queued();
+            ui.label(format!(
+                "Last frame spent {} on scheduled work (budget {}). {} of {} frames went over.",
+                millis(scheduler.last_frame()),
+                millis(FRAME_BUDGET),
+                over_budget,
+                frames
+            ));
+            ui.label(format!("{} jobs on workers, {} waiting for the queue, {} slices waiting", running, held, slices));
+            ui.separator();
+
+            if scheduler.stats().is_empty() {
+                ui.weak("No work has been scheduled yet.");
+                return;
+            }
+            egui::Grid::new("diagnostics_grid").num_columns(6).striped(true).show(ui, |ui| {
+                for heading in ["Task", "Jobs", "Worker time", "Runs", "UI time", "Longest"] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for (name, stats) in scheduler.stats() {
+                    ui.label(name);
+                    ui.label(stats.jobs.to_string());
+                    ui.label(millis(stats.worker_time));
+                    ui.label(stats.runs.to_string());
+                    ui.label(millis(stats.frame_time));
+                    let longest = ui.label(millis(stats.longest));
+                    if stats.longest > FRAME_BUDGET {
+                        longest.on_hover_text("Longer than a frame's budget");
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
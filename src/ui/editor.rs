@@ -1,8 +1,84 @@
 //! Markdown editor panel
 
-use eframe::egui;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use eframe::egui::{self, text::{CCursor, CCursorRange}, Key};
+
+use super::ime;
 use crate::app::RobsidianApp;
+use robsidian_core::note_index::NoteMatch;
+use robsidian_core::text_position::{byte_to_char, char_to_byte};
+
+/// Id of the source editor's text edit
+pub const SOURCE_EDITOR_ID: &str = "source_editor";
+
+/// Maximum number of wiki link suggestions shown
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Wiki link autocompletion state of the source editor
+#[derive(Debug, Default)]
+pub struct LinkCompletion {
+    /// Byte range of the partial link target typed after `[[`
+    query: Option<Range<usize>>,
+    selected: usize,
+    /// Start of a query dismissed with Escape
+    dismissed: Option<usize>,
+    /// Document the query was typed in
+    document: Option<PathBuf>,
+}
+
+impl LinkCompletion {
+    /// Forget the query when another document becomes active
+    fn track(&mut self, document: &Path) {
+        if self.document.as_deref() != Some(document) {
+            *self = Self { document: Some(document.to_path_buf()), ..Self::default() };
+        }
+    }
+
+    /// The query, if it still lies on character boundaries of `content`
+    fn query_in(&self, content: &str) -> Option<Range<usize>> {
+        self.query.clone().filter(|query| content.get(query.clone()).is_some())
+    }
+
+    /// Track the partial link target ending at `cursor`, if any
+    fn update(&mut self, content: &str, cursor: usize) {
+        let query = link_query(content, cursor).filter(|range| self.dismissed != Some(range.start));
+        if query.as_ref().map(|r| r.start) != self.query.as_ref().map(|r| r.start) {
+            self.selected = 0;
+        }
+        if query.is_none() {
+            self.dismissed = None;
+        }
+        self.query = query;
+    }
+}
+
+/// Range of the text typed after an unclosed `[[` on the cursor's line
+fn link_query(content: &str, cursor: usize) -> Option<Range<usize>> {
+    let line_start = content[..cursor].rfind('\n').map_or(0, |i| i + 1);
+    let start = line_start + content[line_start..cursor].rfind("[[")? + 2;
+    let typed = &content[start..cursor];
+    if typed.contains("]]") || typed.contains('|') || typed.contains('#') {
+        return None;
+    }
+    Some(start..cursor)
+}
+
+/// Replace the partial target with the picked note and close the link,
+/// returning the new cursor position
+fn complete_link(content: &mut String, query: Range<usize>, note_match: &NoteMatch) -> usize {
+    // Closing brackets may already be there
+    let closed = content[query.end..].starts_with("]]");
+    let mut insertion = note_match.link_text();
+    if !closed {
+        insertion.push_str("]]");
+    }
+    let cursor = query.start + insertion.len() + if closed { 2 } else { 0 };
+    content.replace_range(query, &insertion);
+    cursor
+}
 
 /// Markdown editor panel
 pub struct EditorPanel;
@@ -11,28 +87,115 @@ impl EditorPanel {
     /// Show the editor panel
     pub fn show(ui: &mut egui::Ui, app: &mut RobsidianApp) {
         ui.vertical(|ui| {
-            // Document tabs (if multiple documents open)
-            if app.documents.len() > 1 {
-                Self::show_tabs(ui, app);
-                ui.separator();
-            }
-
             // Editor area
             egui::ScrollArea::vertical()
                 .id_salt("editor_scroll")
                 .show(ui, |ui| {
                     if let Some(path) = app.active_document.clone() {
-                        if let Some(doc) = app.documents.get_mut(&path) {
+                        if let Some(mut doc) = app.documents.edit(&path) {
+                            let id = egui::Id::new(SOURCE_EDITOR_ID);
+                            let notes = Arc::clone(&app.note_index);
+                            app.link_completion.track(&path);
+
+                            // Keys driving the suggestion list are taken before the text edit sees them,
+                            // unless they belong to an input method composing text
+                            let composing = ime::composing(ui.ctx());
+                            let completion = &mut app.link_completion;
+                            let suggestions = completion
+                                .query_in(&doc.content)
+                                .map(|query| (notes.search(&doc.content[query.clone()], MAX_SUGGESTIONS), query))
+                                .filter(|(matches, _)| !matches.is_empty() && !composing);
+                            let mut accepted = None;
+                            if let Some((matches, query)) = &suggestions {
+                                ui.input_mut(|i| {
+                                    if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) {
+                                        completion.selected = (completion.selected + 1).min(matches.len() - 1);
+                                    }
+                                    if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                                        completion.selected = completion.selected.saturating_sub(1);
+                                    }
+                                    if i.consume_key(egui::Modifiers::NONE, Key::Enter)
+                                        || i.consume_key(egui::Modifiers::NONE, Key::Tab)
+                                    {
+                                        accepted = matches.get(completion.selected).map(|m| (query.clone(), *m));
+                                    }
+                                    if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                                        completion.dismissed = Some(query.start);
+                                    }
+                                });
+                            }
+                            if let Some((query, note_match)) = accepted {
+                                let cursor = complete_link(&mut doc.content, query, &note_match);
+                                doc.modified = true;
+                                app.pending_editor_selection = Some(cursor..cursor);
+                            }
+
+                            // Apply a selection requested by a command
+                            if let Some(range) = app.pending_editor_selection.take() {
+                                let mut state = egui::TextEdit::load_state(ui.ctx(), id).unwrap_or_default();
+                                state.cursor.set_char_range(Some(CCursorRange::two(
+                                    CCursor::new(byte_to_char(&doc.content, range.start)),
+                                    CCursor::new(byte_to_char(&doc.content, range.end)),
+                                )));
+                                state.store(ui.ctx(), id);
+                                ui.ctx().memory_mut(|mem| mem.request_focus(id));
+                            }
+
                             let response = egui::TextEdit::multiline(&mut doc.content)
+                                .id(id)
                                 .font(egui::TextStyle::Monospace)
                                 .code_editor()
                                 .desired_width(f32::INFINITY)
                                 .desired_rows(30)
                                 .show(ui);
 
+                            // The store parses the frontmatter again if it changed
                             if response.response.changed() {
                                 doc.modified = true;
                             }
+
+                            if let Some(cursor) = response.cursor_range {
+                                let chars = cursor.as_sorted_char_range();
+                                let selection = char_to_byte(&doc.content, chars.start)..char_to_byte(&doc.content, chars.end);
+
+                                if selection.is_empty() && response.response.has_focus() {
+                                    app.link_completion.update(&doc.content, selection.end);
+                                } else {
+                                    app.link_completion.query = None;
+                                }
+                                app.editor_selection = Some(selection);
+
+                                // Suggestions below the caret
+                                let query = app.link_completion.query_in(&doc.content);
+                                let matches = query
+                                    .as_ref()
+                                    .map(|query| notes.search(&doc.content[query.clone()], MAX_SUGGESTIONS))
+                                    .unwrap_or_default();
+                                if let (Some(query), false) = (query, matches.is_empty()) {
+                                    let caret = response.galley.pos_from_cursor(cursor.primary);
+                                    let pos = response.galley_pos + caret.left_bottom().to_vec2();
+                                    let mut clicked = None;
+                                    egui::Area::new(id.with("link_completion"))
+                                        .order(egui::Order::Foreground)
+                                        .fixed_pos(pos)
+                                        .show(ui.ctx(), |ui| {
+                                            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                                                for (idx, note_match) in matches.iter().enumerate() {
+                                                    let selected = idx == app.link_completion.selected;
+                                                    if ui.selectable_label(selected, note_match.label()).clicked() {
+                                                        clicked = Some(*note_match);
+                                                    }
+                                                }
+                                            });
+                                        });
+                                    if let Some(note_match) = clicked {
+                                        let cursor = complete_link(&mut doc.content, query, &note_match);
+                                        doc.modified = true;
+                                        app.pending_editor_selection = Some(cursor..cursor);
+                                        app.link_completion.query = None;
+                                    }
+                                }
+                            }
                         }
                     } else {
                         Self::show_welcome(ui);
@@ -41,28 +204,6 @@ impl EditorPanel {
         });
     }
 
-    /// Show document tabs
-    fn show_tabs(ui: &mut egui::Ui, app: &mut RobsidianApp) {
-        ui.horizontal(|ui| {
-            let mut paths_to_show: Vec<_> = app.documents.keys().cloned().collect();
-            paths_to_show.sort();
-
-            for path in paths_to_show {
-                let doc = app.documents.get(&path).unwrap();
-                let title = if doc.modified {
-                    format!("{}*", doc.title())
-                } else {
-                    doc.title()
-                };
-
-                let is_active = app.active_document.as_ref() == Some(&path);
-                if ui.selectable_label(is_active, title).clicked() {
-                    app.active_document = Some(path.clone());
-                }
-            }
-        });
-    }
-
     /// Show welcome screen when no document is open
     fn show_welcome(ui: &mut egui::Ui) {
         ui.vertical_centered(|ui| {
@@ -0,0 +1,127 @@
+//! Passphrase prompts for encrypted notes
+//!
+//! The unlock form replaces the editor while a locked note is active. The
+//! passphrase dialog asks for a new passphrase (entered twice) before a note
+//! or folder is encrypted.
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, Color32, Key};
+
+use robsidian_core::crypto::Passphrase;
+
+/// Shortest passphrase accepted for encrypting
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Passphrase field shown in place of a locked note
+#[derive(Debug, Default)]
+pub struct UnlockForm {
+    input: String,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+}
+
+impl UnlockForm {
+    /// Show the form, returning the passphrase once submitted
+    pub fn show(&mut self, ui: &mut egui::Ui, title: &str) -> Option<Passphrase> {
+        let mut submitted = None;
+        ui.vertical_centered(|ui| {
+            ui.add_space(60.0);
+            ui.heading(format!("🔒 {}", title));
+            ui.label("This note is encrypted. Enter the passphrase to open it.");
+            ui.add_space(8.0);
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .password(true)
+                    .hint_text("Passphrase")
+                    .desired_width(240.0),
+            );
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+            if (ui.button("Unlock").clicked() || entered) && !self.input.is_empty() {
+                submitted = Some(Passphrase::new(&std::mem::take(&mut self.input)));
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(Color32::from_rgb(224, 108, 117), error);
+            }
+        });
+        submitted
+    }
+}
+
+/// What a new passphrase is for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncryptTarget {
+    Note(PathBuf),
+    Folder(PathBuf),
+}
+
+/// Dialog asking for a new passphrase
+#[derive(Debug, Default)]
+pub struct PassphraseDialog {
+    target: Option<EncryptTarget>,
+    input: String,
+    confirm: String,
+}
+
+impl PassphraseDialog {
+    pub fn open(&mut self, target: EncryptTarget) {
+        self.target = Some(target);
+        self.input.clear();
+        self.confirm.clear();
+    }
+
+    /// Show the dialog, returning the target and passphrase once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(EncryptTarget, Passphrase)> {
+        let target = self.target.clone()?;
+        let mut open = true;
+        let mut confirmed = false;
+
+        egui::Window::new("Set Vault Passphrase")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let what = match &target {
+                    EncryptTarget::Note(path) | EncryptTarget::Folder(path) => {
+                        path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+                    }
+                };
+                ui.label(format!("Encrypt {} with a passphrase.", what));
+                ui.weak("The passphrase is not stored anywhere. Notes can't be recovered without it.");
+                ui.add_space(4.0);
+                egui::Grid::new("passphrase_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Passphrase");
+                    ui.add(egui::TextEdit::singleline(&mut self.input).password(true));
+                    ui.end_row();
+                    ui.label("Repeat");
+                    ui.add(egui::TextEdit::singleline(&mut self.confirm).password(true));
+                    ui.end_row();
+                });
+
+                let problem = if self.input.chars().count() < MIN_PASSPHRASE_LEN {
+                    Some(format!("Use at least {} characters", MIN_PASSPHRASE_LEN))
+                } else if self.input != self.confirm {
+                    Some("The passphrases don't match".to_string())
+                } else {
+                    None
+                };
+                if let Some(problem) = &problem {
+                    ui.weak(problem);
+                }
+                if ui.add_enabled(problem.is_none(), egui::Button::new("Encrypt")).clicked() {
+                    confirmed = true;
+                }
+            });
+
+        if !open {
+            self.target = None;
+        }
+        if !confirmed {
+            return None;
+        }
+        self.target = None;
+        self.confirm.clear();
+        Some((target, Passphrase::new(&std::mem::take(&mut self.input))))
+    }
+}
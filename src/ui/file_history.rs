@@ -0,0 +1,166 @@
+//! File history dialog
+//!
+//! Lists the local snapshots of a note, shows the words changed in the
+//! selected one and how it differs from the note's current content, and
+//! restores it on request.
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, Color32, RichText};
+
+use robsidian_core::diff::{diff_lines, DiffKind, WordChange};
+use robsidian_core::document_store::DocumentStore;
+use robsidian_core::snapshots::{Snapshot, SnapshotStore};
+
+/// File history dialog state
+#[derive(Debug, Default)]
+pub struct FileHistoryDialog {
+    /// Note whose history is shown; `None` while closed
+    path: Option<PathBuf>,
+    snapshots: Vec<Snapshot>,
+    selected: usize,
+    /// Content of the selected snapshot, or why it couldn't be read
+    content: Option<Result<String, String>>,
+    /// Words changed in the selected snapshot since the one before, if
+    /// they were recorded
+    changes: Option<Vec<WordChange>>,
+}
+
+impl FileHistoryDialog {
+    /// Show the history of a note
+    pub fn open(&mut self, path: PathBuf, store: &SnapshotStore) {
+        self.snapshots = store.list(&path);
+        self.path = Some(path);
+        self.select(0);
+    }
+
+    fn select(&mut self, idx: usize) {
+        self.selected = idx;
+        self.content = self
+            .snapshots
+            .get(idx)
+            .map(|snapshot| snapshot.read().map_err(|e| format!("{:#}", e)));
+        self.changes = self.snapshots.get(idx).and_then(Snapshot::changes);
+    }
+
+    /// Show the dialog, returning the note and the content to restore when
+    /// the user picks a snapshot to restore
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        documents: &DocumentStore,
+    ) -> Option<(PathBuf, String)> {
+        let path = self.path.clone()?;
+        let current = documents.get(&path).map(|doc| doc.content.as_str());
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut open = true;
+        let mut restore = None;
+        let mut select = None;
+
+        egui::Window::new(format!("File History: {}", name))
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .show(ctx, |ui| {
+                if self.snapshots.is_empty() {
+                    ui.weak("No snapshots yet. One is taken every time the note is saved.");
+                    return;
+                }
+
+                ui.horizontal_top(|ui| {
+                    ui.vertical(|ui| {
+                        ui.set_width(160.0);
+                        egui::ScrollArea::vertical().id_salt("snapshot_list").show(ui, |ui| {
+                            for (idx, snapshot) in self.snapshots.iter().enumerate() {
+                                let label = snapshot.time.format("%Y-%m-%d %H:%M:%S").to_string();
+                                if ui.selectable_label(idx == self.selected, label).clicked() {
+                                    select = Some(idx);
+                                }
+                            }
+                        });
+                    });
+                    ui.separator();
+
+                    ui.vertical(|ui| {
+                        match &self.content {
+                            Some(Ok(content)) => {
+                                if let Some(changes) = &self.changes {
+                                    show_word_changes(ui, changes);
+                                    ui.separator();
+                                }
+                                ui.horizontal(|ui| {
+                                    ui.label("Changes since this snapshot:");
+                                    if ui.button("Restore this version").clicked() {
+                                        restore = Some((path.clone(), content.clone()));
+                                    }
+                                });
+                                ui.separator();
+                                show_line_diff(ui, content, current.unwrap_or_default());
+                            }
+                            Some(Err(e)) => {
+                                ui.colored_label(Color32::from_rgb(224, 108, 117), e);
+                            }
+                            None => {}
+                        }
+                    });
+                });
+            });
+
+        if let Some(idx) = select {
+            self.select(idx);
+        }
+        if !open || restore.is_some() {
+            self.path = None;
+        }
+        restore
+    }
+}
+
+/// Words changed in a snapshot, each after the words leading up to it:
+/// removed ones struck through in red, added ones in green
+fn show_word_changes(ui: &mut egui::Ui, changes: &[WordChange]) {
+    let title = match changes.len() {
+        0 => "No changes in this version".to_string(),
+        1 => "1 change in this version".to_string(),
+        n => format!("{} changes in this version", n),
+    };
+    egui::CollapsingHeader::new(title).id_salt("word_changes").default_open(true).show(ui, |ui| {
+        egui::ScrollArea::vertical().id_salt("word_changes_scroll").max_height(160.0).show(ui, |ui| {
+            for change in changes {
+                let line = |text: &str| text.replace('\n', " ↵ ");
+                let mut job = egui::text::LayoutJob::default();
+                let font = egui::TextStyle::Monospace.resolve(ui.style());
+                let plain = egui::TextFormat::simple(font.clone(), ui.visuals().weak_text_color());
+                job.append(&format!("…{}", line(&change.before)), 0.0, plain);
+                let removed = egui::TextFormat {
+                    strikethrough: egui::Stroke::new(1.0, Color32::from_rgb(224, 108, 117)),
+                    ..egui::TextFormat::simple(font.clone(), Color32::from_rgb(224, 108, 117))
+                };
+                job.append(&line(&change.removed), 0.0, removed);
+                let added = egui::TextFormat::simple(font, Color32::from_rgb(152, 195, 121));
+                job.append(&line(&change.added), 0.0, added);
+                ui.label(job);
+            }
+        });
+    });
+}
+
+/// Line diff from `old` to `new`: removed lines in red, added ones in green
+pub fn show_line_diff(ui: &mut egui::Ui, old: &str, new: &str) {
+    let diff = diff_lines(old, new);
+    egui::ScrollArea::both().id_salt(ui.id().with("line_diff")).show(ui, |ui| {
+        if diff.iter().all(|line| line.kind == DiffKind::Unchanged) {
+            ui.weak("No differences");
+        }
+        for line in diff {
+            let (prefix, color) = match line.kind {
+                DiffKind::Unchanged => (" ", ui.visuals().weak_text_color()),
+                DiffKind::Added => ("+", Color32::from_rgb(152, 195, 121)),
+                DiffKind::Removed => ("-", Color32::from_rgb(224, 108, 117)),
+            };
+            ui.label(RichText::new(format!("{} {}", prefix, line.text)).monospace().color(color));
+        }
+    });
+}
@@ -5,7 +5,11 @@ use std::path::PathBuf;
 use eframe::egui;
 
 use crate::app::RobsidianApp;
-use crate::core::file_system::FileNode;
+use robsidian_core::file_system::FileNode;
+use robsidian_core::navigation::NavEntry;
+
+use super::pinned_blocks::show_pinned_blocks;
+use super::vault_window::{drop_target, DraggedNote};
 
 /// File tree panel
 pub struct FileTreePanel;
@@ -29,6 +33,9 @@ impl FileTreePanel {
 
             ui.separator();
 
+            Self::show_bookmarks(ui, app);
+            show_pinned_blocks(ui, app);
+
             // File tree
             egui::ScrollArea::vertical()
                 .id_salt("file_tree_scroll")
@@ -43,9 +50,73 @@ impl FileTreePanel {
                                 app.open_vault(path);
                             }
                         }
+                        if ui.button("New Vault...").clicked() {
+                            app.open_new_vault_dialog();
+                        }
                     }
                 });
         });
+        // Folders that weren't in the tree stay unrevealed
+        app.reveal_in_tree = None;
+    }
+
+    /// Starred notes of the vault above the tree, with the headings and
+    /// blocks bookmarked in each note under it
+    fn show_bookmarks(ui: &mut egui::Ui, app: &mut RobsidianApp) {
+        let Some(vault) = app.vault_path.clone() else {
+            return;
+        };
+        let notes = app.vault_settings.bookmarked_notes(&vault);
+        if notes.is_empty() {
+            return;
+        }
+
+        egui::CollapsingHeader::new("Bookmarks")
+            .id_salt("starred_notes")
+            .default_open(true)
+            .show(ui, |ui| {
+                for note in notes {
+                    let path = note.path;
+                    let name = path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let is_active = app.active_document.as_ref() == Some(&path);
+                    let icon = if note.starred { "★" } else { "📄" };
+                    let response = ui.selectable_label(is_active, format!("{} {}", icon, name));
+                    if response.clicked() {
+                        app.open_document(path.clone());
+                    }
+                    if note.starred {
+                        response.context_menu(|ui| {
+                            if ui.button("Unstar").clicked() {
+                                app.toggle_star(&path);
+                                ui.close();
+                            }
+                        });
+                    }
+
+                    ui.indent(("bookmarks", &path), |ui| {
+                        for anchor in note.anchors {
+                            let label = match anchor.strip_prefix('^') {
+                                Some(id) => format!("¶ {}", id),
+                                None => format!("# {}", anchor),
+                            };
+                            let response = ui.selectable_label(false, label);
+                            if response.clicked() {
+                                app.navigate_to(NavEntry::new(path.clone(), Some(anchor.clone())));
+                            }
+                            response.context_menu(|ui| {
+                                if ui.button("Remove Bookmark").clicked() {
+                                    app.toggle_bookmark(&path, &anchor);
+                                    ui.close();
+                                }
+                            });
+                        }
+                    });
+                }
+            });
+        ui.separator();
     }
 
     /// Recursively show a file tree node
@@ -60,20 +131,43 @@ impl FileTreePanel {
     /// Show a directory node
     fn show_directory(ui: &mut egui::Ui, node: &FileNode, app: &mut RobsidianApp) {
         let id = ui.make_persistent_id(&node.path);
+        let revealed = app.reveal_in_tree.as_ref().is_some_and(|dir| dir.starts_with(&node.path));
 
-        egui::collapsing_header::CollapsingState::load_with_default_open(
-            ui.ctx(),
-            id,
-            node.expanded,
-        )
-        .show_header(ui, |ui| {
+        let mut state = egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, node.expanded);
+        if revealed && app.reveal_in_tree.as_ref() != Some(&node.path) {
+            state.set_open(true);
+        }
+        state.show_header(ui, |ui| {
             let icon = if node.expanded { "\u{1F4C2}" } else { "\u{1F4C1}" };
-            if ui
-                .selectable_label(false, format!("{} {}", icon, node.name))
-                .clicked()
-            {
+            let encrypted = app
+                .vault_path
+                .as_deref()
+                .is_some_and(|vault| app.vault_settings.in_encrypted_folder(vault, &node.path));
+            let lock = if encrypted { " 🔒" } else { "" };
+            // The folder the followed terminal is in
+            let current = app.terminal_directory.as_ref() == Some(&node.path);
+            let label = app.note_colors().text(&node.path, format!("{} {}{}", icon, node.name, lock));
+            let response = ui.selectable_label(current, label);
+            if response.clicked() {
                 app.file_tree.toggle_expanded(&node.path);
             }
+            if app.reveal_in_tree.as_ref() == Some(&node.path) {
+                response.scroll_to_me(Some(egui::Align::Center));
+            }
+            if let Some(note) = drop_target(ui, &response) {
+                app.copy_note_into(&note.0, &node.path);
+            }
+            response.context_menu(|ui| {
+                if ui.button("Open Terminal Here").clicked() {
+                    app.open_terminal_here(&node.path);
+                    ui.close();
+                }
+                let label = if encrypted { "Stop Encrypting Folder" } else { "Encrypt Folder..." };
+                if ui.button(label).clicked() {
+                    app.toggle_folder_encryption(&node.path);
+                    ui.close();
+                }
+            });
         })
         .body(|ui| {
             for child in &node.children {
@@ -107,11 +201,40 @@ impl FileTreePanel {
             format!("{} {}", icon, node.name)
         };
 
+        let display_name = app.note_colors().text(&node.path, display_name);
         ui.horizontal(|ui| {
             ui.add_space(16.0); // Indent for files
-            if ui.selectable_label(is_active, display_name).clicked() {
+            let response = ui
+                .dnd_drag_source(egui::Id::new(("file_tree_drag", &node.path)), DraggedNote(node.path.clone()), |ui| {
+                    ui.selectable_label(is_active, display_name)
+                })
+                .inner;
+            if response.clicked() {
                 app.open_document(node.path.clone());
             }
+            response.context_menu(|ui| {
+                if ui.button("Rename...").clicked() {
+                    app.rename_dialog.open(node.path.clone());
+                    ui.close();
+                }
+                if !node.is_markdown() {
+                    return;
+                }
+                let label = if app.is_starred(&node.path) { "Unstar" } else { "Star" };
+                if ui.button(label).clicked() {
+                    app.toggle_star(&node.path);
+                    ui.close();
+                }
+                ui.separator();
+                if ui.button("Copy to Vault...").clicked() {
+                    app.open_transfer_dialog(node.path.clone(), false);
+                    ui.close();
+                }
+                if ui.button("Move to Vault...").clicked() {
+                    app.open_transfer_dialog(node.path.clone(), true);
+                    ui.close();
+                }
+            });
         });
     }
 }
@@ -0,0 +1,222 @@
+//! Flashcard review
+//!
+//! Lists the vault's decks with their statistics, and reviews a deck's due
+//! and new cards one at a time: Space shows the answer, then `1` to `4` grade
+//! it from Again to Easy. Cards graded Again come back later in the session.
+//! Escape ends the session, or closes the window.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use eframe::egui::{self, Key, RichText};
+
+use robsidian_core::flashcards::{deck_stats, review_queue, DeckCard, Grade, ReviewLog};
+use crate::ui::live_preview::SimpleLivePreview;
+
+/// Something for the app to do
+#[derive(Debug, Clone)]
+pub enum FlashcardAction {
+    /// A card was graded; the review log should be saved
+    SaveLog,
+    /// Open the note of a card at the card
+    Open { note: PathBuf, offset: usize },
+}
+
+/// Cards of one deck being reviewed
+#[derive(Debug)]
+struct Session {
+    deck: String,
+    /// Indices of the cards left, the current one first
+    queue: VecDeque<usize>,
+    revealed: bool,
+    reviewed: usize,
+}
+
+/// Flashcard review state
+#[derive(Debug, Default)]
+pub struct FlashcardReview {
+    open: bool,
+    vault: PathBuf,
+    cards: Vec<DeckCard>,
+    log: ReviewLog,
+    session: Option<Session>,
+}
+
+impl FlashcardReview {
+    /// Show the decks of a vault's cards
+    pub fn open(&mut self, vault: &Path, cards: Vec<DeckCard>, log: ReviewLog) {
+        self.open = true;
+        self.vault = vault.to_path_buf();
+        self.cards = cards;
+        self.log = log;
+        self.session = None;
+    }
+
+    pub fn log(&self) -> &ReviewLog {
+        &self.log
+    }
+
+    fn start(&mut self, deck: &str) {
+        let today = Local::now().date_naive();
+        let queue = review_queue(&self.cards, deck, &self.log, &self.vault, today).into();
+        self.session = Some(Session { deck: deck.to_string(), queue, revealed: false, reviewed: 0 });
+    }
+
+    fn grade(&mut self, grade: Grade) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        let Some(index) = session.queue.pop_front() else {
+            return;
+        };
+        let card = &self.cards[index];
+        self.log.review(&card.review_key(&self.vault), grade, Local::now().date_naive());
+        if grade == Grade::Again {
+            session.queue.push_back(index);
+        }
+        session.revealed = false;
+        session.reviewed += 1;
+    }
+
+    /// Show the review window, returning the action picked this frame
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<FlashcardAction> {
+        if !self.open {
+            return None;
+        }
+
+        let mut action = None;
+        if ctx.memory(|mem| mem.focused().is_none()) {
+            action = self.handle_keys(ctx);
+        }
+
+        let mut open = true;
+        let mut start = None;
+        let mut grade = None;
+        let mut back = false;
+        egui::Window::new("Flashcards")
+            .open(&mut open)
+            .default_size([560.0, 420.0])
+            .show(ctx, |ui| {
+                if self.session.is_none() {
+                    start = self.show_decks(ui);
+                    return;
+                }
+                let cards = &self.cards;
+                let Some(session) = &mut self.session else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.strong(&session.deck);
+                    ui.weak(format!("{} reviewed, {} left", session.reviewed, session.queue.len()));
+                    back = ui.button("Decks").clicked();
+                });
+                ui.separator();
+                let Some(card) = session.queue.front().and_then(|index| cards.get(*index)) else {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(40.0);
+                        ui.heading("All done");
+                        ui.weak("No more cards are due in this deck today.");
+                    });
+                    return;
+                };
+
+                SimpleLivePreview::show(ui, &card.card.front, None);
+                ui.add_space(8.0);
+                if session.revealed {
+                    ui.separator();
+                    SimpleLivePreview::show(ui, &card.card.back, None);
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        for (number, choice) in Grade::ALL.into_iter().enumerate() {
+                            if ui.button(format!("{} {}", number + 1, choice.name())).clicked() {
+                                grade = Some(choice);
+                            }
+                        }
+                    });
+                } else if ui.button("Show Answer (Space)").clicked() {
+                    session.revealed = true;
+                }
+                ui.add_space(8.0);
+                if ui.link(RichText::new("Open note").small()).clicked() {
+                    action = Some(FlashcardAction::Open { note: card.note.clone(), offset: card.card.offset });
+                }
+            });
+
+        if back {
+            self.session = None;
+        }
+        if let Some(deck) = start {
+            self.start(&deck);
+        }
+        if let Some(grade) = grade {
+            self.grade(grade);
+            action = Some(FlashcardAction::SaveLog);
+        }
+        if !open {
+            self.open = false;
+        }
+        action
+    }
+
+    /// Table of the decks, returning the one picked for review
+    fn show_decks(&self, ui: &mut egui::Ui) -> Option<String> {
+        let stats = deck_stats(&self.cards, &self.log, &self.vault, Local::now().date_naive());
+        if stats.is_empty() {
+            ui.label("No flashcards in this vault.");
+            ui.weak("Write a question and its answer as lines starting with Q:: and A::,");
+            ui.weak("or hide parts of a line as cloze deletions: {{c1::hidden}}.");
+            return None;
+        }
+        let mut start = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("flashcard_decks").num_columns(7).striped(true).show(ui, |ui| {
+                for heading in ["Deck", "Cards", "New", "Due", "Learned", "Today", ""] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for deck in &stats {
+                    ui.label(&deck.name);
+                    ui.label(deck.total.to_string());
+                    ui.label(deck.new.to_string());
+                    ui.label(deck.due.to_string());
+                    ui.label(deck.mature.to_string()).on_hover_text("Reviewed at intervals of three weeks or more");
+                    ui.label(deck.reviewed_today.to_string());
+                    if ui.button("Study").clicked() {
+                        start = Some(deck.name.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+        start
+    }
+
+    fn handle_keys(&mut self, ctx: &egui::Context) -> Option<FlashcardAction> {
+        let mut grade = None;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                if self.session.take().is_none() {
+                    self.open = false;
+                }
+                return;
+            }
+            let Some(session) = &mut self.session else {
+                return;
+            };
+            if session.queue.is_empty() {
+                return;
+            }
+            if !session.revealed {
+                session.revealed =
+                    i.consume_key(egui::Modifiers::NONE, Key::Space) || i.consume_key(egui::Modifiers::NONE, Key::Enter);
+                return;
+            }
+            let keys = [Key::Num1, Key::Num2, Key::Num3, Key::Num4];
+            grade = Grade::ALL.into_iter().zip(keys).find(|(_, key)| i.consume_key(egui::Modifiers::NONE, *key));
+        });
+        let (grade, _) = grade?;
+        self.grade(grade);
+        Some(FlashcardAction::SaveLog)
+    }
+}
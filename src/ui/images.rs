@@ -0,0 +1,148 @@
+//! Image loading for previews
+//!
+//! Images are loaded through egui's image loaders (installed once at startup),
+//! which cache decoded textures by URI. Every preview resolves image sources
+//! through [`resolve_image_uri`] so the same file maps to the same cache entry.
+
+use std::path::Path;
+
+use eframe::egui::{self, load::TexturePoll, Color32, Ui};
+
+use robsidian_core::document::Document;
+
+/// Default banner height in points
+pub const DEFAULT_BANNER_HEIGHT: f32 = 180.0;
+
+/// Install the shared image loaders (file, http, and image decoders)
+pub fn install_loaders(ctx: &egui::Context) {
+    egui_extras::install_image_loaders(ctx);
+}
+
+/// Resolve an image source from a note into a loader URI.
+///
+/// URLs are used as is. Paths are tried relative to the note's folder and then
+/// relative to each ancestor folder up to the vault root, which covers
+/// vault-root relative paths.
+pub fn resolve_image_uri(vault: Option<&Path>, note_path: Option<&Path>, src: &str) -> String {
+    let src = src.trim();
+    if src.contains("://") {
+        return src.to_string();
+    }
+
+    let path = Path::new(src);
+    if path.is_absolute() {
+        return format!("file://{}", path.display());
+    }
+
+    if let Some(folder) = note_path.and_then(Path::parent) {
+        // Outside of a vault, only the note's own folder is looked in
+        let inside = |ancestor: &Path| vault.is_some_and(|vault| ancestor.starts_with(vault)) || ancestor == folder;
+        for ancestor in folder.ancestors().take_while(|ancestor| inside(ancestor)) {
+            let candidate = ancestor.join(path);
+            if candidate.exists() {
+                return format!("file://{}", candidate.display());
+            }
+        }
+        return format!("file://{}", folder.join(path).display());
+    }
+
+    format!("file://{}", src)
+}
+
+/// Banner image configured through a note's frontmatter
+#[derive(Debug, Clone)]
+pub struct Banner {
+    pub uri: String,
+    pub height: f32,
+    /// Which part of a tall image stays visible: 0.0 keeps the top,
+    /// 0.5 the center, and 1.0 the bottom
+    pub offset: f32,
+}
+
+#[derive(Default)]
+struct UriResolver;
+
+impl egui::cache::ComputerMut<(Option<&Path>, &Path, &str), String> for UriResolver {
+    fn compute(&mut self, (vault, note, src): (Option<&Path>, &Path, &str)) -> String {
+        resolve_image_uri(vault, Some(note), src)
+    }
+}
+
+/// Resolved banner sources, kept while the banner is shown every frame
+type BannerUriCache = egui::cache::FrameCache<String, UriResolver>;
+
+impl Banner {
+    /// Read the banner settings of a document, if it has one. Its source is
+    /// resolved again only when the note or its `banner` field changes.
+    pub fn from_document(ui: &Ui, doc: &Document, vault: Option<&Path>) -> Option<Self> {
+        let src = doc.metadata.banner.as_deref()?;
+        let key: (Option<&Path>, &Path, &str) = (vault, &doc.path, src);
+        Some(Self {
+            uri: ui.memory_mut(|mem| mem.caches.cache::<BannerUriCache>().get(key)),
+            height: doc.metadata.banner_height.unwrap_or(DEFAULT_BANNER_HEIGHT),
+            offset: doc.metadata.banner_offset.unwrap_or(0.5),
+        })
+    }
+
+    /// Show the banner full-width, cropped to its height
+    pub fn show(&self, ui: &mut Ui) {
+        show_banner(ui, &self.uri, self.height, self.offset);
+    }
+}
+
+/// Show a full-width banner image, cropped to `height`
+fn show_banner(ui: &mut Ui, uri: &str, height: f32, offset: f32) {
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), height),
+        egui::Sense::hover(),
+    );
+
+    let image = egui::Image::new(uri.to_string());
+    match image.load_for_size(ui.ctx(), rect.size()) {
+        Ok(TexturePoll::Ready { texture }) => {
+            let scaled_height = rect.width() * texture.size.y / texture.size.x.max(1.0);
+            let visible = (height / scaled_height.max(1.0)).min(1.0);
+            let top = offset.clamp(0.0, 1.0) * (1.0 - visible);
+            let uv = egui::Rect::from_min_max(egui::pos2(0.0, top), egui::pos2(1.0, top + visible));
+            ui.painter().image(texture.id, rect, uv, Color32::WHITE);
+        }
+        Ok(TexturePoll::Pending { .. }) => {
+            ui.painter().rect_filled(rect, 4.0, Color32::from_rgb(45, 45, 50));
+            ui.put(rect, egui::Spinner::new());
+        }
+        Err(e) => {
+            ui.painter().rect_filled(rect, 4.0, Color32::from_rgb(45, 45, 50));
+            ui.put(
+                rect,
+                egui::Label::new(
+                    egui::RichText::new(format!("Failed to load banner: {}", e))
+                        .color(Color32::from_rgb(200, 120, 120)),
+                ),
+            );
+        }
+    }
+
+    ui.add_space(8.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_paths_stay_in_the_vault() {
+        let dir = std::env::temp_dir().join(format!("robsidian_images_{}", std::process::id()));
+        let vault = dir.join("vault");
+        std::fs::create_dir_all(vault.join("Notes")).unwrap();
+        std::fs::write(vault.join("banner.png"), "").unwrap();
+        std::fs::write(dir.join("outside.png"), "").unwrap();
+        let note = vault.join("Notes/a.md");
+
+        let uri = resolve_image_uri(Some(&vault), Some(&note), "banner.png");
+        assert_eq!(uri, format!("file://{}", vault.join("banner.png").display()));
+        // A file of the same name above the vault isn't picked up
+        let uri = resolve_image_uri(Some(&vault), Some(&note), "outside.png");
+        assert_eq!(uri, format!("file://{}", vault.join("Notes/outside.png").display()));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
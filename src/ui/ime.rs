@@ -0,0 +1,44 @@
+//! Text composed with an input method (IME), as for Japanese, Chinese and
+//! Korean
+//!
+//! egui's text edits show the composition themselves. The terminal draws it
+//! at its cursor, and the keys the editors handle before their text edit
+//! stay with the input method while it composes.
+
+use eframe::egui;
+
+/// Where the text being composed is kept in egui's memory
+fn composition_id() -> egui::Id {
+    egui::Id::new("ime_composition")
+}
+
+/// Follow this frame's input method events; called once a frame before the
+/// widgets are shown
+pub fn update(ctx: &egui::Context) {
+    let preedit = ctx.input(|i| {
+        i.events.iter().fold(None, |preedit, event| match event {
+            egui::Event::Ime(egui::ImeEvent::Preedit(text)) => Some(text.clone()),
+            egui::Event::Ime(egui::ImeEvent::Commit(_) | egui::ImeEvent::Disabled) => Some(String::new()),
+            _ => preedit,
+        })
+    });
+    if let Some(preedit) = preedit {
+        ctx.data_mut(|data| data.insert_temp(composition_id(), preedit));
+    }
+}
+
+/// Text being composed, empty when there is none
+pub fn preedit(ctx: &egui::Context) -> String {
+    ctx.data(|data| data.get_temp::<String>(composition_id())).unwrap_or_default()
+}
+
+/// Whether keys belong to the input method: while it composes, and in the
+/// frame it commits, so the Enter that commits the text doesn't act too
+pub fn composing(ctx: &egui::Context) -> bool {
+    !preedit(ctx).is_empty()
+        || ctx.input(|i| {
+            i.events.iter().any(|event| {
+                matches!(event, egui::Event::Ime(egui::ImeEvent::Preedit(_) | egui::ImeEvent::Commit(_)))
+            })
+        })
+}
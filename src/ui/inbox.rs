@@ -0,0 +1,290 @@
+//! Inbox review
+//!
+//! Steps through the notes of the inbox folder one at a time, built for the
+//! keyboard: `M` moves the note to a folder, `T` adds tags, `G` merges it into
+//! another note, `A` archives and `D` deletes it. `O` opens it in a tab, the
+//! arrow keys skip between notes and Escape closes the review.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Key, RichText};
+
+use robsidian_core::note_index::NoteIndex;
+use crate::ui::live_preview::SimpleLivePreview;
+
+/// Suggestions listed below a prompt
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Something to do with the note under review
+#[derive(Debug, Clone)]
+pub enum InboxAction {
+    Open(PathBuf),
+    /// Move into a folder relative to the vault
+    Move { note: PathBuf, folder: PathBuf },
+    AddTags { note: PathBuf, tags: Vec<String> },
+    MergeInto { note: PathBuf, target: PathBuf },
+    Archive(PathBuf),
+    Delete(PathBuf),
+}
+
+/// Input being asked for before an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prompt {
+    Move,
+    Tags,
+    Merge,
+}
+
+/// Inbox review state
+#[derive(Debug, Default)]
+pub struct InboxReview {
+    open: bool,
+    notes: Vec<PathBuf>,
+    current: usize,
+    /// Vault folders offered when moving a note
+    folders: Vec<PathBuf>,
+    prompt: Option<Prompt>,
+    input: String,
+    selected: usize,
+    /// Content of the current note
+    preview: Option<(PathBuf, String)>,
+}
+
+impl InboxReview {
+    /// Start reviewing the given notes
+    pub fn open(&mut self, notes: Vec<PathBuf>, folders: Vec<PathBuf>) {
+        self.open = true;
+        self.notes = notes;
+        self.folders = folders;
+        self.current = 0;
+        self.prompt = None;
+        self.preview = None;
+    }
+
+    fn current_note(&self) -> Option<&PathBuf> {
+        self.notes.get(self.current)
+    }
+
+    /// The current note was handled: drop it and show the next one
+    pub fn remove_current(&mut self) {
+        if self.current < self.notes.len() {
+            self.notes.remove(self.current);
+        }
+        self.current = self.current.min(self.notes.len().saturating_sub(1));
+        self.preview = None;
+    }
+
+    /// Re-read the current note after it was changed
+    pub fn reload_current(&mut self) {
+        self.preview = None;
+    }
+
+    fn start_prompt(&mut self, prompt: Prompt) {
+        self.prompt = Some(prompt);
+        self.input.clear();
+        self.selected = 0;
+    }
+
+    /// Show the review window, returning the action picked this frame
+    pub fn show(&mut self, ctx: &egui::Context, notes: &NoteIndex) -> Option<InboxAction> {
+        if !self.open {
+            return None;
+        }
+
+        if let Some(path) = self.current_note().cloned() {
+            if self.preview.as_ref().map(|(p, _)| p) != Some(&path) {
+                let content = std::fs::read_to_string(&path).unwrap_or_else(|e| format!("Could not read the note: {}", e));
+                self.preview = Some((path, content));
+            }
+        }
+
+        let mut action = None;
+        if self.prompt.is_none() && ctx.memory(|mem| mem.focused().is_none()) {
+            action = self.handle_keys(ctx);
+        }
+
+        let mut open = true;
+        egui::Window::new("Inbox")
+            .open(&mut open)
+            .default_size([640.0, 520.0])
+            .show(ctx, |ui| {
+                let Some(path) = self.current_note().cloned() else {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(40.0);
+                        ui.heading("Inbox zero");
+                        ui.weak("Every note in the inbox has been handled.");
+                    });
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    ui.strong(Self::name(&path));
+                    ui.weak(format!("{} of {}", self.current + 1, self.notes.len()));
+                });
+                ui.horizontal_wrapped(|ui| {
+                    for (key, label) in [
+                        ("M", "Move"),
+                        ("T", "Tags"),
+                        ("G", "Merge"),
+                        ("A", "Archive"),
+                        ("D", "Delete"),
+                        ("O", "Open"),
+                        ("←/→", "Skip"),
+                    ] {
+                        ui.label(RichText::new(key).monospace().strong());
+                        ui.weak(label);
+                    }
+                });
+                ui.separator();
+
+                if let Some(prompt) = self.prompt {
+                    if let Some(a) = self.show_prompt(ui, prompt, &path, notes) {
+                        action = Some(a);
+                    }
+                    ui.separator();
+                }
+
+                if let Some((_, content)) = &self.preview {
+                    SimpleLivePreview::show(ui, content, None);
+                }
+            });
+
+        if !open {
+            self.open = false;
+        }
+        action
+    }
+
+    fn name(path: &Path) -> String {
+        path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    /// Single-key actions, used while no prompt or text field is focused
+    fn handle_keys(&mut self, ctx: &egui::Context) -> Option<InboxAction> {
+        let note = self.current_note().cloned();
+        let mut action = None;
+        let mut prompt = None;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                self.open = false;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowRight) && self.current + 1 < self.notes.len() {
+                self.current += 1;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowLeft) {
+                self.current = self.current.saturating_sub(1);
+            }
+            let Some(note) = note else {
+                return;
+            };
+            if i.consume_key(egui::Modifiers::NONE, Key::M) {
+                prompt = Some(Prompt::Move);
+            } else if i.consume_key(egui::Modifiers::NONE, Key::T) {
+                prompt = Some(Prompt::Tags);
+            } else if i.consume_key(egui::Modifiers::NONE, Key::G) {
+                prompt = Some(Prompt::Merge);
+            } else if i.consume_key(egui::Modifiers::NONE, Key::A) {
+                action = Some(InboxAction::Archive(note));
+            } else if i.consume_key(egui::Modifiers::NONE, Key::D) {
+                action = Some(InboxAction::Delete(note));
+            } else if i.consume_key(egui::Modifiers::NONE, Key::O) {
+                action = Some(InboxAction::Open(note));
+            }
+            // Keep the shortcut letter out of the prompt shown this frame
+            if prompt.is_some() {
+                i.events.retain(|event| !matches!(event, egui::Event::Text(_)));
+            }
+        });
+        if let Some(prompt) = prompt {
+            self.start_prompt(prompt);
+        }
+        action
+    }
+
+    /// Input field of a prompt with its suggestions
+    fn show_prompt(&mut self, ui: &mut egui::Ui, prompt: Prompt, note: &Path, notes: &NoteIndex) -> Option<InboxAction> {
+        let hint = match prompt {
+            Prompt::Move => "Move to folder...",
+            Prompt::Tags => "Tags, separated by spaces or commas...",
+            Prompt::Merge => "Merge into note...",
+        };
+        let query = self.input.to_lowercase();
+        // Suggestions: (label, value)
+        let suggestions: Vec<(String, PathBuf)> = match prompt {
+            Prompt::Move => self
+                .folders
+                .iter()
+                .filter(|folder| folder.to_string_lossy().to_lowercase().contains(&query))
+                .take(MAX_SUGGESTIONS)
+                .map(|folder| (folder.display().to_string(), folder.clone()))
+                .collect(),
+            Prompt::Merge => notes
+                .search(&self.input, MAX_SUGGESTIONS + 1)
+                .into_iter()
+                .filter(|m| m.note.path != note)
+                .take(MAX_SUGGESTIONS)
+                .map(|m| (m.label(), m.note.path.clone()))
+                .collect(),
+            Prompt::Tags => Vec::new(),
+        };
+        self.selected = self.selected.min(suggestions.len().saturating_sub(1));
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+        ui.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) && self.selected + 1 < suggestions.len() {
+                self.selected += 1;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            confirmed = i.consume_key(egui::Modifiers::NONE, Key::Enter);
+            cancelled = i.consume_key(egui::Modifiers::NONE, Key::Escape);
+        });
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut self.input)
+                .hint_text(hint)
+                .desired_width(f32::INFINITY),
+        );
+        response.request_focus();
+        if response.changed() {
+            self.selected = 0;
+        }
+        let mut clicked = None;
+        for (idx, (label, value)) in suggestions.iter().enumerate() {
+            if ui.selectable_label(idx == self.selected, label).clicked() {
+                clicked = Some(value.clone());
+            }
+        }
+
+        if cancelled {
+            self.prompt = None;
+            response.surrender_focus();
+            return None;
+        }
+        let picked = clicked.or_else(|| confirmed.then(|| suggestions.get(self.selected).map(|(_, v)| v.clone())).flatten());
+        let note = note.to_path_buf();
+        let action = match prompt {
+            // A new folder can be typed as well
+            Prompt::Move => picked
+                .or_else(|| (confirmed && !self.input.trim().is_empty()).then(|| PathBuf::from(self.input.trim())))
+                .map(|folder| InboxAction::Move { note, folder }),
+            Prompt::Merge => picked.map(|target| InboxAction::MergeInto { note, target }),
+            Prompt::Tags => confirmed.then(|| InboxAction::AddTags {
+                note,
+                tags: self
+                    .input
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            }),
+        };
+        if action.is_some() {
+            self.prompt = None;
+            response.surrender_focus();
+        }
+        action
+    }
+}
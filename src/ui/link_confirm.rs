@@ -0,0 +1,120 @@
+//! External link confirmation and settings dialogs
+
+use eframe::egui::{self, Key, RichText};
+
+use robsidian_core::config::LinkConfig;
+use robsidian_core::link_safety::{link_host, misleading_host};
+
+/// A link the user agreed to open
+#[derive(Debug)]
+pub struct ConfirmedLink {
+    pub url: String,
+    /// Domain to trust from now on
+    pub trust: Option<String>,
+}
+
+/// Confirmation shown before opening a web address
+#[derive(Debug, Default)]
+pub struct LinkConfirmDialog {
+    /// The address, and the text of the link it came from if known
+    link: Option<(String, Option<String>)>,
+    trust: bool,
+}
+
+impl LinkConfirmDialog {
+    pub fn open(&mut self, url: String, text: Option<String>) {
+        self.link = Some((url, text));
+        self.trust = false;
+    }
+
+    /// Show the dialog, returning the link once the user chose to open it
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ConfirmedLink> {
+        let (url, text) = self.link.as_ref()?;
+        let host = link_host(url);
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Open Link?")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                ui.set_max_width(480.0);
+                ui.label("This link goes to:");
+                ui.add(egui::Label::new(RichText::new(url).monospace().strong()).wrap());
+                let claimed = text.as_deref().and_then(|text| misleading_host(text, url));
+                if let Some(claimed) = claimed {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("⚠ Its text shows {}, but it doesn't go there.", claimed),
+                    );
+                }
+                if let Some(host) = &host {
+                    ui.checkbox(&mut self.trust, format!("Always open links to {}", host));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Open").clicked() || ui.input(|i| i.key_pressed(Key::Enter)) {
+                        confirmed = true;
+                    }
+                    if ui.button("Copy Address").clicked() {
+                        ui.ctx().copy_text(url.clone());
+                    }
+                });
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.link = None;
+        }
+        if !confirmed {
+            return None;
+        }
+        let (url, _) = self.link.take()?;
+        Some(ConfirmedLink { url, trust: host.filter(|_| self.trust) })
+    }
+}
+
+/// Dialog editing the external link settings
+#[derive(Debug, Default)]
+pub struct LinkSettingsDialog {
+    /// Settings being edited, with the trusted domains one per line; `None`
+    /// while closed
+    draft: Option<(bool, String)>,
+}
+
+impl LinkSettingsDialog {
+    pub fn open(&mut self, config: &LinkConfig) {
+        self.draft = Some((config.confirm_external, config.trusted_domains.join("\n")));
+    }
+
+    /// Show the dialog, returning the new settings once saved
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<LinkConfig> {
+        let (confirm, domains) = self.draft.as_mut()?;
+        let mut open = true;
+        let mut saved = false;
+
+        egui::Window::new("Link Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.checkbox(confirm, "Confirm before opening web links");
+                ui.add_enabled_ui(*confirm, |ui| {
+                    ui.label("Trusted domains, one per line (their subdomains are trusted too):");
+                    ui.add(egui::TextEdit::multiline(domains).hint_text("example.com").desired_rows(5));
+                });
+                ui.separator();
+                saved = ui.button("Save").clicked();
+            });
+
+        if !open {
+            self.draft = None;
+        }
+        if !saved {
+            return None;
+        }
+        let (confirm_external, domains) = self.draft.take()?;
+        let trusted_domains = domains.lines().map(str::trim).filter(|d| !d.is_empty()).map(str::to_string).collect();
+        Some(LinkConfig { confirm_external, trusted_domains })
+    }
+}
@@ -0,0 +1,108 @@
+//! Vault-wide link style conversion
+//!
+//! Rewrites the links of every note as wiki links or as Markdown links. The
+//! conversion is previewed first, note by note with each link's old and new
+//! form, and notes can be left out before anything is written.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use robsidian_core::link_refactor::{plan_link_conversion, LinkStyle, NoteConversion};
+
+/// Notes to convert, as previewed, with whether each is included
+#[derive(Debug)]
+struct Preview {
+    style: LinkStyle,
+    notes: Vec<(NoteConversion, bool)>,
+}
+
+/// Link style conversion dialog state
+#[derive(Debug)]
+pub struct LinkStyleDialog {
+    open: bool,
+    vault: PathBuf,
+    style: LinkStyle,
+    preview: Option<Preview>,
+}
+
+impl Default for LinkStyleDialog {
+    fn default() -> Self {
+        Self { open: false, vault: PathBuf::new(), style: LinkStyle::Markdown, preview: None }
+    }
+}
+
+impl LinkStyleDialog {
+    pub fn open(&mut self, vault: &Path) {
+        self.open = true;
+        self.vault = vault.to_path_buf();
+        self.preview = None;
+    }
+
+    /// Show the dialog, returning the notes to convert once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Vec<NoteConversion>> {
+        if !self.open {
+            return None;
+        }
+        let mut open = true;
+        let mut convert = false;
+
+        egui::Window::new("Convert Link Style")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([520.0, 440.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Write every link as");
+                    for style in [LinkStyle::Markdown, LinkStyle::Wiki] {
+                        ui.radio_value(&mut self.style, style, style.name());
+                    }
+                    if ui.button("Preview").clicked() {
+                        let notes = plan_link_conversion(&self.vault, self.style);
+                        let notes = notes.into_iter().map(|note| (note, true)).collect();
+                        self.preview = Some(Preview { style: self.style, notes });
+                    }
+                });
+                ui.weak("Links to missing files, web links and links in code are left as they are.");
+                ui.separator();
+
+                let Some(preview) = &mut self.preview else {
+                    ui.label("Preview the conversion to see the links it changes.");
+                    return;
+                };
+                if preview.notes.is_empty() {
+                    ui.label(format!("No links to convert to {}.", preview.style.name().to_lowercase()));
+                    return;
+                }
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (note, included) in &mut preview.notes {
+                        let name = note.note.strip_prefix(&self.vault).unwrap_or(&note.note);
+                        ui.horizontal(|ui| {
+                            ui.checkbox(included, "");
+                            egui::CollapsingHeader::new(format!("{} ({})", name.display(), note.links.len()))
+                                .id_salt(&note.note)
+                                .show(ui, |ui| {
+                                    for link in &note.links {
+                                        ui.monospace(format!("{}  →  {}", link.old, link.new));
+                                    }
+                                });
+                        });
+                    }
+                });
+                ui.separator();
+                let count = preview.notes.iter().filter(|(_, included)| *included).count();
+                let label = format!("Convert {} note(s) to {}", count, preview.style.name().to_lowercase());
+                convert = ui.add_enabled(count > 0, egui::Button::new(label)).clicked();
+            });
+
+        if convert {
+            self.open = false;
+            let preview = self.preview.take()?;
+            return Some(preview.notes.into_iter().filter(|(_, included)| *included).map(|(note, _)| note).collect());
+        }
+        if !open {
+            self.open = false;
+        }
+        None
+    }
+}
@@ -5,13 +5,41 @@
 //! - Rendered preview for all other blocks
 //!
 //! This creates an Obsidian-like editing experience where you can see
-//! formatted output while still being able to edit.
+//! formatted output while still being able to edit. Clicking a rendered
+//! block edits it with the caret on the clicked character (see
+//! [`super::source_map`]).
 
-use eframe::egui::{self, Color32, FontId, ScrollArea, TextEdit, Ui};
+use std::ops::Range;
+use std::sync::Arc;
 
-use super::block_renderer::{render_block, BlockAction};
-use super::markdown_blocks::{find_block_at_position, parse_blocks, ParsedBlock};
-use crate::core::document::Document;
+use eframe::egui::{
+    self,
+    text::{CCursor, CCursorRange},
+    Color32, FontId, Key, Modifiers, ScrollArea, TextEdit, Ui,
+};
+
+use super::block_renderer::{render_block, render_block_toolbar, BlockAction, RenderContext};
+use super::markdown_blocks::{
+    collect_footnotes, edit_region, merge_with_previous, parse_blocks, remove_block, replace_region,
+    reparse_blocks, split_block, ParsedBlock,
+};
+use super::images::Banner;
+use super::ime;
+use super::render_cache::{block_key, BlockRenderCache};
+use super::source_map;
+use super::theme::NoteStyle;
+use robsidian_core::document::Document;
+use robsidian_core::glossary::Glossary;
+use robsidian_core::navigation::{block_id, new_block_id};
+use robsidian_core::note_index::NoteIndex;
+use robsidian_core::text_position::{
+    byte_to_char, byte_to_line_col, char_to_byte, floor_char_boundary, line_col_to_byte,
+    prev_char_boundary,
+};
+use robsidian_core::transform::{callout_block, quote_block};
+
+/// Id of the text edit showing the raw markdown under the cursor
+const RAW_EDITOR_ID: &str = "live_preview_raw_editor";
 
 /// Live preview editor state
 pub struct LivePreviewEditor {
@@ -21,8 +49,44 @@ pub struct LivePreviewEditor {
     parsed_blocks: Vec<ParsedBlock>,
     /// Cache of the content that was parsed (to detect changes)
     cached_content: String,
-    /// Index of the block being edited (if any)
-    editing_block: Option<usize>,
+    /// Render cache key of each parsed block
+    block_keys: Vec<u64>,
+    /// Sizes of rendered blocks, to skip laying out those out of view
+    render_cache: BlockRenderCache,
+    /// Whether the cursor is active, showing the region under it as raw markdown
+    editing: bool,
+    /// Start of the region shown raw on the last frame
+    edit_region_start: Option<usize>,
+    /// Move the raw editor's caret to `cursor_byte_pos` on the next frame
+    resync_cursor: bool,
+    /// Block highlighted for keyboard navigation while not editing
+    selected_block: Option<usize>,
+    /// Block to scroll into view on the next frame
+    scroll_to_block: Option<usize>,
+    /// Heading to scroll to once the document's blocks are parsed
+    pending_heading: Option<String>,
+    /// Abbreviations of the vault's glossary note
+    vault_glossary: Glossary,
+    /// Abbreviations shown in the note: the vault's and the note's own
+    glossary: Arc<Glossary>,
+}
+
+/// Source region shown as raw markdown
+struct RawRegion {
+    range: Range<usize>,
+    /// Whether Enter at the end of the region starts a new block (rather than
+    /// a new line, as in lists, quotes, and code)
+    splits_on_enter: bool,
+}
+
+/// Result of showing the raw editor for one frame
+#[derive(Default)]
+struct RawEdit {
+    /// Document content after an edit
+    new_content: Option<String>,
+    /// New document cursor position
+    cursor: Option<usize>,
+    lost_focus: bool,
 }
 
 impl Default for LivePreviewEditor {
@@ -38,21 +102,110 @@ impl LivePreviewEditor {
             cursor_byte_pos: 0,
             parsed_blocks: Vec::new(),
             cached_content: String::new(),
-            editing_block: None,
+            block_keys: Vec::new(),
+            render_cache: BlockRenderCache::default(),
+            editing: false,
+            edit_region_start: None,
+            resync_cursor: false,
+            selected_block: None,
+            scroll_to_block: None,
+            pending_heading: None,
+            vault_glossary: Glossary::default(),
+            glossary: Arc::default(),
         }
     }
 
-    /// Update the editor with document content
+    /// Set the abbreviations that apply to every note
+    pub fn set_vault_glossary(&mut self, glossary: Glossary) {
+        self.vault_glossary = glossary;
+        self.update_glossary();
+    }
+
+    fn update_glossary(&mut self) {
+        let mut glossary = self.vault_glossary.clone();
+        glossary.extend(&Glossary::parse(&self.cached_content));
+        self.glossary = Arc::new(glossary);
+    }
+
+    /// Block with the id `^id`. An id alone in a paragraph belongs to the
+    /// code block or table before it.
+    fn find_block_id(&self, content: &str, id: &str) -> Option<usize> {
+        let idx = self.parsed_blocks.iter().position(|block| block_id(&content[block.range()]) == Some(id))?;
+        let alone = content[self.parsed_blocks[idx].range()].trim() == format!("^{}", id);
+        Some(if alone { idx.saturating_sub(1) } else { idx })
+    }
+
+    /// Scroll to a heading (matched case-insensitively) or `^block` on the next frame
+    pub fn scroll_to_heading(&mut self, heading: String) {
+        self.pending_heading = Some(heading);
+    }
+
+    /// Scroll to the block holding the byte `offset` of the document shown
+    pub fn scroll_to_offset(&mut self, offset: usize) {
+        self.scroll_to_block = self.parsed_blocks.iter().position(|block| block.range().end > offset);
+    }
+
+    /// Update the editor with document content, reparsing only the blocks
+    /// around an edit
     fn update_blocks(&mut self, content: &str) {
         if content != self.cached_content {
-            self.parsed_blocks = parse_blocks(content);
+            reparse_blocks(&mut self.parsed_blocks, &self.cached_content, content);
             self.cached_content = content.to_string();
+            self.update_glossary();
+            self.block_keys = self.parsed_blocks.iter().map(|block| block_key(&content[block.range()])).collect();
+            self.render_cache.retain(&self.block_keys);
         }
     }
 
-    /// Find which block contains the cursor
-    fn find_cursor_block(&self) -> Option<usize> {
-        find_block_at_position(&self.parsed_blocks, self.cursor_byte_pos)
+    /// Place the cursor and start editing there
+    fn edit_at(&mut self, pos: usize) {
+        self.cursor_byte_pos = pos;
+        self.editing = true;
+        self.resync_cursor = true;
+    }
+
+    /// Move the block selection with Up/Down and start editing it with Enter.
+    /// Only active while nothing else has keyboard focus.
+    fn handle_block_navigation(&mut self, ui: &Ui) {
+        if self.editing
+            || self.parsed_blocks.is_empty()
+            || ui.memory(|mem| mem.focused().is_some())
+            || ime::composing(ui.ctx())
+        {
+            return;
+        }
+
+        let last = self.parsed_blocks.len() - 1;
+        let (up, down, enter) = ui.input(|i| {
+            (
+                i.key_pressed(Key::ArrowUp),
+                i.key_pressed(Key::ArrowDown),
+                i.key_pressed(Key::Enter),
+            )
+        });
+
+        let selected = self.selected_block.map(|idx| idx.min(last));
+        let moved = match selected {
+            None if up || down => Some(if up { last } else { 0 }),
+            Some(idx) if up => Some(idx.saturating_sub(1)),
+            Some(idx) if down => Some((idx + 1).min(last)),
+            _ => None,
+        };
+        if let Some(idx) = moved {
+            self.selected_block = Some(idx);
+            self.scroll_to_block = Some(idx);
+        } else if let (true, Some(idx)) = (enter, selected) {
+            self.edit_at(self.parsed_blocks[idx].range().start);
+        }
+    }
+
+    /// Find the block holding a footnote's definition, or its first reference
+    fn find_footnote_block(&self, label: &str, to_definition: bool) -> Option<usize> {
+        let reference = format!("[^{}]", label);
+        self.parsed_blocks.iter().position(|block| match block {
+            ParsedBlock::FootnoteDefinition { label: l, .. } => to_definition && l == label,
+            _ => !to_definition && self.cached_content[block.range()].contains(&reference),
+        })
     }
 
     /// Show the live preview editor
@@ -60,11 +213,55 @@ impl LivePreviewEditor {
         &mut self,
         ui: &mut Ui,
         document: &mut Document,
+        notes: &Arc<NoteIndex>,
     ) -> Option<BlockAction> {
         let content = document.content.clone();
         self.update_blocks(&content);
+        // The document may have been edited elsewhere since the cursor was placed
+        self.cursor_byte_pos = floor_char_boundary(&content, self.cursor_byte_pos);
+
+        if let Some(heading) = self.pending_heading.take() {
+            self.scroll_to_block = match heading.trim().strip_prefix('^') {
+                Some(id) => self.find_block_id(&content, id),
+                None => self.parsed_blocks.iter().position(|block| {
+                    matches!(block, ParsedBlock::Heading { text, .. } if text.trim().eq_ignore_ascii_case(heading.trim()))
+                }),
+            };
+        }
+
+        self.handle_block_navigation(ui);
+
+        // The block under the cursor (or the blank gap it sits in) is shown raw
+        let (edit_block, edit_range) = if self.editing {
+            let (block, range) = edit_region(&self.parsed_blocks, &content, self.cursor_byte_pos);
+            (block, Some(range))
+        } else {
+            (None, None)
+        };
+        if edit_block.is_some() {
+            self.selected_block = edit_block;
+        }
+        let region_start = edit_range.as_ref().map(|range| range.start);
+        if region_start != self.edit_region_start {
+            self.edit_region_start = region_start;
+            self.resync_cursor = true;
+        }
+        let resync = std::mem::take(&mut self.resync_cursor);
+
+        let render_ctx = RenderContext {
+            note_path: Some(document.path.clone()),
+            footnotes: collect_footnotes(&self.parsed_blocks),
+            notes: Arc::clone(notes),
+            glossary: Arc::clone(&self.glossary),
+        };
+
+        let note_style = NoteStyle::from_classes(&document.metadata.cssclasses);
+        let banner = Banner::from_document(ui, document, notes.vault());
 
         let mut action = None;
+        let mut toolbar_action: Option<(usize, BlockAction)> = None;
+        let mut raw_edit = RawEdit::default();
+        let mut clicked_pos = None;
         let mut new_content = content.clone();
         let mut content_changed = false;
 
@@ -73,97 +270,220 @@ impl LivePreviewEditor {
             .show(ui, |ui| {
                 ui.set_min_width(ui.available_width());
 
-                for (idx, block) in self.parsed_blocks.iter().enumerate() {
-                    let is_editing = self.editing_block == Some(idx);
-                    let block_range = block.range();
+                if let Some(banner) = &banner {
+                    banner.show(ui);
+                }
 
-                    // Create a frame for the block
-                    ui.push_id(idx, |ui| {
-                        // Make the entire block area interactive
-                        let (rect, response) = ui.allocate_exact_size(
-                            egui::vec2(ui.available_width(), 0.0),
-                            egui::Sense::click(),
-                        );
+                note_style.show(ui, |ui| {
+                    let mut gap_shown = edit_block.is_some() || edit_range.is_none();
 
-                        if is_editing {
-                            // Show raw markdown for editing
-                            let block_content = &content[block_range.clone()];
-                            let mut edit_text = block_content.to_string();
-
-                            let text_response = ui.add(
-                                TextEdit::multiline(&mut edit_text)
-                                    .font(FontId::monospace(14.0))
-                                    .desired_width(ui.available_width())
-                                    .frame(true)
-                                    .margin(egui::Margin::same(4)),
-                            );
-
-                            // Update content if changed
-                            if edit_text != block_content {
-                                new_content = format!(
-                                    "{}{}{}",
-                                    &content[..block_range.start],
-                                    edit_text,
-                                    &content[block_range.end..]
-                                );
-                                content_changed = true;
+                    for (idx, block) in self.parsed_blocks.iter().enumerate() {
+                        let block_range = block.range();
+
+                        if let Some(range) = &edit_range {
+                            // A gap being edited is shown before the block following it
+                            if !gap_shown && range.end <= block_range.start {
+                                let region = RawRegion { range: range.clone(), splits_on_enter: false };
+                                raw_edit = show_raw_editor(ui, &content, region, self.cursor_byte_pos, resync);
+                                gap_shown = true;
+                            }
+                            // Blocks nested in the edited block (e.g. images) are part of its source
+                            if edit_block != Some(idx)
+                                && edit_block.is_some()
+                                && range.start <= block_range.start
+                                && block_range.end <= range.end
+                            {
+                                continue;
                             }
+                        }
 
-                            // Click outside to exit edit mode
-                            if text_response.clicked_elsewhere() {
-                                self.editing_block = None;
+                        // A block out of view whose size is known only takes up its space
+                        let key = self.block_keys[idx];
+                        let width = ui.available_width();
+                        let rendered_size = self.render_cache.size(key, width);
+                        if let (Some(size), true) = (rendered_size, edit_block != Some(idx)) {
+                            if !ui.is_rect_visible(egui::Rect::from_min_size(ui.cursor().min, size)) {
+                                let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                                if self.scroll_to_block == Some(idx) {
+                                    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                    self.scroll_to_block = None;
+                                }
+                                continue;
                             }
-                        } else {
+                        }
+
+                        let mut rendered = false;
+                        let block_response = ui.push_id(idx, |ui| {
+                            if edit_block == Some(idx) {
+                                let region = RawRegion {
+                                    range: edit_range.clone().unwrap_or(block_range.clone()),
+                                    splits_on_enter: !matches!(
+                                        block,
+                                        ParsedBlock::CodeBlock { .. } | ParsedBlock::List { .. } | ParsedBlock::BlockQuote { .. }
+                                    ),
+                                };
+                                raw_edit = show_raw_editor(ui, &content, region, self.cursor_byte_pos, resync);
+                                return;
+                            }
+
                             // Show rendered preview
-                            egui::Frame::new()
+                            rendered = true;
+                            let mut block_action = None;
+                            source_map::begin(ui);
+                            let block_rect = egui::Frame::new()
                                 .inner_margin(egui::Margin::same(4))
                                 .show(ui, |ui| {
-                                    if let Some(a) = render_block(ui, block) {
-                                        action = Some(a);
-                                    }
-                                });
-
-                            // Click to start editing this block
-                            if response.clicked() {
-                                self.editing_block = Some(idx);
-                                self.cursor_byte_pos = block_range.start;
+                                    block_action = render_block(ui, block, &render_ctx);
+                                })
+                                .response
+                                .rect;
+                            let text_pieces = source_map::take(ui);
+
+                            // Keyboard selection outline
+                            if !self.editing && self.selected_block == Some(idx) {
+                                ui.painter().rect_stroke(
+                                    block_rect,
+                                    egui::CornerRadius::same(4),
+                                    ui.visuals().selection.stroke,
+                                    egui::StrokeKind::Outside,
+                                );
                             }
 
-                            // Hover effect
-                            if response.hovered() {
+                            // Hover toolbar; checked against the raw pointer position so it
+                            // stays open while the pointer is over the toolbar itself
+                            let pointer = ui.input(|i| i.pointer.hover_pos());
+                            if pointer.is_some_and(|pos| block_rect.contains(pos)) {
                                 ui.painter().rect_stroke(
-                                    rect,
+                                    block_rect,
                                     egui::CornerRadius::same(4),
                                     egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(100, 100, 100, 50)),
                                     egui::StrokeKind::Outside,
                                 );
+                                if let Some(a) = render_block_toolbar(ui, block_rect, block) {
+                                    toolbar_action = Some((idx, a));
+                                }
+                            }
+
+                            // Clicking the block (not one of its links or the toolbar) edits it,
+                            // with the caret at the clicked character
+                            let click = ui.input(|i| i.pointer.primary_clicked().then(|| i.pointer.interact_pos()).flatten());
+                            if let Some(pos) = click {
+                                if block_action.is_none()
+                                    && block_rect.contains(pos)
+                                    && ui.ctx().layer_id_at(pos) == Some(ui.layer_id())
+                                {
+                                    let source = &content[block_range.clone()];
+                                    let offset = source_map::source_offset(&text_pieces, source, pos);
+                                    clicked_pos = Some(block_range.start + offset.unwrap_or(0));
+                                }
+                            }
+
+                            if block_action.is_some() {
+                                action = block_action;
                             }
+                        }).response;
+                        if rendered {
+                            self.render_cache.store(key, width, block_response.rect.size());
                         }
-                    });
-                }
 
-                // Add some space at the bottom for clicking to add content
-                let (rect, add_response) = ui.allocate_exact_size(
-                    egui::vec2(ui.available_width(), 50.0),
-                    egui::Sense::click(),
-                );
+                        if self.scroll_to_block == Some(idx) {
+                            ui.scroll_to_rect(block_response.rect, Some(egui::Align::Center));
+                            self.scroll_to_block = None;
+                        }
+                    }
 
-                if add_response.clicked() {
-                    // Start editing at the end
-                    self.editing_block = Some(self.parsed_blocks.len());
-                    self.cursor_byte_pos = content.len();
-                }
+                    if let (false, Some(range)) = (gap_shown, &edit_range) {
+                        let region = RawRegion { range: range.clone(), splits_on_enter: false };
+                        raw_edit = show_raw_editor(ui, &content, region, self.cursor_byte_pos, resync);
+                    }
 
-                if add_response.hovered() {
-                    ui.painter().rect_stroke(
-                        rect,
-                        egui::CornerRadius::same(4),
-                        egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(100, 100, 100, 30)),
-                        egui::StrokeKind::Outside,
+                    // Add some space at the bottom for clicking to add content
+                    let (rect, add_response) = ui.allocate_exact_size(
+                        egui::vec2(ui.available_width(), 50.0),
+                        egui::Sense::click(),
                     );
-                }
+
+                    if add_response.clicked() {
+                        // Start editing at the end
+                        clicked_pos = Some(content.len());
+                    }
+
+                    if add_response.hovered() {
+                        ui.painter().rect_stroke(
+                            rect,
+                            egui::CornerRadius::same(4),
+                            egui::Stroke::new(1.0, Color32::from_rgba_unmultiplied(100, 100, 100, 30)),
+                            egui::StrokeKind::Outside,
+                        );
+                    }
+                });
             });
 
+        // Track the caret; leaving the editor (click elsewhere, Escape) renders everything
+        if let Some(edited) = raw_edit.new_content {
+            new_content = edited;
+            content_changed = true;
+        }
+        if let Some(cursor) = raw_edit.cursor {
+            if cursor != self.cursor_byte_pos {
+                self.cursor_byte_pos = cursor;
+            }
+        }
+        if let Some(pos) = clicked_pos {
+            self.edit_at(pos);
+        } else if raw_edit.lost_focus {
+            self.editing = false;
+        }
+
+        // Footnote jumps are handled within the editor
+        if let Some(BlockAction::JumpToFootnote { label, to_definition }) = &action {
+            self.scroll_to_block = self.find_footnote_block(label, *to_definition);
+            action = None;
+        }
+
+        // Toolbar actions edit the block in place; running code is left to the app
+        if let Some((idx, toolbar_action)) = toolbar_action {
+            let range = self.parsed_blocks[idx].range();
+            let source = content[range.clone()].trim_end();
+            let replace_block =
+                |replacement: String| replace_region(&content, range.start..range.start + source.len(), &replacement);
+            match toolbar_action {
+                BlockAction::EditBlock => self.edit_at(range.start),
+                BlockAction::CopyBlock => ui.ctx().copy_text(source.to_string()),
+                BlockAction::DeleteBlock => {
+                    new_content = remove_block(&content, range.clone());
+                    content_changed = true;
+                    action = Some(BlockAction::BlockDeleted { previous_content: content.clone() });
+                }
+                BlockAction::ConvertToQuote => {
+                    new_content = replace_block(quote_block(source));
+                    content_changed = true;
+                }
+                BlockAction::ConvertToCallout => {
+                    new_content = replace_block(callout_block(source, "note"));
+                    content_changed = true;
+                }
+                BlockAction::BookmarkBlock | BlockAction::PinBlock => {
+                    let pin = matches!(toolbar_action, BlockAction::PinBlock);
+                    let anchor = match (&self.parsed_blocks[idx], block_id(source)) {
+                        (ParsedBlock::Heading { text, .. }, _) => text.trim().to_string(),
+                        (_, Some(id)) => format!("^{}", id),
+                        (block, None) => {
+                            let id = new_block_id(&content);
+                            // Code blocks and tables can't end in an id, so it gets its own line
+                            let separate = matches!(block, ParsedBlock::CodeBlock { .. } | ParsedBlock::Table { .. });
+                            let marker = if separate { format!("\n\n^{}", id) } else { format!(" ^{}", id) };
+                            new_content = replace_block(format!("{}{}", source, marker));
+                            content_changed = true;
+                            format!("^{}", id)
+                        }
+                    };
+                    action = Some(if pin { BlockAction::Pin(anchor) } else { BlockAction::Bookmark(anchor) });
+                }
+                other => action = Some(other),
+            }
+        }
+
         // Apply content changes
         if content_changed {
             document.set_content(new_content);
@@ -175,21 +495,125 @@ impl LivePreviewEditor {
     }
 }
 
+/// Show the raw markdown of a region in a text edit whose caret follows the
+/// document cursor. Keys that would leave the region act on the document:
+/// arrows move into the neighbouring line, Enter at the end starts a new
+/// block, and Backspace at the start merges with the block before.
+fn show_raw_editor(ui: &mut Ui, content: &str, region: RawRegion, cursor: usize, resync: bool) -> RawEdit {
+    let id = egui::Id::new(RAW_EDITOR_ID);
+    let range = region.range;
+    let source = &content[range.clone()];
+    let mut text = source.to_string();
+
+    if resync {
+        let local = floor_char_boundary(&text, cursor.saturating_sub(range.start));
+        let mut state = TextEdit::load_state(ui.ctx(), id).unwrap_or_default();
+        state.cursor.set_char_range(Some(CCursorRange::one(CCursor::new(byte_to_char(&text, local)))));
+        state.store(ui.ctx(), id);
+        ui.memory_mut(|mem| mem.request_focus(id));
+    }
+
+    // Caret position before this frame's input, when nothing is selected
+    let caret = TextEdit::load_state(ui.ctx(), id)
+        .and_then(|state| state.cursor.char_range())
+        .filter(|range| range.primary == range.secondary)
+        .map(|range| char_to_byte(&text, range.primary.index));
+
+    let mut moved = None;
+    let mut replaced = None;
+    // Keys leaving the block stay with an input method while it composes
+    let composing = ime::composing(ui.ctx());
+    if let Some(local) = caret.filter(|_| ui.memory(|mem| mem.has_focus(id)) && !composing) {
+        let line_start = text[..local].rfind('\n').map_or(0, |i| i + 1);
+        let column = text[line_start..local].chars().count();
+        let on_first_line = line_start == 0;
+        let on_last_line = !text[local..].trim_end_matches('\n').contains('\n');
+        let pos = range.start + local;
+        let (line, _) = byte_to_line_col(content, pos);
+
+        ui.input_mut(|i| {
+            if on_first_line && i.key_pressed(Key::ArrowUp) {
+                if let Some(target) = line.checked_sub(1).and_then(|above| line_col_to_byte(content, above, column)) {
+                    i.consume_key(Modifiers::NONE, Key::ArrowUp);
+                    moved = Some(target);
+                }
+            } else if on_last_line && i.key_pressed(Key::ArrowDown) {
+                if let Some(target) = line_col_to_byte(content, line + 1, column).filter(|t| *t >= range.end) {
+                    i.consume_key(Modifiers::NONE, Key::ArrowDown);
+                    moved = Some(target);
+                }
+            } else if local == 0 && range.start > 0 && i.consume_key(Modifiers::NONE, Key::ArrowLeft) {
+                moved = prev_char_boundary(content, range.start);
+            } else if region.splits_on_enter
+                && local == text.trim_end_matches('\n').len()
+                && i.consume_key(Modifiers::NONE, Key::Enter)
+            {
+                let (split, cursor) = split_block(content, pos);
+                replaced = Some(split);
+                moved = Some(cursor);
+            } else if local == 0 && range.start > 0 && i.consume_key(Modifiers::NONE, Key::Backspace) {
+                let (merged, cursor) = merge_with_previous(content, range.start);
+                replaced = Some(merged);
+                moved = Some(cursor);
+            }
+        });
+    }
+
+    let output = TextEdit::multiline(&mut text)
+        .id(id)
+        .font(FontId::monospace(14.0))
+        .desired_width(ui.available_width())
+        .frame(true)
+        .margin(egui::Margin::same(4))
+        .show(ui);
+
+    let mut edit = RawEdit {
+        lost_focus: output.response.lost_focus(),
+        ..Default::default()
+    };
+    if replaced.is_some() {
+        edit.new_content = replaced;
+    } else if text != source {
+        edit.new_content = Some(replace_region(content, range.clone(), &text));
+    }
+    edit.cursor = moved.or_else(|| {
+        output
+            .cursor_range
+            .map(|cursor| range.start + char_to_byte(&text, cursor.primary.index))
+    });
+    edit
+}
+
 /// Simplified live preview that shows the whole document
 /// with formatting, suitable for read-only preview or simpler editing
 pub struct SimpleLivePreview;
 
 impl SimpleLivePreview {
-    /// Show a simplified live preview (read-only)
-    pub fn show(ui: &mut Ui, content: &str) -> Option<BlockAction> {
+    /// Show a simplified live preview (read-only), optionally limited to the
+    /// first `max_blocks` non-blank blocks
+    pub fn show(ui: &mut Ui, content: &str, max_blocks: Option<usize>) -> Option<BlockAction> {
         let blocks = parse_blocks(content);
+        let render_ctx = RenderContext {
+            footnotes: collect_footnotes(&blocks),
+            ..Default::default()
+        };
         let mut action = None;
 
+        let limit = max_blocks.unwrap_or(usize::MAX);
+        let mut shown = 0;
+        let blocks = blocks.iter().take_while(|block| {
+            if !matches!(block, ParsedBlock::BlankLine { .. }) {
+                shown += 1;
+            }
+            shown <= limit
+        });
+
         ScrollArea::vertical()
             .id_salt("simple_live_preview")
+            .max_height(if max_blocks.is_some() { 320.0 } else { f32::INFINITY })
             .show(ui, |ui| {
-                for block in &blocks {
-                    if let Some(a) = render_block(ui, block) {
+                for block in blocks {
+                    if let Some(a) = render_block(ui, block, &render_ctx) {
                         action = Some(a);
                     }
                 }
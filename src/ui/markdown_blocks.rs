@@ -4,8 +4,12 @@
 //! enabling cursor-aware rendering where the block containing the cursor
 //! shows raw markdown while others show rendered output.
 
+use std::collections::HashMap;
 use std::ops::Range;
 
+/// Placeholder that expands to a live list of the folder's notes
+pub const CHILDREN_PLACEHOLDER: &str = "{{children}}";
+
 /// A list item with potential checkbox state
 #[derive(Debug, Clone)]
 pub struct ListItem {
@@ -15,6 +19,17 @@ pub struct ListItem {
     pub checkbox: Option<bool>,
     /// Nested items (for sublists)
     pub children: Vec<ListItem>,
+    /// Whether the nested sublist is ordered
+    pub children_ordered: bool,
+    /// Starting number of an ordered sublist
+    pub children_start: Option<u64>,
+}
+
+/// A list being parsed, possibly nested inside an item of its parent
+struct ListFrame {
+    items: Vec<ListItem>,
+    ordered: bool,
+    start: Option<u64>,
 }
 
 /// A table cell
@@ -33,6 +48,16 @@ pub enum TableAlignment {
     Right,
 }
 
+impl From<pulldown_cmark::Alignment> for TableAlignment {
+    fn from(alignment: pulldown_cmark::Alignment) -> Self {
+        match alignment {
+            pulldown_cmark::Alignment::None | pulldown_cmark::Alignment::Left => Self::Left,
+            pulldown_cmark::Alignment::Center => Self::Center,
+            pulldown_cmark::Alignment::Right => Self::Right,
+        }
+    }
+}
+
 /// A parsed markdown block with its byte range in the source
 #[derive(Debug, Clone)]
 pub enum ParsedBlock {
@@ -101,6 +126,18 @@ pub enum ParsedBlock {
     BlankLine {
         range: Range<usize>,
     },
+
+    /// Footnote definition `[^label]: text`
+    FootnoteDefinition {
+        label: String,
+        text: String,
+        range: Range<usize>,
+    },
+
+    /// `{{children}}` placeholder listing the notes of the current folder
+    ChildNotes {
+        range: Range<usize>,
+    },
 }
 
 impl ParsedBlock {
@@ -117,6 +154,37 @@ impl ParsedBlock {
             ParsedBlock::Table { range, .. } => range.clone(),
             ParsedBlock::Image { range, .. } => range.clone(),
             ParsedBlock::BlankLine { range, .. } => range.clone(),
+            ParsedBlock::FootnoteDefinition { range, .. } => range.clone(),
+            ParsedBlock::ChildNotes { range } => range.clone(),
+        }
+    }
+
+    /// Get the byte range of this block mutably
+    fn range_mut(&mut self) -> &mut Range<usize> {
+        match self {
+            ParsedBlock::Heading { range, .. } => range,
+            ParsedBlock::Paragraph { range, .. } => range,
+            ParsedBlock::CodeBlock { range, .. } => range,
+            ParsedBlock::List { range, .. } => range,
+            ParsedBlock::WikiLink { range, .. } => range,
+            ParsedBlock::BlockQuote { range, .. } => range,
+            ParsedBlock::HorizontalRule { range, .. } => range,
+            ParsedBlock::Table { range, .. } => range,
+            ParsedBlock::Image { range, .. } => range,
+            ParsedBlock::BlankLine { range, .. } => range,
+            ParsedBlock::FootnoteDefinition { range, .. } => range,
+            ParsedBlock::ChildNotes { range } => range,
+        }
+    }
+
+    /// Move this block (and any nested blocks) by `delta` bytes
+    fn shift(&mut self, delta: isize) {
+        let range = self.range_mut();
+        *range = range.start.saturating_add_signed(delta)..range.end.saturating_add_signed(delta);
+        if let ParsedBlock::BlockQuote { content, .. } = self {
+            for block in content {
+                block.shift(delta);
+            }
         }
     }
 
@@ -127,6 +195,107 @@ impl ParsedBlock {
     }
 }
 
+/// Markers whose edits can change how the rest of the document parses
+const NON_LOCAL_MARKERS: &[&str] = &["```", "~~~", "<!--", "-->", "]:"];
+
+/// Update `blocks` (parsed from `old`) to match `new`, reparsing only the
+/// blocks around the edited region and shifting the ranges of later blocks.
+///
+/// Falls back to a full parse when the edit touches markers such as code
+/// fences whose effect isn't confined to the surrounding blocks.
+pub fn reparse_blocks(blocks: &mut Vec<ParsedBlock>, old: &str, new: &str) {
+    if old == new {
+        return;
+    }
+
+    // Edited region: everything between the common prefix and suffix
+    let prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_edit = prefix..old.len() - suffix;
+    let new_edit = prefix..new.len() - suffix;
+
+    let touches_non_local = |text: &str, edit: &Range<usize>| {
+        // Widen to whole lines so partially edited markers are caught
+        let bytes = text.as_bytes();
+        let start = bytes[..edit.start].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let end = bytes[edit.end..].iter().position(|&b| b == b'\n').map_or(text.len(), |i| edit.end + i);
+        let lines = String::from_utf8_lossy(&bytes[start..end]);
+        NON_LOCAL_MARKERS.iter().any(|marker| lines.contains(marker))
+    };
+    if blocks.is_empty() || touches_non_local(old, &old_edit) || touches_non_local(new, &new_edit) {
+        *blocks = parse_blocks(new);
+        return;
+    }
+
+    // Affected blocks, plus one neighbour on each side since an edit can
+    // merge a block with the one before or after it
+    let first = blocks
+        .iter()
+        .position(|block| block.range().end >= old_edit.start)
+        .unwrap_or(blocks.len() - 1)
+        .saturating_sub(1);
+    let mut last = blocks
+        .iter()
+        .rposition(|block| block.range().start <= old_edit.end)
+        .map_or(first, |idx| (idx + 1).min(blocks.len() - 1))
+        .max(first);
+
+    let start = if first == 0 {
+        0
+    } else {
+        // From the start of the line, since indentation decides what some blocks are
+        let start = blocks[first].range().start.min(old_edit.start);
+        old[..start].rfind('\n').map_or(0, |i| i + 1)
+    };
+    let delta = new.len() as isize - old.len() as isize;
+    let region_blocks = loop {
+        let end = if last == blocks.len() - 1 {
+            old.len()
+        } else {
+            blocks[last].range().end.max(old_edit.end)
+        };
+        let new_end = end.saturating_add_signed(delta);
+
+        // Region boundaries come from unchanged text, but guard against splitting a character
+        if !new.is_char_boundary(start) || !new.is_char_boundary(new_end) {
+            *blocks = parse_blocks(new);
+            return;
+        }
+
+        let mut region_blocks = parse_blocks(&new[start..new_end]);
+        for block in &mut region_blocks {
+            block.shift(start as isize);
+        }
+
+        // The trailing neighbour is unedited, so it must come out as before;
+        // otherwise the edit runs on into it (e.g. a new list item swallowing
+        // the next paragraph) and the region has to grow
+        let mut neighbour = blocks[last].clone();
+        neighbour.shift(delta);
+        let settled = last == blocks.len() - 1
+            || region_blocks.last().is_some_and(|block| format!("{:?}", block) == format!("{:?}", neighbour));
+        if settled {
+            break region_blocks;
+        }
+        last += 1;
+    };
+    for block in &mut blocks[last + 1..] {
+        block.shift(delta);
+    }
+    blocks.splice(first..=last, region_blocks);
+}
+
 /// Parse markdown content into blocks
 pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
     let mut blocks = Vec::new();
@@ -143,24 +312,33 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
     options.insert(Options::ENABLE_STRIKETHROUGH);
     options.insert(Options::ENABLE_TABLES);
     options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_MATH);
 
     let parser = Parser::new_ext(&processed_content, options);
 
     let mut block_start = 0;
     let mut current_text = String::new();
     let mut code_lang: Option<String> = None;
-    let mut list_items: Vec<ListItem> = Vec::new();
-    let mut list_ordered = false;
-    let mut list_start: Option<u64> = None;
-    let mut in_list_item = false;
+    let mut in_code_block = false;
+    // Open lists and items, outermost first; the text of the innermost item
+    // accumulates in `current_item_text`
+    let mut list_stack: Vec<ListFrame> = Vec::new();
+    let mut item_stack: Vec<ListItem> = Vec::new();
     let mut current_item_text = String::new();
-    let mut item_checkbox: Option<bool> = None;
+    // Open blockquotes: their start offset and the enclosing blocks, which are
+    // set aside while the quote's own children collect in `blocks`
+    let mut quote_stack: Vec<(usize, Vec<ParsedBlock>)> = Vec::new();
     let mut table_headers: Vec<TableCell> = Vec::new();
     let mut table_rows: Vec<Vec<TableCell>> = Vec::new();
     let mut current_row: Vec<TableCell> = Vec::new();
     let mut in_table_head = false;
+    let mut table_alignments: Vec<TableAlignment> = Vec::new();
+    let mut footnote_label: Option<String> = None;
+    let mut link_urls: Vec<String> = Vec::new();
 
     for (event, range) in parser.into_offset_iter() {
+        let in_list_item = !item_stack.is_empty();
         match event {
             Event::Start(tag) => {
                 match &tag {
@@ -169,12 +347,30 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                         current_text.clear();
                     }
                     Tag::Paragraph => {
+                        if in_list_item {
+                            // Loose list items wrap their text in paragraphs
+                            if !current_item_text.is_empty() {
+                                current_item_text.push('\n');
+                            }
+                        } else if footnote_label.is_some() {
+                            // Paragraphs inside a footnote definition are merged into it
+                            if !current_text.is_empty() {
+                                current_text.push('\n');
+                            }
+                        } else {
+                            block_start = range.start;
+                            current_text.clear();
+                        }
+                    }
+                    Tag::FootnoteDefinition(label) => {
                         block_start = range.start;
                         current_text.clear();
+                        footnote_label = Some(label.to_string());
                     }
                     Tag::CodeBlock(kind) => {
                         block_start = range.start;
                         current_text.clear();
+                        in_code_block = true;
                         code_lang = match kind {
                             pulldown_cmark::CodeBlockKind::Fenced(lang) => {
                                 let lang = lang.to_string();
@@ -188,24 +384,37 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                         };
                     }
                     Tag::List(start) => {
-                        block_start = range.start;
-                        list_ordered = start.is_some();
-                        list_start = *start;
-                        list_items.clear();
+                        if let Some(parent) = item_stack.last_mut() {
+                            // Park the parent item's text while the sublist is parsed
+                            parent.text = std::mem::take(&mut current_item_text);
+                        } else {
+                            block_start = range.start;
+                        }
+                        list_stack.push(ListFrame {
+                            items: Vec::new(),
+                            ordered: start.is_some(),
+                            start: *start,
+                        });
                     }
                     Tag::Item => {
-                        in_list_item = true;
                         current_item_text.clear();
-                        item_checkbox = None;
+                        item_stack.push(ListItem {
+                            text: String::new(),
+                            checkbox: None,
+                            children: Vec::new(),
+                            children_ordered: false,
+                            children_start: None,
+                        });
                     }
-                    Tag::BlockQuote(_) => {
-                        block_start = range.start;
-                        current_text.clear();
+                    // Quotes inside list items stay part of the item's text
+                    Tag::BlockQuote(_) if !in_list_item => {
+                        quote_stack.push((range.start, std::mem::take(&mut blocks)));
                     }
-                    Tag::Table(_) => {
+                    Tag::Table(alignments) => {
                         block_start = range.start;
                         table_headers.clear();
                         table_rows.clear();
+                        table_alignments = alignments.iter().map(|a| (*a).into()).collect();
                     }
                     Tag::TableHead => {
                         in_table_head = true;
@@ -217,6 +426,23 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                     Tag::TableCell => {
                         current_text.clear();
                     }
+                    Tag::Strong | Tag::Emphasis | Tag::Strikethrough | Tag::Link { .. } => {
+                        // Re-emit normalized inline markup for `parse_inline`
+                        let marker = match &tag {
+                            Tag::Strong => "**",
+                            Tag::Emphasis => "*",
+                            Tag::Strikethrough => "~~",
+                            _ => "[",
+                        };
+                        if let Tag::Link { dest_url, .. } = &tag {
+                            link_urls.push(dest_url.to_string());
+                        }
+                        if in_list_item {
+                            current_item_text.push_str(marker);
+                        } else {
+                            current_text.push_str(marker);
+                        }
+                    }
                     Tag::Image { dest_url, title, .. } => {
                         // Images are inline but we treat them as blocks
                         let alt = current_text.clone();
@@ -246,7 +472,13 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                     }
                     TagEnd::Paragraph => {
                         // Check if this paragraph contains only a wiki link placeholder
-                        if let Some((target, display)) =
+                        if in_list_item || footnote_label.is_some() {
+                            // Emitted with the enclosing footnote definition
+                        } else if current_text.trim() == CHILDREN_PLACEHOLDER {
+                            blocks.push(ParsedBlock::ChildNotes {
+                                range: block_start..range.end,
+                            });
+                        } else if let Some((target, display)) =
                             find_wiki_link_in_text(&current_text)
                         {
                             blocks.push(ParsedBlock::WikiLink {
@@ -261,7 +493,30 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                             });
                         }
                     }
+                    TagEnd::Strong | TagEnd::Emphasis | TagEnd::Strikethrough | TagEnd::Link => {
+                        let marker = match tag_end {
+                            TagEnd::Strong => "**".to_string(),
+                            TagEnd::Emphasis => "*".to_string(),
+                            TagEnd::Strikethrough => "~~".to_string(),
+                            _ => format!("]({})", link_destination(&link_urls.pop().unwrap_or_default())),
+                        };
+                        if in_list_item {
+                            current_item_text.push_str(&marker);
+                        } else {
+                            current_text.push_str(&marker);
+                        }
+                    }
+                    TagEnd::FootnoteDefinition => {
+                        if let Some(label) = footnote_label.take() {
+                            blocks.push(ParsedBlock::FootnoteDefinition {
+                                label,
+                                text: current_text.trim().to_string(),
+                                range: block_start..range.end,
+                            });
+                        }
+                    }
                     TagEnd::CodeBlock => {
+                        in_code_block = false;
                         blocks.push(ParsedBlock::CodeBlock {
                             lang: code_lang.take(),
                             code: current_text.clone(),
@@ -269,29 +524,39 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                         });
                     }
                     TagEnd::List(_) => {
-                        blocks.push(ParsedBlock::List {
-                            items: list_items.clone(),
-                            ordered: list_ordered,
-                            start: list_start,
-                            range: block_start..range.end,
-                        });
+                        if let Some(frame) = list_stack.pop() {
+                            if let Some(parent) = item_stack.last_mut() {
+                                parent.children = frame.items;
+                                parent.children_ordered = frame.ordered;
+                                parent.children_start = frame.start;
+                                current_item_text = std::mem::take(&mut parent.text);
+                            } else {
+                                blocks.push(ParsedBlock::List {
+                                    items: frame.items,
+                                    ordered: frame.ordered,
+                                    start: frame.start,
+                                    range: block_start..range.end,
+                                });
+                            }
+                        }
                     }
                     TagEnd::Item => {
-                        list_items.push(ListItem {
-                            text: current_item_text.clone(),
-                            checkbox: item_checkbox,
-                            children: Vec::new(),
-                        });
-                        in_list_item = false;
+                        if let Some(mut item) = item_stack.pop() {
+                            item.text = current_item_text.trim().to_string();
+                            current_item_text.clear();
+                            if let Some(frame) = list_stack.last_mut() {
+                                frame.items.push(item);
+                            }
+                        }
                     }
-                    TagEnd::BlockQuote(_) => {
-                        blocks.push(ParsedBlock::BlockQuote {
-                            content: vec![ParsedBlock::Paragraph {
-                                text: current_text.clone(),
-                                range: block_start..range.end,
-                            }],
-                            range: block_start..range.end,
-                        });
+                    TagEnd::BlockQuote(_) if !in_list_item => {
+                        if let Some((start, outer)) = quote_stack.pop() {
+                            let content = std::mem::replace(&mut blocks, outer);
+                            blocks.push(ParsedBlock::BlockQuote {
+                                content,
+                                range: start..range.end,
+                            });
+                        }
                     }
                     TagEnd::Table => {
                         blocks.push(ParsedBlock::Table {
@@ -310,16 +575,26 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
                         }
                     }
                     TagEnd::TableCell => {
-                        current_row.push(TableCell {
-                            content: current_text.clone(),
-                            alignment: TableAlignment::Left,
-                        });
+                        // Keep the cell's source so inline formatting can be rendered
+                        let content = processed_content[range.clone()]
+                            .trim()
+                            .trim_matches('|')
+                            .trim()
+                            .replace("\\|", "|");
+                        let alignment = table_alignments
+                            .get(current_row.len())
+                            .copied()
+                            .unwrap_or_default();
+                        current_row.push(TableCell { content, alignment });
                     }
                     _ => {}
                 }
             }
 
             Event::Text(text) => {
+                // Escape literal markup so only real emphasis is re-parsed
+                // later; code blocks aren't re-parsed and keep it as is
+                let text = if in_code_block { text.to_string() } else { escape_inline(&text) };
                 if in_list_item {
                     current_item_text.push_str(&text);
                 } else {
@@ -328,14 +603,31 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
             }
 
             Event::Code(code) => {
+                let code = code_span(&code);
                 if in_list_item {
-                    current_item_text.push('`');
                     current_item_text.push_str(&code);
-                    current_item_text.push('`');
                 } else {
-                    current_text.push('`');
                     current_text.push_str(&code);
-                    current_text.push('`');
+                }
+            }
+
+            Event::InlineMath(tex) | Event::DisplayMath(tex) => {
+                // Kept as `$...$` for inline parsing to render as math
+                let math = format!("${}$", tex.trim());
+                if in_list_item {
+                    current_item_text.push_str(&math);
+                } else {
+                    current_text.push_str(&math);
+                }
+            }
+
+            Event::FootnoteReference(label) => {
+                // Keep the reference in the text so inline parsing can render it
+                let reference = format!("[^{}]", label);
+                if in_list_item {
+                    current_item_text.push_str(&reference);
+                } else {
+                    current_text.push_str(&reference);
                 }
             }
 
@@ -354,7 +646,9 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
             }
 
             Event::TaskListMarker(checked) => {
-                item_checkbox = Some(checked);
+                if let Some(item) = item_stack.last_mut() {
+                    item.checkbox = Some(checked);
+                }
             }
 
             _ => {}
@@ -377,6 +671,43 @@ pub fn parse_blocks(content: &str) -> Vec<ParsedBlock> {
     blocks
 }
 
+/// Replace the source of `range` with `text`, leaving the rest of the content
+/// as it was
+pub fn replace_region(content: &str, range: Range<usize>, text: &str) -> String {
+    format!("{}{}{}", &content[..range.start], text, &content[range.end..])
+}
+
+/// Remove a block from the content, leaving a single blank line between its
+/// neighbours
+pub fn remove_block(content: &str, range: Range<usize>) -> String {
+    let before = content[..range.start].trim_end_matches('\n');
+    let after = content[range.end..].trim_start_matches('\n');
+    match (before.is_empty(), after.is_empty()) {
+        (true, _) => after.to_string(),
+        (false, true) => format!("{}\n", before),
+        (false, false) => format!("{}\n\n{}", before, after),
+    }
+}
+
+/// Open an empty line at `pos` with blank lines on both sides, starting a new
+/// block. Returns the new content and the position of the empty line.
+pub fn split_block(content: &str, pos: usize) -> (String, usize) {
+    (format!("{}\n\n{}", &content[..pos], &content[pos..]), pos + 2)
+}
+
+/// Join the text starting at `start` with the block before it. A blank line
+/// between them collapses to a line break; a single line break is removed.
+/// Returns the new content and the position of the join.
+pub fn merge_with_previous(content: &str, start: usize) -> (String, usize) {
+    let previous_end = content[..start].trim_end_matches('\n').len();
+    let separator = &content[previous_end..start];
+    let joiner = if separator.matches('\n').count() > 1 { "\n" } else { "" };
+    (
+        format!("{}{}{}", &content[..previous_end], joiner, &content[start..]),
+        previous_end + joiner.len(),
+    )
+}
+
 /// Extract wiki links from content and return processed content with placeholders
 fn extract_wiki_links(content: &str) -> (String, Vec<(String, Option<String>)>) {
     let mut result = content.to_string();
@@ -409,9 +740,39 @@ fn find_wiki_link_in_text(text: &str) -> Option<(String, Option<String>)> {
     None
 }
 
-/// Find the block containing a given byte position
-pub fn find_block_at_position(blocks: &[ParsedBlock], byte_pos: usize) -> Option<usize> {
-    blocks.iter().position(|block| block.contains(byte_pos))
+/// Source region shown as raw markdown while the cursor is at `pos`.
+///
+/// This is the (widest) block containing the cursor, or else the blank gap
+/// between the blocks around it. A block's range ends after its newline, so a
+/// cursor there belongs to the next line; only a block ending the document
+/// without a newline also claims the position right after it.
+pub fn edit_region(blocks: &[ParsedBlock], content: &str, pos: usize) -> (Option<usize>, Range<usize>) {
+    let containing = blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| {
+            let range = block.range();
+            block.contains(pos)
+                || (pos == range.end && range.end == content.len() && !content.ends_with('\n'))
+        })
+        .max_by_key(|(_, block)| block.range().len());
+    if let Some((idx, block)) = containing {
+        return (Some(idx), block.range());
+    }
+
+    let start = blocks
+        .iter()
+        .map(|block| block.range().end)
+        .filter(|end| *end <= pos)
+        .max()
+        .unwrap_or(0);
+    let end = blocks
+        .iter()
+        .map(|block| block.range().start)
+        .filter(|start| *start > pos)
+        .min()
+        .unwrap_or(content.len());
+    (None, start..end)
 }
 
 /// Parse inline wiki links from text, returning spans with their types
@@ -422,34 +783,149 @@ pub enum InlineSpan {
     Code(String),
     Bold(String),
     Italic(String),
+    BoldItalic(String),
+    Strikethrough(String),
+    Highlight(String),
     Link { text: String, url: String },
+    FootnoteRef(String),
+    /// TeX between single dollar signs
+    Math(String),
+}
+
+/// Characters that carry inline markup and are escaped in literal text
+const INLINE_MARKUP_CHARS: &[char] = &['\\', '*', '_', '~', '`', '=', '$'];
+
+/// Escape markup characters in literal text so `parse_inline` keeps them as is
+fn escape_inline(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if INLINE_MARKUP_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Remove backslash escapes from inline text
+fn unescape_inline(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(&next) = chars.peek() {
+                if next.is_ascii_punctuation() {
+                    result.push(next);
+                    chars.next();
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Link destination as written in `[text](...)`: in angle brackets when
+/// it has spaces, parentheses or angle brackets of its own
+pub fn link_destination(url: &str) -> String {
+    if !url.contains(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '<' | '>')) {
+        return url.to_string();
+    }
+    format!("<{}>", url.replace('\\', "\\\\").replace('<', "\\<").replace('>', "\\>"))
+}
+
+/// Code span for `code`: fenced by more backticks than any run inside it,
+/// and padded with spaces where backticks or spaces would join the fence
+fn code_span(code: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let pad = code.starts_with('`') || code.ends_with('`') || (code.starts_with(' ') && code.ends_with(' '));
+    if pad && !code.trim().is_empty() {
+        format!("{} {} {}", fence, code, fence)
+    } else {
+        format!("{}{}{}", fence, code, fence)
+    }
+}
+
+/// Constructor for a styled inline span
+type SpanConstructor = fn(String) -> InlineSpan;
+
+/// Emphasis-like delimiters and the span they produce, longest first
+const EMPHASIS_DELIMITERS: &[(&[char], SpanConstructor)] = &[
+    (&['*', '*', '*'], InlineSpan::BoldItalic),
+    (&['*', '*'], InlineSpan::Bold),
+    (&['_', '_'], InlineSpan::Bold),
+    (&['~', '~'], InlineSpan::Strikethrough),
+    (&['=', '='], InlineSpan::Highlight),
+    (&['*'], InlineSpan::Italic),
+    (&['_'], InlineSpan::Italic),
+];
+
+/// Find the next unescaped occurrence of `delim` at or after `from`
+fn find_delimiter(chars: &[char], from: usize, delim: &[char]) -> Option<usize> {
+    let mut i = from;
+    while i + delim.len() <= chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i..i + delim.len()] == *delim {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
 }
 
 /// Parse inline elements from text
+///
+/// Handles wiki links, footnote references, inline code, `[text](url)` links,
+/// `***bold italic***`, `**bold**`/`__bold__`, `*italic*`/`_italic_`,
+/// `~~strikethrough~~`, and `==highlight==`. Backslash escapes are honored.
 pub fn parse_inline(text: &str) -> Vec<InlineSpan> {
     let mut spans = Vec::new();
     let mut current_text = String::new();
     let chars: Vec<char> = text.chars().collect();
     let mut i = 0;
 
+    // Flush pending plain text before pushing a styled span
+    fn flush(spans: &mut Vec<InlineSpan>, current_text: &mut String) {
+        if !current_text.is_empty() {
+            spans.push(InlineSpan::Text(std::mem::take(current_text)));
+        }
+    }
+
     while i < chars.len() {
-        // Check for wiki link [[...]]
-        if i + 1 < chars.len() && chars[i] == '[' && chars[i + 1] == '[' {
-            // Flush current text
-            if !current_text.is_empty() {
-                spans.push(InlineSpan::Text(current_text.clone()));
-                current_text.clear();
-            }
+        // Backslash escape
+        if chars[i] == '\\' && i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() {
+            current_text.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
 
-            // Find closing ]]
+        // Check for footnote reference [^label]
+        if i + 1 < chars.len() && chars[i] == '[' && chars[i + 1] == '^' {
             let start = i + 2;
             let mut end = start;
-            while end + 1 < chars.len() && !(chars[end] == ']' && chars[end + 1] == ']') {
+            while end < chars.len() && chars[end] != ']' && !chars[end].is_whitespace() {
                 end += 1;
             }
 
-            if end + 1 < chars.len() {
-                let link_content: String = chars[start..end].iter().collect();
+            if end < chars.len() && chars[end] == ']' && end > start {
+                flush(&mut spans, &mut current_text);
+                let label: String = chars[start..end].iter().collect();
+                spans.push(InlineSpan::FootnoteRef(label));
+                i = end + 1;
+                continue;
+            }
+        }
+
+        // Check for wiki link [[...]]
+        if i + 1 < chars.len() && chars[i] == '[' && chars[i + 1] == '[' {
+            if let Some(end) = find_delimiter(&chars, i + 2, &[']', ']']) {
+                flush(&mut spans, &mut current_text);
+                let link_content: String = chars[i + 2..end].iter().collect();
                 let parts: Vec<&str> = link_content.splitn(2, '|').collect();
                 let target = parts[0].to_string();
                 let display = parts.get(1).map(|s| s.to_string());
@@ -460,36 +936,320 @@ pub fn parse_inline(text: &str) -> Vec<InlineSpan> {
             }
         }
 
-        // Check for inline code `...`
-        if chars[i] == '`' {
-            // Flush current text
-            if !current_text.is_empty() {
-                spans.push(InlineSpan::Text(current_text.clone()));
-                current_text.clear();
+        // Check for standard link [text](url) or [text](<url>)
+        if chars[i] == '[' {
+            if let Some(text_end) = find_delimiter(&chars, i + 1, &[']', '(']) {
+                let url_start = text_end + 2;
+                let destination = if chars.get(url_start) == Some(&'<') {
+                    find_delimiter(&chars, url_start + 1, &['>', ')']).map(|end| {
+                        let url: String = chars[url_start + 1..end].iter().collect();
+                        (unescape_inline(&url), end + 1)
+                    })
+                } else {
+                    find_delimiter(&chars, url_start, &[')']).map(|end| {
+                        let url: String = chars[url_start..end].iter().collect();
+                        (url.trim().to_string(), end)
+                    })
+                };
+                if let Some((url, url_end)) = destination {
+                    flush(&mut spans, &mut current_text);
+                    let link_text: String = chars[i + 1..text_end].iter().collect();
+                    spans.push(InlineSpan::Link { text: unescape_inline(&link_text), url });
+                    i = url_end + 1;
+                    continue;
+                }
             }
+        }
 
-            let start = i + 1;
+        // Check for inline code: a run of backticks closed by a run of the
+        // same length, with one padding space trimmed from each side
+        if chars[i] == '`' {
+            let fence = chars[i..].iter().take_while(|c| **c == '`').count();
+            let start = i + fence;
             let mut end = start;
-            while end < chars.len() && chars[end] != '`' {
-                end += 1;
+            let mut close = None;
+            while end < chars.len() {
+                let run = chars[end..].iter().take_while(|c| **c == '`').count();
+                if run == fence {
+                    close = Some(end);
+                    break;
+                }
+                end += run.max(1);
             }
 
-            if end < chars.len() {
-                let code: String = chars[start..end].iter().collect();
+            if let Some(end) = close {
+                flush(&mut spans, &mut current_text);
+                let mut code: String = chars[start..end].iter().collect();
+                if code.starts_with(' ') && code.ends_with(' ') && !code.trim().is_empty() {
+                    code = code[1..code.len() - 1].to_string();
+                }
                 spans.push(InlineSpan::Code(code));
+                i = end + fence;
+                continue;
+            }
+            // An unclosed run of backticks is literal text
+            current_text.extend(&chars[i..start]);
+            i = start;
+            continue;
+        }
+
+        // Check for inline math $...$: no space inside the dollars and no
+        // digit after the closing one, so prices stay text
+        if chars[i] == '$' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace() && *c != '$') {
+            let close = find_delimiter(&chars, i + 1, &['$']).filter(|&end| {
+                !chars[end - 1].is_whitespace() && !chars.get(end + 1).is_some_and(char::is_ascii_digit)
+            });
+            if let Some(end) = close {
+                flush(&mut spans, &mut current_text);
+                spans.push(InlineSpan::Math(chars[i + 1..end].iter().collect()));
                 i = end + 1;
                 continue;
             }
         }
 
+        // Check for emphasis-like delimiters
+        let mut matched = false;
+        for &(delim, make_span) in EMPHASIS_DELIMITERS {
+            if !chars[i..].starts_with(delim) {
+                continue;
+            }
+
+            // Intraword underscores are literal (snake_case)
+            if delim[0] == '_' && i > 0 && chars[i - 1].is_alphanumeric() {
+                continue;
+            }
+
+            let start = i + delim.len();
+            if start >= chars.len() || chars[start].is_whitespace() {
+                continue;
+            }
+
+            if let Some(end) = find_delimiter(&chars, start + 1, delim) {
+                flush(&mut spans, &mut current_text);
+                let inner: String = chars[start..end].iter().collect();
+                spans.push(make_span(unescape_inline(&inner)));
+                i = end + delim.len();
+                matched = true;
+                break;
+            }
+        }
+        if matched {
+            continue;
+        }
+
         current_text.push(chars[i]);
         i += 1;
     }
 
     // Flush remaining text
-    if !current_text.is_empty() {
-        spans.push(InlineSpan::Text(current_text));
-    }
+    flush(&mut spans, &mut current_text);
 
     spans
 }
+
+/// Collect footnote definitions from parsed blocks, keyed by label
+pub fn collect_footnotes(blocks: &[ParsedBlock]) -> HashMap<String, String> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ParsedBlock::FootnoteDefinition { label, text, .. } => {
+                Some((label.clone(), text.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footnote_definition_and_reference() {
+        let content = "Some claim[^1].\n\n[^1]: The source.\n";
+        let blocks = parse_blocks(content);
+
+        let footnotes = collect_footnotes(&blocks);
+        assert_eq!(footnotes.get("1").map(String::as_str), Some("The source."));
+
+        let ParsedBlock::Paragraph { text, .. } = &blocks[0] else {
+            panic!("expected paragraph, got {:?}", blocks[0]);
+        };
+        let spans = parse_inline(text);
+        assert!(spans
+            .iter()
+            .any(|span| matches!(span, InlineSpan::FootnoteRef(label) if label == "1")));
+    }
+
+    #[test]
+    fn test_nested_blockquote_content() {
+        let content = "> Intro\n>\n> - item\n>\n> ```rust\n> let x = 1;\n> ```\n>\n> > inner\n";
+        let blocks = parse_blocks(content);
+        assert_eq!(blocks.len(), 1);
+
+        let ParsedBlock::BlockQuote { content: children, range } = &blocks[0] else {
+            panic!("expected blockquote, got {:?}", blocks[0]);
+        };
+        assert_eq!(*range, 0..content.len());
+        assert!(matches!(&children[0], ParsedBlock::Paragraph { text, .. } if text == "Intro"));
+        assert!(matches!(&children[1], ParsedBlock::List { .. }));
+        assert!(matches!(&children[2], ParsedBlock::CodeBlock { lang: Some(lang), .. } if lang == "rust"));
+        let ParsedBlock::BlockQuote { content: inner, .. } = &children[3] else {
+            panic!("expected nested blockquote, got {:?}", children[3]);
+        };
+        assert!(matches!(&inner[0], ParsedBlock::Paragraph { text, range } if text == "inner" && &content[range.clone()] == "inner\n"));
+    }
+
+    #[test]
+    fn test_block_edits() {
+        let content = "One\n\nTwo\n\nThree\n";
+        let blocks = parse_blocks(content);
+        assert_eq!(remove_block(content, blocks[1].range()), "One\n\nThree\n");
+        assert_eq!(remove_block(content, blocks[2].range()), "One\n\nTwo\n");
+    }
+
+    #[test]
+    fn test_incremental_reparse_matches_full_parse() {
+        let original = "# Title\n\nFirst paragraph.\n\n- a\n- b\n\n> quote\n\nLast paragraph.\n";
+        let edits = [
+            original.replace("First", "Edited first"),
+            original.replace("- b\n", "- b\n- c\n"),
+            original.replace("First paragraph.\n\n", ""),
+            original.replace(".\n\n- a", ".\n- a"),
+            original.replace("Last", "Lä"),
+            original.replace("> quote", "```\ncode"),
+            format!("{}Appended.\n", original),
+        ];
+
+        for edited in edits {
+            let mut blocks = parse_blocks(original);
+            reparse_blocks(&mut blocks, original, &edited);
+            assert_eq!(
+                format!("{:?}", blocks),
+                format!("{:?}", parse_blocks(&edited)),
+                "edit: {:?}",
+                edited
+            );
+        }
+    }
+
+    #[test]
+    fn test_edit_region() {
+        let content = "# Title\n\nBody text\n";
+        let blocks = parse_blocks(content);
+        assert_eq!(edit_region(&blocks, content, 3), (Some(0), 0..8));
+        // The blank line between the blocks is a gap
+        assert_eq!(edit_region(&blocks, content, 8), (None, 8..9));
+        assert_eq!(edit_region(&blocks, content, 12), (Some(1), 9..content.len()));
+        // End of a document ending in a newline is an empty gap
+        assert_eq!(edit_region(&blocks, content, content.len()), (None, content.len()..content.len()));
+
+        let content = "Body";
+        let blocks = parse_blocks(content);
+        assert_eq!(edit_region(&blocks, content, 4), (Some(0), 0..4));
+    }
+
+    #[test]
+    fn test_split_and_merge_blocks() {
+        let content = "One\n\nTwo\n";
+        let (split, cursor) = split_block(content, 3);
+        assert_eq!(split, "One\n\n\n\nTwo\n");
+        assert_eq!(cursor, 5);
+
+        assert_eq!(merge_with_previous(content, 5), ("One\nTwo\n".to_string(), 4));
+        assert_eq!(merge_with_previous("# H\nText", 4), ("# HText".to_string(), 3));
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let content = "- a\n  1. b\n  2. c\n     - [x] d\n- e\n";
+        let blocks = parse_blocks(content);
+        assert_eq!(blocks.len(), 1);
+
+        let ParsedBlock::List { items, ordered, .. } = &blocks[0] else {
+            panic!("expected list, got {:?}", blocks[0]);
+        };
+        assert!(!ordered);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].text, "a");
+        assert!(items[0].children_ordered);
+        assert_eq!(items[0].children.len(), 2);
+        assert_eq!(items[0].children[1].text, "c");
+        assert_eq!(items[0].children[1].children[0].text, "d");
+        assert_eq!(items[0].children[1].children[0].checkbox, Some(true));
+        assert_eq!(items[1].text, "e");
+    }
+
+    #[test]
+    fn test_parse_inline_emphasis_and_links() {
+        let spans = parse_inline("a **b** *c* ~~d~~ ==e== [f](https://g) snake_case \\*x\\*");
+        let kinds: Vec<String> = spans
+            .iter()
+            .map(|span| match span {
+                InlineSpan::Text(t) => format!("T({})", t),
+                InlineSpan::Bold(t) => format!("B({})", t),
+                InlineSpan::Italic(t) => format!("I({})", t),
+                InlineSpan::Strikethrough(t) => format!("S({})", t),
+                InlineSpan::Highlight(t) => format!("H({})", t),
+                InlineSpan::Link { text, url } => format!("L({},{})", text, url),
+                other => format!("{:?}", other),
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                "T(a )", "B(b)", "T( )", "I(c)", "T( )", "S(d)", "T( )", "H(e)", "T( )",
+                "L(f,https://g)", "T( snake_case *x*)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_links_and_code_survive_reparsing() {
+        let blocks = parse_blocks("[a](https://x.org/b_(c)) [d](<e f.md>) ``g`h`` `` `i` ``\n");
+        let ParsedBlock::Paragraph { text, .. } = &blocks[0] else {
+            panic!("expected paragraph, got {:?}", blocks[0]);
+        };
+        let spans = parse_inline(text);
+        assert!(matches!(&spans[0], InlineSpan::Link { url, .. } if url == "https://x.org/b_(c)"));
+        assert!(matches!(&spans[2], InlineSpan::Link { url, .. } if url == "e f.md"));
+        assert!(matches!(&spans[4], InlineSpan::Code(code) if code == "g`h"));
+        assert!(matches!(&spans[6], InlineSpan::Code(code) if code == "`i`"));
+    }
+
+    #[test]
+    fn test_parse_inline_math() {
+        let spans = parse_inline("Let $x^2$, \\$y$ and $5 to $10");
+        assert!(matches!(&spans[0], InlineSpan::Text(t) if t == "Let "));
+        assert!(matches!(&spans[1], InlineSpan::Math(tex) if tex == "x^2"));
+        assert!(matches!(&spans[2], InlineSpan::Text(t) if t == ", $y$ and $5 to $10"));
+    }
+
+    #[test]
+    fn test_paragraph_keeps_emphasis_markup() {
+        let blocks = parse_blocks("Some **bold** and a [link](https://example.com) 2\\*3\n");
+        let ParsedBlock::Paragraph { text, .. } = &blocks[0] else {
+            panic!("expected paragraph, got {:?}", blocks[0]);
+        };
+
+        let spans = parse_inline(text);
+        assert!(matches!(&spans[1], InlineSpan::Bold(t) if t == "bold"));
+        assert!(matches!(&spans[3], InlineSpan::Link { url, .. } if url == "https://example.com"));
+        assert!(matches!(&spans[4], InlineSpan::Text(t) if t == " 2*3"));
+    }
+
+    #[test]
+    fn test_table_alignment_and_cell_source() {
+        let content = "| Name | Qty |\n|:----:|----:|\n| `a` | [[Note]] |\n";
+        let blocks = parse_blocks(content);
+
+        let ParsedBlock::Table { headers, rows, .. } = &blocks[0] else {
+            panic!("expected table, got {:?}", blocks[0]);
+        };
+        assert!(matches!(headers[0].alignment, TableAlignment::Center));
+        assert!(matches!(headers[1].alignment, TableAlignment::Right));
+        assert_eq!(rows[0][0].content, "`a`");
+        assert_eq!(rows[0][1].content, "[[Note]]");
+    }
+}
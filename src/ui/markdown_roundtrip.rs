@@ -0,0 +1,266 @@
+//! Property and golden tests for markdown blocks
+//!
+//! Documents are generated by proptest from a pool of markdown lines, so a
+//! failing case is shrunk to a small document before it is reported. Golden
+//! files live in `tests/fixtures/markdown`.
+
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::sync::Arc;
+
+use eframe::egui;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+
+use crate::golden::{assert_golden, fixtures, fixtures_dir};
+
+use super::block_renderer::{render_block, RenderContext};
+use super::markdown_blocks::{
+    collect_footnotes, edit_region, merge_with_previous, parse_blocks, remove_block, reparse_blocks,
+    replace_region, split_block, ParsedBlock,
+};
+
+/// Documents generated per property
+const CASES: u32 = 500;
+
+/// Lines documents are assembled from
+const LINES: &[&str] = &[
+    "",
+    "",
+    "# Heading",
+    "## Sub *heading*",
+    "Plain paragraph text.",
+    "Text with **bold**, `code` and [[Linked Note|alias]].",
+    "Grüße — ünïcödé ✓ 日本語",
+    "[[Wiki Link]]",
+    "- item",
+    "- [ ] task",
+    "- [x] done",
+    "  - nested",
+    "1. first",
+    "2. second",
+    "> quoted",
+    ">",
+    "> > deeper",
+    "```",
+    "```rust",
+    "let x = 1;",
+    "~~~",
+    "    indented code",
+    "| a | b |",
+    "|---|:-:|",
+    "| 1 | 2 |",
+    "---",
+    "***",
+    "Footnote ref[^1].",
+    "[^1]: The note.",
+    "{{children}}",
+    "<!-- comment -->",
+];
+
+/// Inserted text for edits
+const INSERTS: &[&str] = &["", "x", "\n", "\n\n", "# ", "- ", "> ", "```", "ü", "[[A]]", "| c |", "text\nmore"];
+
+/// Documents of lines from `LINES`, with or without a final newline.
+/// Failing ones shrink to fewer and earlier lines.
+fn document() -> impl Strategy<Value = String> {
+    (vec(select(LINES), 1..15), any::<bool>()).prop_map(|(lines, final_newline)| {
+        let mut doc = lines.join("\n");
+        if final_newline {
+            doc.push('\n');
+        }
+        doc
+    })
+}
+
+/// A char boundary of `text` picked by `index`, anywhere from its start to its end
+fn boundary(text: &str, index: Index) -> usize {
+    let mut pos = index.index(text.len() + 1);
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+/// Source that may sit between blocks: blank lines, and HTML such as
+/// comments, which live preview leaves hidden
+fn is_gap(text: &str) -> bool {
+    let text = text.trim();
+    text.is_empty() || text.starts_with('<')
+}
+
+/// Ranges of `blocks` are ordered, lie on char boundaries within `within`,
+/// and only gaps are left between them
+fn assert_covers(content: &str, blocks: &[ParsedBlock], within: Range<usize>) {
+    let mut covered = within.start;
+    for block in blocks {
+        let range = block.range();
+        assert!(range.start <= range.end, "inverted range {:?}", range);
+        assert!(
+            within.start <= range.start && range.end <= within.end,
+            "{:?} outside of {:?}",
+            range,
+            within
+        );
+        assert!(content.is_char_boundary(range.start) && content.is_char_boundary(range.end));
+        assert!(range.start >= covered, "{:?} overlaps the block ending at {}", range, covered);
+        assert!(is_gap(&content[covered..range.start]), "uncovered source {:?}", &content[covered..range.start]);
+        if let ParsedBlock::BlockQuote { content: children, .. } = block {
+            assert_covers_nested(content, children, range.clone());
+        }
+        covered = range.end;
+    }
+    assert!(is_gap(&content[covered..within.end]), "uncovered source {:?}", &content[covered..within.end]);
+}
+
+/// Blocks inside a quote may skip `>` markers but never leave their quote
+fn assert_covers_nested(content: &str, blocks: &[ParsedBlock], within: Range<usize>) {
+    let mut covered = within.start;
+    for block in blocks {
+        let range = block.range();
+        assert!(within.start <= range.start && range.end <= within.end, "{:?} outside of {:?}", range, within);
+        assert!(range.start >= covered, "{:?} overlaps the block ending at {}", range, covered);
+        assert!(content.is_char_boundary(range.start) && content.is_char_boundary(range.end));
+        if let ParsedBlock::BlockQuote { content: children, .. } = block {
+            assert_covers_nested(content, children, range.clone());
+        }
+        covered = range.end;
+    }
+}
+
+/// Assert that `edited` keeps `content[..start]` and `content[end..]` unchanged
+fn assert_untouched(content: &str, edited: &str, start: usize, end: usize) {
+    assert!(edited.starts_with(&content[..start]), "prefix changed: {:?}", edited);
+    assert!(edited.ends_with(&content[end..]), "suffix changed: {:?}", edited);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn test_block_ranges_cover_source(doc in document()) {
+        assert_covers(&doc, &parse_blocks(&doc), 0..doc.len());
+    }
+
+    #[test]
+    fn test_region_edits_round_trip(
+        doc in document(),
+        edits in vec((any::<Index>(), select(INSERTS), select(LINES)), 4),
+    ) {
+        let blocks = parse_blocks(&doc);
+        for (at, insert, line) in edits {
+            let (_, range) = edit_region(&blocks, &doc, boundary(&doc, at));
+
+            // Writing back the unchanged source leaves the document as it was
+            prop_assert_eq!(replace_region(&doc, range.clone(), &doc[range.clone()]), doc.as_str());
+
+            let text = format!("{}{}", insert, line);
+            let edited = replace_region(&doc, range.clone(), &text);
+            assert_untouched(&doc, &edited, range.start, range.end);
+            prop_assert_eq!(edited.len(), doc.len() - range.len() + text.len());
+
+            // Reparsing around the edit agrees with parsing from scratch
+            let mut reparsed = blocks.clone();
+            reparse_blocks(&mut reparsed, &doc, &edited);
+            prop_assert_eq!(format!("{:?}", reparsed), format!("{:?}", parse_blocks(&edited)), "edited {:?}", edited);
+        }
+    }
+
+    #[test]
+    fn test_structural_edits_keep_surrounding_text(doc in document(), at in any::<Index>()) {
+        let blocks = parse_blocks(&doc);
+        for block in &blocks {
+            let range = block.range();
+
+            let removed = remove_block(&doc, range.clone());
+            let before = doc[..range.start].trim_end_matches('\n');
+            let after = doc[range.end..].trim_start_matches('\n');
+            prop_assert!(removed.starts_with(before) && removed.ends_with(after));
+
+            let (merged, join) = merge_with_previous(&doc, range.start);
+            let previous_end = doc[..range.start].trim_end_matches('\n').len();
+            assert_untouched(&doc, &merged, previous_end, range.start);
+            prop_assert!(join >= previous_end && merged.is_char_boundary(join));
+        }
+
+        let pos = boundary(&doc, at);
+        let (split, cursor) = split_block(&doc, pos);
+        assert_untouched(&doc, &split, pos, pos);
+        prop_assert_eq!(&split[pos..cursor], "\n\n");
+    }
+}
+
+fn dump_blocks(content: &str, blocks: &[ParsedBlock]) -> String {
+    let mut out = String::new();
+    for block in blocks {
+        let range = block.range();
+        writeln!(out, "== {:?} {:?}", range, &content[range.clone()]).unwrap();
+        writeln!(out, "{:#?}", block).unwrap();
+    }
+    out
+}
+
+/// Text and rectangles painted by the block renderer for `blocks`
+fn dump_render(blocks: &[ParsedBlock]) -> String {
+    let ctx = egui::Context::default();
+    let input = || egui::RawInput {
+        screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(640.0, 4000.0))),
+        ..Default::default()
+    };
+    let render_ctx = RenderContext {
+        note_path: None,
+        footnotes: collect_footnotes(blocks),
+        notes: Arc::default(),
+        glossary: Arc::default(),
+    };
+    let run = |ctx: &egui::Context| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            for block in blocks {
+                render_block(ui, block, &render_ctx);
+            }
+        });
+    };
+    // The first pass measures grids and tables; the second paints them
+    let _ = ctx.run(input(), run);
+    let output = ctx.run(input(), run);
+
+    let mut out = String::new();
+    for clipped in &output.shapes {
+        dump_shape(&mut out, &clipped.shape);
+    }
+    out
+}
+
+fn dump_shape(out: &mut String, shape: &egui::Shape) {
+    match shape {
+        egui::Shape::Vec(shapes) => shapes.iter().for_each(|shape| dump_shape(out, shape)),
+        egui::Shape::Text(text) => {
+            let pos = text.pos + text.galley.mesh_bounds.min.to_vec2();
+            writeln!(out, "text {:.0},{:.0} {:?}", pos.x, pos.y, text.galley.text()).unwrap();
+        }
+        egui::Shape::Rect(rect) => {
+            let r = rect.rect;
+            writeln!(out, "rect {:.0},{:.0} {:.0}x{:.0}", r.min.x, r.min.y, r.width(), r.height()).unwrap();
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn test_golden_blocks() {
+    for path in fixtures(&fixtures_dir("markdown"), "md") {
+        let content = std::fs::read_to_string(&path).unwrap();
+        let blocks = parse_blocks(&content);
+        assert_covers(&content, &blocks, 0..content.len());
+        assert_golden(&path.with_extension("blocks.txt"), &dump_blocks(&content, &blocks));
+    }
+}
+
+#[test]
+fn test_golden_render() {
+    for path in fixtures(&fixtures_dir("markdown"), "md") {
+        let blocks = parse_blocks(&std::fs::read_to_string(&path).unwrap());
+        assert_golden(&path.with_extension("render.txt"), &dump_render(&blocks));
+    }
+}
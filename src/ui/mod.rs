@@ -1,10 +1,54 @@
 //! UI components for Robsidian
 
 pub mod block_renderer;
+pub mod clipboard_picker;
+pub mod command_palette;
+pub mod diagnostics;
 pub mod editor;
+pub mod encryption;
+pub mod file_history;
 pub mod file_tree;
+pub mod flashcards;
+pub mod images;
+pub mod ime;
+pub mod inbox;
+pub mod link_confirm;
+pub mod link_style;
 pub mod live_preview;
 pub mod markdown_blocks;
+#[cfg(test)]
+mod markdown_roundtrip;
+pub mod new_vault;
+pub mod note_colors;
+pub mod note_elements;
+pub mod notifications;
+pub mod paste_prompt;
+pub mod pinned_blocks;
+pub mod plugin_command;
+pub mod plugin_install;
+pub mod plugin_panels;
+pub mod plugin_permissions;
+pub mod presentation;
 pub mod preview;
+pub mod publish_overview;
+pub mod publish_settings;
+pub mod quick_switcher;
+pub mod rename_dialog;
+pub mod render_cache;
+pub mod save_conflict;
+pub mod settings_import;
 pub mod sidebar;
+pub mod ssh_connect;
+pub mod source_map;
+pub mod sync_settings;
+pub mod tabs;
+pub mod template_picker;
 pub mod terminal;
+pub mod terminal_layout;
+pub mod terminal_settings;
+pub mod text_stats;
+pub mod theme;
+pub mod vault_transfer;
+pub mod vault_window;
+pub mod web_capture;
+pub mod web_server;
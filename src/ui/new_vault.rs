@@ -0,0 +1,95 @@
+//! New vault dialog
+//!
+//! Creates a vault folder, empty or laid out from a preset with a welcome
+//! note, templates and folders, and then opens it.
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use robsidian_core::file_system::VaultPreset;
+
+/// Vault being set up
+#[derive(Debug)]
+struct Draft {
+    name: String,
+    location: String,
+    /// `None` for an empty vault
+    preset: Option<VaultPreset>,
+}
+
+/// New vault dialog state
+#[derive(Debug, Default)]
+pub struct NewVaultDialog {
+    /// `None` while closed
+    draft: Option<Draft>,
+}
+
+impl NewVaultDialog {
+    /// Open the dialog, placing the vault in `location` unless changed
+    pub fn open(&mut self, location: Option<PathBuf>) {
+        let location = location.map(|path| path.to_string_lossy().to_string()).unwrap_or_default();
+        self.draft = Some(Draft { name: String::new(), location, preset: Some(VaultPreset::default()) });
+    }
+
+    /// Show the dialog, returning the folder of the vault to create and its
+    /// preset
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(PathBuf, Option<VaultPreset>)> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut create = None;
+
+        egui::Window::new("New Vault")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("new_vault_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.add(egui::TextEdit::singleline(&mut draft.name).hint_text("My Notes").desired_width(240.0));
+                    ui.end_row();
+
+                    ui.label("Location");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut draft.location).desired_width(180.0));
+                        if ui.button("Browse...").clicked() {
+                            if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                draft.location = path.to_string_lossy().to_string();
+                            }
+                        }
+                    });
+                    ui.end_row();
+                });
+
+                ui.add_space(4.0);
+                ui.label("Start with");
+                ui.radio_value(&mut draft.preset, None, "Empty folder");
+                for preset in VaultPreset::ALL {
+                    ui.radio_value(&mut draft.preset, Some(preset), preset.name());
+                }
+                if let Some(preset) = draft.preset {
+                    ui.weak(preset.description());
+                }
+
+                let name = draft.name.trim();
+                let valid_name = !name.contains(['/', '\\']) && name != "." && name != "..";
+                let folder = PathBuf::from(draft.location.trim()).join(name);
+                let taken = folder.read_dir().is_ok_and(|mut entries| entries.next().is_some());
+                if !valid_name {
+                    ui.colored_label(ui.visuals().error_fg_color, "The name can't contain slashes.");
+                } else if taken && !name.is_empty() {
+                    ui.colored_label(ui.visuals().error_fg_color, "A folder of this name exists there already.");
+                }
+                ui.separator();
+                let valid = valid_name && !taken && !name.is_empty() && !draft.location.trim().is_empty();
+                if ui.add_enabled(valid, egui::Button::new("Create")).clicked() {
+                    create = Some((folder, draft.preset));
+                }
+            });
+
+        if create.is_some() || !open {
+            self.draft = None;
+        }
+        create
+    }
+}
@@ -0,0 +1,162 @@
+//! Folder and tag colors
+//!
+//! Folders and tags can be given a color in one dialog. Notes then take the
+//! color of their first colored tag, or else of the innermost colored folder
+//! they are in, wherever they are listed: the file tree, the tabs, the
+//! quick switcher and the search results of the vault API. The colors are
+//! saved in the vault settings.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Color32};
+
+use robsidian_core::note_index::NoteIndex;
+use robsidian_core::vault_settings::{format_color, parse_color, VaultSettings};
+
+/// Colors of the open vault's notes and folders
+pub struct NoteColors<'a> {
+    pub vault: Option<&'a Path>,
+    pub settings: &'a VaultSettings,
+    pub notes: &'a NoteIndex,
+}
+
+impl NoteColors<'_> {
+    /// Color of a note or folder, if it has one
+    pub fn of(&self, path: &Path) -> Option<Color32> {
+        let vault = self.vault?;
+        let tags = self.notes.note(path).map_or(&[][..], |note| &note.tags[..]);
+        let [r, g, b] = self.settings.color_of(vault, path, tags)?;
+        Some(Color32::from_rgb(r, g, b))
+    }
+
+    /// `text` in the color of `path`
+    pub fn text(&self, path: &Path, text: impl Into<String>) -> egui::RichText {
+        let text = egui::RichText::new(text);
+        match self.of(path) {
+            Some(color) => text.color(color),
+            None => text,
+        }
+    }
+}
+
+/// Colors being edited, by folder relative to the vault and by tag
+#[derive(Debug)]
+struct Draft {
+    folders: Vec<(PathBuf, Option<[u8; 3]>)>,
+    tags: Vec<(String, Option<[u8; 3]>)>,
+}
+
+/// Folder and tag colors dialog state
+#[derive(Debug, Default)]
+pub struct NoteColorsDialog {
+    /// `None` while closed
+    draft: Option<Draft>,
+    filter: String,
+}
+
+impl NoteColorsDialog {
+    /// Edit the colors of the vault's `folders`, relative to it, and `tags`
+    pub fn open(&mut self, settings: &VaultSettings, folders: Vec<PathBuf>, tags: Vec<String>) {
+        let folders = folders
+            .into_iter()
+            .map(|folder| {
+                let color = settings.folder_colors.get(&folder).and_then(|color| parse_color(color));
+                (folder, color)
+            })
+            .collect();
+        let mut tags: Vec<(String, Option<[u8; 3]>)> = tags
+            .into_iter()
+            .map(|tag| {
+                let color = settings
+                    .tag_colors
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&tag))
+                    .and_then(|(_, color)| parse_color(color));
+                (tag, color)
+            })
+            .collect();
+        // Colored tags no note has anymore stay listed, so they can be cleared
+        for (tag, color) in &settings.tag_colors {
+            if !tags.iter().any(|(name, _)| name.eq_ignore_ascii_case(tag)) {
+                tags.push((tag.clone(), parse_color(color)));
+            }
+        }
+        self.draft = Some(Draft { folders, tags });
+        self.filter.clear();
+    }
+
+    /// Show the dialog, returning the folder and tag colors once saved
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(BTreeMap<PathBuf, String>, BTreeMap<String, String>)> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut saved = false;
+
+        egui::Window::new("Folder and Tag Colors")
+            .open(&mut open)
+            .collapsible(false)
+            .default_size([360.0, 420.0])
+            .show(ctx, |ui| {
+                ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter").desired_width(f32::INFINITY));
+                let filter = self.filter.to_lowercase();
+                let shown = |name: &str| filter.is_empty() || name.to_lowercase().contains(&filter);
+                egui::ScrollArea::vertical().max_height(340.0).show(ui, |ui| {
+                    ui.strong("Folders");
+                    if draft.folders.is_empty() {
+                        ui.weak("No folders in this vault.");
+                    }
+                    for (folder, color) in &mut draft.folders {
+                        let name = folder.to_string_lossy().replace('\\', "/");
+                        if shown(&name) {
+                            Self::color_row(ui, &format!("📁 {}", name), color);
+                        }
+                    }
+                    ui.add_space(8.0);
+                    ui.strong("Tags");
+                    if draft.tags.is_empty() {
+                        ui.weak("No note has tags in its frontmatter.");
+                    }
+                    for (tag, color) in &mut draft.tags {
+                        if shown(tag) {
+                            Self::color_row(ui, &format!("#{}", tag), color);
+                        }
+                    }
+                });
+                ui.weak("Notes take the color of their first colored tag, else of their folder.");
+                ui.separator();
+                saved = ui.button("Save").clicked();
+            });
+
+        if saved {
+            let draft = self.draft.take()?;
+            let folders = draft
+                .folders
+                .into_iter()
+                .filter_map(|(folder, color)| Some((folder, format_color(color?))))
+                .collect();
+            let tags = draft.tags.into_iter().filter_map(|(tag, color)| Some((tag, format_color(color?)))).collect();
+            return Some((folders, tags));
+        }
+        if !open {
+            self.draft = None;
+        }
+        None
+    }
+
+    /// A folder or tag with a button picking its color and one clearing it
+    fn color_row(ui: &mut egui::Ui, label: &str, color: &mut Option<[u8; 3]>) {
+        ui.horizontal(|ui| {
+            let mut picked = color.unwrap_or([128, 128, 128]);
+            if ui.color_edit_button_srgb(&mut picked).changed() {
+                *color = Some(picked);
+            }
+            match color {
+                Some([r, g, b]) => ui.colored_label(Color32::from_rgb(*r, *g, *b), label),
+                None => ui.label(label),
+            };
+            if color.is_some() && ui.small_button("✖").on_hover_text("Clear").clicked() {
+                *color = None;
+            }
+        });
+    }
+}
@@ -0,0 +1,210 @@
+//! Footnotes, citations, figures and tables of the active note
+//!
+//! Counted from the note's blocks for the status bar, whose popup lists each
+//! one to jump to it. Code blocks are skipped, so markup shown as code isn't
+//! counted. Citations are Pandoc's: `@key` inside square brackets, as in
+//! `[see @doe99, p. 3; @roe05]`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use eframe::egui;
+use regex_lite::Regex;
+
+use super::markdown_blocks::{parse_blocks, ParsedBlock};
+
+/// Longest label shown in the list, in characters
+const LABEL_LEN: usize = 40;
+
+/// Patterns of the elements found in text
+struct Patterns {
+    /// Brackets holding at least one citation
+    citation_group: Regex,
+    /// Citation key after `@`, at the start of the group or after a separator
+    citation_key: Regex,
+    /// Inline image `![alt](url)` or embedded file `![[file]]`
+    image: Regex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    Footnote,
+    Citation,
+    Figure,
+    Table,
+}
+
+impl ElementKind {
+    pub const ALL: [ElementKind; 4] = [Self::Footnote, Self::Citation, Self::Figure, Self::Table];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Footnote => "Footnotes",
+            Self::Citation => "Citations",
+            Self::Figure => "Figures",
+            Self::Table => "Tables",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Footnote => "¹",
+            Self::Citation => "@",
+            Self::Figure => "🖼",
+            Self::Table => "▦",
+        }
+    }
+}
+
+/// A footnote definition, citation, figure or table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteElement {
+    pub kind: ElementKind,
+    pub label: String,
+    /// Byte offset in the note
+    pub offset: usize,
+}
+
+/// Footnote definitions, citations, figures and tables of a note, in order
+pub fn note_elements(content: &str) -> Vec<NoteElement> {
+    let patterns = Patterns {
+        citation_group: Regex::new(r"\[[^\[\]]*@[^\[\]]*\]").unwrap(),
+        citation_key: Regex::new(r"(?:^|[\s\[;-])@([A-Za-z0-9_][\w:.#$%&+?<>~/-]*)").unwrap(),
+        image: Regex::new(r"!\[\[([^\]|]+)|!\[([^\]]*)\]\(([^)\s]*)").unwrap(),
+    };
+    let mut elements = Vec::new();
+    collect(content, &parse_blocks(content), &patterns, &mut elements);
+    elements.sort_by_key(|element| element.offset);
+    // Images are blocks of their own as well as part of their paragraph
+    elements.dedup();
+    elements
+}
+
+fn collect(content: &str, blocks: &[ParsedBlock], patterns: &Patterns, elements: &mut Vec<NoteElement>) {
+    for block in blocks {
+        let range = block.range();
+        let push = |elements: &mut Vec<NoteElement>, kind, label: &str, offset| {
+            elements.push(NoteElement { kind, label: label.trim().to_string(), offset });
+        };
+        match block {
+            ParsedBlock::CodeBlock { .. } => continue,
+            ParsedBlock::FootnoteDefinition { label, .. } => push(elements, ElementKind::Footnote, label, range.start),
+            ParsedBlock::Table { headers, .. } => {
+                let label: Vec<&str> = headers.iter().map(|cell| cell.content.trim()).collect();
+                push(elements, ElementKind::Table, &label.join(" | "), range.start);
+            }
+            ParsedBlock::BlockQuote { content: quoted, .. } => {
+                collect(content, quoted, patterns, elements);
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(text) = content.get(range.clone()) else {
+            continue;
+        };
+        for group in patterns.citation_group.find_iter(text) {
+            for key in patterns.citation_key.captures_iter(group.as_str()).filter_map(|captures| captures.get(1)) {
+                let key_text = key.as_str().trim_end_matches(['.', ':', ',']);
+                push(elements, ElementKind::Citation, key_text, range.start + group.start() + key.start() - 1);
+            }
+        }
+        for image in patterns.image.captures_iter(text) {
+            let label = [1, 2, 3]
+                .iter()
+                .filter_map(|group| image.get(*group))
+                .map(|found| found.as_str())
+                .find(|label| !label.trim().is_empty())
+                .unwrap_or_default();
+            push(elements, ElementKind::Figure, label, range.start + image.get(0).map_or(0, |found| found.start()));
+        }
+    }
+}
+
+/// Elements of the note last shown, found again only when it changes
+#[derive(Default)]
+pub struct NoteElementCounter {
+    hash: Option<u64>,
+    elements: Arc<Vec<NoteElement>>,
+}
+
+impl NoteElementCounter {
+    pub fn elements(&mut self, content: &str) -> Arc<Vec<NoteElement>> {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.hash != Some(hash) {
+            self.hash = Some(hash);
+            self.elements = Arc::new(note_elements(content));
+        }
+        Arc::clone(&self.elements)
+    }
+}
+
+/// Counts of each kind of element present, as a status bar menu listing
+/// them. Returns the offset of the element clicked.
+pub fn show_note_elements(ui: &mut egui::Ui, elements: &[NoteElement]) -> Option<usize> {
+    let counts: Vec<(ElementKind, usize)> = ElementKind::ALL
+        .iter()
+        .map(|kind| (*kind, elements.iter().filter(|element| element.kind == *kind).count()))
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    if counts.is_empty() {
+        return None;
+    }
+    let summary: Vec<String> = counts.iter().map(|(kind, count)| format!("{} {}", kind.icon(), count)).collect();
+    let mut clicked = None;
+    ui.menu_button(summary.join("  "), |ui| {
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            for (kind, count) in &counts {
+                ui.strong(format!("{} ({})", kind.name(), count));
+                for element in elements.iter().filter(|element| element.kind == *kind) {
+                    let mut label: String = element.label.chars().take(LABEL_LEN).collect();
+                    if element.label.chars().count() > LABEL_LEN {
+                        label.push('…');
+                    }
+                    if label.is_empty() {
+                        label = "(untitled)".to_string();
+                    }
+                    if ui.button(label).clicked() {
+                        clicked = Some(element.offset);
+                        ui.close();
+                    }
+                }
+            }
+        });
+    })
+    .response
+    .on_hover_text("Footnotes, citations, figures and tables");
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_elements() {
+        let content = "Text[^1] as shown [see @doe99, p. 3; @roe05].\n\n\
+                       ![Results](plot.png)\n\n\
+                       | A | B |\n|---|---|\n| 1 | 2 |\n\n\
+                       ```\n[@not_cited] ![[skipped.png]]\n```\n\n\
+                       Mail me@example.com\n\n\
+                       [^1]: The footnote\n";
+        let elements = note_elements(content);
+        let found: Vec<(ElementKind, &str)> =
+            elements.iter().map(|element| (element.kind, element.label.as_str())).collect();
+        assert_eq!(
+            found,
+            [
+                (ElementKind::Citation, "doe99"),
+                (ElementKind::Citation, "roe05"),
+                (ElementKind::Figure, "Results"),
+                (ElementKind::Table, "A | B"),
+                (ElementKind::Footnote, "1"),
+            ]
+        );
+        assert_eq!(&content[elements[0].offset..elements[0].offset + 6], "@doe99");
+    }
+}
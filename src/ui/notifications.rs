@@ -0,0 +1,261 @@
+//! Toast notifications
+//!
+//! Short messages shown in the bottom-right corner for a few seconds, with an
+//! optional action button. Every notification is also kept in a history panel
+//! so nothing is missed while looking elsewhere; warnings and errors can be
+//! listed on their own there as problems.
+
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local};
+use eframe::egui::{self, Color32, RichText};
+
+/// Seconds a toast stays on screen
+const TOAST_SECONDS: f64 = 4.0;
+/// Seconds an error toast stays on screen
+const ERROR_TOAST_SECONDS: f64 = 8.0;
+/// Notifications kept in the history
+const MAX_HISTORY: usize = 100;
+
+/// How important a notification is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn icon(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Success => "✔",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+        }
+    }
+
+    fn color(self) -> Color32 {
+        match self {
+            Severity::Info => Color32::from_rgb(139, 180, 233),
+            Severity::Success => Color32::from_rgb(152, 195, 121),
+            Severity::Warning => Color32::from_rgb(229, 192, 123),
+            Severity::Error => Color32::from_rgb(224, 108, 117),
+        }
+    }
+}
+
+/// Button offered by a notification
+#[derive(Debug, Clone)]
+pub enum ToastAction {
+    /// Restore a note's content from before an edit. `edited` is a hash of
+    /// the content the edit left, so nothing typed since is thrown away.
+    Undo { path: PathBuf, content: String, edited: u64 },
+    /// Open a note
+    Open(PathBuf),
+    /// Try opening a note again
+    RetryOpen(PathBuf),
+    /// Try saving a note again
+    RetrySave(PathBuf),
+    /// Install the newer version of a plugin, from its source
+    UpdatePlugin(String),
+}
+
+impl ToastAction {
+    /// Undo an edit that changed a note from `previous` to `current`
+    pub fn undo(path: PathBuf, previous: String, current: &str) -> Self {
+        ToastAction::Undo { path, content: previous, edited: content_hash(current) }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            ToastAction::Undo { .. } => "Undo",
+            ToastAction::Open(_) => "Open",
+            ToastAction::RetryOpen(_) | ToastAction::RetrySave(_) => "Retry",
+            ToastAction::UpdatePlugin(_) => "Update",
+        }
+    }
+}
+
+/// Hash telling whether a note still has the content an edit left
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A message shown to the user
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    pub action: Option<ToastAction>,
+    time: DateTime<Local>,
+    /// UI time when the toast was first drawn
+    shown_at: Option<f64>,
+    dismissed: bool,
+}
+
+impl Notification {
+    /// Whether the notification reports a warning or an error
+    fn is_problem(&self) -> bool {
+        matches!(self.severity, Severity::Warning | Severity::Error)
+    }
+}
+
+/// Toasts and the notification history
+#[derive(Debug, Default)]
+pub struct Notifications {
+    items: Vec<Notification>,
+    /// Whether the history panel is shown
+    pub history_open: bool,
+    /// Whether the history panel lists only warnings and errors
+    pub problems_only: bool,
+}
+
+impl Notifications {
+    /// Show a notification, also writing it to the log
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>, action: Option<ToastAction>) {
+        let message = message.into();
+        match severity {
+            Severity::Error => tracing::error!("{}", message),
+            Severity::Warning => tracing::warn!("{}", message),
+            Severity::Info | Severity::Success => tracing::info!("{}", message),
+        }
+
+        self.items.push(Notification {
+            severity,
+            message,
+            action,
+            time: Local::now(),
+            shown_at: None,
+            dismissed: false,
+        });
+        if self.items.len() > MAX_HISTORY {
+            self.items.remove(0);
+        }
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message, None);
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message, None);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message, None);
+    }
+
+    /// Number of notifications in the history
+    pub fn count(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Number of warnings and errors in the history
+    pub fn problem_count(&self) -> usize {
+        self.items.iter().filter(|item| item.is_problem()).count()
+    }
+
+    /// Draw the toasts and the history panel, returning an action button
+    /// clicked this frame
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<ToastAction> {
+        let now = ctx.input(|i| i.time);
+        let mut clicked = None;
+
+        let mut visible = Vec::new();
+        for (idx, item) in self.items.iter_mut().enumerate() {
+            let shown_at = *item.shown_at.get_or_insert(now);
+            let lifetime = if item.severity == Severity::Error {
+                ERROR_TOAST_SECONDS
+            } else {
+                TOAST_SECONDS
+            };
+            if !item.dismissed && now - shown_at < lifetime {
+                visible.push(idx);
+            }
+        }
+
+        if !visible.is_empty() {
+            egui::Area::new(egui::Id::new("toasts"))
+                .order(egui::Order::Foreground)
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+                .show(ctx, |ui| {
+                    ui.set_max_width(360.0);
+                    for idx in visible {
+                        let item = &mut self.items[idx];
+                        let response = egui::Frame::popup(ui.style())
+                            .show(ui, |ui| {
+                                if let Some(action) = Self::show_item(ui, item) {
+                                    clicked = Some(action);
+                                    item.dismissed = true;
+                                }
+                                if ui.small_button("Dismiss").clicked() {
+                                    item.dismissed = true;
+                                }
+                            })
+                            .response;
+                        // Keep a toast up while the pointer is over it
+                        if response.contains_pointer() {
+                            item.shown_at = Some(now);
+                        }
+                        ui.add_space(4.0);
+                    }
+                });
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+
+        if self.history_open {
+            let mut open = true;
+            egui::Window::new("Notifications")
+                .open(&mut open)
+                .default_width(400.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.problems_only, "Problems only");
+                        if ui.button("Clear").clicked() {
+                            self.items.clear();
+                        }
+                    });
+                    ui.separator();
+
+                    let problems_only = self.problems_only;
+                    let shown = || self.items.iter().rev().filter(move |item| !problems_only || item.is_problem());
+                    if shown().next().is_none() {
+                        ui.weak(if problems_only { "No problems" } else { "No notifications" });
+                    }
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        // Newest first
+                        for item in shown() {
+                            ui.weak(item.time.format("%H:%M:%S").to_string());
+                            if let Some(action) = Self::show_item(ui, item) {
+                                clicked = Some(action);
+                            }
+                            ui.separator();
+                        }
+                    });
+                });
+            self.history_open = open;
+        }
+
+        clicked
+    }
+
+    /// Icon, message and action button of a notification
+    fn show_item(ui: &mut egui::Ui, item: &Notification) -> Option<ToastAction> {
+        let mut clicked = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(RichText::new(item.severity.icon()).color(item.severity.color()));
+            ui.label(&item.message);
+            if let Some(action) = &item.action {
+                if ui.small_button(action.label()).clicked() {
+                    clicked = Some(action.clone());
+                }
+            }
+        });
+        clicked
+    }
+}
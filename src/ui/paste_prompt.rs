@@ -0,0 +1,74 @@
+//! Large paste prompt
+//!
+//! Asks whether a large or code-like paste should go in a fenced code block
+//! or be inserted as it is.
+
+use eframe::egui::{self, Key};
+
+use robsidian_core::paste_guard::{fence, guess_language, LARGE_PASTE_BYTES};
+
+/// Lines of the paste shown as a preview
+const PREVIEW_LINES: usize = 8;
+
+/// Large paste prompt state
+#[derive(Debug, Default)]
+pub struct PastePrompt {
+    text: Option<String>,
+    lang: String,
+}
+
+impl PastePrompt {
+    pub fn open(&mut self, text: String) {
+        self.lang = guess_language(&text).to_string();
+        self.text = Some(text);
+    }
+
+    /// Show the prompt, returning the text to insert once the user chose
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        let text = self.text.as_ref()?;
+
+        let mut open = true;
+        let mut fenced = false;
+        let mut as_is = false;
+        egui::Window::new("Paste")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                let lines = text.lines().count();
+                let size = if text.len() > LARGE_PASTE_BYTES {
+                    format!("{} KB", text.len() / 1024)
+                } else {
+                    format!("{} characters", text.chars().count())
+                };
+                ui.label(format!("The clipboard holds {} lines ({}) that look like code or logs.", lines, size));
+                let preview: String = text.lines().take(PREVIEW_LINES).collect::<Vec<_>>().join("\n");
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(480.0);
+                    ui.add(egui::Label::new(egui::RichText::new(preview).monospace()).truncate());
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Language:");
+                    ui.add(egui::TextEdit::singleline(&mut self.lang).hint_text("none").desired_width(120.0));
+                });
+                ui.horizontal(|ui| {
+                    fenced = ui.button("Insert as Code Block").clicked();
+                    as_is = ui.button("Insert as Is").clicked();
+                });
+                if ui.input(|i| i.key_pressed(Key::Enter)) {
+                    fenced = true;
+                }
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.text = None;
+            return None;
+        }
+        if !fenced && !as_is {
+            return None;
+        }
+        let text = self.text.take()?;
+        Some(if fenced { fence(&text, &self.lang) } else { text })
+    }
+}
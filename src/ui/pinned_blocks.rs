@@ -0,0 +1,161 @@
+//! Blocks pinned to the sidebar
+//!
+//! A block pinned from its toolbar is found again by its `^id` block
+//! reference, which it is given if it has none, and a pinned heading by its
+//! text. Each is shown rendered in the sidebar, a heading with its section,
+//! and follows the note as it changes: open notes are read as they are
+//! being edited, closed ones again when their file changes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use eframe::egui;
+
+use robsidian_core::navigation::{block_id, NavEntry};
+
+use crate::app::RobsidianApp;
+use super::block_renderer::BlockAction;
+use super::live_preview::SimpleLivePreview;
+use super::markdown_blocks::{parse_blocks, ParsedBlock};
+
+/// Source of the block with the `^id` anchor, without its id, or of the
+/// section under the heading with the other anchors. An id alone in a
+/// paragraph belongs to the code block or table before it.
+pub fn pinned_source<'a>(content: &'a str, anchor: &str) -> Option<&'a str> {
+    let blocks = parse_blocks(content);
+    let range = match anchor.strip_prefix('^') {
+        Some(id) => {
+            let idx = blocks.iter().position(|block| block_id(&content[block.range()]) == Some(id))?;
+            let alone = content[blocks[idx].range()].trim() == anchor;
+            let idx = if alone {
+                blocks[..idx].iter().rposition(|block| !matches!(block, ParsedBlock::BlankLine { .. }))?
+            } else {
+                idx
+            };
+            blocks[idx].range()
+        }
+        None => {
+            let (idx, level) = blocks.iter().enumerate().find_map(|(idx, block)| match block {
+                ParsedBlock::Heading { level, text, .. } if text.trim().eq_ignore_ascii_case(anchor) => {
+                    Some((idx, *level))
+                }
+                _ => None,
+            })?;
+            let end = blocks[idx + 1..]
+                .iter()
+                .find(|block| matches!(block, ParsedBlock::Heading { level: other, .. } if *other <= level))
+                .map_or(content.len(), |block| block.range().start);
+            blocks[idx].range().start..end
+        }
+    };
+    let source = content[range].trim_end();
+    Some(source.strip_suffix(anchor).map_or(source, str::trim_end))
+}
+
+/// Contents of the closed notes with pinned blocks, by the time their file
+/// was modified
+#[derive(Debug, Default)]
+pub struct PinnedNotes {
+    notes: HashMap<PathBuf, (Option<SystemTime>, String)>,
+}
+
+impl PinnedNotes {
+    /// Content of a note's file, read again when it was modified
+    fn content(&mut self, path: &Path) -> Option<&str> {
+        let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        let stale = self.notes.get(path).is_none_or(|(read, _)| *read != modified);
+        if stale {
+            let content = std::fs::read_to_string(path).ok()?;
+            self.notes.insert(path.to_path_buf(), (modified, content));
+        }
+        self.notes.get(path).map(|(_, content)| content.as_str())
+    }
+}
+
+/// Pinned blocks of the vault as widgets, each titled with the note and
+/// anchor it comes from
+pub fn show_pinned_blocks(ui: &mut egui::Ui, app: &mut RobsidianApp) {
+    let Some(vault) = app.vault_path.clone() else {
+        return;
+    };
+    if app.vault_settings.pinned_blocks.is_empty() {
+        return;
+    }
+    let pinned = app.vault_settings.pinned_blocks.clone();
+    let mut open = None;
+    let mut unpin = None;
+    let mut action = None;
+
+    egui::CollapsingHeader::new("Pinned")
+        .id_salt("pinned_blocks")
+        .default_open(true)
+        .show(ui, |ui| {
+            for (idx, pin) in pinned.iter().enumerate() {
+                let path = vault.join(&pin.note);
+                let entry = NavEntry::new(path.clone(), Some(pin.anchor.clone()));
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    ui.set_width(ui.available_width());
+                    ui.horizontal(|ui| {
+                        if ui.link(entry.label()).on_hover_text("Open the note there").clicked() {
+                            open = Some(entry.clone());
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("✖").on_hover_text("Unpin").clicked() {
+                                unpin = Some((path.clone(), pin.anchor.clone()));
+                            }
+                        });
+                    });
+                    // Open notes show their unsaved edits
+                    let content = match app.documents.get(&path) {
+                        Some(doc) => Some(doc.content.clone()),
+                        None => app.pinned_notes.content(&path).map(str::to_string),
+                    };
+                    match content.as_deref().and_then(|content| pinned_source(content, &pin.anchor)) {
+                        Some(source) => {
+                            ui.push_id(("pinned_block", idx), |ui| {
+                                if let Some(clicked) = SimpleLivePreview::show(ui, source, None) {
+                                    action = Some(clicked);
+                                }
+                            });
+                        }
+                        None => {
+                            ui.weak("This block is no longer in the note.");
+                        }
+                    }
+                });
+            }
+        });
+    ui.separator();
+
+    if let Some((path, anchor)) = unpin {
+        app.toggle_pin(&path, &anchor);
+    }
+    if let Some(entry) = open {
+        app.navigate_to(entry);
+    }
+    match action {
+        Some(BlockAction::NavigateToNote(target)) => app.follow_link(&target),
+        Some(BlockAction::OpenUrl { url, text }) => app.open_external_url(url, Some(text)),
+        Some(BlockAction::OpenFile(path)) => app.navigate_to(NavEntry::new(path, None)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_source() {
+        let content = "# Plan\n\n- [ ] one\n- [x] two ^tasks\n\n## Later\n\nText\n\n# Data\n\n\
+                       | A |\n|---|\n| 1 |\n\n^table\n";
+        assert_eq!(pinned_source(content, "^tasks"), Some("- [ ] one\n- [x] two"));
+        assert_eq!(pinned_source(content, "^table"), Some("| A |\n|---|\n| 1 |"));
+        assert_eq!(
+            pinned_source(content, "plan"),
+            Some("# Plan\n\n- [ ] one\n- [x] two ^tasks\n\n## Later\n\nText")
+        );
+        assert_eq!(pinned_source(content, "^missing"), None);
+    }
+}
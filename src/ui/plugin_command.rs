@@ -0,0 +1,68 @@
+//! Plugin command prompt
+//!
+//! Asks for the arguments of a command a plugin registered, and where its
+//! answer should go.
+
+use eframe::egui::{self, Key};
+
+use crate::plugin::api::{split_args, PluginCommand};
+
+/// A plugin command the user confirmed
+#[derive(Debug)]
+pub struct PluginInvocation {
+    pub plugin: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Insert the answer at the cursor instead of showing it
+    pub insert: bool,
+}
+
+/// Plugin command prompt state
+#[derive(Debug, Default)]
+pub struct PluginCommandPrompt {
+    command: Option<(String, PluginCommand)>,
+    args: String,
+    insert: bool,
+}
+
+impl PluginCommandPrompt {
+    pub fn open(&mut self, plugin: String, command: PluginCommand) {
+        self.command = Some((plugin, command));
+        self.args.clear();
+    }
+
+    /// Show the prompt, returning the invocation once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PluginInvocation> {
+        let (plugin, command) = self.command.as_ref()?;
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new(&command.name)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                ui.weak(format!("From plugin \"{}\"", plugin));
+                if !command.description.is_empty() {
+                    ui.label(&command.description);
+                }
+                let hint = if command.usage.is_empty() { "Arguments (optional)" } else { command.usage.as_str() };
+                let response = ui.add(egui::TextEdit::singleline(&mut self.args).hint_text(hint).desired_width(420.0));
+                response.request_focus();
+                ui.checkbox(&mut self.insert, "Insert the result at the cursor");
+                if ui.button("Run").clicked() || ui.input(|i| i.key_pressed(Key::Enter)) {
+                    confirmed = true;
+                }
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.command = None;
+        }
+        if !confirmed {
+            return None;
+        }
+        let (plugin, command) = self.command.take()?;
+        Some(PluginInvocation { plugin, command: command.name, args: split_args(&self.args), insert: self.insert })
+    }
+}
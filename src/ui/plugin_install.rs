@@ -0,0 +1,58 @@
+//! Plugin install prompt
+//!
+//! Asks for the archive URL or GitHub repository of a plugin to install.
+
+use eframe::egui::{self, Key};
+
+/// Plugin install prompt state
+#[derive(Debug, Default)]
+pub struct PluginInstallPrompt {
+    open: bool,
+    source: String,
+}
+
+impl PluginInstallPrompt {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.source.clear();
+    }
+
+    /// Show the prompt, returning the source once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Install Plugin")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                ui.label("Paste the address of a plugin's .tar.gz archive, or its GitHub repository.");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.source)
+                        .hint_text("owner/repo or https://...")
+                        .desired_width(420.0),
+                );
+                response.request_focus();
+                ui.weak("The permissions the plugin asks for are shown before it is installed.");
+                let entered = ui.input(|i| i.key_pressed(Key::Enter));
+                let valid = !self.source.trim().is_empty();
+                if (ui.add_enabled(valid, egui::Button::new("Install")).clicked() || entered) && valid {
+                    confirmed = true;
+                }
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.open = false;
+        }
+        if !confirmed {
+            return None;
+        }
+        self.open = false;
+        Some(self.source.trim().to_string())
+    }
+}
@@ -0,0 +1,153 @@
+//! Rendering of plugin panels
+//!
+//! Draws the widget trees plugins send for their panels, and reports back
+//! what the user did with them. The text typed into a plugin's inputs is kept
+//! here until it's submitted.
+
+use std::collections::HashMap;
+
+use eframe::egui::{self, Key, RichText};
+
+use crate::plugin::manager::PluginManager;
+use crate::plugin::widgets::{PanelLocation, Widget};
+
+/// Something done in a plugin's panel
+#[derive(Debug)]
+pub enum PanelAction {
+    /// A widget with an id was used; see `on-panel-event` in the WIT world
+    Event { plugin: String, panel: String, widget: String, value: String },
+    OpenUrl { url: String, text: String },
+}
+
+/// Plugin panel renderer state
+#[derive(Debug, Default)]
+pub struct PluginPanels {
+    /// Text typed into inputs, by plugin, panel and widget id
+    drafts: HashMap<(String, String, String), String>,
+}
+
+impl PluginPanels {
+    /// Show the sidebar panels as collapsible sections
+    pub fn show_sidebar(&mut self, ui: &mut egui::Ui, plugins: &PluginManager) -> Vec<PanelAction> {
+        let mut actions = Vec::new();
+        for (plugin, panel) in plugins.panels() {
+            if panel.location != PanelLocation::Sidebar {
+                continue;
+            }
+            egui::CollapsingHeader::new(&panel.title)
+                .id_salt(("plugin_panel", plugin, &panel.id))
+                .default_open(true)
+                .show(ui, |ui| {
+                    let mut view = PanelView { drafts: &mut self.drafts, plugin, panel: &panel.id, actions: &mut actions };
+                    view.show(ui, &panel.widgets);
+                });
+        }
+        actions
+    }
+
+    /// Show the status bar panels inline, in the current layout
+    pub fn show_status_bar(&mut self, ui: &mut egui::Ui, plugins: &PluginManager) -> Vec<PanelAction> {
+        let mut actions = Vec::new();
+        for (plugin, panel) in plugins.panels() {
+            if panel.location != PanelLocation::StatusBar {
+                continue;
+            }
+            ui.separator();
+            let mut view = PanelView { drafts: &mut self.drafts, plugin, panel: &panel.id, actions: &mut actions };
+            view.show(ui, &panel.widgets);
+        }
+        actions
+    }
+
+    /// Whether any plugin has a sidebar panel
+    pub fn has_sidebar(plugins: &PluginManager) -> bool {
+        plugins.panels().iter().any(|(_, panel)| panel.location == PanelLocation::Sidebar)
+    }
+}
+
+/// One panel being drawn
+struct PanelView<'a> {
+    drafts: &'a mut HashMap<(String, String, String), String>,
+    plugin: &'a str,
+    panel: &'a str,
+    actions: &'a mut Vec<PanelAction>,
+}
+
+impl PanelView<'_> {
+    fn event(&mut self, widget: &str, value: impl Into<String>) {
+        self.actions.push(PanelAction::Event {
+            plugin: self.plugin.to_string(),
+            panel: self.panel.to_string(),
+            widget: widget.to_string(),
+            value: value.into(),
+        });
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, widgets: &[Widget]) {
+        for widget in widgets {
+            self.show_widget(ui, widget);
+        }
+    }
+
+    fn show_widget(&mut self, ui: &mut egui::Ui, widget: &Widget) {
+        match widget {
+            Widget::Label { text } => {
+                ui.label(text);
+            }
+            Widget::Heading { text } => {
+                ui.label(RichText::new(text).strong().size(16.0));
+            }
+            Widget::Code { text } => {
+                ui.add(egui::Label::new(RichText::new(text).monospace()).selectable(true));
+            }
+            Widget::Separator => {
+                ui.separator();
+            }
+            Widget::Button { id, text } => {
+                if ui.button(text).clicked() {
+                    self.event(id, "");
+                }
+            }
+            Widget::Checkbox { id, text, checked } => {
+                let mut checked = *checked;
+                if ui.checkbox(&mut checked, text).changed() {
+                    self.event(id, checked.to_string());
+                }
+            }
+            Widget::TextInput { id, value, hint } => {
+                // Once submitted, the plugin's value shows again
+                let key = (self.plugin.to_string(), self.panel.to_string(), id.clone());
+                let draft = self.drafts.entry(key.clone()).or_insert_with(|| value.clone());
+                let response = ui.add(egui::TextEdit::singleline(draft).hint_text(hint.as_str()).desired_width(160.0));
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    let value = self.drafts.remove(&key).unwrap_or_default();
+                    self.event(id, value);
+                }
+            }
+            Widget::Progress { value, text } => {
+                let mut bar = egui::ProgressBar::new(value.clamp(0.0, 1.0)).desired_width(160.0);
+                if !text.is_empty() {
+                    bar = bar.text(text.as_str());
+                }
+                ui.add(bar);
+            }
+            Widget::Link { text, url } => {
+                if ui.link(text).on_hover_text(url).clicked() {
+                    self.actions.push(PanelAction::OpenUrl { url: url.clone(), text: text.clone() });
+                }
+            }
+            Widget::Horizontal { children } => {
+                ui.horizontal(|ui| self.show(ui, children));
+            }
+            Widget::Vertical { children } => {
+                ui.vertical(|ui| self.show(ui, children));
+            }
+            Widget::Collapsing { title, children, open } => {
+                egui::CollapsingHeader::new(title)
+                    .id_salt((self.plugin, self.panel, title))
+                    .default_open(*open)
+                    .show(ui, |ui| self.show(ui, children));
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Plugin permission prompt
+//!
+//! Shows what a downloaded plugin may do before it is installed, and what
+//! an update asks for beyond what was allowed so far.
+
+use eframe::egui;
+
+use crate::plugin::api::PluginPermission;
+use crate::plugin::loader::DownloadedPlugin;
+
+/// A downloaded plugin waiting for the user's answer
+struct Pending {
+    plugin: DownloadedPlugin,
+    /// Permissions of the installed version, when this is an update
+    granted: Option<Vec<PluginPermission>>,
+}
+
+/// Plugin permission prompt state
+#[derive(Default)]
+pub struct PluginPermissionPrompt {
+    /// Plugins to ask about, in the order they were downloaded
+    pending: Vec<Pending>,
+}
+
+impl PluginPermissionPrompt {
+    /// Ask before installing `plugin`. `granted` holds the permissions of
+    /// the version installed now, if any.
+    pub fn open(&mut self, plugin: DownloadedPlugin, granted: Option<Vec<PluginPermission>>) {
+        self.pending.retain(|pending| pending.plugin.manifest.id != plugin.manifest.id);
+        self.pending.push(Pending { plugin, granted });
+    }
+
+    /// Show the prompt for the first plugin waiting, returning it once the
+    /// user allows it
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<DownloadedPlugin> {
+        let pending = self.pending.first()?;
+        let manifest = &pending.plugin.manifest;
+        let mut open = true;
+        let mut allowed = false;
+        let mut declined = false;
+
+        let title = if pending.granted.is_some() { "Update Plugin" } else { "Install Plugin" };
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.strong(format!("{} {}", manifest.name, manifest.version));
+                if !manifest.author.is_empty() {
+                    ui.weak(format!("by {}", manifest.author));
+                }
+                ui.weak(format!("from {}", pending.plugin.source));
+                ui.add_space(4.0);
+                if manifest.permissions.is_empty() {
+                    ui.label("The plugin asks for no permissions.");
+                } else {
+                    ui.label("The plugin will be able to:");
+                    for permission in &manifest.permissions {
+                        let new = pending.granted.as_ref().is_some_and(|granted| !granted.contains(permission));
+                        ui.horizontal(|ui| {
+                            ui.label(format!("• {}", permission.description()));
+                            if new {
+                                ui.colored_label(ui.visuals().warn_fg_color, "new");
+                            }
+                        });
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let confirm = if pending.granted.is_some() { "Allow and Update" } else { "Allow and Install" };
+                    allowed = ui.button(confirm).clicked();
+                    declined = ui.button("Cancel").clicked();
+                });
+            });
+
+        if allowed {
+            return Some(self.pending.remove(0).plugin);
+        }
+        if declined || !open {
+            self.pending.remove(0);
+        }
+        None
+    }
+}
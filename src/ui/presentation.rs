@@ -0,0 +1,167 @@
+//! Presentation window
+//!
+//! Shows the rendered preview of a note in a separate frameless window that
+//! stays on top, for screen-sharing a note while editing it in the main
+//! window. The text can be zoomed with the buttons in the window's header or
+//! with Ctrl+= / Ctrl+- / Ctrl+0; Escape closes it.
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, Key};
+
+use robsidian_core::document::Document;
+use robsidian_core::document_store::DocumentStore;
+use crate::ui::live_preview::SimpleLivePreview;
+
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 4.0;
+const SCALE_STEP: f32 = 0.25;
+
+/// State of the presentation window
+#[derive(Debug)]
+pub struct PresentationWindow {
+    /// Note being presented; `None` while the window is closed
+    path: Option<PathBuf>,
+    /// Text scale of the rendered note
+    scale: f32,
+}
+
+impl Default for PresentationWindow {
+    fn default() -> Self {
+        Self {
+            path: None,
+            scale: 1.5,
+        }
+    }
+}
+
+impl PresentationWindow {
+    pub fn is_open(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// Present a note, or close the window if it already presents that note
+    pub fn toggle(&mut self, path: PathBuf) {
+        if self.path.as_ref() == Some(&path) {
+            self.path = None;
+        } else {
+            self.path = Some(path);
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.path = None;
+    }
+
+    fn zoom(&mut self, delta: f32) {
+        self.scale = (self.scale + delta).clamp(MIN_SCALE, MAX_SCALE);
+    }
+
+    /// Show the window with the current content of the presented note
+    pub fn show(&mut self, ctx: &egui::Context, documents: &DocumentStore) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        let title = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("presentation"),
+            egui::ViewportBuilder::default()
+                .with_title(format!("{} - Presentation", title))
+                .with_decorations(false)
+                .with_always_on_top()
+                .with_inner_size([800.0, 600.0]),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // The backend can't open another window: fall back to an
+                    // in-app window
+                    let mut open = true;
+                    egui::Window::new(format!("{} - Presentation", title))
+                        .open(&mut open)
+                        .default_size([600.0, 450.0])
+                        .show(ctx, |ui| self.show_contents(ui, &title, documents.get(&path)));
+                    if !open {
+                        self.path = None;
+                    }
+                    return;
+                }
+
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    self.show_contents(ui, &title, documents.get(&path));
+                });
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.path = None;
+                }
+            },
+        );
+    }
+
+    /// Header with the zoom controls, then the rendered note
+    fn show_contents(&mut self, ui: &mut egui::Ui, title: &str, doc: Option<&Document>) {
+        ui.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::COMMAND, Key::Equals)
+                || i.consume_key(egui::Modifiers::COMMAND, Key::Plus)
+            {
+                self.zoom(SCALE_STEP);
+            }
+            if i.consume_key(egui::Modifiers::COMMAND, Key::Minus) {
+                self.zoom(-SCALE_STEP);
+            }
+            if i.consume_key(egui::Modifiers::COMMAND, Key::Num0) {
+                self.scale = 1.0;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                self.path = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            // The window has no title bar: drag it by its header
+            let header = ui.interact(
+                ui.max_rect(),
+                ui.id().with("presentation_header"),
+                egui::Sense::drag(),
+            );
+            if header.drag_started() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            }
+
+            ui.strong(title);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("✖").on_hover_text("Close (Esc)").clicked() {
+                    self.path = None;
+                }
+                if ui.button("+").on_hover_text("Zoom in (Ctrl+=)").clicked() {
+                    self.zoom(SCALE_STEP);
+                }
+                if ui
+                    .button(format!("{:.0}%", self.scale * 100.0))
+                    .on_hover_text("Reset zoom (Ctrl+0)")
+                    .clicked()
+                {
+                    self.scale = 1.0;
+                }
+                if ui.button("−").on_hover_text("Zoom out (Ctrl+-)").clicked() {
+                    self.zoom(-SCALE_STEP);
+                }
+            });
+        });
+        ui.separator();
+
+        let Some(doc) = doc else {
+            ui.centered_and_justified(|ui| {
+                ui.label("The note is not open anymore");
+            });
+            return;
+        };
+
+        for font in ui.style_mut().text_styles.values_mut() {
+            font.size *= self.scale;
+        }
+        ui.spacing_mut().item_spacing *= self.scale;
+        SimpleLivePreview::show(ui, doc.content_without_frontmatter(), None);
+    }
+}
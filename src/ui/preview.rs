@@ -4,6 +4,18 @@ use eframe::egui;
 use egui_commonmark::CommonMarkViewer;
 
 use crate::app::RobsidianApp;
+use robsidian_core::captions::replace_captions;
+use robsidian_core::file_system::format_modified;
+use robsidian_core::glossary::definition_line;
+use robsidian_core::link_refactor::code_ranges;
+use crate::terminal::code_runner::runner;
+use crate::ui::markdown_blocks::{parse_blocks, ParsedBlock, CHILDREN_PLACEHOLDER};
+use crate::ui::images::Banner;
+use crate::ui::render_cache::cached_child_notes;
+use crate::ui::theme::NoteStyle;
+
+/// Language and content of a code block that can be run in the terminal
+type RunnableCode = (Option<String>, String);
 
 /// Markdown preview panel
 pub struct PreviewPanel;
@@ -12,20 +24,95 @@ impl PreviewPanel {
     /// Show the preview panel
     pub fn show(ui: &mut egui::Ui, app: &mut RobsidianApp) {
         // Get content first to avoid borrow conflicts
-        let content = app
-            .active_document()
-            .map(|doc| doc.content_without_frontmatter().to_string());
+        let vault = app.vault_path.as_deref();
+        let content = app.active_document().map(|doc| {
+            let content = doc.content_without_frontmatter();
+            let content = if content.contains(CHILDREN_PLACEHOLDER) {
+                Self::replace_children(content, &Self::child_notes_markdown(ui, &doc.path))
+            } else {
+                content.to_string()
+            };
+            // Abbreviation definitions aren't part of the text
+            let content = if content.lines().any(|line| definition_line(line).is_some()) {
+                content.split_inclusive('\n').filter(|line| definition_line(line).is_none()).collect()
+            } else {
+                content
+            };
+            let content = replace_captions(&content, |caption| format!("**{}:** *{}*", caption.label(), caption.text));
+            let style = NoteStyle::from_classes(&doc.metadata.cssclasses);
+            (content, style, Banner::from_document(ui, doc, vault))
+        });
 
+        let mut run = None;
         egui::ScrollArea::vertical()
             .id_salt("preview_scroll")
             .show(ui, |ui| {
-                if let Some(content) = content {
-                    CommonMarkViewer::new()
-                        .show(ui, &mut app.commonmark_cache, &content);
+                if let Some((content, style, banner)) = content {
+                    if let Some(banner) = banner {
+                        banner.show(ui);
+                    }
+                    style.show(ui, |ui| {
+                        for (idx, (markdown, code)) in Self::runnable_segments(&content).into_iter().enumerate() {
+                            ui.push_id(idx, |ui| {
+                                CommonMarkViewer::new().show(ui, &mut app.commonmark_cache, markdown);
+                            });
+                            if let Some(code) = code {
+                                if ui.small_button("▶ Run").on_hover_text("Run in terminal").clicked() {
+                                    run = Some(code);
+                                }
+                            }
+                        }
+                    });
                 } else {
                     Self::show_empty(ui);
                 }
             });
+        if let Some((lang, code)) = run {
+            app.run_code_block(lang.as_deref(), &code);
+        }
+    }
+
+    /// Markdown split after each code block that can be run, with the
+    /// language and code of that block, so it can get a Run button
+    fn runnable_segments(markdown: &str) -> Vec<(&str, Option<RunnableCode>)> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for block in parse_blocks(markdown) {
+            if let ParsedBlock::CodeBlock { lang, code, range } = block {
+                if runner(lang.as_deref()).is_some() {
+                    segments.push((&markdown[start..range.end], Some((lang, code))));
+                    start = range.end;
+                }
+            }
+        }
+        segments.push((&markdown[start..], None));
+        segments
+    }
+
+    /// Replace the `{{children}}` placeholders outside of code with
+    /// `children`, so code can show the placeholder itself
+    fn replace_children(content: &str, children: &str) -> String {
+        let code = code_ranges(content);
+        let mut replaced = String::with_capacity(content.len());
+        let mut last = 0;
+        for (at, _) in content.match_indices(CHILDREN_PLACEHOLDER) {
+            if code.iter().any(|range| range.contains(&at)) {
+                continue;
+            }
+            replaced.push_str(&content[last..at]);
+            replaced.push_str(children);
+            last = at + CHILDREN_PLACEHOLDER.len();
+        }
+        replaced.push_str(&content[last..]);
+        replaced
+    }
+
+    /// Build a markdown list of the notes in the folder of `note_path`
+    fn child_notes_markdown(ui: &egui::Ui, note_path: &std::path::Path) -> String {
+        cached_child_notes(ui, note_path)
+            .iter()
+            .map(|child| format!("- **{}** — {}\n", child.title, format_modified(child.modified)))
+            .collect()
     }
 
     /// Show empty state
@@ -37,3 +124,17 @@ impl PreviewPanel {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_children_placeholder_outside_code() {
+        let content = "{{children}}\n\nWrite `{{children}}` to list them:\n\n```\n{{children}}\n```\n";
+        assert_eq!(
+            PreviewPanel::replace_children(content, "- **A**\n"),
+            "- **A**\n\n\nWrite `{{children}}` to list them:\n\n```\n{{children}}\n```\n"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Publish overview
+//!
+//! Shows which notes the HTML export will include today, which are scheduled
+//! for a later date, and which are drafts.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+
+use robsidian_core::export::PublishState;
+
+/// Something picked in the overview
+#[derive(Debug, Clone)]
+pub enum PublishAction {
+    Open(PathBuf),
+    Export,
+}
+
+/// Publish overview state
+#[derive(Debug, Default)]
+pub struct PublishOverview {
+    /// Publish state of each note; `None` while closed
+    plan: Option<Vec<(PathBuf, PublishState)>>,
+    vault: PathBuf,
+}
+
+impl PublishOverview {
+    pub fn open(&mut self, vault: PathBuf, plan: Vec<(PathBuf, PublishState)>) {
+        self.vault = vault;
+        self.plan = Some(plan);
+    }
+
+    fn name(&self, path: &Path) -> String {
+        path.strip_prefix(&self.vault).unwrap_or(path).with_extension("").to_string_lossy().to_string()
+    }
+
+    /// Show the overview, returning what was picked this frame
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PublishAction> {
+        let plan = self.plan.as_ref()?;
+        let published: Vec<&PathBuf> = plan
+            .iter()
+            .filter(|(_, state)| *state == PublishState::Published)
+            .map(|(path, _)| path)
+            .collect();
+        let mut scheduled: Vec<(&PathBuf, chrono::NaiveDate)> = plan
+            .iter()
+            .filter_map(|(path, state)| match state {
+                PublishState::Scheduled(date) => Some((path, *date)),
+                _ => None,
+            })
+            .collect();
+        scheduled.sort_by_key(|(_, date)| *date);
+        let drafts: Vec<&PathBuf> = plan
+            .iter()
+            .filter(|(_, state)| *state == PublishState::Draft)
+            .map(|(path, _)| path)
+            .collect();
+
+        let mut open = true;
+        let mut action = None;
+        egui::Window::new("Publish Overview")
+            .open(&mut open)
+            .default_size([420.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} notes will be exported today.", published.len()));
+                    if ui.add_enabled(!published.is_empty(), egui::Button::new("Export...")).clicked() {
+                        action = Some(PublishAction::Export);
+                    }
+                });
+                ui.weak("Flag notes with \"publish: true\" in their frontmatter; \"publish_date\" holds them back until that day.");
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new(format!("Published ({})", published.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for path in &published {
+                                if ui.link(self.name(path)).clicked() {
+                                    action = Some(PublishAction::Open(path.to_path_buf()));
+                                }
+                            }
+                        });
+                    egui::CollapsingHeader::new(format!("Scheduled ({})", scheduled.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for (path, date) in &scheduled {
+                                ui.horizontal(|ui| {
+                                    ui.weak(date.format("%Y-%m-%d").to_string());
+                                    if ui.link(self.name(path)).clicked() {
+                                        action = Some(PublishAction::Open(path.to_path_buf()));
+                                    }
+                                });
+                            }
+                        });
+                    egui::CollapsingHeader::new(format!("Drafts ({})", drafts.len()))
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            for path in &drafts {
+                                if ui.link(self.name(path)).clicked() {
+                                    action = Some(PublishAction::Open(path.to_path_buf()));
+                                }
+                            }
+                        });
+                });
+            });
+
+        if !open || matches!(action, Some(PublishAction::Export)) {
+            self.plan = None;
+        }
+        action
+    }
+}
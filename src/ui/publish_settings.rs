@@ -0,0 +1,87 @@
+//! Publish settings dialog
+//!
+//! Chooses where the Publish Note command sends notes: a GitHub Gist or a
+//! web endpoint.
+
+use eframe::egui;
+
+use robsidian_core::note_publish::PublishTarget;
+
+/// Publish settings dialog state
+#[derive(Debug, Default)]
+pub struct PublishSettingsDialog {
+    /// Target being edited; the outer `None` while closed
+    draft: Option<Option<PublishTarget>>,
+}
+
+impl PublishSettingsDialog {
+    pub fn open(&mut self, target: &Option<PublishTarget>) {
+        self.draft = Some(target.clone());
+    }
+
+    /// Show the dialog, returning the new target once saved
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Option<PublishTarget>> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut saved = false;
+
+        egui::Window::new("Publish Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Publish to");
+                    let kind = match draft {
+                        None => "Nowhere",
+                        Some(PublishTarget::Gist { .. }) => "GitHub Gist",
+                        Some(PublishTarget::Endpoint { .. }) => "Web endpoint",
+                    };
+                    egui::ComboBox::from_id_salt("publish_target").selected_text(kind).show_ui(ui, |ui| {
+                        if ui.selectable_label(kind == "Nowhere", "Nowhere").clicked() {
+                            *draft = None;
+                        }
+                        if ui.selectable_label(kind == "GitHub Gist", "GitHub Gist").clicked() && kind != "GitHub Gist" {
+                            *draft = Some(PublishTarget::Gist { token: String::new(), public: false });
+                        }
+                        if ui.selectable_label(kind == "Web endpoint", "Web endpoint").clicked() && kind != "Web endpoint" {
+                            *draft = Some(PublishTarget::Endpoint { url: String::new(), token: String::new() });
+                        }
+                    });
+                });
+
+                egui::Grid::new("publish_target_grid").num_columns(2).show(ui, |ui| {
+                    let row = |ui: &mut egui::Ui, label: &str, value: &mut String, secret: bool| {
+                        ui.label(label);
+                        ui.add(egui::TextEdit::singleline(value).password(secret).desired_width(280.0));
+                        ui.end_row();
+                    };
+                    match draft {
+                        Some(PublishTarget::Gist { token, public }) => {
+                            row(ui, "Access token", token, true);
+                            ui.label("");
+                            ui.checkbox(public, "Public Gists (listed on your profile)");
+                            ui.end_row();
+                        }
+                        Some(PublishTarget::Endpoint { url, token }) => {
+                            row(ui, "Endpoint URL", url, false);
+                            row(ui, "Bearer token", token, true);
+                        }
+                        None => {}
+                    }
+                });
+                ui.separator();
+                if ui.button("Save").clicked() {
+                    saved = true;
+                }
+            });
+
+        if saved {
+            return self.draft.take();
+        }
+        if !open {
+            self.draft = None;
+        }
+        None
+    }
+}
@@ -0,0 +1,219 @@
+//! Quick switcher
+//!
+//! Opened with Ctrl+O: type part of a note's name or one of its aliases and
+//! press Enter to open it.
+//!
+//! Tab switches to searching every recent vault. Notes of another vault open
+//! by switching to that vault, or with Shift in a new window.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use eframe::egui::{self, Key};
+
+use robsidian_core::note_index::NoteIndex;
+
+use super::note_colors::NoteColors;
+
+/// Maximum number of notes listed
+const MAX_RESULTS: usize = 50;
+
+/// A note picked in the switcher
+#[derive(Debug, Clone)]
+pub struct SwitchTarget {
+    /// Vault the note is in, when it isn't the open one
+    pub vault: Option<PathBuf>,
+    pub note: PathBuf,
+    /// Open it in a new window instead of this one
+    pub new_window: bool,
+}
+
+/// Quick switcher state
+#[derive(Debug, Default)]
+pub struct QuickSwitcher {
+    pub open: bool,
+    query: String,
+    selected: usize,
+    /// Search every recent vault instead of the open one
+    all_vaults: bool,
+    /// Recent vaults other than the open one
+    other_vaults: Vec<PathBuf>,
+    /// Indexes of `other_vaults`, built when first needed
+    other_indexes: Vec<(PathBuf, NoteIndex)>,
+    indexing: Option<Receiver<Vec<(PathBuf, NoteIndex)>>>,
+}
+
+impl QuickSwitcher {
+    /// Open the switcher with an empty query
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Set the recent vaults searched in all-vaults mode, dropping indexes
+    /// that are no longer needed
+    pub fn set_other_vaults(&mut self, vaults: Vec<PathBuf>) {
+        if vaults != self.other_vaults {
+            self.other_vaults = vaults;
+            self.other_indexes.clear();
+            self.indexing = None;
+        }
+    }
+
+    /// Index the other vaults in the background
+    fn start_indexing(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let vaults = self.other_vaults.clone();
+        thread::spawn(move || {
+            let indexes = vaults
+                .into_iter()
+                .filter(|vault| vault.is_dir())
+                .map(|vault| {
+                    let index = NoteIndex::build(&vault);
+                    (vault, index)
+                })
+                .collect();
+            let _ = tx.send(indexes);
+        });
+        self.indexing = Some(rx);
+    }
+
+    fn vault_name(vault: &Path) -> String {
+        vault.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    /// Matches of the open vault, then of the other vaults when searching
+    /// all of them: (label, picked note)
+    fn matches(&self, notes: &NoteIndex) -> Vec<(String, SwitchTarget)> {
+        let target = |vault: Option<&PathBuf>, note: &Path| SwitchTarget {
+            vault: vault.cloned(),
+            note: note.to_path_buf(),
+            new_window: false,
+        };
+        let mut matches: Vec<(String, SwitchTarget)> = notes
+            .search(&self.query, MAX_RESULTS)
+            .into_iter()
+            .map(|m| (m.label(), target(None, &m.note.path)))
+            .collect();
+        if self.all_vaults {
+            for (vault, index) in &self.other_indexes {
+                let name = Self::vault_name(vault);
+                matches.extend(
+                    index
+                        .search(&self.query, MAX_RESULTS)
+                        .into_iter()
+                        .map(|m| (format!("{}  ·  {}", m.label(), name), target(Some(vault), &m.note.path))),
+                );
+            }
+            matches.truncate(MAX_RESULTS);
+        }
+        matches
+    }
+
+    /// Show the switcher, returning the note picked this frame
+    pub fn show(&mut self, ctx: &egui::Context, notes: &NoteIndex, colors: &NoteColors) -> Option<SwitchTarget> {
+        if !self.open {
+            return None;
+        }
+
+        if let Some(indexes) = self.indexing.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.other_indexes = indexes;
+            self.indexing = None;
+        }
+        let toggled = ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, Key::Tab));
+        if toggled {
+            self.all_vaults = !self.all_vaults;
+        }
+        if self.all_vaults && self.other_indexes.is_empty() && self.indexing.is_none() && !self.other_vaults.is_empty() {
+            self.start_indexing();
+        }
+        if self.indexing.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let matches = self.matches(notes);
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+        let target = |(_, target): &(String, SwitchTarget), new_window: bool| SwitchTarget {
+            new_window,
+            ..target.clone()
+        };
+
+        let mut picked = None;
+        let mut moved = false;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                self.open = false;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) && self.selected + 1 < matches.len() {
+                self.selected += 1;
+                moved = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                self.selected = self.selected.saturating_sub(1);
+                moved = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::Enter) {
+                picked = matches.get(self.selected).map(|m| target(m, false));
+            }
+            if i.consume_key(egui::Modifiers::SHIFT, Key::Enter) {
+                picked = matches.get(self.selected).map(|m| target(m, true));
+            }
+        });
+
+        egui::Window::new("Quick Switcher")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(480.0, 0.0))
+            .show(ctx, |ui| {
+                let hint = if self.all_vaults { "Find a note in any recent vault..." } else { "Find a note or alias..." };
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text(hint)
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() || toggled {
+                    self.selected = 0;
+                }
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(self.all_vaults, "All vaults").clicked() {
+                        self.all_vaults = !self.all_vaults;
+                    }
+                    if self.indexing.is_some() {
+                        ui.spinner();
+                        ui.weak("Indexing vaults...");
+                    } else {
+                        ui.weak("Tab: all vaults · Shift+Enter: new window");
+                    }
+                });
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.weak("No matching notes");
+                    }
+                    for (idx, note_match) in matches.iter().enumerate() {
+                        let label = colors.text(&note_match.1.note, &note_match.0);
+                        let row = ui.selectable_label(idx == self.selected, label);
+                        if moved && idx == self.selected {
+                            row.scroll_to_me(None);
+                        }
+                        if row.clicked() {
+                            let new_window = ui.input(|i| i.modifiers.shift);
+                            picked = Some(target(note_match, new_window));
+                        }
+                    }
+                });
+            });
+
+        if picked.is_some() {
+            self.open = false;
+        }
+        picked
+    }
+}
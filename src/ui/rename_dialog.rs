@@ -0,0 +1,51 @@
+//! Rename dialog for files in the vault
+
+use std::path::PathBuf;
+
+use eframe::egui::{self, Key};
+
+/// Rename dialog state
+#[derive(Debug, Default)]
+pub struct RenameDialog {
+    path: Option<PathBuf>,
+    name: String,
+}
+
+impl RenameDialog {
+    pub fn open(&mut self, path: PathBuf) {
+        self.name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        self.path = Some(path);
+    }
+
+    /// Show the dialog, returning the file and its new name once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(PathBuf, String)> {
+        let path = self.path.as_ref()?;
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Rename")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(320.0));
+                response.request_focus();
+                ui.weak("Links and embeds pointing to it are updated across the vault.");
+                let unchanged = path.file_name().is_some_and(|name| name.to_string_lossy() == self.name.trim());
+                let valid = !self.name.trim().is_empty() && !unchanged;
+                let entered = ui.input(|i| i.key_pressed(Key::Enter));
+                if (ui.add_enabled(valid, egui::Button::new("Rename")).clicked() || entered) && valid {
+                    confirmed = true;
+                }
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.path = None;
+        }
+        if !confirmed {
+            return None;
+        }
+        Some((self.path.take()?, self.name.trim().to_string()))
+    }
+}
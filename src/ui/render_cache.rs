@@ -0,0 +1,205 @@
+//! Render caching for live preview
+//!
+//! Blocks are keyed by a hash of their Markdown source, so a block keeps what
+//! was worked out for it across frames and across edits elsewhere in the
+//! note: the inline spans of its text, the laid out text of code blocks and
+//! table cells, and its measured size. Blocks scrolled
+//! out of view then only reserve their space instead of being parsed and laid
+//! out again, and the cost of a frame follows the visible and changed blocks
+//! rather than the length of the note.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use eframe::egui::{self, text::LayoutJob, Galley, Ui, Vec2};
+
+use super::markdown_blocks::{parse_inline, InlineSpan};
+use robsidian_core::document::Document;
+use robsidian_core::file_system::{list_child_notes, ChildNote};
+
+/// Key of a block: a hash of its Markdown source
+pub fn block_key(source: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Galleys kept per kind of text before the cache starts over
+const MAX_GALLEYS: usize = 1024;
+
+/// Sizes of rendered blocks, by block key
+#[derive(Debug, Default)]
+pub struct BlockRenderCache {
+    /// Width the block was laid out at, and the size it took
+    sizes: HashMap<u64, (f32, Vec2)>,
+}
+
+impl BlockRenderCache {
+    /// Size of a block last rendered at this width
+    pub fn size(&self, key: u64, width: f32) -> Option<Vec2> {
+        self.sizes
+            .get(&key)
+            .filter(|(laid_out, _)| (laid_out - width).abs() < 0.5)
+            .map(|(_, size)| *size)
+    }
+
+    pub fn store(&mut self, key: u64, width: f32, size: Vec2) {
+        self.sizes.insert(key, (width, size));
+    }
+
+    /// Forget blocks that are no longer in the note
+    pub fn retain(&mut self, keys: &[u64]) {
+        let keys: HashSet<&u64> = keys.iter().collect();
+        self.sizes.retain(|key, _| keys.contains(key));
+    }
+}
+
+#[derive(Default)]
+struct InlineParser;
+
+impl egui::cache::ComputerMut<&str, Arc<Vec<InlineSpan>>> for InlineParser {
+    fn compute(&mut self, text: &str) -> Arc<Vec<InlineSpan>> {
+        Arc::new(parse_inline(text))
+    }
+}
+
+/// Parsed inline spans, kept as long as their text is shown every frame
+type InlineCache = egui::cache::FrameCache<Arc<Vec<InlineSpan>>, InlineParser>;
+
+/// Inline spans of a block's text, parsed only when the text is new
+pub fn cached_inline(ui: &Ui, text: &str) -> Arc<Vec<InlineSpan>> {
+    ui.memory_mut(|mem| mem.caches.cache::<InlineCache>().get(text))
+}
+
+/// Laid out text, by a hash of what it shows and the width it wraps at
+#[derive(Clone, Default)]
+struct GalleyCache {
+    /// Font atlas the galleys' glyphs are in; egui starts a new one when
+    /// the scale changes or it fills up
+    atlas: usize,
+    galleys: HashMap<(u64, u32), Arc<Galley>>,
+}
+
+/// Text laid out by `job`, done again only when `key` or the wrap width
+/// changes. `kind` keeps the galleys of different uses apart.
+pub fn cached_galley(
+    ui: &Ui,
+    kind: &str,
+    key: impl Hash,
+    wrap_width: f32,
+    job: impl FnOnce(f32) -> LayoutJob,
+) -> Arc<Galley> {
+    let id = egui::Id::new(("galley_cache", kind));
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    let wrap_width = wrap_width.floor();
+    let key = (hasher.finish(), wrap_width as u32);
+    let atlas = ui.fonts(|fonts| Arc::as_ptr(&fonts.texture_atlas()) as usize);
+
+    let cached = ui.data_mut(|data| {
+        let cache = data.get_temp_mut_or_default::<GalleyCache>(id);
+        if cache.atlas != atlas || cache.galleys.len() >= MAX_GALLEYS {
+            *cache = GalleyCache { atlas, galleys: HashMap::new() };
+        }
+        cache.galleys.get(&key).cloned()
+    });
+    if let Some(galley) = cached {
+        return galley;
+    }
+    // Laid out outside of `data_mut`, which holds the context's lock
+    let galley = ui.fonts(|fonts| fonts.layout_job(job(wrap_width)));
+    ui.data_mut(|data| data.get_temp_mut_or_default::<GalleyCache>(id).galleys.insert(key, Arc::clone(&galley)));
+    galley
+}
+
+/// Title and body of a note shown in a link hover preview
+pub struct NotePreview {
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Default)]
+struct NoteLoader;
+
+impl egui::cache::ComputerMut<(&Path, Option<SystemTime>), Option<Arc<NotePreview>>> for NoteLoader {
+    fn compute(&mut self, (path, _): (&Path, Option<SystemTime>)) -> Option<Arc<NotePreview>> {
+        let doc = Document::open(path).ok()?;
+        Some(Arc::new(NotePreview { title: doc.title(), body: doc.content_without_frontmatter().to_string() }))
+    }
+}
+
+/// Previews of linked notes, kept while their link stays hovered
+type NotePreviewCache = egui::cache::FrameCache<Option<Arc<NotePreview>>, NoteLoader>;
+
+/// Preview of the note at `path`, read again only once the file was saved
+/// since
+pub fn cached_note_preview(ui: &Ui, path: &Path) -> Option<Arc<NotePreview>> {
+    let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    ui.memory_mut(|mem| mem.caches.cache::<NotePreviewCache>().get((path, modified)))
+}
+
+/// Child note lists of folder notes, read while the file tree stays the same
+#[derive(Clone, Default)]
+struct ChildNotesCache {
+    generation: u64,
+    lists: HashMap<PathBuf, Arc<Vec<ChildNote>>>,
+}
+
+fn child_notes_id() -> egui::Id {
+    egui::Id::new("child_notes_cache")
+}
+
+/// Notes listed by the folder note at `note`, read from its folder only the
+/// first time
+pub fn cached_child_notes(ui: &Ui, note: &Path) -> Arc<Vec<ChildNote>> {
+    let cached = ui.data_mut(|data| {
+        data.get_temp_mut_or_default::<ChildNotesCache>(child_notes_id()).lists.get(note).cloned()
+    });
+    if let Some(children) = cached {
+        return children;
+    }
+    let children = Arc::new(list_child_notes(note));
+    ui.data_mut(|data| {
+        let cache = data.get_temp_mut_or_default::<ChildNotesCache>(child_notes_id());
+        cache.lists.insert(note.to_path_buf(), Arc::clone(&children));
+    });
+    children
+}
+
+/// Forget the child note lists once the file tree was built again, as its
+/// `generation` tells
+pub fn file_tree_changed(ctx: &egui::Context, generation: u64) {
+    ctx.data_mut(|data| {
+        let cache = data.get_temp_mut_or_default::<ChildNotesCache>(child_notes_id());
+        if cache.generation != generation {
+            *cache = ChildNotesCache { generation, lists: HashMap::new() };
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_galley() {
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let galley = |text: &str, wrap_width| {
+                    cached_galley(ui, "code", text, wrap_width, |wrap_width| {
+                        let font = egui::FontId::monospace(14.0);
+                        LayoutJob::simple(text.to_string(), font, egui::Color32::WHITE, wrap_width)
+                    })
+                };
+                let first = galley("let x = 1;", 200.0);
+                assert!(Arc::ptr_eq(&first, &galley("let x = 1;", 200.4)));
+                assert_eq!(galley("let x = 1;", 120.0).job.wrap.max_width, 120.0);
+                assert_eq!(galley("let y = 1;", 200.0).text(), "let y = 1;");
+            });
+        });
+    }
+}
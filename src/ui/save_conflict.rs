@@ -0,0 +1,83 @@
+//! Save conflict dialog
+//!
+//! Shown when a note can't be saved because its file was changed outside
+//! the app since it was opened. The user keeps their version, takes the one
+//! on disk, or merges both.
+
+
+use eframe::egui;
+
+use robsidian_core::document::SaveConflict;
+use robsidian_core::document_store::DocumentStore;
+use crate::ui::file_history::show_line_diff;
+
+/// How the user resolved a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    /// Overwrite the file with the editor's content
+    KeepMine,
+    /// Replace the editor's content with the file's
+    UseDisk,
+    /// Merge both versions into the editor
+    Merge,
+    /// Decide later; the note stays unsaved
+    Later,
+}
+
+/// Conflicts waiting for a decision, shown one at a time
+#[derive(Debug, Default)]
+pub struct SaveConflictDialog {
+    conflicts: Vec<SaveConflict>,
+}
+
+impl SaveConflictDialog {
+    /// Queue a conflict, replacing an older one for the same note
+    pub fn push(&mut self, conflict: SaveConflict) {
+        self.conflicts.retain(|c| c.path != conflict.path);
+        self.conflicts.push(conflict);
+    }
+
+    /// Show the oldest conflict, returning it with the user's choice once
+    /// made
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        documents: &DocumentStore,
+    ) -> Option<(SaveConflict, ConflictChoice)> {
+        let conflict = self.conflicts.first()?;
+        let mine = documents.get(&conflict.path).map_or("", |doc| doc.content.as_str());
+        let mut choice = None;
+
+        egui::Window::new("File Changed on Disk")
+            .collapsible(false)
+            .default_size([640.0, 420.0])
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} was changed by another program after it was opened. \
+                     Saving now would overwrite those changes.",
+                    conflict.path.display()
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Merge").on_hover_text("Combine both versions in the editor").clicked() {
+                        choice = Some(ConflictChoice::Merge);
+                    }
+                    if ui.button("Keep Mine").on_hover_text("Overwrite the file").clicked() {
+                        choice = Some(ConflictChoice::KeepMine);
+                    }
+                    if ui.button("Use Disk Version").on_hover_text("Discard the unsaved edits").clicked() {
+                        choice = Some(ConflictChoice::UseDisk);
+                    }
+                    if ui.button("Later").clicked() {
+                        choice = Some(ConflictChoice::Later);
+                    }
+                });
+                ui.separator();
+                ui.weak("Changes on disk compared to your version:");
+                show_line_diff(ui, mine, &conflict.disk.content);
+            });
+
+        let choice = choice?;
+        Some((self.conflicts.remove(0), choice))
+    }
+}
@@ -0,0 +1,81 @@
+//! Settings import dialog
+//!
+//! Shows what a settings bundle holds and imports the parts picked.
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use robsidian_core::settings_bundle::{BundlePart, SettingsBundle};
+
+/// Bundle being imported
+#[derive(Debug)]
+struct Draft {
+    path: PathBuf,
+    bundle: SettingsBundle,
+    /// Parts the bundle has, and whether each is imported
+    parts: Vec<(BundlePart, bool)>,
+}
+
+/// Settings import dialog state
+#[derive(Debug, Default)]
+pub struct SettingsImportDialog {
+    /// `None` while closed
+    draft: Option<Draft>,
+}
+
+impl SettingsImportDialog {
+    /// Offer the parts of a bundle read from `path`, all picked
+    pub fn open(&mut self, path: PathBuf, bundle: SettingsBundle) {
+        let parts = bundle.parts().into_iter().map(|part| (part, true)).collect();
+        self.draft = Some(Draft { path, bundle, parts });
+    }
+
+    /// Show the dialog, returning the bundle and the parts to import once
+    /// the user confirms
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(SettingsBundle, Vec<BundlePart>)> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut import = false;
+
+        egui::Window::new("Import Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let name = draft.path.file_name().unwrap_or_default().to_string_lossy();
+                ui.label(format!("Import from {}:", name));
+                ui.add_space(4.0);
+                for (part, picked) in &mut draft.parts {
+                    let detail = match part {
+                        BundlePart::Settings => "editor, interface, terminal and other preferences".to_string(),
+                        BundlePart::TerminalThemes => draft.bundle.terminal_themes.len().to_string(),
+                        BundlePart::Scripts => draft.bundle.scripts.len().to_string(),
+                        BundlePart::Plugins => draft.bundle.enabled_plugins.join(", "),
+                    };
+                    ui.checkbox(picked, format!("{} ({})", part.label(), detail));
+                }
+                ui.add_space(4.0);
+                ui.weak(
+                    "Vaults, folders, credentials, the shell, SSH hosts and clipboard \
+                     access on this machine are kept. \
+                     Plugins that aren't installed yet are enabled once they are.",
+                );
+                ui.separator();
+                let any = draft.parts.iter().any(|(_, picked)| *picked);
+                if ui.add_enabled(any, egui::Button::new("Import")).clicked() {
+                    import = true;
+                }
+            });
+
+        if import {
+            let draft = self.draft.take()?;
+            let parts = draft.parts.into_iter().filter(|(_, picked)| *picked).map(|(part, _)| part).collect();
+            return Some((draft.bundle, parts));
+        }
+        if !open {
+            self.draft = None;
+        }
+        None
+    }
+}
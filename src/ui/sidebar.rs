@@ -34,11 +34,11 @@ impl Sidebar {
             // Recent files
             if !app.documents.is_empty() {
                 ui.collapsing("Open Files", |ui| {
-                    let paths: Vec<_> = app.documents.keys().cloned().collect();
+                    let paths: Vec<_> = app.documents.paths().cloned().collect();
                     for path in paths {
                         if let Some(file_name) = path.file_name() {
                             if ui.button(file_name.to_string_lossy()).clicked() {
-                                app.active_document = Some(path);
+                                app.open_document(path);
                             }
                         }
                     }
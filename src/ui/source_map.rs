@@ -0,0 +1,239 @@
+//! Mapping clicks on rendered text back to the markdown source
+//!
+//! While a block is rendered for live preview, the text it shows is recorded
+//! piece by piece with its layout. A click then finds the character under
+//! the pointer and the same character in the block's source: each piece is
+//! looked up in the source after the previous one, verbatim if it starts
+//! there once the markup rendering dropped is skipped (code spans, plain
+//! words) and else character by character, skipping escapes too. Pieces that
+//! show something other than their source (math, links and their aliases)
+//! are recorded with that source, so they are found by it.
+
+use std::sync::Arc;
+
+use eframe::egui::{self, Galley, Pos2, Ui, WidgetText};
+
+/// Text shown at a position
+#[derive(Clone)]
+pub struct TextPiece {
+    pos: Pos2,
+    galley: Arc<Galley>,
+    /// Markdown the text was rendered from, when it isn't the text itself
+    source: Option<String>,
+}
+
+fn pieces_id() -> egui::Id {
+    egui::Id::new("source_map_pieces")
+}
+
+/// Start recording the text shown from now on
+pub fn begin(ui: &Ui) {
+    ui.data_mut(|data| data.insert_temp::<Option<Vec<TextPiece>>>(pieces_id(), Some(Vec::new())));
+}
+
+/// Stop recording, returning the text shown since [`begin`]
+pub fn take(ui: &Ui) -> Vec<TextPiece> {
+    ui.data_mut(|data| data.remove_temp::<Option<Vec<TextPiece>>>(pieces_id()))
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Show text like [`Ui::label`], recording it while a block is being mapped
+pub fn label(ui: &mut Ui, text: impl Into<WidgetText>) -> egui::Response {
+    source_label(ui, text, None)
+}
+
+/// Show text rendered from `source`, such as math, recording it with its source
+pub fn label_from(ui: &mut Ui, text: impl Into<WidgetText>, source: String) -> egui::Response {
+    source_label(ui, text, Some(source))
+}
+
+/// Show a link like [`Ui::link`], recording its text with the `source` it
+/// was rendered from
+pub fn link(ui: &mut Ui, text: impl Into<WidgetText>, source: String) -> egui::Response {
+    let (pos, galley, response) = egui::Label::new(text).sense(egui::Sense::click()).layout_in_ui(ui);
+    if ui.is_rect_visible(response.rect) {
+        let color = ui.visuals().hyperlink_color;
+        let underline = if response.hovered() || response.has_focus() {
+            egui::Stroke::new(ui.style().interact(&response).fg_stroke.width, color)
+        } else {
+            egui::Stroke::NONE
+        };
+        let shape = egui::epaint::TextShape::new(pos, Arc::clone(&galley), color).with_underline(underline);
+        ui.painter().add(shape);
+        if response.hovered() {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        }
+    }
+    record(ui, TextPiece { pos, galley, source: Some(source) });
+    response
+}
+
+fn source_label(ui: &mut Ui, text: impl Into<WidgetText>, source: Option<String>) -> egui::Response {
+    let (pos, galley, response) = egui::Label::new(text).selectable(false).layout_in_ui(ui);
+    if ui.is_rect_visible(response.rect) {
+        let color = ui.style().interact(&response).text_color();
+        ui.painter().galley(pos, Arc::clone(&galley), color);
+    }
+    record(ui, TextPiece { pos, galley, source });
+    response
+}
+
+/// Add a piece to the recording, if one is going on
+fn record(ui: &Ui, piece: TextPiece) {
+    ui.data_mut(|data| {
+        if let Some(pieces) = data.get_temp_mut_or_default::<Option<Vec<TextPiece>>>(pieces_id()) {
+            pieces.push(piece);
+        }
+    });
+}
+
+/// Byte offset in `source` of the character shown under `pointer`, if the
+/// pointer is over recorded text
+pub fn source_offset(pieces: &[TextPiece], source: &str, pointer: Pos2) -> Option<usize> {
+    let mut from = 0;
+    for piece in pieces {
+        let text = piece.galley.text();
+        let (positions, end) = match place(source, from, text, piece.source.as_deref()) {
+            Some((positions, end)) => (Some(positions), Some(end)),
+            None => (None, None),
+        };
+        let hit = piece
+            .galley
+            .rows
+            .iter()
+            .any(|row| row.rect_without_leading_space().translate(piece.pos.to_vec2()).contains(pointer));
+        if hit {
+            // Clicking past the middle of the last character puts the caret after it
+            let index = piece.galley.cursor_from_pos(pointer - piece.pos).index;
+            let Some(positions) = positions else {
+                return Some(from);
+            };
+            return Some(match positions.get(index) {
+                Some(pos) => *pos,
+                None => positions.last().map_or(from, |last| after(source, *last)),
+            });
+        }
+        if let Some(end) = end {
+            from = end;
+        }
+    }
+    None
+}
+
+/// Byte offsets in `source`, at or after `from`, of the characters of `text`,
+/// and the offset after the piece. A piece with its own markdown is found by
+/// it, and its text within it; characters not shown verbatim there (a
+/// footnote's brackets) land on the construct's start.
+fn place(source: &str, from: usize, text: &str, markdown: Option<&str>) -> Option<(Vec<usize>, usize)> {
+    let Some(markdown) = markdown else {
+        let positions = locate(source, from, text)?;
+        let end = positions.last().map_or(from, |last| after(source, *last));
+        return Some((positions, end));
+    };
+    let start = from + verbatim_start(source.get(from..)?, markdown)?;
+    let positions = match markdown.rfind(text) {
+        Some(inner) if !text.is_empty() => text.char_indices().map(|(offset, _)| start + inner + offset).collect(),
+        _ => text.chars().map(|_| start).collect(),
+    };
+    Some((positions, start + markdown.len()))
+}
+
+/// Offset of the character after the one at `pos`
+fn after(source: &str, pos: usize) -> usize {
+    pos + source[pos..].chars().next().map_or(0, char::len_utf8)
+}
+
+/// Characters rendering drops: inline markup, escapes and block syntax
+const DROPPED_MARKUP: &[char] = &[
+    '\\', '*', '_', '~', '=', '`', '$', '#', '>', '|', '-', '+', ':', '[', ']', '^', '!',
+];
+
+/// Offset in `rest` where `text` starts, if it does so right after markup
+/// rendering dropped. A later copy of the text past other shown characters
+/// isn't taken for it.
+fn verbatim_start(rest: &str, text: &str) -> Option<usize> {
+    let mut pos = 0;
+    loop {
+        if rest[pos..].starts_with(text) {
+            return Some(pos);
+        }
+        pos += dropped_len(rest, pos)?;
+    }
+}
+
+/// Length of the markup rendering dropped at `pos` in `rest`, if there is any
+fn dropped_len(rest: &str, pos: usize) -> Option<usize> {
+    let here = &rest[pos..];
+    let line_start = pos == 0 || rest[..pos].ends_with('\n');
+    if line_start && (here.starts_with("```") || here.starts_with("~~~")) {
+        // A fence and its language
+        return Some(here.find('\n').map_or(here.len(), |end| end + 1));
+    }
+    let digits = here.len() - here.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if line_start && digits > 0 && here[digits..].starts_with(['.', ')']) {
+        return Some(digits + 1);
+    }
+    if here.starts_with("[x]") || here.starts_with("[X]") {
+        return Some(3);
+    }
+    let c = here.chars().next()?;
+    (c.is_whitespace() || DROPPED_MARKUP.contains(&c)).then(|| c.len_utf8())
+}
+
+/// Byte offsets in `source`, at or after `from`, of the characters of `text`
+fn locate(source: &str, from: usize, text: &str) -> Option<Vec<usize>> {
+    let rest = source.get(from..)?;
+    if let Some(start) = verbatim_start(rest, text) {
+        return Some(text.char_indices().map(|(offset, _)| from + start + offset).collect());
+    }
+    // Characters rendering kept, in order, with markup between them
+    let mut positions = Vec::new();
+    let mut chars = rest.char_indices();
+    for c in text.chars() {
+        let (offset, _) = chars.find(|(_, source_char)| *source_char == c)?;
+        positions.push(from + offset);
+    }
+    Some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let source = "Some **bold** and `code`, \\*escaped\\*";
+        assert_eq!(locate(source, 0, "bold"), Some(vec![7, 8, 9, 10]));
+        assert_eq!(locate(source, 11, "code"), Some(vec![19, 20, 21, 22]));
+        // Escapes are skipped
+        let escaped = locate(source, 24, "*escaped*").unwrap();
+        assert_eq!((escaped[0], escaped[8]), (27, 36));
+        assert_eq!(locate(source, 30, "bold"), None);
+    }
+
+    #[test]
+    fn test_locate_takes_the_next_occurrence() {
+        // The escaped stars are the shown ones, not the emphasis further on
+        let source = "\\*a\\* and *a* and more";
+        assert_eq!(locate(source, 0, "*a* and ").unwrap()[..3], [1, 2, 4]);
+        // A fence's language isn't taken for the code
+        let fence = "```rust\nrm -rf\n```";
+        assert_eq!(locate(fence, 0, "rm -rf").unwrap()[0], 8);
+    }
+
+    #[test]
+    fn test_place_math_and_aliases() {
+        let source = "Let $x$ be x";
+        assert_eq!(place(source, 4, "x", Some("$x$")), Some((vec![5], 7)));
+        assert_eq!(place(source, 7, " be x", None).unwrap().0[4], 11);
+
+        let source = "See [[target|alias]] alias";
+        let (positions, end) = place(source, 4, "alias", Some("[[target|alias]]")).unwrap();
+        assert_eq!((positions[0], end), (13, 20));
+        assert_eq!(place(source, end, " alias", None).unwrap().0[1], 21);
+        // The target isn't shown, so words after the link aren't looked for in it
+        assert_eq!(locate("[[x]]x", 5, "x"), Some(vec![5]));
+        assert_eq!(place("[^1] x", 0, "[1]", Some("[^1]")), Some((vec![0, 0, 0], 4)));
+    }
+}
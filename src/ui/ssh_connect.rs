@@ -0,0 +1,116 @@
+//! SSH quick-connect dialog
+//!
+//! Opens a terminal tab on a server, either one of those saved in the
+//! terminal settings or one typed in, which can be saved for next time.
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use robsidian_core::config::SshHost;
+
+/// Server being typed in
+#[derive(Debug, Default)]
+struct Draft {
+    host: SshHost,
+    /// Port and key as typed
+    port: String,
+    identity_file: String,
+    save: bool,
+}
+
+/// SSH quick-connect dialog state
+#[derive(Debug, Default)]
+pub struct SshConnectDialog {
+    /// `None` while closed
+    draft: Option<Draft>,
+}
+
+impl SshConnectDialog {
+    pub fn open(&mut self) {
+        self.draft = Some(Draft { save: true, ..Draft::default() });
+    }
+
+    /// Show the dialog, returning the server to connect to and whether to
+    /// save it in the settings
+    pub fn show(&mut self, ctx: &egui::Context, saved: &[SshHost]) -> Option<(SshHost, bool)> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut connect = None;
+
+        egui::Window::new("Connect over SSH")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if !saved.is_empty() {
+                    ui.label("Saved servers");
+                    for host in saved {
+                        ui.horizontal(|ui| {
+                            if ui.button(host.label()).clicked() {
+                                connect = Some((host.clone(), false));
+                            }
+                            ui.weak(host.destination());
+                        });
+                    }
+                    ui.separator();
+                }
+
+                egui::Grid::new("ssh_connect_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Host");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.host.host).hint_text("example.com").desired_width(240.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("User");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.host.user).hint_text("ssh's default").desired_width(240.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Port");
+                    ui.add(egui::TextEdit::singleline(&mut draft.port).hint_text("22").desired_width(80.0));
+                    ui.end_row();
+
+                    ui.label("Key file");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.identity_file)
+                            .hint_text("~/.ssh/id_ed25519")
+                            .desired_width(240.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Name");
+                    let destination = draft.host.destination();
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.host.name).hint_text(destination).desired_width(240.0),
+                    );
+                    ui.end_row();
+                });
+                ui.checkbox(&mut draft.save, "Save in the terminal settings");
+                ui.weak("Passwords and host keys are asked for in the terminal.");
+
+                let port = draft.port.trim();
+                let port_valid = port.is_empty() || port.parse::<u16>().is_ok();
+                if !port_valid {
+                    ui.colored_label(ui.visuals().error_fg_color, "The port must be a number up to 65535.");
+                }
+                ui.separator();
+                let valid = !draft.host.host.trim().is_empty() && port_valid;
+                let enter = ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.add_enabled(valid, egui::Button::new("Connect")).clicked() || (valid && enter) {
+                    let mut host = draft.host.clone();
+                    host.port = port.parse().ok();
+                    let key = draft.identity_file.trim();
+                    host.identity_file = (!key.is_empty()).then(|| PathBuf::from(key));
+                    connect = Some((host, draft.save));
+                }
+            });
+
+        if connect.is_some() || !open {
+            self.draft = None;
+        }
+        connect
+    }
+}
@@ -0,0 +1,120 @@
+//! Sync settings dialog
+//!
+//! Edits where the vault is synced to, how often, and how conflicts are
+//! settled.
+
+use eframe::egui;
+
+use robsidian_core::config::SyncConfig;
+use robsidian_core::sync::{ConflictStrategy, RemoteConfig};
+
+/// Sync settings dialog state
+#[derive(Debug, Default)]
+pub struct SyncSettingsDialog {
+    /// Settings being edited; `None` while closed
+    draft: Option<SyncConfig>,
+}
+
+impl SyncSettingsDialog {
+    pub fn open(&mut self, config: &SyncConfig) {
+        self.draft = Some(config.clone());
+    }
+
+    /// Show the dialog, returning the new settings once saved
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<SyncConfig> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut saved = false;
+
+        egui::Window::new("Sync Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Remote");
+                    let kind = match draft.remote {
+                        None => "None",
+                        Some(RemoteConfig::WebDav { .. }) => "WebDAV",
+                        Some(RemoteConfig::S3 { .. }) => "S3",
+                    };
+                    egui::ComboBox::from_id_salt("sync_remote").selected_text(kind).show_ui(ui, |ui| {
+                        if ui.selectable_label(kind == "None", "None").clicked() {
+                            draft.remote = None;
+                        }
+                        if ui.selectable_label(kind == "WebDAV", "WebDAV").clicked() && kind != "WebDAV" {
+                            draft.remote = Some(RemoteConfig::WebDav {
+                                url: String::new(),
+                                username: String::new(),
+                                password: String::new(),
+                            });
+                        }
+                        if ui.selectable_label(kind == "S3", "S3").clicked() && kind != "S3" {
+                            draft.remote = Some(RemoteConfig::S3 {
+                                endpoint: String::new(),
+                                region: "us-east-1".to_string(),
+                                bucket: String::new(),
+                                prefix: String::new(),
+                                access_key: String::new(),
+                                secret_key: String::new(),
+                            });
+                        }
+                    });
+                });
+
+                egui::Grid::new("sync_remote_grid").num_columns(2).show(ui, |ui| {
+                    let row = |ui: &mut egui::Ui, label: &str, value: &mut String, secret: bool| {
+                        ui.label(label);
+                        ui.add(egui::TextEdit::singleline(value).password(secret).desired_width(280.0));
+                        ui.end_row();
+                    };
+                    match &mut draft.remote {
+                        Some(RemoteConfig::WebDav { url, username, password }) => {
+                            row(ui, "Folder URL", url, false);
+                            row(ui, "Username", username, false);
+                            row(ui, "Password", password, true);
+                        }
+                        Some(RemoteConfig::S3 { endpoint, region, bucket, prefix, access_key, secret_key }) => {
+                            row(ui, "Endpoint", endpoint, false);
+                            row(ui, "Region", region, false);
+                            row(ui, "Bucket", bucket, false);
+                            row(ui, "Prefix", prefix, false);
+                            row(ui, "Access key", access_key, false);
+                            row(ui, "Secret key", secret_key, true);
+                        }
+                        None => {}
+                    }
+                });
+                ui.separator();
+
+                ui.checkbox(&mut draft.enabled, "Sync in the background");
+                ui.horizontal(|ui| {
+                    ui.label("Every");
+                    ui.add(egui::DragValue::new(&mut draft.interval_minutes).range(0..=1440));
+                    ui.label("minutes (0 = only when asked)");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("When a note changed on both sides");
+                    egui::ComboBox::from_id_salt("sync_conflicts")
+                        .selected_text(draft.conflicts.label())
+                        .show_ui(ui, |ui| {
+                            for strategy in ConflictStrategy::ALL {
+                                ui.selectable_value(&mut draft.conflicts, strategy, strategy.label());
+                            }
+                        });
+                });
+                ui.separator();
+                if ui.button("Save").clicked() {
+                    saved = true;
+                }
+            });
+
+        if saved {
+            return self.draft.take();
+        }
+        if !open {
+            self.draft = None;
+        }
+        None
+    }
+}
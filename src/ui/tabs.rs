@@ -0,0 +1,155 @@
+//! Editor tab bar
+//!
+//! Each tab remembers its own view mode, so one note can be read while
+//! another is being edited. Pinned tabs keep their note when links are
+//! followed from them.
+
+use eframe::egui;
+
+use robsidian_core::document::is_scratch_path;
+
+use crate::app::RobsidianApp;
+
+/// Tab bar shown above the editor area
+pub struct TabBar;
+
+impl TabBar {
+    /// Show the tab bar with navigation buttons and a view mode toggle for
+    /// the active tab
+    pub fn show(ui: &mut egui::Ui, app: &mut RobsidianApp) {
+        ui.horizontal(|ui| {
+            Self::show_navigation(ui, app);
+            ui.separator();
+
+            // Mode and star toggles for the active tab, pinned to the right
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Some(path) = app.active_document.clone().filter(|path| !is_scratch_path(path)) {
+                    let starred = app.is_starred(&path);
+                    let (icon, hover) = if starred { ("★", "Unstar note") } else { ("☆", "Star note") };
+                    if ui.button(icon).on_hover_text(hover).clicked() {
+                        app.toggle_star(&path);
+                    }
+                }
+                if let Some(tab) = app.active_tab_mut() {
+                    let next = tab.view_mode.next_document_mode();
+                    if ui
+                        .button(tab.view_mode.label())
+                        .on_hover_text(format!("Switch this tab to {}", next.label()))
+                        .clicked()
+                    {
+                        tab.view_mode = next;
+                    }
+                }
+
+                ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
+                    egui::ScrollArea::horizontal()
+                        .id_salt("editor_tabs")
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| Self::show_tabs(ui, app));
+                        });
+                });
+            });
+        });
+    }
+
+    /// Back/forward buttons and the history dropdown of the active tab
+    fn show_navigation(ui: &mut egui::Ui, app: &mut RobsidianApp) {
+        let Some(tab) = app.active_tab() else {
+            return;
+        };
+        let can_back = tab.history.can_go_back();
+        let can_forward = tab.history.can_go_forward();
+
+        if ui
+            .add_enabled(can_back, egui::Button::new("◀"))
+            .on_hover_text("Back (Alt+Left)")
+            .clicked()
+        {
+            app.go_back();
+        }
+        if ui
+            .add_enabled(can_forward, egui::Button::new("▶"))
+            .on_hover_text("Forward (Alt+Right)")
+            .clicked()
+        {
+            app.go_forward();
+        }
+
+        let mut jump = None;
+        ui.menu_button("⏷", |ui| {
+            let Some(tab) = app.active_tab() else {
+                return;
+            };
+            let current = tab.history.current_index();
+            // Newest first, like a browser's history list
+            for (idx, entry) in tab.history.entries().iter().enumerate().rev() {
+                if ui.selectable_label(idx == current, entry.label()).clicked() {
+                    jump = Some(idx);
+                    ui.close();
+                }
+            }
+        })
+        .response
+        .on_hover_text("History");
+        if let Some(idx) = jump {
+            app.jump_in_history(idx);
+        }
+    }
+
+    /// Show one selectable label per open tab
+    fn show_tabs(ui: &mut egui::Ui, app: &mut RobsidianApp) {
+        let mut clicked = None;
+        let mut toggle_pin = None;
+        let mut toggle_star = None;
+
+        for (idx, tab) in app.tabs.iter().enumerate() {
+            let title = match app.documents.get(&tab.path) {
+                Some(doc) if doc.modified => format!("{}*", doc.title()),
+                Some(doc) => doc.title(),
+                // Still loading
+                None => tab
+                    .path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            };
+            let title = if tab.pinned { format!("📌 {}", title) } else { title };
+            // Read-only while another program edits the note
+            let title = match app.external_editors.editor_of(&tab.path) {
+                Some(_) => format!("🔒 {}", title),
+                None => title,
+            };
+            if app.io.operation(&tab.path).is_some() {
+                ui.spinner();
+            }
+
+            let response = ui
+                .selectable_label(idx == app.active_tab, app.note_colors().text(&tab.path, title))
+                .on_hover_text(tab.view_mode.label());
+            if response.clicked() {
+                clicked = Some(idx);
+            }
+            response.context_menu(|ui| {
+                if ui.button(if tab.pinned { "Unpin" } else { "Pin" }).clicked() {
+                    toggle_pin = Some(idx);
+                    ui.close();
+                }
+                let star = if app.is_starred(&tab.path) { "Unstar" } else { "Star" };
+                if ui.button(star).clicked() {
+                    toggle_star = Some(tab.path.clone());
+                    ui.close();
+                }
+            });
+        }
+
+        if let Some(idx) = clicked {
+            app.activate_tab(idx);
+        }
+        if let Some(tab) = toggle_pin.and_then(|idx| app.tabs.get_mut(idx)) {
+            tab.pinned = !tab.pinned;
+        }
+        if let Some(path) = toggle_star {
+            app.toggle_star(&path);
+        }
+    }
+}
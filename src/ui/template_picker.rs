@@ -0,0 +1,108 @@
+//! Template picker
+//!
+//! Lists the vault's templates by name; the picked one is inserted into the
+//! active note.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Key};
+
+/// Template picker state
+#[derive(Debug, Default)]
+pub struct TemplatePicker {
+    open: bool,
+    templates: Vec<PathBuf>,
+    /// Folder the templates are in, for display names relative to it
+    folder: PathBuf,
+    query: String,
+    selected: usize,
+}
+
+impl TemplatePicker {
+    /// Open the picker with the templates of a folder
+    pub fn open(&mut self, folder: PathBuf, templates: Vec<PathBuf>) {
+        self.open = true;
+        self.folder = folder;
+        self.templates = templates;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    fn name(&self, template: &Path) -> String {
+        template
+            .strip_prefix(&self.folder)
+            .unwrap_or(template)
+            .with_extension("")
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Show the picker, returning the template picked this frame
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        let query = self.query.to_lowercase();
+        let matches: Vec<(&PathBuf, String)> = self
+            .templates
+            .iter()
+            .map(|template| (template, self.name(template)))
+            .filter(|(_, name)| name.to_lowercase().contains(&query))
+            .collect();
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+
+        let mut picked = None;
+        let mut close = false;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, Key::Escape) {
+                close = true;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowDown) && self.selected + 1 < matches.len() {
+                self.selected += 1;
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::ArrowUp) {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            if i.consume_key(egui::Modifiers::NONE, Key::Enter) {
+                picked = matches.get(self.selected).map(|(path, _)| (*path).clone());
+            }
+        });
+
+        egui::Window::new("Insert Template")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 60.0))
+            .fixed_size(egui::vec2(400.0, 0.0))
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Find a template...")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    if matches.is_empty() {
+                        ui.weak("No matching templates");
+                    }
+                    for (idx, (path, name)) in matches.iter().enumerate() {
+                        if ui.selectable_label(idx == self.selected, name).clicked() {
+                            picked = Some((*path).clone());
+                        }
+                    }
+                });
+            });
+
+        if picked.is_some() || close {
+            self.open = false;
+        }
+        picked
+    }
+}
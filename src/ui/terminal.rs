@@ -1,8 +1,86 @@
 //! Terminal UI panel
 
-use eframe::egui::{self, Color32, FontId, Key, RichText};
+use std::time::Duration;
 
-use crate::terminal::{PtyTerminalState, TerminalKey, TerminalState};
+use eframe::egui::{self, Color32, FontId, Key};
+
+use robsidian_core::config::BellStyle;
+use crate::terminal::{
+    CellPos, PtyTerminalState, Selection, SplitDirection, TerminalKey, TerminalState, OTHER_SHELLS,
+};
+
+use super::ime;
+use super::terminal_layout::LineLayouts;
+
+/// Id of the PTY terminal's area, which has focus while typing into it
+pub const TERMINAL_ID: &str = "pty_terminal";
+
+/// Id of the simple terminal's command input
+const INPUT_ID: &str = "terminal_input";
+
+/// Most matches the history search popup lists at once
+const HISTORY_SEARCH_ROWS: usize = 10;
+
+/// Width of the strip holding the scrollback scrollbar
+const SCROLLBAR_WIDTH: f32 = 10.0;
+
+/// Seconds the cursor stays on, and then off, while the terminal has focus
+const CURSOR_BLINK: f64 = 0.5;
+
+/// How long the terminal flashes when a program rings the bell
+const BELL_FLASH: Duration = Duration::from_millis(200);
+
+/// Longest title shown on a terminal tab, in characters
+const TAB_TITLE_LEN: usize = 24;
+
+/// Title shortened to fit a tab, keeping its end where paths and commands
+/// differ the most
+fn tab_title(title: &str) -> String {
+    let len = title.chars().count();
+    if len <= TAB_TITLE_LEN {
+        return title.to_string();
+    }
+    let tail: String = title.chars().skip(len - (TAB_TITLE_LEN - 1)).collect();
+    format!("…{}", tail)
+}
+
+/// Area of split pane `number` of `count`, arranged in `direction` with
+/// `gap` between them
+fn pane_rect(area: egui::Rect, direction: SplitDirection, number: usize, count: usize, gap: f32) -> egui::Rect {
+    let count = count.max(1) as f32;
+    let number = number as f32;
+    match direction {
+        SplitDirection::Right => {
+            let width = (area.width() - gap * (count - 1.0)) / count;
+            let left = area.min.x + number * (width + gap);
+            egui::Rect::from_x_y_ranges(left..=left + width, area.y_range())
+        }
+        SplitDirection::Down => {
+            let height = (area.height() - gap * (count - 1.0)) / count;
+            let top = area.min.y + number * (height + gap);
+            egui::Rect::from_x_y_ranges(area.x_range(), top..=top + height)
+        }
+    }
+}
+
+/// Size of a terminal cell in the monospace font at the chosen size
+struct CellMetrics {
+    font_id: FontId,
+    /// Advance of one glyph
+    width: f32,
+    /// Height of a row
+    height: f32,
+}
+
+impl CellMetrics {
+    /// Measure the cells of the monospace font at `font_size` points, as laid
+    /// out at the current pixels per point
+    fn measure(ui: &egui::Ui, font_size: f32) -> Self {
+        let font_id = FontId::monospace(font_size);
+        let (width, height) = ui.fonts(|fonts| (fonts.glyph_width(&font_id, 'M'), fonts.row_height(&font_id)));
+        Self { font_id, width: width.max(1.0), height: height.max(1.0) }
+    }
+}
 
 /// Terminal panel
 pub struct TerminalPanel;
@@ -54,10 +132,15 @@ impl TerminalPanel {
 
             // Input area
             ui.separator();
+            if terminal.history_search.is_some() {
+                Self::show_history_search(ui, terminal);
+                return;
+            }
             ui.horizontal(|ui| {
                 ui.label("$");
                 let response = ui.add(
                     egui::TextEdit::singleline(&mut terminal.input)
+                        .id(egui::Id::new(INPUT_ID))
                         .font(egui::TextStyle::Monospace)
                         .desired_width(ui.available_width() - 60.0),
                 );
@@ -66,6 +149,15 @@ impl TerminalPanel {
                     terminal.execute_command();
                     response.request_focus();
                 }
+                if response.has_focus() {
+                    if ui.input(|i| i.key_pressed(Key::ArrowUp)) {
+                        terminal.history_up();
+                    } else if ui.input(|i| i.key_pressed(Key::ArrowDown)) {
+                        terminal.history_down();
+                    } else if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, Key::R)) {
+                        terminal.reverse_search();
+                    }
+                }
 
                 if ui.button("Run").clicked() {
                     terminal.execute_command();
@@ -73,23 +165,145 @@ impl TerminalPanel {
             });
         });
     }
+
+    /// Query line of the Ctrl+R history search, in place of the input, with
+    /// the matching commands in a popup above it, the newest at the bottom.
+    /// Ctrl+R or Up selects an older match and Down a newer one, Enter runs
+    /// the selected command, Tab or a click takes it into the input to edit
+    /// it and Escape goes back.
+    fn show_history_search(ui: &mut egui::Ui, terminal: &mut TerminalState) {
+        let matches = terminal.search_matches();
+        let selected = matches.iter().position(|&index| terminal.is_found(index)).unwrap_or(0);
+        let popup_pos = ui.cursor().min;
+        let mut clicked = None;
+        if !matches.is_empty() {
+            // A window of the matches that keeps the selected one shown
+            let first = selected.saturating_sub(HISTORY_SEARCH_ROWS - 1);
+            let shown = &matches[first..matches.len().min(first + HISTORY_SEARCH_ROWS)];
+            egui::Area::new(egui::Id::new(INPUT_ID).with("history_search"))
+                .order(egui::Order::Foreground)
+                .pivot(egui::Align2::LEFT_BOTTOM)
+                .fixed_pos(popup_pos)
+                .show(ui.ctx(), |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        if first + shown.len() < matches.len() {
+                            ui.weak(format!("{} older", matches.len() - first - shown.len()));
+                        }
+                        for &index in shown.iter().rev() {
+                            let command = &terminal.history.entries()[index];
+                            let text = egui::RichText::new(command).monospace();
+                            if ui.selectable_label(terminal.is_found(index), text).clicked() {
+                                clicked = Some(index);
+                            }
+                        }
+                    });
+                });
+        }
+
+        let Some(search) = &mut terminal.history_search else {
+            return;
+        };
+        let mut query_changed = false;
+        let (older, newer, run, edit, cancel) = ui
+            .horizontal(|ui| {
+                ui.label("(reverse-i-search)");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut search.query)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(ui.available_width() / 3.0),
+                );
+                response.request_focus();
+                query_changed = response.changed();
+                if matches.is_empty() {
+                    ui.weak("No match");
+                }
+                ui.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::COMMAND, Key::R)
+                            || i.consume_key(egui::Modifiers::NONE, Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, Key::ArrowDown),
+                        i.key_pressed(Key::Enter),
+                        i.consume_key(egui::Modifiers::NONE, Key::Tab),
+                        i.key_pressed(Key::Escape),
+                    )
+                })
+            })
+            .inner;
+
+        if query_changed {
+            terminal.update_search();
+        }
+        if let Some(index) = clicked {
+            terminal.select_match(index);
+        }
+        if older {
+            terminal.reverse_search();
+        } else if newer {
+            terminal.forward_search();
+        } else if run || edit || cancel || clicked.is_some() {
+            terminal.finish_search(!cancel);
+            if run {
+                terminal.execute_command();
+            }
+            ui.memory_mut(|memory| memory.request_focus(egui::Id::new(INPUT_ID)));
+        }
+    }
 }
 
 /// PTY Terminal panel for interactive shell sessions
+///
+/// The panel doesn't repaint on its own: the PTY reader thread wakes the UI
+/// when output arrives (see [`PtyTerminalState::set_waker`]), and otherwise
+/// only the cursor blink asks for a frame, twice a second while focused. An
+/// idle terminal therefore keeps the app at about 0% CPU.
 pub struct PtyTerminalPanel;
 
 impl PtyTerminalPanel {
-    /// Show the PTY terminal panel
-    pub fn show(ui: &mut egui::Ui, terminal: &mut PtyTerminalState, ctx: &egui::Context) {
+    /// Menu picking the color scheme of the current tab
+    fn show_scheme_picker(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        let current = terminal.current_tab().and_then(|tab| tab.color_scheme.clone());
+        let mut picked = None;
+        ui.menu_button("🎨", |ui| {
+            if ui.radio(current.is_none(), "As in the settings").clicked() {
+                picked = Some(None);
+            }
+            ui.separator();
+            for scheme in terminal.color_schemes() {
+                let selected = current.as_deref() == Some(scheme.name.as_ref());
+                if ui.radio(selected, scheme.name.as_ref()).clicked() {
+                    picked = Some(Some(scheme.name.to_string()));
+                }
+            }
+        })
+        .response
+        .on_hover_text("Colors of this tab");
+        if let Some(name) = picked {
+            terminal.set_tab_color_scheme(name);
+        }
+    }
+
+    /// Show the PTY terminal panel with its text at `font_size` points.
+    /// Returns whether the terminal's output should go into the active note.
+    pub fn show(ui: &mut egui::Ui, terminal: &mut PtyTerminalState, ctx: &egui::Context, font_size: f32) -> bool {
         // Process any pending output
         terminal.process_all_output();
 
+        let mut insert_output = false;
         ui.vertical(|ui| {
             // Header with shell info and controls
             ui.horizontal(|ui| {
-                if let Some(tab) = terminal.current_tab() {
-                    let shell_name = tab.pty.shell_name();
-                    ui.heading(format!("Terminal ({})", shell_name));
+                if terminal.renaming.is_some() {
+                    Self::show_rename(ui, terminal);
+                } else if let Some(tab) = terminal.current_tab() {
+                    // The name given to the tab, or the title programs report,
+                    // e.g. the directory or the running command
+                    let heading = match tab.label() {
+                        Some(label) => ui.heading(label).on_hover_text(tab.shell_name()),
+                        None => ui.heading(format!("Terminal ({})", tab.shell_name())),
+                    };
+                    if heading.interact(egui::Sense::click()).double_clicked() {
+                        terminal.start_renaming();
+                    }
                 } else {
                     ui.heading("Terminal");
                 }
@@ -99,19 +313,26 @@ impl PtyTerminalPanel {
                     egui::ComboBox::from_id_salt("shell_selector")
                         .selected_text("+ New")
                         .show_ui(ui, |ui| {
-                            if ui.selectable_label(false, "Nushell").clicked() {
-                                terminal.new_tab_with_shell("nu");
+                            let default = format!("Default ({})", terminal.default_shell);
+                            if ui.selectable_label(false, default).clicked() {
+                                terminal.new_tab();
                             }
-                            if ui.selectable_label(false, "PowerShell").clicked() {
-                                terminal.new_tab_with_shell("pwsh");
+                            for (name, program) in OTHER_SHELLS {
+                                if ui.selectable_label(false, *name).clicked() {
+                                    terminal.new_tab_with_shell(program);
+                                }
                             }
-                            #[cfg(windows)]
-                            if ui.selectable_label(false, "CMD").clicked() {
-                                terminal.new_tab_with_shell("cmd");
+                            if !terminal.ssh_hosts.is_empty() {
+                                ui.separator();
                             }
-                            #[cfg(not(windows))]
-                            if ui.selectable_label(false, "Bash").clicked() {
-                                terminal.new_tab_with_shell("bash");
+                            let mut connect = None;
+                            for host in &terminal.ssh_hosts {
+                                if ui.selectable_label(false, format!("SSH: {}", host.label())).clicked() {
+                                    connect = Some(host.clone());
+                                }
+                            }
+                            if let Some(host) = connect {
+                                terminal.connect_ssh(&host);
                             }
                         });
 
@@ -120,70 +341,546 @@ impl PtyTerminalPanel {
                             terminal.close_current_tab();
                         }
                     }
+                    Self::show_scheme_picker(ui, terminal);
+                    if ui.button("⬓").on_hover_text("Split down").clicked() {
+                        terminal.split(SplitDirection::Down);
+                    }
+                    if ui.button("◫").on_hover_text("Split right (Alt+arrows move between panes)").clicked() {
+                        terminal.split(SplitDirection::Right);
+                    }
+                    insert_output = ui
+                        .button("Insert into Note")
+                        .on_hover_text("Insert the selection, or the last command's output, into the note")
+                        .clicked();
                 });
             });
 
             // Tab bar (if multiple terminals)
             if terminal.tabs.len() > 1 {
                 // Collect tab info first to avoid borrow issues
-                let tab_info: Vec<(usize, String)> = terminal
+                let tab_info: Vec<(usize, String, Option<String>)> = terminal
                     .tabs
                     .iter()
                     .enumerate()
-                    .map(|(idx, tab)| (idx, tab.pty.shell_name().to_string()))
+                    .map(|(idx, tab)| (idx, tab.shell_name().to_string(), tab.label().map(str::to_string)))
                     .collect();
 
                 let mut clicked_tab = None;
+                let mut rename = false;
                 ui.horizontal(|ui| {
-                    for (idx, shell_name) in &tab_info {
-                        let label = format!("{} {}", shell_name, idx + 1);
+                    for (idx, shell_name, title) in &tab_info {
+                        let label = match title {
+                            Some(title) => tab_title(title),
+                            None => format!("{} {}", shell_name, idx + 1),
+                        };
                         let selected = terminal.active_tab == *idx;
 
-                        if ui.selectable_label(selected, label).clicked() {
+                        let response = ui.selectable_label(selected, label);
+                        let response = match title {
+                            Some(title) => response.on_hover_text(format!("{} ({})", title, shell_name)),
+                            None => response,
+                        };
+                        if response.clicked() || response.double_clicked() {
                             clicked_tab = Some(*idx);
+                            rename = response.double_clicked();
                         }
                     }
                 });
 
                 if let Some(idx) = clicked_tab {
                     terminal.switch_tab(idx);
+                    if rename {
+                        terminal.start_renaming();
+                    }
                 }
             }
 
             ui.separator();
 
-            // Check for error state
-            if let Some(tab) = terminal.current_tab() {
-                if let Some(error) = &tab.error {
-                    ui.colored_label(Color32::RED, error);
-                    ui.add_space(8.0);
-                    ui.label("Tips:");
-                    ui.label("- Make sure Nushell is installed: https://www.nushell.sh/");
-                    ui.label("- Or try a different shell from the dropdown");
-                    return;
+            Self::show_search_bar(ui, terminal);
+
+            // Terminal content area, split between the panes
+            let area = ui.available_rect_before_wrap();
+            ui.advance_cursor_after_rect(area);
+            Self::handle_tab_keys(ui, terminal);
+            let shown = terminal.shown_tabs();
+            if shown.len() > 1 {
+                Self::handle_pane_keys(ui, terminal);
+            }
+            let focused = terminal.active_tab;
+            let mut focus = focused;
+            let gap = ui.spacing().item_spacing.x;
+            for (number, tab) in shown.iter().enumerate() {
+                let rect = pane_rect(area, terminal.split_direction, number, shown.len(), gap);
+                let mut pane_ui = ui.new_child(egui::UiBuilder::new().max_rect(rect).id_salt(("terminal_pane", tab)));
+                terminal.active_tab = *tab;
+                if Self::show_pane(&mut pane_ui, terminal, ctx, font_size, *tab == focused) {
+                    focus = *tab;
                 }
             }
+            terminal.active_tab = focus;
+            if focus != focused {
+                terminal.renaming = None;
+                ui.memory_mut(|memory| memory.request_focus(egui::Id::new(TERMINAL_ID)));
+            }
+        });
+        insert_output
+    }
 
-            // Terminal content area
-            let available_rect = ui.available_rect_before_wrap();
-            let response = ui.allocate_rect(available_rect, egui::Sense::click_and_drag());
+    /// Edit the current tab's name in place of the header. Enter keeps it,
+    /// Escape or clicking elsewhere leaves the tab as it was.
+    fn show_rename(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        let Some(name) = &mut terminal.renaming else {
+            return;
+        };
+        let response = ui.add(egui::TextEdit::singleline(name).hint_text("Tab name").desired_width(200.0));
+        if !response.has_focus() && !response.lost_focus() {
+            response.request_focus();
+        }
+        if response.lost_focus() {
+            let name = terminal.renaming.take().unwrap_or_default();
+            if ui.input(|i| i.key_pressed(Key::Enter)) {
+                terminal.rename_current_tab(&name);
+            }
+            ui.memory_mut(|memory| memory.request_focus(egui::Id::new(TERMINAL_ID)));
+        }
+    }
+
+    /// Move the focus between split panes with Alt+arrow keys while one of
+    /// them has it
+    fn handle_pane_keys(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        if !ui.memory(|memory| memory.has_focus(egui::Id::new(TERMINAL_ID))) {
+            return;
+        }
+        let (next, previous) = ui.input_mut(|i| {
+            let mut pressed = |key| i.consume_key(egui::Modifiers::ALT, key);
+            (pressed(Key::ArrowRight) || pressed(Key::ArrowDown), pressed(Key::ArrowLeft) || pressed(Key::ArrowUp))
+        });
+        if next || previous {
+            terminal.focus_pane(next);
+        }
+    }
+
+    /// Go to the next or previous tab with Ctrl+PageDown and Ctrl+PageUp
+    /// while the terminal has the focus
+    fn handle_tab_keys(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        if !ui.memory(|memory| memory.has_focus(egui::Id::new(TERMINAL_ID))) {
+            return;
+        }
+        let (next, previous) = ui.input_mut(|i| {
+            (i.consume_key(egui::Modifiers::CTRL, Key::PageDown), i.consume_key(egui::Modifiers::CTRL, Key::PageUp))
+        });
+        if next || previous {
+            terminal.cycle_tab(next);
+        }
+    }
+
+    /// Show the active tab in a pane. The focused pane's area has the
+    /// terminal's id, so it has the keyboard focus. Returns whether another
+    /// pane was clicked to focus it.
+    fn show_pane(
+        ui: &mut egui::Ui,
+        terminal: &mut PtyTerminalState,
+        ctx: &egui::Context,
+        font_size: f32,
+        focused: bool,
+    ) -> bool {
+        // Check for error state
+        if terminal.current_tab().is_some_and(|tab| tab.error.is_some()) {
+            Self::show_start_error(ui, terminal);
+            return false;
+        }
+
+        let available_rect = ui.available_rect_before_wrap();
+        ui.advance_cursor_after_rect(available_rect);
+        let id = if focused {
+            egui::Id::new(TERMINAL_ID)
+        } else {
+            egui::Id::new(TERMINAL_ID).with(terminal.active_tab)
+        };
+        let response = ui.interact(available_rect, id, egui::Sense::click_and_drag());
+        let (text_rect, bar_rect) = available_rect.split_left_right_at_x(available_rect.max.x - SCROLLBAR_WIDTH);
+        let metrics = CellMetrics::measure(ui, font_size);
+        Self::fit_to_rect(terminal, text_rect, &metrics);
+
+        // Request focus when clicked
+        if response.clicked() && focused {
+            response.request_focus();
+        }
+
+        // Handle keyboard input when focused
+        if response.has_focus() {
+            Self::handle_keyboard_input(ui, terminal);
+        }
+        if response.hovered() {
+            Self::handle_scroll_wheel(ui, terminal, response.id, &metrics);
+        }
+        Self::handle_links(ui, terminal, &response, text_rect, &metrics);
+        Self::handle_selection(terminal, &response, text_rect, &metrics);
+
+        // Draw terminal content; the program may have hidden the cursor
+        let cursor_shown = terminal.current_tab().is_none_or(|tab| tab.buffer.cursor_visible());
+        let cursor_visible = if !cursor_shown {
+            false
+        } else if response.has_focus() {
+            let phase = ui.input(|input| input.time) % (2.0 * CURSOR_BLINK);
+            let next_blink = CURSOR_BLINK - phase % CURSOR_BLINK;
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(next_blink));
+            phase < CURSOR_BLINK
+        } else {
+            true
+        };
+        Self::render_terminal_buffer(ui, terminal, text_rect, &metrics, cursor_visible);
+        if response.has_focus() {
+            Self::show_composition(ui, terminal, text_rect, &metrics);
+        }
+        Self::show_scrollbar(ui, terminal, bar_rect, response.id);
+        Self::show_jump_to_bottom(ui, terminal, text_rect);
+        if focused && terminal.panes.len() > 1 {
+            let stroke = ui.visuals().selection.stroke;
+            ui.painter().rect_stroke(available_rect, 0.0, stroke, egui::StrokeKind::Inside);
+        }
+        Self::show_bell(ui, terminal, available_rect);
+        response.clicked() && !focused
+    }
+
+    /// Let the input method compose text into the focused pane, with its
+    /// candidates next to the cursor, and draw the text being composed over
+    /// the cursor until it is committed
+    fn show_composition(ui: &egui::Ui, terminal: &PtyTerminalState, rect: egui::Rect, metrics: &CellMetrics) {
+        let Some(tab) = terminal.current_tab() else {
+            return;
+        };
+        let cursor = tab.buffer.cursor();
+        let row = cursor.row as usize + tab.buffer.view_offset();
+        let min = rect.min + egui::vec2(cursor.col as f32 * metrics.width, row as f32 * metrics.height);
+        let cursor_rect = egui::Rect::from_min_size(min, egui::vec2(1.0, metrics.height));
+        ui.ctx().output_mut(|output| output.ime = Some(egui::output::IMEOutput { rect, cursor_rect }));
+
+        let preedit = ime::preedit(ui.ctx());
+        if preedit.is_empty() {
+            return;
+        }
+        let scheme = terminal.color_scheme();
+        let galley = ui.painter().layout_no_wrap(preedit, metrics.font_id.clone(), scheme.foreground);
+        let text_rect = egui::Rect::from_min_size(min, egui::vec2(galley.size().x, metrics.height));
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(text_rect, 0.0, scheme.background);
+        painter.galley(min, galley, scheme.foreground);
+        let underline = egui::Stroke::new(1.0, scheme.foreground);
+        painter.hline(text_rect.x_range(), text_rect.bottom() - 1.0, underline);
+    }
+
+    /// Flash the pane for a moment after a program rang the bell
+    fn show_bell(ui: &egui::Ui, terminal: &PtyTerminalState, rect: egui::Rect) {
+        let Some(rung) = terminal.current_tab().and_then(|tab| tab.bell) else {
+            return;
+        };
+        let elapsed = rung.elapsed();
+        if terminal.bell_style == BellStyle::Off || elapsed >= BELL_FLASH {
+            return;
+        }
+        let fade = 1.0 - elapsed.as_secs_f32() / BELL_FLASH.as_secs_f32();
+        let color = ui.visuals().text_color().gamma_multiply(0.25 * fade);
+        ui.painter().rect_filled(rect, 0.0, color);
+        ui.ctx().request_repaint();
+    }
+
+    /// Why the current tab's shell didn't start, with the other shells found
+    /// to start instead
+    fn show_start_error(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        let Some(tab) = terminal.current_tab() else {
+            return;
+        };
+        let mut start = None;
+        egui::ScrollArea::vertical().id_salt("shell_diagnostics").show(ui, |ui| {
+            if let Some(error) = &tab.error {
+                ui.colored_label(Color32::RED, error);
+            }
+            let Some(diagnostics) = &tab.diagnostics else {
+                return;
+            };
+            ui.add_space(8.0);
+            let shell = &diagnostics.shell;
+            match &diagnostics.found {
+                Some(found) => ui.label(format!("{} was found at {}, but didn't start.", shell, found.display())),
+                None => ui.label(format!("{} was not found on the PATH.", shell)),
+            };
+            if ui.button("Retry").clicked() {
+                start = Some(shell.clone());
+            }
+
+            ui.add_space(8.0);
+            if diagnostics.candidates.is_empty() {
+                ui.label("No other shell was found on the PATH.");
+            } else {
+                ui.label("Shells found on this system:");
+                for (name, found) in &diagnostics.candidates {
+                    ui.horizontal(|ui| {
+                        if ui.button(format!("Start {}", name)).clicked() {
+                            start = Some(name.clone());
+                        }
+                        ui.weak(found.display().to_string());
+                    });
+                }
+            }
+            ui.label("Pick another default shell in Terminal Settings to start it in new tabs.");
+
+            ui.add_space(8.0);
+            egui::CollapsingHeader::new(format!("PATH searched ({} folders)", diagnostics.path.len()))
+                .id_salt("shell_path")
+                .show(ui, |ui| {
+                    for dir in &diagnostics.path {
+                        let text = egui::RichText::new(dir.display().to_string()).monospace();
+                        if dir.is_dir() {
+                            ui.label(text);
+                        } else {
+                            ui.label(text.weak()).on_hover_text("Folder does not exist");
+                        }
+                    }
+                });
+        });
+        if let Some(shell) = start {
+            terminal.replace_current_tab(&shell);
+        }
+    }
 
-            // Request focus when clicked
-            if response.clicked() {
+    /// Bar with the shown terminal's search query, its options and where the
+    /// current match is among them. Enter moves up to older output, and
+    /// Shift+Enter back down.
+    fn show_search_bar(ui: &mut egui::Ui, terminal: &mut PtyTerminalState) {
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        let Some(search) = &mut tab.search else {
+            return;
+        };
+        let id = egui::Id::new(TERMINAL_ID).with("search");
+        // Move down (true) or up (false) a match
+        let mut step = None;
+        let mut close = false;
+        let mut reveal = false;
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut search.query)
+                    .id(id)
+                    .hint_text("Find in terminal")
+                    .desired_width(220.0),
+            );
+            if std::mem::take(&mut search.focus) {
                 response.request_focus();
             }
+            if response.lost_focus() {
+                if ui.input(|input| input.key_pressed(Key::Enter)) {
+                    step = Some(ui.input(|input| input.modifiers.shift));
+                    response.request_focus();
+                } else if ui.input(|input| input.key_pressed(Key::Escape)) {
+                    close = true;
+                }
+            }
+            ui.toggle_value(&mut search.case_sensitive, "Aa").on_hover_text("Match case");
+            ui.toggle_value(&mut search.regex, ".*").on_hover_text("Regular expression");
+            if ui.button("⏶").on_hover_text("Previous match (Enter)").clicked() {
+                step = Some(false);
+            }
+            if ui.button("⏷").on_hover_text("Next match (Shift+Enter)").clicked() {
+                step = Some(true);
+            }
+
+            reveal = search.update(&tab.buffer);
+            if let Some(error) = search.error() {
+                ui.colored_label(Color32::RED, "Invalid pattern").on_hover_text(error);
+            } else if let Some(number) = search.current_number() {
+                ui.label(format!("{} of {}", number, search.matches().len()));
+            } else if !search.query.is_empty() {
+                ui.label("No matches");
+            }
 
-            // Handle keyboard input when focused
-            if response.has_focus() {
-                Self::handle_keyboard_input(ui, terminal);
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("✕").on_hover_text("Close (Escape)").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+        match step {
+            Some(true) => reveal |= search.next().is_some(),
+            Some(false) => reveal |= search.previous().is_some(),
+            None => {}
+        }
+        if let Some(line) = search.current().filter(|_| reveal).map(|found| found.line) {
+            tab.buffer.reveal_line(line);
+        }
+        if close {
+            tab.search = None;
+            ui.memory_mut(|memory| memory.request_focus(egui::Id::new(TERMINAL_ID)));
+        }
+    }
+
+    /// Resize the shown terminal to the cells that fit its area, so
+    /// full-screen programs use the whole panel
+    fn fit_to_rect(terminal: &mut PtyTerminalState, rect: egui::Rect, metrics: &CellMetrics) {
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        let cols = (rect.width() / metrics.width).floor().clamp(2.0, u16::MAX as f32) as u16;
+        let rows = (rect.height() / metrics.height).floor().clamp(1.0, u16::MAX as f32) as u16;
+        if tab.pty.is_some() && tab.size() != (cols, rows) {
+            if let Err(e) = tab.resize(cols, rows) {
+                tracing::warn!("{:#}", e);
             }
+        }
+    }
+
+    /// Cell under a point of the terminal area, clamped to the cells in view
+    fn cell_at(
+        terminal: &PtyTerminalState,
+        rect: egui::Rect,
+        metrics: &CellMetrics,
+        pos: egui::Pos2,
+    ) -> Option<CellPos> {
+        let buffer = &terminal.current_tab()?.buffer;
+        let (cols, rows) = buffer.size();
+        let col = ((pos.x - rect.min.x) / metrics.width).floor().clamp(0.0, cols.saturating_sub(1) as f32);
+        let row = ((pos.y - rect.min.y) / metrics.height).floor().clamp(0.0, rows.saturating_sub(1) as f32);
+        Some(CellPos { line: buffer.first_visible_line() + row as usize, col: col as usize })
+    }
+
+    /// Open the link under the pointer on Ctrl+click (Cmd+click on macOS).
+    /// The app asks before opening addresses from untrusted domains.
+    fn handle_links(
+        ui: &egui::Ui,
+        terminal: &PtyTerminalState,
+        response: &egui::Response,
+        rect: egui::Rect,
+        metrics: &CellMetrics,
+    ) {
+        if !ui.input(|i| i.modifiers.command) {
+            return;
+        }
+        let Some(pos) = response.hover_pos().filter(|pos| rect.contains(*pos)) else {
+            return;
+        };
+        let link = Self::cell_at(terminal, rect, metrics, pos)
+            .and_then(|cell| terminal.current_tab()?.buffer.link_at(cell));
+        let Some(link) = link else {
+            return;
+        };
+        ui.ctx().set_cursor_icon(egui::CursorIcon::PointingHand);
+        if response.clicked() {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(link));
+        }
+    }
 
-            // Draw terminal content
-            Self::render_terminal_buffer(ui, terminal, available_rect);
+    /// Select cells by dragging; a click clears the selection
+    fn handle_selection(
+        terminal: &mut PtyTerminalState,
+        response: &egui::Response,
+        rect: egui::Rect,
+        metrics: &CellMetrics,
+    ) {
+        let Some(pos) = response.interact_pointer_pos() else {
+            return;
+        };
+        if response.dragged_by(egui::PointerButton::Primary) {
+            // Dragging past the top or bottom edge scrolls the view along
+            if let Some(tab) = terminal.current_tab_mut() {
+                if pos.y < rect.min.y {
+                    tab.buffer.scroll_view(1);
+                } else if pos.y > rect.max.y {
+                    tab.buffer.scroll_view(-1);
+                }
+            }
+        }
+        let Some(cell) = Self::cell_at(terminal, rect, metrics, pos) else {
+            return;
+        };
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        if response.drag_started_by(egui::PointerButton::Primary) {
+            tab.selection = Some(Selection { anchor: cell, head: cell });
+        } else if response.dragged_by(egui::PointerButton::Primary) {
+            if let Some(selection) = &mut tab.selection {
+                selection.head = cell;
+            }
+        } else if response.clicked() {
+            tab.selection = None;
+        }
+    }
 
-            // Request continuous repainting for terminal updates
-            ctx.request_repaint();
+    /// Scroll the view through the scrollback with the mouse wheel
+    fn handle_scroll_wheel(ui: &mut egui::Ui, terminal: &mut PtyTerminalState, id: egui::Id, metrics: &CellMetrics) {
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        let delta = ui.input(|input| input.smooth_scroll_delta.y);
+        if delta == 0.0 {
+            return;
+        }
+        // Scrolling comes in fractions of a line, so the rest carries over
+        let pending = ui.data_mut(|data| {
+            let pending = data.get_temp_mut_or_default::<f32>(id.with("scroll"));
+            *pending += delta;
+            let lines = (*pending / metrics.height).trunc();
+            *pending -= lines * metrics.height;
+            lines
         });
+        tab.buffer.scroll_view(pending as isize);
+    }
+
+    /// Scrollbar over the scrollback and the screen; dragging it or clicking
+    /// on it moves the view
+    fn show_scrollbar(ui: &mut egui::Ui, terminal: &mut PtyTerminalState, rect: egui::Rect, id: egui::Id) {
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        let scrollback = tab.buffer.scrollback().len();
+        if scrollback == 0 {
+            return;
+        }
+        let rows = tab.buffer.lines().len();
+        let thumb_height = (rect.height() * rows as f32 / (scrollback + rows) as f32).max(20.0);
+        let travel = (rect.height() - thumb_height).max(1.0);
+
+        let response = ui.interact(rect, id.with("scrollbar"), egui::Sense::click_and_drag());
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let fraction = ((pointer.y - rect.min.y - thumb_height / 2.0) / travel).clamp(0.0, 1.0);
+            tab.buffer.set_view_offset(((1.0 - fraction) * scrollback as f32).round() as usize);
+        }
+
+        let fraction = (scrollback - tab.buffer.view_offset()) as f32 / scrollback as f32;
+        let thumb = egui::Rect::from_min_size(
+            egui::pos2(rect.min.x + 2.0, rect.min.y + travel * fraction),
+            egui::vec2(rect.width() - 4.0, thumb_height),
+        );
+        let color = if response.hovered() || response.dragged() {
+            Color32::from_gray(140)
+        } else {
+            Color32::from_gray(90)
+        };
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_rgb(30, 30, 30));
+        painter.rect_filled(thumb, 3.0, color);
+    }
+
+    /// Button back to the live output while the view is scrolled back
+    fn show_jump_to_bottom(ui: &mut egui::Ui, terminal: &mut PtyTerminalState, rect: egui::Rect) {
+        let Some(tab) = terminal.current_tab_mut() else {
+            return;
+        };
+        let offset = tab.buffer.view_offset();
+        if offset == 0 {
+            return;
+        }
+        let size = egui::vec2(150.0, 22.0);
+        let button_rect = egui::Rect::from_min_size(rect.right_bottom() - size - egui::vec2(8.0, 8.0), size);
+        let button = ui
+            .put(button_rect, egui::Button::new("⬇ Jump to bottom"))
+            .on_hover_text(format!("{} lines below (Shift+End)", offset));
+        if button.clicked() {
+            tab.buffer.set_view_offset(0);
+        }
     }
 
     /// Handle keyboard input for the PTY terminal
@@ -192,10 +889,83 @@ impl PtyTerminalPanel {
             return;
         };
 
+        let mut copied = None;
+        let composing = ime::composing(ui.ctx());
         ui.input(|input| {
             // Handle special key combinations first
             let modifiers = input.modifiers;
 
+            if modifiers.command && modifiers.shift && input.key_pressed(Key::F) {
+                tab.open_search();
+                return;
+            }
+
+            // Shift+PageUp/PageDown/Home/End move the view through the
+            // scrollback instead of going to the program
+            if modifiers.shift {
+                let page = tab.buffer.lines().len().saturating_sub(1).max(1) as isize;
+                let scrolled = if input.key_pressed(Key::PageUp) {
+                    tab.buffer.scroll_view(page);
+                    true
+                } else if input.key_pressed(Key::PageDown) {
+                    tab.buffer.scroll_view(-page);
+                    true
+                } else if input.key_pressed(Key::Home) {
+                    tab.buffer.set_view_offset(usize::MAX);
+                    true
+                } else if input.key_pressed(Key::End) {
+                    tab.buffer.set_view_offset(0);
+                    true
+                } else {
+                    false
+                };
+                if scrolled {
+                    return;
+                }
+            }
+
+            // Typing goes back to the live output
+            if input.events.iter().any(|event| {
+                matches!(
+                    event,
+                    egui::Event::Text(_)
+                        | egui::Event::Paste(_)
+                        | egui::Event::Ime(egui::ImeEvent::Commit(_))
+                        | egui::Event::Key { pressed: true, .. }
+                )
+            }) {
+                tab.buffer.set_view_offset(0);
+            }
+
+            // While an input method composes text the keys are its own; the
+            // program gets the text once it is committed
+            if composing {
+                for event in &input.events {
+                    if let egui::Event::Ime(egui::ImeEvent::Commit(text)) = event {
+                        let _ = tab.write(text.as_bytes());
+                    }
+                }
+                return;
+            }
+
+            // Ctrl+C and Ctrl+V reach egui as copy and paste. Ctrl+Shift+C
+            // copies the selection (so does Cmd+C on macOS); plain Ctrl+C
+            // still interrupts the program.
+            for event in &input.events {
+                match event {
+                    egui::Event::Copy if modifiers.shift || cfg!(target_os = "macos") => {
+                        copied = tab.selected_text().filter(|text| !text.is_empty());
+                    }
+                    egui::Event::Copy => {
+                        let _ = tab.send_key(TerminalKey::CtrlC);
+                    }
+                    egui::Event::Paste(text) => {
+                        let _ = tab.paste(text);
+                    }
+                    _ => {}
+                }
+            }
+
             // Ctrl+C
             if modifiers.ctrl && input.key_pressed(Key::C) {
                 let _ = tab.send_key(TerminalKey::CtrlC);
@@ -283,6 +1053,10 @@ impl PtyTerminalPanel {
                 }
             }
         });
+        // Copied outside the input lock, which `copy_text` would wait on
+        if let Some(text) = copied {
+            ui.ctx().copy_text(text);
+        }
     }
 
     /// Render the terminal buffer content
@@ -290,89 +1064,95 @@ impl PtyTerminalPanel {
         ui: &mut egui::Ui,
         terminal: &PtyTerminalState,
         rect: egui::Rect,
+        metrics: &CellMetrics,
+        cursor_visible: bool,
     ) {
         let Some(tab) = terminal.current_tab() else {
             return;
         };
 
         let painter = ui.painter_at(rect);
-        let font_id = FontId::monospace(14.0);
-
-        // Calculate character dimensions
-        let char_width = 8.4; // Approximate for monospace
-        let line_height = 16.0;
+        let char_width = metrics.width;
+        let line_height = metrics.height;
+        let cell_rect = |row: usize, cols: std::ops::Range<usize>| {
+            let min = rect.min + egui::vec2(cols.start as f32 * char_width, row as f32 * line_height);
+            egui::Rect::from_min_size(min, egui::vec2(cols.len() as f32 * char_width, line_height))
+        };
 
         let buffer = &tab.buffer;
         let cursor = buffer.cursor();
+        let first_line = buffer.first_visible_line();
+        // Screen rows are pushed down by the scrollback lines in view
+        let cursor_row = cursor.row as usize + buffer.view_offset();
 
-        // Draw background
-        painter.rect_filled(rect, 0.0, Color32::from_rgb(30, 30, 30));
+        let scheme = terminal.color_scheme();
+        painter.rect_filled(rect, 0.0, scheme.background);
 
-        // Draw each line
-        for (row_idx, line) in buffer.lines().iter().enumerate() {
-            let y = rect.min.y + (row_idx as f32) * line_height;
+        // Each pane keeps the layouts of its own lines
+        let layouts_id = egui::Id::new(TERMINAL_ID).with(("layouts", terminal.active_tab));
+        let mut layouts = ui.data_mut(|data| std::mem::take(data.get_temp_mut_or_default::<LineLayouts>(layouts_id)));
+        ui.fonts(|fonts| layouts.begin_frame(fonts, &metrics.font_id, scheme));
 
-            if y > rect.max.y {
-                break; // Don't draw outside visible area
+        for (row_idx, line) in buffer.visible_lines().enumerate() {
+            if rect.min.y + row_idx as f32 * line_height > rect.max.y {
+                break;
             }
 
-            for (col_idx, styled_char) in line.chars.iter().enumerate() {
-                let x = rect.min.x + (col_idx as f32) * char_width;
-
-                if x > rect.max.x {
-                    break;
+            // Backgrounds, one rectangle per run of cells sharing a color
+            let mut col = 0;
+            while col < line.chars.len() {
+                let bg = scheme.bg(&line.chars[col]);
+                let end = (col..line.chars.len())
+                    .find(|&end| scheme.bg(&line.chars[end]) != bg)
+                    .unwrap_or(line.chars.len());
+                if let Some(bg) = bg {
+                    painter.rect_filled(cell_rect(row_idx, col..end), 0.0, bg);
                 }
+                col = end;
+            }
 
-                let pos = egui::pos2(x, y);
-
-                // Draw background if not transparent
-                let bg = styled_char.effective_bg();
-                if bg != Color32::TRANSPARENT {
-                    let bg_rect = egui::Rect::from_min_size(
-                        pos,
-                        egui::vec2(char_width, line_height),
-                    );
-                    painter.rect_filled(bg_rect, 0.0, bg);
+            // Search matches, the current one brighter
+            if let Some(search) = &tab.search {
+                let line = first_line + row_idx;
+                let matches = search.matches();
+                let from = matches.partition_point(|found| found.line < line);
+                for found in matches[from..].iter().take_while(|found| found.line == line) {
+                    let color = if search.current() == Some(found) {
+                        Color32::from_rgba_unmultiplied(255, 150, 0, 200)
+                    } else {
+                        Color32::from_rgba_unmultiplied(200, 170, 0, 100)
+                    };
+                    painter.rect_filled(cell_rect(row_idx, found.cols.clone()), 0.0, color);
                 }
+            }
 
-                // Draw cursor
-                if row_idx == cursor.row as usize && col_idx == cursor.col as usize {
-                    let cursor_rect = egui::Rect::from_min_size(
-                        pos,
-                        egui::vec2(char_width, line_height),
-                    );
-                    painter.rect_filled(cursor_rect, 0.0, Color32::from_rgba_unmultiplied(255, 255, 255, 128));
+            // A selection covers a single run of cells on each line
+            if let Some(selection) = tab.selection {
+                let selected = |col: &usize| selection.contains(CellPos { line: first_line + row_idx, col: *col });
+                if let Some(start) = (0..line.chars.len()).find(selected) {
+                    let end = (start..line.chars.len()).find(|col| !selected(col)).unwrap_or(line.chars.len());
+                    let color = Color32::from_rgba_unmultiplied(70, 110, 180, 160);
+                    painter.rect_filled(cell_rect(row_idx, start..end), 0.0, color);
                 }
+            }
 
-                // Draw character
-                if styled_char.c != ' ' {
-                    let fg = styled_char.effective_fg();
-                    let mut text = RichText::new(styled_char.c.to_string())
-                        .font(font_id.clone())
-                        .color(fg);
-
-                    if styled_char.bold {
-                        text = text.strong();
-                    }
-                    if styled_char.italic {
-                        text = text.italics();
-                    }
-                    if styled_char.underline {
-                        text = text.underline();
-                    }
-                    if styled_char.strikethrough {
-                        text = text.strikethrough();
-                    }
+            if cursor_visible && row_idx == cursor_row {
+                let col = cursor.col as usize;
+                // A double-width character covers its spacer cell too
+                let width = if line.get(col).is_some_and(|ch| ch.wide) { 2 } else { 1 };
+                let [r, g, b, _] = scheme.cursor.to_array();
+                let color = Color32::from_rgba_unmultiplied(r, g, b, 128);
+                painter.rect_filled(cell_rect(row_idx, col..col + width), 0.0, color);
+            }
 
-                    painter.text(
-                        pos,
-                        egui::Align2::LEFT_TOP,
-                        styled_char.c.to_string(),
-                        font_id.clone(),
-                        fg,
-                    );
-                }
+            let runs = ui.fonts(|fonts| layouts.runs(fonts, line));
+            for run in runs.iter() {
+                let pos = cell_rect(row_idx, run.col..run.col).min;
+                painter.galley(pos, run.galley.clone(), Color32::WHITE);
             }
         }
+
+        layouts.end_frame();
+        ui.data_mut(|data| data.insert_temp(layouts_id, layouts));
     }
 }
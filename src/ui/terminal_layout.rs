@@ -0,0 +1,177 @@
+//! Text layout of the terminal grid
+//!
+//! A line is laid out as runs of cells, one galley per run with a section
+//! per style, rather than one text shape per cell. Layouts are kept by line revision
+//! (see [`TerminalLine::revision`]), so a frame only lays out the lines whose
+//! cells changed; lines that merely scrolled keep theirs. Characters the
+//! monospace font can't draw one cell wide (double-width characters, glyphs
+//! from fallback fonts, characters with combining marks) get a galley of
+//! their own at their column, which keeps the grid aligned. Text is colored
+//! by the terminal's [`ColorScheme`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use eframe::egui::text::{Fonts, Galley, LayoutJob, TextFormat};
+use eframe::egui::{Color32, FontId, Stroke};
+
+use crate::terminal::buffer::{find_urls, StyledChar, TerminalLine};
+use crate::terminal::ColorScheme;
+
+/// Text starting at a column of the line
+pub struct TextRun {
+    pub col: usize,
+    pub galley: Arc<Galley>,
+}
+
+/// Laid-out lines of the terminals in view, by line revision
+#[derive(Clone, Default)]
+pub struct LineLayouts {
+    font_id: FontId,
+    /// Color scheme the lines were laid out in
+    scheme: ColorScheme,
+    pixels_per_point: f32,
+    /// Identity of the font atlas the galleys' glyphs are in; egui starts a
+    /// new one when the old one fills up
+    atlas: usize,
+    lines: HashMap<u64, Arc<Vec<TextRun>>>,
+    shown: HashSet<u64>,
+}
+
+impl LineLayouts {
+    /// Start a frame drawn with `font_id` in `scheme`; another font, scheme,
+    /// scale or font atlas than last frame's lays every line out again
+    pub fn begin_frame(&mut self, fonts: &Fonts, font_id: &FontId, scheme: &ColorScheme) {
+        let pixels_per_point = fonts.pixels_per_point();
+        let atlas = Arc::as_ptr(&fonts.texture_atlas()) as usize;
+        let changed = self.font_id != *font_id || self.scheme != *scheme;
+        if changed || self.pixels_per_point != pixels_per_point || self.atlas != atlas {
+            self.lines.clear();
+            self.font_id = font_id.clone();
+            self.scheme = scheme.clone();
+            self.pixels_per_point = pixels_per_point;
+            self.atlas = atlas;
+        }
+        self.shown.clear();
+    }
+
+    /// Text runs of a line, laid out only if the line changed since it was
+    /// last shown
+    pub fn runs(&mut self, fonts: &Fonts, line: &TerminalLine) -> Arc<Vec<TextRun>> {
+        let revision = line.revision();
+        self.shown.insert(revision);
+        let font_id = &self.font_id;
+        let scheme = &self.scheme;
+        self.lines
+            .entry(revision)
+            .or_insert_with(|| Arc::new(layout_line(fonts, font_id, scheme, line)))
+            .clone()
+    }
+
+    /// Forget the lines that weren't shown this frame
+    pub fn end_frame(&mut self) {
+        let shown = &self.shown;
+        self.lines.retain(|revision, _| shown.contains(revision));
+    }
+}
+
+/// How a cell's text is drawn
+#[derive(PartialEq)]
+struct RunStyle {
+    fg: Color32,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+}
+
+impl RunStyle {
+    fn of(ch: &StyledChar, linked: bool, scheme: &ColorScheme) -> Self {
+        Self {
+            fg: scheme.fg(ch),
+            italic: ch.italic,
+            // Links are underlined; Ctrl+click opens them
+            underline: ch.underline || linked,
+            strikethrough: ch.strikethrough,
+        }
+    }
+
+    /// Whether a cell can join a run of this style. Blank cells show no
+    /// glyph, so only their lines matter.
+    fn takes(&self, ch: &StyledChar, style: &RunStyle) -> bool {
+        let blank = ch.c == ' ' && !style.underline && !style.strikethrough;
+        self == style || (blank && !self.underline && !self.strikethrough)
+    }
+
+    fn format(&self, font_id: &FontId) -> TextFormat {
+        let line = |set: bool| if set { Stroke::new(1.0, self.fg) } else { Stroke::NONE };
+        TextFormat {
+            font_id: font_id.clone(),
+            color: self.fg,
+            italics: self.italic,
+            underline: line(self.underline),
+            strikethrough: line(self.strikethrough),
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether a cell's glyph is drawn exactly one cell wide
+fn fits_cell(fonts: &Fonts, font_id: &FontId, cell_width: f32, ch: &StyledChar) -> bool {
+    !ch.wide
+        && !ch.is_spacer()
+        && ch.combining.is_empty()
+        && (ch.c.is_ascii() || (fonts.glyph_width(font_id, ch.c) - cell_width).abs() < 0.01)
+}
+
+fn layout_line(fonts: &Fonts, font_id: &FontId, scheme: &ColorScheme, line: &TerminalLine) -> Vec<TextRun> {
+    let cell_width = fonts.glyph_width(font_id, 'M');
+    let urls = find_urls(&line.chars);
+    let linked = |col: usize| line.chars[col].link.is_some() || urls.iter().any(|url| url.contains(&col));
+
+    let mut runs = Vec::new();
+    let mut col = 0;
+    while col < line.chars.len() {
+        let ch = &line.chars[col];
+        // Drawn with the double-width character before it
+        if ch.is_spacer() {
+            col += 1;
+            continue;
+        }
+        let mut style = RunStyle::of(ch, linked(col), scheme);
+        let mut text = String::new();
+        ch.push_text(&mut text);
+        let mut job = LayoutJob::default();
+        let mut drawn = !is_blank(&text, &style);
+        let start = col;
+        col += 1;
+        // Cells that fit share one galley, a section per style
+        if fits_cell(fonts, font_id, cell_width, ch) {
+            while let Some(next) = line.chars.get(col) {
+                if !fits_cell(fonts, font_id, cell_width, next) {
+                    break;
+                }
+                let next_style = RunStyle::of(next, linked(col), scheme);
+                if !style.takes(next, &next_style) {
+                    job.append(&text, 0.0, style.format(font_id));
+                    text.clear();
+                    style = next_style;
+                }
+                next.push_text(&mut text);
+                drawn |= !is_blank(&text, &style);
+                col += 1;
+            }
+        }
+
+        if !drawn {
+            continue;
+        }
+        job.append(&text, 0.0, style.format(font_id));
+        runs.push(TextRun { col: start, galley: fonts.layout_job(job) });
+    }
+    runs
+}
+
+/// Whether text in `style` shows nothing
+fn is_blank(text: &str, style: &RunStyle) -> bool {
+    text.trim().is_empty() && !style.underline && !style.strikethrough
+}
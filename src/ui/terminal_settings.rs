@@ -0,0 +1,176 @@
+//! Terminal settings dialog
+//!
+//! Edits the shell new terminals run, how it starts, how much scrollback
+//! they keep, the colors they are drawn in and the saved SSH servers.
+
+use eframe::egui;
+
+use robsidian_core::config::{BellStyle, ClipboardAccess, TerminalConfig, SCROLLBACK_LINES};
+use robsidian_core::external_editor::split_command;
+
+use crate::terminal::{control, ColorScheme};
+
+/// Settings being edited, with the arguments and variables as typed
+#[derive(Debug)]
+struct Draft {
+    config: TerminalConfig,
+    /// Arguments on one line, quoted where they hold spaces
+    args: String,
+    /// One `NAME=value` per line
+    env: String,
+}
+
+/// Terminal settings dialog state
+#[derive(Debug, Default)]
+pub struct TerminalSettingsDialog {
+    /// `None` while closed
+    draft: Option<Draft>,
+}
+
+impl TerminalSettingsDialog {
+    pub fn open(&mut self, config: &TerminalConfig) {
+        let args = config
+            .args
+            .iter()
+            .map(|arg| if arg.contains(char::is_whitespace) { format!("\"{}\"", arg) } else { arg.clone() })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let env = config.env.iter().map(|(name, value)| format!("{}={}\n", name, value)).collect();
+        self.draft = Some(Draft { config: config.clone(), args, env });
+    }
+
+    /// Show the dialog, offering `schemes` for the colors, returning the new
+    /// settings once saved
+    pub fn show(&mut self, ctx: &egui::Context, schemes: &[ColorScheme]) -> Option<TerminalConfig> {
+        let draft = self.draft.as_mut()?;
+        let mut open = true;
+        let mut saved = false;
+
+        egui::Window::new("Terminal Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("terminal_settings_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Shell");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut draft.config.shell)
+                            .hint_text(TerminalConfig::default().shell_program())
+                            .desired_width(280.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Arguments");
+                    ui.add(egui::TextEdit::singleline(&mut draft.args).hint_text("-l").desired_width(280.0));
+                    ui.end_row();
+
+                    ui.label("Environment");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut draft.env)
+                            .hint_text("NAME=value, one per line")
+                            .desired_rows(3)
+                            .desired_width(280.0),
+                    );
+                    ui.end_row();
+
+                    ui.label("Scrollback");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut draft.config.scrollback_lines).range(SCROLLBACK_LINES));
+                        ui.label("lines");
+                    });
+                    ui.end_row();
+
+                    ui.label("Colors");
+                    egui::ComboBox::from_id_salt("terminal_color_scheme")
+                        .selected_text(draft.config.color_scheme.as_str())
+                        .show_ui(ui, |ui| {
+                            for scheme in schemes {
+                                let name = scheme.name.to_string();
+                                ui.selectable_value(&mut draft.config.color_scheme, name, scheme.name.as_ref());
+                            }
+                        })
+                        .response
+                        .on_hover_text(format!(
+                            "Theme files in {} add more",
+                            TerminalConfig::theme_dir().display()
+                        ));
+                    ui.end_row();
+
+                    ui.label("Bell");
+                    egui::ComboBox::from_id_salt("terminal_bell")
+                        .selected_text(draft.config.bell.label())
+                        .show_ui(ui, |ui| {
+                            for style in BellStyle::ALL {
+                                ui.selectable_value(&mut draft.config.bell, style, style.label());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Clipboard");
+                    egui::ComboBox::from_id_salt("terminal_clipboard")
+                        .selected_text(draft.config.clipboard.label())
+                        .show_ui(ui, |ui| {
+                            for access in ClipboardAccess::ALL {
+                                ui.selectable_value(&mut draft.config.clipboard, access, access.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Also applies to programs on servers you connect to");
+                    ui.end_row();
+
+                    // Servers are added from the quick-connect dialog
+                    if !draft.config.ssh_hosts.is_empty() {
+                        ui.label("Servers");
+                        ui.vertical(|ui| {
+                            let mut removed = None;
+                            for (index, host) in draft.config.ssh_hosts.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    ui.label(host.label()).on_hover_text(host.ssh_args().join(" "));
+                                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                                        removed = Some(index);
+                                    }
+                                });
+                            }
+                            if let Some(index) = removed {
+                                draft.config.ssh_hosts.remove(index);
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.checkbox(&mut draft.config.start_in_vault, "Start in the vault's folder");
+                ui.checkbox(&mut draft.config.follow_directory, "Reveal the terminal's folder in the file tree");
+                ui.weak("The shell, its arguments and environment apply to new terminals.");
+                if control::AVAILABLE {
+                    ui.weak("Scripts can open and create notes by writing to $ROBSIDIAN_CONTROL.");
+                } else {
+                    ui.weak(
+                        "Shells get $ROBSIDIAN_VAULT and $ROBSIDIAN_ACTIVE_NOTE. Opening notes from scripts \
+                         through $ROBSIDIAN_CONTROL isn't available on Windows yet.",
+                    );
+                }
+                ui.separator();
+                if ui.button("Save").clicked() {
+                    saved = true;
+                }
+            });
+
+        if saved {
+            let draft = self.draft.take()?;
+            let mut config = draft.config;
+            config.args = split_command(&draft.args);
+            config.env = draft
+                .env
+                .lines()
+                .filter_map(|line| line.split_once('='))
+                .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+                .filter(|(name, _)| !name.is_empty())
+                .collect();
+            return Some(config);
+        }
+        if !open {
+            self.draft = None;
+        }
+        None
+    }
+}
@@ -0,0 +1,38 @@
+//! Text statistics popup contents
+
+use eframe::egui;
+
+use robsidian_core::text_stats::TextStats;
+
+/// Plain-words verdict for a Flesch reading ease score
+fn ease_label(score: f32) -> &'static str {
+    match score {
+        s if s >= 80.0 => "easy",
+        s if s >= 60.0 => "plain English",
+        s if s >= 30.0 => "difficult",
+        _ => "very difficult",
+    }
+}
+
+/// Counts and readability scores as a grid
+pub fn show_text_stats(ui: &mut egui::Ui, stats: &TextStats) {
+    egui::Grid::new("text_stats").num_columns(2).striped(true).show(ui, |ui| {
+        let mut row = |name: &str, value: String| {
+            ui.label(name);
+            ui.label(value);
+            ui.end_row();
+        };
+        row("Words", stats.words.to_string());
+        row("Characters", stats.characters.to_string());
+        row("Without spaces", stats.characters_no_spaces.to_string());
+        row("Sentences", stats.sentences.to_string());
+        if stats.words == 0 {
+            return;
+        }
+        row("Words per sentence", format!("{:.1}", stats.words_per_sentence()));
+        let ease = stats.reading_ease();
+        row("Reading ease", format!("{:.0} ({})", ease, ease_label(ease)));
+        row("Grade level", format!("{:.1}", stats.grade_level().max(0.0)));
+        row("Reading time", format!("{} min", stats.reading_minutes()));
+    });
+}
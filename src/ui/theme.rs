@@ -0,0 +1,135 @@
+//! Theme definitions for note previews
+//!
+//! Notes can opt into named visual variants through the `cssclass` (or
+//! `cssclasses`/`style`) frontmatter key. Each variant tweaks how the note's
+//! preview is laid out: a colored banner, a wide layout, or a serif font.
+
+use eframe::egui::{self, Color32, FontFamily, Ui};
+
+/// Font family name used by the `serif` variant
+pub const SERIF_FAMILY: &str = "serif";
+
+/// Maximum content width for notes without the `wide` variant
+const READABLE_WIDTH: f32 = 860.0;
+
+/// Height of the banner strip painted by `banner-*` variants
+const BANNER_HEIGHT: f32 = 48.0;
+
+/// A single named variant
+#[derive(Debug, Clone, Copy)]
+enum NoteVariant {
+    Banner(Color32),
+    Wide,
+    Serif,
+}
+
+/// Built-in note variants, looked up by the names used in frontmatter
+const NOTE_VARIANTS: &[(&str, NoteVariant)] = &[
+    ("wide", NoteVariant::Wide),
+    ("serif", NoteVariant::Serif),
+    ("banner-red", NoteVariant::Banner(Color32::from_rgb(160, 60, 60))),
+    ("banner-orange", NoteVariant::Banner(Color32::from_rgb(180, 110, 50))),
+    ("banner-green", NoteVariant::Banner(Color32::from_rgb(60, 130, 80))),
+    ("banner-blue", NoteVariant::Banner(Color32::from_rgb(60, 100, 160))),
+    ("banner-purple", NoteVariant::Banner(Color32::from_rgb(120, 80, 160))),
+];
+
+/// Combined style of a note, resolved from its frontmatter classes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoteStyle {
+    pub banner_color: Option<Color32>,
+    pub wide: bool,
+    pub serif: bool,
+}
+
+impl NoteStyle {
+    /// Resolve a style from frontmatter class names, ignoring unknown ones
+    pub fn from_classes(classes: &[String]) -> Self {
+        let mut style = Self::default();
+
+        for class in classes {
+            let Some((_, variant)) = NOTE_VARIANTS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(class))
+            else {
+                continue;
+            };
+
+            match variant {
+                NoteVariant::Banner(color) => style.banner_color = Some(*color),
+                NoteVariant::Wide => style.wide = true,
+                NoteVariant::Serif => style.serif = true,
+            }
+        }
+
+        style
+    }
+
+    /// Show note content with this style applied
+    pub fn show<R>(&self, ui: &mut Ui, add_contents: impl FnOnce(&mut Ui) -> R) -> R {
+        if let Some(color) = self.banner_color {
+            let (rect, _) = ui.allocate_exact_size(
+                egui::vec2(ui.available_width(), BANNER_HEIGHT),
+                egui::Sense::hover(),
+            );
+            ui.painter().rect_filled(rect, 4.0, color);
+            ui.add_space(8.0);
+        }
+
+        let available = ui.available_width();
+        let width = if self.wide {
+            available
+        } else {
+            available.min(READABLE_WIDTH)
+        };
+        let margin = ((available - width) / 2.0).max(0.0);
+
+        ui.horizontal_top(|ui| {
+            ui.add_space(margin);
+            ui.vertical(|ui| {
+                ui.set_max_width(width);
+
+                if self.serif {
+                    let family = FontFamily::Name(SERIF_FAMILY.into());
+                    for font_id in ui.style_mut().text_styles.values_mut() {
+                        if font_id.family == FontFamily::Proportional {
+                            font_id.family = family.clone();
+                        }
+                    }
+                }
+
+                add_contents(ui)
+            })
+            .inner
+        })
+        .inner
+    }
+}
+
+/// Register the font families used by note variants.
+///
+/// The serif family uses `assets/fonts/serif.ttf` when present and otherwise
+/// falls back to the proportional fonts, so the variant never fails to render.
+pub fn register_note_fonts(fonts: &mut egui::FontDefinitions) {
+    let mut family = Vec::new();
+
+    if let Ok(bytes) = std::fs::read("assets/fonts/serif.ttf") {
+        fonts.font_data.insert(
+            SERIF_FAMILY.to_owned(),
+            std::sync::Arc::new(egui::FontData::from_owned(bytes)),
+        );
+        family.push(SERIF_FAMILY.to_owned());
+    }
+
+    family.extend(
+        fonts
+            .families
+            .get(&FontFamily::Proportional)
+            .cloned()
+            .unwrap_or_default(),
+    );
+
+    fonts
+        .families
+        .insert(FontFamily::Name(SERIF_FAMILY.into()), family);
+}
@@ -0,0 +1,94 @@
+//! Move/copy to vault dialog
+
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use robsidian_core::vault_transfer::TransferOptions;
+
+/// A transfer the user confirmed
+#[derive(Debug)]
+pub struct TransferRequest {
+    pub note: PathBuf,
+    pub vault: PathBuf,
+    pub options: TransferOptions,
+}
+
+/// Dialog picking the vault to move or copy a note to
+#[derive(Debug)]
+pub struct TransferDialog {
+    note: Option<PathBuf>,
+    vaults: Vec<PathBuf>,
+    target: usize,
+    options: TransferOptions,
+}
+
+impl Default for TransferDialog {
+    fn default() -> Self {
+        Self {
+            note: None,
+            vaults: Vec::new(),
+            target: 0,
+            options: TransferOptions { remove_original: false, attachments: true, fix_links: true },
+        }
+    }
+}
+
+impl TransferDialog {
+    /// Offer to move (or copy) a note to one of `vaults`
+    pub fn open(&mut self, note: PathBuf, vaults: Vec<PathBuf>, remove_original: bool) {
+        self.note = Some(note);
+        self.vaults = vaults;
+        self.target = 0;
+        self.options.remove_original = remove_original;
+    }
+
+    /// Show the dialog, returning the transfer once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<TransferRequest> {
+        let note = self.note.clone()?;
+        let name = note.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let vault_name = |vault: &PathBuf| {
+            vault.file_name().map_or(vault.display().to_string(), |n| n.to_string_lossy().to_string())
+        };
+        let verb = if self.options.remove_original { "Move" } else { "Copy" };
+        let mut open = true;
+        let mut confirmed = false;
+
+        egui::Window::new(format!("{} to Vault", verb))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} \"{}\" to", verb.to_lowercase(), name));
+                if self.vaults.is_empty() {
+                    ui.weak("No other vaults yet. Open one first, from File > Open Vault.");
+                    return;
+                }
+                egui::ComboBox::from_id_salt("transfer_vault")
+                    .selected_text(vault_name(&self.vaults[self.target]))
+                    .show_ui(ui, |ui| {
+                        for (i, vault) in self.vaults.iter().enumerate() {
+                            ui.selectable_value(&mut self.target, i, vault_name(vault))
+                                .on_hover_text(vault.display().to_string());
+                        }
+                    });
+                ui.checkbox(&mut self.options.attachments, "Bring the attachments it embeds");
+                if self.options.remove_original {
+                    ui.checkbox(&mut self.options.fix_links, "Point links to it at its new place");
+                }
+                ui.separator();
+                if ui.button(verb).clicked() {
+                    confirmed = true;
+                }
+            });
+
+        if confirmed {
+            self.note = None;
+            return Some(TransferRequest { note, vault: self.vaults[self.target].clone(), options: self.options });
+        }
+        if !open {
+            self.note = None;
+        }
+        None
+    }
+}
@@ -0,0 +1,261 @@
+//! Second vault window
+//!
+//! Shows another vault next to the main one, with its own file tree, open
+//! notes and vault settings. Notes can be dragged between the two file trees
+//! to copy them from one vault to the other.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use eframe::egui::{self, Key};
+
+use robsidian_core::document::Document;
+use robsidian_core::file_system::{copy_into, FileNode, FileTree};
+use robsidian_core::vault_settings::VaultSettings;
+
+use super::live_preview::SimpleLivePreview;
+
+/// Payload of a note dragged from a file tree
+#[derive(Debug, Clone)]
+pub struct DraggedNote(pub PathBuf);
+
+/// Something that happened in the window the app should know about
+#[derive(Debug)]
+pub enum VaultWindowEvent {
+    /// A note of the main vault was copied here, to this path
+    Copied(PathBuf),
+    /// Copying, opening or saving a note failed
+    Failed(String),
+    /// The window was closed
+    Closed,
+}
+
+/// A vault open in its own window
+#[derive(Default)]
+pub struct VaultWindow {
+    vault: Option<PathBuf>,
+    tree: FileTree,
+    settings: VaultSettings,
+    documents: HashMap<PathBuf, Document>,
+    active: Option<PathBuf>,
+    /// Show the active note rendered instead of its source
+    preview: bool,
+    events: Vec<VaultWindowEvent>,
+}
+
+impl VaultWindow {
+    /// Vault shown in the window, if it's open
+    pub fn vault(&self) -> Option<&Path> {
+        self.vault.as_deref()
+    }
+
+    pub fn open(&mut self, vault: PathBuf) -> Result<()> {
+        self.tree = FileTree::from_path(&vault)?;
+        self.settings = VaultSettings::load(&vault).unwrap_or_default();
+        self.documents.clear();
+        self.active = None;
+        self.vault = Some(vault);
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Show the window, returning what happened in it
+    pub fn show(&mut self, ctx: &egui::Context) -> Vec<VaultWindowEvent> {
+        let Some(vault) = self.vault.clone() else {
+            return Vec::new();
+        };
+        let name = vault.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let title = format!("{} - Robsidian", name);
+
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("second_vault"),
+            egui::ViewportBuilder::default().with_title(&title).with_inner_size([900.0, 650.0]),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // The backend can't open another window: fall back to an
+                    // in-app window
+                    let mut open = true;
+                    egui::Window::new(&title)
+                        .open(&mut open)
+                        .default_size([700.0, 500.0])
+                        .show(ctx, |ui| {
+                            egui::SidePanel::left("second_vault_tree").resizable(true).show_inside(ui, |ui| {
+                                self.show_tree(ui);
+                            });
+                            egui::CentralPanel::default().show_inside(ui, |ui| self.show_note(ui));
+                        });
+                    if !open {
+                        self.close_window();
+                    }
+                    return;
+                }
+
+                egui::SidePanel::left("second_vault_tree")
+                    .resizable(true)
+                    .default_width(250.0)
+                    .show(ctx, |ui| self.show_tree(ui));
+                egui::CentralPanel::default().show(ctx, |ui| self.show_note(ui));
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.close_window();
+                }
+            },
+        );
+        std::mem::take(&mut self.events)
+    }
+
+    fn close_window(&mut self) {
+        let events = std::mem::take(&mut self.events);
+        self.close();
+        self.events = events;
+        self.events.push(VaultWindowEvent::Closed);
+    }
+
+    fn show_tree(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Explorer");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("\u{21BB}").on_hover_text("Refresh").clicked() {
+                    let _ = self.tree.refresh();
+                }
+            });
+        });
+        ui.separator();
+        egui::ScrollArea::vertical().id_salt("second_vault_scroll").show(ui, |ui| {
+            if let Some(vault) = self.vault.clone() {
+                for path in self.settings.starred_notes(&vault) {
+                    let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                    if ui.selectable_label(self.active.as_ref() == Some(&path), format!("★ {}", name)).clicked() {
+                        self.open_note(&path);
+                    }
+                }
+            }
+            if let Some(root) = self.tree.root.clone() {
+                self.show_node(ui, &root);
+            }
+        });
+    }
+
+    fn show_node(&mut self, ui: &mut egui::Ui, node: &FileNode) {
+        if !node.is_dir {
+            ui.horizontal(|ui| {
+                ui.add_space(16.0);
+                let is_active = self.active.as_ref() == Some(&node.path);
+                let id = egui::Id::new(("second_vault_drag", &node.path));
+                let response = ui
+                    .dnd_drag_source(id, DraggedNote(node.path.clone()), |ui| {
+                        ui.selectable_label(is_active, format!("\u{1F4DD} {}", node.name))
+                    })
+                    .inner;
+                if response.clicked() && node.is_markdown() {
+                    self.open_note(&node.path);
+                }
+            });
+            return;
+        }
+
+        let id = ui.make_persistent_id(("second_vault", &node.path));
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, node.expanded)
+            .show_header(ui, |ui| {
+                let response = ui.selectable_label(false, format!("\u{1F4C1} {}", node.name));
+                if let Some(note) = drop_target(ui, &response) {
+                    self.copy_here(&note.0, &node.path);
+                }
+            })
+            .body(|ui| {
+                for child in &node.children {
+                    self.show_node(ui, child);
+                }
+            });
+    }
+
+    /// Copy a note dragged from the main vault into one of this vault's
+    /// folders
+    fn copy_here(&mut self, note: &Path, folder: &Path) {
+        if self.vault.as_deref().is_some_and(|vault| note.starts_with(vault)) {
+            return;
+        }
+        match copy_into(note, folder) {
+            Ok(path) => {
+                let _ = self.tree.refresh();
+                self.events.push(VaultWindowEvent::Copied(path));
+            }
+            Err(e) => self.events.push(VaultWindowEvent::Failed(format!("{:#}", e))),
+        }
+    }
+
+    fn open_note(&mut self, path: &Path) {
+        if !self.documents.contains_key(path) {
+            match Document::open(path) {
+                Ok(doc) => {
+                    self.documents.insert(path.to_path_buf(), doc);
+                }
+                Err(e) => {
+                    self.events.push(VaultWindowEvent::Failed(format!("{:#}", e)));
+                    return;
+                }
+            }
+        }
+        self.active = Some(path.to_path_buf());
+    }
+
+    fn show_note(&mut self, ui: &mut egui::Ui) {
+        let Some(doc) = self.active.as_ref().and_then(|path| self.documents.get_mut(path)) else {
+            ui.centered_and_justified(|ui| ui.label("Pick a note, or drag notes between the two vaults"));
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.strong(format!("{}{}", doc.title(), if doc.modified { "*" } else { "" }));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.toggle_value(&mut self.preview, "Preview");
+                if ui.add_enabled(doc.modified, egui::Button::new("Save")).clicked() {
+                    if let Err(e) = doc.save_mut() {
+                        self.events.push(VaultWindowEvent::Failed(format!("{:#}", e)));
+                    }
+                }
+            });
+        });
+        ui.separator();
+
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, Key::S)) {
+            if let Err(e) = doc.save_mut() {
+                self.events.push(VaultWindowEvent::Failed(format!("{:#}", e)));
+            }
+        }
+
+        egui::ScrollArea::vertical().id_salt("second_vault_note").show(ui, |ui| {
+            if self.preview || doc.locked {
+                SimpleLivePreview::show(ui, &doc.content, None);
+            } else {
+                let mut content = doc.content.clone();
+                let response = ui.add(
+                    egui::TextEdit::multiline(&mut content)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(30),
+                );
+                if response.changed() {
+                    doc.set_content(content);
+                }
+            }
+        });
+    }
+}
+
+/// Highlight a folder row while a note is dragged over it, returning the
+/// note once dropped there
+pub fn drop_target(ui: &egui::Ui, response: &egui::Response) -> Option<std::sync::Arc<DraggedNote>> {
+    if response.dnd_hover_payload::<DraggedNote>().is_some() {
+        ui.painter().rect_stroke(
+            response.rect,
+            2.0,
+            ui.visuals().selection.stroke,
+            egui::StrokeKind::Inside,
+        );
+    }
+    response.dnd_release_payload::<DraggedNote>()
+}
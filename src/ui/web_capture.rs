@@ -0,0 +1,57 @@
+//! Web capture prompt
+//!
+//! Asks for the address of a page to save as a read-later note.
+
+use eframe::egui::{self, Key};
+
+/// Web capture prompt state
+#[derive(Debug, Default)]
+pub struct CapturePrompt {
+    open: bool,
+    url: String,
+}
+
+impl CapturePrompt {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.url.clear();
+    }
+
+    /// Show the prompt, returning the address once confirmed
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let mut open = true;
+        let mut confirmed = false;
+        egui::Window::new("Capture Web Article")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 100.0))
+            .show(ctx, |ui| {
+                ui.label("Save the readable content of a page as a note.");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.url)
+                        .hint_text("https://...")
+                        .desired_width(420.0),
+                );
+                response.request_focus();
+                let entered = ui.input(|i| i.key_pressed(Key::Enter));
+                let valid = !self.url.trim().is_empty();
+                if (ui.add_enabled(valid, egui::Button::new("Capture")).clicked() || entered) && valid {
+                    confirmed = true;
+                }
+            });
+
+        if !open || ctx.input(|i| i.key_pressed(Key::Escape)) {
+            self.open = false;
+        }
+        if !confirmed {
+            return None;
+        }
+        self.open = false;
+        Some(self.url.trim().to_string())
+    }
+}
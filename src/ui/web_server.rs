@@ -0,0 +1,89 @@
+//! Web server dialog
+//!
+//! Starts and stops the read-only web view of the vault and shows the address
+//! to open on other devices.
+
+use eframe::egui;
+
+use robsidian_core::config::WebServerConfig;
+use robsidian_core::web_server::generate_token;
+
+/// What the dialog asks the app to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebServerAction {
+    Start,
+    Stop,
+}
+
+/// Web server dialog state
+#[derive(Debug, Default)]
+pub struct WebServerDialog {
+    open: bool,
+}
+
+impl WebServerDialog {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// Show the dialog. `address` is where the running server can be
+    /// reached, `None` while it is stopped.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        config: &mut WebServerConfig,
+        address: Option<&str>,
+    ) -> Option<WebServerAction> {
+        if !self.open {
+            return None;
+        }
+
+        let mut action = None;
+        egui::Window::new("Web View")
+            .open(&mut self.open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Read the vault from a phone or another computer on this network.");
+                ui.add_space(4.0);
+                ui.add_enabled_ui(address.is_none(), |ui| {
+                    egui::Grid::new("web_server_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Port");
+                        ui.add(egui::DragValue::new(&mut config.port).range(1024..=65535));
+                        ui.end_row();
+                        ui.label("Access token");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut config.token).desired_width(260.0));
+                            if ui.button("New").on_hover_text("Generate a new token").clicked() {
+                                config.token = generate_token();
+                            }
+                        });
+                        ui.end_row();
+                    });
+                });
+                ui.separator();
+
+                match address {
+                    Some(address) => {
+                        ui.label("Open this address on the other device:");
+                        ui.horizontal(|ui| {
+                            ui.monospace(address);
+                            if ui.small_button("Copy").clicked() {
+                                ui.ctx().copy_text(address.to_string());
+                            }
+                        });
+                        ui.weak("Anyone with this address can read your notes while the server runs.");
+                        if ui.button("Stop").clicked() {
+                            action = Some(WebServerAction::Stop);
+                        }
+                    }
+                    None => {
+                        if ui.button("Start").clicked() {
+                            action = Some(WebServerAction::Start);
+                        }
+                    }
+                }
+            });
+        action
+    }
+}